@@ -4,12 +4,17 @@ import { getCurrentWebview } from "@tauri-apps/api/webview";
 import { useCallback, useEffect, useMemo, useRef, useState } from "react";
 import { IsolationTest } from "@/components/isolated";
 import { MediaProvider } from "@/components/outputs/media-provider";
+import { sidecarCellsAfterRun } from "@/components/outputs/sidecar-routing";
 import {
   useWidgetStoreRequired,
   WidgetStoreProvider,
 } from "@/components/widgets/widget-store-context";
 import { WidgetView } from "@/components/widgets/widget-view";
-import { useSyncedSettings, useSyncedTheme } from "@/hooks/useSyncedSettings";
+import {
+  type Runtime,
+  useSyncedSettings,
+  useSyncedTheme,
+} from "@/hooks/useSyncedSettings";
 import { ErrorBoundary } from "@/lib/error-boundary";
 import { CondaDependencyHeader } from "./components/CondaDependencyHeader";
 import {
@@ -19,11 +24,15 @@ import {
 import { DebugBanner } from "./components/DebugBanner";
 import { DenoDependencyHeader } from "./components/DenoDependencyHeader";
 import { DependencyHeader } from "./components/DependencyHeader";
+import { DirectoryPicker } from "./components/DirectoryPicker";
 import { NotebookToolbar } from "./components/NotebookToolbar";
 import { NotebookView } from "./components/NotebookView";
 import { TrustDialog } from "./components/TrustDialog";
 import { useCondaDependencies } from "./hooks/useCondaDependencies";
-import { useDaemonKernel } from "./hooks/useDaemonKernel";
+import {
+  type InstallSuggestion,
+  useDaemonKernel,
+} from "./hooks/useDaemonKernel";
 import { useDenoDependencies } from "./hooks/useDenoDependencies";
 import { type EnvSyncState, useDependencies } from "./hooks/useDependencies";
 import { useEnvProgress } from "./hooks/useEnvProgress";
@@ -31,7 +40,12 @@ import { useDaemonInfo, useGitInfo } from "./hooks/useGitInfo";
 import { useNotebook } from "./hooks/useNotebook";
 import { useTrust } from "./hooks/useTrust";
 import { useUpdater } from "./hooks/useUpdater";
-import type { JupyterMessage } from "./types";
+import { errorMessage } from "./lib/command-error";
+import type {
+  DaemonNotebookResponse,
+  DroppedVariable,
+  JupyterMessage,
+} from "./types";
 
 /** MIME bundle type for page payloads */
 export type MimeBundle = Record<string, unknown>;
@@ -42,6 +56,19 @@ export interface CellPagePayload {
   start: number;
 }
 
+/** Environment source detected from the notebook's path (from `redetect_environment`) */
+interface DetectedEnvironment {
+  env_source: string;
+  project_file: string;
+}
+
+/** Both uv and conda inline dependencies exist (from `detect_env_conflict`) */
+interface EnvConflict {
+  auto_launch_section: "uv" | "conda";
+  uv_only: string[];
+  conda_only: string[];
+}
+
 /**
  * Module-level reference for daemon comm sending.
  * Set by AppContent when daemon kernel is initialized.
@@ -88,6 +115,8 @@ function AppContent() {
     setDefaultUvPackages,
     defaultCondaPackages,
     setDefaultCondaPackages,
+    sidecarMime,
+    largeOutputBytes,
   } = useSyncedSettings();
 
   const {
@@ -96,14 +125,17 @@ function AppContent() {
     setFocusedCellId,
     updateCellSource,
     addCell,
+    duplicateCell,
     deleteCell,
     save,
     openNotebook,
     cloneNotebook,
+    saveCopyWithoutOutputs,
     dirty,
     updateOutputByDisplayId,
     setExecutionCount,
     clearCellOutputs,
+    setOutputCollapsed,
     formatCell,
   } = useNotebook();
 
@@ -111,6 +143,23 @@ function AppContent() {
   const [showIsolationTest, setShowIsolationTest] = useState(false);
   const [trustDialogOpen, setTrustDialogOpen] = useState(false);
   const [clearingDeps, setClearingDeps] = useState(false);
+  // Package offered for a cell's failed import, until installed or dismissed
+  const [installSuggestion, setInstallSuggestion] =
+    useState<InstallSuggestion | null>(null);
+  const [installingSuggestion, setInstallingSuggestion] = useState(false);
+  const [repairingEnv, setRepairingEnv] = useState(false);
+  // Outcome of the last restart & restore, until dismissed
+  const [stateRestoreNotice, setStateRestoreNotice] = useState<{
+    restored: string[];
+    dropped: DroppedVariable[];
+    error?: string;
+  } | null>(null);
+
+  // Project environment found after Save As moved the notebook, until
+  // applied or dismissed
+  const [redetectedEnv, setRedetectedEnv] =
+    useState<DetectedEnvironment | null>(null);
+  const [applyingRedetectedEnv, setApplyingRedetectedEnv] = useState(false);
 
   // Daemon startup status (installing, starting, failed, etc.)
   const [daemonStatus, setDaemonStatus] = useState<DaemonStatus>(null);
@@ -129,13 +178,13 @@ function AppContent() {
   // Track pending kernel start that was blocked by trust dialog
   const pendingKernelStartRef = useRef(false);
 
-  // Notebook runtime type (python or deno)
-  const [runtime, setRuntime] = useState<"python" | "deno">("python");
+  // Notebook runtime type (python, deno, or a custom kernelspec name)
+  const [runtime, setRuntime] = useState<Runtime>("python");
 
   // Load runtime from notebook metadata on mount
   useEffect(() => {
     invoke<string>("get_notebook_runtime").then((r) => {
-      setRuntime(r as "python" | "deno");
+      setRuntime(r);
     });
   }, []);
 
@@ -154,6 +203,7 @@ function AppContent() {
     syncedWhileRunning,
     needsKernelRestart,
     addDependency,
+    importRequirements,
     removeDependency,
     clearAllDependencies: clearAllUvDeps,
     syncState,
@@ -175,8 +225,11 @@ function AppContent() {
     needsKernelRestart: condaNeedsKernelRestart,
     addDependency: addCondaDependency,
     removeDependency: removeCondaDependency,
+    addPipDependency: addCondaPipDependency,
+    removePipDependency: removeCondaPipDependency,
     clearAllDependencies: clearAllCondaDeps,
     setChannels: setCondaChannels,
+    setChannelPriority: setCondaChannelPriority,
     setPython: setCondaPython,
     environmentYmlInfo,
     environmentYmlDeps,
@@ -185,6 +238,46 @@ function AppContent() {
     importFromPixi,
   } = useCondaDependencies();
 
+  // Detect uv/conda conflicts up front so the user picks one before launch
+  const [envConflict, setEnvConflict] = useState<EnvConflict | null>(null);
+  useEffect(() => {
+    if (!dependencies || !condaDependencies) {
+      setEnvConflict(null);
+      return;
+    }
+    invoke<EnvConflict | null>("detect_env_conflict")
+      .then(setEnvConflict)
+      .catch((e) => console.error("Failed to detect env conflict:", e));
+  }, [dependencies, condaDependencies]);
+
+  // Other open notebooks using this notebook's env_id (e.g. a copied file),
+  // from this app's windows and from the daemon's running kernels
+  const [sharedEnvWith, setSharedEnvWith] = useState<string[]>([]);
+  const [kernelsSharingEnv, setKernelsSharingEnv] = useState<string[]>([]);
+  const sharedEnvNames = useMemo(
+    () => [...new Set([...sharedEnvWith, ...kernelsSharingEnv])],
+    [sharedEnvWith, kernelsSharingEnv],
+  );
+  const handleEnvIdCollision = useCallback((otherNotebooks: string[]) => {
+    // Notebook IDs are file paths, or UUIDs for unsaved notebooks
+    setKernelsSharingEnv(
+      otherNotebooks.map((id) =>
+        /[\\/]/.test(id) ? (id.split(/[\\/]/).pop() ?? id) : "Untitled",
+      ),
+    );
+  }, []);
+  const [regeneratingEnvId, setRegeneratingEnvId] = useState(false);
+  const detectSharedEnvId = useCallback(() => {
+    invoke<string[]>("detect_shared_env_id")
+      .then(setSharedEnvWith)
+      .catch((e) => console.error("Failed to detect shared env_id:", e));
+  }, []);
+  useEffect(() => {
+    detectSharedEnvId();
+    window.addEventListener("focus", detectSharedEnvId);
+    return () => window.removeEventListener("focus", detectSharedEnvId);
+  }, [detectSharedEnvId]);
+
   // Deno config detection and settings
   const {
     denoAvailable,
@@ -243,10 +336,15 @@ function AppContent() {
     launchKernel,
     executeCell,
     clearOutputs,
+    clearAllOutputs,
     interruptKernel,
     shutdownKernel,
     syncEnvironment,
+    envRepairAvailable,
+    repairEnvironment,
     runAllCells: daemonRunAllCells,
+    snapshotKernelState,
+    restoreKernelState,
     sendCommMessage,
   } = useDaemonKernel({
     // Daemon execution: Automerge is the source of truth for outputs.
@@ -259,11 +357,53 @@ function AppContent() {
     onUpdateDisplayData: updateOutputByDisplayId,
     onClearOutputs: clearCellOutputs, // Handle broadcast from other windows
     onCommMessage: handleCommMessage, // Route comm messages to widget store
+    onSuggestInstall: setInstallSuggestion,
+    onEnvIdCollision: handleEnvIdCollision,
   });
 
   // Derive values from daemon kernel
   const envSource = kernelInfo.envSource ?? null;
 
+  // The sidecar attaches to the running kernel and only sees outputs from
+  // then on. It is opened, once per kernel, before cells run, and only the
+  // cells run while it is attached have routed outputs moved there; the
+  // rest render inline. Placeholder links reopen it on demand.
+  const sidecarAttachRef = useRef<Promise<boolean> | null>(null);
+  const [sidecarCellIds, setSidecarCellIds] = useState<ReadonlySet<string>>(
+    () => new Set(),
+  );
+  useEffect(() => {
+    if (kernelStatus === "starting" || kernelStatus === "not_started") {
+      sidecarAttachRef.current = null;
+      setSidecarCellIds(new Set());
+    }
+  }, [kernelStatus]);
+
+  const openSidecar = useCallback(() => {
+    invoke("open_sidecar").catch((e) =>
+      console.error("[sidecar] Failed to open:", errorMessage(e)),
+    );
+  }, []);
+
+  const attachSidecar = useCallback(
+    async (cellIds: string[]) => {
+      if (sidecarMime.length === 0) return;
+      sidecarAttachRef.current ??= invoke("open_sidecar").then(
+        () => true,
+        (e) => {
+          console.error("[sidecar] Failed to open:", errorMessage(e));
+          sidecarAttachRef.current = null;
+          return false;
+        },
+      );
+      const attached = await sidecarAttachRef.current;
+      setSidecarCellIds((cells) =>
+        sidecarCellsAfterRun(cells, cellIds, attached),
+      );
+    },
+    [sidecarMime],
+  );
+
   // Set up daemon comm sender for widget messages
   useEffect(() => {
     setDaemonCommSender(async (message: unknown) => {
@@ -302,6 +442,12 @@ function AppContent() {
           ? "conda"
           : null;
 
+  // Conda environments install the suggested package by its conda-forge name
+  const suggestedPackage =
+    envType === "conda"
+      ? installSuggestion?.condaPackage
+      : installSuggestion?.package;
+
   // Pre-start hint for the env badge (more specific than envType: distinguishes pixi)
   const envTypeHint = envSource
     ? null // backend has spoken, no hint needed
@@ -394,7 +540,7 @@ function AppContent() {
     return false;
   }, [checkTrust, launchKernel]);
 
-  // Handler to sync deps - tries hot-sync for UV additions, falls back to restart
+  // Handler to sync deps - tries hot-sync for UV changes, falls back to restart
   // Always checks trust before any operation that installs packages
   const handleSyncDeps = useCallback(async (): Promise<boolean> => {
     // Check trust first - required before any package installation (hot-sync or restart)
@@ -409,18 +555,19 @@ function AppContent() {
     }
 
     // Trusted - proceed with sync/restart
-    // For UV inline deps with only additions, try hot-sync first
+    // For UV inline deps, try hot-sync first
     const isUvInline = envSource === "uv:inline";
-    const hasOnlyAdditions =
-      envSyncState?.diff?.added?.length && !envSyncState?.diff?.removed?.length;
+    const hasPackageChanges =
+      envSyncState?.diff?.added?.length || envSyncState?.diff?.removed?.length;
 
-    if (isUvInline && hasOnlyAdditions) {
-      console.log("[App] Trying hot-sync for UV additions (trusted)");
+    if (isUvInline && hasPackageChanges) {
+      console.log("[App] Trying hot-sync for UV changes (trusted)");
       const response = await syncEnvironment();
 
       if (response.result === "sync_environment_complete") {
-        console.log("[App] Hot-sync succeeded:", response.synced_packages);
-        return true;
+        // Failed packages stay in the sync banner's pending list
+        console.log("[App] Hot-sync succeeded:", response.installed);
+        return response.failed.length === 0;
       }
 
       if (
@@ -448,6 +595,27 @@ function AppContent() {
     tryStartKernel,
   ]);
 
+  // Add the package behind a failed import, then sync it into the environment
+  const installSuggestedPackage = useCallback(
+    async (suggestion: InstallSuggestion) => {
+      setInstallingSuggestion(true);
+      try {
+        if (envType === "conda") {
+          await addCondaDependency(suggestion.condaPackage);
+        } else {
+          await addDependency(suggestion.package);
+        }
+        setInstallSuggestion(null);
+        await handleSyncDeps();
+      } catch (e) {
+        console.error("[App] Failed to install suggested package:", e);
+      } finally {
+        setInstallingSuggestion(false);
+      }
+    },
+    [envType, addDependency, addCondaDependency, handleSyncDeps],
+  );
+
   // Restart and run all cells
   const restartAndRunAll = useCallback(async () => {
     const codeCells = cells.filter((c) => c.cell_type === "code");
@@ -472,6 +640,8 @@ function AppContent() {
       return;
     }
 
+    await attachSidecar(codeCells.map((cell) => cell.id));
+
     // Daemon reads cell sources from Automerge doc and queues them
     const response = await daemonRunAllCells();
     if (response.result === "error") {
@@ -485,9 +655,66 @@ function AppContent() {
     clearOutputs,
     shutdownKernel,
     tryStartKernel,
+    attachSidecar,
     daemonRunAllCells,
   ]);
 
+  // Restart the kernel, carrying the user's variables over with dill.
+  // The kernel is left alone if the snapshot fails (e.g. dill missing).
+  const handleRestartAndRestore = useCallback(async () => {
+    setStateRestoreNotice(null);
+    try {
+      const snapshot = await snapshotKernelState();
+      if (snapshot.result !== "kernel_state_snapshot") return;
+
+      await shutdownKernel();
+      const kernelStarted = await tryStartKernel();
+      if (!kernelStarted) {
+        console.log("[App] restartAndRestore: kernel not started");
+        return;
+      }
+
+      const restored = await restoreKernelState();
+      if (restored.result !== "kernel_state_restored") return;
+      setStateRestoreNotice({
+        restored: restored.restored,
+        dropped: [...snapshot.dropped, ...restored.dropped],
+      });
+    } catch (e) {
+      console.error("[App] restartAndRestore failed:", e);
+      setStateRestoreNotice({
+        restored: [],
+        dropped: [],
+        error: errorMessage(e),
+      });
+    }
+  }, [
+    snapshotKernelState,
+    shutdownKernel,
+    tryStartKernel,
+    restoreKernelState,
+  ]);
+
+  // Restart the kernel in the environment re-detected after Save As
+  const applyRedetectedEnv = useCallback(async (env: DetectedEnvironment) => {
+    setApplyingRedetectedEnv(true);
+    try {
+      const response = await invoke<DaemonNotebookResponse>(
+        "switch_environment",
+        { optionId: env.env_source },
+      );
+      if (response.result === "error") {
+        console.error("[App] applyRedetectedEnv: daemon error", response.error);
+        return;
+      }
+      setRedetectedEnv(null);
+    } catch (e) {
+      console.error("[App] applyRedetectedEnv failed:", e);
+    } finally {
+      setApplyingRedetectedEnv(false);
+    }
+  }, []);
+
   // Handle trust approval from dialog
   const handleTrustApprove = useCallback(async () => {
     const success = await approveTrust();
@@ -536,6 +763,7 @@ function AppContent() {
         // For startup races (e.g. daemon already auto-starting), still try execute.
         if (!started && pendingKernelStartRef.current) return;
       }
+      await attachSidecar([cellId]);
       const response = await executeCell(cellId);
       if (response.result === "error") {
         console.error("[App] handleExecuteCell: daemon error", response.error);
@@ -549,6 +777,7 @@ function AppContent() {
       cells,
       kernelStatus,
       tryStartKernel,
+      attachSidecar,
       executeCell,
     ],
   );
@@ -568,6 +797,23 @@ function AppContent() {
     [tryStartKernel],
   );
 
+  // Rebuild the environment behind a failed launch, then start the kernel on it
+  const handleRepairEnvironment = useCallback(async () => {
+    setRepairingEnv(true);
+    try {
+      const response = await repairEnvironment();
+      if (response.result === "environment_repaired") {
+        await tryStartKernel();
+      } else if (response.result === "error") {
+        console.error("[App] Environment repair failed:", response.error);
+      }
+    } catch (e) {
+      console.error("[App] Environment repair failed:", e);
+    } finally {
+      setRepairingEnv(false);
+    }
+  }, [repairEnvironment, tryStartKernel]);
+
   // Restart kernel (shutdown then start)
   const handleRestartKernel = useCallback(async () => {
     await shutdownKernel();
@@ -598,6 +844,8 @@ function AppContent() {
       }
     }
 
+    await attachSidecar(codeCells.map((cell) => cell.id));
+
     // Daemon reads cell sources from Automerge doc and queues them
     const response = await daemonRunAllCells();
     if (response.result === "error") {
@@ -611,9 +859,24 @@ function AppContent() {
     cells,
     clearCellOutputs,
     clearOutputs,
+    attachSidecar,
     daemonRunAllCells,
   ]);
 
+  const handleClearAllOutputs = useCallback(async () => {
+    // Clear locally for immediate feedback; the daemon syncs other windows
+    for (const cell of cells) {
+      clearCellOutputs(cell.id);
+    }
+    const response = await clearAllOutputs();
+    if (response.result === "error") {
+      console.error(
+        "[App] handleClearAllOutputs: daemon error",
+        response.error,
+      );
+    }
+  }, [cells, clearCellOutputs, clearAllOutputs]);
+
   const handleRestartAndRunAll = useCallback(async () => {
     // Backend clears outputs and emits cells:outputs_cleared before queuing,
     // then ensureKernelStarted restarts the kernel
@@ -643,6 +906,24 @@ function AppContent() {
     };
   }, [save]);
 
+  // Save As re-detects project files from the new location; offer to switch
+  // when the running kernel's environment came from somewhere else
+  useEffect(() => {
+    const webview = getCurrentWebview();
+    const unlistenPromise = webview.listen<DetectedEnvironment | null>(
+      "environment:redetected",
+      (event) => {
+        const detected = event.payload;
+        setRedetectedEnv(
+          detected && detected.env_source !== envSource ? detected : null,
+        );
+      },
+    );
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, [envSource]);
+
   // Cmd+O to open (keyboard and native menu)
   useEffect(() => {
     const webview = getCurrentWebview();
@@ -678,6 +959,18 @@ function AppContent() {
     };
   }, [cloneNotebook]);
 
+  // Save a copy without outputs via native menu
+  useEffect(() => {
+    const webview = getCurrentWebview();
+    const unlistenPromise = webview.listen("menu:save-copy-clean", () => {
+      saveCopyWithoutOutputs();
+    });
+
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, [saveCopyWithoutOutputs]);
+
   // Kernel menu: Run All Cells
   useEffect(() => {
     const webview = getCurrentWebview();
@@ -689,6 +982,17 @@ function AppContent() {
     };
   }, [handleRunAllCells]);
 
+  // Kernel menu: Clear All Outputs
+  useEffect(() => {
+    const webview = getCurrentWebview();
+    const unlistenPromise = webview.listen("menu:clear-all-outputs", () => {
+      handleClearAllOutputs();
+    });
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, [handleClearAllOutputs]);
+
   // Kernel menu: Restart & Run All Cells
   useEffect(() => {
     const webview = getCurrentWebview();
@@ -700,6 +1004,17 @@ function AppContent() {
     };
   }, [handleRestartAndRunAll]);
 
+  // Kernel menu: Restart & Restore Variables
+  useEffect(() => {
+    const webview = getCurrentWebview();
+    const unlistenPromise = webview.listen("menu:restart-and-restore", () => {
+      handleRestartAndRestore();
+    });
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, [handleRestartAndRestore]);
+
   // Zoom controls via native menu
   useEffect(() => {
     const webview = getCurrentWebview();
@@ -856,7 +1171,7 @@ function AppContent() {
             .catch((e) => {
               setDaemonStatus({
                 status: "failed",
-                error: `Reconnection failed: ${e}`,
+                error: `Reconnection failed: ${errorMessage(e)}`,
               });
             });
         }}
@@ -896,57 +1211,244 @@ function AppContent() {
         onRestartToUpdate={restartToUpdate}
       />
       {/* Dual-dependency choice: both UV and conda deps exist, let user pick */}
-      {dependencyHeaderOpen &&
-        runtime === "python" &&
-        hasUvDependencies &&
-        hasCondaDependencies && (
-          <div className="border-b bg-amber-50/50 dark:bg-amber-950/20 px-3 py-2">
-            <div className="flex items-center gap-2 text-xs text-amber-700 dark:text-amber-400">
-              <span className="shrink-0">&#9888;</span>
-              <span className="font-medium">
-                This notebook has both uv and conda dependencies.
-              </span>
-              <div className="flex gap-1.5 ml-auto shrink-0">
-                <button
-                  disabled={clearingDeps}
-                  onClick={async () => {
-                    setClearingDeps(true);
-                    try {
-                      await clearAllCondaDeps();
-                    } finally {
-                      setClearingDeps(false);
-                    }
-                  }}
-                  className="px-2 py-0.5 text-xs font-medium rounded bg-fuchsia-100 dark:bg-fuchsia-900/40 hover:bg-fuchsia-200 dark:hover:bg-fuchsia-800/50 text-fuchsia-800 dark:text-fuchsia-300 border border-fuchsia-300 dark:border-fuchsia-700 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
-                >
-                  Use uv ({dependencies?.dependencies?.length ?? 0}{" "}
-                  {(dependencies?.dependencies?.length ?? 0) === 1
-                    ? "package"
-                    : "packages"}
-                  )
-                </button>
-                <button
-                  disabled={clearingDeps}
-                  onClick={async () => {
-                    setClearingDeps(true);
-                    try {
-                      await clearAllUvDeps();
-                    } finally {
-                      setClearingDeps(false);
-                    }
-                  }}
-                  className="px-2 py-0.5 text-xs font-medium rounded bg-emerald-100 dark:bg-emerald-900/40 hover:bg-emerald-200 dark:hover:bg-emerald-800/50 text-emerald-800 dark:text-emerald-300 border border-emerald-300 dark:border-emerald-700 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
-                >
-                  Use conda ({condaDependencies?.dependencies?.length ?? 0}{" "}
-                  {(condaDependencies?.dependencies?.length ?? 0) === 1
-                    ? "package"
-                    : "packages"}
-                  )
-                </button>
+      {runtime === "python" && envConflict && (
+        <div
+          data-testid="env-conflict-banner"
+          className="border-b bg-amber-50/50 dark:bg-amber-950/20 px-3 py-2"
+        >
+          <div className="flex items-center gap-2 text-xs text-amber-700 dark:text-amber-400">
+            <span className="shrink-0">&#9888;</span>
+            <div className="min-w-0">
+              <div className="font-medium">
+                This notebook has both uv and conda dependencies. Kernels
+                start with {envConflict.auto_launch_section} until you pick
+                one.
               </div>
+              {envConflict.uv_only.length > 0 && (
+                <div className="truncate">
+                  Only in uv: {envConflict.uv_only.join(", ")}
+                </div>
+              )}
+              {envConflict.conda_only.length > 0 && (
+                <div className="truncate">
+                  Only in conda: {envConflict.conda_only.join(", ")}
+                </div>
+              )}
+            </div>
+            <div className="flex gap-1.5 ml-auto shrink-0">
+              <button
+                disabled={clearingDeps}
+                onClick={async () => {
+                  setClearingDeps(true);
+                  try {
+                    await clearAllCondaDeps();
+                  } finally {
+                    setClearingDeps(false);
+                  }
+                }}
+                className="px-2 py-0.5 text-xs font-medium rounded bg-fuchsia-100 dark:bg-fuchsia-900/40 hover:bg-fuchsia-200 dark:hover:bg-fuchsia-800/50 text-fuchsia-800 dark:text-fuchsia-300 border border-fuchsia-300 dark:border-fuchsia-700 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
+              >
+                Use uv ({dependencies?.dependencies?.length ?? 0}{" "}
+                {(dependencies?.dependencies?.length ?? 0) === 1
+                  ? "package"
+                  : "packages"}
+                )
+              </button>
+              <button
+                disabled={clearingDeps}
+                onClick={async () => {
+                  setClearingDeps(true);
+                  try {
+                    await clearAllUvDeps();
+                  } finally {
+                    setClearingDeps(false);
+                  }
+                }}
+                className="px-2 py-0.5 text-xs font-medium rounded bg-emerald-100 dark:bg-emerald-900/40 hover:bg-emerald-200 dark:hover:bg-emerald-800/50 text-emerald-800 dark:text-emerald-300 border border-emerald-300 dark:border-emerald-700 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
+              >
+                Use conda ({condaDependencies?.dependencies?.length ?? 0}{" "}
+                {(condaDependencies?.dependencies?.length ?? 0) === 1
+                  ? "package"
+                  : "packages"}
+                )
+              </button>
+            </div>
+          </div>
+        </div>
+      )}
+      {/* Launch failed on a broken cached environment: offer to rebuild it */}
+      {envRepairAvailable && (
+        <div
+          data-testid="env-repair-banner"
+          className="border-b bg-amber-50/50 dark:bg-amber-950/20 px-3 py-2"
+        >
+          <div className="flex items-center gap-2 text-xs text-amber-700 dark:text-amber-400">
+            <span className="shrink-0">&#9888;</span>
+            <span className="min-w-0 truncate">
+              The kernel couldn't start because its environment looks broken.
+            </span>
+            <button
+              disabled={repairingEnv}
+              onClick={handleRepairEnvironment}
+              className="ml-auto shrink-0 px-2 py-0.5 text-xs font-medium rounded bg-amber-100 dark:bg-amber-900/40 hover:bg-amber-200 dark:hover:bg-amber-800/50 text-amber-800 dark:text-amber-300 border border-amber-300 dark:border-amber-700 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
+            >
+              {repairingEnv ? "Rebuilding..." : "Rebuild environment"}
+            </button>
+          </div>
+        </div>
+      )}
+      {/* Shared env_id: another open notebook would fight over the same env */}
+      {sharedEnvNames.length > 0 && (
+        <div
+          data-testid="shared-env-banner"
+          className="border-b bg-amber-50/50 dark:bg-amber-950/20 px-3 py-2"
+        >
+          <div className="flex items-center gap-2 text-xs text-amber-700 dark:text-amber-400">
+            <span className="shrink-0">&#9888;</span>
+            <span className="min-w-0 truncate">
+              This notebook shares its environment with{" "}
+              {sharedEnvNames.join(", ")}. Changes to one affect the other.
+            </span>
+            <button
+              disabled={regeneratingEnvId}
+              onClick={async () => {
+                setRegeneratingEnvId(true);
+                try {
+                  await invoke("regenerate_env_id", { copyEnvironment: true });
+                  setKernelsSharingEnv([]);
+                } catch (e) {
+                  console.error("Failed to regenerate env_id:", e);
+                } finally {
+                  setRegeneratingEnvId(false);
+                  detectSharedEnvId();
+                }
+              }}
+              className="ml-auto shrink-0 px-2 py-0.5 text-xs font-medium rounded bg-amber-100 dark:bg-amber-900/40 hover:bg-amber-200 dark:hover:bg-amber-800/50 text-amber-800 dark:text-amber-300 border border-amber-300 dark:border-amber-700 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
+            >
+              {regeneratingEnvId ? "Separating..." : "Use separate environment"}
+            </button>
+          </div>
+        </div>
+      )}
+      {/* Last-resort kernel: no uv or conda, so dependencies can't be installed */}
+      {envSource === "system:python" && (
+        <div
+          data-testid="system-python-banner"
+          className="border-b bg-amber-50/50 dark:bg-amber-950/20 px-3 py-2"
+        >
+          <div className="flex items-center gap-2 text-xs text-amber-700 dark:text-amber-400">
+            <span className="shrink-0">&#9888;</span>
+            <span className="min-w-0 truncate">
+              Running on the system Python because neither uv nor conda is
+              available. Dependency management is unavailable; packages must
+              already be installed.
+            </span>
+          </div>
+        </div>
+      )}
+      {runtime === "python" && installSuggestion && (
+        <div
+          data-testid="install-suggestion-banner"
+          className="border-b bg-sky-50/50 dark:bg-sky-950/20 px-3 py-2"
+        >
+          <div className="flex items-center gap-2 text-xs text-sky-700 dark:text-sky-400">
+            <span className="min-w-0 truncate">
+              No module named <code>{installSuggestion.module}</code>. Add{" "}
+              <code>{suggestedPackage}</code> to this notebook's
+              dependencies?
+            </span>
+            <div className="flex gap-1.5 ml-auto shrink-0">
+              <button
+                disabled={installingSuggestion}
+                onClick={() => installSuggestedPackage(installSuggestion)}
+                className="px-2 py-0.5 text-xs font-medium rounded bg-sky-100 dark:bg-sky-900/40 hover:bg-sky-200 dark:hover:bg-sky-800/50 text-sky-800 dark:text-sky-300 border border-sky-300 dark:border-sky-700 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
+              >
+                Install {suggestedPackage}
+              </button>
+              <button
+                disabled={installingSuggestion}
+                onClick={() => setInstallSuggestion(null)}
+                className="px-2 py-0.5 text-xs font-medium rounded hover:bg-sky-100 dark:hover:bg-sky-900/40 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
+              >
+                Dismiss
+              </button>
             </div>
           </div>
-        )}
+        </div>
+      )}
+      {runtime === "python" && redetectedEnv && (
+        <div
+          data-testid="redetected-env-banner"
+          className="border-b bg-sky-50/50 dark:bg-sky-950/20 px-3 py-2"
+        >
+          <div className="flex items-center gap-2 text-xs text-sky-700 dark:text-sky-400">
+            <span
+              className="min-w-0 truncate"
+              title={redetectedEnv.project_file}
+            >
+              This notebook is now in a project with{" "}
+              <code>{redetectedEnv.project_file.split(/[\\/]/).pop()}</code>.
+              Restart the kernel in its environment?
+            </span>
+            <div className="flex gap-1.5 ml-auto shrink-0">
+              <button
+                disabled={applyingRedetectedEnv}
+                onClick={() => applyRedetectedEnv(redetectedEnv)}
+                className="px-2 py-0.5 text-xs font-medium rounded bg-sky-100 dark:bg-sky-900/40 hover:bg-sky-200 dark:hover:bg-sky-800/50 text-sky-800 dark:text-sky-300 border border-sky-300 dark:border-sky-700 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
+              >
+                {applyingRedetectedEnv ? "Restarting..." : "Restart kernel"}
+              </button>
+              <button
+                disabled={applyingRedetectedEnv}
+                onClick={() => setRedetectedEnv(null)}
+                className="px-2 py-0.5 text-xs font-medium rounded hover:bg-sky-100 dark:hover:bg-sky-900/40 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
+              >
+                Dismiss
+              </button>
+            </div>
+          </div>
+        </div>
+      )}
+      {stateRestoreNotice && (
+        <div
+          data-testid="state-restore-banner"
+          className="border-b bg-sky-50/50 dark:bg-sky-950/20 px-3 py-2"
+        >
+          <div className="flex items-center gap-2 text-xs text-sky-700 dark:text-sky-400">
+            <span
+              className="min-w-0 truncate"
+              title={stateRestoreNotice.dropped
+                .map((d) => `${d.name}: ${d.reason}`)
+                .join("\n")}
+            >
+              {stateRestoreNotice.error
+                ? `Couldn't restore variables: ${stateRestoreNotice.error}`
+                : `Restored ${stateRestoreNotice.restored.length} ${
+                    stateRestoreNotice.restored.length === 1
+                      ? "variable"
+                      : "variables"
+                  }.`}
+              {stateRestoreNotice.dropped.length > 0 && (
+                <>
+                  {" "}
+                  Dropped:{" "}
+                  <code>
+                    {stateRestoreNotice.dropped.map((d) => d.name).join(", ")}
+                  </code>
+                </>
+              )}
+            </span>
+            <div className="flex gap-1.5 ml-auto shrink-0">
+              <button
+                onClick={() => setStateRestoreNotice(null)}
+                className="px-2 py-0.5 text-xs font-medium rounded hover:bg-sky-100 dark:hover:bg-sky-900/40 transition-colors"
+              >
+                Dismiss
+              </button>
+            </div>
+          </div>
+        </div>
+      )}
       {dependencyHeaderOpen && runtime === "deno" && (
         <DenoDependencyHeader
           denoAvailable={denoAvailable}
@@ -962,6 +1464,7 @@ function AppContent() {
         <CondaDependencyHeader
           dependencies={condaDependencies?.dependencies ?? []}
           channels={condaDependencies?.channels ?? []}
+          channelPriority={condaDependencies?.channel_priority ?? null}
           python={condaDependencies?.python ?? null}
           loading={condaDepsLoading}
           syncing={condaSyncing}
@@ -970,7 +1473,11 @@ function AppContent() {
           needsKernelRestart={condaNeedsKernelRestart}
           onAdd={addCondaDependency}
           onRemove={removeCondaDependency}
+          pipDependencies={condaDependencies?.pip_dependencies ?? []}
+          onAddPip={addCondaPipDependency}
+          onRemovePip={removeCondaPipDependency}
           onSetChannels={setCondaChannels}
+          onSetChannelPriority={setCondaChannelPriority}
           onSetPython={setCondaPython}
           onSyncNow={condaDerivedSyncState ? handleSyncDeps : syncCondaNow}
           envProgress={envProgress.envType === "conda" ? envProgress : null}
@@ -990,6 +1497,7 @@ function AppContent() {
           syncedWhileRunning={syncedWhileRunning}
           needsKernelRestart={needsKernelRestart}
           onAdd={addDependency}
+          onImportRequirements={importRequirements}
           onRemove={removeDependency}
           syncState={uvDerivedSyncState ?? syncState}
           onSyncNow={uvDerivedSyncState ? handleSyncDeps : syncNow}
@@ -1001,6 +1509,7 @@ function AppContent() {
         />
       )}
       {showIsolationTest && <IsolationTest />}
+      <DirectoryPicker />
       <TrustDialog
         open={trustDialogOpen}
         onOpenChange={setTrustDialogOpen}
@@ -1022,9 +1531,15 @@ function AppContent() {
         onExecuteCell={handleExecuteCell}
         onInterruptKernel={interruptKernel}
         onDeleteCell={deleteCell}
+        onDuplicateCell={duplicateCell}
         onAddCell={handleAddCell}
         onClearPagePayload={clearPagePayload}
         onFormatCell={formatCell}
+        sidecarMime={sidecarMime}
+        sidecarCellIds={sidecarCellIds}
+        largeOutputBytes={largeOutputBytes}
+        onOpenSidecar={openSidecar}
+        onSetOutputCollapsed={setOutputCollapsed}
       />
     </div>
   );
@@ -1,5 +1,5 @@
 import type { KeyBinding } from "@codemirror/view";
-import { Trash2, X } from "lucide-react";
+import { Copy, Trash2, X } from "lucide-react";
 import {
   lazy,
   Suspense,
@@ -33,6 +33,18 @@ const HistorySearchDialog = lazy(() =>
   })),
 );
 
+/** Output height for cells with nbformat `scrolled` set, in pixels */
+const SCROLLED_OUTPUT_HEIGHT = 400;
+
+/** Format an execution duration like JupyterLab's execution-time extension */
+function formatDuration(ms: number): string {
+  if (ms < 1000) return `${ms}ms`;
+  if (ms < 60_000) return `${(ms / 1000).toFixed(1)}s`;
+  const minutes = Math.floor(ms / 60_000);
+  const seconds = Math.round((ms % 60_000) / 1000);
+  return `${minutes}m ${seconds}s`;
+}
+
 /** Page payload display component - Zed REPL style */
 function PagePayloadDisplay({
   data,
@@ -84,12 +96,22 @@ interface CodeCellProps {
   onExecute: () => void;
   onInterrupt: () => void;
   onDelete: () => void;
+  /** Insert a copy of this cell, with its outputs, below it */
+  onDuplicate?: () => void;
   onFocusPrevious?: (cursorPosition: "start" | "end") => void;
   onFocusNext?: (cursorPosition: "start" | "end") => void;
   onInsertCellAfter?: () => void;
   onClearPagePayload?: () => void;
   onFormat?: () => void;
   isLastCell?: boolean;
+  sidecarMime?: readonly string[];
+  largeOutputBytes?: number;
+  onOpenSidecar?: () => void;
+  /** Collapse or expand the outputs, or one output by index */
+  onSetOutputCollapsed?: (
+    outputIndex: number | null,
+    collapsed: boolean,
+  ) => void;
 }
 
 export function CodeCell({
@@ -103,12 +125,17 @@ export function CodeCell({
   onExecute,
   onInterrupt,
   onDelete,
+  onDuplicate,
   onFocusPrevious,
   onFocusNext,
   onInsertCellAfter,
   onClearPagePayload,
   onFormat,
   isLastCell = false,
+  sidecarMime,
+  largeOutputBytes,
+  onOpenSidecar,
+  onSetOutputCollapsed,
 }: CodeCellProps) {
   const editorRef = useRef<CodeMirrorEditorRef>(null);
   const { registerEditor, unregisterEditor } = useEditorRegistry();
@@ -214,15 +241,28 @@ export function CodeCell({
   );
 
   const rightGutterContent = (
-    <button
-      type="button"
-      tabIndex={-1}
-      onClick={onDelete}
-      className="flex items-center justify-center rounded p-1 text-muted-foreground/40 transition-colors hover:text-destructive"
-      title="Delete cell"
-    >
-      <Trash2 className="h-3.5 w-3.5" />
-    </button>
+    <div className="flex flex-col items-center">
+      <button
+        type="button"
+        tabIndex={-1}
+        onClick={onDelete}
+        className="flex items-center justify-center rounded p-1 text-muted-foreground/40 transition-colors hover:text-destructive"
+        title="Delete cell"
+      >
+        <Trash2 className="h-3.5 w-3.5" />
+      </button>
+      {onDuplicate && (
+        <button
+          type="button"
+          tabIndex={-1}
+          onClick={onDuplicate}
+          className="flex items-center justify-center rounded p-1 text-muted-foreground/40 transition-colors hover:text-foreground"
+          title="Duplicate cell"
+        >
+          <Copy className="h-3.5 w-3.5" />
+        </button>
+      )}
+    </div>
   );
 
   return (
@@ -251,6 +291,16 @@ export function CodeCell({
               />
             </div>
 
+            {/* Last execution time */}
+            {cell.execution && !isExecuting && (
+              <div
+                className="px-2 text-right text-xs text-muted-foreground/60"
+                title={`Finished ${new Date(cell.execution.completed_at).toLocaleString()}`}
+              >
+                {formatDuration(cell.execution.duration_ms)}
+              </div>
+            )}
+
             {/* Page Payload (documentation from ? or ??) */}
             {pagePayload && (
               <div className="px-2 py-1">
@@ -271,7 +321,32 @@ export function CodeCell({
             )}
           </>
         }
-        outputContent={<OutputArea outputs={cell.outputs} preloadIframe />}
+        outputContent={
+          <OutputArea
+            outputs={cell.outputs}
+            preloadIframe
+            sidecarMime={sidecarMime}
+            largeOutputBytes={largeOutputBytes}
+            onOpenSidecar={onOpenSidecar}
+            collapsed={cell.collapsed}
+            onToggleCollapse={
+              onSetOutputCollapsed
+                ? () => onSetOutputCollapsed(null, !cell.collapsed)
+                : undefined
+            }
+            maxHeight={cell.scrolled ? SCROLLED_OUTPUT_HEIGHT : undefined}
+            collapsedOutputs={cell.collapsed_outputs}
+            onToggleOutputCollapse={
+              onSetOutputCollapsed
+                ? (index) =>
+                    onSetOutputCollapsed(
+                      index,
+                      !cell.collapsed_outputs?.includes(index),
+                    )
+                : undefined
+            }
+          />
+        }
         hideOutput={cell.outputs.length === 0}
       />
 
@@ -10,16 +10,20 @@ import {
 import { type KeyboardEvent, useCallback, useState } from "react";
 import { Progress } from "@/components/ui/progress";
 import type {
+  CondaChannelPriority,
   CondaSyncState,
   EnvironmentYmlDeps,
   EnvironmentYmlInfo,
 } from "../hooks/useCondaDependencies";
 import type { EnvProgressState } from "../hooks/useEnvProgress";
+import { errorMessage } from "../lib/command-error";
 import type { PixiInfo } from "../types";
+import { RevealInFileManagerButton } from "./RevealInFileManagerButton";
 
 interface CondaDependencyHeaderProps {
   dependencies: string[];
   channels: string[];
+  channelPriority: CondaChannelPriority | null;
   python: string | null;
   loading: boolean;
   syncing: boolean;
@@ -28,7 +32,12 @@ interface CondaDependencyHeaderProps {
   needsKernelRestart: boolean;
   onAdd: (pkg: string) => Promise<void>;
   onRemove: (pkg: string) => Promise<void>;
+  /** PyPI-only packages, pip-installed after the conda solve */
+  pipDependencies?: string[];
+  onAddPip?: (pkg: string) => Promise<void>;
+  onRemovePip?: (pkg: string) => Promise<void>;
   onSetChannels: (channels: string[]) => Promise<void>;
+  onSetChannelPriority: (priority: CondaChannelPriority) => Promise<void>;
   onSetPython: (python: string | null) => Promise<void>;
   onSyncNow: () => Promise<boolean>;
   /** Environment preparation progress state */
@@ -47,6 +56,7 @@ interface CondaDependencyHeaderProps {
 export function CondaDependencyHeader({
   dependencies,
   channels,
+  channelPriority,
   python,
   loading,
   syncing,
@@ -55,7 +65,11 @@ export function CondaDependencyHeader({
   needsKernelRestart,
   onAdd,
   onRemove,
+  pipDependencies = [],
+  onAddPip,
+  onRemovePip,
   onSetChannels,
+  onSetChannelPriority,
   onSetPython,
   onSyncNow,
   envProgress,
@@ -66,13 +80,21 @@ export function CondaDependencyHeader({
   onImportFromPixi,
 }: CondaDependencyHeaderProps) {
   const [newDep, setNewDep] = useState("");
+  const [addError, setAddError] = useState<string | null>(null);
+  const [newPipDep, setNewPipDep] = useState("");
+  const [pipError, setPipError] = useState<string | null>(null);
   const [newChannel, setNewChannel] = useState("");
   const [showChannelInput, setShowChannelInput] = useState(false);
 
   const handleAdd = useCallback(async () => {
     if (newDep.trim()) {
-      await onAdd(newDep.trim());
-      setNewDep("");
+      try {
+        await onAdd(newDep.trim());
+        setNewDep("");
+        setAddError(null);
+      } catch (e) {
+        setAddError(errorMessage(e));
+      }
     }
   }, [newDep, onAdd]);
 
@@ -86,6 +108,18 @@ export function CondaDependencyHeader({
     [handleAdd],
   );
 
+  const handleAddPip = useCallback(async () => {
+    if (onAddPip && newPipDep.trim()) {
+      try {
+        await onAddPip(newPipDep.trim());
+        setNewPipDep("");
+        setPipError(null);
+      } catch (e) {
+        setPipError(errorMessage(e));
+      }
+    }
+  }, [newPipDep, onAddPip]);
+
   const handleAddChannel = useCallback(async () => {
     if (newChannel.trim()) {
       const updated = [...channels, newChannel.trim()];
@@ -218,6 +252,7 @@ export function CondaDependencyHeader({
                   </span>
                 )}
               </span>
+              <RevealInFileManagerButton path={environmentYmlInfo.path} />
             </div>
             {environmentYmlDeps &&
               (environmentYmlDeps.dependencies.length > 0 ||
@@ -295,6 +330,7 @@ export function CondaDependencyHeader({
                     {pixiInfo.dependency_count !== 1 ? "s" : ""}
                   </span>
                 </span>
+                <RevealInFileManagerButton path={pixiInfo.path} />
               </div>
               {onImportFromPixi && (
                 <button
@@ -314,7 +350,24 @@ export function CondaDependencyHeader({
 
         {/* Channels */}
         <div className="mb-2">
-          <div className="mb-1 text-xs text-muted-foreground">Channels:</div>
+          <div className="mb-1 flex items-center gap-3 text-xs text-muted-foreground">
+            <span>Channels:</span>
+            <label
+              className="flex items-center gap-1"
+              title="Take each package only from the first channel listed that has it"
+            >
+              <input
+                type="checkbox"
+                checked={channelPriority !== "disabled"}
+                onChange={(e) =>
+                  onSetChannelPriority(e.target.checked ? "strict" : "disabled")
+                }
+                disabled={loading}
+                data-testid="conda-strict-priority"
+              />
+              Strict priority
+            </label>
+          </div>
           <div className="flex flex-wrap gap-1.5 items-center">
             {displayChannels.map((channel) => (
               <div
@@ -428,7 +481,10 @@ export function CondaDependencyHeader({
           <input
             type="text"
             value={newDep}
-            onChange={(e) => setNewDep(e.target.value)}
+            onChange={(e) => {
+              setNewDep(e.target.value);
+              setAddError(null);
+            }}
             onKeyDown={handleKeyDown}
             placeholder="package or package>=version"
             data-testid="conda-deps-add-input"
@@ -448,6 +504,85 @@ export function CondaDependencyHeader({
             Add
           </button>
         </div>
+        {addError && (
+          <div
+            className="mt-1.5 text-xs text-destructive"
+            data-testid="conda-deps-add-error"
+          >
+            {addError}
+          </div>
+        )}
+
+        {/* PyPI-only packages, pip-installed after the conda solve */}
+        {onAddPip && (
+          <div className="mt-3" data-testid="conda-pip-deps">
+            <div className="mb-1 text-xs text-muted-foreground">
+              PyPI packages (installed with pip after conda):
+            </div>
+            {pipDependencies.length > 0 && (
+              <div className="mb-2 flex flex-wrap gap-1.5">
+                {pipDependencies.map((dep) => (
+                  <div
+                    key={dep}
+                    className="flex items-center gap-1 rounded bg-background px-2 py-1 text-xs border border-dashed"
+                  >
+                    <span className="font-mono">{dep}</span>
+                    {onRemovePip && (
+                      <button
+                        type="button"
+                        onClick={() => onRemovePip(dep)}
+                        className="text-muted-foreground hover:text-foreground transition-colors"
+                        disabled={loading}
+                        title={`Remove ${dep}`}
+                      >
+                        <X className="h-3 w-3" />
+                      </button>
+                    )}
+                  </div>
+                ))}
+              </div>
+            )}
+            <div className="flex gap-2">
+              <input
+                type="text"
+                value={newPipDep}
+                onChange={(e) => {
+                  setNewPipDep(e.target.value);
+                  setPipError(null);
+                }}
+                onKeyDown={(e) => {
+                  if (e.key === "Enter") {
+                    e.preventDefault();
+                    handleAddPip();
+                  }
+                }}
+                placeholder="PyPI-only package"
+                data-testid="conda-pip-deps-add-input"
+                className="flex-1 rounded border bg-background px-2 py-1 text-xs placeholder:text-muted-foreground focus:outline-none focus:ring-1 focus:ring-primary"
+                disabled={loading}
+                autoComplete="off"
+                spellCheck={false}
+              />
+              <button
+                type="button"
+                onClick={handleAddPip}
+                disabled={loading || !newPipDep.trim()}
+                className="flex items-center gap-1 rounded border bg-background px-2 py-1 text-xs transition-colors hover:bg-muted disabled:opacity-50"
+              >
+                <Plus className="h-3 w-3" />
+                pip
+              </button>
+            </div>
+            {pipError && (
+              <div
+                className="mt-1.5 text-xs text-destructive"
+                data-testid="conda-pip-deps-add-error"
+              >
+                {pipError}
+              </div>
+            )}
+          </div>
+        )}
       </div>
     </div>
   );
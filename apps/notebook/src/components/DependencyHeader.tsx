@@ -1,10 +1,18 @@
 import { Download, FileText, Info, Plus, RefreshCw, X } from "lucide-react";
-import { type KeyboardEvent, useCallback, useState } from "react";
+import {
+  type ClipboardEvent,
+  type KeyboardEvent,
+  useCallback,
+  useState,
+} from "react";
 import type {
   EnvSyncState,
   PyProjectDeps,
   PyProjectInfo,
+  RequirementsImport,
 } from "../hooks/useDependencies";
+import { errorMessage } from "../lib/command-error";
+import { RevealInFileManagerButton } from "./RevealInFileManagerButton";
 
 interface DependencyHeaderProps {
   dependencies: string[];
@@ -14,6 +22,8 @@ interface DependencyHeaderProps {
   syncedWhileRunning: boolean;
   needsKernelRestart: boolean;
   onAdd: (pkg: string) => Promise<void>;
+  /** Add every line of a pasted requirements.txt block */
+  onImportRequirements?: (text: string) => Promise<RequirementsImport>;
   onRemove: (pkg: string) => Promise<void>;
   // Environment sync state
   syncState?: EnvSyncState | null;
@@ -37,6 +47,7 @@ export function DependencyHeader({
   syncedWhileRunning,
   needsKernelRestart,
   onAdd,
+  onImportRequirements,
   onRemove,
   syncState,
   onSyncNow,
@@ -47,14 +58,43 @@ export function DependencyHeader({
   isUsingProjectEnv,
 }: DependencyHeaderProps) {
   const [newDep, setNewDep] = useState("");
+  const [addError, setAddError] = useState<string | null>(null);
+  const [importNotice, setImportNotice] = useState<string | null>(null);
 
   const handleAdd = useCallback(async () => {
     if (newDep.trim()) {
-      await onAdd(newDep.trim());
-      setNewDep("");
+      try {
+        await onAdd(newDep.trim());
+        setNewDep("");
+        setAddError(null);
+      } catch (e) {
+        setAddError(errorMessage(e));
+      }
     }
   }, [newDep, onAdd]);
 
+  // Multi-line pastes are treated as a requirements.txt block
+  const handlePaste = useCallback(
+    async (e: ClipboardEvent<HTMLInputElement>) => {
+      const text = e.clipboardData.getData("text");
+      if (!onImportRequirements || !text.includes("\n")) return;
+      e.preventDefault();
+      try {
+        const { added, rejected, skipped } = await onImportRequirements(text);
+        const notice = [
+          `Added ${added} package${added === 1 ? "" : "s"}.`,
+          ...skipped.map((line) => `Ignored ${line}`),
+        ];
+        setImportNotice(notice.join(" "));
+        setAddError(rejected.length > 0 ? rejected.join("\n") : null);
+      } catch (err) {
+        setImportNotice(null);
+        setAddError(errorMessage(err));
+      }
+    },
+    [onImportRequirements],
+  );
+
   const handleKeyDown = useCallback(
     (e: KeyboardEvent<HTMLInputElement>) => {
       if (e.key === "Enter") {
@@ -168,6 +208,7 @@ export function DependencyHeader({
                     </span>
                   )}
                 </span>
+                <RevealInFileManagerButton path={pyprojectInfo.path} />
               </div>
               <div className="flex items-center gap-2">
                 {onUseProjectEnv && !isUsingProjectEnv && (
@@ -286,30 +327,53 @@ export function DependencyHeader({
 
         {/* Add dependency input (hidden when using project env) */}
         {!isUsingProjectEnv && (
-          <div className="flex gap-2">
-            <input
-              type="text"
-              value={newDep}
-              onChange={(e) => setNewDep(e.target.value)}
-              onKeyDown={handleKeyDown}
-              placeholder="package or package>=version"
-              data-testid="deps-add-input"
-              className="flex-1 rounded border bg-background px-2 py-1 text-xs placeholder:text-muted-foreground focus:outline-none focus:ring-1 focus:ring-primary"
-              disabled={loading}
-              autoComplete="off"
-              spellCheck={false}
-            />
-            <button
-              type="button"
-              onClick={handleAdd}
-              disabled={loading || !newDep.trim()}
-              data-testid="deps-add-button"
-              className="flex items-center gap-1 rounded bg-uv px-2 py-1 text-xs text-white transition-colors hover:bg-uv/90 disabled:opacity-50"
-            >
-              <Plus className="h-3 w-3" />
-              Add
-            </button>
-          </div>
+          <>
+            <div className="flex gap-2">
+              <input
+                type="text"
+                value={newDep}
+                onChange={(e) => {
+                  setNewDep(e.target.value);
+                  setAddError(null);
+                  setImportNotice(null);
+                }}
+                onKeyDown={handleKeyDown}
+                onPaste={handlePaste}
+                placeholder="package or package>=version"
+                data-testid="deps-add-input"
+                className="flex-1 rounded border bg-background px-2 py-1 text-xs placeholder:text-muted-foreground focus:outline-none focus:ring-1 focus:ring-primary"
+                disabled={loading}
+                autoComplete="off"
+                spellCheck={false}
+              />
+              <button
+                type="button"
+                onClick={handleAdd}
+                disabled={loading || !newDep.trim()}
+                data-testid="deps-add-button"
+                className="flex items-center gap-1 rounded bg-uv px-2 py-1 text-xs text-white transition-colors hover:bg-uv/90 disabled:opacity-50"
+              >
+                <Plus className="h-3 w-3" />
+                Add
+              </button>
+            </div>
+            {importNotice && (
+              <div
+                className="mt-1.5 text-xs text-muted-foreground"
+                data-testid="deps-import-notice"
+              >
+                {importNotice}
+              </div>
+            )}
+            {addError && (
+              <div
+                className="mt-1.5 whitespace-pre-line text-xs text-destructive"
+                data-testid="deps-add-error"
+              >
+                {addError}
+              </div>
+            )}
+          </>
         )}
       </div>
     </div>
@@ -0,0 +1,92 @@
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWebview } from "@tauri-apps/api/webview";
+import { FileText } from "lucide-react";
+import { useEffect, useState } from "react";
+import { errorMessage } from "../lib/command-error";
+import type { DirectoryListing } from "../types";
+
+/**
+ * Picker over the notebooks in a directory the app was opened on.
+ *
+ * Shown until a notebook is picked, which then loads in this window.
+ */
+export function DirectoryPicker() {
+  const [listing, setListing] = useState<DirectoryListing | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    // The event can fire before this listener exists, so also ask directly
+    invoke<DirectoryListing | null>("get_directory_listing")
+      .then(setListing)
+      .catch(console.error);
+
+    const webview = getCurrentWebview();
+    const unlistenListing = webview.listen<DirectoryListing>(
+      "notebook:directory-listing",
+      (event) => setListing(event.payload),
+    );
+    const unlistenOpened = webview.listen("notebook:file-opened", () =>
+      setListing(null),
+    );
+    return () => {
+      unlistenListing.then((fn) => fn());
+      unlistenOpened.then((fn) => fn());
+    };
+  }, []);
+
+  if (!listing) return null;
+
+  const open = (path: string) => {
+    setError(null);
+    invoke("open_notebook_in_current_window", { path }).catch((e) =>
+      setError(errorMessage(e)),
+    );
+  };
+
+  return (
+    <div className="fixed inset-0 z-50 flex items-center justify-center bg-background/80 backdrop-blur-sm">
+      <div className="w-full max-w-lg rounded-lg border bg-background shadow-lg">
+        <div className="border-b px-4 py-3">
+          <h2 className="text-sm font-medium">Open a notebook</h2>
+          <p className="truncate text-xs text-muted-foreground">
+            {listing.directory}
+          </p>
+        </div>
+        {listing.notebooks.length === 0 ? (
+          <p className="px-4 py-6 text-center text-sm text-muted-foreground">
+            No notebooks in this directory
+          </p>
+        ) : (
+          <ul className="max-h-96 overflow-y-auto py-1">
+            {listing.notebooks.map((entry) => (
+              <li key={entry.path}>
+                <button
+                  type="button"
+                  onClick={() => open(entry.path)}
+                  className="flex w-full items-center gap-2 px-4 py-1.5 text-left text-sm hover:bg-muted transition-colors"
+                >
+                  <FileText className="h-3.5 w-3.5 shrink-0 text-muted-foreground" />
+                  <span className="truncate">{entry.relative_path}</span>
+                </button>
+              </li>
+            ))}
+          </ul>
+        )}
+        {(listing.truncated || error) && (
+          <div className="border-t px-4 py-2 text-xs text-muted-foreground">
+            {error ?? "Showing the first notebooks found"}
+          </div>
+        )}
+        <div className="flex justify-end border-t px-4 py-2">
+          <button
+            type="button"
+            onClick={() => setListing(null)}
+            className="px-2 py-0.5 text-xs font-medium rounded hover:bg-muted transition-colors"
+          >
+            Start with an untitled notebook
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
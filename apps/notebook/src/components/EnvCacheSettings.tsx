@@ -0,0 +1,99 @@
+import { AlertCircle, Trash2 } from "lucide-react";
+import { useEnvCache } from "../hooks/useEnvCache";
+
+/** Format a byte count as a human-readable size */
+function formatSize(bytes: number): string {
+  const units = ["B", "KiB", "MiB", "GiB"];
+  let size = bytes;
+  let unit = 0;
+  while (size >= 1024 && unit < units.length - 1) {
+    size /= 1024;
+    unit++;
+  }
+  return unit === 0 ? `${size} B` : `${size.toFixed(1)} ${units[unit]}`;
+}
+
+/** Settings section listing cached environments, with buttons to clear them */
+export function EnvCacheSettings() {
+  const { info, loading, error, clear } = useEnvCache();
+
+  const unused = info?.entries.filter((e) => !e.in_use) ?? [];
+  const unusedBytes = unused.reduce((sum, e) => sum + e.size_bytes, 0);
+
+  return (
+    <div className="space-y-2" data-testid="settings-env-cache">
+      <div className="flex items-center justify-between gap-3">
+        <div>
+          <span className="text-xs font-semibold text-muted-foreground uppercase tracking-wider">
+            Environment Cache
+          </span>
+          <p className="text-[11px] text-muted-foreground/70 mt-0.5">
+            {info
+              ? `${formatSize(info.total_bytes)} in ${info.entries.length} environment${info.entries.length === 1 ? "" : "s"}`
+              : "Loading…"}
+          </p>
+        </div>
+        {unused.length > 0 && (
+          <button
+            type="button"
+            disabled={loading}
+            onClick={() => clear(unused.map((e) => e.env_id))}
+            className="flex items-center gap-1.5 rounded-md border px-2.5 py-1 text-xs text-muted-foreground transition-colors hover:text-foreground disabled:opacity-50"
+            data-testid="settings-env-cache-clear-unused"
+          >
+            <Trash2 className="h-3.5 w-3.5" />
+            Clear unused ({formatSize(unusedBytes)})
+          </button>
+        )}
+      </div>
+
+      {error && (
+        <div className="flex items-start gap-2 text-xs text-destructive">
+          <AlertCircle className="h-3.5 w-3.5 mt-0.5 shrink-0" />
+          <span>{error}</span>
+        </div>
+      )}
+
+      {info && info.entries.length > 0 && (
+        <div className="max-h-40 overflow-y-auto rounded-md border">
+          {info.entries.map((entry) => (
+            <div
+              key={entry.path}
+              className="flex items-center gap-3 border-b px-2.5 py-1 text-xs last:border-b-0"
+            >
+              <code
+                className="flex-1 truncate text-muted-foreground"
+                title={entry.path}
+              >
+                {entry.env_id}
+              </code>
+              <span className="text-muted-foreground/70 whitespace-nowrap">
+                {entry.last_used
+                  ? new Date(entry.last_used).toLocaleDateString()
+                  : "never used"}
+              </span>
+              <span className="w-16 text-right tabular-nums whitespace-nowrap">
+                {formatSize(entry.size_bytes)}
+              </span>
+              {entry.in_use ? (
+                <span className="w-12 text-right text-[11px] text-emerald-600 dark:text-emerald-400">
+                  in use
+                </span>
+              ) : (
+                <button
+                  type="button"
+                  disabled={loading}
+                  onClick={() => clear([entry.env_id])}
+                  className="flex w-12 justify-end text-muted-foreground transition-colors hover:text-foreground disabled:opacity-50"
+                  aria-label={`Clear ${entry.env_id}`}
+                >
+                  <Trash2 className="h-3.5 w-3.5" />
+                </button>
+              )}
+            </div>
+          ))}
+        </div>
+      )}
+    </div>
+  );
+}
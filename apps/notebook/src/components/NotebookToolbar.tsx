@@ -26,6 +26,7 @@ import { cn } from "@/lib/utils";
 import type { EnvProgressState } from "../hooks/useEnvProgress";
 import type { UpdateStatus } from "../hooks/useUpdater";
 import type { KernelspecInfo } from "../types";
+import { EnvCacheSettings } from "./EnvCacheSettings";
 
 /** Deno logo icon (from tabler icons) */
 function DenoIcon({ className }: { className?: string }) {
@@ -348,11 +349,13 @@ export function NotebookToolbar({
   const envManager: EnvBadgeVariant | null =
     runtime === "python"
       ? envSource && (kernelStatus === "idle" || kernelStatus === "busy")
-        ? envSource.startsWith("conda:pixi")
-          ? "pixi"
-          : envSource.startsWith("conda")
-            ? "conda"
-            : "uv"
+        ? envSource.startsWith("system:")
+          ? null
+          : envSource.startsWith("conda:pixi")
+            ? "pixi"
+            : envSource.startsWith("conda")
+              ? "conda"
+              : "uv"
         : (envTypeHint ?? null)
       : null;
 
@@ -516,11 +519,18 @@ export function NotebookToolbar({
               "flex items-center gap-1 rounded px-1.5 py-0.5 text-[10px] font-medium transition-colors",
               runtime === "deno"
                 ? "bg-emerald-500/10 text-emerald-600 hover:bg-emerald-500/20 dark:text-emerald-400"
-                : "bg-blue-500/10 text-blue-600 hover:bg-blue-500/20 dark:text-blue-400",
+                : runtime === "python"
+                  ? "bg-blue-500/10 text-blue-600 hover:bg-blue-500/20 dark:text-blue-400"
+                  : "bg-muted text-muted-foreground hover:bg-muted/80",
               isDepsOpen && "ring-1 ring-current/25",
             )}
             title={(() => {
-              const lang = runtime === "deno" ? "Deno/TypeScript" : "Python";
+              const lang =
+                runtime === "deno"
+                  ? "Deno/TypeScript"
+                  : runtime === "python"
+                    ? "Python"
+                    : `${runtime} kernelspec`;
               const mgr = envManager ? ` · ${envManager}` : "";
               const action = isDepsOpen
                 ? "close environment panel"
@@ -533,11 +543,13 @@ export function NotebookToolbar({
                 <DenoIcon className="h-3 w-3" />
                 <span>Deno</span>
               </>
-            ) : (
+            ) : runtime === "python" ? (
               <>
                 <PythonIcon className="h-3 w-3" />
                 <span>Python</span>
               </>
+            ) : (
+              <span>{runtime}</span>
             )}
             {envManager && (
               <>
@@ -852,6 +864,9 @@ export function NotebookToolbar({
                 </div>
               </div>
             )}
+
+            {/* Cached environments */}
+            <EnvCacheSettings />
           </div>
         </CollapsibleContent>
       </header>
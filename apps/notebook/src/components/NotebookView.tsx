@@ -23,9 +23,20 @@ interface NotebookViewProps {
   onExecuteCell: (cellId: string) => void;
   onInterruptKernel: () => void;
   onDeleteCell: (cellId: string) => void;
+  onDuplicateCell?: (cellId: string) => void;
   onAddCell: (type: "code" | "markdown", afterCellId?: string | null) => void;
   onClearPagePayload: (cellId: string) => void;
   onFormatCell?: (cellId: string) => void;
+  sidecarMime?: readonly string[];
+  /** Cells whose latest run happened with the sidecar attached */
+  sidecarCellIds?: ReadonlySet<string>;
+  largeOutputBytes?: number;
+  onOpenSidecar?: () => void;
+  onSetOutputCollapsed?: (
+    cellId: string,
+    outputIndex: number | null,
+    collapsed: boolean,
+  ) => void;
 }
 
 function AddCellButtons({
@@ -130,9 +141,15 @@ function NotebookViewContent({
   onExecuteCell,
   onInterruptKernel,
   onDeleteCell,
+  onDuplicateCell,
   onAddCell,
   onClearPagePayload,
   onFormatCell,
+  sidecarMime,
+  sidecarCellIds,
+  largeOutputBytes,
+  onOpenSidecar,
+  onSetOutputCollapsed,
 }: NotebookViewProps) {
   const containerRef = useRef<HTMLDivElement>(null);
   const { focusCell } = useEditorRegistry();
@@ -179,12 +196,26 @@ function NotebookViewContent({
             onExecute={() => onExecuteCell(cell.id)}
             onInterrupt={onInterruptKernel}
             onDelete={() => onDeleteCell(cell.id)}
+            onDuplicate={
+              onDuplicateCell ? () => onDuplicateCell(cell.id) : undefined
+            }
             onFocusPrevious={onFocusPrevious}
             onFocusNext={onFocusNext}
             onInsertCellAfter={() => onAddCell("code", cell.id)}
             onClearPagePayload={() => onClearPagePayload(cell.id)}
             onFormat={onFormatCell ? () => onFormatCell(cell.id) : undefined}
             isLastCell={index === cells.length - 1}
+            sidecarMime={
+              sidecarCellIds?.has(cell.id) ? sidecarMime : undefined
+            }
+            largeOutputBytes={largeOutputBytes}
+            onOpenSidecar={onOpenSidecar}
+            onSetOutputCollapsed={
+              onSetOutputCollapsed
+                ? (outputIndex, collapsed) =>
+                    onSetOutputCollapsed(cell.id, outputIndex, collapsed)
+                : undefined
+            }
           />
         );
       }
@@ -227,9 +258,15 @@ function NotebookViewContent({
       onExecuteCell,
       onInterruptKernel,
       onDeleteCell,
+      onDuplicateCell,
       onAddCell,
       onClearPagePayload,
       onFormatCell,
+      sidecarMime,
+      sidecarCellIds,
+      largeOutputBytes,
+      onOpenSidecar,
+      onSetOutputCollapsed,
       focusCell,
     ],
   );
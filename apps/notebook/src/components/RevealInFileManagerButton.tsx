@@ -0,0 +1,24 @@
+import { invoke } from "@tauri-apps/api/core";
+import { FolderOpen } from "lucide-react";
+import { errorMessage } from "../lib/command-error";
+
+/** Icon button that shows a file in the OS file manager. */
+export function RevealInFileManagerButton({ path }: { path: string }) {
+  const reveal = () => {
+    invoke("reveal_in_file_manager", { path }).catch((e) =>
+      console.error("[reveal] Failed to show file:", errorMessage(e)),
+    );
+  };
+
+  return (
+    <button
+      type="button"
+      onClick={reveal}
+      className="inline-flex items-center text-muted-foreground/70 hover:text-foreground transition-colors"
+      title="Show in file manager"
+      aria-label="Show in file manager"
+    >
+      <FolderOpen className="h-3 w-3" />
+    </button>
+  );
+}
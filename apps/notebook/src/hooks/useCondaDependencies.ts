@@ -3,10 +3,21 @@ import { getCurrentWebview } from "@tauri-apps/api/webview";
 import { useCallback, useEffect, useState } from "react";
 import type { PixiInfo } from "../types";
 
+/**
+ * How strictly channel order is enforced. "strict" takes each package only
+ * from the highest-priority channel that has it.
+ */
+export type CondaChannelPriority = "strict" | "disabled";
+
 export interface CondaDependencies {
   dependencies: string[];
+  /** Highest priority first */
   channels: string[];
+  /** null defers to settings (strict unless turned off) */
+  channel_priority: CondaChannelPriority | null;
   python: string | null;
+  /** PyPI-only packages, pip-installed after the conda solve */
+  pip_dependencies: string[];
 }
 
 /** Info about a detected environment.yml */
@@ -166,6 +177,8 @@ export function useCondaDependencies() {
         await checkSyncState();
       } catch (e) {
         console.error("Failed to add conda dependency:", e);
+        // Invalid specifiers are reported back to the input
+        throw e;
       } finally {
         setLoading(false);
       }
@@ -192,6 +205,43 @@ export function useCondaDependencies() {
     [loadDependencies, resignTrust, checkSyncState],
   );
 
+  // PyPI-only packages, pip-installed into the conda env after the solve
+  const addPipDependency = useCallback(
+    async (pkg: string) => {
+      if (!pkg.trim()) return;
+      setLoading(true);
+      try {
+        await invoke("add_conda_pip_dependency", { package: pkg.trim() });
+        await loadDependencies();
+        await resignTrust();
+        await checkSyncState();
+      } catch (e) {
+        console.error("Failed to add pip dependency:", e);
+        throw e;
+      } finally {
+        setLoading(false);
+      }
+    },
+    [loadDependencies, resignTrust, checkSyncState],
+  );
+
+  const removePipDependency = useCallback(
+    async (pkg: string) => {
+      setLoading(true);
+      try {
+        await invoke("remove_conda_pip_dependency", { package: pkg });
+        await loadDependencies();
+        await resignTrust();
+        await checkSyncState();
+      } catch (e) {
+        console.error("Failed to remove pip dependency:", e);
+      } finally {
+        setLoading(false);
+      }
+    },
+    [loadDependencies, resignTrust, checkSyncState],
+  );
+
   // Remove the entire conda dependency section from notebook metadata
   const clearAllDependencies = useCallback(async () => {
     setLoading(true);
@@ -220,6 +270,7 @@ export function useCondaDependencies() {
         await invoke("set_conda_dependencies", {
           dependencies: dependencies?.dependencies ?? [],
           channels,
+          channelPriority: dependencies?.channel_priority ?? null,
           python: dependencies?.python ?? null,
         });
         await loadDependencies();
@@ -234,6 +285,28 @@ export function useCondaDependencies() {
     [dependencies, loadDependencies, resignTrust],
   );
 
+  const setChannelPriority = useCallback(
+    async (priority: CondaChannelPriority) => {
+      setLoading(true);
+      try {
+        await invoke("set_conda_dependencies", {
+          dependencies: dependencies?.dependencies ?? [],
+          channels: dependencies?.channels ?? [],
+          channelPriority: priority,
+          python: dependencies?.python ?? null,
+        });
+        await loadDependencies();
+        // Re-sign to keep notebook trusted after user modification
+        await resignTrust();
+      } catch (e) {
+        console.error("Failed to set channel priority:", e);
+      } finally {
+        setLoading(false);
+      }
+    },
+    [dependencies, loadDependencies, resignTrust],
+  );
+
   const setPython = useCallback(
     async (version: string | null) => {
       setLoading(true);
@@ -241,6 +314,7 @@ export function useCondaDependencies() {
         await invoke("set_conda_dependencies", {
           dependencies: dependencies?.dependencies ?? [],
           channels: dependencies?.channels ?? [],
+          channelPriority: dependencies?.channel_priority ?? null,
           python: version,
         });
         await loadDependencies();
@@ -288,8 +362,11 @@ export function useCondaDependencies() {
     loadDependencies,
     addDependency,
     removeDependency,
+    addPipDependency,
+    removePipDependency,
     clearAllDependencies,
     setChannels,
+    setChannelPriority,
     setPython,
     syncNow,
     importFromPixi,
@@ -9,6 +9,7 @@
 import { invoke } from "@tauri-apps/api/core";
 import { getCurrentWebview } from "@tauri-apps/api/webview";
 import { useCallback, useEffect, useRef, useState } from "react";
+import { isCommandError } from "../lib/command-error";
 import type {
   DaemonBroadcast,
   DaemonNotebookResponse,
@@ -27,7 +28,8 @@ export type DaemonKernelStatus =
   | "idle"
   | "busy"
   | "error"
-  | "shutdown";
+  | "shutdown"
+  | "dead";
 
 /** Queue state from daemon */
 export interface DaemonQueueState {
@@ -58,6 +60,25 @@ interface UseDaemonKernelOptions {
   onClearOutputs?: (cellId: string) => void;
   /** Called when a comm message is received (for widgets) */
   onCommMessage?: (msg: JupyterMessage) => void;
+  /** Called when a cell failed to import a module that isn't installed */
+  onSuggestInstall?: (suggestion: InstallSuggestion) => void;
+  /** Called when other open notebooks' kernels share this notebook's env_id */
+  onEnvIdCollision?: (otherNotebooks: string[]) => void;
+}
+
+/** A missing module and the package that likely provides it */
+export interface InstallSuggestion {
+  cellId: string;
+  module: string;
+  /** PyPI name, for uv environments */
+  package: string;
+  /** conda-forge name, for conda environments */
+  condaPackage: string;
+}
+
+/** Launch errors the daemon returns when preparing the environment fails */
+function isEnvironmentError(error: string): boolean {
+  return /failed to prepare .*environment/i.test(error);
 }
 
 export function useDaemonKernel({
@@ -70,6 +91,8 @@ export function useDaemonKernel({
   onUpdateDisplayData,
   onClearOutputs,
   onCommMessage,
+  onSuggestInstall,
+  onEnvIdCollision,
 }: UseDaemonKernelOptions) {
   const [kernelStatus, setKernelStatus] =
     useState<DaemonKernelStatus>("not_started");
@@ -93,6 +116,10 @@ export function useDaemonKernel({
     };
   } | null>(null);
 
+  // Set when the last launch failed while preparing the environment, so the
+  // UI can offer a repair instead of just the error
+  const [envRepairAvailable, setEnvRepairAvailable] = useState(false);
+
   // Store blob port in ref for use in event handlers
   const blobPortRef = useRef<number>(0);
 
@@ -111,6 +138,8 @@ export function useDaemonKernel({
     onUpdateDisplayData,
     onClearOutputs,
     onCommMessage,
+    onSuggestInstall,
+    onEnvIdCollision,
   });
   callbacksRef.current = {
     onOutput,
@@ -122,6 +151,8 @@ export function useDaemonKernel({
     onUpdateDisplayData,
     onClearOutputs,
     onCommMessage,
+    onSuggestInstall,
+    onEnvIdCollision,
   };
 
   // Listen for daemon broadcasts
@@ -286,11 +317,83 @@ export function useDaemonKernel({
             break;
           }
 
+          case "kernel_lifecycle": {
+            if (
+              broadcast.state === "launched" ||
+              broadcast.state === "reattached"
+            ) {
+              // A kernel started, or outlived the previous window; pick up
+              // its type and environment
+              setKernelInfo({
+                kernelType: broadcast.kernel_type,
+                envSource: broadcast.env_source,
+              });
+            } else if (broadcast.state === "crashed") {
+              setKernelStatus("dead");
+              const code =
+                broadcast.exit_code != null
+                  ? ` (exit code ${broadcast.exit_code})`
+                  : "";
+              callbacksRef.current.onKernelError?.(
+                `Kernel died unexpectedly${code}. Restart the kernel to continue.`,
+              );
+            } else if (broadcast.state === "unresponsive") {
+              // The process is up but ipykernel never answered
+              setKernelStatus("error");
+              callbacksRef.current.onKernelError?.(
+                `${broadcast.reason ?? "Kernel is not responding"}. Restart the kernel to continue.`,
+              );
+            } else if (broadcast.state === "error") {
+              // Launch failed; the last stderr line usually names the cause
+              // (e.g. a missing ipykernel)
+              setKernelStatus("error");
+              const reason = broadcast.reason ?? "Kernel failed to start";
+              const lastLine = broadcast.stderr_tail?.trim().split("\n").pop();
+              callbacksRef.current.onKernelError?.(
+                lastLine ? `${reason}: ${lastLine}` : reason,
+              );
+            } else if (broadcast.state === "auto_launch_disabled") {
+              // The notebook opted out of auto-launch; wait for the user
+              console.log("[daemon-kernel] Auto-launch disabled for notebook");
+              setKernelStatus("not_started");
+            }
+            break;
+          }
+
           case "outputs_cleared": {
             callbacksRef.current.onClearOutputs?.(broadcast.cell_id);
             break;
           }
 
+          case "suggest_install": {
+            callbacksRef.current.onSuggestInstall?.({
+              cellId: broadcast.cell_id,
+              module: broadcast.module,
+              package: broadcast.package,
+              condaPackage: broadcast.conda_package,
+            });
+            break;
+          }
+
+          case "kernel_payload": {
+            // `exit()` in a cell; page and set_next_input payloads reach the
+            // notebook through the doc, which the daemon already updated
+            if (
+              broadcast.payload.source === "ask_exit" &&
+              !broadcast.payload.keepkernel
+            ) {
+              invoke("shutdown_kernel_via_daemon").catch((e) =>
+                console.error("[daemon-kernel] shutdown on exit failed:", e),
+              );
+            }
+            break;
+          }
+
+          case "env_id_collision": {
+            callbacksRef.current.onEnvIdCollision?.(broadcast.other_notebooks);
+            break;
+          }
+
           case "comm": {
             // Comm message from kernel (for widgets)
             const { onCommMessage } = callbacksRef.current;
@@ -525,14 +628,21 @@ export function useDaemonKernel({
             envSource: response.env_source,
           });
           setKernelStatus("idle");
+          setEnvRepairAvailable(false);
         } else if (response.result === "error") {
           setKernelStatus("error");
+          setEnvRepairAvailable(isEnvironmentError(response.error));
         }
 
         return response;
       } catch (e) {
         console.error("[daemon-kernel] launch failed:", e);
-        setKernelStatus("error");
+        // Nothing was launched without a daemon connection; stay retryable
+        setKernelStatus(
+          isCommandError(e) && e.code === "not_connected"
+            ? "not_started"
+            : "error",
+        );
         throw e;
       }
     },
@@ -574,6 +684,18 @@ export function useDaemonKernel({
     [],
   );
 
+  /** Clear every code cell's outputs via the daemon (also reclaims blobs) */
+  const clearAllOutputs =
+    useCallback(async (): Promise<DaemonNotebookResponse> => {
+      console.log("[daemon-kernel] clearing all outputs");
+      try {
+        return await invoke<DaemonNotebookResponse>("clear_all_outputs");
+      } catch (e) {
+        console.error("[daemon-kernel] clear all outputs failed:", e);
+        throw e;
+      }
+    }, []);
+
   /** Interrupt kernel execution via the daemon */
   const interruptKernel =
     useCallback(async (): Promise<DaemonNotebookResponse> => {
@@ -603,7 +725,31 @@ export function useDaemonKernel({
       }
     }, []);
 
-  /** Hot-sync environment - install new packages without restart (UV only) */
+  /** Rebuild a broken cached environment (after a failed launch) */
+  const repairEnvironment =
+    useCallback(async (): Promise<DaemonNotebookResponse> => {
+      console.log("[daemon-kernel] repairing environment");
+      try {
+        const response = await invoke<DaemonNotebookResponse>(
+          "repair_environment",
+        );
+        if (response.result === "environment_repaired") {
+          console.log(
+            "[daemon-kernel] repair complete:",
+            response.env_source,
+            "rebuilt:",
+            response.rebuilt,
+          );
+          setEnvRepairAvailable(false);
+        }
+        return response;
+      } catch (e) {
+        console.error("[daemon-kernel] repair environment failed:", e);
+        throw e;
+      }
+    }, []);
+
+  /** Hot-sync environment - install or remove packages without restart (UV only) */
   const syncEnvironment =
     useCallback(async (): Promise<DaemonNotebookResponse> => {
       console.log("[daemon-kernel] syncing environment");
@@ -614,8 +760,13 @@ export function useDaemonKernel({
         if (response.result === "sync_environment_complete") {
           console.log(
             "[daemon-kernel] sync complete:",
-            response.synced_packages,
+            response.installed,
+            "removed:",
+            response.removed,
           );
+          for (const [pkg, reason] of response.failed) {
+            console.warn(`[daemon-kernel] failed to sync ${pkg}:`, reason);
+          }
         } else if (response.result === "sync_environment_failed") {
           console.warn(
             "[daemon-kernel] sync failed:",
@@ -631,6 +782,20 @@ export function useDaemonKernel({
       }
     }, []);
 
+  /** Save the kernel's variables for restoring after a restart (Python) */
+  const snapshotKernelState =
+    useCallback(async (): Promise<DaemonNotebookResponse> => {
+      console.log("[daemon-kernel] snapshotting kernel state");
+      return await invoke<DaemonNotebookResponse>("snapshot_kernel_state");
+    }, []);
+
+  /** Load the last kernel state snapshot into the running kernel */
+  const restoreKernelState =
+    useCallback(async (): Promise<DaemonNotebookResponse> => {
+      console.log("[daemon-kernel] restoring kernel state");
+      return await invoke<DaemonNotebookResponse>("restore_kernel_state");
+    }, []);
+
   /** Get current queue state from daemon */
   const refreshQueueState = useCallback(async () => {
     try {
@@ -659,6 +824,26 @@ export function useDaemonKernel({
     }
   }, []);
 
+  /**
+   * Run exactly these cells, in this order (daemon reads from synced doc).
+   * Unknown ids and non-code cells are skipped and reported back.
+   */
+  const runCells = useCallback(
+    async (cellIds: string[]): Promise<DaemonNotebookResponse> => {
+      console.log("[daemon-kernel] running cells:", cellIds);
+      try {
+        return await invoke<DaemonNotebookResponse>(
+          "execute_cells_via_daemon",
+          { cellIds },
+        );
+      } catch (e) {
+        console.error("[daemon-kernel] run cells failed:", e);
+        throw e;
+      }
+    },
+    [],
+  );
+
   /** Send a comm message to the kernel via the daemon (for widget interactions) */
   const sendCommMessage = useCallback(
     async (message: {
@@ -720,16 +905,28 @@ export function useDaemonKernel({
     executeCell,
     /** Clear outputs for a cell */
     clearOutputs,
+    /** Clear outputs of every code cell */
+    clearAllOutputs,
     /** Interrupt kernel execution */
     interruptKernel,
     /** Shutdown the kernel */
     shutdownKernel,
-    /** Hot-sync environment - install new packages without restart (UV only) */
+    /** Hot-sync environment - install or remove packages without restart (UV only) */
     syncEnvironment,
+    /** Whether the last launch failed with an environment error */
+    envRepairAvailable,
+    /** Rebuild the notebook's cached environment if it is broken */
+    repairEnvironment,
+    /** Save the kernel's variables to restore after a restart (Python) */
+    snapshotKernelState,
+    /** Load the last kernel state snapshot into the running kernel */
+    restoreKernelState,
     /** Refresh queue state from daemon */
     refreshQueueState,
     /** Run all code cells (daemon reads from synced doc) */
     runAllCells,
+    /** Run the given cells in order (e.g. a non-contiguous selection) */
+    runCells,
     /** Send a comm message to the kernel (for widget interactions) */
     sendCommMessage,
     /** Check if a cell is currently executing */
@@ -14,6 +14,16 @@ export type EnvSyncState =
   | { status: "synced" }
   | { status: "dirty"; added: string[]; removed: string[] };
 
+/** Outcome of importing a pasted requirements.txt block */
+export interface RequirementsImport {
+  /** Dependencies added or whose constraint changed */
+  added: number;
+  /** Invalid lines, with the reason */
+  rejected: string[];
+  /** pip directives (-r, -e, ...) that were ignored */
+  skipped: string[];
+}
+
 /** Full pyproject.toml dependencies for display */
 export interface PyProjectDeps {
   path: string;
@@ -149,6 +159,29 @@ export function useDependencies() {
         await checkSyncState();
       } catch (e) {
         console.error("Failed to add dependency:", e);
+        // Invalid specifiers are reported back to the input
+        throw e;
+      } finally {
+        setLoading(false);
+      }
+    },
+    [loadDependencies, resignTrust, checkSyncState],
+  );
+
+  const importRequirements = useCallback(
+    async (text: string) => {
+      setLoading(true);
+      try {
+        const result = await invoke<RequirementsImport>(
+          "import_requirements_text",
+          { text },
+        );
+        if (result.added > 0) {
+          await loadDependencies();
+          await resignTrust();
+          await checkSyncState();
+        }
+        return result;
       } finally {
         setLoading(false);
       }
@@ -278,6 +311,7 @@ export function useDependencies() {
     needsKernelRestart,
     loadDependencies,
     addDependency,
+    importRequirements,
     removeDependency,
     clearAllDependencies,
     setRequiresPython,
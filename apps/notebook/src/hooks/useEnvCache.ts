@@ -0,0 +1,75 @@
+import { invoke } from "@tauri-apps/api/core";
+import { useCallback, useEffect, useState } from "react";
+import { errorMessage } from "../lib/command-error";
+
+/** A cached environment on disk, as reported by the daemon */
+export interface EnvCacheEntry {
+  env_id: string;
+  path: string;
+  size_bytes: number;
+  /** ISO timestamp of the last kernel launch from this env */
+  last_used?: string;
+  /** Hash of the dependencies the env was built for; absent for pool envs */
+  deps_hash?: string;
+  /** A running kernel uses this env, so it can't be cleared */
+  in_use: boolean;
+}
+
+export interface EnvCacheInfo {
+  entries: EnvCacheEntry[];
+  total_bytes: number;
+}
+
+export interface EnvCacheCleared {
+  removed: string[];
+  in_use: string[];
+  freed_bytes: number;
+}
+
+/** Cached environments and their disk usage, loaded on mount */
+export function useEnvCache() {
+  const [info, setInfo] = useState<EnvCacheInfo | null>(null);
+  const [loading, setLoading] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  const refresh = useCallback(async () => {
+    setLoading(true);
+    try {
+      setInfo(await invoke<EnvCacheInfo>("get_env_cache_info"));
+      setError(null);
+    } catch (e) {
+      setError(errorMessage(e));
+      console.error("Failed to get environment cache info:", e);
+    } finally {
+      setLoading(false);
+    }
+  }, []);
+
+  // Delete envs by id; envs in use by a kernel are skipped by the daemon
+  const clear = useCallback(
+    async (envIds: string[]) => {
+      setLoading(true);
+      try {
+        const cleared = await invoke<EnvCacheCleared>("clear_env_cache", {
+          envIds,
+        });
+        setError(null);
+        return cleared;
+      } catch (e) {
+        setError(errorMessage(e));
+        console.error("Failed to clear environment cache:", e);
+        return null;
+      } finally {
+        setLoading(false);
+        await refresh();
+      }
+    },
+    [refresh],
+  );
+
+  useEffect(() => {
+    refresh();
+  }, [refresh]);
+
+  return { info, loading, error, refresh, clear };
+}
@@ -100,6 +100,27 @@ function getStatusText(event: EnvProgressEvent): string {
       >;
       return `Installing ${e.packages.length} packages...`;
     }
+    case "package_installed": {
+      const e = event as Extract<
+        EnvProgressPhase,
+        { phase: "package_installed" }
+      >;
+      return `Installed ${e.package}`;
+    }
+    case "package_removed": {
+      const e = event as Extract<EnvProgressPhase, { phase: "package_removed" }>;
+      return `Removed ${e.package}`;
+    }
+    case "package_failed": {
+      const e = event as Extract<EnvProgressPhase, { phase: "package_failed" }>;
+      return `Failed to sync ${e.package}: ${e.message}`;
+    }
+    case "copy_progress": {
+      const e = event as Extract<EnvProgressPhase, { phase: "copy_progress" }>;
+      return `Copying environment ${formatBytes(e.bytes_copied)} / ${formatBytes(e.bytes_total)}`;
+    }
+    case "copy_cancelled":
+      return "Environment copy cancelled";
     case "ready":
       return "Environment ready";
     case "error": {
@@ -126,6 +147,10 @@ function extractProgress(
     const e = event as Extract<EnvProgressPhase, { phase: "link_progress" }>;
     return { completed: e.completed, total: e.total };
   }
+  if (phase === "copy_progress") {
+    const e = event as Extract<EnvProgressPhase, { phase: "copy_progress" }>;
+    return { completed: e.bytes_copied, total: e.bytes_total };
+  }
   return null;
 }
 
@@ -149,9 +174,10 @@ export function useEnvProgress() {
       if (cancelled) return;
 
       const phase = payload.phase;
-      // Only "ready" and "cache_hit" are terminal success states
+      // "ready", "cache_hit" and "copy_cancelled" end the operation
       // "error" is terminal but we keep error visible
-      const isTerminalSuccess = phase === "ready" || phase === "cache_hit";
+      const isTerminalSuccess =
+        phase === "ready" || phase === "cache_hit" || phase === "copy_cancelled";
       const isError = phase === "error";
       const error = isError
         ? (payload as Extract<EnvProgressPhase, { phase: "error" }>).message
@@ -1,5 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
 import { useCallback, useRef, useState } from "react";
+import { errorMessage } from "../lib/command-error";
 
 export interface HistoryEntry {
   session: number;
@@ -82,7 +83,7 @@ export function useHistorySearch() {
         setCacheResult(pattern, entries);
       }
     } catch (e) {
-      const errorMsg = e instanceof Error ? e.message : String(e);
+      const errorMsg = errorMessage(e);
       // Only update error if this is still the current search
       if (currentSearchRef.current === pattern) {
         setError(errorMsg);
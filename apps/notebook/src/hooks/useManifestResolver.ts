@@ -16,6 +16,8 @@ interface DisplayDataManifest {
   data: Record<string, ContentRef>;
   metadata?: Record<string, unknown>;
   transient?: { display_id?: string };
+  /** Preferred MIME type in `data`; every type is still present. */
+  primary?: string;
 }
 
 interface ExecuteResultManifest {
@@ -24,6 +26,8 @@ interface ExecuteResultManifest {
   metadata?: Record<string, unknown>;
   execution_count?: number | null;
   transient?: { display_id?: string };
+  /** Preferred MIME type in `data`; every type is still present. */
+  primary?: string;
 }
 
 interface StreamManifest {
@@ -53,6 +57,29 @@ export function looksLikeBlobHash(s: string): boolean {
   return /^[a-f0-9]{64}$/.test(s);
 }
 
+/**
+ * Bearer token for the blob server, learned alongside the port.
+ * Null when the daemon's blob server doesn't require one.
+ */
+let blobToken: string | null = null;
+
+/**
+ * Refresh the blob server token from the daemon info.
+ */
+export async function loadBlobToken(): Promise<void> {
+  blobToken = await invoke<string | null>("get_blob_token");
+}
+
+/**
+ * Fetch a blob by hash, sending the auth token when one is configured.
+ */
+export function fetchBlob(blobPort: number, hash: string): Promise<Response> {
+  const headers: HeadersInit = blobToken
+    ? { Authorization: `Bearer ${blobToken}` }
+    : {};
+  return fetch(`http://127.0.0.1:${blobPort}/blob/${hash}`, { headers });
+}
+
 /**
  * Resolve a ContentRef to its string value.
  */
@@ -64,7 +91,7 @@ async function resolveContentRef(
     return ref.inline;
   }
   // Fetch from blob store
-  const response = await fetch(`http://127.0.0.1:${blobPort}/blob/${ref.blob}`);
+  const response = await fetchBlob(blobPort, ref.blob);
   if (!response.ok) {
     throw new Error(`Failed to fetch blob ${ref.blob}: ${response.status}`);
   }
@@ -157,7 +184,7 @@ export async function resolveManifest(
 }
 
 /**
- * Fetch blob port with retry logic.
+ * Fetch blob port (and auth token, if any) with retry logic.
  */
 export async function fetchBlobPortWithRetry(
   maxAttempts = 5,
@@ -166,6 +193,7 @@ export async function fetchBlobPortWithRetry(
   for (let attempt = 1; attempt <= maxAttempts; attempt++) {
     try {
       const port = await invoke<number>("get_blob_port");
+      await loadBlobToken();
       return port;
     } catch (e) {
       if (attempt < maxAttempts) {
@@ -209,9 +237,7 @@ export async function resolveOutputString(
 
   // It's a blob hash - fetch manifest and resolve
   try {
-    const response = await fetch(
-      `http://127.0.0.1:${blobPort}/blob/${outputStr}`,
-    );
+    const response = await fetchBlob(blobPort, outputStr);
     if (!response.ok) {
       console.warn(
         `[manifest-resolver] Failed to fetch manifest ${outputStr}: ${response.status}`,
@@ -297,9 +323,7 @@ export function useManifestResolver() {
       const promise = (async () => {
         try {
           // Fetch manifest from blob store
-          const response = await fetch(
-            `http://127.0.0.1:${blobPort}/blob/${outputStr}`,
-          );
+          const response = await fetchBlob(blobPort, outputStr);
           if (!response.ok) {
             console.warn(
               `[manifest-resolver] Failed to fetch manifest ${outputStr}: ${response.status}`,
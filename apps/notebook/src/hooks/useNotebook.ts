@@ -5,7 +5,12 @@ import {
   save as saveDialog,
 } from "@tauri-apps/plugin-dialog";
 import { useCallback, useEffect, useRef, useState } from "react";
-import type { JupyterOutput, NotebookCell } from "../types";
+import type {
+  CellExecutionTiming,
+  JupyterOutput,
+  NotebookCell,
+} from "../types";
+import { fetchBlob, loadBlobToken } from "./useManifestResolver";
 
 /**
  * Snapshot of a cell from the Automerge sync client.
@@ -17,6 +22,10 @@ interface CellSnapshot {
   source: string;
   execution_count: string; // "5" or "null"
   outputs: string[]; // JSON-encoded Jupyter outputs or manifest hashes
+  execution?: CellExecutionTiming;
+  collapsed?: boolean;
+  scrolled?: boolean;
+  collapsed_outputs?: number[];
 }
 
 /**
@@ -70,7 +79,7 @@ async function resolveContentRef(
   if ("inline" in ref) {
     return ref.inline;
   }
-  const response = await fetch(`http://127.0.0.1:${blobPort}/blob/${ref.blob}`);
+  const response = await fetchBlob(blobPort, ref.blob);
   if (!response.ok) {
     throw new Error(`Failed to fetch blob ${ref.blob}: ${response.status}`);
   }
@@ -187,9 +196,7 @@ async function resolveOutput(
 
   try {
     // Fetch manifest from blob store
-    const response = await fetch(
-      `http://127.0.0.1:${blobPort}/blob/${outputStr}`,
-    );
+    const response = await fetchBlob(blobPort, outputStr);
     if (!response.ok) {
       console.warn(
         `[notebook-sync] Failed to fetch manifest ${outputStr}: ${response.status}`,
@@ -271,6 +278,10 @@ async function cellSnapshotsToNotebookCells(
           source: snap.source,
           execution_count: Number.isNaN(executionCount) ? null : executionCount,
           outputs,
+          execution: snap.execution,
+          collapsed: snap.collapsed,
+          scrolled: snap.scrolled,
+          collapsed_outputs: snap.collapsed_outputs,
         };
       }
 
@@ -294,10 +305,15 @@ export function useNotebook() {
 
   // Helper to refresh blob port (called on mount and daemon:ready)
   const refreshBlobPort = useCallback(() => {
-    blobPortPromiseRef.current = invoke<number>("get_blob_port").catch((e) => {
-      console.warn("[notebook] Failed to get blob port:", e);
-      return null;
-    });
+    blobPortPromiseRef.current = invoke<number>("get_blob_port")
+      .then(async (port) => {
+        await loadBlobToken();
+        return port;
+      })
+      .catch((e) => {
+        console.warn("[notebook] Failed to get blob port:", e);
+        return null;
+      });
   }, []);
 
   // Fetch blob port on mount for manifest resolution
@@ -404,12 +420,14 @@ export function useNotebook() {
 
     // Listen for daemon ready signal before requesting Automerge state.
     // The backend emits daemon:ready after notebook sync is initialized.
+    // Reconnects can leave local state out of step with the daemon, so this
+    // reconciles it (and emits notebook:updated) rather than only refreshing.
     const unlistenReady = webview.listen("daemon:ready", () => {
       if (!isMounted) return;
       // Refresh blob port (daemon may have restarted with new port)
       refreshBlobPort();
-      invoke("refresh_from_automerge").catch((e) =>
-        console.warn("[notebook-sync] refresh_from_automerge failed:", e),
+      invoke("reconcile_with_daemon").catch((e) =>
+        console.warn("[notebook-sync] reconcile_with_daemon failed:", e),
       );
     });
 
@@ -474,6 +492,43 @@ export function useNotebook() {
     [],
   );
 
+  const duplicateCell = useCallback(
+    async (cellId: string, includeOutputs = true) => {
+      try {
+        const newCell = await invoke<NotebookCell>("duplicate_cell", {
+          cellId,
+          includeOutputs,
+        });
+        setCells((prev) => {
+          const idx = prev.findIndex((c) => c.id === cellId);
+          if (idx === -1) return [...prev, newCell];
+          // Copy the outputs on screen; the backend's local copy may lag
+          const original = prev[idx];
+          const copy =
+            includeOutputs &&
+            original.cell_type === "code" &&
+            newCell.cell_type === "code"
+              ? {
+                  ...newCell,
+                  outputs: original.outputs,
+                  execution_count: original.execution_count,
+                }
+              : newCell;
+          const next = [...prev];
+          next.splice(idx + 1, 0, copy);
+          return next;
+        });
+        setFocusedCellId(newCell.id);
+        setDirty(true);
+        return newCell;
+      } catch (e) {
+        console.error("duplicate_cell failed:", e);
+        return null;
+      }
+    },
+    [],
+  );
+
   const deleteCell = useCallback(async (cellId: string) => {
     try {
       await invoke("delete_cell", { cellId });
@@ -561,6 +616,34 @@ export function useNotebook() {
     }
   }, []);
 
+  const saveCopyWithoutOutputs = useCallback(async () => {
+    try {
+      const defaultDir = await invoke<string>("get_default_save_directory");
+
+      const filePath = await saveDialog({
+        filters: [{ name: "Jupyter Notebook", extensions: ["ipynb"] }],
+        defaultPath: `${defaultDir}/Untitled-Clean.ipynb`,
+      });
+
+      if (!filePath) {
+        return; // User cancelled
+      }
+
+      // Write the stripped copy; the open notebook keeps its path and outputs
+      await invoke("save_notebook_copy_clean", { path: filePath });
+    } catch (e) {
+      console.error("save_notebook_copy_clean failed:", e);
+    }
+  }, []);
+
+  const cancelCloneEnvironmentCopy = useCallback(async () => {
+    try {
+      await invoke("cancel_clone_environment_copy");
+    } catch (e) {
+      console.error("cancel_clone_environment_copy failed:", e);
+    }
+  }, []);
+
   const appendOutput = useCallback((cellId: string, output: JupyterOutput) => {
     setCells((prev) =>
       prev.map((c) => {
@@ -620,6 +703,36 @@ export function useNotebook() {
     );
   }, []);
 
+  /**
+   * Collapse or expand a cell's outputs, or one output when `outputIndex`
+   * is given. Synced to other windows through the Automerge doc.
+   */
+  const setOutputCollapsed = useCallback(
+    (cellId: string, outputIndex: number | null, collapsed: boolean) => {
+      setCells((prev) =>
+        prev.map((c) => {
+          if (c.id !== cellId || c.cell_type !== "code") return c;
+          if (outputIndex === null) {
+            return { ...c, collapsed: collapsed || undefined };
+          }
+          const others = (c.collapsed_outputs ?? []).filter(
+            (i) => i !== outputIndex,
+          );
+          return {
+            ...c,
+            collapsed_outputs: collapsed
+              ? [...others, outputIndex].sort((a, b) => a - b)
+              : others,
+          };
+        }),
+      );
+      invoke("set_output_collapsed", { cellId, outputIndex, collapsed }).catch(
+        console.error,
+      );
+    },
+    [],
+  );
+
   /**
    * Format a cell's source code using the appropriate formatter.
    * The backend handles the formatting and emits a cell:source_updated event
@@ -652,14 +765,18 @@ export function useNotebook() {
     updateCellSource,
     clearCellOutputs,
     addCell,
+    duplicateCell,
     deleteCell,
     save,
     openNotebook,
     cloneNotebook,
+    saveCopyWithoutOutputs,
+    cancelCloneEnvironmentCopy,
     dirty,
     appendOutput,
     updateOutputByDisplayId,
     setExecutionCount,
+    setOutputCollapsed,
     formatCell,
   };
 }
@@ -1,5 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
 import { useCallback, useEffect, useState } from "react";
+import { errorMessage } from "../lib/command-error";
 
 /** Trust status from the backend */
 export type TrustStatusType =
@@ -13,6 +14,8 @@ export interface TrustInfo {
   uv_dependencies: string[];
   conda_dependencies: string[];
   conda_channels: string[];
+  /** Trusted directory that made this notebook trusted, if any */
+  trusted_scope?: string;
 }
 
 export interface TyposquatWarning {
@@ -50,8 +53,7 @@ export function useTrust() {
 
       return info;
     } catch (e) {
-      const message = e instanceof Error ? e.message : String(e);
-      setError(message);
+      setError(errorMessage(e));
       console.error("Failed to check trust:", e);
       return null;
     } finally {
@@ -69,8 +71,7 @@ export function useTrust() {
       await checkTrust();
       return true;
     } catch (e) {
-      const message = e instanceof Error ? e.message : String(e);
-      setError(message);
+      setError(errorMessage(e));
       console.error("Failed to approve trust:", e);
       return false;
     } finally {
@@ -0,0 +1,34 @@
+/**
+ * Errors thrown by `invoke()` when a Tauri command fails.
+ *
+ * Mirrors `CommandError` in crates/notebook/src/command_error.rs. Branch on
+ * `code` rather than matching on `message`.
+ */
+export type CommandErrorCode =
+  | "not_connected"
+  | "kernel_error"
+  | "validation"
+  | "io"
+  | "timeout"
+  | "not_found";
+
+export interface CommandError {
+  code: CommandErrorCode;
+  message: string;
+}
+
+export function isCommandError(e: unknown): e is CommandError {
+  return (
+    typeof e === "object" &&
+    e !== null &&
+    typeof (e as CommandError).code === "string" &&
+    typeof (e as CommandError).message === "string"
+  );
+}
+
+/** Human-readable message for anything caught from `invoke()`. */
+export function errorMessage(e: unknown): string {
+  if (isCommandError(e)) return e.message;
+  if (e instanceof Error) return e.message;
+  return String(e);
+}
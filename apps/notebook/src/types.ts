@@ -1,9 +1,23 @@
+/** Timing of a cell's most recent execution, recorded by the daemon */
+export interface CellExecutionTiming {
+  started_at: string;
+  completed_at: string;
+  duration_ms: number;
+}
+
 export interface CodeCell {
   cell_type: "code";
   id: string;
   source: string;
   execution_count: number | null;
   outputs: JupyterOutput[];
+  execution?: CellExecutionTiming;
+  /** nbformat `collapsed`: outputs hidden */
+  collapsed?: boolean;
+  /** nbformat `scrolled`: outputs scroll in a fixed-height area */
+  scrolled?: boolean;
+  /** Indices of individually collapsed outputs */
+  collapsed_outputs?: number[];
 }
 
 export interface MarkdownCell {
@@ -97,6 +111,11 @@ export type EnvProgressPhase =
   | { phase: "install_complete"; elapsed_ms: number }
   | { phase: "creating_venv" }
   | { phase: "installing_packages"; packages: string[] }
+  | { phase: "package_installed"; package: string }
+  | { phase: "package_removed"; package: string }
+  | { phase: "package_failed"; package: string; message: string }
+  | { phase: "copy_progress"; bytes_copied: number; bytes_total: number }
+  | { phase: "copy_cancelled" }
   | { phase: "ready"; env_path: string; python_path: string }
   | { phase: "error"; message: string };
 
@@ -128,12 +147,22 @@ export interface EnvironmentYmlInfo {
   pip_dependency_count: number;
   python: string | null;
   channels: string[];
+  variable_names: string[];
 }
 
 // =============================================================================
 // Daemon Broadcast Types (Phase 8: Daemon-owned kernel execution)
 // =============================================================================
 
+/** A line of environment build output (see stream_env_build_log) */
+export interface EnvBuildLogLine {
+  /** Increases by one per line, across builds */
+  seq: number;
+  env_type: "conda" | "uv";
+  stream: "stdout" | "stderr";
+  text: string;
+}
+
 /** Snapshot of a comm channel's state for multi-window sync */
 export interface CommSnapshot {
   comm_id: string;
@@ -144,6 +173,23 @@ export interface CommSnapshot {
   buffers?: number[][];
 }
 
+/** A package in an environment's dependency tree */
+export interface DepNode {
+  name: string;
+  version: string;
+  /** Declared by the notebook rather than pulled in by another package */
+  direct: boolean;
+  /** Already shown elsewhere in the tree, which lists its dependencies */
+  deduplicated?: boolean;
+  dependencies?: DepNode[];
+}
+
+/** Frontend action a kernel asked for in an execute_reply payload */
+export type KernelPayload =
+  | { source: "page"; data: Record<string, unknown>; start: number }
+  | { source: "set_next_input"; text: string; replace: boolean }
+  | { source: "ask_exit"; keepkernel: boolean };
+
 /** Broadcast events from daemon for kernel operations */
 export type DaemonBroadcast =
   | {
@@ -161,6 +207,7 @@ export type DaemonBroadcast =
       cell_id: string;
       output_type: string; // "stream" | "display_data" | "execute_result" | "error"
       output_json: string; // Serialized output in nbformat shape
+      output_index?: number; // Set when replacing an existing output (stream upserts)
     }
   | {
       event: "display_update";
@@ -177,14 +224,57 @@ export type DaemonBroadcast =
       executing?: string;
       queued: string[];
     }
+  | {
+      event: "run_all_progress";
+      completed: number;
+      total: number;
+      current_cell?: string;
+    }
+  | {
+      event: "run_all_cancelled";
+      skipped: number;
+    }
   | {
       event: "kernel_error";
       error: string;
     }
+  | {
+      event: "kernel_lifecycle";
+      state:
+        | "launched"
+        | "unresponsive"
+        | "crashed"
+        | "reattached"
+        | "auto_launch_disabled"
+        | "error";
+      kernel_type?: string;
+      env_source?: string;
+      exit_code?: number;
+      stderr_tail?: string;
+      cancelled_cells: string[];
+      reason?: string;
+    }
   | {
       event: "outputs_cleared";
       cell_id: string;
     }
+  | {
+      event: "suggest_install";
+      cell_id: string;
+      module: string;
+      package: string;
+      conda_package: string;
+    }
+  | {
+      event: "kernel_payload";
+      cell_id: string;
+      payload: KernelPayload;
+    }
+  | {
+      event: "env_id_collision";
+      env_id: string;
+      other_notebooks: string[];
+    }
   | {
       event: "comm";
       msg_type: string; // "comm_open" | "comm_msg" | "comm_close"
@@ -195,6 +285,7 @@ export type DaemonBroadcast =
       event: "comm_sync";
       comms: CommSnapshot[]; // All active comms for widget reconstruction
     }
+  | ({ event: "env_build_log" } & EnvBuildLogLine)
   | ({
       event: "env_progress";
       env_type: "conda" | "uv";
@@ -210,6 +301,32 @@ export type DaemonBroadcast =
       };
     };
 
+/** An environment the daemon can launch the kernel with (see list_available_environments) */
+export interface EnvOption {
+  id: string;
+  label: string;
+  env_source: string;
+  startup_cost: "instant" | "fast" | "slow";
+  is_default: boolean;
+}
+
+/** A notebook from the daemon's registry (see list_recent_notebooks) */
+export interface NotebookRecord {
+  notebook_id: string;
+  path?: string;
+  last_env_source?: string;
+  /** RFC 3339 timestamp */
+  last_opened: string;
+}
+
+/** An output whose text matched a search (see search_outputs) */
+export interface OutputMatch {
+  cell_id: string;
+  output_index: number;
+  output_type: string;
+  snippet: string;
+}
+
 /** Response types from daemon notebook requests */
 export type DaemonNotebookResponse =
   | { result: "kernel_launched"; kernel_type: string; env_source: string }
@@ -219,7 +336,14 @@ export type DaemonNotebookResponse =
       env_source: string;
     }
   | { result: "cell_queued"; cell_id: string }
+  | { result: "cell_batch_queued"; cell_ids: string[]; skipped?: string[] }
   | { result: "outputs_cleared"; cell_id: string }
+  | {
+      result: "all_outputs_cleared";
+      cell_ids: string[];
+      blobs_reclaimed: number;
+    }
+  | { result: "outputs_copied"; cell_id: string; count: number }
   | { result: "interrupt_sent" }
   | { result: "kernel_shutting_down" }
   | { result: "no_kernel" }
@@ -233,10 +357,62 @@ export type DaemonNotebookResponse =
   | { result: "all_cells_queued"; count: number }
   | { result: "ok" }
   | { result: "error"; error: string }
+  | { result: "environments"; options: EnvOption[] }
+  | { result: "notebooks"; notebooks: NotebookRecord[] }
+  | { result: "output_matches"; matches: OutputMatch[] }
+  | { result: "env_build_log"; lines: EnvBuildLogLine[] }
+  | { result: "cell_outputs"; cell_id: string; outputs: unknown[] }
+  | {
+      result: "cell_timings";
+      timings: ({ cell_id: string } & CellExecutionTiming)[];
+    }
+  | { result: "environment_repaired"; env_source: string; rebuilt: boolean }
+  | { result: "environment_pinned"; env_id: string; env_path: string }
+  | { result: "environment_unpinned"; env_id: string; env_path: string }
+  | { result: "dependency_tree"; env_type: "uv" | "conda"; roots: DepNode[] }
   | { result: "sync_environment_started"; packages: string[] }
-  | { result: "sync_environment_complete"; synced_packages: string[] }
+  | {
+      result: "sync_environment_complete";
+      installed: string[];
+      /** [package, error] for each package that failed to sync */
+      failed: [string, string][];
+      removed: string[];
+    }
   | {
       result: "sync_environment_failed";
       error: string;
       needs_restart: boolean;
+    }
+  | {
+      result: "kernel_state_snapshot";
+      saved: string[];
+      dropped: DroppedVariable[];
+    }
+  | {
+      result: "kernel_state_restored";
+      restored: string[];
+      dropped: DroppedVariable[];
     };
+
+/** A variable a kernel state snapshot or restore had to leave out. */
+export interface DroppedVariable {
+  name: string;
+  /** The exception raised while pickling or unpickling */
+  reason: string;
+}
+
+/** A notebook in a directory the app was opened on (see get_directory_listing) */
+export interface NotebookEntry {
+  path: string;
+  /** Relative to the opened directory, `/`-separated */
+  relative_path: string;
+  name: string;
+}
+
+/** Payload of `notebook:directory-listing` */
+export interface DirectoryListing {
+  directory: string;
+  notebooks: NotebookEntry[];
+  /** Whether the scan stopped early because there were too many notebooks */
+  truncated: boolean;
+}
@@ -1,5 +1,11 @@
 import { useState, useEffect, useCallback, useRef, useMemo } from "react";
 import { MediaRouter } from "@/components/outputs/media-router";
+import {
+  DEFAULT_LARGE_OUTPUT_BYTES,
+  formatSize,
+  isLargeOutput,
+  outputSize,
+} from "@/components/outputs/large-output";
 import { MediaProvider } from "@/components/outputs/media-provider";
 // Register built-in ipywidgets (IntSlider, etc.)
 import "@/components/widgets/controls";
@@ -60,6 +66,22 @@ interface OutputCellProps {
 }
 
 function OutputCell({ output, index }: OutputCellProps) {
+  // Hold back huge outputs so one giant repr doesn't freeze the viewer
+  const [showLarge, setShowLarge] = useState(false);
+  if (!showLarge && isLargeOutput(output, DEFAULT_LARGE_OUTPUT_BYTES)) {
+    return (
+      <div className="output-cell px-4 py-1" data-index={index}>
+        <button
+          type="button"
+          onClick={() => setShowLarge(true)}
+          className="text-xs text-muted-foreground hover:text-foreground transition-colors"
+        >
+          Render large output ({formatSize(outputSize(output))})
+        </button>
+      </div>
+    );
+  }
+
   if (output.output_type === "stream") {
     return (
       <AnsiStreamOutput
@@ -1,7 +1,48 @@
+//! Launch, connect to, and drive Jupyter kernels.
+//!
+//! [`KernelClient`] owns a kernel's connection info and, for kernels it
+//! started, the kernel process. It powers the `runt` CLI and can be embedded
+//! in other tools:
+//!
+//! ```no_run
+//! use jupyter_protocol::{JupyterMessageContent, MediaType};
+//! use kernel_client::KernelClient;
+//!
+//! # async fn run() -> runtimelib::Result<()> {
+//! let kernelspec = runtimelib::find_kernelspec("python3").await?;
+//! let mut client = KernelClient::start_from_kernelspec(kernelspec).await?;
+//!
+//! // Outputs stream to the callback as the kernel publishes them on iopub
+//! let reply = client
+//!     .execute("print('hello')", |content| match content {
+//!         JupyterMessageContent::StreamContent(stream) => print!("{}", stream.text),
+//!         JupyterMessageContent::ExecuteResult(result) => {
+//!             if let Some(MediaType::Plain(text)) = result.data.content.first() {
+//!                 println!("{}", text);
+//!             }
+//!         }
+//!         _ => {}
+//!     })
+//!     .await?;
+//! println!("status: {:?}", reply.status);
+//!
+//! client.shutdown(false).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Kernels started elsewhere can be driven through their connection file with
+//! [`KernelClient::from_connection_file`], and [`watch_kernels`] reports them
+//! as they start and stop.
+
 use std::path::{Path, PathBuf};
 
 use std::future::Future;
 
+pub mod watch;
+
+pub use watch::{watch_kernels, watch_kernels_in, KernelEvent, KernelWatcher, DEBOUNCE};
+
 use jupyter_protocol::{
     ConnectionInfo, ExecuteReply, ExecuteRequest, InputReply, InputRequest, InterruptRequest,
     JupyterMessage, JupyterMessageContent, ReplyStatus, ShutdownRequest,
@@ -41,6 +82,10 @@ fn default_kernel_cwd() -> PathBuf {
     std::env::temp_dir()
 }
 
+/// A handle to a running Jupyter kernel.
+///
+/// Each request opens its own ZeroMQ sockets, so a client can be cheaply
+/// recreated from the connection file by another process.
 pub struct KernelClient {
     kernel_id: String,
     session_id: String,
@@ -50,6 +95,10 @@ pub struct KernelClient {
 }
 
 impl KernelClient {
+    /// Launch a kernel from an installed kernelspec.
+    ///
+    /// A connection file named `runt-kernel-{kernel_id}.json` is written to the
+    /// Jupyter runtime dir, and the kernel process is owned by the client.
     pub async fn start_from_kernelspec(kernelspec: KernelspecDir) -> Result<Self> {
         let kernel_id = petname(2, "-").expect("failed to generate petname");
         let session_id = Uuid::new_v4().to_string();
@@ -96,6 +145,17 @@ impl KernelClient {
     ///
     /// The command is split on whitespace and `{connection_file}` is replaced
     /// with the path to the generated connection file.
+    ///
+    /// ```no_run
+    /// # async fn run() -> runtimelib::Result<()> {
+    /// let client = kernel_client::KernelClient::start_from_command(
+    ///     "python -m ipykernel_launcher -f {connection_file}",
+    /// )
+    /// .await?;
+    /// println!("started {}", client.kernel_id());
+    /// # Ok(())
+    /// # }
+    /// ```
     pub async fn start_from_command(cmd: &str) -> Result<Self> {
         let kernel_id = petname(2, "-").expect("failed to generate petname");
         let session_id = Uuid::new_v4().to_string();
@@ -146,6 +206,20 @@ impl KernelClient {
         })
     }
 
+    /// Connect to a kernel started by another client.
+    ///
+    /// The file must be named `runt-kernel-{kernel_id}.json`. The returned
+    /// client doesn't own the kernel process, but [`shutdown`](Self::shutdown)
+    /// still asks the kernel to exit and removes the connection file.
+    ///
+    /// ```no_run
+    /// # async fn run() -> runtimelib::Result<()> {
+    /// let path = runtimelib::runtime_dir().join("runt-kernel-happy-otter.json");
+    /// let mut client = kernel_client::KernelClient::from_connection_file(path).await?;
+    /// client.interrupt().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
     pub async fn from_connection_file(path: impl AsRef<Path>) -> Result<Self> {
         let connection_file = path.as_ref().to_path_buf();
         let content = tokio::fs::read_to_string(&connection_file).await?;
@@ -182,6 +256,7 @@ impl KernelClient {
         &self.session_id
     }
 
+    /// Send an `interrupt_request` on the control channel.
     pub async fn interrupt(&mut self) -> Result<()> {
         let mut control =
             create_client_control_connection(&self.connection_info, &self.session_id).await?;
@@ -190,6 +265,8 @@ impl KernelClient {
         Ok(())
     }
 
+    /// Ask the kernel to shut down, then kill the process if this client owns
+    /// it and remove the connection file.
     pub async fn shutdown(&mut self, restart: bool) -> Result<()> {
         // Try a graceful shutdown with a timeout
         let graceful = tokio::time::timeout(
@@ -247,6 +324,10 @@ impl KernelClient {
         Ok(())
     }
 
+    /// Execute code and wait for the `execute_reply`.
+    ///
+    /// Every iopub message the request produces (streams, display data,
+    /// results, errors, status) is passed to `on_iopub` as it arrives.
     pub async fn execute<F>(&self, code: &str, mut on_iopub: F) -> Result<ExecuteReply>
     where
         F: FnMut(JupyterMessageContent),
@@ -296,12 +377,12 @@ impl KernelClient {
             }
         }
     }
+
     /// Execute code with stdin support, allowing the kernel to request user input.
     ///
     /// Creates shell and stdin connections that share a ZMQ identity (required
     /// by the Jupyter protocol for stdin routing). When the kernel sends an
     /// `input_request`, the `on_stdin` callback is invoked to get the user's response.
-    #[allow(dead_code)]
     pub async fn execute_with_stdin<F, G, Fut>(
         &self,
         code: &str,
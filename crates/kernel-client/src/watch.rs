@@ -0,0 +1,153 @@
+//! Push notifications for kernels appearing and disappearing.
+//!
+//! Every kernel launched by a [`KernelClient`](crate::KernelClient) writes a
+//! `runt-kernel-{kernel_id}.json` connection file to the Jupyter runtime dir.
+//! [`watch_kernels`] watches that directory and reports connection files as
+//! they come and go, so monitors don't have to poll it.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use notify::RecursiveMode;
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use tokio::sync::mpsc;
+
+use crate::extract_kernel_id;
+
+/// How long the watcher waits for a burst of filesystem changes to settle.
+///
+/// Editors and kernel launchers often write a temp file and rename it into
+/// place; the debounced event only reports the final state.
+pub const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// A connection file appeared in or disappeared from the watched directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KernelEvent {
+    Added {
+        kernel_id: String,
+        connection_file: PathBuf,
+    },
+    Removed {
+        kernel_id: String,
+        connection_file: PathBuf,
+    },
+}
+
+impl KernelEvent {
+    pub fn kernel_id(&self) -> &str {
+        match self {
+            KernelEvent::Added { kernel_id, .. } | KernelEvent::Removed { kernel_id, .. } => {
+                kernel_id
+            }
+        }
+    }
+
+    pub fn connection_file(&self) -> &Path {
+        match self {
+            KernelEvent::Added {
+                connection_file, ..
+            }
+            | KernelEvent::Removed {
+                connection_file, ..
+            } => connection_file,
+        }
+    }
+}
+
+/// Receives [`KernelEvent`]s until dropped, which stops the watcher.
+pub struct KernelWatcher {
+    events: mpsc::UnboundedReceiver<KernelEvent>,
+    _debouncer: Debouncer<notify::RecommendedWatcher>,
+}
+
+impl KernelWatcher {
+    /// Wait for the next kernel event.
+    pub async fn next(&mut self) -> Option<KernelEvent> {
+        self.events.recv().await
+    }
+}
+
+/// Watch the Jupyter runtime dir for kernels started or stopped by any
+/// client.
+///
+/// Connection files present when the watch starts are treated as known and
+/// don't produce `Added` events; list them first if needed.
+///
+/// ```no_run
+/// # async fn run() -> notify::Result<()> {
+/// use kernel_client::{watch_kernels, KernelEvent};
+///
+/// let mut watcher = watch_kernels()?;
+/// while let Some(event) = watcher.next().await {
+///     match event {
+///         KernelEvent::Added { kernel_id, .. } => println!("+ {}", kernel_id),
+///         KernelEvent::Removed { kernel_id, .. } => println!("- {}", kernel_id),
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn watch_kernels() -> notify::Result<KernelWatcher> {
+    watch_kernels_in(&runtimelib::runtime_dir())
+}
+
+/// Watch `dir` for `runt-kernel-*.json` connection files. See
+/// [`watch_kernels`].
+pub fn watch_kernels_in(dir: &Path) -> notify::Result<KernelWatcher> {
+    std::fs::create_dir_all(dir)?;
+
+    let mut known: HashSet<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| connection_file_kernel_id(path).is_some())
+        .collect();
+
+    let (tx, events) = mpsc::unbounded_channel();
+    let mut debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| {
+        let Ok(changes) = result else {
+            return;
+        };
+        for change in changes {
+            let path = change.path;
+            let Some(kernel_id) = connection_file_kernel_id(&path) else {
+                continue;
+            };
+            // Debounced events don't say what happened, so compare the file's
+            // current state against what we've already reported
+            let event = if path.exists() {
+                if !known.insert(path.clone()) {
+                    continue;
+                }
+                KernelEvent::Added {
+                    kernel_id,
+                    connection_file: path,
+                }
+            } else {
+                if !known.remove(&path) {
+                    continue;
+                }
+                KernelEvent::Removed {
+                    kernel_id,
+                    connection_file: path,
+                }
+            };
+            let _ = tx.send(event);
+        }
+    })?;
+    debouncer
+        .watcher()
+        .watch(dir, RecursiveMode::NonRecursive)?;
+
+    Ok(KernelWatcher {
+        events,
+        _debouncer: debouncer,
+    })
+}
+
+/// The kernel ID for a `runt-kernel-{id}.json` path, `None` for other files.
+fn connection_file_kernel_id(path: &Path) -> Option<String> {
+    if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+        return None;
+    }
+    extract_kernel_id(path)
+}
@@ -0,0 +1,54 @@
+//! Exercises the public API without launching a kernel.
+
+use jupyter_protocol::ConnectionInfo;
+use kernel_client::KernelClient;
+
+fn connection_info() -> ConnectionInfo {
+    ConnectionInfo {
+        transport: jupyter_protocol::connection_info::Transport::TCP,
+        ip: "127.0.0.1".to_string(),
+        stdin_port: 9001,
+        control_port: 9002,
+        hb_port: 9003,
+        shell_port: 9004,
+        iopub_port: 9005,
+        signature_scheme: "hmac-sha256".to_string(),
+        key: "secret".to_string(),
+        kernel_name: Some("python3".to_string()),
+    }
+}
+
+#[tokio::test]
+async fn from_connection_file_reads_connection_info() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let path = tmp.path().join("runt-kernel-happy-otter.json");
+    std::fs::write(&path, serde_json::to_string(&connection_info()).unwrap()).unwrap();
+
+    let client = KernelClient::from_connection_file(&path).await.unwrap();
+    assert_eq!(client.kernel_id(), "happy-otter");
+    assert_eq!(client.connection_file(), path);
+    assert_eq!(client.connection_info().shell_port, 9004);
+    assert_eq!(client.connection_info().key, "secret");
+    assert!(!client.session_id().is_empty());
+
+    // Each client gets its own session
+    let other = KernelClient::from_connection_file(&path).await.unwrap();
+    assert_ne!(client.session_id(), other.session_id());
+}
+
+#[tokio::test]
+async fn from_connection_file_requires_runt_kernel_name() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let path = tmp.path().join("kernel-1234.json");
+    std::fs::write(&path, serde_json::to_string(&connection_info()).unwrap()).unwrap();
+
+    assert!(KernelClient::from_connection_file(&path).await.is_err());
+}
+
+#[tokio::test]
+async fn from_connection_file_errors_on_missing_file() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let path = tmp.path().join("runt-kernel-missing.json");
+
+    assert!(KernelClient::from_connection_file(&path).await.is_err());
+}
@@ -0,0 +1,75 @@
+//! Connection-file watcher events.
+
+use std::time::Duration;
+
+use kernel_client::{watch_kernels_in, KernelEvent, KernelWatcher};
+
+async fn next_event(watcher: &mut KernelWatcher) -> KernelEvent {
+    tokio::time::timeout(Duration::from_secs(5), watcher.next())
+        .await
+        .expect("timed out waiting for kernel event")
+        .expect("watcher closed")
+}
+
+#[tokio::test]
+async fn creating_and_deleting_connection_file_emits_events() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let mut watcher = watch_kernels_in(tmp.path()).unwrap();
+    let path = tmp.path().join("runt-kernel-happy-otter.json");
+
+    std::fs::write(&path, "{}").unwrap();
+    assert_eq!(
+        next_event(&mut watcher).await,
+        KernelEvent::Added {
+            kernel_id: "happy-otter".to_string(),
+            connection_file: path.clone(),
+        }
+    );
+
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        next_event(&mut watcher).await,
+        KernelEvent::Removed {
+            kernel_id: "happy-otter".to_string(),
+            connection_file: path,
+        }
+    );
+}
+
+#[tokio::test]
+async fn write_then_rename_emits_single_added_event() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let mut watcher = watch_kernels_in(tmp.path()).unwrap();
+
+    // Unrelated files are ignored
+    std::fs::write(tmp.path().join("kernel-1234.json"), "{}").unwrap();
+
+    let temp = tmp.path().join("runt-kernel-calm-heron.json.tmp");
+    let path = tmp.path().join("runt-kernel-calm-heron.json");
+    std::fs::write(&temp, "{}").unwrap();
+    std::fs::rename(&temp, &path).unwrap();
+    // Rewriting in place is not a new kernel
+    std::fs::write(&path, "{\"key\": \"\"}").unwrap();
+
+    let event = next_event(&mut watcher).await;
+    assert_eq!(event.kernel_id(), "calm-heron");
+    assert_eq!(event.connection_file(), path);
+    assert!(matches!(event, KernelEvent::Added { .. }));
+
+    let extra = tokio::time::timeout(Duration::from_millis(600), watcher.next()).await;
+    assert!(extra.is_err(), "unexpected event: {:?}", extra);
+}
+
+#[tokio::test]
+async fn existing_connection_files_are_known_at_start() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let path = tmp.path().join("runt-kernel-old-kernel.json");
+    std::fs::write(&path, "{}").unwrap();
+
+    let mut watcher = watch_kernels_in(tmp.path()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let event = next_event(&mut watcher).await;
+    assert!(matches!(event, KernelEvent::Removed { .. }));
+    assert_eq!(event.kernel_id(), "old-kernel");
+}
@@ -19,18 +19,47 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Instant;
 
-use crate::progress::{EnvProgressPhase, ProgressHandler, RattlerReporter};
+use crate::dep_tree::{tree_from_packages, DepNode, InstalledPackage};
+use crate::progress::{output_with_log, EnvProgressPhase, ProgressHandler, RattlerReporter};
+
+/// How the solver picks between channels that carry the same package.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelPriority {
+    /// Take each package only from the first listed channel that has it, so
+    /// channel order decides where packages come from (conda's `strict`).
+    #[default]
+    Strict,
+    /// Take the best version from any listed channel.
+    Disabled,
+}
+
+impl From<ChannelPriority> for rattler_solve::ChannelPriority {
+    fn from(priority: ChannelPriority) -> Self {
+        match priority {
+            ChannelPriority::Strict => rattler_solve::ChannelPriority::Strict,
+            ChannelPriority::Disabled => rattler_solve::ChannelPriority::Disabled,
+        }
+    }
+}
 
 /// Conda dependency specification.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CondaDependencies {
     pub dependencies: Vec<String>,
+    /// Channels in priority order, highest first. Defaults to conda-forge.
     #[serde(default)]
     pub channels: Vec<String>,
+    #[serde(default)]
+    pub channel_priority: ChannelPriority,
     pub python: Option<String>,
     /// Unique environment ID for per-notebook isolation.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub env_id: Option<String>,
+    /// PyPI packages installed with pip after the conda solve
+    /// (pixi `[pypi-dependencies]`, environment.yml `pip:` entries).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pip_dependencies: Vec<String>,
 }
 
 /// A resolved conda environment on disk.
@@ -50,8 +79,10 @@ pub fn default_cache_dir_conda() -> PathBuf {
 
 /// Compute a stable cache key for the given dependencies.
 ///
-/// The hash includes sorted deps, sorted channels, python constraint,
-/// and env_id (for per-notebook isolation).
+/// The hash includes sorted deps, sorted channels, sorted pip deps, python
+/// constraint, and env_id (for per-notebook isolation). Channels are sorted
+/// even under strict priority so existing envs keep their hash; the solver
+/// still sees them in the user's order.
 pub fn compute_env_hash(deps: &CondaDependencies) -> String {
     let mut hasher = Sha256::new();
 
@@ -70,6 +101,18 @@ pub fn compute_env_hash(deps: &CondaDependencies) -> String {
         hasher.update(b"\n");
     }
 
+    let mut sorted_pip = deps.pip_dependencies.clone();
+    sorted_pip.sort();
+    for dep in &sorted_pip {
+        hasher.update(b"pip:");
+        hasher.update(dep.as_bytes());
+        hasher.update(b"\n");
+    }
+
+    if deps.channel_priority == ChannelPriority::Disabled {
+        hasher.update(b"priority:disabled\n");
+    }
+
     if let Some(ref py) = deps.python {
         hasher.update(b"python:");
         hasher.update(py.as_bytes());
@@ -161,6 +204,15 @@ pub async fn prepare_environment_in(
         ));
     }
 
+    if !deps.pip_dependencies.is_empty() {
+        if let Err(e) =
+            install_pip_dependencies(&python_path, &deps.pip_dependencies, handler.as_ref()).await
+        {
+            tokio::fs::remove_dir_all(&env_path).await.ok();
+            return Err(e);
+        }
+    }
+
     handler.on_progress(
         "conda",
         EnvProgressPhase::Ready {
@@ -175,38 +227,96 @@ pub async fn prepare_environment_in(
     })
 }
 
-/// Core rattler solve + install logic, extracted for reuse by prepare and prewarm.
-async fn install_conda_env(
-    env_path: &Path,
-    deps: &CondaDependencies,
-    handler: Arc<dyn ProgressHandler>,
-) -> Result<()> {
-    let cache_dir = env_path
-        .parent()
-        .unwrap_or_else(|| Path::new("/tmp"))
-        .to_path_buf();
-    let channel_config = ChannelConfig::default_with_root_dir(cache_dir);
-
-    // Parse channels
-    let channels: Vec<Channel> = if deps.channels.is_empty() {
-        vec![Channel::from_str("conda-forge", &channel_config)?]
-    } else {
-        deps.channels
-            .iter()
-            .map(|c| Channel::from_str(c, &channel_config))
-            .collect::<std::result::Result<Vec<_>, _>>()?
-    };
-
-    let channel_names: Vec<String> = channels.iter().map(|c| c.name().to_string()).collect();
+/// Arguments for `uv` to pip-install `pip_deps` into the env owning `python_path`.
+///
+/// Deps keep their declared order. Option entries such as `-r requirements.txt`
+/// are split so the flag and its value reach uv as separate arguments.
+fn pip_install_args(python_path: &Path, pip_deps: &[String]) -> Vec<String> {
+    let mut args = vec![
+        "pip".to_string(),
+        "install".to_string(),
+        "--python".to_string(),
+        python_path.to_string_lossy().to_string(),
+    ];
+    for dep in pip_deps {
+        match dep.split_once(char::is_whitespace) {
+            Some((flag, value)) if flag.starts_with('-') => {
+                args.push(flag.to_string());
+                args.push(value.trim().to_string());
+            }
+            _ => args.push(dep.clone()),
+        }
+    }
+    args
+}
 
+/// Install PyPI packages into a freshly solved conda prefix using uv.
+async fn install_pip_dependencies(
+    python_path: &Path,
+    pip_deps: &[String],
+    handler: &dyn ProgressHandler,
+) -> Result<()> {
+    info!(
+        "Installing {} pip dependencies into conda env",
+        pip_deps.len()
+    );
     handler.on_progress(
         "conda",
-        EnvProgressPhase::FetchingRepodata {
-            channels: channel_names,
+        EnvProgressPhase::InstallingPackages {
+            packages: pip_deps.to_vec(),
         },
     );
 
-    // Build specs
+    let uv_path = kernel_launch::tools::get_uv_path().await?;
+    let output = output_with_log(
+        tokio::process::Command::new(&uv_path).args(pip_install_args(python_path, pip_deps)),
+        "conda",
+        handler,
+    )
+    .await?;
+
+    if !output.status.success() {
+        // Conda packages are already in place at this point; say so, so a bad
+        // pip entry isn't mistaken for a conda solve failure.
+        let error_msg = format!(
+            "pip install failed after conda solve: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        handler.on_progress(
+            "conda",
+            EnvProgressPhase::Error {
+                message: error_msg.clone(),
+            },
+        );
+        return Err(anyhow!(error_msg));
+    }
+
+    Ok(())
+}
+
+/// The channels to solve against, in priority order: conda-forge if none are
+/// given, with repeats dropped so a channel keeps its first position.
+fn parse_channels(
+    deps: &CondaDependencies,
+    channel_config: &ChannelConfig,
+) -> Result<Vec<Channel>> {
+    if deps.channels.is_empty() {
+        return Ok(vec![Channel::from_str("conda-forge", channel_config)?]);
+    }
+    let mut channels: Vec<Channel> = Vec::with_capacity(deps.channels.len());
+    for name in &deps.channels {
+        let channel = Channel::from_str(name, channel_config)?;
+        if !channels.iter().any(|c| c.base_url == channel.base_url) {
+            channels.push(channel);
+        }
+    }
+    Ok(channels)
+}
+
+/// Match specs for the conda solve: python, the kernel packages, and the
+/// conda dependencies. `pip_dependencies` aren't conda packages and are left
+/// for the pip phase that follows.
+fn solve_specs(deps: &CondaDependencies) -> Result<Vec<MatchSpec>> {
     let match_spec_options = ParseMatchSpecOptions::strict();
     let mut specs: Vec<MatchSpec> = Vec::new();
 
@@ -228,6 +338,34 @@ async fn install_conda_env(
         }
     }
 
+    Ok(specs)
+}
+
+/// Core rattler solve + install logic, extracted for reuse by prepare and prewarm.
+async fn install_conda_env(
+    env_path: &Path,
+    deps: &CondaDependencies,
+    handler: Arc<dyn ProgressHandler>,
+) -> Result<()> {
+    let cache_dir = env_path
+        .parent()
+        .unwrap_or_else(|| Path::new("/tmp"))
+        .to_path_buf();
+    let channel_config = ChannelConfig::default_with_root_dir(cache_dir);
+
+    let channels = parse_channels(deps, &channel_config)?;
+
+    let channel_names: Vec<String> = channels.iter().map(|c| c.name().to_string()).collect();
+
+    handler.on_progress(
+        "conda",
+        EnvProgressPhase::FetchingRepodata {
+            channels: channel_names,
+        },
+    );
+
+    let specs = solve_specs(deps)?;
+
     // Rattler cache
     let rattler_cache_dir = default_cache_dir()
         .map_err(|e| anyhow!("could not determine rattler cache directory: {}", e))?;
@@ -363,6 +501,7 @@ async fn install_conda_env(
     let solver_task = SolverTask {
         virtual_packages,
         specs,
+        channel_priority: deps.channel_priority.into(),
         ..SolverTask::from_iter(&repo_data)
     };
 
@@ -441,6 +580,76 @@ async fn install_conda_env(
     Ok(())
 }
 
+/// Describe why a cached conda environment can't host a kernel, or `None`
+/// if it can. See [`crate::uv::diagnose_environment`].
+pub async fn diagnose_environment(env: &CondaEnvironment) -> Option<String> {
+    crate::uv::diagnose_interpreter(&env.python_path).await
+}
+
+/// Rebuild a cached conda environment in place if it is broken.
+///
+/// Returns `Ok(false)` if the environment was healthy and left alone, or
+/// `Ok(true)` once a broken one has been removed and re-solved from `deps`.
+pub async fn repair_environment(
+    deps: &CondaDependencies,
+    handler: Arc<dyn ProgressHandler>,
+) -> Result<bool> {
+    repair_environment_in(deps, &default_cache_dir_conda(), handler).await
+}
+
+/// Like [`repair_environment`] but with an explicit cache directory.
+pub async fn repair_environment_in(
+    deps: &CondaDependencies,
+    cache_dir: &Path,
+    handler: Arc<dyn ProgressHandler>,
+) -> Result<bool> {
+    let env_path = cache_dir.join(compute_env_hash(deps));
+
+    #[cfg(target_os = "windows")]
+    let python_path = env_path.join("python.exe");
+    #[cfg(not(target_os = "windows"))]
+    let python_path = env_path.join("bin").join("python");
+
+    let Some(problem) = crate::uv::diagnose_interpreter(&python_path).await else {
+        info!(
+            "Conda environment at {:?} is healthy, nothing to repair",
+            env_path
+        );
+        return Ok(false);
+    };
+
+    warn!(
+        "Conda environment at {:?} is broken ({}), rebuilding",
+        env_path, problem
+    );
+    if env_path.exists() {
+        tokio::fs::remove_dir_all(&env_path).await?;
+    }
+    prepare_environment_in(deps, cache_dir, handler).await?;
+
+    Ok(true)
+}
+
+/// The dependency tree of an environment, from the records in its
+/// `conda-meta` directory. Packages named in `declared` (the notebook's
+/// dependencies) are marked direct. Packages pip installed on top of the
+/// conda solve aren't included.
+pub fn dependency_tree(env: &CondaEnvironment, declared: &[String]) -> Result<Vec<DepNode>> {
+    let records = PrefixRecord::collect_from_prefix::<PrefixRecord>(&env.env_path)?;
+    let packages = records
+        .into_iter()
+        .map(|record| {
+            let package = record.repodata_record.package_record;
+            InstalledPackage {
+                name: package.name.as_normalized().to_string(),
+                version: package.version.to_string(),
+                depends: package.depends,
+            }
+        })
+        .collect();
+    Ok(tree_from_packages(packages, declared))
+}
+
 /// Create a prewarmed conda environment with ipykernel, ipywidgets,
 /// and any caller-supplied extra packages.
 ///
@@ -482,8 +691,10 @@ pub async fn create_prewarmed_environment_in(
     let deps = CondaDependencies {
         dependencies: deps_list,
         channels: vec!["conda-forge".to_string()],
+        channel_priority: ChannelPriority::Strict,
         python: None,
         env_id: None,
+        pip_dependencies: vec![],
     };
 
     install_conda_env(&env_path, &deps, handler.clone()).await?;
@@ -523,8 +734,10 @@ pub async fn claim_prewarmed_environment_in(
     let deps = CondaDependencies {
         dependencies: vec!["ipykernel".to_string()],
         channels: vec!["conda-forge".to_string()],
+        channel_priority: ChannelPriority::Strict,
         python: None,
         env_id: Some(env_id.to_string()),
+        pip_dependencies: vec![],
     };
     let hash = compute_env_hash(&deps);
     let dest_path = cache_dir.join(&hash);
@@ -691,14 +904,7 @@ pub async fn sync_dependencies(env: &CondaEnvironment, deps: &CondaDependencies)
         .to_path_buf();
     let channel_config = ChannelConfig::default_with_root_dir(cache_dir);
 
-    let channels: Vec<Channel> = if deps.channels.is_empty() {
-        vec![Channel::from_str("conda-forge", &channel_config)?]
-    } else {
-        deps.channels
-            .iter()
-            .map(|c| Channel::from_str(c, &channel_config))
-            .collect::<std::result::Result<Vec<_>, _>>()?
-    };
+    let channels = parse_channels(deps, &channel_config)?;
 
     let match_spec_options = ParseMatchSpecOptions::strict();
     let mut specs: Vec<MatchSpec> = Vec::new();
@@ -794,6 +1000,7 @@ pub async fn sync_dependencies(env: &CondaEnvironment, deps: &CondaDependencies)
             .iter()
             .map(|r| r.repodata_record.clone())
             .collect(),
+        channel_priority: deps.channel_priority.into(),
         ..SolverTask::from_iter(&repo_data)
     };
 
@@ -874,8 +1081,10 @@ mod tests {
         let deps = CondaDependencies {
             dependencies: vec!["pandas".to_string(), "numpy".to_string()],
             channels: vec!["conda-forge".to_string()],
+            channel_priority: ChannelPriority::Strict,
             python: Some("3.11".to_string()),
             env_id: Some("test-env-id".to_string()),
+            pip_dependencies: vec![],
         };
 
         let hash1 = compute_env_hash(&deps);
@@ -888,15 +1097,19 @@ mod tests {
         let deps1 = CondaDependencies {
             dependencies: vec!["pandas".to_string(), "numpy".to_string()],
             channels: vec![],
+            channel_priority: ChannelPriority::Strict,
             python: None,
             env_id: Some("test-env-1".to_string()),
+            pip_dependencies: vec![],
         };
 
         let deps2 = CondaDependencies {
             dependencies: vec!["numpy".to_string(), "pandas".to_string()],
             channels: vec![],
+            channel_priority: ChannelPriority::Strict,
             python: None,
             env_id: Some("test-env-1".to_string()),
+            pip_dependencies: vec![],
         };
 
         assert_eq!(compute_env_hash(&deps1), compute_env_hash(&deps2));
@@ -907,15 +1120,19 @@ mod tests {
         let deps1 = CondaDependencies {
             dependencies: vec!["pandas".to_string()],
             channels: vec![],
+            channel_priority: ChannelPriority::Strict,
             python: None,
             env_id: Some("test-env-1".to_string()),
+            pip_dependencies: vec![],
         };
 
         let deps2 = CondaDependencies {
             dependencies: vec!["numpy".to_string()],
             channels: vec![],
+            channel_priority: ChannelPriority::Strict,
             python: None,
             env_id: Some("test-env-1".to_string()),
+            pip_dependencies: vec![],
         };
 
         assert_ne!(compute_env_hash(&deps1), compute_env_hash(&deps2));
@@ -926,15 +1143,19 @@ mod tests {
         let deps1 = CondaDependencies {
             dependencies: vec!["numpy".to_string()],
             channels: vec!["conda-forge".to_string()],
+            channel_priority: ChannelPriority::Strict,
             python: None,
             env_id: Some("test-env-1".to_string()),
+            pip_dependencies: vec![],
         };
 
         let deps2 = CondaDependencies {
             dependencies: vec!["numpy".to_string()],
             channels: vec!["defaults".to_string()],
+            channel_priority: ChannelPriority::Strict,
             python: None,
             env_id: Some("test-env-1".to_string()),
+            pip_dependencies: vec![],
         };
 
         assert_ne!(compute_env_hash(&deps1), compute_env_hash(&deps2));
@@ -945,17 +1166,215 @@ mod tests {
         let deps1 = CondaDependencies {
             dependencies: vec!["numpy".to_string()],
             channels: vec!["conda-forge".to_string()],
+            channel_priority: ChannelPriority::Strict,
             python: None,
             env_id: Some("notebook-1".to_string()),
+            pip_dependencies: vec![],
         };
 
         let deps2 = CondaDependencies {
             dependencies: vec!["numpy".to_string()],
             channels: vec!["conda-forge".to_string()],
+            channel_priority: ChannelPriority::Strict,
             python: None,
             env_id: Some("notebook-2".to_string()),
+            pip_dependencies: vec![],
         };
 
         assert_ne!(compute_env_hash(&deps1), compute_env_hash(&deps2));
     }
+
+    #[test]
+    fn test_compute_env_hash_includes_pip_deps() {
+        let conda_only = CondaDependencies {
+            dependencies: vec!["numpy".to_string()],
+            channels: vec!["conda-forge".to_string()],
+            channel_priority: ChannelPriority::Strict,
+            python: None,
+            env_id: None,
+            pip_dependencies: vec![],
+        };
+        let with_pip = CondaDependencies {
+            pip_dependencies: vec!["requests".to_string(), "fastapi".to_string()],
+            ..conda_only.clone()
+        };
+        let with_pip_reordered = CondaDependencies {
+            pip_dependencies: vec!["fastapi".to_string(), "requests".to_string()],
+            ..conda_only.clone()
+        };
+
+        assert_ne!(compute_env_hash(&conda_only), compute_env_hash(&with_pip));
+        assert_eq!(
+            compute_env_hash(&with_pip),
+            compute_env_hash(&with_pip_reordered)
+        );
+    }
+
+    #[test]
+    fn test_compute_env_hash_channel_order() {
+        let forge_first = CondaDependencies {
+            dependencies: vec!["numpy".to_string()],
+            channels: vec!["conda-forge".to_string(), "bioconda".to_string()],
+            channel_priority: ChannelPriority::Strict,
+            python: None,
+            env_id: None,
+            pip_dependencies: vec![],
+        };
+        let bioconda_first = CondaDependencies {
+            channels: vec!["bioconda".to_string(), "conda-forge".to_string()],
+            ..forge_first.clone()
+        };
+        // Reordering channels reuses the cached env under either priority
+        assert_eq!(
+            compute_env_hash(&forge_first),
+            compute_env_hash(&bioconda_first)
+        );
+
+        let disabled = |deps: &CondaDependencies| CondaDependencies {
+            channel_priority: ChannelPriority::Disabled,
+            ..deps.clone()
+        };
+        assert_eq!(
+            compute_env_hash(&disabled(&forge_first)),
+            compute_env_hash(&disabled(&bioconda_first))
+        );
+        assert_ne!(
+            compute_env_hash(&forge_first),
+            compute_env_hash(&disabled(&forge_first))
+        );
+    }
+
+    #[test]
+    fn test_channel_priority_reaches_solver() {
+        assert_eq!(
+            rattler_solve::ChannelPriority::from(ChannelPriority::Strict),
+            rattler_solve::ChannelPriority::Strict
+        );
+        assert_eq!(
+            rattler_solve::ChannelPriority::from(ChannelPriority::Disabled),
+            rattler_solve::ChannelPriority::Disabled
+        );
+
+        // Older metadata without the field solves strictly
+        let deps: CondaDependencies =
+            serde_json::from_str(r#"{"dependencies": ["numpy"], "python": null}"#).unwrap();
+        assert_eq!(deps.channel_priority, ChannelPriority::Strict);
+        let deps: CondaDependencies = serde_json::from_str(
+            r#"{"dependencies": [], "python": null, "channel_priority": "disabled"}"#,
+        )
+        .unwrap();
+        assert_eq!(deps.channel_priority, ChannelPriority::Disabled);
+    }
+
+    #[test]
+    fn test_parse_channels_keeps_priority_order() {
+        let config = ChannelConfig::default_with_root_dir(PathBuf::from("/tmp"));
+        let deps = CondaDependencies {
+            dependencies: vec![],
+            channels: vec![
+                "bioconda".to_string(),
+                "conda-forge".to_string(),
+                "bioconda".to_string(),
+            ],
+            channel_priority: ChannelPriority::Strict,
+            python: None,
+            env_id: None,
+            pip_dependencies: vec![],
+        };
+        let names: Vec<String> = parse_channels(&deps, &config)
+            .unwrap()
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect();
+        assert_eq!(names, vec!["bioconda", "conda-forge"]);
+
+        let no_channels = CondaDependencies {
+            channels: vec![],
+            ..deps
+        };
+        let names: Vec<String> = parse_channels(&no_channels, &config)
+            .unwrap()
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect();
+        assert_eq!(names, vec!["conda-forge"]);
+    }
+
+    #[test]
+    fn test_pip_deps_reach_install_args() {
+        let python = Path::new("/envs/abc/bin/python");
+        let args = pip_install_args(python, &["requests>=2".to_string(), "fastapi".to_string()]);
+
+        assert_eq!(
+            args,
+            vec![
+                "pip",
+                "install",
+                "--python",
+                "/envs/abc/bin/python",
+                "requests>=2",
+                "fastapi"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mixed_env_installs_each_dep_in_its_phase() {
+        // A conda-only GIS stack with PyPI-only packages on top
+        let deps = CondaDependencies {
+            dependencies: vec!["gdal>=3.8".to_string(), "geopandas".to_string()],
+            channels: vec![],
+            channel_priority: ChannelPriority::Strict,
+            python: Some("3.12".to_string()),
+            env_id: None,
+            pip_dependencies: vec!["overturemaps".to_string(), "lonboard==0.10".to_string()],
+        };
+
+        let solved: Vec<String> = solve_specs(&deps)
+            .unwrap()
+            .iter()
+            .map(|spec| spec.to_string())
+            .collect();
+        for conda_dep in ["gdal >=3.8", "geopandas"] {
+            assert!(solved.iter().any(|s| s == conda_dep), "{solved:?}");
+        }
+        assert!(
+            !solved
+                .iter()
+                .any(|s| s.contains("overturemaps") || s.contains("lonboard")),
+            "pip deps must not reach the conda solve: {solved:?}"
+        );
+
+        let pip_args = pip_install_args(Path::new("/envs/abc/bin/python"), &deps.pip_dependencies);
+        assert_eq!(&pip_args[4..], &["overturemaps", "lonboard==0.10"]);
+        assert!(!pip_args.iter().any(|a| a.contains("gdal")));
+    }
+
+    #[test]
+    fn test_pip_install_args_keep_order_and_split_requirement_files() {
+        let python = Path::new("/envs/abc/bin/python");
+        let args = pip_install_args(
+            python,
+            &[
+                "torch".to_string(),
+                "-r /project/requirements.txt".to_string(),
+                "requests >= 2".to_string(),
+            ],
+        );
+
+        assert_eq!(
+            &args[4..],
+            &["torch", "-r", "/project/requirements.txt", "requests >= 2"]
+        );
+    }
+
+    #[test]
+    fn test_pip_deps_roundtrip_and_default() {
+        let json = r#"{"dependencies":["numpy"],"python":null}"#;
+        let deps: CondaDependencies = serde_json::from_str(json).unwrap();
+        assert!(deps.pip_dependencies.is_empty());
+        assert!(!serde_json::to_string(&deps)
+            .unwrap()
+            .contains("pip_dependencies"));
+    }
 }
@@ -0,0 +1,305 @@
+//! Dependency trees of installed environments.
+//!
+//! Shows why a package is installed: each root is a package nothing else in
+//! the environment depends on, and its children are what it pulled in. UV
+//! environments are read from `uv pip tree`, conda environments from their
+//! `conda-meta` records.
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+/// A package in an environment's dependency tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DepNode {
+    /// Normalized package name.
+    pub name: String,
+    pub version: String,
+    /// Whether the notebook declares this package, as opposed to it being
+    /// pulled in by another one.
+    pub direct: bool,
+    /// Set on a package already shown elsewhere in the tree; its
+    /// dependencies are listed there instead of repeated here.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deduplicated: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<DepNode>,
+}
+
+/// Normalized package name of a requirement or match spec
+/// (`"Scikit_Learn>=1.0"` -> `"scikit-learn"`).
+pub fn package_name(spec: &str) -> String {
+    spec.trim()
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .collect::<String>()
+        .to_lowercase()
+        .replace('_', "-")
+}
+
+fn declared_names(declared: &[String]) -> HashSet<String> {
+    declared.iter().map(|spec| package_name(spec)).collect()
+}
+
+/// Split one level of tree drawing off the front of a line.
+fn strip_branch(line: &str) -> Option<&str> {
+    ["├── ", "└── ", "│   ", "    "]
+        .iter()
+        .find_map(|prefix| line.strip_prefix(prefix))
+}
+
+/// Parse `uv pip tree` (or `pipdeptree`-style) output into its roots.
+///
+/// Each line is `name vX.Y`, indented four columns of tree drawing per
+/// level. A trailing `(*)` marks a package whose subtree was already shown.
+/// Packages named in `declared` are marked direct.
+pub fn parse_pip_tree(output: &str, declared: &[String]) -> Vec<DepNode> {
+    let declared = declared_names(declared);
+    let mut roots = Vec::new();
+    // The path from a root to the most recently parsed package
+    let mut path: Vec<DepNode> = Vec::new();
+
+    for line in output.lines() {
+        let mut rest = line;
+        let mut depth = 0;
+        while let Some(stripped) = strip_branch(rest) {
+            rest = stripped;
+            depth += 1;
+        }
+
+        let mut tokens = rest.split_whitespace();
+        let (Some(name), Some(version)) = (tokens.next(), tokens.next()) else {
+            continue;
+        };
+        // Skips the "(*) Package tree already displayed" legend
+        let Some(version) = version.strip_prefix('v') else {
+            continue;
+        };
+        let name = package_name(name);
+        let node = DepNode {
+            direct: declared.contains(&name),
+            deduplicated: tokens.any(|token| token == "(*)"),
+            name,
+            version: version.to_string(),
+            dependencies: Vec::new(),
+        };
+
+        while path.len() > depth {
+            close_last(&mut path, &mut roots);
+        }
+        path.push(node);
+    }
+
+    while !path.is_empty() {
+        close_last(&mut path, &mut roots);
+    }
+    roots
+}
+
+/// Attach the last node on `path` to its parent, or to `roots` at the top.
+fn close_last(path: &mut Vec<DepNode>, roots: &mut Vec<DepNode>) {
+    if let Some(node) = path.pop() {
+        match path.last_mut() {
+            Some(parent) => parent.dependencies.push(node),
+            None => roots.push(node),
+        }
+    }
+}
+
+/// An installed package and the names of the packages it depends on.
+pub(crate) struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+    pub depends: Vec<String>,
+}
+
+/// Build the tree of installed `packages`. Roots are the packages nothing
+/// else depends on, plus one per dependency cycle nothing outside it reaches.
+/// Dependencies that aren't installed (virtual packages like `__glibc`) are
+/// left out, and a package reached a second time is marked deduplicated.
+pub(crate) fn tree_from_packages(
+    packages: Vec<InstalledPackage>,
+    declared: &[String],
+) -> Vec<DepNode> {
+    let declared = declared_names(declared);
+    let packages: BTreeMap<String, InstalledPackage> = packages
+        .into_iter()
+        .map(|package| (package_name(&package.name), package))
+        .collect();
+    let depended_on: HashSet<String> = packages
+        .values()
+        .flat_map(|package| package.depends.iter().map(|dep| package_name(dep)))
+        .collect();
+
+    let mut shown = HashSet::new();
+    let mut roots: Vec<DepNode> = packages
+        .keys()
+        .filter(|name| !depended_on.contains(*name))
+        .map(|name| expand(name, &packages, &declared, &mut shown))
+        .collect();
+    for name in packages.keys() {
+        if !shown.contains(name) {
+            roots.push(expand(name, &packages, &declared, &mut shown));
+        }
+    }
+    roots
+}
+
+fn expand(
+    name: &str,
+    packages: &BTreeMap<String, InstalledPackage>,
+    declared: &HashSet<String>,
+    shown: &mut HashSet<String>,
+) -> DepNode {
+    let package = &packages[name];
+    let first_time = shown.insert(name.to_string());
+    let dependencies = if first_time {
+        let mut names: Vec<String> = package
+            .depends
+            .iter()
+            .map(|dep| package_name(dep))
+            .filter(|dep| packages.contains_key(dep))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+            .iter()
+            .map(|dep| expand(dep, packages, declared, shown))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    DepNode {
+        name: name.to_string(),
+        version: package.version.clone(),
+        direct: declared.contains(name),
+        deduplicated: !first_time && !package.depends.is_empty(),
+        dependencies,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(name: &str, version: &str, direct: bool) -> DepNode {
+        DepNode {
+            name: name.to_string(),
+            version: version.to_string(),
+            direct,
+            deduplicated: false,
+            dependencies: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_package_name() {
+        assert_eq!(package_name("numpy"), "numpy");
+        assert_eq!(package_name("Scikit_Learn>=1.0"), "scikit-learn");
+        assert_eq!(
+            package_name(" pandas[excel] ; python_version>'3'"),
+            "pandas"
+        );
+        assert_eq!(package_name("zope.interface==6.0"), "zope.interface");
+    }
+
+    #[test]
+    fn test_parse_pip_tree_nesting_and_direct_flags() {
+        let output = "\
+pandas v2.2.0
+├── numpy v1.26.4
+├── python-dateutil v2.8.2
+│   └── six v1.16.0
+└── tzdata v2024.1
+requests v2.31.0
+├── certifi v2024.2.2
+└── urllib3 v2.2.0
+";
+        let declared = vec!["pandas>=2".to_string(), "NumPy".to_string()];
+        let tree = parse_pip_tree(output, &declared);
+
+        let mut dateutil = leaf("python-dateutil", "2.8.2", false);
+        dateutil.dependencies = vec![leaf("six", "1.16.0", false)];
+        let mut pandas = leaf("pandas", "2.2.0", true);
+        pandas.dependencies = vec![
+            leaf("numpy", "1.26.4", true),
+            dateutil,
+            leaf("tzdata", "2024.1", false),
+        ];
+        let mut requests = leaf("requests", "2.31.0", false);
+        requests.dependencies = vec![
+            leaf("certifi", "2024.2.2", false),
+            leaf("urllib3", "2.2.0", false),
+        ];
+        assert_eq!(tree, vec![pandas, requests]);
+    }
+
+    #[test]
+    fn test_parse_pip_tree_marks_repeated_subtrees() {
+        let output = "\
+ipykernel v6.29.0
+├── comm v0.2.1
+│   └── traitlets v5.14.1
+└── traitlets v5.14.1 (*)
+(*) Package tree already displayed
+";
+        let tree = parse_pip_tree(output, &[]);
+        assert_eq!(tree.len(), 1);
+        let deps = &tree[0].dependencies;
+        assert_eq!(
+            deps[0].dependencies,
+            vec![leaf("traitlets", "5.14.1", false)]
+        );
+        assert_eq!(deps[1].name, "traitlets");
+        assert!(deps[1].deduplicated);
+    }
+
+    #[test]
+    fn test_tree_from_packages() {
+        let package = |name: &str, depends: &[&str]| InstalledPackage {
+            name: name.to_string(),
+            version: "1.0".to_string(),
+            depends: depends.iter().map(|dep| dep.to_string()).collect(),
+        };
+        let tree = tree_from_packages(
+            vec![
+                package(
+                    "scipy",
+                    &["numpy >=1.23", "python >=3.10", "__glibc >=2.17"],
+                ),
+                package("numpy", &["python >=3.10"]),
+                package("python", &["pip"]),
+                package("pip", &["python >=3.8"]),
+            ],
+            &["scipy".to_string()],
+        );
+
+        // Only scipy has no dependents; python and pip reach each other
+        assert_eq!(tree.len(), 1);
+        let scipy = &tree[0];
+        assert!(scipy.direct);
+        let names: Vec<&str> = scipy.dependencies.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["numpy", "python"]);
+        let numpy = &scipy.dependencies[0];
+        assert!(!numpy.direct);
+        assert_eq!(numpy.dependencies[0].name, "python");
+        assert_eq!(numpy.dependencies[0].dependencies[0].name, "pip");
+        // python was expanded under numpy, so scipy's python is a repeat
+        assert!(scipy.dependencies[1].deduplicated);
+        assert!(scipy.dependencies[1].dependencies.is_empty());
+    }
+
+    #[test]
+    fn test_tree_from_packages_keeps_unreached_cycles() {
+        let package = |name: &str, depends: &[&str]| InstalledPackage {
+            name: name.to_string(),
+            version: "1.0".to_string(),
+            depends: depends.iter().map(|dep| dep.to_string()).collect(),
+        };
+        let tree = tree_from_packages(vec![package("a", &["b"]), package("b", &["a"])], &[]);
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].name, "a");
+        assert_eq!(tree[0].dependencies[0].name, "b");
+        assert!(tree[0].dependencies[0].dependencies[0].deduplicated);
+    }
+}
@@ -25,10 +25,13 @@
 //! ```
 
 pub mod conda;
+pub mod dep_tree;
+pub mod package_names;
 pub mod progress;
 pub mod uv;
 
 // Re-export key types
-pub use conda::{CondaDependencies, CondaEnvironment};
-pub use progress::{EnvProgressPhase, LogHandler, ProgressHandler};
-pub use uv::{UvDependencies, UvEnvironment};
+pub use conda::{ChannelPriority, CondaDependencies, CondaEnvironment};
+pub use dep_tree::DepNode;
+pub use progress::{EnvProgressPhase, LogHandler, LogStream, ProgressHandler};
+pub use uv::{SyncReport, UvDependencies, UvEnvironment};
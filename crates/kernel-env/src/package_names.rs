@@ -0,0 +1,54 @@
+//! Packages published under different names on PyPI and conda-forge.
+//!
+//! Most Python packages share a name across the two, so only the renamed
+//! ones are listed. PyPI names are normalized: lowercase, with `-`
+//! separators.
+
+/// PyPI name → conda-forge name. When several PyPI names map to one conda
+/// package, the first is used in the other direction.
+pub const PYPI_TO_CONDA: &[(&str, &str)] = &[
+    ("opencv-python", "opencv"),
+    ("opencv-python-headless", "opencv"),
+    ("opencv-contrib-python", "opencv"),
+    ("torch", "pytorch"),
+    ("tables", "pytables"),
+    ("msgpack", "msgpack-python"),
+    ("pyqt5", "pyqt"),
+    ("psycopg2-binary", "psycopg2"),
+    ("graphviz", "python-graphviz"),
+    ("kaleido", "python-kaleido"),
+    ("duckdb", "python-duckdb"),
+    ("typing-extensions", "typing_extensions"),
+    ("ruamel-yaml", "ruamel.yaml"),
+];
+
+/// The conda-forge name of the PyPI package `pypi_name`, if it differs.
+pub fn conda_rename(pypi_name: &str) -> Option<&'static str> {
+    PYPI_TO_CONDA
+        .iter()
+        .find(|(pypi, _)| *pypi == pypi_name)
+        .map(|(_, conda)| *conda)
+}
+
+/// The PyPI name of the conda-forge package `conda_name`, if it differs.
+pub fn pypi_rename(conda_name: &str) -> Option<&'static str> {
+    PYPI_TO_CONDA
+        .iter()
+        .find(|(_, conda)| *conda == conda_name)
+        .map(|(pypi, _)| *pypi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renames_both_ways() {
+        assert_eq!(conda_rename("torch"), Some("pytorch"));
+        assert_eq!(conda_rename("numpy"), None);
+        // Several PyPI names share a conda package; the first wins
+        assert_eq!(conda_rename("opencv-python-headless"), Some("opencv"));
+        assert_eq!(pypi_rename("opencv"), Some("opencv-python"));
+        assert_eq!(pypi_rename("numpy"), None);
+    }
+}
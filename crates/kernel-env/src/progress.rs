@@ -70,6 +70,17 @@ pub enum EnvProgressPhase {
     CreatingVenv,
     /// Installing pip packages (UV-specific).
     InstallingPackages { packages: Vec<String> },
+    /// A package was installed into a running environment.
+    PackageInstalled { package: String },
+    /// A package was uninstalled from a running environment.
+    PackageRemoved { package: String },
+    /// A package couldn't be installed into or removed from a running
+    /// environment. Other packages may still have synced.
+    PackageFailed { package: String, message: String },
+    /// Copying an existing environment to a new location.
+    CopyProgress { bytes_copied: u64, bytes_total: u64 },
+    /// Environment copy was cancelled; the partial copy has been removed.
+    CopyCancelled,
     /// Environment is ready.
     Ready {
         env_path: String,
@@ -88,6 +99,75 @@ pub trait ProgressHandler: Send + Sync {
     ///
     /// `env_type` is `"conda"` or `"uv"`.
     fn on_progress(&self, env_type: &str, phase: EnvProgressPhase);
+
+    /// Called for each line a build subprocess (uv) writes, as it's written.
+    ///
+    /// Lets consumers show a live build console. Ignored by default.
+    fn on_log_line(&self, _env_type: &str, _stream: LogStream, _line: &str) {}
+}
+
+/// Which output stream of a build subprocess a log line came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// Run `command` to completion like [`tokio::process::Command::output`],
+/// passing each line it writes to [`ProgressHandler::on_log_line`] as it
+/// arrives.
+///
+/// Each stream's lines are delivered in order. The returned output still
+/// holds everything written, so callers can report the full stderr when the
+/// command fails.
+pub async fn output_with_log(
+    command: &mut tokio::process::Command,
+    env_type: &str,
+    handler: &dyn ProgressHandler,
+) -> std::io::Result<std::process::Output> {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    let mut child = command
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+    let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        return Err(std::io::Error::other("subprocess output was not piped"));
+    };
+    let mut stdout_lines = BufReader::new(stdout).split(b'\n');
+    let mut stderr_lines = BufReader::new(stderr).split(b'\n');
+
+    let mut output = std::process::Output {
+        status: Default::default(),
+        stdout: Vec::new(),
+        stderr: Vec::new(),
+    };
+    let (mut stdout_open, mut stderr_open) = (true, true);
+    while stdout_open || stderr_open {
+        let (stream, line) = tokio::select! {
+            line = stdout_lines.next_segment(), if stdout_open => (LogStream::Stdout, line?),
+            line = stderr_lines.next_segment(), if stderr_open => (LogStream::Stderr, line?),
+        };
+        let Some(mut line) = line else {
+            match stream {
+                LogStream::Stdout => stdout_open = false,
+                LogStream::Stderr => stderr_open = false,
+            }
+            continue;
+        };
+
+        let text = String::from_utf8_lossy(&line);
+        handler.on_log_line(env_type, stream, text.trim_end_matches('\r'));
+        line.push(b'\n');
+        match stream {
+            LogStream::Stdout => output.stdout.extend_from_slice(&line),
+            LogStream::Stderr => output.stderr.extend_from_slice(&line),
+        }
+    }
+
+    output.status = child.wait().await?;
+    Ok(output)
 }
 
 /// Log-only progress handler.
@@ -144,6 +224,24 @@ impl ProgressHandler for LogHandler {
             EnvProgressPhase::InstallingPackages { packages } => {
                 log::info!("[{env_type}] Installing packages: {packages:?}");
             }
+            EnvProgressPhase::PackageInstalled { package } => {
+                log::info!("[{env_type}] Installed {package}");
+            }
+            EnvProgressPhase::PackageRemoved { package } => {
+                log::info!("[{env_type}] Removed {package}");
+            }
+            EnvProgressPhase::PackageFailed { package, message } => {
+                log::warn!("[{env_type}] Failed to sync {package}: {message}");
+            }
+            EnvProgressPhase::CopyProgress {
+                bytes_copied,
+                bytes_total,
+            } => {
+                log::debug!("[{env_type}] Copied {bytes_copied}/{bytes_total} bytes");
+            }
+            EnvProgressPhase::CopyCancelled => {
+                log::info!("[{env_type}] Environment copy cancelled");
+            }
             EnvProgressPhase::Ready {
                 env_path,
                 python_path,
@@ -155,6 +253,10 @@ impl ProgressHandler for LogHandler {
             }
         }
     }
+
+    fn on_log_line(&self, env_type: &str, _stream: LogStream, line: &str) {
+        log::debug!("[{env_type}] {line}");
+    }
 }
 
 /// Rattler [`Reporter`] implementation that delegates to [`ProgressHandler`].
@@ -388,3 +490,50 @@ impl Reporter for RattlerReporter {
 
     fn on_pre_unlink_complete(&self, _index: usize, _success: bool) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct LineRecorder(Mutex<Vec<(LogStream, String)>>);
+
+    impl ProgressHandler for LineRecorder {
+        fn on_progress(&self, _env_type: &str, _phase: EnvProgressPhase) {}
+
+        fn on_log_line(&self, _env_type: &str, stream: LogStream, line: &str) {
+            self.0.lock().unwrap().push((stream, line.to_string()));
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_output_with_log_tees_each_line() {
+        let recorder = LineRecorder::default();
+        let output = output_with_log(
+            tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg("echo one; echo oops >&2; echo two; printf 'no newline' >&2; exit 3"),
+            "uv",
+            &recorder,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"one\ntwo\n");
+        assert_eq!(output.stderr, b"oops\nno newline\n");
+
+        let lines = recorder.0.into_inner().unwrap();
+        let of = |stream| {
+            lines
+                .iter()
+                .filter(|(s, _)| *s == stream)
+                .map(|(_, line)| line.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(of(LogStream::Stdout), ["one", "two"]);
+        assert_eq!(of(LogStream::Stderr), ["oops", "no newline"]);
+    }
+}
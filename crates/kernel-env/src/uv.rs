@@ -2,18 +2,21 @@
 //!
 //! Creates, caches, and prewarms UV virtual environments for Jupyter kernels.
 //! Environments are keyed by a SHA-256 hash of (dependencies + requires-python
-//! + env_id) and stored under the cache directory. UV is auto-bootstrapped via
+//! + index-url + env_id) and stored under the cache directory. UV is auto-bootstrapped via
 //!   rattler if not found on PATH.
 
 use anyhow::{anyhow, Result};
 use log::info;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use crate::progress::{EnvProgressPhase, ProgressHandler};
+use crate::dep_tree::{package_name, parse_pip_tree, DepNode};
+use crate::progress::{output_with_log, EnvProgressPhase, ProgressHandler};
 
 /// UV dependency specification.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +24,9 @@ pub struct UvDependencies {
     pub dependencies: Vec<String>,
     #[serde(rename = "requires-python")]
     pub requires_python: Option<String>,
+    /// Package index to install from instead of PyPI.
+    #[serde(rename = "index-url", default, skip_serializing_if = "Option::is_none")]
+    pub index_url: Option<String>,
 }
 
 /// A resolved UV virtual environment on disk.
@@ -72,6 +78,12 @@ pub fn compute_env_hash(deps: &UvDependencies, env_id: Option<&str>) -> String {
         hasher.update(py.as_bytes());
     }
 
+    // Only hashed when set, so envs built against PyPI keep their hashes
+    if let Some(ref url) = deps.index_url {
+        hasher.update(b"index-url:");
+        hasher.update(url.as_bytes());
+    }
+
     let hash = hasher.finalize();
     format!("{:x}", hash)[..16].to_string()
 }
@@ -163,11 +175,7 @@ pub async fn prepare_environment_in(
         }
     }
 
-    let venv_output = venv_cmd
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await?;
+    let venv_output = output_with_log(&mut venv_cmd, "uv", handler.as_ref()).await?;
 
     if !venv_output.status.success() {
         let stderr = String::from_utf8_lossy(&venv_output.stderr);
@@ -203,12 +211,12 @@ pub async fn prepare_environment_in(
         },
     );
 
-    let install_output = tokio::process::Command::new(&uv_path)
-        .args(&install_args)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await?;
+    let mut install_cmd = tokio::process::Command::new(&uv_path);
+    install_cmd.args(&install_args);
+    if let Some(ref url) = deps.index_url {
+        install_cmd.arg("--index-url").arg(url);
+    }
+    let install_output = output_with_log(&mut install_cmd, "uv", handler.as_ref()).await?;
 
     if !install_output.status.success() {
         tokio::fs::remove_dir_all(&venv_path).await.ok();
@@ -238,39 +246,322 @@ pub async fn prepare_environment_in(
     })
 }
 
-/// Install additional dependencies into an existing environment.
-pub async fn sync_dependencies(env: &UvEnvironment, deps: &[String]) -> Result<()> {
-    if deps.is_empty() {
-        return Ok(());
+/// Describe why a cached environment can't host a kernel, or `None` if it can.
+///
+/// An environment is broken when its interpreter is missing or can't import
+/// `ipykernel`, e.g. after the host Python it was created from went away.
+pub async fn diagnose_environment(env: &UvEnvironment) -> Option<String> {
+    diagnose_interpreter(&env.python_path).await
+}
+
+/// Check that `python_path` exists and can import ipykernel.
+pub(crate) async fn diagnose_interpreter(python_path: &Path) -> Option<String> {
+    if !python_path.exists() {
+        return Some(format!("interpreter missing at {:?}", python_path));
+    }
+
+    match tokio::process::Command::new(python_path)
+        .args(["-c", "import ipykernel"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+    {
+        Ok(output) if output.status.success() => None,
+        Ok(output) => Some(format!(
+            "ipykernel failed to import: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(e) => Some(format!("failed to run {:?}: {}", python_path, e)),
+    }
+}
+
+/// Rebuild a cached environment in place if it is broken.
+///
+/// Returns `Ok(false)` if the environment was healthy and left alone, or
+/// `Ok(true)` once a broken one has been removed and recreated from `deps`.
+pub async fn repair_environment(
+    deps: &UvDependencies,
+    env_id: Option<&str>,
+    handler: Arc<dyn ProgressHandler>,
+) -> Result<bool> {
+    repair_environment_in(deps, env_id, &default_cache_dir_uv(), handler).await
+}
+
+/// Like [`repair_environment`] but with an explicit cache directory.
+pub async fn repair_environment_in(
+    deps: &UvDependencies,
+    env_id: Option<&str>,
+    cache_dir: &Path,
+    handler: Arc<dyn ProgressHandler>,
+) -> Result<bool> {
+    let venv_path = cache_dir.join(compute_env_hash(deps, env_id));
+
+    #[cfg(target_os = "windows")]
+    let python_path = venv_path.join("Scripts").join("python.exe");
+    #[cfg(not(target_os = "windows"))]
+    let python_path = venv_path.join("bin").join("python");
+
+    let Some(problem) = diagnose_interpreter(&python_path).await else {
+        info!(
+            "Environment at {:?} is healthy, nothing to repair",
+            venv_path
+        );
+        return Ok(false);
+    };
+
+    log::warn!(
+        "Environment at {:?} is broken ({}), rebuilding",
+        venv_path,
+        problem
+    );
+    if venv_path.exists() {
+        tokio::fs::remove_dir_all(&venv_path).await?;
     }
+    prepare_environment_in(deps, env_id, cache_dir, handler).await?;
 
-    info!("Syncing {} dependencies to {:?}", deps.len(), env.venv_path);
+    Ok(true)
+}
+
+/// What [`sync_dependencies`] changed in an environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncReport {
+    /// Packages that were installed.
+    pub installed: Vec<String>,
+    /// Packages that couldn't be installed or removed, with uv's error.
+    pub failed: Vec<(String, String)>,
+    /// Packages dropped from the environment. One that a remaining package
+    /// still depends on stays installed.
+    pub removed: Vec<String>,
+}
 
+impl SyncReport {
+    /// Whether nothing changed because every package failed.
+    pub fn all_failed(&self) -> bool {
+        !self.failed.is_empty() && self.installed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Install `added` into and uninstall `removed` from an existing environment.
+///
+/// Added packages are installed in one go. If that fails, each is retried on
+/// its own so one bad package doesn't hold back the rest. Removed packages
+/// that something left in the environment still depends on aren't
+/// uninstalled, so the running kernel keeps working. The report says
+/// which packages changed and why the others failed, and each outcome is
+/// sent to `handler` as it happens. Only a failure to run uv is an error.
+pub async fn sync_dependencies(
+    env: &UvEnvironment,
+    added: &[String],
+    removed: &[String],
+    handler: Arc<dyn ProgressHandler>,
+) -> Result<SyncReport> {
+    if added.is_empty() && removed.is_empty() {
+        return Ok(SyncReport::default());
+    }
     let uv_path = kernel_launch::tools::get_uv_path().await?;
+    sync_dependencies_with(&uv_path, env, added, removed, handler.as_ref()).await
+}
 
-    let mut install_args = vec![
-        "pip".to_string(),
-        "install".to_string(),
-        "--python".to_string(),
-        env.python_path.to_string_lossy().to_string(),
-    ];
+/// Run `uv pip <command>` on `packages`. The outer error is a failure to run
+/// uv; the inner one is uv's stderr when it exits unsuccessfully.
+async fn uv_pip(
+    uv_path: &Path,
+    env: &UvEnvironment,
+    command: &str,
+    packages: &[String],
+) -> Result<std::result::Result<(), String>> {
+    let output = tokio::process::Command::new(uv_path)
+        .arg("pip")
+        .arg(command)
+        .arg("--python")
+        .arg(&env.python_path)
+        .args(packages)
+        .output()
+        .await?;
 
-    for dep in deps {
-        install_args.push(dep.clone());
+    if output.status.success() {
+        Ok(Ok(()))
+    } else {
+        Ok(Err(String::from_utf8_lossy(&output.stderr)
+            .trim()
+            .to_string()))
+    }
+}
+
+async fn sync_dependencies_with(
+    uv_path: &Path,
+    env: &UvEnvironment,
+    added: &[String],
+    removed: &[String],
+    handler: &dyn ProgressHandler,
+) -> Result<SyncReport> {
+    info!(
+        "Syncing dependencies to {:?}: +{} -{}",
+        env.venv_path,
+        added.len(),
+        removed.len()
+    );
+    let mut report = SyncReport::default();
+
+    if !added.is_empty() {
+        handler.on_progress(
+            "uv",
+            EnvProgressPhase::InstallingPackages {
+                packages: added.to_vec(),
+            },
+        );
+
+        let outcomes = match uv_pip(uv_path, env, "install", added).await? {
+            Ok(()) => added.iter().map(|dep| (dep, Ok(()))).collect(),
+            Err(error) if added.len() == 1 => vec![(&added[0], Err(error))],
+            Err(error) => {
+                log::warn!("Installing packages together failed, retrying one by one: {error}");
+                let mut outcomes = Vec::new();
+                for dep in added {
+                    let outcome =
+                        uv_pip(uv_path, env, "install", std::slice::from_ref(dep)).await?;
+                    outcomes.push((dep, outcome));
+                }
+                outcomes
+            }
+        };
+
+        for (dep, outcome) in outcomes {
+            match outcome {
+                Ok(()) => {
+                    handler.on_progress(
+                        "uv",
+                        EnvProgressPhase::PackageInstalled {
+                            package: dep.clone(),
+                        },
+                    );
+                    report.installed.push(dep.clone());
+                }
+                Err(error) => {
+                    handler.on_progress(
+                        "uv",
+                        EnvProgressPhase::PackageFailed {
+                            package: dep.clone(),
+                            message: error.clone(),
+                        },
+                    );
+                    report.failed.push((dep.clone(), error));
+                }
+            }
+        }
+    }
+
+    let required = if removed.is_empty() {
+        HashSet::new()
+    } else {
+        let tree = dependency_tree_with(uv_path, env, &[]).await?;
+        still_required(&tree, removed)
+    };
+
+    for dep in removed {
+        // `uv pip uninstall` takes bare package names
+        let name = package_name(dep);
+        if required.contains(&name) {
+            info!("Keeping {name} installed, other packages depend on it");
+            report.removed.push(dep.clone());
+            continue;
+        }
+        match uv_pip(uv_path, env, "uninstall", &[name]).await? {
+            Ok(()) => {
+                handler.on_progress(
+                    "uv",
+                    EnvProgressPhase::PackageRemoved {
+                        package: dep.clone(),
+                    },
+                );
+                report.removed.push(dep.clone());
+            }
+            Err(error) => {
+                handler.on_progress(
+                    "uv",
+                    EnvProgressPhase::PackageFailed {
+                        package: dep.clone(),
+                        message: error.clone(),
+                    },
+                );
+                report.failed.push((dep.clone(), error));
+            }
+        }
+    }
+
+    info!(
+        "Dependency sync finished: {} installed, {} removed, {} failed",
+        report.installed.len(),
+        report.removed.len(),
+        report.failed.len()
+    );
+    Ok(report)
+}
+
+/// Names of the `removed` packages that a package staying in the
+/// environment depends on, directly or through other kept packages.
+fn still_required(tree: &[DepNode], removed: &[String]) -> HashSet<String> {
+    fn collect_edges<'a>(node: &'a DepNode, edges: &mut Vec<(&'a str, &'a str)>) {
+        for dep in &node.dependencies {
+            edges.push((&node.name, &dep.name));
+            collect_edges(dep, edges);
+        }
+    }
+
+    let mut edges = Vec::new();
+    for root in tree {
+        collect_edges(root, &mut edges);
+    }
+    let removed: HashSet<String> = removed.iter().map(|dep| package_name(dep)).collect();
+
+    // A removed package is kept once anything kept depends on it, which can
+    // in turn keep its own dependencies
+    let mut required = HashSet::new();
+    loop {
+        let before = required.len();
+        for (parent, child) in &edges {
+            let parent_kept = !removed.contains(*parent) || required.contains(*parent);
+            if parent_kept && removed.contains(*child) {
+                required.insert(child.to_string());
+            }
+        }
+        if required.len() == before {
+            return required;
+        }
     }
+}
 
-    let output = tokio::process::Command::new(&uv_path)
-        .args(&install_args)
+/// The dependency tree of an environment, from `uv pip tree`. Packages
+/// named in `declared` (the notebook's dependencies) are marked direct.
+pub async fn dependency_tree(env: &UvEnvironment, declared: &[String]) -> Result<Vec<DepNode>> {
+    let uv_path = kernel_launch::tools::get_uv_path().await?;
+    dependency_tree_with(&uv_path, env, declared).await
+}
+
+async fn dependency_tree_with(
+    uv_path: &Path,
+    env: &UvEnvironment,
+    declared: &[String],
+) -> Result<Vec<DepNode>> {
+    let output = tokio::process::Command::new(uv_path)
+        .arg("pip")
+        .arg("tree")
+        .arg("--python")
+        .arg(&env.python_path)
         .output()
         .await?;
 
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("Failed to sync dependencies: {}", stderr));
+        return Err(anyhow!(
+            "uv pip tree failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
     }
-
-    info!("Dependencies synced successfully");
-    Ok(())
+    Ok(parse_pip_tree(
+        &String::from_utf8_lossy(&output.stdout),
+        declared,
+    ))
 }
 
 /// Create a prewarmed environment with ipykernel, ipywidgets, and
@@ -310,13 +601,14 @@ pub async fn create_prewarmed_environment_in(
 
     handler.on_progress("uv", EnvProgressPhase::CreatingVenv);
 
-    let venv_output = tokio::process::Command::new(&uv_path)
-        .arg("venv")
-        .arg(&venv_path)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await?;
+    let venv_output = output_with_log(
+        tokio::process::Command::new(&uv_path)
+            .arg("venv")
+            .arg(&venv_path),
+        "uv",
+        handler.as_ref(),
+    )
+    .await?;
 
     if !venv_output.status.success() {
         let stderr = String::from_utf8_lossy(&venv_output.stderr);
@@ -348,12 +640,12 @@ pub async fn create_prewarmed_environment_in(
         },
     );
 
-    let install_output = tokio::process::Command::new(&uv_path)
-        .args(&install_args)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await?;
+    let install_output = output_with_log(
+        tokio::process::Command::new(&uv_path).args(&install_args),
+        "uv",
+        handler.as_ref(),
+    )
+    .await?;
 
     if !install_output.status.success() {
         tokio::fs::remove_dir_all(&venv_path).await.ok();
@@ -404,6 +696,7 @@ pub async fn claim_prewarmed_environment_in(
     let deps = UvDependencies {
         dependencies: vec![],
         requires_python: None,
+        index_url: None,
     };
     let hash = compute_env_hash(&deps, Some(env_id));
     let dest_path = cache_dir.join(&hash);
@@ -436,7 +729,13 @@ pub async fn claim_prewarmed_environment_in(
         }
         Err(e) => {
             info!("[prewarm] Rename failed ({}), falling back to copy", e);
-            copy_dir_recursive(&prewarmed.venv_path, &dest_path).await?;
+            copy_dir_recursive(
+                &prewarmed.venv_path,
+                &dest_path,
+                None,
+                &AtomicBool::new(false),
+            )
+            .await?;
             tokio::fs::remove_dir_all(&prewarmed.venv_path).await.ok();
             info!("[prewarm] Environment claimed via copy");
         }
@@ -546,22 +845,53 @@ pub fn is_environment_warmed(env: &UvEnvironment) -> bool {
     env.venv_path.join(".warmed").exists()
 }
 
-/// Copy an existing UV environment to a new location.
-pub async fn copy_environment(source: &UvEnvironment, new_env_id: &str) -> Result<UvEnvironment> {
-    let cache_dir = default_cache_dir_uv();
-    let dest_path = cache_dir.join(new_env_id);
+/// Copy an existing UV environment to where launch looks up `deps` for
+/// `new_env_id`.
+///
+/// Streams [`EnvProgressPhase::CopyProgress`] through `handler` and checks
+/// `cancel` between files. Returns `Ok(None)` if the copy was cancelled, in
+/// which case the partial copy is removed and the caller should create a
+/// fresh environment instead.
+pub async fn copy_environment(
+    source: &UvEnvironment,
+    deps: &UvDependencies,
+    new_env_id: &str,
+    handler: Arc<dyn ProgressHandler>,
+    cancel: &AtomicBool,
+) -> Result<Option<UvEnvironment>> {
+    copy_environment_in(
+        source,
+        deps,
+        new_env_id,
+        &default_cache_dir_uv(),
+        handler,
+        cancel,
+    )
+    .await
+}
+
+/// Like [`copy_environment`] but with an explicit cache directory.
+pub async fn copy_environment_in(
+    source: &UvEnvironment,
+    deps: &UvDependencies,
+    new_env_id: &str,
+    cache_dir: &Path,
+    handler: Arc<dyn ProgressHandler>,
+    cancel: &AtomicBool,
+) -> Result<Option<UvEnvironment>> {
+    let dest_path = cache_dir.join(compute_env_hash(deps, Some(new_env_id)));
+
+    #[cfg(target_os = "windows")]
+    let python_path = dest_path.join("Scripts").join("python.exe");
+    #[cfg(not(target_os = "windows"))]
+    let python_path = dest_path.join("bin").join("python");
 
     if dest_path.exists() {
         info!("Clone environment already exists at {:?}", dest_path);
-        #[cfg(target_os = "windows")]
-        let python_path = dest_path.join("Scripts").join("python.exe");
-        #[cfg(not(target_os = "windows"))]
-        let python_path = dest_path.join("bin").join("python");
-
-        return Ok(UvEnvironment {
+        return Ok(Some(UvEnvironment {
             venv_path: dest_path,
             python_path,
-        });
+        }));
     }
 
     info!(
@@ -569,19 +899,67 @@ pub async fn copy_environment(source: &UvEnvironment, new_env_id: &str) -> Resul
         source.venv_path, dest_path
     );
 
-    copy_dir_recursive(&source.venv_path, &dest_path).await?;
+    let mut progress = CopyProgress {
+        bytes_copied: 0,
+        bytes_total: dir_size(&source.venv_path).await?,
+        last_reported: 0,
+        handler: handler.as_ref(),
+    };
+    progress.report();
+
+    let copied = match copy_dir_recursive(
+        &source.venv_path,
+        &dest_path,
+        Some(&mut progress),
+        cancel,
+    )
+    .await
+    {
+        Ok(copied) => copied,
+        Err(e) => {
+            tokio::fs::remove_dir_all(&dest_path).await.ok();
+            return Err(e);
+        }
+    };
 
-    #[cfg(target_os = "windows")]
-    let python_path = dest_path.join("Scripts").join("python.exe");
-    #[cfg(not(target_os = "windows"))]
-    let python_path = dest_path.join("bin").join("python");
+    if !copied {
+        info!("Environment copy cancelled, removing {:?}", dest_path);
+        tokio::fs::remove_dir_all(&dest_path).await.ok();
+        handler.on_progress("uv", EnvProgressPhase::CopyCancelled);
+        return Ok(None);
+    }
+
+    progress.report();
+
+    // A byte-for-byte copy still points at the source prefix, so fix it up and
+    // make sure the interpreter actually starts before handing the env out.
+    let relocated = match relocate_environment(&source.venv_path, &dest_path).await {
+        Ok(()) => validate_interpreter(&python_path).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = relocated {
+        log::warn!(
+            "Copied environment at {:?} is unusable, removing: {}",
+            dest_path,
+            e
+        );
+        tokio::fs::remove_dir_all(&dest_path).await.ok();
+        return Err(e);
+    }
 
+    handler.on_progress(
+        "uv",
+        EnvProgressPhase::Ready {
+            env_path: dest_path.to_string_lossy().to_string(),
+            python_path: python_path.to_string_lossy().to_string(),
+        },
+    );
     info!("Environment copied successfully");
 
-    Ok(UvEnvironment {
+    Ok(Some(UvEnvironment {
         venv_path: dest_path,
         python_path,
-    })
+    }))
 }
 
 /// No-op cleanup (cached environments are kept for reuse).
@@ -608,18 +986,165 @@ pub async fn clear_cache() -> Result<()> {
     Ok(())
 }
 
+/// Rewrite absolute references to `src` inside a copied venv at `dst`.
+///
+/// Covers `pyvenv.cfg` (including a `prompt` named after the source
+/// directory) and text scripts in the venv's bin directory, whose shebangs
+/// and activate scripts embed the venv prefix.
+async fn relocate_environment(src: &Path, dst: &Path) -> Result<()> {
+    let src_prefix = src.to_string_lossy().to_string();
+    let dst_prefix = dst.to_string_lossy().to_string();
+
+    let cfg_path = dst.join("pyvenv.cfg");
+    if let Ok(cfg) = tokio::fs::read_to_string(&cfg_path).await {
+        let src_name = src.file_name().map(|n| n.to_string_lossy().to_string());
+        let dst_name = dst
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let mut rewritten = String::with_capacity(cfg.len());
+        for line in cfg.lines() {
+            match line.split_once('=') {
+                Some((key, value))
+                    if key.trim() == "prompt" && Some(value.trim()) == src_name.as_deref() =>
+                {
+                    rewritten.push_str(&format!("{} = {}", key.trim(), dst_name));
+                }
+                _ => rewritten.push_str(&line.replace(&src_prefix, &dst_prefix)),
+            }
+            rewritten.push('\n');
+        }
+        tokio::fs::write(&cfg_path, rewritten).await?;
+    }
+
+    #[cfg(target_os = "windows")]
+    let bin_dir = dst.join("Scripts");
+    #[cfg(not(target_os = "windows"))]
+    let bin_dir = dst.join("bin");
+
+    let mut entries = match tokio::fs::read_dir(&bin_dir).await {
+        Ok(entries) => entries,
+        Err(_) => return Ok(()),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        if !entry.file_type().await?.is_file() {
+            continue;
+        }
+        // Binary launchers aren't valid UTF-8 and are left alone
+        let Ok(content) = tokio::fs::read_to_string(entry.path()).await else {
+            continue;
+        };
+        if content.contains(&src_prefix) {
+            tokio::fs::write(entry.path(), content.replace(&src_prefix, &dst_prefix)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that a copied environment's interpreter starts.
+async fn validate_interpreter(python_path: &Path) -> Result<()> {
+    let output = tokio::process::Command::new(python_path)
+        .args(["-c", "import sys"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| anyhow!("Failed to run {:?}: {}", python_path, e))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Copied environment's python failed to start: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Minimum number of bytes between two copy progress events.
+const COPY_PROGRESS_INTERVAL: u64 = 8 * 1024 * 1024;
+
+/// Running byte count for [`copy_environment`], throttled to
+/// [`COPY_PROGRESS_INTERVAL`].
+struct CopyProgress<'a> {
+    bytes_copied: u64,
+    bytes_total: u64,
+    last_reported: u64,
+    handler: &'a dyn ProgressHandler,
+}
+
+impl CopyProgress<'_> {
+    fn add(&mut self, bytes: u64) {
+        self.bytes_copied += bytes;
+        if self.bytes_copied - self.last_reported >= COPY_PROGRESS_INTERVAL {
+            self.report();
+        }
+    }
+
+    fn report(&mut self) {
+        self.last_reported = self.bytes_copied;
+        self.handler.on_progress(
+            "uv",
+            EnvProgressPhase::CopyProgress {
+                bytes_copied: self.bytes_copied,
+                bytes_total: self.bytes_total,
+            },
+        );
+    }
+}
+
+/// Total size of regular files under `path` (symlinks are not followed).
+async fn dir_size(path: &Path) -> Result<u64> {
+    let mut total = 0;
+    let mut entries = tokio::fs::read_dir(path).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        let ty = entry.file_type().await?;
+        if ty.is_dir() {
+            total += Box::pin(dir_size(&entry.path())).await?;
+        } else if ty.is_file() {
+            total += entry.metadata().await?.len();
+        }
+    }
+
+    Ok(total)
+}
+
 /// Recursively copy a directory, preserving symlinks.
-async fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
+///
+/// Reports copied bytes to `progress` if given. Returns `Ok(false)` if
+/// `cancel` was set before the copy finished.
+async fn copy_dir_recursive(
+    src: &Path,
+    dst: &Path,
+    mut progress: Option<&mut CopyProgress<'_>>,
+    cancel: &AtomicBool,
+) -> Result<bool> {
     tokio::fs::create_dir_all(dst).await?;
     let mut entries = tokio::fs::read_dir(src).await?;
 
     while let Some(entry) = entries.next_entry().await? {
+        if cancel.load(Ordering::SeqCst) {
+            return Ok(false);
+        }
+
         let ty = entry.file_type().await?;
         let src_path = entry.path();
         let dst_path = dst.join(entry.file_name());
 
         if ty.is_dir() {
-            Box::pin(copy_dir_recursive(&src_path, &dst_path)).await?;
+            if !Box::pin(copy_dir_recursive(
+                &src_path,
+                &dst_path,
+                progress.as_deref_mut(),
+                cancel,
+            ))
+            .await?
+            {
+                return Ok(false);
+            }
         } else if ty.is_symlink() {
             #[cfg(unix)]
             {
@@ -629,22 +1154,238 @@ async fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
             #[cfg(windows)]
             tokio::fs::copy(&src_path, &dst_path).await?;
         } else {
-            tokio::fs::copy(&src_path, &dst_path).await?;
+            let bytes = tokio::fs::copy(&src_path, &dst_path).await?;
+            if let Some(progress) = progress.as_deref_mut() {
+                progress.add(bytes);
+            }
         }
     }
 
-    Ok(())
+    Ok(true)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::progress::LogHandler;
+    use std::sync::Mutex;
+
+    /// Collects every progress phase for assertions.
+    #[derive(Default)]
+    struct RecordingHandler(Mutex<Vec<EnvProgressPhase>>);
+
+    impl ProgressHandler for RecordingHandler {
+        fn on_progress(&self, _env_type: &str, phase: EnvProgressPhase) {
+            self.0.lock().unwrap().push(phase);
+        }
+    }
+
+    fn no_deps() -> UvDependencies {
+        UvDependencies {
+            dependencies: vec![],
+            requires_python: None,
+            index_url: None,
+        }
+    }
+
+    /// Where the "clone-1" copy of an env with no deps lands.
+    fn clone_path(cache_dir: &Path) -> PathBuf {
+        cache_dir.join(compute_env_hash(&no_deps(), Some("clone-1")))
+    }
+
+    /// A venv-shaped directory whose `bin/python` is a shell script exiting
+    /// with `python_exit_code`.
+    #[cfg(unix)]
+    fn fake_venv(root: &Path, python_exit_code: i32) -> UvEnvironment {
+        use std::os::unix::fs::PermissionsExt;
+
+        let venv_path = root.join("source-env");
+        let venv = venv_path.display();
+        std::fs::create_dir_all(venv_path.join("bin")).unwrap();
+        std::fs::create_dir_all(venv_path.join("lib/site-packages/pkg")).unwrap();
+
+        let python = venv_path.join("bin/python");
+        std::fs::write(&python, format!("#!/bin/sh\nexit {python_exit_code}\n")).unwrap();
+        std::fs::set_permissions(&python, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(
+            venv_path.join("bin/tool"),
+            format!("#!{venv}/bin/python\nimport tool\n"),
+        )
+        .unwrap();
+        std::fs::write(
+            venv_path.join("pyvenv.cfg"),
+            format!("home = /usr/bin\nprompt = source-env\ncommand = uv venv {venv}\n"),
+        )
+        .unwrap();
+        std::fs::write(
+            venv_path.join("lib/site-packages/pkg/__init__.py"),
+            "x = 1\n",
+        )
+        .unwrap();
+        UvEnvironment {
+            python_path: python,
+            venv_path,
+        }
+    }
+
+    /// A stand-in for uv that logs each call, fails to install any package
+    /// starting with `bad-`, and answers `pip tree` with `uv-tree.txt`.
+    #[cfg(unix)]
+    fn fake_uv(root: &Path) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let uv = root.join("uv");
+        let log = root.join("uv.log");
+        let tree = root.join("uv-tree.txt");
+        std::fs::write(
+            &uv,
+            format!(
+                "#!/bin/sh\n\
+                 echo \"$*\" >> {log}\n\
+                 if [ \"$2\" = tree ]; then cat {tree} 2>/dev/null; exit 0; fi\n\
+                 for arg in \"$@\"; do\n\
+                 case \"$arg\" in bad-*) echo \"error: no version of $arg found\" >&2; exit 1;; esac\n\
+                 done\n",
+                log = log.display(),
+                tree = tree.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&uv, std::fs::Permissions::from_mode(0o755)).unwrap();
+        uv
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sync_reports_partial_failure() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let uv = fake_uv(tmp.path());
+        let env = fake_venv(tmp.path(), 0);
+        let handler = RecordingHandler::default();
+
+        let added = ["pandas", "bad-pkg", "numpy>=2"].map(String::from);
+        let removed = ["Old_Pkg>=1".to_string()];
+        let report = sync_dependencies_with(&uv, &env, &added, &removed, &handler)
+            .await
+            .unwrap();
+
+        assert_eq!(report.installed, vec!["pandas", "numpy>=2"]);
+        assert_eq!(
+            report.failed,
+            vec![(
+                "bad-pkg".to_string(),
+                "error: no version of bad-pkg found".to_string()
+            )]
+        );
+        assert_eq!(report.removed, vec!["Old_Pkg>=1"]);
+        assert!(!report.all_failed());
+
+        // The batch failed, so each package was retried on its own; the
+        // removal uses the bare package name
+        let calls = std::fs::read_to_string(tmp.path().join("uv.log")).unwrap();
+        let calls: Vec<&str> = calls.lines().collect();
+        assert_eq!(calls.len(), 6);
+        let python = env.python_path.display();
+        assert_eq!(calls[1], format!("pip install --python {python} pandas"));
+        assert_eq!(calls[4], format!("pip tree --python {python}"));
+        assert_eq!(calls[5], format!("pip uninstall --python {python} old-pkg"));
+
+        let phases = handler.0.lock().unwrap();
+        assert!(matches!(
+            &phases[0],
+            EnvProgressPhase::InstallingPackages { packages } if packages.len() == 3
+        ));
+        assert!(matches!(
+            &phases[1],
+            EnvProgressPhase::PackageInstalled { package } if package == "pandas"
+        ));
+        assert!(matches!(
+            &phases[2],
+            EnvProgressPhase::PackageFailed { package, message }
+                if package == "bad-pkg" && message.contains("no version")
+        ));
+        assert!(matches!(
+            &phases[4],
+            EnvProgressPhase::PackageRemoved { package } if package == "Old_Pkg>=1"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sync_keeps_removed_package_others_need() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let uv = fake_uv(tmp.path());
+        let env = fake_venv(tmp.path(), 0);
+        // numpy was declared alongside pandas, which also needs it
+        std::fs::write(
+            tmp.path().join("uv-tree.txt"),
+            "old-pkg v1.0\n\
+             pandas v2.2.0\n\
+             ├── numpy v1.26.4\n\
+             └── pytz v2024.1\n",
+        )
+        .unwrap();
+
+        let removed = ["numpy".to_string(), "old-pkg".to_string()];
+        let report = sync_dependencies_with(&uv, &env, &[], &removed, &LogHandler)
+            .await
+            .unwrap();
+
+        assert_eq!(report.removed, vec!["numpy", "old-pkg"]);
+        assert!(report.failed.is_empty());
+        let calls = std::fs::read_to_string(tmp.path().join("uv.log")).unwrap();
+        let uninstalls: Vec<&str> = calls
+            .lines()
+            .filter(|call| call.starts_with("pip uninstall"))
+            .collect();
+        assert_eq!(uninstalls.len(), 1);
+        assert!(uninstalls[0].ends_with(" old-pkg"));
+    }
+
+    #[test]
+    fn test_still_required_follows_kept_removals() {
+        // matplotlib stays, so removed pillow stays, and so does removed
+        // olefile that only pillow needs
+        let tree = parse_pip_tree(
+            "matplotlib v3.8.0\n\
+             └── pillow v10.0.0\n\
+             \x20   └── olefile v0.47\n\
+             requests v2.31.0\n\
+             └── idna v3.6\n",
+            &[],
+        );
+        let removed = ["Pillow>=9", "olefile", "requests", "idna"].map(String::from);
+        let required = still_required(&tree, &removed);
+        assert_eq!(
+            required,
+            HashSet::from(["pillow".to_string(), "olefile".to_string()])
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sync_single_failing_package() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let uv = fake_uv(tmp.path());
+        let env = fake_venv(tmp.path(), 0);
+
+        let added = ["bad-pkg".to_string()];
+        let report = sync_dependencies_with(&uv, &env, &added, &[], &LogHandler)
+            .await
+            .unwrap();
+
+        assert!(report.all_failed());
+        assert_eq!(report.failed[0].0, "bad-pkg");
+        let calls = std::fs::read_to_string(tmp.path().join("uv.log")).unwrap();
+        assert_eq!(calls.lines().count(), 1);
+    }
 
     #[test]
     fn test_compute_env_hash_stable() {
         let deps = UvDependencies {
             dependencies: vec!["pandas".to_string(), "numpy".to_string()],
             requires_python: Some(">=3.10".to_string()),
+            index_url: None,
         };
 
         let hash1 = compute_env_hash(&deps, None);
@@ -657,11 +1398,13 @@ mod tests {
         let deps1 = UvDependencies {
             dependencies: vec!["pandas".to_string(), "numpy".to_string()],
             requires_python: None,
+            index_url: None,
         };
 
         let deps2 = UvDependencies {
             dependencies: vec!["numpy".to_string(), "pandas".to_string()],
             requires_python: None,
+            index_url: None,
         };
 
         assert_eq!(
@@ -675,11 +1418,13 @@ mod tests {
         let deps1 = UvDependencies {
             dependencies: vec!["pandas".to_string()],
             requires_python: None,
+            index_url: None,
         };
 
         let deps2 = UvDependencies {
             dependencies: vec!["numpy".to_string()],
             requires_python: None,
+            index_url: None,
         };
 
         assert_ne!(
@@ -688,11 +1433,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_compute_env_hash_includes_index_url() {
+        let pypi = UvDependencies {
+            dependencies: vec!["pandas".to_string()],
+            requires_python: None,
+            index_url: None,
+        };
+        let mirror = UvDependencies {
+            index_url: Some("https://pypi.example.com/simple".to_string()),
+            ..pypi.clone()
+        };
+
+        assert_ne!(
+            compute_env_hash(&pypi, None),
+            compute_env_hash(&mirror, None)
+        );
+    }
+
     #[test]
     fn test_compute_env_hash_env_id_isolation() {
         let deps = UvDependencies {
             dependencies: vec![],
             requires_python: None,
+            index_url: None,
         };
 
         let hash1 = compute_env_hash(&deps, Some("notebook-1"));
@@ -705,6 +1469,7 @@ mod tests {
         let deps = UvDependencies {
             dependencies: vec!["pandas".to_string()],
             requires_python: None,
+            index_url: None,
         };
 
         let hash1 = compute_env_hash(&deps, Some("notebook-1"));
@@ -712,4 +1477,289 @@ mod tests {
         // env_id is only included for empty deps
         assert_eq!(hash1, hash2);
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_copy_environment_reports_progress() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let source = fake_venv(tmp.path(), 0);
+        let cache_dir = tmp.path().join("cache");
+        let handler = Arc::new(RecordingHandler::default());
+
+        let copied = copy_environment_in(
+            &source,
+            &no_deps(),
+            "clone-1",
+            &cache_dir,
+            handler.clone(),
+            &AtomicBool::new(false),
+        )
+        .await
+        .unwrap()
+        .expect("copy should not be cancelled");
+
+        assert_eq!(copied.venv_path, clone_path(&cache_dir));
+        assert!(copied
+            .venv_path
+            .join("lib/site-packages/pkg/__init__.py")
+            .exists());
+
+        let phases = handler.0.lock().unwrap();
+        let progress: Vec<(u64, u64)> = phases
+            .iter()
+            .filter_map(|p| match p {
+                EnvProgressPhase::CopyProgress {
+                    bytes_copied,
+                    bytes_total,
+                } => Some((*bytes_copied, *bytes_total)),
+                _ => None,
+            })
+            .collect();
+        let total = progress[0].1;
+        assert!(total > 0);
+        assert_eq!(progress.first(), Some(&(0, total)));
+        assert_eq!(progress.last(), Some(&(total, total)));
+        assert!(matches!(
+            phases.last(),
+            Some(EnvProgressPhase::Ready { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_launch_finds_copied_env() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let source = fake_venv(tmp.path(), 0);
+        let cache_dir = tmp.path().join("cache");
+
+        let copied = copy_environment_in(
+            &source,
+            &no_deps(),
+            "clone-1",
+            &cache_dir,
+            Arc::new(LogHandler),
+            &AtomicBool::new(false),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        // The clone's launch takes the cache hit instead of rebuilding
+        let handler = Arc::new(RecordingHandler::default());
+        let launched =
+            prepare_environment_in(&no_deps(), Some("clone-1"), &cache_dir, handler.clone())
+                .await
+                .unwrap();
+        assert_eq!(launched.venv_path, copied.venv_path);
+        assert!(handler
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|p| matches!(p, EnvProgressPhase::CacheHit { .. })));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancelled_copy_removes_partial_env() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let source = fake_venv(tmp.path(), 0);
+        let cache_dir = tmp.path().join("cache");
+        let handler = Arc::new(RecordingHandler::default());
+
+        let result = copy_environment_in(
+            &source,
+            &no_deps(),
+            "clone-1",
+            &cache_dir,
+            handler.clone(),
+            &AtomicBool::new(true),
+        )
+        .await
+        .unwrap();
+
+        // None tells the caller to fall back to a fresh environment
+        assert!(result.is_none());
+        assert!(!clone_path(&cache_dir).exists());
+        assert!(source.venv_path.join("pyvenv.cfg").exists());
+        assert!(matches!(
+            handler.0.lock().unwrap().last(),
+            Some(EnvProgressPhase::CopyCancelled)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_copy_rewrites_shebangs_and_pyvenv_cfg() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let source = fake_venv(tmp.path(), 0);
+        let cache_dir = tmp.path().join("cache");
+
+        let copied = copy_environment_in(
+            &source,
+            &no_deps(),
+            "clone-1",
+            &cache_dir,
+            Arc::new(LogHandler),
+            &AtomicBool::new(false),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        let dest = copied.venv_path.display().to_string();
+        let tool = std::fs::read_to_string(copied.venv_path.join("bin/tool")).unwrap();
+        assert_eq!(
+            tool.lines().next(),
+            Some(format!("#!{dest}/bin/python").as_str())
+        );
+
+        let cfg = std::fs::read_to_string(copied.venv_path.join("pyvenv.cfg")).unwrap();
+        assert!(cfg.contains("home = /usr/bin"));
+        assert!(cfg.contains(&format!(
+            "prompt = {}",
+            compute_env_hash(&no_deps(), Some("clone-1"))
+        )));
+        assert!(cfg.contains(&format!("command = uv venv {dest}")));
+        assert!(!cfg.contains(&source.venv_path.display().to_string()));
+
+        // The source env is untouched
+        let source_tool = std::fs::read_to_string(source.venv_path.join("bin/tool")).unwrap();
+        assert!(source_tool.starts_with(&format!("#!{}", source.venv_path.display())));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    #[ignore] // Run with --ignored; needs python3 with the venv module
+    async fn test_copied_venv_has_working_interpreter() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let venv_path = tmp.path().join("source-env");
+        let created = tokio::process::Command::new("python3")
+            .args(["-m", "venv", "--without-pip"])
+            .arg(&venv_path)
+            .status()
+            .await
+            .expect("python3 not found");
+        assert!(created.success(), "python3 -m venv failed");
+        let source = UvEnvironment {
+            python_path: venv_path.join("bin/python"),
+            venv_path,
+        };
+        let cache_dir = tmp.path().join("cache");
+
+        let copied = copy_environment_in(
+            &source,
+            &no_deps(),
+            "clone-1",
+            &cache_dir,
+            Arc::new(LogHandler),
+            &AtomicBool::new(false),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        let output = tokio::process::Command::new(&copied.python_path)
+            .args(["-c", "import sys; print(sys.prefix)"])
+            .output()
+            .await
+            .unwrap();
+        assert!(output.status.success());
+        let prefix = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        assert_eq!(
+            std::fs::canonicalize(prefix).unwrap(),
+            std::fs::canonicalize(&copied.venv_path).unwrap()
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_copy_with_broken_interpreter_is_removed() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let source = fake_venv(tmp.path(), 1);
+        let cache_dir = tmp.path().join("cache");
+
+        let result = copy_environment_in(
+            &source,
+            &no_deps(),
+            "clone-1",
+            &cache_dir,
+            Arc::new(LogHandler),
+            &AtomicBool::new(false),
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert!(!clone_path(&cache_dir).exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_diagnose_detects_corrupted_env() {
+        let tmp = tempfile::TempDir::new().unwrap();
+
+        let healthy = fake_venv(tmp.path(), 0);
+        assert_eq!(diagnose_environment(&healthy).await, None);
+
+        // ipykernel import fails
+        let broken = fake_venv(&tmp.path().join("broken"), 1);
+        let problem = diagnose_environment(&broken).await.unwrap();
+        assert!(problem.contains("ipykernel"), "{problem}");
+
+        // Interpreter deleted out from under the env
+        std::fs::remove_file(&healthy.python_path).unwrap();
+        let problem = diagnose_environment(&healthy).await.unwrap();
+        assert!(problem.contains("interpreter missing"), "{problem}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_repair_leaves_healthy_env_alone() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let cache_dir = tmp.path().join("cache");
+        let deps = UvDependencies {
+            dependencies: vec!["pandas".to_string()],
+            requires_python: None,
+            index_url: None,
+        };
+        let hash = compute_env_hash(&deps, None);
+        let env = fake_venv(&cache_dir, 0);
+        std::fs::rename(&env.venv_path, cache_dir.join(&hash)).unwrap();
+
+        let handler = Arc::new(RecordingHandler::default());
+        let rebuilt = repair_environment_in(&deps, None, &cache_dir, handler.clone())
+            .await
+            .unwrap();
+
+        assert!(!rebuilt);
+        assert!(cache_dir.join(&hash).join("pyvenv.cfg").exists());
+        assert!(handler.0.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    #[ignore] // Run with --ignored; needs uv and network access
+    async fn test_repair_rebuilds_broken_env() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let cache_dir = tmp.path().join("cache");
+        let deps = UvDependencies {
+            dependencies: vec![],
+            requires_python: None,
+            index_url: None,
+        };
+        let hash = compute_env_hash(&deps, None);
+        // An env whose interpreter can't import ipykernel
+        let env = fake_venv(&cache_dir, 1);
+        std::fs::rename(&env.venv_path, cache_dir.join(&hash)).unwrap();
+
+        let handler = Arc::new(RecordingHandler::default());
+        let rebuilt = repair_environment_in(&deps, None, &cache_dir, handler.clone())
+            .await
+            .unwrap();
+
+        assert!(rebuilt);
+        let python_path = cache_dir.join(&hash).join("bin/python");
+        assert_eq!(diagnose_interpreter(&python_path).await, None);
+        assert!(!handler.0.lock().unwrap().is_empty());
+    }
 }
@@ -9,7 +9,9 @@
 //!
 //! # Tool Bootstrapping
 //!
-//! Tools are automatically installed from conda-forge if not found on PATH:
+//! Tools are automatically installed from conda-forge if not found on PATH.
+//! [`tools::set_tool_preferences`] can point at explicit binaries or skip
+//! PATH and always bootstrap:
 //!
 //! ```ignore
 //! use kernel_launch::tools;
@@ -22,4 +24,7 @@
 pub mod tools;
 
 // Re-export commonly used items
-pub use tools::{get_deno_path, get_ruff_path, get_uv_path, BootstrappedTool};
+pub use tools::{
+    get_deno_path, get_ruff_path, get_uv_path, verify_all, BootstrappedTool, ResolvedTool,
+    ToolHealth, ToolPreferences, ToolSource,
+};
@@ -5,9 +5,17 @@
 //!
 //! For Deno specifically, we download directly from GitHub releases for better reliability,
 //! with a fallback to conda-forge via rattler.
+//!
+//! [`ToolPreferences`] (the `tools` settings) decide where `uv`, `ruff` and `deno` come
+//! from: an explicit path wins, then a binary on PATH when `prefer_system` is set, and
+//! bootstrapping is the fallback. [`get_uv_tool`] and friends report which one was used.
+//!
+//! A freshly bootstrapped binary must answer `--version` before it's cached; one that
+//! doesn't (a partial download, the wrong architecture) is reinstalled once.
+//! [`verify_all`] runs the same check on every tool.
 
 use anyhow::{anyhow, Result};
-use log::info;
+use log::{info, warn};
 use rattler::{default_cache_dir, install::Installer, package_cache::PackageCache};
 use rattler_conda_types::{
     Channel, ChannelConfig, GenericVirtualPackage, MatchSpec, ParseMatchSpecOptions, Platform,
@@ -15,10 +23,12 @@ use rattler_conda_types::{
 use rattler_repodata_gateway::Gateway;
 use rattler_solve::{resolvo, SolverImpl, SolverTask};
 use sha2::{Digest, Sha256};
+use std::future::Future;
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use tokio::sync::OnceCell;
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::Mutex;
 use zip::ZipArchive;
 
 /// Target Deno version for GitHub download.
@@ -221,51 +231,264 @@ pub async fn bootstrap_tool(tool_name: &str, version: Option<&str>) -> Result<Bo
     })
 }
 
-/// Global cache for the ruff binary path.
-/// This avoids repeated lookups once ruff is bootstrapped.
-static RUFF_PATH: OnceCell<Arc<Result<PathBuf, String>>> = OnceCell::const_new();
+/// Where a resolved tool binary came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolSource {
+    /// An explicit path from [`ToolPreferences`]
+    Configured,
+    /// Found on PATH
+    System,
+    /// Installed into the tools cache (conda-forge, or GitHub for deno)
+    Bootstrapped,
+}
 
-/// Get the path to ruff, bootstrapping it if necessary.
-///
-/// This function:
-/// 1. First checks if ruff is available on PATH (fast path)
-/// 2. If not, bootstraps it via rattler from conda-forge
-/// 3. Caches the result for subsequent calls
-///
-/// Returns the path to the ruff binary, or an error if it can't be obtained.
-pub async fn get_ruff_path() -> Result<PathBuf> {
-    let result = RUFF_PATH
-        .get_or_init(|| async {
-            // First, check if ruff is on PATH
-            if let Ok(output) = tokio::process::Command::new("ruff")
-                .arg("--version")
-                .output()
-                .await
-            {
-                if output.status.success() {
-                    info!("Using system ruff");
-                    return Arc::new(Ok(PathBuf::from("ruff")));
-                }
-            }
+impl std::fmt::Display for ToolSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Configured => "configured",
+            Self::System => "system",
+            Self::Bootstrapped => "bootstrapped",
+        })
+    }
+}
+
+/// A tool binary and where it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedTool {
+    /// Path to the binary (a bare name for binaries found on PATH)
+    pub path: PathBuf,
+    pub source: ToolSource,
+}
+
+/// Where to look for `uv`, `ruff` and `deno`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolPreferences {
+    /// Use this uv binary instead of searching
+    pub uv_path: Option<PathBuf>,
+    /// Use this ruff binary instead of searching
+    pub ruff_path: Option<PathBuf>,
+    /// Use this deno binary instead of searching
+    pub deno_path: Option<PathBuf>,
+    /// Use a binary found on PATH before bootstrapping one
+    pub prefer_system: bool,
+}
 
-            // Not on PATH, bootstrap via rattler
-            info!("ruff not found on PATH, bootstrapping via rattler...");
-            match bootstrap_tool("ruff", None).await {
-                Ok(tool) => Arc::new(Ok(tool.binary_path)),
-                Err(e) => Arc::new(Err(e.to_string())),
+impl ToolPreferences {
+    const DEFAULT: Self = Self {
+        uv_path: None,
+        ruff_path: None,
+        deno_path: None,
+        prefer_system: true,
+    };
+}
+
+impl Default for ToolPreferences {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+static PREFERENCES: RwLock<ToolPreferences> = RwLock::new(ToolPreferences::DEFAULT);
+
+/// Set where tools are looked up. Tools already resolved under different
+/// preferences are looked up again on their next use.
+pub fn set_tool_preferences(preferences: ToolPreferences) {
+    if let Ok(mut current) = PREFERENCES.write() {
+        if *current != preferences {
+            info!("Tool preferences changed: {:?}", preferences);
+            *current = preferences;
+        }
+    }
+}
+
+/// The preferences set by [`set_tool_preferences`].
+pub fn tool_preferences() -> ToolPreferences {
+    PREFERENCES
+        .read()
+        .map(|preferences| preferences.clone())
+        .unwrap_or_default()
+}
+
+/// Pick a tool binary: an explicit path wins, then the binary `system` finds
+/// on PATH when `prefer_system` is set, then the one `bootstrap` installs.
+/// `system` and `bootstrap` only run if they're needed.
+async fn resolve_tool(
+    name: &str,
+    explicit: Option<&Path>,
+    prefer_system: bool,
+    system: impl Future<Output = Option<PathBuf>>,
+    bootstrap: impl Future<Output = Result<PathBuf>>,
+) -> Result<ResolvedTool> {
+    let resolved = 'found: {
+        if let Some(path) = explicit {
+            if path.is_file() {
+                break 'found ResolvedTool {
+                    path: path.to_path_buf(),
+                    source: ToolSource::Configured,
+                };
             }
-        })
-        .await;
+            warn!(
+                "Configured {} path {:?} is not a file, searching instead",
+                name, path
+            );
+        }
+        if prefer_system {
+            if let Some(path) = system.await {
+                break 'found ResolvedTool {
+                    path,
+                    source: ToolSource::System,
+                };
+            }
+            info!("{} not found on PATH, bootstrapping...", name);
+        }
+        ResolvedTool {
+            path: bootstrap.await?,
+            source: ToolSource::Bootstrapped,
+        }
+    };
+    info!("Using {} {} at {:?}", resolved.source, name, resolved.path);
+    Ok(resolved)
+}
+
+/// How long a `--version` self-test may take.
+const VERSION_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Run `binary --version`, returning the first line it prints.
+async fn version_check(binary: PathBuf) -> Result<String> {
+    let output = tokio::time::timeout(
+        VERSION_CHECK_TIMEOUT,
+        tokio::process::Command::new(&binary)
+            .arg("--version")
+            .output(),
+    )
+    .await
+    .map_err(|_| anyhow!("`{} --version` timed out", binary.display()))?
+    .map_err(|e| anyhow!("could not run {}: {}", binary.display(), e))?;
 
-    match result.as_ref() {
-        Ok(path) => Ok(path.clone()),
-        Err(e) => Err(anyhow!("{}", e)),
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`{} --version` failed ({}): {}",
+            binary.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().next().unwrap_or_default().trim().to_string())
+}
+
+/// Install a tool with `install` and self-test it with `check` before it's
+/// cached as good. A binary that fails the check is removed along with its
+/// environment and installed once more; failing again is an error.
+async fn bootstrap_verified<I, C>(
+    name: &str,
+    mut install: impl FnMut() -> I,
+    check: impl Fn(PathBuf) -> C,
+) -> Result<PathBuf>
+where
+    I: Future<Output = Result<BootstrappedTool>>,
+    C: Future<Output = Result<String>>,
+{
+    let mut attempt = 1;
+    loop {
+        let tool = install().await?;
+        match check(tool.binary_path.clone()).await {
+            Ok(version) => {
+                info!("Bootstrapped {} passed its self-test: {}", name, version);
+                return Ok(tool.binary_path);
+            }
+            Err(e) if attempt == 1 => {
+                warn!(
+                    "Bootstrapped {} at {:?} failed its self-test, reinstalling: {}",
+                    name, tool.binary_path, e
+                );
+                if tool.env_path.exists() {
+                    tokio::fs::remove_dir_all(&tool.env_path).await?;
+                }
+                attempt += 1;
+            }
+            Err(e) => {
+                return Err(anyhow!(
+                    "Bootstrapped {} failed its self-test after reinstalling: {}",
+                    name,
+                    e
+                ));
+            }
+        }
     }
 }
 
-/// Global cache for the deno binary path.
-/// This avoids repeated lookups once deno is bootstrapped.
-static DENO_PATH: OnceCell<Arc<Result<PathBuf, String>>> = OnceCell::const_new();
+/// A tool's last resolution, and the explicit path and `prefer_system` it
+/// was made under.
+type ToolCache = Mutex<Option<(Option<PathBuf>, bool, Result<ResolvedTool, String>)>>;
+
+/// [`resolve_tool`] once per change in preferences, caching the result
+/// (including failures) in between.
+async fn cached_tool(
+    cache: &ToolCache,
+    name: &str,
+    explicit: impl FnOnce(&ToolPreferences) -> Option<PathBuf>,
+    system: impl Future<Output = Option<PathBuf>>,
+    bootstrap: impl Future<Output = Result<PathBuf>>,
+) -> Result<ResolvedTool> {
+    let preferences = tool_preferences();
+    let explicit = explicit(&preferences);
+    let mut cached = cache.lock().await;
+    let result = match cached.as_ref() {
+        Some((path, prefer_system, result))
+            if *path == explicit && *prefer_system == preferences.prefer_system =>
+        {
+            result.clone()
+        }
+        _ => {
+            let result = resolve_tool(
+                name,
+                explicit.as_deref(),
+                preferences.prefer_system,
+                system,
+                bootstrap,
+            )
+            .await
+            .map_err(|e| e.to_string());
+            *cached = Some((explicit, preferences.prefer_system, result.clone()));
+            result
+        }
+    };
+    result.map_err(|e| anyhow!("{}", e))
+}
+
+/// `name` on PATH, if running `name --version` succeeds.
+async fn system_tool(name: &str) -> Option<PathBuf> {
+    let output = tokio::process::Command::new(name)
+        .arg("--version")
+        .output()
+        .await
+        .ok()?;
+    output.status.success().then(|| PathBuf::from(name))
+}
+
+static RUFF_TOOL: ToolCache = Mutex::const_new(None);
+
+/// Get ruff and where it came from, bootstrapping it if necessary.
+///
+/// Uses the configured `ruff_path` if set, then ruff on PATH (when
+/// `prefer_system` is set), then bootstraps it via rattler from conda-forge.
+/// Cached until the preferences change.
+pub async fn get_ruff_tool() -> Result<ResolvedTool> {
+    cached_tool(
+        &RUFF_TOOL,
+        "ruff",
+        |preferences| preferences.ruff_path.clone(),
+        system_tool("ruff"),
+        bootstrap_verified("ruff", || bootstrap_tool("ruff", None), version_check),
+    )
+    .await
+}
+
+/// Get the path to ruff; see [`get_ruff_tool`].
+pub async fn get_ruff_path() -> Result<PathBuf> {
+    Ok(get_ruff_tool().await?.path)
+}
 
 /// Check if a usable Deno is available without triggering a bootstrap.
 ///
@@ -276,17 +499,24 @@ static DENO_PATH: OnceCell<Arc<Result<PathBuf, String>>> = OnceCell::const_new()
 /// This is intended for UI availability checks where we don't want to
 /// trigger a full download during initialization.
 pub async fn check_deno_available_without_bootstrap() -> bool {
+    let preferences = tool_preferences();
+    if preferences.deno_path.is_some_and(|path| path.is_file()) {
+        return true;
+    }
+
     // Check for acceptable system deno (2.x+)
-    if let Ok(output) = tokio::process::Command::new("deno")
-        .arg("--version")
-        .output()
-        .await
-    {
-        if output.status.success() {
-            let version_str = String::from_utf8_lossy(&output.stdout);
-            if let Some(major) = parse_deno_major_version(&version_str) {
-                if major >= DENO_MIN_MAJOR_VERSION {
-                    return true;
+    if preferences.prefer_system {
+        if let Ok(output) = tokio::process::Command::new("deno")
+            .arg("--version")
+            .output()
+            .await
+        {
+            if output.status.success() {
+                let version_str = String::from_utf8_lossy(&output.stdout);
+                if let Some(major) = parse_deno_major_version(&version_str) {
+                    if major >= DENO_MIN_MAJOR_VERSION {
+                        return true;
+                    }
                 }
             }
         }
@@ -520,90 +750,118 @@ async fn download_deno_from_github(version: &str) -> Result<BootstrappedTool> {
     })
 }
 
-/// Get the path to deno, with the following priority:
-///
-/// 1. System deno if version >= 2.x (fast path, respects user's installation)
-/// 2. Download from GitHub releases (v2.7.1) - most reliable source
-/// 3. Fallback to rattler/conda-forge if GitHub download fails
-///
-/// Results are cached for subsequent calls.
-pub async fn get_deno_path() -> Result<PathBuf> {
-    let result = DENO_PATH
-        .get_or_init(|| async {
-            // 1. Check for acceptable system deno (2.x+)
-            if let Some(path) = check_system_deno_acceptable().await {
-                return Arc::new(Ok(path));
-            }
+/// Download deno from GitHub releases, falling back to rattler/conda-forge.
+async fn bootstrap_deno() -> Result<PathBuf> {
+    info!(
+        "Downloading deno {} from GitHub releases...",
+        DENO_TARGET_VERSION
+    );
+    let github = bootstrap_verified(
+        "deno",
+        || download_deno_from_github(DENO_TARGET_VERSION),
+        version_check,
+    );
+    match github.await {
+        Ok(path) => return Ok(path),
+        Err(e) => {
+            info!("GitHub download failed: {}. Falling back to rattler...", e);
+        }
+    }
 
-            // 2. Try GitHub download (primary method)
-            info!(
-                "Downloading deno {} from GitHub releases...",
-                DENO_TARGET_VERSION
-            );
-            match download_deno_from_github(DENO_TARGET_VERSION).await {
-                Ok(tool) => return Arc::new(Ok(tool.binary_path)),
-                Err(e) => {
-                    info!("GitHub download failed: {}. Falling back to rattler...", e);
-                }
-            }
+    info!("Bootstrapping deno via rattler from conda-forge...");
+    bootstrap_verified("deno", || bootstrap_tool("deno", None), version_check).await
+}
 
-            // 3. Fallback to rattler
-            info!("Bootstrapping deno via rattler from conda-forge...");
-            match bootstrap_tool("deno", None).await {
-                Ok(tool) => Arc::new(Ok(tool.binary_path)),
-                Err(e) => Arc::new(Err(e.to_string())),
-            }
-        })
-        .await;
+static DENO_TOOL: ToolCache = Mutex::const_new(None);
 
-    match result.as_ref() {
-        Ok(path) => Ok(path.clone()),
-        Err(e) => Err(anyhow!("{}", e)),
-    }
+/// Get deno and where it came from, with the following priority:
+///
+/// 1. The configured `deno_path`
+/// 2. System deno if version >= 2.x (when `prefer_system` is set)
+/// 3. Download from GitHub releases (v2.7.1) - most reliable source
+/// 4. Fallback to rattler/conda-forge if GitHub download fails
+///
+/// Cached until the preferences change.
+pub async fn get_deno_tool() -> Result<ResolvedTool> {
+    cached_tool(
+        &DENO_TOOL,
+        "deno",
+        |preferences| preferences.deno_path.clone(),
+        check_system_deno_acceptable(),
+        bootstrap_deno(),
+    )
+    .await
 }
 
-/// Global cache for the uv binary path.
-/// This avoids repeated lookups once uv is bootstrapped.
-static UV_PATH: OnceCell<Arc<Result<PathBuf, String>>> = OnceCell::const_new();
+/// Get the path to deno; see [`get_deno_tool`].
+pub async fn get_deno_path() -> Result<PathBuf> {
+    Ok(get_deno_tool().await?.path)
+}
 
-/// Get the path to uv, bootstrapping it if necessary.
-///
-/// This function:
-/// 1. First checks if uv is available on PATH (fast path)
-/// 2. If not, bootstraps it via rattler from conda-forge
-/// 3. Caches the result for subsequent calls
+static UV_TOOL: ToolCache = Mutex::const_new(None);
+
+/// Get uv and where it came from, bootstrapping it if necessary.
 ///
-/// Returns the path to the uv binary, or an error if it can't be obtained.
+/// Uses the configured `uv_path` if set, then uv on PATH (when
+/// `prefer_system` is set), then bootstraps it via rattler from conda-forge.
+/// Cached until the preferences change.
+pub async fn get_uv_tool() -> Result<ResolvedTool> {
+    cached_tool(
+        &UV_TOOL,
+        "uv",
+        |preferences| preferences.uv_path.clone(),
+        system_tool("uv"),
+        bootstrap_verified("uv", || bootstrap_tool("uv", None), version_check),
+    )
+    .await
+}
+
+/// Get the path to uv; see [`get_uv_tool`].
 pub async fn get_uv_path() -> Result<PathBuf> {
-    let result = UV_PATH
-        .get_or_init(|| async {
-            // First, check if uv is on PATH
-            if let Ok(output) = tokio::process::Command::new("uv")
-                .arg("--version")
-                .output()
-                .await
-            {
-                if output.status.success() {
-                    info!("Using system uv");
-                    return Arc::new(Ok(PathBuf::from("uv")));
-                }
-            }
+    Ok(get_uv_tool().await?.path)
+}
 
-            // Not on PATH, bootstrap via rattler
-            info!("uv not found on PATH, bootstrapping via rattler...");
-            match bootstrap_tool("uv", None).await {
-                Ok(tool) => Arc::new(Ok(tool.binary_path)),
-                Err(e) => Arc::new(Err(e.to_string())),
-            }
-        })
-        .await;
+/// Whether a tool can be found and runs.
+#[derive(Debug, Clone)]
+pub struct ToolHealth {
+    /// `uv`, `ruff` or `deno`
+    pub name: &'static str,
+    /// The binary that was checked, if one could be found or bootstrapped
+    pub tool: Option<ResolvedTool>,
+    /// The first line of `--version` output, or why the tool is unusable
+    pub status: Result<String, String>,
+}
 
-    match result.as_ref() {
-        Ok(path) => Ok(path.clone()),
-        Err(e) => Err(anyhow!("{}", e)),
+impl ToolHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.status.is_ok()
     }
 }
 
+/// Resolve uv, ruff and deno (bootstrapping any that are missing) and check
+/// that each answers `--version`.
+pub async fn verify_all() -> Vec<ToolHealth> {
+    let (uv, ruff, deno) = tokio::join!(get_uv_tool(), get_ruff_tool(), get_deno_tool());
+    let mut health = Vec::new();
+    for (name, resolved) in [("uv", uv), ("ruff", ruff), ("deno", deno)] {
+        health.push(match resolved {
+            Ok(tool) => ToolHealth {
+                name,
+                status: version_check(tool.path.clone())
+                    .await
+                    .map_err(|e| e.to_string()),
+                tool: Some(tool),
+            },
+            Err(e) => ToolHealth {
+                name,
+                tool: None,
+                status: Err(e.to_string()),
+            },
+        });
+    }
+    health
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -756,4 +1014,158 @@ mod tests {
         assert!(DENO_TARGET_VERSION.contains('.'));
         assert!(DENO_MIN_MAJOR_VERSION >= 2);
     }
+
+    async fn found(path: &str) -> Option<PathBuf> {
+        Some(PathBuf::from(path))
+    }
+
+    async fn bootstrapped() -> Result<PathBuf> {
+        Ok(PathBuf::from("/cache/tools/bin/uv"))
+    }
+
+    async fn unreachable_bootstrap() -> Result<PathBuf> {
+        panic!("bootstrapped although a binary was found")
+    }
+
+    #[tokio::test]
+    async fn test_explicit_path_wins_over_system_and_bootstrap() {
+        let binary = tempfile::NamedTempFile::new().unwrap();
+        let tool = resolve_tool(
+            "uv",
+            Some(binary.path()),
+            true,
+            found("uv"),
+            unreachable_bootstrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(tool.path, binary.path());
+        assert_eq!(tool.source, ToolSource::Configured);
+    }
+
+    #[tokio::test]
+    async fn test_system_preferred_over_bootstrap() {
+        let tool = resolve_tool("uv", None, true, found("uv"), unreachable_bootstrap())
+            .await
+            .unwrap();
+        assert_eq!(tool.path, PathBuf::from("uv"));
+        assert_eq!(tool.source, ToolSource::System);
+
+        // Nothing on PATH: bootstrap
+        let tool = resolve_tool("uv", None, true, async { None }, bootstrapped())
+            .await
+            .unwrap();
+        assert_eq!(tool.source, ToolSource::Bootstrapped);
+    }
+
+    #[tokio::test]
+    async fn test_system_skipped_unless_preferred() {
+        let tool = resolve_tool(
+            "uv",
+            None,
+            false,
+            async { panic!("searched PATH although prefer_system is off") },
+            bootstrapped(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(tool.path, PathBuf::from("/cache/tools/bin/uv"));
+        assert_eq!(tool.source, ToolSource::Bootstrapped);
+    }
+
+    #[tokio::test]
+    async fn test_missing_explicit_path_falls_through() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("uv");
+        let tool = resolve_tool(
+            "uv",
+            Some(&missing),
+            true,
+            found("uv"),
+            unreachable_bootstrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(tool.source, ToolSource::System);
+    }
+
+    /// An `install` for [`bootstrap_verified`] that creates a fake tool
+    /// environment under `root`, recording whether a previous install was
+    /// still present each time it runs.
+    fn fake_install<'a>(
+        root: &'a Path,
+        leftovers: &'a std::sync::Mutex<Vec<bool>>,
+    ) -> impl FnMut() -> std::future::Ready<Result<BootstrappedTool>> + 'a {
+        move || {
+            let env_path = root.join("uv-env");
+            leftovers.lock().unwrap().push(env_path.exists());
+            let binary_path = binary_path_for_env(&env_path, "uv");
+            std::fs::create_dir_all(binary_path.parent().unwrap()).unwrap();
+            std::fs::write(&binary_path, "").unwrap();
+            std::future::ready(Ok(BootstrappedTool {
+                binary_path,
+                env_path,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_self_test_reinstalls() {
+        let root = tempfile::tempdir().unwrap();
+        let leftovers = std::sync::Mutex::new(Vec::new());
+        let checks = std::sync::atomic::AtomicUsize::new(0);
+
+        let path = bootstrap_verified("uv", fake_install(root.path(), &leftovers), |_| {
+            let n = checks.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async move {
+                if n == 0 {
+                    Err(anyhow!("exec format error"))
+                } else {
+                    Ok("uv 0.5.0".to_string())
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        // The broken install was removed before installing again
+        assert_eq!(*leftovers.lock().unwrap(), vec![false, false]);
+        assert_eq!(checks.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert!(path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_self_test_failing_twice_is_an_error() {
+        let root = tempfile::tempdir().unwrap();
+        let leftovers = std::sync::Mutex::new(Vec::new());
+
+        let err = bootstrap_verified("uv", fake_install(root.path(), &leftovers), |_| async {
+            Err(anyhow!("exec format error"))
+        })
+        .await
+        .unwrap_err();
+
+        assert_eq!(leftovers.lock().unwrap().len(), 2);
+        assert!(err.to_string().contains("after reinstalling"));
+    }
+
+    #[tokio::test]
+    async fn test_passing_self_test_installs_once() {
+        let root = tempfile::tempdir().unwrap();
+        let leftovers = std::sync::Mutex::new(Vec::new());
+
+        bootstrap_verified("uv", fake_install(root.path(), &leftovers), |_| async {
+            Ok("uv 0.5.0".to_string())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(leftovers.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_version_check_fails_for_missing_binary() {
+        let root = tempfile::tempdir().unwrap();
+        assert!(version_check(root.path().join("uv")).await.is_err());
+    }
 }
@@ -0,0 +1,176 @@
+//! Structured errors returned by the Tauri commands.
+//!
+//! Every command fails with a [`CommandError`], which serializes to the
+//! frontend as `{ "code": "not_connected", "message": "..." }`. The `code` is
+//! stable, so the frontend can decide whether to retry, prompt, or just show
+//! the message without matching on error text.
+
+use std::fmt;
+use std::sync::PoisonError;
+
+use runtimed::notebook_sync_client::NotebookSyncError;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+/// Error returned from a Tauri command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    /// The window has no daemon connection, or the connection dropped.
+    /// Usually transient: the frontend can retry once `daemon:ready` fires.
+    NotConnected(String),
+    /// The daemon or kernel rejected the request (no kernel running, kernel
+    /// error reply, unexpected daemon response).
+    KernelError(String),
+    /// The caller passed something invalid: a malformed package spec, an
+    /// unknown setting, a cell that can't be deleted.
+    Validation(String),
+    /// Reading or writing a file failed.
+    Io(String),
+    /// A request to the daemon timed out.
+    Timeout(String),
+    /// A notebook, window, cell, or project file the command needs is missing.
+    NotFound(String),
+}
+
+impl CommandError {
+    /// The window isn't connected to the daemon.
+    pub fn not_connected() -> Self {
+        Self::NotConnected("Not connected to daemon".to_string())
+    }
+
+    /// Stable machine-readable code, sent to the frontend as `code`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotConnected(_) => "not_connected",
+            Self::KernelError(_) => "kernel_error",
+            Self::Validation(_) => "validation",
+            Self::Io(_) => "io",
+            Self::Timeout(_) => "timeout",
+            Self::NotFound(_) => "not_found",
+        }
+    }
+
+    /// Human-readable description, sent to the frontend as `message`.
+    pub fn message(&self) -> &str {
+        match self {
+            Self::NotConnected(m)
+            | Self::KernelError(m)
+            | Self::Validation(m)
+            | Self::Io(m)
+            | Self::Timeout(m)
+            | Self::NotFound(m) => m,
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl Serialize for CommandError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("CommandError", 2)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", self.message())?;
+        s.end()
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => Self::NotFound(e.to_string()),
+            _ => Self::Io(e.to_string()),
+        }
+    }
+}
+
+/// A poisoned notebook state lock means an earlier command panicked midway;
+/// the in-memory notebook can't be trusted, much like a failed read.
+impl<T> From<PoisonError<T>> for CommandError {
+    fn from(e: PoisonError<T>) -> Self {
+        Self::Io(format!("Notebook state unavailable: {e}"))
+    }
+}
+
+impl From<NotebookSyncError> for CommandError {
+    fn from(e: NotebookSyncError) -> Self {
+        match e {
+            NotebookSyncError::Timeout => Self::Timeout(e.to_string()),
+            NotebookSyncError::CellNotFound(_) => Self::NotFound(e.to_string()),
+            NotebookSyncError::ConnectionFailed(_)
+            | NotebookSyncError::SyncError(_)
+            | NotebookSyncError::Disconnected
+            | NotebookSyncError::ChannelClosed => Self::NotConnected(e.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for CommandError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Validation(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_code_and_message() {
+        let json = serde_json::to_value(CommandError::not_connected()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "code": "not_connected",
+                "message": "Not connected to daemon",
+            })
+        );
+    }
+
+    #[test]
+    fn test_sync_errors_map_to_codes() {
+        let code = |e: NotebookSyncError| CommandError::from(e).code();
+        assert_eq!(code(NotebookSyncError::Timeout), "timeout");
+        assert_eq!(code(NotebookSyncError::Disconnected), "not_connected");
+        assert_eq!(code(NotebookSyncError::ChannelClosed), "not_connected");
+        assert_eq!(
+            code(NotebookSyncError::CellNotFound("c1".into())),
+            "not_found"
+        );
+    }
+
+    #[test]
+    fn test_io_errors_map_to_codes() {
+        let missing = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        assert_eq!(CommandError::from(missing).code(), "not_found");
+        let denied = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "nope");
+        assert_eq!(
+            CommandError::from(denied),
+            CommandError::Io("nope".to_string())
+        );
+    }
+
+    #[test]
+    fn test_poisoned_lock_maps_to_io() {
+        let lock = std::sync::Arc::new(std::sync::Mutex::new(()));
+        let poisoner = lock.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("poison");
+        })
+        .join();
+        let err = CommandError::from(lock.lock().unwrap_err());
+        assert_eq!(err.code(), "io");
+    }
+
+    #[test]
+    fn test_invalid_json_is_validation() {
+        let err = serde_json::from_str::<String>("not json").unwrap_err();
+        assert_eq!(CommandError::from(err).code(), "validation");
+    }
+}
@@ -5,9 +5,13 @@
 //! creation to `kernel_env::conda`.
 
 use anyhow::Result;
+use rattler_conda_types::{MatchSpec, PackageNameMatcher, ParseStrictness};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Runtime, Wry};
+
+use crate::settings::CondaDefaults;
+use crate::uv_env::{upsert_dependency, NormalizedReq, SpecError};
 
 // Re-export core types from kernel-env for backward compatibility
 pub use kernel_env::conda::CondaEnvironment;
@@ -20,11 +24,19 @@ pub use kernel_env::progress::EnvProgressPhase;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CondaDependencies {
     pub dependencies: Vec<String>,
+    /// Channels in priority order.
     #[serde(default)]
     pub channels: Vec<String>,
+    /// How strictly `channels` order is enforced. `None` defers to the
+    /// user's settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_priority: Option<kernel_env::ChannelPriority>,
     pub python: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub env_id: Option<String>,
+    /// PyPI packages installed with pip after the conda solve.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pip_dependencies: Vec<String>,
 }
 
 impl From<CondaDependencies> for kernel_env::CondaDependencies {
@@ -32,8 +44,10 @@ impl From<CondaDependencies> for kernel_env::CondaDependencies {
         Self {
             dependencies: deps.dependencies,
             channels: deps.channels,
+            channel_priority: deps.channel_priority.unwrap_or_default(),
             python: deps.python,
             env_id: deps.env_id,
+            pip_dependencies: deps.pip_dependencies,
         }
     }
 }
@@ -43,8 +57,10 @@ impl From<kernel_env::CondaDependencies> for CondaDependencies {
         Self {
             dependencies: deps.dependencies,
             channels: deps.channels,
+            channel_priority: Some(deps.channel_priority),
             python: deps.python,
             env_id: deps.env_id,
+            pip_dependencies: deps.pip_dependencies,
         }
     }
 }
@@ -58,17 +74,17 @@ pub struct EnvProgressEvent {
 }
 
 /// Progress handler that emits Tauri events.
-pub struct TauriProgressHandler {
-    app: AppHandle,
+pub struct TauriProgressHandler<R: Runtime = Wry> {
+    app: AppHandle<R>,
 }
 
-impl TauriProgressHandler {
-    pub fn new(app: AppHandle) -> Self {
+impl<R: Runtime> TauriProgressHandler<R> {
+    pub fn new(app: AppHandle<R>) -> Self {
         Self { app }
     }
 }
 
-impl kernel_env::ProgressHandler for TauriProgressHandler {
+impl<R: Runtime> kernel_env::ProgressHandler for TauriProgressHandler<R> {
     fn on_progress(&self, env_type: &str, phase: EnvProgressPhase) {
         let event = EnvProgressEvent {
             env_type: env_type.to_string(),
@@ -80,32 +96,43 @@ impl kernel_env::ProgressHandler for TauriProgressHandler {
     }
 }
 
+/// Progress handler for environment creation: `env:progress` events when an
+/// app handle is available, log lines otherwise.
+pub fn progress_handler<R: Runtime>(
+    app: Option<&AppHandle<R>>,
+) -> Arc<dyn kernel_env::ProgressHandler> {
+    match app {
+        Some(a) => Arc::new(TauriProgressHandler::new(a.clone())),
+        None => Arc::new(kernel_env::LogHandler),
+    }
+}
+
 // =====================================================================
 // Metadata operations (notebook-specific, depend on nbformat)
 // =====================================================================
 
-/// Extract dependencies from notebook metadata.
+/// Extract dependencies from notebook metadata (`runt.conda`).
+///
+/// Notebooks with a legacy top-level `conda` section are migrated on load by
+/// [`crate::metadata_migration`].
 pub fn extract_dependencies(metadata: &nbformat::v4::Metadata) -> Option<CondaDependencies> {
-    // New format: metadata.runt.conda
-    if let Some(runt_value) = metadata.additional.get("runt") {
-        if let Some(conda_value) = runt_value.get("conda") {
-            if let Ok(deps) = serde_json::from_value(conda_value.clone()) {
-                return Some(deps);
-            }
-        }
-    }
-    // Legacy format: metadata.conda
-    let conda_value = metadata.additional.get("conda")?;
+    let conda_value = metadata.additional.get("runt")?.get("conda")?;
     serde_json::from_value(conda_value.clone()).ok()
 }
 
 /// Set conda dependencies in notebook metadata (nested under runt).
 pub fn set_dependencies(metadata: &mut nbformat::v4::Metadata, deps: &CondaDependencies) {
-    let conda_value = serde_json::json!({
+    let mut conda_value = serde_json::json!({
         "dependencies": deps.dependencies,
         "channels": deps.channels,
         "python": deps.python,
     });
+    if let Some(priority) = deps.channel_priority {
+        conda_value["channel_priority"] = serde_json::json!(priority);
+    }
+    if !deps.pip_dependencies.is_empty() {
+        conda_value["pip_dependencies"] = serde_json::json!(deps.pip_dependencies);
+    }
 
     let runt = metadata
         .additional
@@ -137,13 +164,88 @@ pub fn remove_conda_config(metadata: &mut nbformat::v4::Metadata) {
     metadata.additional.remove("conda");
 }
 
+// =====================================================================
+// Match spec validation
+// =====================================================================
+
+/// Validate a conda match spec (e.g. `numpy>=1.24`, `conda-forge::scipy=1.11`)
+/// and normalize its package name. Channel prefixes and version constraints
+/// are kept as written.
+pub fn parse_match_spec(spec: &str) -> Result<NormalizedReq, SpecError> {
+    let trimmed = spec.trim();
+    if trimmed.is_empty() {
+        return Err(SpecError::new(spec, "package name is empty"));
+    }
+
+    let match_spec = MatchSpec::from_str(trimmed, ParseStrictness::Strict)
+        .map_err(|e| SpecError::new(spec, e.to_string()))?;
+    let name = match match_spec.name {
+        Some(PackageNameMatcher::Exact(name)) => name.as_normalized().to_string(),
+        Some(_) => return Err(SpecError::new(spec, "package name can't be a pattern")),
+        None => return Err(SpecError::new(spec, "package name is missing")),
+    };
+
+    let (channel, rest) = match trimmed.rfind("::") {
+        Some(i) => trimmed.split_at(i + 2),
+        None => ("", trimmed),
+    };
+    let name_len = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .unwrap_or(rest.len());
+    Ok(NormalizedReq {
+        spec: format!("{}{}{}", channel, name, &rest[name_len..]),
+        name,
+    })
+}
+
+/// The normalized package name of a stored match spec, falling back to the
+/// text before any version operator for entries that don't parse.
+pub fn match_spec_name(spec: &str) -> String {
+    match parse_match_spec(spec) {
+        Ok(req) => req.name,
+        Err(_) => spec
+            .split(&['>', '<', '=', '!', '~', '['][..])
+            .next()
+            .unwrap_or(spec)
+            .trim()
+            .to_lowercase(),
+    }
+}
+
+/// [`upsert_dependency`] for conda match specs.
+pub fn upsert_match_spec(deps: &mut Vec<String>, req: &NormalizedReq) -> bool {
+    upsert_dependency(deps, req, match_spec_name)
+}
+
 // =====================================================================
 // Environment operations (delegating to kernel-env)
 // =====================================================================
 
+/// The env spec handed to kernel-env. A notebook that lists no channels or
+/// leaves the priority unset gets them from the conda settings, as the daemon
+/// resolves them.
+fn resolve_dependencies(
+    deps: &CondaDependencies,
+    defaults: &CondaDefaults,
+) -> kernel_env::CondaDependencies {
+    let mut resolved = kernel_env::CondaDependencies::from(deps.clone());
+    if deps.channels.is_empty() {
+        resolved.channels = defaults.default_channels();
+    }
+    if deps.channel_priority.is_none() {
+        resolved.channel_priority = defaults.channel_priority();
+    }
+    resolved
+}
+
+/// Resolve `deps` against the conda settings on disk.
+fn resolve_with_settings(deps: &CondaDependencies) -> kernel_env::CondaDependencies {
+    resolve_dependencies(deps, &crate::settings::load_settings().conda)
+}
+
 /// Compute a cache key for the given dependencies.
 pub fn compute_env_hash(deps: &CondaDependencies) -> String {
-    kernel_env::conda::compute_env_hash(&deps.clone().into())
+    kernel_env::conda::compute_env_hash(&resolve_with_settings(deps))
 }
 
 /// Prepare a conda environment with the given dependencies.
@@ -151,11 +253,8 @@ pub async fn prepare_environment(
     deps: &CondaDependencies,
     app: Option<&AppHandle>,
 ) -> Result<CondaEnvironment> {
-    let handler: Arc<dyn kernel_env::ProgressHandler> = match app {
-        Some(a) => Arc::new(TauriProgressHandler::new(a.clone())),
-        None => Arc::new(kernel_env::LogHandler),
-    };
-    kernel_env::conda::prepare_environment(&deps.clone().into(), handler).await
+    let handler = progress_handler(app);
+    kernel_env::conda::prepare_environment(&resolve_with_settings(deps), handler).await
 }
 
 /// Create a prewarmed conda environment.
@@ -163,10 +262,7 @@ pub async fn create_prewarmed_conda_environment(
     app: Option<&AppHandle>,
 ) -> Result<CondaEnvironment> {
     let extra: Vec<String> = crate::settings::load_settings().conda.default_packages;
-    let handler: Arc<dyn kernel_env::ProgressHandler> = match app {
-        Some(a) => Arc::new(TauriProgressHandler::new(a.clone())),
-        None => Arc::new(kernel_env::LogHandler),
-    };
+    let handler = progress_handler(app);
     kernel_env::conda::create_prewarmed_environment(&extra, handler).await
 }
 
@@ -195,7 +291,7 @@ pub async fn find_existing_prewarmed_conda_environments() -> Vec<CondaEnvironmen
 
 /// Install additional dependencies into an existing environment.
 pub async fn sync_dependencies(env: &CondaEnvironment, deps: &CondaDependencies) -> Result<()> {
-    kernel_env::conda::sync_dependencies(env, &deps.clone().into()).await
+    kernel_env::conda::sync_dependencies(env, &resolve_with_settings(deps)).await
 }
 
 #[cfg(test)]
@@ -207,8 +303,10 @@ mod tests {
         let deps = CondaDependencies {
             dependencies: vec!["pandas".to_string(), "numpy".to_string()],
             channels: vec!["conda-forge".to_string()],
+            channel_priority: None,
             python: Some("3.11".to_string()),
             env_id: Some("test-env-id".to_string()),
+            pip_dependencies: vec![],
         };
 
         let hash1 = compute_env_hash(&deps);
@@ -221,15 +319,19 @@ mod tests {
         let deps1 = CondaDependencies {
             dependencies: vec!["pandas".to_string(), "numpy".to_string()],
             channels: vec![],
+            channel_priority: None,
             python: None,
             env_id: Some("test-env-1".to_string()),
+            pip_dependencies: vec![],
         };
 
         let deps2 = CondaDependencies {
             dependencies: vec!["numpy".to_string(), "pandas".to_string()],
             channels: vec![],
+            channel_priority: None,
             python: None,
             env_id: Some("test-env-1".to_string()),
+            pip_dependencies: vec![],
         };
 
         assert_eq!(compute_env_hash(&deps1), compute_env_hash(&deps2));
@@ -240,15 +342,19 @@ mod tests {
         let deps1 = CondaDependencies {
             dependencies: vec!["pandas".to_string()],
             channels: vec![],
+            channel_priority: None,
             python: None,
             env_id: Some("test-env-1".to_string()),
+            pip_dependencies: vec![],
         };
 
         let deps2 = CondaDependencies {
             dependencies: vec!["numpy".to_string()],
             channels: vec![],
+            channel_priority: None,
             python: None,
             env_id: Some("test-env-1".to_string()),
+            pip_dependencies: vec![],
         };
 
         assert_ne!(compute_env_hash(&deps1), compute_env_hash(&deps2));
@@ -259,15 +365,19 @@ mod tests {
         let deps1 = CondaDependencies {
             dependencies: vec!["numpy".to_string()],
             channels: vec!["conda-forge".to_string()],
+            channel_priority: None,
             python: None,
             env_id: Some("test-env-1".to_string()),
+            pip_dependencies: vec![],
         };
 
         let deps2 = CondaDependencies {
             dependencies: vec!["numpy".to_string()],
             channels: vec!["defaults".to_string()],
+            channel_priority: None,
             python: None,
             env_id: Some("test-env-1".to_string()),
+            pip_dependencies: vec![],
         };
 
         assert_ne!(compute_env_hash(&deps1), compute_env_hash(&deps2));
@@ -278,17 +388,235 @@ mod tests {
         let deps1 = CondaDependencies {
             dependencies: vec!["numpy".to_string()],
             channels: vec!["conda-forge".to_string()],
+            channel_priority: None,
             python: None,
             env_id: Some("notebook-1".to_string()),
+            pip_dependencies: vec![],
         };
 
         let deps2 = CondaDependencies {
             dependencies: vec!["numpy".to_string()],
             channels: vec!["conda-forge".to_string()],
+            channel_priority: None,
             python: None,
             env_id: Some("notebook-2".to_string()),
+            pip_dependencies: vec![],
         };
 
         assert_ne!(compute_env_hash(&deps1), compute_env_hash(&deps2));
     }
+
+    #[test]
+    fn test_parse_match_spec() {
+        for spec in [
+            "numpy",
+            "numpy=1.24",
+            "numpy >=1.24,<2",
+            "conda-forge::scipy=1.11",
+        ] {
+            assert_eq!(parse_match_spec(spec).unwrap().spec, spec);
+        }
+        let spec = parse_match_spec(" conda-forge::PyTorch>=2 ").unwrap();
+        assert_eq!(spec.name, "pytorch");
+        assert_eq!(spec.spec, "conda-forge::pytorch>=2");
+
+        for spec in ["", "numpy==", "numpy 1.2 py_0", "@@", "numpy*"] {
+            assert!(
+                parse_match_spec(spec).is_err(),
+                "{spec:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_upsert_match_spec() {
+        let upsert = |deps: &mut Vec<String>, spec: &str| {
+            upsert_match_spec(deps, &parse_match_spec(spec).unwrap())
+        };
+        let mut deps = vec!["numpy=1.24".to_string()];
+
+        // Add new
+        assert!(upsert(&mut deps, "scipy"));
+        // Update existing version
+        assert!(upsert(&mut deps, "numpy>=2"));
+        assert_eq!(deps, vec!["numpy>=2", "scipy"]);
+        // Exact duplicate and bare name are no-ops
+        assert!(!upsert(&mut deps, "numpy>=2"));
+        assert!(!upsert(&mut deps, "numpy"));
+        assert_eq!(deps, vec!["numpy>=2", "scipy"]);
+    }
+
+    #[test]
+    fn test_resolve_dependencies_uses_configured_defaults() {
+        let defaults = CondaDefaults {
+            channels: vec!["internal-mirror".to_string()],
+            strict_channel_priority: false,
+            ..Default::default()
+        };
+        let deps = CondaDependencies {
+            dependencies: vec!["numpy".to_string()],
+            channels: vec![],
+            channel_priority: None,
+            python: None,
+            env_id: None,
+            pip_dependencies: vec![],
+        };
+        let resolved = resolve_dependencies(&deps, &defaults);
+        assert_eq!(resolved.channels, vec!["internal-mirror"]);
+        assert_eq!(
+            resolved.channel_priority,
+            kernel_env::ChannelPriority::Disabled
+        );
+
+        // The notebook's own channels and priority win
+        let own = CondaDependencies {
+            channels: vec!["bioconda".to_string()],
+            channel_priority: Some(kernel_env::ChannelPriority::Strict),
+            ..deps
+        };
+        let resolved = resolve_dependencies(&own, &defaults);
+        assert_eq!(resolved.channels, vec!["bioconda"]);
+        assert_eq!(
+            resolved.channel_priority,
+            kernel_env::ChannelPriority::Strict
+        );
+    }
+
+    #[test]
+    fn test_set_dependencies_keeps_pip_dependencies() {
+        let mut metadata = nbformat::v4::Metadata {
+            kernelspec: None,
+            language_info: None,
+            authors: None,
+            additional: Default::default(),
+        };
+        let deps = CondaDependencies {
+            dependencies: vec!["gdal".to_string()],
+            channels: vec!["conda-forge".to_string()],
+            channel_priority: None,
+            python: None,
+            env_id: None,
+            pip_dependencies: vec!["overturemaps".to_string()],
+        };
+        set_dependencies(&mut metadata, &deps);
+
+        let extracted = extract_dependencies(&metadata).unwrap();
+        assert_eq!(extracted.dependencies, vec!["gdal"]);
+        assert_eq!(extracted.pip_dependencies, vec!["overturemaps"]);
+
+        // Conda-only notebooks don't grow an empty pip list
+        set_dependencies(
+            &mut metadata,
+            &CondaDependencies {
+                pip_dependencies: vec![],
+                ..deps
+            },
+        );
+        assert!(metadata.additional["runt"]["conda"]
+            .get("pip_dependencies")
+            .is_none());
+    }
+
+    #[test]
+    fn test_tauri_progress_handler_emits_every_phase() {
+        use kernel_env::ProgressHandler;
+        use std::sync::Mutex;
+        use tauri::Listener;
+
+        let app = tauri::test::mock_app();
+        let received = Arc::new(Mutex::new(Vec::<serde_json::Value>::new()));
+        let sink = received.clone();
+        app.listen_any("env:progress", move |event| {
+            sink.lock()
+                .unwrap()
+                .push(serde_json::from_str(event.payload()).unwrap());
+        });
+
+        let phases = vec![
+            EnvProgressPhase::Starting {
+                env_hash: "abc".to_string(),
+            },
+            EnvProgressPhase::CacheHit {
+                env_path: "/envs/abc".to_string(),
+            },
+            EnvProgressPhase::FetchingRepodata {
+                channels: vec!["conda-forge".to_string()],
+            },
+            EnvProgressPhase::RepodataComplete {
+                record_count: 10,
+                elapsed_ms: 5,
+            },
+            EnvProgressPhase::Solving { spec_count: 2 },
+            EnvProgressPhase::SolveComplete {
+                package_count: 4,
+                elapsed_ms: 7,
+            },
+            EnvProgressPhase::Installing { total: 4 },
+            EnvProgressPhase::DownloadProgress {
+                completed: 1,
+                total: 4,
+                current_package: "numpy".to_string(),
+                bytes_downloaded: 100,
+                bytes_total: Some(400),
+                bytes_per_second: 50.0,
+            },
+            EnvProgressPhase::LinkProgress {
+                completed: 2,
+                total: 4,
+                current_package: "pandas".to_string(),
+            },
+            EnvProgressPhase::InstallComplete { elapsed_ms: 9 },
+            EnvProgressPhase::CreatingVenv,
+            EnvProgressPhase::InstallingPackages {
+                packages: vec!["requests".to_string()],
+            },
+            EnvProgressPhase::CopyProgress {
+                bytes_copied: 1,
+                bytes_total: 2,
+            },
+            EnvProgressPhase::CopyCancelled,
+            EnvProgressPhase::Ready {
+                env_path: "/envs/abc".to_string(),
+                python_path: "/envs/abc/bin/python".to_string(),
+            },
+            EnvProgressPhase::Error {
+                message: "boom".to_string(),
+            },
+        ];
+
+        let handler = TauriProgressHandler::new(app.handle().clone());
+        for phase in phases {
+            handler.on_progress("uv", phase);
+        }
+
+        let received = received.lock().unwrap();
+        let names: Vec<&str> = received
+            .iter()
+            .map(|event| event["phase"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "starting",
+                "cache_hit",
+                "fetching_repodata",
+                "repodata_complete",
+                "solving",
+                "solve_complete",
+                "installing",
+                "download_progress",
+                "link_progress",
+                "install_complete",
+                "creating_venv",
+                "installing_packages",
+                "copy_progress",
+                "copy_cancelled",
+                "ready",
+                "error",
+            ]
+        );
+        assert!(received.iter().all(|event| event["env_type"] == "uv"));
+        assert_eq!(received[7]["current_package"], "numpy");
+        assert_eq!(received[15]["message"], "boom");
+    }
 }
@@ -0,0 +1,346 @@
+//! Best-effort translation of inline dependencies between uv (PyPI) and
+//! conda sections.
+//!
+//! Most Python packages share a name on PyPI and conda-forge, but a handful
+//! are renamed (`opencv-python` is `opencv`, `torch` is `pytorch`) and some
+//! conda packages have no PyPI counterpart at all. Migration maps names
+//! through [`PYPI_TO_CONDA`] and [`SAME_NAME`], translates version constraints
+//! where the two syntaxes overlap, and flags everything it had to guess at.
+//! uv packages with no known conda package move to conda's pip phase, so
+//! they still install.
+//!
+//! [`PYPI_TO_CONDA`]: kernel_env::package_names::PYPI_TO_CONDA
+
+use std::str::FromStr;
+
+use kernel_env::package_names::{conda_rename, pypi_rename};
+use pep508_rs::{Requirement, VerbatimUrl, VersionOrUrl};
+
+use crate::conda_env::parse_match_spec;
+use crate::uv_env::parse_requirement;
+
+/// Packages known to have the same name on PyPI and conda-forge.
+const SAME_NAME: &[&str] = &[
+    "altair",
+    "beautifulsoup4",
+    "bokeh",
+    "cython",
+    "dask",
+    "fastapi",
+    "flask",
+    "geopandas",
+    "h5py",
+    "httpx",
+    "ipywidgets",
+    "jax",
+    "lxml",
+    "matplotlib",
+    "networkx",
+    "nltk",
+    "numba",
+    "numpy",
+    "openpyxl",
+    "pandas",
+    "pillow",
+    "plotly",
+    "polars",
+    "pyarrow",
+    "pydantic",
+    "pyyaml",
+    "requests",
+    "rich",
+    "scikit-image",
+    "scikit-learn",
+    "scipy",
+    "seaborn",
+    "shapely",
+    "spacy",
+    "sqlalchemy",
+    "statsmodels",
+    "sympy",
+    "tensorflow",
+    "tqdm",
+    "transformers",
+    "xarray",
+];
+
+/// conda packages with no PyPI counterpart (interpreters, system libraries,
+/// toolchains). Not carried over to uv.
+const CONDA_ONLY: &[&str] = &[
+    "python",
+    "pip",
+    "nodejs",
+    "r-base",
+    "cudatoolkit",
+    "cuda-toolkit",
+    "compilers",
+    "c-compiler",
+    "cxx-compiler",
+    "make",
+    "git",
+    "ffmpeg",
+    "graphviz",
+];
+
+/// A source dependency migration couldn't translate cleanly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlaggedDependency {
+    /// The dependency as written in the source section
+    pub spec: String,
+    pub reason: String,
+}
+
+/// Dependencies translated for the target section.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Migration {
+    pub dependencies: Vec<String>,
+    /// PyPI requirements for conda's pip phase (uv → conda only)
+    pub pip_dependencies: Vec<String>,
+    pub flagged: Vec<FlaggedDependency>,
+}
+
+impl Migration {
+    fn flag(&mut self, spec: &str, reason: &str) {
+        self.flagged.push(FlaggedDependency {
+            spec: spec.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+}
+
+/// The conda package for a normalized PyPI name, if known.
+fn conda_name(pypi_name: &str) -> Option<&'static str> {
+    conda_rename(pypi_name).or_else(|| SAME_NAME.iter().find(|name| **name == pypi_name).copied())
+}
+
+/// The PyPI package for a normalized conda name, if known.
+fn pypi_name(conda_name: &str) -> Option<&'static str> {
+    pypi_rename(conda_name).or_else(|| SAME_NAME.iter().find(|name| **name == conda_name).copied())
+}
+
+/// Translate uv (PEP 508) requirements into conda match specs.
+///
+/// Requirements with extras, environment markers or URLs, and packages with
+/// no known conda name, go to `pip_dependencies` unchanged.
+pub fn uv_to_conda(requirements: &[String]) -> Migration {
+    let mut migration = Migration::default();
+    for spec in requirements {
+        let Ok(req) = Requirement::<VerbatimUrl>::from_str(spec.trim()) else {
+            migration.flag(spec, "not a valid requirement; left out");
+            continue;
+        };
+        let plain = req.extras.is_empty() && req.marker.is_true();
+        let (name, version) = match (&req.version_or_url, plain) {
+            (Some(VersionOrUrl::Url(_)), _) | (_, false) => {
+                migration.pip_dependencies.push(spec.trim().to_string());
+                migration.flag(spec, "needs pip (extras, markers or a URL)");
+                continue;
+            }
+            (Some(VersionOrUrl::VersionSpecifier(specifiers)), true) => (
+                req.name.to_string(),
+                specifiers.to_string().replace(' ', ""),
+            ),
+            (None, true) => (req.name.to_string(), String::new()),
+        };
+
+        let Some(conda) = conda_name(&name) else {
+            migration.pip_dependencies.push(spec.trim().to_string());
+            migration.flag(spec, "no known conda package; installed with pip");
+            continue;
+        };
+        match parse_match_spec(&format!("{conda}{version}")) {
+            Ok(converted) => migration.dependencies.push(converted.spec),
+            Err(_) => {
+                migration.dependencies.push(conda.to_string());
+                migration.flag(spec, "version constraint can't be translated; dropped");
+            }
+        }
+    }
+    migration
+}
+
+/// Translate conda match specs into uv (PEP 508) requirements.
+///
+/// Channel prefixes and build strings are dropped. Packages not in the
+/// translation table keep their conda name, since most conda Python
+/// packages match PyPI, but are flagged for review.
+pub fn conda_to_uv(match_specs: &[String]) -> Migration {
+    let mut migration = Migration::default();
+    for spec in match_specs {
+        let Ok(parsed) = parse_match_spec(spec) else {
+            migration.flag(spec, "not a valid match spec; left out");
+            continue;
+        };
+        if CONDA_ONLY.contains(&parsed.name.as_str()) {
+            migration.flag(spec, "conda-only package; not migrated");
+            continue;
+        }
+        let name = match pypi_name(&parsed.name) {
+            Some(name) => name.to_string(),
+            None => {
+                migration.flag(spec, "no known PyPI package; kept under the conda name");
+                parsed.name.clone()
+            }
+        };
+
+        let constraint = &parsed.spec[parsed.spec.rfind("::").map_or(0, |i| i + 2)..];
+        let constraint = &constraint[parsed.name.len()..];
+        match conda_version_to_pep440(constraint)
+            .and_then(|version| parse_requirement(&format!("{name}{version}")).ok())
+        {
+            Some(converted) => migration.dependencies.push(converted.spec),
+            None => {
+                migration.dependencies.push(name);
+                migration.flag(spec, "version constraint can't be translated; dropped");
+            }
+        }
+    }
+    migration
+}
+
+/// Rewrite a conda version constraint (the part of a match spec after the
+/// name) as a PEP 440 specifier. `=1.2` and `1.2.*` become `==1.2.*`; a
+/// build string after the version is dropped. `None` for constraints PEP 440
+/// can't express, like `|` alternatives.
+fn conda_version_to_pep440(constraint: &str) -> Option<String> {
+    let constraint = constraint.trim();
+    if constraint.is_empty() {
+        return Some(String::new());
+    }
+    if constraint.contains('|') || constraint.contains('[') {
+        return None;
+    }
+    // "1.2.* py311_0" carries a build string
+    let constraint = constraint.split_whitespace().next()?;
+    let constraint = match constraint.strip_prefix('=') {
+        Some(rest) if !rest.starts_with('=') => {
+            let version = rest.split('=').next()?;
+            return Some(format!("=={}.*", version.trim_end_matches(".*")));
+        }
+        _ => constraint,
+    };
+
+    let parts: Vec<String> = constraint
+        .split(',')
+        .map(|part| {
+            if part.starts_with(|c: char| c.is_ascii_digit()) {
+                format!("=={part}")
+            } else {
+                part.to_string()
+            }
+        })
+        .collect();
+    Some(parts.join(","))
+}
+
+/// The conda `python` version for a uv `requires-python` specifier, using
+/// its lowest version the way `uv venv --python` does.
+pub fn requires_python_to_conda(requires_python: &str) -> Option<String> {
+    let version = requires_python
+        .trim_start_matches(|c: char| !c.is_ascii_digit())
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .next()
+        .unwrap_or_default();
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn specs(specs: &[&str]) -> Vec<String> {
+        specs.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_uv_to_conda_mixed() {
+        let migration = uv_to_conda(&specs(&[
+            "numpy>=1.24,<2",
+            "opencv-python==4.9.0.80",
+            "Torch",
+            "my-internal-lib>=0.3",
+            "dask[dataframe]",
+        ]));
+
+        assert_eq!(
+            migration.dependencies,
+            vec!["numpy>=1.24,<2", "opencv==4.9.0.80", "pytorch"]
+        );
+        assert_eq!(
+            migration.pip_dependencies,
+            vec!["my-internal-lib>=0.3", "dask[dataframe]"]
+        );
+        let flagged: Vec<&str> = migration.flagged.iter().map(|f| f.spec.as_str()).collect();
+        assert_eq!(flagged, vec!["my-internal-lib>=0.3", "dask[dataframe]"]);
+        assert!(migration.flagged[0]
+            .reason
+            .contains("no known conda package"));
+    }
+
+    #[test]
+    fn test_conda_to_uv_mixed() {
+        let migration = conda_to_uv(&specs(&[
+            "conda-forge::pandas>=2.0",
+            "opencv=4.9",
+            "pytorch",
+            "numpy 1.26.* py311_0",
+            "python=3.11",
+            "graphviz",
+            "some-conda-tool",
+            "scipy 1.11.*|1.12.*",
+        ]));
+
+        assert_eq!(
+            migration.dependencies,
+            vec![
+                "pandas>=2.0",
+                "opencv-python==4.9.*",
+                "torch",
+                "numpy==1.26.*",
+                "some-conda-tool",
+                "scipy",
+            ]
+        );
+        let flagged: Vec<(&str, &str)> = migration
+            .flagged
+            .iter()
+            .map(|f| (f.spec.as_str(), f.reason.as_str()))
+            .collect();
+        assert_eq!(
+            flagged,
+            vec![
+                ("python=3.11", "conda-only package; not migrated"),
+                ("graphviz", "conda-only package; not migrated"),
+                (
+                    "some-conda-tool",
+                    "no known PyPI package; kept under the conda name"
+                ),
+                (
+                    "scipy 1.11.*|1.12.*",
+                    "version constraint can't be translated; dropped"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_round_trip_renamed_packages() {
+        let uv = specs(&["opencv-python>=4", "tables", "graphviz"]);
+        let conda = uv_to_conda(&uv);
+        assert_eq!(
+            conda.dependencies,
+            vec!["opencv>=4", "pytables", "python-graphviz"]
+        );
+        assert_eq!(conda_to_uv(&conda.dependencies).dependencies, uv);
+    }
+
+    #[test]
+    fn test_requires_python_to_conda() {
+        assert_eq!(requires_python_to_conda(">=3.10").as_deref(), Some("3.10"));
+        assert_eq!(
+            requires_python_to_conda(">=3.9,<3.13").as_deref(),
+            Some("3.9")
+        );
+        assert_eq!(requires_python_to_conda(""), None);
+    }
+}
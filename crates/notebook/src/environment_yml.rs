@@ -2,12 +2,21 @@
 //!
 //! This module handles finding and parsing conda environment.yml files to extract
 //! dependencies for notebook environments. Supports both conda dependencies
-//! and pip dependencies from the `pip:` sub-list.
+//! and pip dependencies from the `pip:` sub-list (with `-r` requirement files
+//! resolved relative to the environment.yml).
+//!
+//! Lines carrying a conda-build style selector comment (`- pywin32  # [win]`)
+//! are dropped before parsing when the selector doesn't match the current
+//! platform (see [`runtimed::environment_yml`]), and the `variables:` block
+//! is collected so it can be set in the kernel's environment.
 
 use anyhow::{anyhow, Result};
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
+use runtimed::environment_yml::{apply_selectors, yaml_scalar_to_string};
+
 use crate::conda_env::CondaDependencies;
 
 /// Configuration extracted from an environment.yml file.
@@ -25,6 +34,8 @@ pub struct EnvironmentYmlConfig {
     pub pip_dependencies: Vec<String>,
     /// Python version constraint extracted from the `python` dependency.
     pub python: Option<String>,
+    /// Environment variables from the `variables:` block.
+    pub variables: BTreeMap<String, String>,
 }
 
 /// Serializable info about a detected environment.yml for the frontend.
@@ -48,6 +59,8 @@ pub struct EnvironmentYmlInfo {
     pub python: Option<String>,
     /// Conda channels.
     pub channels: Vec<String>,
+    /// Names of the variables set by the `variables:` block.
+    pub variable_names: Vec<String>,
 }
 
 // Raw YAML structure for parsing
@@ -57,6 +70,7 @@ struct RawEnvironmentYml {
     name: Option<String>,
     channels: Option<Vec<String>>,
     dependencies: Option<Vec<serde_yaml::Value>>,
+    variables: Option<serde_yaml::Mapping>,
 }
 
 /// Find an environment.yml (or environment.yaml) file by walking up from the given path.
@@ -116,6 +130,7 @@ pub fn parse_environment_yml(path: &Path) -> Result<EnvironmentYmlConfig> {
     let content = std::fs::read_to_string(path)
         .map_err(|e| anyhow!("Failed to read environment.yml: {}", e))?;
 
+    let content = apply_selectors(&content);
     let raw: RawEnvironmentYml = serde_yaml::from_str(&content)
         .map_err(|e| anyhow!("Failed to parse environment.yml: {}", e))?;
 
@@ -144,9 +159,10 @@ pub fn parse_environment_yml(path: &Path) -> Result<EnvironmentYmlConfig> {
                     if let Some(serde_yaml::Value::Sequence(pip_list)) =
                         map.get(serde_yaml::Value::String("pip".to_string()))
                     {
+                        let yml_dir = path.parent().unwrap_or(Path::new("."));
                         for pip_dep in pip_list {
                             if let serde_yaml::Value::String(s) = pip_dep {
-                                pip_dependencies.push(s.clone());
+                                pip_dependencies.push(resolve_pip_dependency(s, yml_dir));
                             }
                         }
                     }
@@ -159,6 +175,17 @@ pub fn parse_environment_yml(path: &Path) -> Result<EnvironmentYmlConfig> {
         }
     }
 
+    let variables = raw
+        .variables
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| {
+            let key = yaml_scalar_to_string(&key)?;
+            let value = yaml_scalar_to_string(&value)?;
+            Some((key, value))
+        })
+        .collect();
+
     Ok(EnvironmentYmlConfig {
         path: path.to_path_buf(),
         name,
@@ -166,9 +193,36 @@ pub fn parse_environment_yml(path: &Path) -> Result<EnvironmentYmlConfig> {
         dependencies,
         pip_dependencies,
         python,
+        variables,
     })
 }
 
+/// Resolve `-r`/`--requirement` entries in a `pip:` list against the yml's directory.
+///
+/// pip resolves these relative to the environment file, not the working
+/// directory of whoever installs the env, so make them absolute up front.
+/// Any other entry is returned unchanged.
+fn resolve_pip_dependency(dep: &str, yml_dir: &Path) -> String {
+    let dep = dep.trim();
+    let file = dep
+        .strip_prefix("--requirement=")
+        .or_else(|| dep.strip_prefix("--requirement "))
+        .or_else(|| dep.strip_prefix("-r"));
+
+    match file.map(str::trim) {
+        Some(file) if !file.is_empty() => {
+            let file = Path::new(file);
+            let resolved = if file.is_absolute() {
+                file.to_path_buf()
+            } else {
+                yml_dir.join(file)
+            };
+            format!("-r {}", resolved.display())
+        }
+        _ => dep.to_string(),
+    }
+}
+
 /// Check if a dependency string refers to the python package.
 ///
 /// Matches "python", "python=3.10", "python>=3.9", etc.
@@ -239,6 +293,7 @@ pub fn create_environment_yml_info(
         pip_dependency_count: config.pip_dependencies.len(),
         python: config.python.clone(),
         channels: config.channels.clone(),
+        variable_names: config.variables.keys().cloned().collect(),
     }
 }
 
@@ -247,8 +302,10 @@ pub fn convert_to_conda_dependencies(config: &EnvironmentYmlConfig) -> CondaDepe
     CondaDependencies {
         dependencies: config.dependencies.clone(),
         channels: config.channels.clone(),
+        channel_priority: None,
         python: config.python.clone(),
         env_id: None,
+        pip_dependencies: config.pip_dependencies.clone(),
     }
 }
 
@@ -606,6 +663,74 @@ dependencies:
             .contains(&"transformers".to_string()));
     }
 
+    #[test]
+    fn test_parse_pip_requirement_files_relative_to_yml() {
+        let temp = TempDir::new().unwrap();
+        let env_dir = temp.path().join("env");
+        std::fs::create_dir(&env_dir).unwrap();
+        create_environment_yml(
+            &env_dir,
+            "environment.yml",
+            r#"
+dependencies:
+  - numpy
+  - pip:
+    - torch
+    - -r requirements.txt
+    - --requirement=../shared/dev.txt
+    - -r /abs/extra.txt
+    - requests
+"#,
+        );
+
+        let config = parse_environment_yml(&env_dir.join("environment.yml")).unwrap();
+        // Order is preserved so pip sees entries exactly as declared
+        assert_eq!(
+            config.pip_dependencies,
+            vec![
+                "torch".to_string(),
+                format!("-r {}", env_dir.join("requirements.txt").display()),
+                format!("-r {}", env_dir.join("../shared/dev.txt").display()),
+                "-r /abs/extra.txt".to_string(),
+                "requests".to_string(),
+            ]
+        );
+        assert_eq!(
+            convert_to_conda_dependencies(&config).pip_dependencies,
+            config.pip_dependencies
+        );
+    }
+
+    #[test]
+    fn test_parse_variables() {
+        let temp = TempDir::new().unwrap();
+        create_environment_yml(
+            temp.path(),
+            "environment.yml",
+            r#"
+name: myenv
+dependencies:
+  - numpy
+variables:
+  DATA_DIR: /data/shared
+  OMP_NUM_THREADS: 4
+  DEBUG: true
+"#,
+        );
+
+        let config = parse_environment_yml(&temp.path().join("environment.yml")).unwrap();
+        assert_eq!(config.variables.len(), 3);
+        assert_eq!(config.variables["DATA_DIR"], "/data/shared");
+        assert_eq!(config.variables["OMP_NUM_THREADS"], "4");
+        assert_eq!(config.variables["DEBUG"], "true");
+
+        let info = create_environment_yml_info(&config, &temp.path().join("nb.ipynb"));
+        assert_eq!(
+            info.variable_names,
+            vec!["DATA_DIR", "DEBUG", "OMP_NUM_THREADS"]
+        );
+    }
+
     #[test]
     fn test_parse_no_channels_defaults() {
         let temp = TempDir::new().unwrap();
@@ -774,6 +899,7 @@ dependencies:
             dependencies: vec!["numpy".to_string(), "pandas>=2.0".to_string()],
             pip_dependencies: vec!["requests".to_string()],
             python: Some("3.11".to_string()),
+            variables: BTreeMap::new(),
         };
 
         let conda_deps = convert_to_conda_dependencies(&config);
@@ -781,6 +907,7 @@ dependencies:
         assert_eq!(conda_deps.channels, config.channels);
         assert_eq!(conda_deps.python, config.python);
         assert!(conda_deps.env_id.is_none());
+        assert_eq!(conda_deps.pip_dependencies, vec!["requests"]);
     }
 
     #[test]
@@ -792,6 +919,7 @@ dependencies:
             dependencies: vec![],
             pip_dependencies: vec![],
             python: None,
+            variables: BTreeMap::new(),
         };
 
         let conda_deps = convert_to_conda_dependencies(&config);
@@ -813,6 +941,7 @@ dependencies:
             dependencies: vec!["numpy".to_string()],
             pip_dependencies: vec![],
             python: None,
+            variables: BTreeMap::new(),
         };
 
         let conda_deps = convert_to_conda_dependencies(&config);
@@ -839,6 +968,7 @@ dependencies:
             dependencies: vec!["numpy".to_string(), "pandas".to_string()],
             pip_dependencies: vec!["requests".to_string()],
             python: Some("3.11".to_string()),
+            variables: BTreeMap::new(),
         };
 
         let notebook_path = notebooks_dir.join("test.ipynb");
@@ -859,6 +989,7 @@ dependencies:
             dependencies: vec!["numpy".to_string()],
             pip_dependencies: vec![],
             python: None,
+            variables: BTreeMap::new(),
         };
 
         let notebook_path = temp.path().join("test.ipynb");
@@ -880,6 +1011,7 @@ dependencies:
             ],
             pip_dependencies: vec!["requests".to_string(), "fastapi".to_string()],
             python: Some("3.10".to_string()),
+            variables: BTreeMap::new(),
         };
 
         let notebook_path = PathBuf::from("/test/notebook.ipynb");
@@ -902,6 +1034,7 @@ dependencies:
             dependencies: vec![],
             pip_dependencies: vec![],
             python: None,
+            variables: BTreeMap::new(),
         };
 
         let notebook_path = PathBuf::from("/test/notebook.ipynb");
@@ -926,6 +1059,7 @@ dependencies:
             dependencies: vec!["numpy".to_string(), "pandas".to_string()],
             pip_dependencies: vec!["requests".to_string()],
             python: Some("3.10".to_string()),
+            variables: BTreeMap::new(),
         };
 
         let (conda, pip) = get_all_dependencies(&config);
@@ -3,9 +3,13 @@
 //! Supports:
 //! - Python via `ruff format` (auto-bootstrapped via rattler if not on PATH)
 //! - TypeScript/JavaScript via `deno fmt` (auto-bootstrapped via rattler if not on PATH)
+//!
+//! Also renders a cell and its outputs as Markdown ([`cell_to_markdown`]) for
+//! pasting into bug reports.
 
 use crate::tools;
 use anyhow::{anyhow, Result};
+use nbformat::v4::{Cell, Output};
 use serde::{Deserialize, Serialize};
 use std::process::Stdio;
 use tokio::io::AsyncWriteExt;
@@ -154,9 +158,135 @@ pub async fn format_deno(source: &str, language: &str) -> Result<FormatResult> {
     }
 }
 
+/// Wrap `text` in a fenced code block, using a fence longer than any run of
+/// backticks inside it.
+fn fenced(language: &str, text: &str) -> String {
+    let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    let body = text.strip_suffix('\n').unwrap_or(text);
+    format!("{fence}{language}\n{body}\n{fence}")
+}
+
+/// The `text/plain` representation of a display or execute result, if any.
+fn plain_text(data: &serde_json::Value) -> Option<String> {
+    match data.get("text/plain")? {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Array(lines) => {
+            Some(lines.iter().filter_map(|line| line.as_str()).collect())
+        }
+        _ => None,
+    }
+}
+
+fn output_to_markdown(output: &Output) -> String {
+    match output {
+        Output::Stream { text, .. } => fenced("", &text.0),
+        Output::Error(error) => {
+            let traceback = runtimed::output_store::strip_ansi(&error.traceback.join("\n"));
+            if traceback.trim().is_empty() {
+                fenced("", &format!("{}: {}", error.ename, error.evalue))
+            } else {
+                fenced("", &traceback)
+            }
+        }
+        Output::DisplayData(_) | Output::ExecuteResult(_) => {
+            let value = serde_json::to_value(output).unwrap_or_default();
+            let data = value.get("data").cloned().unwrap_or_default();
+            if let Some(text) = plain_text(&data) {
+                return fenced("", &text);
+            }
+            let mime = data
+                .as_object()
+                .and_then(|bundle| bundle.keys().next().cloned())
+                .unwrap_or_default();
+            if mime.starts_with("image/") {
+                "[image output omitted]".to_string()
+            } else {
+                format!("[{mime} output omitted]")
+            }
+        }
+    }
+}
+
+/// Render a cell as Markdown: code cells as a fenced `language` block
+/// followed by their outputs, markdown and raw cells as their source.
+///
+/// Streams are kept as written and tracebacks are stripped of ANSI colors.
+/// Results without a `text/plain` form (plots, HTML) are noted as omitted.
+pub fn cell_to_markdown(cell: &Cell, language: &str) -> String {
+    match cell {
+        Cell::Code {
+            source, outputs, ..
+        } => {
+            let mut blocks = vec![fenced(language, &source.concat())];
+            blocks.extend(outputs.iter().map(output_to_markdown));
+            blocks.join("\n\n")
+        }
+        Cell::Markdown { source, .. } | Cell::Raw { source, .. } => source.concat(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
+
+    fn code_cell(source: &str, outputs: serde_json::Value) -> Cell {
+        serde_json::from_value(json!({
+            "cell_type": "code",
+            "id": "a",
+            "metadata": {},
+            "execution_count": 1,
+            "source": source,
+            "outputs": outputs
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_cell_to_markdown_with_error() {
+        let cell = code_cell(
+            "1 / 0",
+            json!([{
+                "output_type": "error",
+                "ename": "ZeroDivisionError",
+                "evalue": "division by zero",
+                "traceback": [
+                    "\u{1b}[0;31m---------------------------------------------------------------------------\u{1b}[0m",
+                    "\u{1b}[0;31mZeroDivisionError\u{1b}[0m: division by zero"
+                ]
+            }]),
+        );
+
+        assert_eq!(
+            cell_to_markdown(&cell, "python"),
+            "```python\n1 / 0\n```\n\n```\n\
+             ---------------------------------------------------------------------------\n\
+             ZeroDivisionError: division by zero\n```"
+        );
+    }
+
+    #[test]
+    fn test_cell_to_markdown_with_stream_and_image() {
+        let cell = code_cell(
+            "print('hi')\nplot()",
+            json!([
+                {"output_type": "stream", "name": "stdout", "text": ["hi\n", "there\n"]},
+                {"output_type": "display_data", "metadata": {}, "data": {"image/png": "iVBORw0KGgo="}}
+            ]),
+        );
+
+        assert_eq!(
+            cell_to_markdown(&cell, "python"),
+            "```python\nprint('hi')\nplot()\n```\n\n```\nhi\nthere\n```\n\n[image output omitted]"
+        );
+    }
+
+    #[test]
+    fn test_cell_to_markdown_lengthens_fence() {
+        let cell = code_cell("s = '```'", json!([]));
+        assert_eq!(cell_to_markdown(&cell, ""), "````\ns = '```'\n````");
+    }
 
     #[tokio::test]
     async fn test_format_python_empty() {
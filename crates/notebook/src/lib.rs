@@ -1,13 +1,20 @@
 pub mod cli_install;
+pub mod command_error;
 pub mod conda_env;
 pub mod deno_env;
+pub mod dependency_migration;
 pub mod environment_yml;
 pub mod format;
 pub mod menu;
+pub mod metadata_migration;
+pub mod notebook_directory;
 pub mod notebook_state;
 pub mod pixi;
 pub mod project_file;
 pub mod pyproject;
+pub mod raw_metadata;
+pub mod reconcile;
+pub mod reveal;
 pub mod runtime;
 pub mod session;
 pub mod settings;
@@ -17,20 +24,26 @@ pub use kernel_launch::tools;
 pub mod trust;
 pub mod typosquat;
 pub mod uv_env;
+pub mod validation;
 #[cfg(feature = "webdriver-test")]
 pub mod webdriver;
 
 pub use runtime::Runtime;
 
+use command_error::CommandError;
+
 use notebook_state::{FrontendCell, NotebookState};
 use runtimed::notebook_doc::CellSnapshot;
 use runtimed::notebook_sync_client::{NotebookSyncClient, NotebookSyncHandle};
-use runtimed::protocol::{CompletionItem, HistoryEntry, NotebookRequest, NotebookResponse};
+use runtimed::protocol::{
+    CompletionItem, DebugCommand, EnvOption, HistoryEntry, NotebookBroadcast, NotebookRequest,
+    NotebookResponse,
+};
 
 use log::{debug, info, warn};
 use nbformat::v4::{Cell, CellId, CellMetadata};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 
 /// Shared notebook sync handle for cross-window state synchronization.
@@ -57,24 +70,31 @@ impl WindowNotebookRegistry {
         &self,
         label: impl Into<String>,
         context: WindowNotebookContext,
-    ) -> Result<(), String> {
-        let mut contexts = self.contexts.lock().map_err(|e| e.to_string())?;
+    ) -> Result<(), CommandError> {
+        let mut contexts = self.contexts.lock()?;
         contexts.insert(label.into(), context);
         Ok(())
     }
 
-    fn get(&self, label: &str) -> Result<WindowNotebookContext, String> {
-        let contexts = self.contexts.lock().map_err(|e| e.to_string())?;
-        contexts
-            .get(label)
-            .cloned()
-            .ok_or_else(|| format!("No notebook context for window '{label}'"))
+    fn get(&self, label: &str) -> Result<WindowNotebookContext, CommandError> {
+        let contexts = self.contexts.lock()?;
+        contexts.get(label).cloned().ok_or_else(|| {
+            CommandError::NotFound(format!("No notebook context for window '{label}'"))
+        })
     }
 }
 
 /// Newtype wrapper for reconnect-in-progress flag (distinguishes from other AtomicBool states).
 struct ReconnectInProgress(Arc<AtomicBool>);
 
+/// Cancellation flag for the environment copy done by `clone_notebook_to_path`.
+struct CloneCopyCancel(Arc<AtomicBool>);
+
+/// Notebook picker listings for windows opened on a directory, keyed by
+/// window label. A window's entry is dropped once a notebook is picked.
+#[derive(Clone, Default)]
+struct DirectoryListings(Arc<Mutex<HashMap<String, notebook_directory::DirectoryListing>>>);
+
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
@@ -150,21 +170,21 @@ fn derive_notebook_id(state: &NotebookState) -> String {
 fn notebook_state_for_window(
     window: &tauri::Window,
     registry: &WindowNotebookRegistry,
-) -> Result<Arc<Mutex<NotebookState>>, String> {
+) -> Result<Arc<Mutex<NotebookState>>, CommandError> {
     Ok(registry.get(window.label())?.notebook_state)
 }
 
 fn notebook_sync_for_window(
     window: &tauri::Window,
     registry: &WindowNotebookRegistry,
-) -> Result<SharedNotebookSync, String> {
+) -> Result<SharedNotebookSync, CommandError> {
     Ok(registry.get(window.label())?.notebook_sync)
 }
 
 fn sync_generation_for_window(
     window: &tauri::Window,
     registry: &WindowNotebookRegistry,
-) -> Result<Arc<AtomicU64>, String> {
+) -> Result<Arc<AtomicU64>, CommandError> {
     Ok(registry.get(window.label())?.sync_generation)
 }
 
@@ -193,18 +213,38 @@ fn cell_snapshot_to_nbformat(snap: &CellSnapshot) -> Cell {
             .map(|s| s.to_string())
             .collect()
     };
+    let mut additional = std::collections::HashMap::new();
+    if !snap.collapsed_outputs.is_empty() {
+        additional.insert(
+            "runt".to_string(),
+            serde_json::json!({ "collapsed_outputs": snap.collapsed_outputs }),
+        );
+    }
     let metadata = CellMetadata {
         id: None,
-        collapsed: None,
-        scrolled: None,
+        collapsed: snap.collapsed,
+        scrolled: snap.scrolled,
         deletable: None,
         editable: None,
         format: None,
         name: None,
-        tags: None,
+        tags: (!snap.tags.is_empty()).then(|| snap.tags.clone()),
         jupyter: None,
-        execution: None,
-        additional: std::collections::HashMap::new(),
+        execution: snap
+            .execution
+            .as_ref()
+            .map(|timing| nbformat::v4::ExecutionMetadata {
+                iopub_execute_input: Some(timing.started_at.clone()),
+                iopub_status_busy: None,
+                shell_execute_reply: None,
+                shell_execute_reply_started: None,
+                iopub_status_idle: Some(timing.completed_at.clone()),
+                additional: std::collections::HashMap::from([(
+                    "duration_ms".to_string(),
+                    serde_json::json!(timing.duration_ms),
+                )]),
+            }),
+        additional,
     };
 
     match snap.cell_type.as_str() {
@@ -242,6 +282,39 @@ fn cell_snapshot_to_nbformat(snap: &CellSnapshot) -> Cell {
     }
 }
 
+/// The daemon's cells with output manifests resolved to nbformat JSON.
+///
+/// [`NotebookSyncHandle::get_cells`] returns kernel outputs as manifest
+/// hashes, which [`cell_snapshot_to_nbformat`] can't parse and drops, so
+/// anything that compares or copies outputs needs these instead.
+async fn resolved_daemon_cells(
+    handle: &NotebookSyncHandle,
+) -> Result<Vec<CellSnapshot>, CommandError> {
+    match handle
+        .send_request(NotebookRequest::GetResolvedCells {})
+        .await?
+    {
+        NotebookResponse::ResolvedCells { cells } => Ok(cells),
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
+    }
+}
+
+/// A cell's output layout from its nbformat metadata: `collapsed`,
+/// `scrolled`, and the indices under `runt.collapsed_outputs`.
+fn cell_output_layout(cell: &Cell) -> (Option<bool>, Option<bool>, Vec<usize>) {
+    let metadata = cell.metadata();
+    let collapsed_outputs = metadata
+        .additional
+        .get("runt")
+        .and_then(|runt| runt.get("collapsed_outputs"))
+        .and_then(|indices| serde_json::from_value(indices.clone()).ok())
+        .unwrap_or_default();
+    (metadata.collapsed, metadata.scrolled, collapsed_outputs)
+}
+
 /// Initialize notebook sync with the daemon.
 ///
 /// Connects to the daemon's notebook sync service using the split pattern,
@@ -255,13 +328,30 @@ async fn initialize_notebook_sync(
     notebook_state: Arc<Mutex<NotebookState>>,
     notebook_sync: SharedNotebookSync,
     sync_generation: Arc<AtomicU64>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     // Increment generation to invalidate any stale cleanup from previous connections
     let current_generation = sync_generation.fetch_add(1, Ordering::SeqCst) + 1;
 
-    let (notebook_id, cells) = {
-        let state = notebook_state.lock().map_err(|e| e.to_string())?;
-        (derive_notebook_id(&state), state.cells_for_frontend())
+    let (notebook_id, cells, layouts, tags) = {
+        let state = notebook_state.lock()?;
+        let layouts: Vec<_> = state
+            .notebook
+            .cells
+            .iter()
+            .map(cell_output_layout)
+            .collect();
+        let tags: Vec<Vec<String>> = state
+            .notebook
+            .cells
+            .iter()
+            .map(|cell| cell.metadata().tags.clone().unwrap_or_default())
+            .collect();
+        (
+            derive_notebook_id(&state),
+            state.cells_for_frontend(),
+            layouts,
+            tags,
+        )
     };
 
     let socket_path = runtimed::default_socket_path();
@@ -275,7 +365,7 @@ async fn initialize_notebook_sync(
     let (handle, mut receiver, mut broadcast_receiver, initial_cells, initial_metadata) =
         NotebookSyncClient::connect_split(socket_path, notebook_id.clone())
             .await
-            .map_err(|e| format!("sync connect: {}", e))?;
+            .map_err(|e| CommandError::NotConnected(format!("sync connect: {}", e)))?;
 
     // Populate Automerge doc if empty (new room or first window)
     if initial_cells.is_empty() {
@@ -289,23 +379,32 @@ async fn initialize_notebook_sync(
                 FrontendCell::Markdown { id, source } => (id.as_str(), "markdown", source.as_str()),
                 FrontendCell::Raw { id, source } => (id.as_str(), "raw", source.as_str()),
             };
-            handle
-                .add_cell(i, id, cell_type)
-                .await
-                .map_err(|e| format!("add_cell: {}", e))?;
+            handle.add_cell(i, id, cell_type).await?;
             if !source.is_empty() {
-                handle
-                    .update_source(id, source)
-                    .await
-                    .map_err(|e| format!("update_source: {}", e))?;
+                handle.update_source(id, source).await?;
+            }
+
+            let (collapsed, scrolled, collapsed_outputs) = &layouts[i];
+            if collapsed.is_some() {
+                handle.set_cell_collapsed(id, *collapsed).await?;
+            }
+            if scrolled.is_some() {
+                handle.set_cell_scrolled(id, *scrolled).await?;
+            }
+            for &output_index in collapsed_outputs {
+                handle.set_output_collapsed(id, output_index, true).await?;
+            }
+            // Tags like skip-execution change how the daemon runs the cell
+            if !tags[i].is_empty() {
+                handle.set_cell_tags(id, tags[i].clone()).await?;
             }
         }
 
         // Also push notebook metadata to Automerge doc
         let metadata_json = {
-            let state = notebook_state.lock().map_err(|e| e.to_string())?;
+            let state = notebook_state.lock()?;
             let snapshot = notebook_state::snapshot_from_nbformat(&state.notebook.metadata);
-            serde_json::to_string(&snapshot).map_err(|e| format!("serialize metadata: {}", e))?
+            serde_json::to_string(&snapshot)?
         };
         info!(
             "[notebook-sync] Pushing metadata to Automerge doc for {}",
@@ -317,7 +416,7 @@ async fn initialize_notebook_sync(
                 &metadata_json,
             )
             .await
-            .map_err(|e| format!("set_metadata: {}", e))?;
+            .map_err(CommandError::from)?;
     } else {
         info!(
             "[notebook-sync] Joining existing room with {} cells",
@@ -326,7 +425,7 @@ async fn initialize_notebook_sync(
         // Update local NotebookState to match Automerge state
         // This prevents race conditions where load_notebook returns stale disk content
         {
-            let mut state = notebook_state.lock().map_err(|e| e.to_string())?;
+            let mut state = notebook_state.lock()?;
             state.notebook.cells = initial_cells
                 .iter()
                 .map(cell_snapshot_to_nbformat)
@@ -469,6 +568,18 @@ async fn initialize_notebook_sync(
             {
                 warn!("[notebook-sync] Failed to emit daemon:broadcast: {}", e);
             }
+            let event = match broadcast {
+                NotebookBroadcast::KernelLifecycle { .. } => Some("kernel:lifecycle"),
+                NotebookBroadcast::RunAllProgress { .. } => Some("run_all:progress"),
+                NotebookBroadcast::RunAllCancelled { .. } => Some("run_all:cancelled"),
+                _ => None,
+            };
+            if let Some(event) = event {
+                if let Err(e) = emit_to_label::<_, _, _>(&window, window.label(), event, &broadcast)
+                {
+                    warn!("[notebook-sync] Failed to emit {}: {}", event, e);
+                }
+            }
         }
         warn!(
             "[notebook-sync] Broadcast receiver loop ended for {} (gen {}) - daemon disconnected (broadcast_tx dropped)",
@@ -572,11 +683,191 @@ async fn push_metadata_to_sync(
     }
 }
 
+/// Whether quitting the app should shut down the daemon kernels it has open.
+///
+/// Normally the daemon reaps a kernel once its last window disconnects, so
+/// quitting needs no extra step. With `keep_kernel_on_close` that no longer
+/// happens, and quitting shuts kernels down unless `keep_kernel_on_exit` is set.
+fn shutdown_kernels_on_exit(settings: &runtimed::settings_doc::SyncedSettings) -> bool {
+    settings.keep_kernel_on_close && !settings.keep_kernel_on_exit
+}
+
+/// Ask the daemon to shut down the kernel of every open notebook window.
+fn shutdown_daemon_kernels(registry: &WindowNotebookRegistry) {
+    let syncs: Vec<SharedNotebookSync> = registry
+        .contexts
+        .lock()
+        .map(|contexts| {
+            contexts
+                .values()
+                .map(|context| context.notebook_sync.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    tauri::async_runtime::block_on(async {
+        for notebook_sync in syncs {
+            let Some(handle) = notebook_sync.lock().await.clone() else {
+                continue;
+            };
+            let request = handle.send_request(NotebookRequest::ShutdownKernel {});
+            match tokio::time::timeout(std::time::Duration::from_secs(2), request).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => warn!("[session] Failed to shut down kernel on exit: {}", e),
+                Err(_) => warn!("[session] Timed out shutting down kernel on exit"),
+            }
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
-    use super::next_available_sample_path;
+    use super::{
+        assign_fresh_env_id, auto_launch_section, cell_snapshot_to_nbformat, env_conflict,
+        next_available_sample_path, notebook_env_id, shutdown_kernels_on_exit, EnvConflict,
+    };
+    use runtimed::notebook_doc::CellSnapshot;
+    use runtimed::settings_doc::SyncedSettings;
     use tempfile::TempDir;
 
+    fn metadata_with_runt(runt: serde_json::Value) -> nbformat::v4::Metadata {
+        nbformat::v4::Metadata {
+            kernelspec: None,
+            language_info: None,
+            authors: None,
+            additional: [("runt".to_string(), runt)].into_iter().collect(),
+        }
+    }
+
+    fn code_snapshot(outputs: Vec<String>) -> CellSnapshot {
+        CellSnapshot {
+            id: "9a7f6d1e-2f7c-4b5e-9d1a-3c2b1a0f9e8d".to_string(),
+            cell_type: "code".to_string(),
+            source: "print('hi')".to_string(),
+            execution_count: "1".to_string(),
+            outputs,
+            execution: None,
+            collapsed: None,
+            scrolled: None,
+            collapsed_outputs: Vec::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn manifest_hash_outputs_only_survive_once_resolved() {
+        let hash = "ab".repeat(32);
+        let unresolved = cell_snapshot_to_nbformat(&code_snapshot(vec![hash]));
+        let nbformat::v4::Cell::Code { outputs, .. } = &unresolved else {
+            panic!("Expected code cell");
+        };
+        assert!(outputs.is_empty());
+
+        // What GetResolvedCells returns for the same cell
+        let resolved = serde_json::json!({
+            "output_type": "stream", "name": "stdout", "text": "hi\n"
+        });
+        let cell = cell_snapshot_to_nbformat(&code_snapshot(vec![resolved.to_string()]));
+        let markdown = crate::format::cell_to_markdown(&cell, "python");
+        assert!(markdown.contains("hi"), "{markdown}");
+        let nbformat::v4::Cell::Code { outputs, .. } = &cell else {
+            panic!("Expected code cell");
+        };
+        assert_eq!(outputs.len(), 1);
+    }
+
+    #[test]
+    fn exit_leaves_kernels_to_daemon_by_default() {
+        let settings = SyncedSettings::default();
+        assert!(!shutdown_kernels_on_exit(&settings));
+    }
+
+    #[test]
+    fn exit_shuts_down_kernels_kept_on_close() {
+        let mut settings = SyncedSettings {
+            keep_kernel_on_close: true,
+            ..Default::default()
+        };
+        assert!(shutdown_kernels_on_exit(&settings));
+
+        settings.keep_kernel_on_exit = true;
+        assert!(!shutdown_kernels_on_exit(&settings));
+    }
+
+    #[test]
+    fn env_conflict_reports_packages_unique_to_each_section() {
+        let metadata = metadata_with_runt(serde_json::json!({
+            "uv": { "dependencies": ["NumPy>=2", "requests"] },
+            "conda": { "dependencies": ["numpy=2.1", "conda-forge::gdal"], "channels": [] },
+        }));
+        assert_eq!(
+            env_conflict(&metadata),
+            Some(EnvConflict {
+                auto_launch_section: "uv".to_string(),
+                uv_only: vec!["requests".to_string()],
+                conda_only: vec!["conda-forge::gdal".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn env_conflict_needs_both_sections_non_empty() {
+        let uv_only = metadata_with_runt(serde_json::json!({
+            "uv": { "dependencies": ["numpy"] },
+        }));
+        assert_eq!(env_conflict(&uv_only), None);
+
+        let empty_conda = metadata_with_runt(serde_json::json!({
+            "uv": { "dependencies": ["numpy"] },
+            "conda": { "dependencies": [], "channels": [] },
+        }));
+        assert_eq!(env_conflict(&empty_conda), None);
+    }
+
+    #[test]
+    fn auto_launch_prefers_uv_over_conda() {
+        let deps = vec!["numpy".to_string()];
+        assert_eq!(auto_launch_section(&deps, &deps), Some("uv"));
+        assert_eq!(auto_launch_section(&[], &deps), Some("conda"));
+        assert_eq!(auto_launch_section(&[], &[]), None);
+    }
+
+    #[test]
+    fn regenerated_env_id_is_new_and_keeps_runt_metadata() {
+        let runt = serde_json::json!({
+            "schema_version": "1",
+            "env_id": "shared-env-id",
+            "uv": { "dependencies": ["numpy"] },
+            "trust_signature": "hmac-sha256:abc",
+        });
+        let mut metadata = metadata_with_runt(runt.clone());
+
+        let new_id = assign_fresh_env_id(&mut metadata);
+        assert_ne!(new_id, "shared-env-id");
+        assert!(uuid::Uuid::parse_str(&new_id).is_ok());
+        assert_eq!(notebook_env_id(&metadata), Some(new_id.as_str()));
+
+        // Everything but env_id is untouched
+        let mut expected = runt;
+        expected["env_id"] = serde_json::json!(new_id);
+        assert_eq!(metadata.additional["runt"], expected);
+
+        // Each regeneration picks another id
+        assert_ne!(assign_fresh_env_id(&mut metadata), new_id);
+    }
+
+    #[test]
+    fn regenerated_env_id_creates_runt_metadata() {
+        let mut metadata = metadata_with_runt(serde_json::json!({}));
+        metadata.additional.clear();
+
+        let new_id = assign_fresh_env_id(&mut metadata);
+        assert_eq!(
+            metadata.additional["runt"],
+            serde_json::json!({ "schema_version": "1", "env_id": new_id })
+        );
+    }
+
     #[test]
     fn next_available_sample_path_reuses_original_name_when_available() {
         let temp_dir = TempDir::new().expect("temp dir");
@@ -769,21 +1060,32 @@ async fn get_daemon_info() -> Option<DaemonInfoForBanner> {
 /// Get the blob server port from the running daemon.
 /// Used by the frontend to resolve manifest hashes to outputs.
 #[tauri::command]
-async fn get_blob_port() -> Result<u16, String> {
+async fn get_blob_port() -> Result<u16, CommandError> {
     let info = runtimed::singleton::get_running_daemon_info()
-        .ok_or_else(|| "Daemon not running".to_string())?;
+        .ok_or_else(|| CommandError::NotConnected("Daemon not running".to_string()))?;
     info.blob_port
-        .ok_or_else(|| "Blob server not available".to_string())
+        .ok_or_else(|| CommandError::NotConnected("Blob server not available".to_string()))
+}
+
+/// Get the bearer token the blob server requires, if one is configured.
+/// The frontend sends it with every blob fetch.
+#[tauri::command]
+async fn get_blob_token() -> Result<Option<String>, CommandError> {
+    let info = runtimed::singleton::get_running_daemon_info()
+        .ok_or_else(|| CommandError::NotConnected("Daemon not running".to_string()))?;
+    Ok(info.blob_token)
 }
 
 #[tauri::command]
 async fn load_notebook(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Vec<FrontendCell>, String> {
+) -> Result<Vec<FrontendCell>, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
-    let state = state.lock().map_err(|e| e.to_string())?;
-    Ok(state.cells_for_frontend())
+    let state = state.lock()?;
+    let mut cells = state.cells_for_frontend();
+    state.sanitize_frontend_outputs(&mut cells, &settings::load_settings().outputs);
+    Ok(cells)
 }
 
 /// Check if the notebook has a file path set
@@ -791,9 +1093,9 @@ async fn load_notebook(
 async fn has_notebook_path(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<bool, String> {
+) -> Result<bool, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
-    let state = state.lock().map_err(|e| e.to_string())?;
+    let state = state.lock()?;
     Ok(state.path.is_some())
 }
 
@@ -802,9 +1104,9 @@ async fn has_notebook_path(
 async fn get_notebook_path(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Option<String>, String> {
+) -> Result<Option<String>, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
-    let state = state.lock().map_err(|e| e.to_string())?;
+    let state = state.lock()?;
     Ok(state.path.as_ref().map(|p| p.to_string_lossy().to_string()))
 }
 
@@ -817,16 +1119,15 @@ async fn get_notebook_path(
 async fn save_notebook(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     // First pass: collect cells to format (release lock for async formatting)
     let (runtime, cells_to_format, path) = {
-        let nb = state.lock().map_err(|e| e.to_string())?;
-        let path = nb
-            .path
-            .clone()
-            .ok_or_else(|| "No file path set - use save_notebook_as".to_string())?;
+        let nb = state.lock()?;
+        let path = nb.path.clone().ok_or_else(|| {
+            CommandError::NotFound("No file path set - use save_notebook_as".to_string())
+        })?;
         let rt = nb.get_runtime();
 
         // Collect all code cells with their sources
@@ -861,7 +1162,7 @@ async fn save_notebook(
             if cell_source != source {
                 // Update notebook state with formatted code
                 {
-                    let mut nb = state.lock().map_err(|e| e.to_string())?;
+                    let mut nb = state.lock()?;
                     nb.update_cell_source(&cell_id, cell_source);
                 }
                 // Emit event to sync frontend
@@ -879,6 +1180,8 @@ async fn save_notebook(
         // Formatting errors are silently ignored - save with original code
     }
 
+    ensure_savable(&state)?;
+
     // Ensure latest metadata is pushed to daemon before saving
     push_metadata_to_sync(&state, &notebook_sync).await;
 
@@ -918,14 +1221,14 @@ async fn save_notebook(
 
     // Fallback: save locally if daemon save didn't work
     if !daemon_saved {
-        let nb = state.lock().map_err(|e| e.to_string())?;
-        let content = nb.serialize()?;
-        std::fs::write(&path, &content).map_err(|e| e.to_string())?;
+        let nb = state.lock()?;
+        let content = nb.serialize().map_err(CommandError::Io)?;
+        std::fs::write(&path, &content)?;
     }
 
     // Mark as clean
     {
-        let mut nb = state.lock().map_err(|e| e.to_string())?;
+        let mut nb = state.lock()?;
         nb.dirty = false;
     }
     Ok(())
@@ -940,7 +1243,7 @@ async fn save_notebook_as(
     path: String,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let sync_generation = sync_generation_for_window(&window, registry.inner())?;
@@ -948,7 +1251,7 @@ async fn save_notebook_as(
 
     // First pass: collect cells to format (release lock for async formatting)
     let (runtime, cells_to_format) = {
-        let nb = state.lock().map_err(|e| e.to_string())?;
+        let nb = state.lock()?;
         let rt = nb.get_runtime();
 
         // Collect all code cells with their sources
@@ -983,7 +1286,7 @@ async fn save_notebook_as(
             if cell_source != source {
                 // Update notebook state with formatted code
                 {
-                    let mut nb = state.lock().map_err(|e| e.to_string())?;
+                    let mut nb = state.lock()?;
                     nb.update_cell_source(&cell_id, cell_source);
                 }
                 // Emit event to sync frontend
@@ -1000,11 +1303,13 @@ async fn save_notebook_as(
         }
     }
 
+    ensure_savable(&state)?;
+
     // Now save
     {
-        let mut nb = state.lock().map_err(|e| e.to_string())?;
-        let content = nb.serialize()?;
-        std::fs::write(&save_path, &content).map_err(|e| e.to_string())?;
+        let mut nb = state.lock()?;
+        let content = nb.serialize().map_err(CommandError::Io)?;
+        std::fs::write(&save_path, &content)?;
 
         // Update the stored path and window title
         let filename = save_path
@@ -1013,7 +1318,7 @@ async fn save_notebook_as(
             .unwrap_or("Untitled.ipynb");
         let _ = window.set_title(filename);
 
-        nb.path = Some(save_path);
+        nb.path = Some(save_path.clone());
         nb.dirty = false;
     }
 
@@ -1032,31 +1337,68 @@ async fn save_notebook_as(
     let webview_window = window
         .app_handle()
         .get_webview_window(window.label())
-        .ok_or_else(|| "Current webview window not found".to_string())?;
+        .ok_or_else(|| CommandError::NotFound("Current webview window not found".to_string()))?;
     if let Err(e) =
         initialize_notebook_sync(webview_window, state, notebook_sync, sync_generation).await
     {
         warn!("[save-as] Daemon reconnect failed (save succeeded): {}", e);
     }
 
+    // The new location may be inside a project the running kernel's
+    // environment doesn't come from; let the UI offer to switch.
+    let detected = project_file::redetect_environment(&save_path);
+    info!(
+        "[save-as] Re-detected environment: {:?}",
+        detected.as_ref().map(|d| &d.env_source)
+    );
+    let _ = emit_to_label::<_, _, _>(&window, window.label(), "environment:redetected", &detected);
+
     Ok(())
 }
 
+/// Re-run project file detection from the notebook's current path.
+///
+/// Returns the env_source a kernel launched now would get from a nearby
+/// pyproject.toml, pixi.toml or environment.yml, for the UI to offer
+/// restarting the kernel with. Called automatically after Save As.
+#[tauri::command]
+async fn redetect_environment(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<Option<project_file::DetectedEnvironment>, CommandError> {
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let notebook_path = {
+        let state = state.lock()?;
+        state.path.clone()
+    };
+    Ok(notebook_path
+        .as_deref()
+        .and_then(project_file::redetect_environment))
+}
+
 /// Clone the current notebook for saving as a new file.
 /// Generates a fresh env_id and clears outputs/execution counts.
+///
+/// If the source notebook has a cached uv environment, it is copied for the
+/// clone with progress reported as `env:progress` events. The copy can be
+/// aborted with `cancel_clone_environment_copy`; a cancelled or failed copy
+/// leaves the clone to create a fresh environment on first launch.
 #[tauri::command]
 async fn clone_notebook_to_path(
     path: String,
+    app: tauri::AppHandle,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+    cancel: tauri::State<'_, CloneCopyCancel>,
+) -> Result<(), CommandError> {
     let notebook_state = notebook_state_for_window(&window, registry.inner())?;
     // Generate fresh env_id upfront
     let new_env_id = uuid::Uuid::new_v4().to_string();
 
     // Clone notebook structure while holding the lock
-    let cloned_notebook = {
-        let state = notebook_state.lock().map_err(|e| e.to_string())?;
+    let (cloned_notebook, source_env) = {
+        let state = notebook_state.lock()?;
+        let source_env = cached_uv_environment(&state.notebook.metadata);
         let mut cloned = state.notebook.clone();
 
         // Update runt metadata with new env_id (canonical location for env_id)
@@ -1067,142 +1409,569 @@ async fn clone_notebook_to_path(
         }
 
         // Clear outputs and execution counts from all code cells
-        for cell in &mut cloned.cells {
-            if let nbformat::v4::Cell::Code {
-                outputs,
-                execution_count,
-                ..
-            } = cell
-            {
-                outputs.clear();
-                *execution_count = None;
-            }
-        }
+        notebook_state::clear_all_outputs(&mut cloned.cells);
 
-        cloned
+        (cloned, source_env)
     };
 
     // Serialize and write to path
     let nb = nbformat::Notebook::V4(cloned_notebook);
-    let content = nbformat::serialize_notebook(&nb).map_err(|e| e.to_string())?;
-    std::fs::write(&path, &content).map_err(|e| e.to_string())?;
+    let content = nbformat::serialize_notebook(&nb).map_err(|e| CommandError::Io(e.to_string()))?;
+    std::fs::write(&path, &content)?;
+
+    if let Some((deps, source)) = source_env {
+        copy_environment_for_env_id(&source, &deps, &new_env_id, app, &cancel.0).await;
+    }
 
     Ok(())
 }
 
-/// Open a notebook file in a new window within the current app process.
+/// Write a copy of the current notebook to `path` with all outputs and
+/// execution counts stripped, for sharing or committing.
+///
+/// Unlike `clone_notebook_to_path`, the copy keeps the notebook's env_id, and
+/// the open notebook's path and dirty flag are left as they are.
 #[tauri::command]
-async fn open_notebook_in_new_window(
+async fn save_notebook_copy_clean(
     path: String,
-    app: tauri::AppHandle,
+    window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
-    open_notebook_window(&app, registry.inner(), Path::new(&path))
+) -> Result<(), CommandError> {
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let content = state
+        .lock()?
+        .serialize_without_outputs()
+        .map_err(CommandError::Io)?;
+    std::fs::write(&path, &content)?;
+    info!(
+        "[save-copy] Wrote notebook copy without outputs to {}",
+        path
+    );
+    Ok(())
 }
 
-fn create_notebook_window(
-    app: &tauri::AppHandle,
-    registry: &WindowNotebookRegistry,
-    state: NotebookState,
-) -> Result<String, String> {
-    create_notebook_window_with_label(app, registry, state, None)
+/// Check the open notebook against nbformat v4, returning every issue found.
+#[tauri::command]
+async fn validate_notebook(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<Vec<validation::ValidationIssue>, CommandError> {
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let issues = state.lock()?.validate();
+    Ok(issues)
 }
 
-fn create_notebook_window_with_label(
-    app: &tauri::AppHandle,
-    registry: &WindowNotebookRegistry,
-    state: NotebookState,
-    custom_label: Option<String>,
-) -> Result<String, String> {
-    let title = state
-        .path
-        .as_ref()
-        .and_then(|p| p.file_name())
-        .and_then(|n| n.to_str())
-        .unwrap_or("Untitled.ipynb")
-        .to_string();
-
-    // Use custom label if provided, otherwise generate a deterministic one
-    let label = custom_label.unwrap_or_else(|| {
-        // Generate stable label based on path or env_id for window-state plugin
-        if let Some(path) = &state.path {
-            let hash = runtimed::worktree_hash(path);
-            format!("notebook-{}", &hash[..8])
-        } else {
-            // For untitled notebooks, use env_id if available
-            let env_id = state
-                .notebook
-                .metadata
-                .additional
-                .get("runt")
-                .and_then(|v| v.get("env_id"))
-                .and_then(|v| v.as_str());
-            if let Some(id) = env_id {
-                format!("notebook-{}", &id[..8.min(id.len())])
-            } else {
-                format!("notebook-{}", uuid::Uuid::new_v4())
-            }
-        }
-    });
-    let context = create_window_context(state);
-    registry.insert(label.clone(), context.clone())?;
-
-    let window =
-        match tauri::WebviewWindowBuilder::new(app, label.clone(), tauri::WebviewUrl::default())
-            .title(&title)
-            .inner_size(1100.0, 750.0)
-            .resizable(true)
-            .build()
-        {
-            Ok(window) => window,
-            Err(error) => {
-                let mut contexts = registry.contexts.lock().map_err(|e| e.to_string())?;
-                contexts.remove(&label);
-                return Err(error.to_string());
-            }
-        };
+/// The notebook's raw metadata: everything besides the kernelspec, language
+/// info and authors, as one JSON object.
+#[tauri::command]
+async fn get_raw_metadata(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<serde_json::Value, CommandError> {
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let metadata = raw_metadata::raw_metadata(&state.lock()?.notebook.metadata);
+    Ok(metadata)
+}
 
-    let context = registry.get(&label)?;
-    tauri::async_runtime::spawn(async move {
-        if let Err(e) = initialize_notebook_sync(
-            window,
-            context.notebook_state,
-            context.notebook_sync,
-            context.sync_generation,
+/// Replace the notebook's raw metadata with `metadata`, migrating it to the
+/// current `runt` layout. Edits that break the `runt` sections are refused,
+/// and changing the trust signature needs `confirm`.
+#[tauri::command]
+async fn set_raw_metadata(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+    metadata: serde_json::Value,
+    confirm: Option<bool>,
+) -> Result<(), CommandError> {
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    {
+        let mut s = state.lock()?;
+        raw_metadata::set_raw_metadata(
+            &mut s.notebook.metadata,
+            metadata,
+            confirm.unwrap_or(false),
         )
-        .await
-        {
-            warn!("[startup] Notebook sync initialization failed: {}", e);
-        }
-    });
+        .map_err(|e| CommandError::Validation(e.to_string()))?;
+        s.dirty = true;
+    }
+    push_metadata_to_sync(&state, &notebook_sync).await;
+    Ok(())
+}
 
-    Ok(label)
+/// With the `block_invalid_save` setting on, refuse to save a notebook that
+/// has validation errors. Warnings never block.
+fn ensure_savable(state: &Mutex<NotebookState>) -> Result<(), CommandError> {
+    if !settings::load_settings().block_invalid_save {
+        return Ok(());
+    }
+    let errors: Vec<String> = state
+        .lock()?
+        .validate()
+        .into_iter()
+        .filter(validation::ValidationIssue::is_error)
+        .map(|issue| issue.to_string())
+        .collect();
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(CommandError::Validation(format!(
+        "Notebook is not valid nbformat: {}",
+        errors.join("; ")
+    )))
 }
 
-fn open_notebook_window(
-    app: &tauri::AppHandle,
-    registry: &WindowNotebookRegistry,
-    path: &Path,
-) -> Result<(), String> {
-    let runtime = settings::load_settings().default_runtime;
-    let state = load_notebook_state_for_path(path, runtime)?;
-    create_notebook_window(app, registry, state).map(|_| ())
+/// Copy a cached uv environment for a new env_id, reporting progress as
+/// `env:progress` events. A cancelled or failed copy is logged and left for
+/// the notebook to create a fresh environment on first launch.
+async fn copy_environment_for_env_id(
+    source: &uv_env::UvEnvironment,
+    deps: &uv_env::NotebookDependencies,
+    new_env_id: &str,
+    app: tauri::AppHandle,
+    cancel: &AtomicBool,
+) {
+    cancel.store(false, Ordering::SeqCst);
+    let handler: Arc<dyn kernel_env::ProgressHandler> =
+        Arc::new(conda_env::TauriProgressHandler::new(app));
+    match uv_env::copy_environment(source, deps, new_env_id, handler.clone(), cancel).await {
+        Ok(Some(env)) => info!("[env-copy] Copied environment to {:?}", env.venv_path),
+        Ok(None) => info!("[env-copy] Environment copy cancelled, will use a fresh env"),
+        Err(e) => {
+            warn!(
+                "[env-copy] Environment copy failed, will use a fresh env: {}",
+                e
+            );
+            handler.on_progress(
+                "uv",
+                kernel_env::EnvProgressPhase::Error {
+                    message: format!("Failed to copy environment: {}", e),
+                },
+            );
+        }
+    }
 }
 
-fn next_available_sample_path(base_dir: &Path, file_name: &str) -> PathBuf {
-    let file_path = Path::new(file_name);
-    let stem = file_path
-        .file_stem()
-        .and_then(OsStr::to_str)
-        .unwrap_or("sample-notebook");
-    let ext = file_path.extension().and_then(OsStr::to_str);
+/// The notebook's env_id from its runt metadata.
+fn notebook_env_id(metadata: &nbformat::v4::Metadata) -> Option<&str> {
+    metadata
+        .additional
+        .get("runt")
+        .and_then(|runt| runt.get("env_id"))
+        .and_then(|id| id.as_str())
+}
 
-    let mut candidate = base_dir.join(file_name);
-    let mut index = 2;
+/// Give the notebook a fresh env_id, keeping the rest of its runt metadata.
+/// Returns the new id.
+fn assign_fresh_env_id(metadata: &mut nbformat::v4::Metadata) -> String {
+    let env_id = uuid::Uuid::new_v4().to_string();
+    let runt = metadata
+        .additional
+        .entry("runt".to_string())
+        .or_insert_with(|| serde_json::json!({"schema_version": "1"}));
+    if let Some(obj) = runt.as_object_mut() {
+        obj.insert("env_id".to_string(), serde_json::json!(env_id));
+    }
+    env_id
+}
 
-    while candidate.exists() {
-        let next_name = match ext {
-            Some(ext) => format!("{stem}-{index}.{ext}"),
+/// Assign the notebook a fresh env_id so it stops sharing an environment
+/// with another notebook (e.g. a copy made outside the app).
+///
+/// With `copy_environment`, the current cached uv environment is copied for
+/// the new id like `clone_notebook_to_path` does, so the next kernel starts
+/// without reinstalling. Returns the new env_id.
+#[tauri::command]
+async fn regenerate_env_id(
+    copy_environment: bool,
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+    cancel: tauri::State<'_, CloneCopyCancel>,
+) -> Result<String, CommandError> {
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let (new_env_id, source_env) = {
+        let mut s = state.lock()?;
+        let source_env = copy_environment
+            .then(|| cached_uv_environment(&s.notebook.metadata))
+            .flatten();
+        let new_env_id = assign_fresh_env_id(&mut s.notebook.metadata);
+        s.dirty = true;
+        (new_env_id, source_env)
+    };
+    info!("[env-id] Regenerated env_id: {}", new_env_id);
+    push_metadata_to_sync(&state, &notebook_sync).await;
+
+    if let Some((deps, source)) = source_env {
+        copy_environment_for_env_id(&source, &deps, &new_env_id, app, &cancel.0).await;
+    }
+
+    Ok(new_env_id)
+}
+
+/// Names of the other open notebooks that share this notebook's env_id, so
+/// the UI can offer `regenerate_env_id`.
+#[tauri::command]
+async fn detect_shared_env_id(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<Vec<String>, CommandError> {
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let Some(env_id) = notebook_env_id(&state.lock()?.notebook.metadata).map(String::from) else {
+        return Ok(vec![]);
+    };
+
+    // Snapshot the other windows first so no notebook lock is taken while
+    // holding the registry lock
+    let others: Vec<_> = registry
+        .contexts
+        .lock()?
+        .iter()
+        .filter(|(label, _)| label.as_str() != window.label())
+        .map(|(_, context)| context.notebook_state.clone())
+        .collect();
+
+    let mut sharing = vec![];
+    for other in others {
+        let other = other.lock()?;
+        if notebook_env_id(&other.notebook.metadata) == Some(env_id.as_str()) {
+            sharing.push(
+                other
+                    .path
+                    .as_ref()
+                    .and_then(|p| p.file_name())
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "Untitled".to_string()),
+            );
+        }
+    }
+    sharing.sort();
+    Ok(sharing)
+}
+
+/// Abort an in-progress environment copy started by `clone_notebook_to_path`
+/// or `regenerate_env_id`.
+#[tauri::command]
+fn cancel_clone_environment_copy(cancel: tauri::State<'_, CloneCopyCancel>) {
+    cancel.0.store(true, Ordering::SeqCst);
+}
+
+/// The notebook's uv dependencies and their cached environment for its
+/// env_id, if one exists.
+fn cached_uv_environment(
+    metadata: &nbformat::v4::Metadata,
+) -> Option<(uv_env::NotebookDependencies, uv_env::UvEnvironment)> {
+    let deps = uv_env::extract_dependencies(metadata)?;
+    let env_id = notebook_env_id(metadata);
+    let venv_path =
+        kernel_env::uv::default_cache_dir_uv().join(uv_env::compute_env_hash(&deps, env_id));
+
+    #[cfg(target_os = "windows")]
+    let python_path = venv_path.join("Scripts").join("python.exe");
+    #[cfg(not(target_os = "windows"))]
+    let python_path = venv_path.join("bin").join("python");
+
+    python_path.exists().then_some((
+        deps,
+        uv_env::UvEnvironment {
+            venv_path,
+            python_path,
+        },
+    ))
+}
+
+/// Open a notebook file in a new window within the current app process.
+#[tauri::command]
+async fn open_notebook_in_new_window(
+    path: String,
+    app: tauri::AppHandle,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<(), CommandError> {
+    open_notebook_window(&app, registry.inner(), Path::new(&path))
+}
+
+/// The notebook picker listing for this window, if it was opened on a
+/// directory and no notebook has been picked yet.
+///
+/// The listing is also emitted as `notebook:directory-listing`, but that can
+/// fire before the frontend is listening, so it asks on load too.
+#[tauri::command]
+async fn get_directory_listing(
+    window: tauri::Window,
+    listings: tauri::State<'_, DirectoryListings>,
+) -> Result<Option<notebook_directory::DirectoryListing>, CommandError> {
+    let listings = listings.0.lock()?;
+    Ok(listings.get(window.label()).cloned())
+}
+
+/// Open a notebook picked from the directory listing in the current window.
+#[tauri::command]
+async fn open_notebook_in_current_window(
+    path: String,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+    listings: tauri::State<'_, DirectoryListings>,
+) -> Result<(), CommandError> {
+    let path = PathBuf::from(path);
+    if !path.is_file() {
+        return Err(CommandError::NotFound(format!(
+            "Notebook not found: {}",
+            path.display()
+        )));
+    }
+
+    let notebook_state = notebook_state_for_window(&window, registry.inner())?;
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let sync_generation = sync_generation_for_window(&window, registry.inner())?;
+
+    let new_state = load_notebook_state_for_path(&path, settings::load_settings().default_runtime)?;
+    {
+        let mut state = notebook_state.lock()?;
+        if state.dirty {
+            return Err(CommandError::Validation(
+                "The current notebook has unsaved changes".to_string(),
+            ));
+        }
+        *state = new_state;
+    }
+    listings.0.lock()?.remove(window.label());
+
+    let title = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("Untitled.ipynb");
+    let _ = window.set_title(title);
+
+    // Leave the untitled notebook's room and join the picked notebook's
+    {
+        let mut sync_guard = notebook_sync.lock().await;
+        *sync_guard = None;
+    }
+    let webview_window = window
+        .app_handle()
+        .get_webview_window(window.label())
+        .ok_or_else(|| CommandError::NotFound("Current webview window not found".to_string()))?;
+    if let Err(e) = initialize_notebook_sync(
+        webview_window,
+        notebook_state,
+        notebook_sync,
+        sync_generation,
+    )
+    .await
+    {
+        warn!("[open] Daemon sync failed for picked notebook: {}", e);
+    }
+
+    let _ = emit_to_label::<_, _, _>(&window, window.label(), "notebook:file-opened", ());
+    Ok(())
+}
+
+fn create_notebook_window(
+    app: &tauri::AppHandle,
+    registry: &WindowNotebookRegistry,
+    state: NotebookState,
+) -> Result<String, CommandError> {
+    create_notebook_window_with_label(app, registry, state, None)
+}
+
+fn create_notebook_window_with_label(
+    app: &tauri::AppHandle,
+    registry: &WindowNotebookRegistry,
+    state: NotebookState,
+    custom_label: Option<String>,
+) -> Result<String, CommandError> {
+    let title = state
+        .path
+        .as_ref()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .unwrap_or("Untitled.ipynb")
+        .to_string();
+
+    // Use custom label if provided, otherwise generate a deterministic one
+    let label = custom_label.unwrap_or_else(|| {
+        // Generate stable label based on path or env_id for window-state plugin
+        if let Some(path) = &state.path {
+            let hash = runtimed::worktree_hash(path);
+            format!("notebook-{}", &hash[..8])
+        } else {
+            // For untitled notebooks, use env_id if available
+            let env_id = state
+                .notebook
+                .metadata
+                .additional
+                .get("runt")
+                .and_then(|v| v.get("env_id"))
+                .and_then(|v| v.as_str());
+            if let Some(id) = env_id {
+                format!("notebook-{}", &id[..8.min(id.len())])
+            } else {
+                format!("notebook-{}", uuid::Uuid::new_v4())
+            }
+        }
+    });
+    let context = create_window_context(state);
+    registry.insert(label.clone(), context.clone())?;
+
+    let window =
+        match tauri::WebviewWindowBuilder::new(app, label.clone(), tauri::WebviewUrl::default())
+            .title(&title)
+            .inner_size(1100.0, 750.0)
+            .resizable(true)
+            .build()
+        {
+            Ok(window) => window,
+            Err(error) => {
+                let mut contexts = registry.contexts.lock()?;
+                contexts.remove(&label);
+                return Err(CommandError::Io(error.to_string()));
+            }
+        };
+
+    let context = registry.get(&label)?;
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match initialize_notebook_sync(
+            window,
+            context.notebook_state,
+            context.notebook_sync,
+            context.sync_generation,
+        )
+        .await
+        {
+            // The daemon has recorded this notebook by now
+            Ok(()) => refresh_menu(&app_handle, settings::load_settings().keybindings),
+            Err(e) => warn!("[startup] Notebook sync initialization failed: {}", e),
+        }
+    });
+
+    Ok(label)
+}
+
+/// Notebooks for File > Open > Open Recent, from the daemon's registry.
+///
+/// Falls back to reading the registry file directly when the daemon isn't
+/// reachable, so the menu still works while it is starting or down.
+async fn fetch_recent_notebooks() -> Vec<menu::RecentNotebook> {
+    let from_daemon = match runtimed::singleton::get_running_daemon_info() {
+        Some(info) => runtimed::client::PoolClient::new(PathBuf::from(&info.endpoint))
+            .list_notebooks()
+            .await
+            .ok(),
+        None => None,
+    };
+    let records = from_daemon.unwrap_or_else(|| {
+        runtimed::notebook_registry::NotebookRegistry::load(
+            &runtimed::default_notebook_registry_path(),
+        )
+        .list()
+    });
+
+    menu::build_recent_list(
+        records
+            .into_iter()
+            .filter_map(|record| record.path.map(PathBuf::from)),
+        dirs::home_dir().as_deref(),
+    )
+}
+
+/// Installed kernelspecs offered under File > New Notebook As...
+async fn fetch_menu_kernelspecs() -> Vec<menu::KernelspecMenuEntry> {
+    menu::build_kernelspec_list(runtimelib::list_kernelspecs().await.into_iter().map(|s| {
+        (
+            s.kernel_name,
+            s.kernelspec.display_name,
+            s.kernelspec.language,
+        )
+    }))
+}
+
+/// Rebuild the menu bar so Open Recent reflects the latest registry,
+/// New Notebook As... lists installed kernelspecs, and accelerators reflect
+/// the user's `keybindings` setting.
+fn refresh_menu(app: &tauri::AppHandle, keybindings: std::collections::BTreeMap<String, String>) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let recent = fetch_recent_notebooks().await;
+        let kernelspecs = fetch_menu_kernelspecs().await;
+        let accelerators = menu::MenuAccelerators::from_keybindings(&keybindings);
+        if let Err(e) = menu::create_menu(&app, &recent, &kernelspecs, &accelerators)
+            .and_then(|menu| app.set_menu(menu))
+        {
+            warn!("[menu] Failed to refresh menu: {}", e);
+        }
+    });
+}
+
+/// Shut down every kernel the daemon owns, across all open notebooks.
+///
+/// Returns the IDs of the notebooks whose kernel was stopped.
+#[tauri::command]
+async fn shutdown_all_kernels() -> Result<Vec<String>, CommandError> {
+    let info = runtimed::singleton::get_running_daemon_info()
+        .ok_or_else(|| CommandError::NotConnected("Daemon is not running".to_string()))?;
+    runtimed::client::PoolClient::new(PathBuf::from(&info.endpoint))
+        .shutdown_all_kernels()
+        .await
+        .map_err(|e| CommandError::NotConnected(format!("daemon request failed: {}", e)))
+}
+
+/// List the daemon's cached environments with their disk usage.
+#[tauri::command]
+async fn get_env_cache_info() -> Result<runtimed::env_cache::EnvCacheInfo, CommandError> {
+    let info = runtimed::singleton::get_running_daemon_info()
+        .ok_or_else(|| CommandError::NotConnected("Daemon is not running".to_string()))?;
+    runtimed::client::PoolClient::new(PathBuf::from(&info.endpoint))
+        .get_env_cache_info()
+        .await
+        .map_err(|e| CommandError::NotConnected(format!("daemon request failed: {}", e)))
+}
+
+/// Delete cached environments by `env_id`. Environments a running kernel uses
+/// are kept and reported back.
+#[tauri::command]
+async fn clear_env_cache(
+    env_ids: Vec<String>,
+) -> Result<runtimed::env_cache::EnvCacheCleared, CommandError> {
+    let info = runtimed::singleton::get_running_daemon_info()
+        .ok_or_else(|| CommandError::NotConnected("Daemon is not running".to_string()))?;
+    runtimed::client::PoolClient::new(PathBuf::from(&info.endpoint))
+        .clear_env_cache(env_ids)
+        .await
+        .map_err(|e| CommandError::NotConnected(format!("daemon request failed: {}", e)))
+}
+
+/// List recently opened notebooks that still exist on disk.
+#[tauri::command]
+async fn get_recent_notebooks() -> Result<Vec<menu::RecentNotebook>, CommandError> {
+    Ok(fetch_recent_notebooks().await)
+}
+
+fn open_notebook_window(
+    app: &tauri::AppHandle,
+    registry: &WindowNotebookRegistry,
+    path: &Path,
+) -> Result<(), CommandError> {
+    let runtime = settings::load_settings().default_runtime;
+    let state = load_notebook_state_for_path(path, runtime)?;
+    create_notebook_window(app, registry, state).map(|_| ())
+}
+
+fn next_available_sample_path(base_dir: &Path, file_name: &str) -> PathBuf {
+    let file_path = Path::new(file_name);
+    let stem = file_path
+        .file_stem()
+        .and_then(OsStr::to_str)
+        .unwrap_or("sample-notebook");
+    let ext = file_path.extension().and_then(OsStr::to_str);
+
+    let mut candidate = base_dir.join(file_name);
+    let mut index = 2;
+
+    while candidate.exists() {
+        let next_name = match ext {
+            Some(ext) => format!("{stem}-{index}.{ext}"),
             None => format!("{stem}-{index}"),
         };
         candidate = base_dir.join(next_name);
@@ -1215,19 +1984,20 @@ fn next_available_sample_path(base_dir: &Path, file_name: &str) -> PathBuf {
 fn materialize_sample_notebook(
     app: &tauri::AppHandle,
     sample: &crate::menu::BundledSampleNotebook,
-) -> Result<PathBuf, String> {
+) -> Result<PathBuf, CommandError> {
     let base_dir = app
         .path()
         .app_local_data_dir()
-        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+        .map_err(|e| CommandError::Io(format!("Failed to resolve app data directory: {}", e)))?
         .join("sample-notebooks");
 
-    std::fs::create_dir_all(&base_dir)
-        .map_err(|e| format!("Failed to create sample notebook directory: {}", e))?;
+    std::fs::create_dir_all(&base_dir).map_err(|e| {
+        CommandError::Io(format!("Failed to create sample notebook directory: {}", e))
+    })?;
 
     let destination = next_available_sample_path(&base_dir, sample.file_name);
     std::fs::write(&destination, sample.contents)
-        .map_err(|e| format!("Failed to write sample notebook: {}", e))?;
+        .map_err(|e| CommandError::Io(format!("Failed to write sample notebook: {}", e)))?;
 
     Ok(destination)
 }
@@ -1236,7 +2006,7 @@ fn open_bundled_sample_notebook(
     app: &tauri::AppHandle,
     registry: &WindowNotebookRegistry,
     sample: &crate::menu::BundledSampleNotebook,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let path = materialize_sample_notebook(app, sample)?;
     open_notebook_window(app, registry, &path)
 }
@@ -1247,12 +2017,12 @@ async fn update_cell_source(
     source: String,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     // Update local state synchronously for responsiveness
     {
-        let mut s = state.lock().map_err(|e| e.to_string())?;
+        let mut s = state.lock()?;
         s.update_cell_source(&cell_id, &source);
     }
 
@@ -1276,12 +2046,12 @@ async fn add_cell(
     after_cell_id: Option<String>,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<FrontendCell, String> {
+) -> Result<FrontendCell, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     // Add to local state first
     let (cell, index) = {
-        let mut s = state.lock().map_err(|e| e.to_string())?;
+        let mut s = state.lock()?;
 
         // Find the index where the new cell will be inserted
         let insert_index = match &after_cell_id {
@@ -1291,7 +2061,7 @@ async fn add_cell(
 
         let cell = s
             .add_cell(&cell_type, after_cell_id.as_deref())
-            .ok_or_else(|| format!("Invalid cell type: {}", cell_type))?;
+            .ok_or_else(|| CommandError::Validation(format!("Invalid cell type: {}", cell_type)))?;
 
         (cell, insert_index)
     };
@@ -1318,19 +2088,94 @@ async fn add_cell(
     Ok(cell)
 }
 
+/// Insert a copy of a cell right after it, with a fresh id. Outputs and the
+/// execution count are copied when `include_outputs` is set.
+///
+/// The returned cell's outputs come from local state, which may lag behind
+/// the doc; the frontend copies the outputs it is already showing.
+#[tauri::command]
+async fn duplicate_cell(
+    cell_id: String,
+    include_outputs: bool,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<FrontendCell, CommandError> {
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    // Duplicate in local state first
+    let (index, cell) = {
+        let mut state = state.lock()?;
+        let (index, cell) = state
+            .duplicate_cell(&cell_id, include_outputs)
+            .ok_or_else(|| CommandError::NotFound(format!("Cell {} not found", cell_id)))?;
+        let mut cells = [cell];
+        state.sanitize_frontend_outputs(&mut cells, &settings::load_settings().outputs);
+        let [cell] = cells;
+        (index, cell)
+    };
+
+    // Sync to daemon
+    let guard = notebook_sync.lock().await;
+    let Some(handle) = guard.as_ref() else {
+        info!("[notebook-sync] No sync handle available for duplicate_cell");
+        return Ok(cell);
+    };
+    let (new_id, cell_type, source) = match &cell {
+        FrontendCell::Code { id, source, .. } => (id, "code", source),
+        FrontendCell::Markdown { id, source } => (id, "markdown", source),
+        FrontendCell::Raw { id, source } => (id, "raw", source),
+    };
+    info!(
+        "[notebook-sync] Syncing duplicate_cell {} -> {} at index {}",
+        cell_id, new_id, index
+    );
+    if let Err(e) = handle.add_cell(index, new_id, cell_type).await {
+        warn!("[notebook-sync] duplicate_cell add failed: {}", e);
+        return Ok(cell);
+    }
+    if let Err(e) = handle.update_source(new_id, source).await {
+        warn!("[notebook-sync] duplicate_cell update_source failed: {}", e);
+    }
+
+    if include_outputs && cell_type == "code" {
+        // The doc is authoritative for outputs (kernel output lands there, not
+        // in local state). The daemon copies its entries and takes blob refs
+        // for them, so the duplicate keeps its outputs when the original is
+        // cleared or rerun.
+        let request = NotebookRequest::CopyOutputs {
+            from_cell_id: cell_id.clone(),
+            to_cell_id: new_id.clone(),
+        };
+        match handle.send_request(request).await {
+            Ok(NotebookResponse::Error { error }) => {
+                warn!(
+                    "[notebook-sync] duplicate_cell copy outputs failed: {}",
+                    error
+                )
+            }
+            Ok(_) => {}
+            Err(e) => warn!("[notebook-sync] duplicate_cell copy outputs failed: {}", e),
+        }
+    }
+
+    Ok(cell)
+}
+
 #[tauri::command]
 async fn delete_cell(
     cell_id: String,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     // Delete from local state first
     {
-        let mut s = state.lock().map_err(|e| e.to_string())?;
+        let mut s = state.lock()?;
         if !s.delete_cell(&cell_id) {
-            return Err("Cannot delete cell (last cell or not found)".to_string());
+            return Err(CommandError::Validation(
+                "Cannot delete cell (last cell or not found)".to_string(),
+            ));
         }
     }
 
@@ -1340,162 +2185,718 @@ async fn delete_cell(
             warn!("[notebook-sync] delete_cell failed: {}", e);
         }
     }
-
+
+    Ok(())
+}
+
+// ============================================================================
+// Daemon Kernel Operations
+// ============================================================================
+// These commands route kernel operations through the daemon, which owns the
+// kernel lifecycle and execution queue. This enables multi-window kernel sharing.
+
+/// Launch a kernel via the daemon.
+///
+/// If a kernel is already running for this notebook, returns info about the existing kernel.
+/// The notebook_path is automatically derived from the sync handle if not provided.
+#[tauri::command]
+async fn launch_kernel_via_daemon(
+    kernel_type: String,
+    env_source: String,
+    notebook_path: Option<String>,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<NotebookResponse, CommandError> {
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    // Use notebook_id from the sync handle if notebook_path not provided,
+    // but only if it looks like a real file path (not a UUID for untitled notebooks)
+    let resolved_path = notebook_path.or_else(|| {
+        let id = handle.notebook_id();
+        // Check if it looks like a file path (contains path separator or starts with /)
+        if id.contains('/') || id.contains('\\') {
+            Some(id.to_string())
+        } else {
+            // Likely a UUID for an untitled notebook - don't use as path
+            None
+        }
+    });
+
+    info!(
+        "[daemon-kernel] launch_kernel_via_daemon: type={}, env_source={}, path={:?}",
+        kernel_type, env_source, resolved_path
+    );
+
+    handle
+        .send_request(NotebookRequest::LaunchKernel {
+            kernel_type,
+            env_source,
+            notebook_path: resolved_path,
+        })
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Queue a cell for execution via the daemon.
+///
+/// Execute a cell via the daemon (reads source from synced document).
+///
+/// This is the preferred method - ensures execution matches synced document state.
+/// The daemon reads the cell source from the Automerge doc instead of receiving it as a parameter.
+#[tauri::command]
+async fn execute_cell_via_daemon(
+    cell_id: String,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<NotebookResponse, CommandError> {
+    info!(
+        "[daemon-kernel] execute_cell_via_daemon: cell_id={}",
+        cell_id
+    );
+
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    handle
+        .send_request(NotebookRequest::ExecuteCell { cell_id })
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Clear outputs for a cell via the daemon.
+#[tauri::command]
+async fn clear_outputs_via_daemon(
+    cell_id: String,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<NotebookResponse, CommandError> {
+    info!(
+        "[daemon-kernel] clear_outputs_via_daemon: cell_id={}",
+        cell_id
+    );
+
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    handle
+        .send_request(NotebookRequest::ClearOutputs { cell_id })
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Clear outputs and execution counts of every code cell via the daemon.
+/// The daemon also deletes blobs that no open notebook references anymore.
+#[tauri::command]
+async fn clear_all_outputs(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<NotebookResponse, CommandError> {
+    info!("[daemon-kernel] clear_all_outputs");
+
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    handle
+        .send_request(NotebookRequest::ClearAllOutputs {})
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Interrupt kernel execution via the daemon.
+#[tauri::command]
+async fn interrupt_via_daemon(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<NotebookResponse, CommandError> {
+    info!("[daemon-kernel] interrupt_via_daemon");
+
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    handle
+        .send_request(NotebookRequest::InterruptExecution {})
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Shutdown the kernel via the daemon.
+#[tauri::command]
+async fn shutdown_kernel_via_daemon(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<NotebookResponse, CommandError> {
+    info!("[daemon-kernel] shutdown_kernel_via_daemon");
+
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    handle
+        .send_request(NotebookRequest::ShutdownKernel {})
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Sync environment via the daemon - hot-install new packages without restart.
+/// Only supported for UV inline deps.
+#[tauri::command]
+async fn sync_environment_via_daemon(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<NotebookResponse, CommandError> {
+    info!("[daemon-kernel] sync_environment_via_daemon");
+
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    handle
+        .send_request(NotebookRequest::SyncEnvironment {})
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Rebuild the notebook's cached environment via the daemon if it is broken.
+/// Offered when launching the kernel fails with an environment error.
+#[tauri::command]
+async fn repair_environment(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<NotebookResponse, CommandError> {
+    info!("[daemon-kernel] repair_environment");
+
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    handle
+        .send_request(NotebookRequest::RepairEnvironment {})
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Pin the running kernel's prewarmed environment to this notebook, so the
+/// daemon reattaches it on every launch and never recycles it.
+#[tauri::command]
+async fn pin_environment(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<NotebookResponse, CommandError> {
+    info!("[daemon-kernel] pin_environment");
+
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    handle
+        .send_request(NotebookRequest::PinEnvironment {})
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Release the environment pinned to this notebook.
+#[tauri::command]
+async fn unpin_environment(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<NotebookResponse, CommandError> {
+    info!("[daemon-kernel] unpin_environment");
+
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    handle
+        .send_request(NotebookRequest::UnpinEnvironment {})
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Get the dependency tree of the running kernel's environment, marking the
+/// packages the notebook declares as direct.
+#[tauri::command]
+async fn get_dependency_tree(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<NotebookResponse, CommandError> {
+    info!("[daemon-kernel] get_dependency_tree");
+
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    handle
+        .send_request(NotebookRequest::GetDependencyTree {})
+        .await
+        .map_err(CommandError::from)
+}
+
+/// List the environments the daemon could launch this notebook's kernel with.
+#[tauri::command]
+async fn list_available_environments(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<Vec<EnvOption>, CommandError> {
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    let response = handle
+        .send_request(NotebookRequest::ListEnvironments {})
+        .await?;
+
+    match response {
+        NotebookResponse::Environments { options } => Ok(options),
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
+    }
+}
+
+/// List notebooks the daemon has opened, most recently opened first.
+#[tauri::command]
+async fn list_recent_notebooks(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<Vec<runtimed::notebook_registry::NotebookRecord>, CommandError> {
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    let response = handle
+        .send_request(NotebookRequest::ListNotebooks {})
+        .await?;
+
+    match response {
+        NotebookResponse::Notebooks { notebooks } => Ok(notebooks),
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
+    }
+}
+
+/// Search the notebook's outputs for text, returning matching cells with snippets.
+#[tauri::command]
+async fn search_outputs(
+    query: String,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<Vec<runtimed::protocol::OutputMatch>, CommandError> {
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    let response = handle
+        .send_request(NotebookRequest::SearchOutputs { query })
+        .await?;
+
+    match response {
+        NotebookResponse::OutputMatches { matches } => Ok(matches),
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
+    }
+}
+
+/// Get a cell's outputs as manifests in a single request.
+/// Blob-backed content carries a `url` to fetch from the blob server.
+#[tauri::command]
+async fn get_cell_outputs(
+    cell_id: String,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<Vec<serde_json::Value>, CommandError> {
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    let response = handle
+        .send_request(NotebookRequest::GetCellOutputs { cell_id })
+        .await?;
+
+    match response {
+        NotebookResponse::CellOutputs { outputs, .. } => Ok(outputs),
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
+    }
+}
+
+/// Get the timing of each cell's most recent execution from the daemon.
+#[tauri::command]
+async fn get_cell_timings(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<Vec<runtimed::protocol::CellTiming>, CommandError> {
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    let response = handle
+        .send_request(NotebookRequest::GetCellTimings {})
+        .await?;
+
+    match response {
+        NotebookResponse::CellTimings { timings } => Ok(timings),
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
+    }
+}
+
+/// Get the most recent entries of the notebook's execution log, oldest first.
+/// Empty unless the `run_log` setting was on while cells ran.
+#[tauri::command]
+async fn get_run_log(
+    limit: Option<usize>,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<Vec<runtimed::run_log::RunLogEntry>, CommandError> {
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    let response = handle
+        .send_request(NotebookRequest::GetRunLog {
+            limit: limit.unwrap_or(200),
+        })
+        .await?;
+
+    match response {
+        NotebookResponse::RunLog { entries } => Ok(entries),
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
+    }
+}
+
+/// Get the command line, working directory and environment changes the
+/// notebook's kernel was last spawned with, so a failed launch can be
+/// reproduced in a terminal.
+#[tauri::command]
+async fn get_kernel_launch_spec(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<Option<runtimed::kernel_manager::LaunchSpec>, CommandError> {
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    let response = handle
+        .send_request(NotebookRequest::GetLaunchSpec {})
+        .await?;
+
+    match response {
+        NotebookResponse::LaunchSpec { spec } => Ok(spec),
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
+    }
+}
+
+/// Start or stop streaming environment build output (uv's stdout and stderr)
+/// to this window as `env_build_log` daemon broadcasts. Subscribing returns
+/// the lines the current or last build has written so far.
+#[tauri::command]
+async fn stream_env_build_log(
+    subscribe: bool,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<Vec<runtimed::protocol::EnvBuildLogLine>, CommandError> {
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    let response = handle
+        .send_request(NotebookRequest::StreamEnvBuildLog { subscribe })
+        .await?;
+
+    match response {
+        NotebookResponse::EnvBuildLog { lines } => Ok(lines),
+        NotebookResponse::Ok {} => Ok(Vec::new()),
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
+    }
+}
+
+/// Collapse or expand a cell's outputs, or a single output when
+/// `output_index` is given. Synced to other windows and saved with the
+/// notebook.
+#[tauri::command]
+async fn set_output_collapsed(
+    cell_id: String,
+    output_index: Option<usize>,
+    collapsed: bool,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<(), CommandError> {
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    match output_index {
+        Some(index) => {
+            handle
+                .set_output_collapsed(&cell_id, index, collapsed)
+                .await?
+        }
+        // Expanded is nbformat's default, so it's stored as no flag
+        None => {
+            handle
+                .set_cell_collapsed(&cell_id, collapsed.then_some(true))
+                .await?
+        }
+    }
     Ok(())
 }
 
-// ============================================================================
-// Daemon Kernel Operations
-// ============================================================================
-// These commands route kernel operations through the daemon, which owns the
-// kernel lifecycle and execution queue. This enables multi-window kernel sharing.
+/// Toggle whether a cell's outputs scroll in a fixed-height area.
+#[tauri::command]
+async fn set_output_scrolled(
+    cell_id: String,
+    scrolled: bool,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<(), CommandError> {
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+    handle.set_cell_scrolled(&cell_id, Some(scrolled)).await?;
+    Ok(())
+}
 
-/// Launch a kernel via the daemon.
-///
-/// If a kernel is already running for this notebook, returns info about the existing kernel.
-/// The notebook_path is automatically derived from the sync handle if not provided.
+/// Open the sidecar viewer (`runt jupyter sidecar`) attached to the
+/// notebook's running kernel. Outputs of the MIME types listed in the
+/// `outputs.sidecar_mime` setting are shown there instead of inline.
 #[tauri::command]
-async fn launch_kernel_via_daemon(
-    kernel_type: String,
-    env_source: String,
-    notebook_path: Option<String>,
+async fn open_sidecar(
+    app: tauri::AppHandle,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<NotebookResponse, String> {
+) -> Result<(), CommandError> {
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let guard = notebook_sync.lock().await;
-    let handle = guard.as_ref().ok_or("Not connected to daemon")?;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
 
-    // Use notebook_id from the sync handle if notebook_path not provided,
-    // but only if it looks like a real file path (not a UUID for untitled notebooks)
-    let resolved_path = notebook_path.or_else(|| {
-        let id = handle.notebook_id();
-        // Check if it looks like a file path (contains path separator or starts with /)
-        if id.contains('/') || id.contains('\\') {
-            Some(id.to_string())
-        } else {
-            // Likely a UUID for an untitled notebook - don't use as path
-            None
+    let response = handle
+        .send_request(NotebookRequest::GetConnectionFile {})
+        .await?;
+    let connection_file = match response {
+        NotebookResponse::ConnectionFile { path: Some(path) } => path,
+        NotebookResponse::ConnectionFile { path: None } => {
+            return Err(CommandError::KernelError("No kernel running".to_string()))
         }
-    });
+        NotebookResponse::Error { error } => return Err(CommandError::KernelError(error)),
+        _ => {
+            return Err(CommandError::KernelError(
+                "Unexpected response from daemon".to_string(),
+            ))
+        }
+    };
 
+    // Dev builds don't bundle runt; fall back to the one on PATH
+    let runt =
+        crate::cli_install::get_bundled_runt_path(&app).unwrap_or_else(|| PathBuf::from("runt"));
     info!(
-        "[daemon-kernel] launch_kernel_via_daemon: type={}, env_source={}, path={:?}",
-        kernel_type, env_source, resolved_path
+        "[sidecar] Opening sidecar for {}",
+        connection_file.display()
     );
-
-    handle
-        .send_request(NotebookRequest::LaunchKernel {
-            kernel_type,
-            env_source,
-            notebook_path: resolved_path,
-        })
-        .await
-        .map_err(|e| format!("daemon request failed: {}", e))
+    std::process::Command::new(&runt)
+        .args(["jupyter", "sidecar", "--quiet"])
+        .arg(&connection_file)
+        .spawn()
+        .map_err(|e| CommandError::Io(format!("Failed to launch {}: {}", runt.display(), e)))?;
+    Ok(())
 }
 
-/// Queue a cell for execution via the daemon.
-///
-/// Execute a cell via the daemon (reads source from synced document).
-///
-/// This is the preferred method - ensures execution matches synced document state.
-/// The daemon reads the cell source from the Automerge doc instead of receiving it as a parameter.
+/// Save the Python kernel's variables via the daemon so the next kernel can
+/// restore them. Variables that can't be pickled are listed as dropped.
 #[tauri::command]
-async fn execute_cell_via_daemon(
-    cell_id: String,
+async fn snapshot_kernel_state(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<NotebookResponse, String> {
-    info!(
-        "[daemon-kernel] execute_cell_via_daemon: cell_id={}",
-        cell_id
-    );
+) -> Result<NotebookResponse, CommandError> {
+    info!("[daemon-kernel] snapshot_kernel_state");
 
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let guard = notebook_sync.lock().await;
-    let handle = guard.as_ref().ok_or("Not connected to daemon")?;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
 
-    handle
-        .send_request(NotebookRequest::ExecuteCell { cell_id })
-        .await
-        .map_err(|e| format!("daemon request failed: {}", e))
+    let response = handle
+        .send_request(NotebookRequest::SnapshotKernelState {})
+        .await?;
+
+    match response {
+        NotebookResponse::KernelStateSnapshot { .. } => Ok(response),
+        NotebookResponse::NoKernel {} => {
+            Err(CommandError::KernelError("No kernel running".to_string()))
+        }
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
+    }
 }
 
-/// Clear outputs for a cell via the daemon.
+/// Load the last kernel state snapshot into the running kernel via the daemon.
 #[tauri::command]
-async fn clear_outputs_via_daemon(
-    cell_id: String,
+async fn restore_kernel_state(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<NotebookResponse, String> {
-    info!(
-        "[daemon-kernel] clear_outputs_via_daemon: cell_id={}",
-        cell_id
-    );
+) -> Result<NotebookResponse, CommandError> {
+    info!("[daemon-kernel] restore_kernel_state");
 
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let guard = notebook_sync.lock().await;
-    let handle = guard.as_ref().ok_or("Not connected to daemon")?;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
 
-    handle
-        .send_request(NotebookRequest::ClearOutputs { cell_id })
-        .await
-        .map_err(|e| format!("daemon request failed: {}", e))
+    let response = handle
+        .send_request(NotebookRequest::RestoreKernelState {})
+        .await?;
+
+    match response {
+        NotebookResponse::KernelStateRestored { .. } => Ok(response),
+        NotebookResponse::NoKernel {} => {
+            Err(CommandError::KernelError("No kernel running".to_string()))
+        }
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
+    }
 }
 
-/// Interrupt kernel execution via the daemon.
-#[tauri::command]
-async fn interrupt_via_daemon(
-    window: tauri::Window,
-    registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<NotebookResponse, String> {
-    info!("[daemon-kernel] interrupt_via_daemon");
+/// Send a request to the kernel's debugger via the daemon and return the body
+/// of the debugger's response.
+async fn send_debug_command(
+    window: &tauri::Window,
+    registry: &WindowNotebookRegistry,
+    request: DebugCommand,
+) -> Result<serde_json::Value, CommandError> {
+    info!("[daemon-kernel] debug {:?}", request);
 
-    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let notebook_sync = notebook_sync_for_window(window, registry)?;
     let guard = notebook_sync.lock().await;
-    let handle = guard.as_ref().ok_or("Not connected to daemon")?;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
 
-    handle
-        .send_request(NotebookRequest::InterruptExecution {})
-        .await
-        .map_err(|e| format!("daemon request failed: {}", e))
+    let response = handle
+        .send_request(NotebookRequest::Debug { request })
+        .await?;
+
+    match response {
+        NotebookResponse::DebugResult { body } => Ok(body),
+        NotebookResponse::NoKernel {} => {
+            Err(CommandError::KernelError("No kernel running".to_string()))
+        }
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
+    }
 }
 
-/// Shutdown the kernel via the daemon.
+/// Start a debug session with the kernel. Returns the debugger's capabilities.
 #[tauri::command]
-async fn shutdown_kernel_via_daemon(
+async fn debug_initialize(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<NotebookResponse, String> {
-    info!("[daemon-kernel] shutdown_kernel_via_daemon");
+) -> Result<serde_json::Value, CommandError> {
+    send_debug_command(&window, registry.inner(), DebugCommand::Initialize {}).await
+}
 
-    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
-    let guard = notebook_sync.lock().await;
-    let handle = guard.as_ref().ok_or("Not connected to daemon")?;
+/// Replace the breakpoints in the source file at `path` with ones on `lines`.
+#[tauri::command]
+async fn debug_set_breakpoints(
+    path: String,
+    lines: Vec<u32>,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<serde_json::Value, CommandError> {
+    send_debug_command(
+        &window,
+        registry.inner(),
+        DebugCommand::SetBreakpoints { path, lines },
+    )
+    .await
+}
 
-    handle
-        .send_request(NotebookRequest::ShutdownKernel {})
-        .await
-        .map_err(|e| format!("daemon request failed: {}", e))
+/// Resume a thread stopped at a breakpoint.
+#[tauri::command]
+async fn debug_continue(
+    thread_id: i64,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<serde_json::Value, CommandError> {
+    send_debug_command(
+        &window,
+        registry.inner(),
+        DebugCommand::Continue { thread_id },
+    )
+    .await
 }
 
-/// Sync environment via the daemon - hot-install new packages without restart.
-/// Only supported for UV inline deps.
+/// Get the call stack of a stopped thread.
 #[tauri::command]
-async fn sync_environment_via_daemon(
+async fn debug_stack_trace(
+    thread_id: i64,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<NotebookResponse, String> {
-    info!("[daemon-kernel] sync_environment_via_daemon");
+) -> Result<serde_json::Value, CommandError> {
+    send_debug_command(
+        &window,
+        registry.inner(),
+        DebugCommand::StackTrace { thread_id },
+    )
+    .await
+}
+
+/// Restart the kernel via the daemon using one of the listed environments.
+#[tauri::command]
+async fn switch_environment(
+    option_id: String,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<NotebookResponse, CommandError> {
+    info!("[daemon-kernel] switch_environment: {}", option_id);
+
+    // Tell the daemon where the notebook lives now: after Save As the room
+    // may still be the one opened for the untitled notebook, and project
+    // environments are found relative to the file
+    let notebook_path = {
+        let state = notebook_state_for_window(&window, registry.inner())?;
+        let state = state.lock()?;
+        state
+            .path
+            .as_ref()
+            .map(|p| p.to_string_lossy().into_owned())
+    };
 
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let guard = notebook_sync.lock().await;
-    let handle = guard.as_ref().ok_or("Not connected to daemon")?;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
 
     handle
-        .send_request(NotebookRequest::SyncEnvironment {})
+        .send_request(NotebookRequest::SwitchEnvironment {
+            option_id,
+            notebook_path,
+        })
         .await
-        .map_err(|e| format!("daemon request failed: {}", e))
+        .map_err(CommandError::from)
 }
 
 /// Get kernel info from the daemon.
@@ -1503,7 +2904,7 @@ async fn sync_environment_via_daemon(
 async fn get_daemon_kernel_info(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<NotebookResponse, String> {
+) -> Result<NotebookResponse, CommandError> {
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let guard = notebook_sync.lock().await;
     let has_handle = guard.is_some();
@@ -1514,13 +2915,40 @@ async fn get_daemon_kernel_info(
 
     let handle = guard.as_ref().ok_or_else(|| {
         warn!("[daemon-kernel] get_daemon_kernel_info: notebook_sync is None - connection may have failed or been cleared");
-        "Not connected to daemon".to_string()
+        CommandError::not_connected()
     })?;
 
     handle
         .send_request(NotebookRequest::GetKernelInfo {})
         .await
-        .map_err(|e| format!("daemon request failed: {}", e))
+        .map_err(CommandError::from)
+}
+
+/// The environment source the running kernel was launched with (e.g.
+/// `"uv:prewarmed"`, `"conda:inline"`), or `None` if no kernel is running.
+///
+/// The same value arrives in the kernel's launch lifecycle event; this lets
+/// a window that missed it (after a reconnect) ask again.
+#[tauri::command]
+async fn get_kernel_env_source(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<Option<String>, CommandError> {
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    match handle
+        .send_request(NotebookRequest::GetKernelInfo {})
+        .await
+        .map_err(CommandError::from)?
+    {
+        NotebookResponse::KernelInfo { env_source, .. } => Ok(env_source),
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
+    }
 }
 
 /// Check if daemon is connected.
@@ -1529,7 +2957,7 @@ async fn get_daemon_kernel_info(
 async fn is_daemon_connected(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<bool, String> {
+) -> Result<bool, CommandError> {
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let guard = notebook_sync.lock().await;
     Ok(guard.is_some())
@@ -1540,17 +2968,41 @@ async fn is_daemon_connected(
 async fn get_daemon_queue_state(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<NotebookResponse, String> {
+) -> Result<NotebookResponse, CommandError> {
     info!("[daemon-kernel] get_daemon_queue_state");
 
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let guard = notebook_sync.lock().await;
-    let handle = guard.as_ref().ok_or("Not connected to daemon")?;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
 
     handle
         .send_request(NotebookRequest::GetQueueState {})
         .await
-        .map_err(|e| format!("daemon request failed: {}", e))
+        .map_err(CommandError::from)
+}
+
+/// Execute several cells via the daemon as one pipelined batch, in the given
+/// order. Daemon reads cell sources from the synced Automerge document and
+/// skips unknown ids and non-code cells.
+#[tauri::command]
+async fn execute_cells_via_daemon(
+    cell_ids: Vec<String>,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<NotebookResponse, CommandError> {
+    info!(
+        "[daemon-kernel] execute_cells_via_daemon: {} cells",
+        cell_ids.len()
+    );
+
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    handle
+        .send_request(NotebookRequest::QueueCellBatch { cell_ids })
+        .await
+        .map_err(CommandError::from)
 }
 
 /// Run all code cells via the daemon.
@@ -1559,17 +3011,36 @@ async fn get_daemon_queue_state(
 async fn run_all_cells_via_daemon(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<NotebookResponse, String> {
+) -> Result<NotebookResponse, CommandError> {
     info!("[daemon-kernel] run_all_cells_via_daemon");
 
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let guard = notebook_sync.lock().await;
-    let handle = guard.as_ref().ok_or("Not connected to daemon")?;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
 
     handle
         .send_request(NotebookRequest::RunAllCells {})
         .await
-        .map_err(|e| format!("daemon request failed: {}", e))
+        .map_err(CommandError::from)
+}
+
+/// Cancel a run-all: interrupt the executing cell and drop every queued cell.
+/// The daemon reports how many were skipped with a `run_all:cancelled` event.
+#[tauri::command]
+async fn cancel_run_all(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<NotebookResponse, CommandError> {
+    info!("[daemon-kernel] cancel_run_all");
+
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    handle
+        .send_request(NotebookRequest::CancelRunAll {})
+        .await
+        .map_err(CommandError::from)
 }
 
 /// Send a comm message to the kernel via the daemon (for widget interactions).
@@ -1581,7 +3052,7 @@ async fn send_comm_via_daemon(
     message: serde_json::Value,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<NotebookResponse, String> {
+) -> Result<NotebookResponse, CommandError> {
     let msg_type = message
         .get("header")
         .and_then(|h| h.get("msg_type"))
@@ -1594,12 +3065,12 @@ async fn send_comm_via_daemon(
 
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let guard = notebook_sync.lock().await;
-    let handle = guard.as_ref().ok_or("Not connected to daemon")?;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
 
     handle
         .send_request(NotebookRequest::SendComm { message })
         .await
-        .map_err(|e| format!("daemon request failed: {}", e))
+        .map_err(CommandError::from)
 }
 
 /// Get kernel input history via daemon.
@@ -1612,7 +3083,7 @@ async fn get_history_via_daemon(
     n: i32,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Vec<HistoryEntry>, String> {
+) -> Result<Vec<HistoryEntry>, CommandError> {
     info!(
         "[daemon-kernel] get_history_via_daemon: pattern={:?}, n={}",
         pattern, n
@@ -1620,7 +3091,7 @@ async fn get_history_via_daemon(
 
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let guard = notebook_sync.lock().await;
-    let handle = guard.as_ref().ok_or("Not connected to daemon")?;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
 
     let response = handle
         .send_request(NotebookRequest::GetHistory {
@@ -1628,14 +3099,17 @@ async fn get_history_via_daemon(
             n,
             unique: true,
         })
-        .await
-        .map_err(|e| format!("daemon request failed: {}", e))?;
+        .await?;
 
     match response {
         NotebookResponse::HistoryResult { entries } => Ok(entries),
-        NotebookResponse::NoKernel {} => Err("No kernel running".to_string()),
-        NotebookResponse::Error { error } => Err(error),
-        _ => Err("Unexpected response from daemon".to_string()),
+        NotebookResponse::NoKernel {} => {
+            Err(CommandError::KernelError("No kernel running".to_string()))
+        }
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
     }
 }
 
@@ -1657,7 +3131,7 @@ async fn complete_via_daemon(
     cursor_pos: usize,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<CompletionResult, String> {
+) -> Result<CompletionResult, CommandError> {
     debug!(
         "[daemon-kernel] complete_via_daemon: cursor_pos={}",
         cursor_pos
@@ -1665,12 +3139,11 @@ async fn complete_via_daemon(
 
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let guard = notebook_sync.lock().await;
-    let handle = guard.as_ref().ok_or("Not connected to daemon")?;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
 
     let response = handle
         .send_request(NotebookRequest::Complete { code, cursor_pos })
-        .await
-        .map_err(|e| format!("daemon request failed: {}", e))?;
+        .await?;
 
     match response {
         NotebookResponse::CompletionResult {
@@ -1682,9 +3155,70 @@ async fn complete_via_daemon(
             cursor_start,
             cursor_end,
         }),
-        NotebookResponse::NoKernel {} => Err("No kernel running".to_string()),
-        NotebookResponse::Error { error } => Err(error),
-        _ => Err("Unexpected response from daemon".to_string()),
+        NotebookResponse::NoKernel {} => {
+            Err(CommandError::KernelError("No kernel running".to_string()))
+        }
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
+    }
+}
+
+/// Result type for inspect requests (matches frontend interface).
+#[derive(Serialize)]
+struct InspectResult {
+    found: bool,
+    text: Option<String>,
+    source: Option<String>,
+}
+
+/// Get documentation for the symbol at the cursor via daemon.
+///
+/// Uses the language server when `lsp.enabled` is set and it has an answer,
+/// otherwise the kernel's inspect_request.
+#[tauri::command]
+async fn inspect_via_daemon(
+    code: String,
+    cursor_pos: usize,
+    detail_level: Option<usize>,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<InspectResult, CommandError> {
+    debug!(
+        "[daemon-kernel] inspect_via_daemon: cursor_pos={}",
+        cursor_pos
+    );
+
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    let response = handle
+        .send_request(NotebookRequest::Inspect {
+            code,
+            cursor_pos,
+            detail_level: detail_level.unwrap_or(0),
+        })
+        .await?;
+
+    match response {
+        NotebookResponse::InspectResult {
+            found,
+            text,
+            source,
+        } => Ok(InspectResult {
+            found,
+            text,
+            source,
+        }),
+        NotebookResponse::NoKernel {} => {
+            Err(CommandError::KernelError("No kernel running".to_string()))
+        }
+        NotebookResponse::Error { error } => Err(CommandError::KernelError(error)),
+        _ => Err(CommandError::KernelError(
+            "Unexpected response from daemon".to_string(),
+        )),
     }
 }
 
@@ -1696,7 +3230,7 @@ async fn reconnect_to_daemon(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
     reconnect_in_progress: tauri::State<'_, ReconnectInProgress>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     info!("[daemon-kernel] reconnect_to_daemon");
 
     let notebook_state = notebook_state_for_window(&window, registry.inner())?;
@@ -1730,7 +3264,7 @@ async fn reconnect_to_daemon(
     let webview_window = window
         .app_handle()
         .get_webview_window(window.label())
-        .ok_or_else(|| "Current webview window not found".to_string())?;
+        .ok_or_else(|| CommandError::NotFound("Current webview window not found".to_string()))?;
     let result = initialize_notebook_sync(
         webview_window,
         notebook_state,
@@ -1752,15 +3286,12 @@ async fn reconnect_to_daemon(
 async fn refresh_from_automerge(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let guard = notebook_sync.lock().await;
-    let handle = guard.as_ref().ok_or("Not connected to daemon")?;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
 
-    let cells = handle
-        .get_cells()
-        .await
-        .map_err(|e| format!("Failed to get cells: {}", e))?;
+    let cells = handle.get_cells().await?;
 
     info!(
         "[notebook-sync] Refreshing frontend with {} cells from Automerge",
@@ -1769,7 +3300,46 @@ async fn refresh_from_automerge(
 
     // Emit to frontend (which will resolve manifest hashes)
     emit_to_label::<_, _, _>(&window, window.label(), "notebook:updated", &cells)
-        .map_err(|e| format!("Failed to emit notebook:updated: {}", e))
+        .map_err(|e| CommandError::Io(format!("Failed to emit notebook:updated: {}", e)))
+}
+
+/// Bring local notebook state back in line with the daemon's doc.
+///
+/// Fetches the daemon's cells, applies the smallest set of deletes, moves,
+/// adds and content updates that makes local state match (see
+/// [`reconcile::plan`]), and emits a single `notebook:updated`. Returns the
+/// operations applied; empty when nothing had drifted.
+#[tauri::command]
+async fn reconcile_with_daemon(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<Vec<reconcile::CellOp>, CommandError> {
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let guard = notebook_sync.lock().await;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
+
+    // Outputs must be resolved before they're compared or copied: as
+    // manifest hashes they'd all read as missing
+    let snapshots = resolved_daemon_cells(handle).await?;
+    let daemon_cells: Vec<Cell> = snapshots.iter().map(cell_snapshot_to_nbformat).collect();
+
+    let ops = {
+        let mut s = state.lock()?;
+        let ops = reconcile::plan(&s.notebook.cells, &daemon_cells);
+        reconcile::apply(&mut s.notebook.cells, &daemon_cells, &ops);
+        ops
+    };
+    if !ops.is_empty() {
+        info!(
+            "[notebook-sync] Reconciled {} local cell ops with the daemon",
+            ops.len()
+        );
+    }
+
+    emit_to_label::<_, _, _>(&window, window.label(), "notebook:updated", &snapshots)
+        .map_err(|e| CommandError::Io(format!("Failed to emit notebook:updated: {}", e)))?;
+    Ok(ops)
 }
 
 /// Debug: Get Automerge document state from the daemon.
@@ -1779,17 +3349,14 @@ async fn refresh_from_automerge(
 async fn debug_get_automerge_state(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Vec<serde_json::Value>, String> {
+) -> Result<Vec<serde_json::Value>, CommandError> {
     info!("[debug] Getting Automerge state from daemon");
 
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let guard = notebook_sync.lock().await;
-    let handle = guard.as_ref().ok_or("Not connected to daemon")?;
+    let handle = guard.as_ref().ok_or_else(CommandError::not_connected)?;
 
-    let cells = handle
-        .get_cells()
-        .await
-        .map_err(|e| format!("Failed to get cells: {}", e))?;
+    let cells = handle.get_cells().await?;
 
     // Convert CellSnapshots to JSON for easy inspection
     let json_cells: Vec<serde_json::Value> = cells
@@ -1814,11 +3381,11 @@ async fn debug_get_automerge_state(
 fn debug_get_local_state(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Vec<serde_json::Value>, String> {
+) -> Result<Vec<serde_json::Value>, CommandError> {
     info!("[debug] Getting local notebook state");
 
     let state = notebook_state_for_window(&window, registry.inner())?;
-    let state = state.lock().map_err(|e| e.to_string())?;
+    let state = state.lock()?;
 
     // Use cells_for_frontend which handles the nbformat Cell enum
     let frontend_cells = state.cells_for_frontend();
@@ -1858,9 +3425,9 @@ fn debug_get_local_state(
 async fn get_preferred_kernelspec(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Option<String>, String> {
+) -> Result<Option<String>, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
-    let state = state.lock().map_err(|e| e.to_string())?;
+    let state = state.lock()?;
     Ok(state
         .notebook
         .metadata
@@ -1870,7 +3437,7 @@ async fn get_preferred_kernelspec(
 }
 
 #[tauri::command]
-async fn list_kernelspecs() -> Result<Vec<KernelspecInfo>, String> {
+async fn list_kernelspecs() -> Result<Vec<KernelspecInfo>, CommandError> {
     let specs = runtimelib::list_kernelspecs().await;
     Ok(specs
         .into_iter()
@@ -1904,9 +3471,9 @@ async fn check_uv_available() -> bool {
 async fn get_notebook_dependencies(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Option<NotebookDependenciesJson>, String> {
+) -> Result<Option<NotebookDependenciesJson>, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
-    let state = state.lock().map_err(|e| e.to_string())?;
+    let state = state.lock()?;
     let deps = uv_env::extract_dependencies(&state.notebook.metadata);
     Ok(deps.map(|d| NotebookDependenciesJson {
         dependencies: d.dependencies,
@@ -1921,11 +3488,16 @@ async fn set_notebook_dependencies(
     requires_python: Option<String>,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
+    let dependencies = dependencies
+        .iter()
+        .map(|spec| uv_env::parse_requirement(spec).map(|req| req.spec))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| CommandError::Validation(e.to_string()))?;
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     {
-        let mut state = state.lock().map_err(|e| e.to_string())?;
+        let mut state = state.lock()?;
         let deps = uv_env::NotebookDependencies {
             dependencies,
             requires_python,
@@ -1937,56 +3509,107 @@ async fn set_notebook_dependencies(
     Ok(())
 }
 
-/// Add a single dependency to the notebook.
+/// Add a dependency to the notebook, or update the constraint of an existing one.
+#[tauri::command]
+async fn add_dependency(
+    package: String,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<(), CommandError> {
+    let req =
+        uv_env::parse_requirement(&package).map_err(|e| CommandError::Validation(e.to_string()))?;
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let changed = {
+        let mut s = state.lock()?;
+
+        // Get existing deps or create new
+        let existing = uv_env::extract_dependencies(&s.notebook.metadata);
+        let mut deps = existing
+            .as_ref()
+            .map(|d| d.dependencies.clone())
+            .unwrap_or_default();
+
+        // Add the package, or update its constraint if it's already listed
+        if uv_env::upsert_requirement(&mut deps, &req) {
+            let requires_python = existing.and_then(|d| d.requires_python);
+            let new_deps = uv_env::NotebookDependencies {
+                dependencies: deps,
+                requires_python,
+            };
+            uv_env::set_dependencies(&mut s.notebook.metadata, &new_deps);
+            s.dirty = true;
+            true
+        } else {
+            false
+        }
+    };
+    if changed {
+        push_metadata_to_sync(&state, &notebook_sync).await;
+    }
+    Ok(())
+}
+
+/// Outcome of importing a pasted requirements block.
+#[derive(Serialize)]
+struct RequirementsImportJson {
+    /// Dependencies added or whose constraint changed
+    added: usize,
+    /// Lines that aren't valid requirements, with the reason
+    rejected: Vec<String>,
+    /// pip directives (`-r`, `-e`, ...) that were ignored
+    skipped: Vec<String>,
+}
+
+/// Add every requirement in a pasted `requirements.txt` block to the notebook.
+///
+/// Valid lines are merged like `add_dependency`; invalid lines and pip
+/// directives are reported back rather than failing the whole import.
 #[tauri::command]
-async fn add_dependency(
-    package: String,
+async fn import_requirements_text(
+    text: String,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<RequirementsImportJson, CommandError> {
+    let parsed = uv_env::parse_requirements_text(&text);
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
-    let changed = {
-        let mut s = state.lock().map_err(|e| e.to_string())?;
-
-        // Get existing deps or create new
+    let added = {
+        let mut s = state.lock()?;
         let existing = uv_env::extract_dependencies(&s.notebook.metadata);
         let mut deps = existing
             .as_ref()
             .map(|d| d.dependencies.clone())
             .unwrap_or_default();
-
-        // Check if already exists (by package name, ignoring version specifiers)
-        let pkg_name = package
-            .split(&['>', '<', '=', '!', '~', '['][..])
-            .next()
-            .unwrap_or(&package);
-        let already_exists = deps.iter().any(|d| {
-            let existing_name = d
-                .split(&['>', '<', '=', '!', '~', '['][..])
-                .next()
-                .unwrap_or(d);
-            existing_name.eq_ignore_ascii_case(pkg_name)
-        });
-
-        if !already_exists {
-            deps.push(package);
-            let requires_python = existing.and_then(|d| d.requires_python);
+        let added = parsed
+            .requirements
+            .iter()
+            .filter(|req| uv_env::upsert_requirement(&mut deps, req))
+            .count();
+        if added > 0 {
             let new_deps = uv_env::NotebookDependencies {
                 dependencies: deps,
-                requires_python,
+                requires_python: existing.and_then(|d| d.requires_python),
             };
             uv_env::set_dependencies(&mut s.notebook.metadata, &new_deps);
             s.dirty = true;
-            true
-        } else {
-            false
         }
+        added
     };
-    if changed {
+    if added > 0 {
         push_metadata_to_sync(&state, &notebook_sync).await;
     }
-    Ok(())
+    info!(
+        "[deps] Imported {} requirements ({} rejected, {} directives skipped)",
+        added,
+        parsed.rejected.len(),
+        parsed.skipped.len()
+    );
+    Ok(RequirementsImportJson {
+        added,
+        rejected: parsed.rejected.iter().map(|e| e.to_string()).collect(),
+        skipped: parsed.skipped,
+    })
 }
 
 /// Remove a dependency from the notebook.
@@ -1995,11 +3618,11 @@ async fn remove_dependency(
     package: String,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     {
-        let mut s = state.lock().map_err(|e| e.to_string())?;
+        let mut s = state.lock()?;
         let existing = uv_env::extract_dependencies(&s.notebook.metadata);
         if let Some(existing) = existing {
             let pkg_name = package
@@ -2038,18 +3661,18 @@ async fn clear_dependency_section(
     section: String,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     if section != "uv" && section != "conda" {
-        return Err(format!(
+        return Err(CommandError::Validation(format!(
             "Invalid section: {}. Must be 'uv' or 'conda'.",
             section
-        ));
+        )));
     }
 
     {
-        let mut s = state.lock().map_err(|e| e.to_string())?;
+        let mut s = state.lock()?;
         match section.as_str() {
             "uv" => {
                 if uv_env::has_uv_config(&s.notebook.metadata) {
@@ -2070,6 +3693,210 @@ async fn clear_dependency_section(
     Ok(())
 }
 
+/// A dependency `migrate_dependencies` couldn't translate cleanly.
+#[derive(Serialize)]
+struct FlaggedDependencyJson {
+    spec: String,
+    reason: String,
+}
+
+/// Outcome of migrating dependencies between the uv and conda sections.
+#[derive(Serialize)]
+struct DependencyMigrationJson {
+    /// Dependencies written to the target section
+    migrated: usize,
+    /// Source dependencies that were guessed at, moved to pip, or left out
+    flagged: Vec<FlaggedDependencyJson>,
+}
+
+/// Move the notebook's inline dependencies from one section ("uv" or
+/// "conda") to the other, translating package names and version
+/// constraints where they differ. Dependencies are merged into any existing
+/// target section, and the source section is removed.
+#[tauri::command]
+async fn migrate_dependencies(
+    from: String,
+    to: String,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<DependencyMigrationJson, CommandError> {
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+
+    let result = {
+        let mut s = state.lock()?;
+        let metadata = &mut s.notebook.metadata;
+        let (migration, migrated) = match (from.as_str(), to.as_str()) {
+            ("uv", "conda") => {
+                let source = uv_env::extract_dependencies(metadata).ok_or_else(|| {
+                    CommandError::NotFound("Notebook has no uv dependencies".to_string())
+                })?;
+                let migration = dependency_migration::uv_to_conda(&source.dependencies);
+                let mut target = conda_env::extract_dependencies(metadata).unwrap_or(
+                    conda_env::CondaDependencies {
+                        dependencies: Vec::new(),
+                        channels: Vec::new(),
+                        channel_priority: None,
+                        python: None,
+                        env_id: None,
+                        pip_dependencies: Vec::new(),
+                    },
+                );
+                let mut migrated = 0;
+                for spec in &migration.dependencies {
+                    if let Ok(req) = conda_env::parse_match_spec(spec) {
+                        conda_env::upsert_match_spec(&mut target.dependencies, &req);
+                        migrated += 1;
+                    }
+                }
+                for spec in &migration.pip_dependencies {
+                    if let Ok(req) = uv_env::parse_requirement(spec) {
+                        uv_env::upsert_requirement(&mut target.pip_dependencies, &req);
+                        migrated += 1;
+                    }
+                }
+                if target.python.is_none() {
+                    target.python = source
+                        .requires_python
+                        .as_deref()
+                        .and_then(dependency_migration::requires_python_to_conda);
+                }
+                target.env_id = None;
+                conda_env::set_dependencies(metadata, &target);
+                uv_env::remove_uv_config(metadata);
+                (migration, migrated)
+            }
+            ("conda", "uv") => {
+                let source = conda_env::extract_dependencies(metadata).ok_or_else(|| {
+                    CommandError::NotFound("Notebook has no conda dependencies".to_string())
+                })?;
+                let migration = dependency_migration::conda_to_uv(&source.dependencies);
+                let mut target = uv_env::extract_dependencies(metadata).unwrap_or(
+                    uv_env::NotebookDependencies {
+                        dependencies: Vec::new(),
+                        requires_python: None,
+                    },
+                );
+                // conda's pip phase already holds PyPI requirements
+                let mut migrated = 0;
+                for spec in migration
+                    .dependencies
+                    .iter()
+                    .chain(&source.pip_dependencies)
+                {
+                    if let Ok(req) = uv_env::parse_requirement(spec) {
+                        uv_env::upsert_requirement(&mut target.dependencies, &req);
+                        migrated += 1;
+                    }
+                }
+                if target.requires_python.is_none() {
+                    target.requires_python = source.python.map(|py| format!(">={}", py));
+                }
+                uv_env::set_dependencies(metadata, &target);
+                conda_env::remove_conda_config(metadata);
+                (migration, migrated)
+            }
+            _ => {
+                return Err(CommandError::Validation(format!(
+                    "Can't migrate dependencies from '{}' to '{}'. Use 'uv' and 'conda'.",
+                    from, to
+                )))
+            }
+        };
+        s.dirty = true;
+        DependencyMigrationJson {
+            migrated,
+            flagged: migration
+                .flagged
+                .into_iter()
+                .map(|f| FlaggedDependencyJson {
+                    spec: f.spec,
+                    reason: f.reason,
+                })
+                .collect(),
+        }
+    };
+    push_metadata_to_sync(&state, &notebook_sync).await;
+    info!(
+        "[deps] Migrated {} dependencies from {} to {} ({} flagged)",
+        result.migrated,
+        from,
+        to,
+        result.flagged.len()
+    );
+    Ok(result)
+}
+
+/// Both uv and conda inline dependencies are present in the notebook.
+#[derive(Serialize, Debug, PartialEq)]
+struct EnvConflict {
+    /// The section auto-launch would use ("uv" or "conda").
+    auto_launch_section: String,
+    /// Packages only listed in the uv section.
+    uv_only: Vec<String>,
+    /// Packages only listed in the conda section.
+    conda_only: Vec<String>,
+}
+
+/// The inline section the daemon's auto-launch picks when both have
+/// dependencies. Mirrors `check_inline_deps` in runtimed, which checks uv
+/// before conda.
+fn auto_launch_section(uv_deps: &[String], conda_deps: &[String]) -> Option<&'static str> {
+    if !uv_deps.is_empty() {
+        Some("uv")
+    } else if !conda_deps.is_empty() {
+        Some("conda")
+    } else {
+        None
+    }
+}
+
+/// Report a conflict when both uv and conda sections list dependencies.
+fn env_conflict(metadata: &nbformat::v4::Metadata) -> Option<EnvConflict> {
+    let uv_deps = uv_env::extract_dependencies(metadata)?.dependencies;
+    let conda_deps = conda_env::extract_dependencies(metadata)?.dependencies;
+    if uv_deps.is_empty() || conda_deps.is_empty() {
+        return None;
+    }
+
+    let uv_names: HashSet<String> = uv_deps
+        .iter()
+        .map(|d| uv_env::requirement_name(d))
+        .collect();
+    let conda_names: HashSet<String> = conda_deps
+        .iter()
+        .map(|d| conda_env::match_spec_name(d))
+        .collect();
+    let uv_only = uv_deps
+        .iter()
+        .filter(|d| !conda_names.contains(&uv_env::requirement_name(d)))
+        .cloned()
+        .collect();
+    let conda_only = conda_deps
+        .iter()
+        .filter(|d| !uv_names.contains(&conda_env::match_spec_name(d)))
+        .cloned()
+        .collect();
+
+    Some(EnvConflict {
+        auto_launch_section: auto_launch_section(&uv_deps, &conda_deps)?.to_string(),
+        uv_only,
+        conda_only,
+    })
+}
+
+/// Detect a notebook with both uv and conda inline dependencies, so the user
+/// can pick one before a kernel launches with the other.
+#[tauri::command]
+async fn detect_env_conflict(
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<Option<EnvConflict>, CommandError> {
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let state = state.lock()?;
+    Ok(env_conflict(&state.notebook.metadata))
+}
+
 // ============================================================================
 // Conda Dependency Management Commands
 // ============================================================================
@@ -2079,7 +3906,12 @@ async fn clear_dependency_section(
 struct CondaDependenciesJson {
     dependencies: Vec<String>,
     channels: Vec<String>,
+    #[serde(default)]
+    channel_priority: Option<kernel_env::ChannelPriority>,
     python: Option<String>,
+    /// PyPI-only packages pip-installed after the conda solve
+    #[serde(default)]
+    pip_dependencies: Vec<String>,
 }
 
 /// Get conda dependencies from notebook metadata.
@@ -2087,14 +3919,16 @@ struct CondaDependenciesJson {
 async fn get_conda_dependencies(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Option<CondaDependenciesJson>, String> {
+) -> Result<Option<CondaDependenciesJson>, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
-    let state = state.lock().map_err(|e| e.to_string())?;
+    let state = state.lock()?;
     let deps = conda_env::extract_dependencies(&state.notebook.metadata);
     Ok(deps.map(|d| CondaDependenciesJson {
         dependencies: d.dependencies,
         channels: d.channels,
+        channel_priority: d.channel_priority,
         python: d.python,
+        pip_dependencies: d.pip_dependencies,
     }))
 }
 
@@ -2103,19 +3937,31 @@ async fn get_conda_dependencies(
 async fn set_conda_dependencies(
     dependencies: Vec<String>,
     channels: Vec<String>,
+    channel_priority: Option<kernel_env::ChannelPriority>,
     python: Option<String>,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
+    let dependencies = dependencies
+        .iter()
+        .map(|spec| conda_env::parse_match_spec(spec).map(|req| req.spec))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| CommandError::Validation(e.to_string()))?;
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     {
-        let mut s = state.lock().map_err(|e| e.to_string())?;
+        let mut s = state.lock()?;
+        // The conda editor doesn't manage pip deps, so keep any already recorded
+        let pip_dependencies = conda_env::extract_dependencies(&s.notebook.metadata)
+            .map(|d| d.pip_dependencies)
+            .unwrap_or_default();
         let deps = conda_env::CondaDependencies {
             dependencies,
             channels,
+            channel_priority,
             python,
             env_id: None,
+            pip_dependencies,
         };
         conda_env::set_dependencies(&mut s.notebook.metadata, &deps);
         s.dirty = true;
@@ -2124,17 +3970,19 @@ async fn set_conda_dependencies(
     Ok(())
 }
 
-/// Add a single conda dependency to the notebook.
+/// Add a conda dependency to the notebook, or update the constraint of an existing one.
 #[tauri::command]
 async fn add_conda_dependency(
     package: String,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
+    let req = conda_env::parse_match_spec(&package)
+        .map_err(|e| CommandError::Validation(e.to_string()))?;
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let changed = {
-        let mut s = state.lock().map_err(|e| e.to_string())?;
+        let mut s = state.lock()?;
 
         let existing = conda_env::extract_dependencies(&s.notebook.metadata);
         let mut deps = existing
@@ -2145,27 +3993,22 @@ async fn add_conda_dependency(
             .as_ref()
             .map(|d| d.channels.clone())
             .unwrap_or_default();
+        let channel_priority = existing.as_ref().and_then(|d| d.channel_priority);
         let python = existing.as_ref().and_then(|d| d.python.clone());
+        let pip_dependencies = existing
+            .as_ref()
+            .map(|d| d.pip_dependencies.clone())
+            .unwrap_or_default();
 
-        let pkg_name = package
-            .split(&['>', '<', '=', '!', '~', '['][..])
-            .next()
-            .unwrap_or(&package);
-        let already_exists = deps.iter().any(|d| {
-            let existing_name = d
-                .split(&['>', '<', '=', '!', '~', '['][..])
-                .next()
-                .unwrap_or(d);
-            existing_name.eq_ignore_ascii_case(pkg_name)
-        });
-
-        if !already_exists {
-            deps.push(package);
+        // Add the package, or update its constraint if it's already listed
+        if conda_env::upsert_match_spec(&mut deps, &req) {
             let new_deps = conda_env::CondaDependencies {
                 dependencies: deps,
                 channels,
+                channel_priority,
                 python,
                 env_id: None,
+                pip_dependencies,
             };
             conda_env::set_dependencies(&mut s.notebook.metadata, &new_deps);
             s.dirty = true;
@@ -2186,11 +4029,11 @@ async fn remove_conda_dependency(
     package: String,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     {
-        let mut s = state.lock().map_err(|e| e.to_string())?;
+        let mut s = state.lock()?;
         let existing = conda_env::extract_dependencies(&s.notebook.metadata);
         if let Some(existing) = existing {
             let pkg_name = package
@@ -2211,8 +4054,10 @@ async fn remove_conda_dependency(
             let new_deps = conda_env::CondaDependencies {
                 dependencies: deps,
                 channels: existing.channels,
+                channel_priority: existing.channel_priority,
                 python: existing.python,
                 env_id: existing.env_id,
+                pip_dependencies: existing.pip_dependencies,
             };
             conda_env::set_dependencies(&mut s.notebook.metadata, &new_deps);
             s.dirty = true;
@@ -2222,6 +4067,91 @@ async fn remove_conda_dependency(
     Ok(())
 }
 
+/// Add a PyPI package to a conda notebook, pip-installed into the conda
+/// environment after the solve. For packages conda channels don't carry.
+#[tauri::command]
+async fn add_conda_pip_dependency(
+    package: String,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<(), CommandError> {
+    let req =
+        uv_env::parse_requirement(&package).map_err(|e| CommandError::Validation(e.to_string()))?;
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let changed = {
+        let mut s = state.lock()?;
+        let mut deps = conda_env::extract_dependencies(&s.notebook.metadata).unwrap_or(
+            conda_env::CondaDependencies {
+                dependencies: vec![],
+                channels: vec![],
+                channel_priority: None,
+                python: None,
+                env_id: None,
+                pip_dependencies: vec![],
+            },
+        );
+        if uv_env::upsert_requirement(&mut deps.pip_dependencies, &req) {
+            conda_env::set_dependencies(&mut s.notebook.metadata, &deps);
+            s.dirty = true;
+            true
+        } else {
+            false
+        }
+    };
+    if changed {
+        push_metadata_to_sync(&state, &notebook_sync).await;
+    }
+    Ok(())
+}
+
+/// Remove a pip-installed package from a conda notebook.
+#[tauri::command]
+async fn remove_conda_pip_dependency(
+    package: String,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<(), CommandError> {
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+    let changed = {
+        let mut s = state.lock()?;
+        match conda_env::extract_dependencies(&s.notebook.metadata) {
+            Some(mut deps) => {
+                let name = uv_env::requirement_name(&package);
+                let before = deps.pip_dependencies.len();
+                deps.pip_dependencies
+                    .retain(|d| uv_env::requirement_name(d) != name);
+                let changed = deps.pip_dependencies.len() != before;
+                if changed {
+                    conda_env::set_dependencies(&mut s.notebook.metadata, &deps);
+                    s.dirty = true;
+                }
+                changed
+            }
+            None => false,
+        }
+    };
+    if changed {
+        push_metadata_to_sync(&state, &notebook_sync).await;
+    }
+    Ok(())
+}
+
+/// Show `path` (e.g. a detected project file) in the OS file manager.
+#[tauri::command]
+async fn reveal_in_file_manager(path: String) -> Result<(), CommandError> {
+    let path = PathBuf::from(path);
+    if !path.exists() {
+        return Err(CommandError::NotFound(format!(
+            "{} does not exist",
+            path.display()
+        )));
+    }
+    reveal::reveal(&path)
+        .map_err(|e| CommandError::Io(format!("Failed to open the file manager: {}", e)))
+}
+
 // ============================================================================
 // pyproject.toml Discovery and Environment Commands
 // ============================================================================
@@ -2231,10 +4161,10 @@ async fn remove_conda_dependency(
 async fn detect_pyproject(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Option<pyproject::PyProjectInfo>, String> {
+) -> Result<Option<pyproject::PyProjectInfo>, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_path = {
-        let state = state.lock().map_err(|e| e.to_string())?;
+        let state = state.lock()?;
         state.path.clone()
     };
 
@@ -2249,7 +4179,8 @@ async fn detect_pyproject(
     };
 
     // Parse and create info
-    let config = pyproject::parse_pyproject(&pyproject_path).map_err(|e| e.to_string())?;
+    let config =
+        pyproject::parse_pyproject(&pyproject_path).map_err(|e| CommandError::Io(e.to_string()))?;
     let info = pyproject::create_pyproject_info(&config, &notebook_path);
 
     info!(
@@ -2277,10 +4208,10 @@ struct PyProjectDepsJson {
 async fn get_pyproject_dependencies(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Option<PyProjectDepsJson>, String> {
+) -> Result<Option<PyProjectDepsJson>, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_path = {
-        let state = state.lock().map_err(|e| e.to_string())?;
+        let state = state.lock()?;
         state.path.clone()
     };
 
@@ -2292,7 +4223,8 @@ async fn get_pyproject_dependencies(
         return Ok(None);
     };
 
-    let config = pyproject::parse_pyproject(&pyproject_path).map_err(|e| e.to_string())?;
+    let config =
+        pyproject::parse_pyproject(&pyproject_path).map_err(|e| CommandError::Io(e.to_string()))?;
 
     let relative_path = pathdiff::diff_paths(
         &config.path,
@@ -2318,26 +4250,29 @@ async fn get_pyproject_dependencies(
 async fn import_pyproject_dependencies(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let notebook_path = {
-        let s = state.lock().map_err(|e| e.to_string())?;
+        let s = state.lock()?;
         s.path.clone()
     };
 
     let Some(notebook_path) = notebook_path else {
-        return Err("No notebook path set".to_string());
+        return Err(CommandError::NotFound("No notebook path set".to_string()));
     };
 
     let Some(pyproject_path) = pyproject::find_pyproject(&notebook_path) else {
-        return Err("No pyproject.toml found".to_string());
+        return Err(CommandError::NotFound(
+            "No pyproject.toml found".to_string(),
+        ));
     };
 
-    let config = pyproject::parse_pyproject(&pyproject_path).map_err(|e| e.to_string())?;
+    let config =
+        pyproject::parse_pyproject(&pyproject_path).map_err(|e| CommandError::Io(e.to_string()))?;
 
     {
-        let mut s = state.lock().map_err(|e| e.to_string())?;
+        let mut s = state.lock()?;
         let all_deps = pyproject::get_all_dependencies(&config);
         let deps = uv_env::NotebookDependencies {
             dependencies: all_deps.clone(),
@@ -2365,10 +4300,11 @@ async fn import_pyproject_dependencies(
 async fn verify_notebook_trust(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<trust::TrustInfo, String> {
+) -> Result<trust::TrustInfo, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
-    let state = state.lock().map_err(|e| e.to_string())?;
-    trust::verify_notebook_trust(&state.notebook.metadata.additional)
+    let state = state.lock()?;
+    trust::verify_notebook_trust(&state.notebook.metadata.additional, state.path.as_deref())
+        .map_err(CommandError::Io)
 }
 
 /// Approve the notebook's dependencies and sign them with the local trust key.
@@ -2379,14 +4315,15 @@ async fn verify_notebook_trust(
 async fn approve_notebook_trust(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     {
-        let mut s = state.lock().map_err(|e| e.to_string())?;
+        let mut s = state.lock()?;
 
         // Compute signature over current dependencies
-        let signature = trust::sign_notebook_dependencies(&s.notebook.metadata.additional)?;
+        let signature = trust::sign_notebook_dependencies(&s.notebook.metadata.additional)
+            .map_err(CommandError::Io)?;
 
         // Get or create the runt metadata section
         let runt_value = s
@@ -2414,6 +4351,31 @@ async fn approve_notebook_trust(
     Ok(())
 }
 
+/// Trust every notebook under `path`, so they don't need approving one by one.
+#[tauri::command]
+async fn trust_directory(path: String) -> Result<trust::TrustScope, CommandError> {
+    let path = PathBuf::from(path);
+    if !path.is_dir() {
+        return Err(CommandError::NotFound(format!(
+            "{} is not a directory",
+            path.display()
+        )));
+    }
+    trust::trust_directory(&path).map_err(CommandError::Io)
+}
+
+/// Stop trusting notebooks under `path`. Returns whether it was trusted.
+#[tauri::command]
+async fn revoke_directory_trust(path: String) -> Result<bool, CommandError> {
+    trust::revoke_directory_trust(Path::new(&path)).map_err(CommandError::Io)
+}
+
+/// List the trusted directories.
+#[tauri::command]
+async fn list_trusted_directories() -> Result<Vec<trust::TrustScope>, CommandError> {
+    trust::trusted_directories().map_err(CommandError::Io)
+}
+
 /// Check packages for typosquatting (similar names to popular packages).
 ///
 /// Returns warnings for any packages that look like potential typosquats.
@@ -2431,10 +4393,10 @@ async fn check_typosquats(packages: Vec<String>) -> Vec<typosquat::TyposquatWarn
 async fn detect_pixi_toml(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Option<pixi::PixiInfo>, String> {
+) -> Result<Option<pixi::PixiInfo>, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_path = {
-        let state = state.lock().map_err(|e| e.to_string())?;
+        let state = state.lock()?;
         state.path.clone()
     };
 
@@ -2449,7 +4411,7 @@ async fn detect_pixi_toml(
     };
 
     // Parse and create info
-    let config = pixi::parse_pixi_toml(&pixi_path).map_err(|e| e.to_string())?;
+    let config = pixi::parse_pixi_toml(&pixi_path).map_err(|e| CommandError::Io(e.to_string()))?;
     let info = pixi::create_pixi_info(&config, &notebook_path);
 
     info!(
@@ -2469,10 +4431,10 @@ async fn detect_pixi_toml(
 async fn detect_environment_yml(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Option<environment_yml::EnvironmentYmlInfo>, String> {
+) -> Result<Option<environment_yml::EnvironmentYmlInfo>, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_path = {
-        let state = state.lock().map_err(|e| e.to_string())?;
+        let state = state.lock()?;
         state.path.clone()
     };
 
@@ -2487,7 +4449,8 @@ async fn detect_environment_yml(
     };
 
     // Parse and create info
-    let config = environment_yml::parse_environment_yml(&yml_path).map_err(|e| e.to_string())?;
+    let config = environment_yml::parse_environment_yml(&yml_path)
+        .map_err(|e| CommandError::Io(e.to_string()))?;
     let info = environment_yml::create_environment_yml_info(&config, &notebook_path);
 
     info!(
@@ -2515,10 +4478,10 @@ struct EnvironmentYmlDepsJson {
 async fn get_environment_yml_dependencies(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Option<EnvironmentYmlDepsJson>, String> {
+) -> Result<Option<EnvironmentYmlDepsJson>, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_path = {
-        let state = state.lock().map_err(|e| e.to_string())?;
+        let state = state.lock()?;
         state.path.clone()
     };
 
@@ -2530,7 +4493,8 @@ async fn get_environment_yml_dependencies(
         return Ok(None);
     };
 
-    let config = environment_yml::parse_environment_yml(&yml_path).map_err(|e| e.to_string())?;
+    let config = environment_yml::parse_environment_yml(&yml_path)
+        .map_err(|e| CommandError::Io(e.to_string()))?;
 
     let relative_path = pathdiff::diff_paths(
         &config.path,
@@ -2556,38 +4520,41 @@ async fn get_environment_yml_dependencies(
 async fn import_pixi_dependencies(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     let notebook_path = {
-        let s = state.lock().map_err(|e| e.to_string())?;
+        let s = state.lock()?;
         s.path.clone()
     };
 
     let Some(notebook_path) = notebook_path else {
-        return Err("No notebook path set".to_string());
+        return Err(CommandError::NotFound("No notebook path set".to_string()));
     };
 
     let Some(pixi_path) = pixi::find_pixi_toml(&notebook_path) else {
-        return Err("No pixi.toml found".to_string());
+        return Err(CommandError::NotFound("No pixi.toml found".to_string()));
     };
 
-    let config = pixi::parse_pixi_toml(&pixi_path).map_err(|e| e.to_string())?;
+    let config = pixi::parse_pixi_toml(&pixi_path).map_err(|e| CommandError::Io(e.to_string()))?;
     let conda_deps = pixi::convert_to_conda_dependencies(&config);
 
     {
-        let mut s = state.lock().map_err(|e| e.to_string())?;
+        let mut s = state.lock()?;
         let deps = conda_env::CondaDependencies {
             dependencies: conda_deps.dependencies.clone(),
             channels: conda_deps.channels,
+            channel_priority: conda_deps.channel_priority,
             python: conda_deps.python,
             env_id: None,
+            pip_dependencies: conda_deps.pip_dependencies.clone(),
         };
         conda_env::set_dependencies(&mut s.notebook.metadata, &deps);
         s.dirty = true;
         info!(
-            "Imported {} dependencies from pixi.toml into notebook conda metadata",
-            conda_deps.dependencies.len()
+            "Imported {} conda and {} PyPI dependencies from pixi.toml into notebook conda metadata",
+            conda_deps.dependencies.len(),
+            conda_deps.pip_dependencies.len()
         );
     }
     push_metadata_to_sync(&state, &notebook_sync).await;
@@ -2604,10 +4571,10 @@ async fn check_deno_available() -> bool {
 
 /// Get the installed Deno version
 #[tauri::command]
-async fn get_deno_version() -> Result<String, String> {
+async fn get_deno_version() -> Result<String, CommandError> {
     deno_env::get_deno_version()
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| CommandError::NotFound(e.to_string()))
 }
 
 /// Get the runtime type from notebook metadata
@@ -2615,9 +4582,9 @@ async fn get_deno_version() -> Result<String, String> {
 async fn get_notebook_runtime(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<String, String> {
+) -> Result<String, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
-    let state = state.lock().map_err(|e| e.to_string())?;
+    let state = state.lock()?;
     Ok(state.get_runtime().to_string())
 }
 
@@ -2626,10 +4593,10 @@ async fn get_notebook_runtime(
 async fn detect_deno_config(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Option<deno_env::DenoConfigInfo>, String> {
+) -> Result<Option<deno_env::DenoConfigInfo>, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_path = {
-        let state = state.lock().map_err(|e| e.to_string())?;
+        let state = state.lock()?;
         state.path.clone()
     };
 
@@ -2641,7 +4608,8 @@ async fn detect_deno_config(
         return Ok(None);
     };
 
-    let config = deno_env::parse_deno_config(&config_path).map_err(|e| e.to_string())?;
+    let config =
+        deno_env::parse_deno_config(&config_path).map_err(|e| CommandError::Io(e.to_string()))?;
     Ok(Some(deno_env::create_deno_config_info(
         &config,
         &notebook_path,
@@ -2653,9 +4621,9 @@ async fn detect_deno_config(
 async fn get_deno_permissions(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<Vec<String>, String> {
+) -> Result<Vec<String>, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
-    let state = state.lock().map_err(|e| e.to_string())?;
+    let state = state.lock()?;
     let deps = deno_env::extract_deno_metadata(&state.notebook.metadata);
     Ok(deps.map(|d| d.permissions).unwrap_or_default())
 }
@@ -2666,11 +4634,11 @@ async fn set_deno_permissions(
     permissions: Vec<String>,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     {
-        let mut s = state.lock().map_err(|e| e.to_string())?;
+        let mut s = state.lock()?;
         let mut deno_deps =
             deno_env::extract_deno_metadata(&s.notebook.metadata).unwrap_or_default();
         deno_deps.permissions = permissions;
@@ -2686,9 +4654,9 @@ async fn set_deno_permissions(
 async fn get_deno_flexible_npm_imports(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<bool, String> {
+) -> Result<bool, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
-    let state = state.lock().map_err(|e| e.to_string())?;
+    let state = state.lock()?;
     let deps = deno_env::extract_deno_metadata(&state.notebook.metadata);
     Ok(deps.map(|d| d.flexible_npm_imports).unwrap_or(true))
 }
@@ -2699,11 +4667,11 @@ async fn set_deno_flexible_npm_imports(
     enabled: bool,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
     {
-        let mut s = state.lock().map_err(|e| e.to_string())?;
+        let mut s = state.lock()?;
         let mut deno_deps =
             deno_env::extract_deno_metadata(&s.notebook.metadata).unwrap_or_default();
         deno_deps.flexible_npm_imports = enabled;
@@ -2722,14 +4690,14 @@ async fn format_cell(
     cell_id: String,
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<format::FormatResult, String> {
+) -> Result<format::FormatResult, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     // Get current source and runtime
     let (source, runtime) = {
-        let nb = state.lock().map_err(|e| e.to_string())?;
+        let nb = state.lock()?;
         let src = nb
             .get_cell_source(&cell_id)
-            .ok_or_else(|| "Cell not found".to_string())?;
+            .ok_or_else(|| CommandError::NotFound("Cell not found".to_string()))?;
         let rt = nb.get_runtime();
         (src, rt)
     };
@@ -2747,12 +4715,14 @@ async fn format_cell(
     let mut result = match runtime {
         Runtime::Python => format::format_python(&source)
             .await
-            .map_err(|e| e.to_string())?,
+            .map_err(|e| CommandError::Io(e.to_string()))?,
         Runtime::Deno => format::format_deno(&source, "typescript")
             .await
-            .map_err(|e| e.to_string())?,
+            .map_err(|e| CommandError::Io(e.to_string()))?,
         Runtime::Other(ref s) => {
-            return Err(format!("No formatter available for runtime: {s}"));
+            return Err(CommandError::Validation(format!(
+                "No formatter available for runtime: {s}"
+            )));
         }
     };
 
@@ -2763,7 +4733,7 @@ async fn format_cell(
     // If formatting changed the source, update the backend state and notify frontend
     if result.changed {
         {
-            let mut nb = state.lock().map_err(|e| e.to_string())?;
+            let mut nb = state.lock()?;
             nb.update_cell_source(&cell_id, &result.source);
         }
         // Emit event to notify frontend of the source change
@@ -2781,16 +4751,65 @@ async fn format_cell(
     Ok(result)
 }
 
+/// Render a cell's source and outputs as Markdown for pasting into an issue.
+///
+/// Outputs come from the daemon's doc when connected, since kernel output
+/// lands there rather than in local state.
+#[tauri::command]
+async fn copy_cell_as_markdown(
+    cell_id: String,
+    window: tauri::Window,
+    registry: tauri::State<'_, WindowNotebookRegistry>,
+) -> Result<String, CommandError> {
+    let state = notebook_state_for_window(&window, registry.inner())?;
+    let notebook_sync = notebook_sync_for_window(&window, registry.inner())?;
+
+    let synced_cell = match notebook_sync.lock().await.as_ref() {
+        Some(handle) => match resolved_daemon_cells(handle).await {
+            Ok(cells) => cells
+                .iter()
+                .find(|c| c.id == cell_id)
+                .map(cell_snapshot_to_nbformat),
+            Err(e) => {
+                warn!(
+                    "[notebook-sync] copy_cell_as_markdown could not resolve cells: {}",
+                    e
+                );
+                None
+            }
+        },
+        None => None,
+    };
+
+    let nb = state.lock()?;
+    let cell = match synced_cell {
+        Some(cell) => cell,
+        None => nb
+            .notebook
+            .cells
+            .iter()
+            .find(|c| c.id().as_str() == cell_id)
+            .cloned()
+            .ok_or_else(|| CommandError::NotFound(format!("Cell {} not found", cell_id)))?,
+    };
+    let language = match nb.get_runtime() {
+        Runtime::Python => "python".to_string(),
+        Runtime::Deno => "typescript".to_string(),
+        Runtime::Other(name) => name,
+    };
+    Ok(format::cell_to_markdown(&cell, &language))
+}
+
 /// Check if a formatter is available for the current notebook runtime.
 /// Returns true if ruff is available for Python notebooks or deno for TypeScript notebooks.
 #[tauri::command]
 async fn check_formatter_available(
     window: tauri::Window,
     registry: tauri::State<'_, WindowNotebookRegistry>,
-) -> Result<bool, String> {
+) -> Result<bool, CommandError> {
     let state = notebook_state_for_window(&window, registry.inner())?;
     let runtime = {
-        let nb = state.lock().map_err(|e| e.to_string())?;
+        let nb = state.lock()?;
         nb.get_runtime()
     };
 
@@ -2809,29 +4828,29 @@ async fn get_settings() -> runtimed::settings_doc::SyncedSettings {
 
 /// Set the default runtime preference
 #[tauri::command]
-async fn set_default_runtime(runtime: Runtime) -> Result<(), String> {
+async fn set_default_runtime(runtime: Runtime) -> Result<(), CommandError> {
     let mut settings = settings::load_settings();
     settings.default_runtime = runtime;
-    settings::save_settings(&settings).map_err(|e| e.to_string())
+    settings::save_settings(&settings).map_err(|e| CommandError::Io(e.to_string()))
 }
 
 /// Set the default Python environment type (uv or conda)
 #[tauri::command]
-async fn set_default_python_env(env_type: String) -> Result<(), String> {
+async fn set_default_python_env(env_type: String) -> Result<(), CommandError> {
     let python_env: settings::PythonEnvType = env_type
         .parse()
         .expect("FromStr for PythonEnvType is infallible");
 
     let mut settings = settings::load_settings();
     settings.default_python_env = python_env;
-    settings::save_settings(&settings).map_err(|e| e.to_string())
+    settings::save_settings(&settings).map_err(|e| CommandError::Io(e.to_string()))
 }
 
 /// Get synced settings from the Automerge settings document via runtimed.
 /// Falls back to reading settings.json when the daemon is unavailable,
 /// so the frontend always gets real settings instead of hardcoded defaults.
 #[tauri::command]
-async fn get_synced_settings() -> Result<runtimed::settings_doc::SyncedSettings, String> {
+async fn get_synced_settings() -> Result<runtimed::settings_doc::SyncedSettings, CommandError> {
     match runtimed::sync_client::try_get_synced_settings().await {
         Ok(settings) => {
             log::info!(
@@ -2858,44 +4877,105 @@ async fn get_synced_settings() -> Result<runtimed::settings_doc::SyncedSettings,
 }
 
 /// Persist a setting to local settings.json (for keys that have local representation).
-fn save_setting_locally(key: &str, value: &serde_json::Value) -> Result<(), String> {
+fn save_setting_locally(key: &str, value: &serde_json::Value) -> Result<(), CommandError> {
     match key {
         "theme" => {
-            let value_str = value.as_str().ok_or("expected string")?;
+            let value_str = value
+                .as_str()
+                .ok_or_else(|| CommandError::Validation("expected string".to_string()))?;
             let theme: runtimed::settings_doc::ThemeMode =
-                serde_json::from_str(&format!("\"{value_str}\"")).map_err(|e| e.to_string())?;
+                serde_json::from_str(&format!("\"{value_str}\""))?;
             let mut s = settings::load_settings();
             s.theme = theme;
-            settings::save_settings(&s).map_err(|e| e.to_string())
+            settings::save_settings(&s).map_err(|e| CommandError::Io(e.to_string()))
         }
         "default_runtime" => {
-            let value_str = value.as_str().ok_or("expected string")?;
-            let runtime: Runtime =
-                serde_json::from_str(&format!("\"{}\"", value_str)).map_err(|e| e.to_string())?;
+            let value_str = value
+                .as_str()
+                .ok_or_else(|| CommandError::Validation("expected string".to_string()))?;
+            let runtime: Runtime = serde_json::from_str(&format!("\"{}\"", value_str))?;
             let mut s = settings::load_settings();
             s.default_runtime = runtime;
-            settings::save_settings(&s).map_err(|e| e.to_string())
+            settings::save_settings(&s).map_err(|e| CommandError::Io(e.to_string()))
         }
         "default_python_env" => {
-            let value_str = value.as_str().ok_or("expected string")?;
+            let value_str = value
+                .as_str()
+                .ok_or_else(|| CommandError::Validation("expected string".to_string()))?;
             let env_type: settings::PythonEnvType = value_str
                 .parse()
                 .expect("FromStr for PythonEnvType is infallible");
             let mut s = settings::load_settings();
             s.default_python_env = env_type;
-            settings::save_settings(&s).map_err(|e| e.to_string())
+            settings::save_settings(&s).map_err(|e| CommandError::Io(e.to_string()))
         }
         "uv.default_packages" => {
             let packages = json_value_to_string_vec(value);
             let mut s = settings::load_settings();
             s.uv.default_packages = packages;
-            settings::save_settings(&s).map_err(|e| e.to_string())
+            settings::save_settings(&s).map_err(|e| CommandError::Io(e.to_string()))
         }
         "conda.default_packages" => {
             let packages = json_value_to_string_vec(value);
             let mut s = settings::load_settings();
             s.conda.default_packages = packages;
-            settings::save_settings(&s).map_err(|e| e.to_string())
+            settings::save_settings(&s).map_err(|e| CommandError::Io(e.to_string()))
+        }
+        "ansi_spans" => {
+            let enabled = value
+                .as_bool()
+                .ok_or_else(|| CommandError::Validation("expected boolean".to_string()))?;
+            let mut s = settings::load_settings();
+            s.ansi_spans = enabled;
+            settings::save_settings(&s).map_err(|e| CommandError::Io(e.to_string()))
+        }
+        "keep_kernel_on_close" => {
+            let enabled = value
+                .as_bool()
+                .ok_or_else(|| CommandError::Validation("expected boolean".to_string()))?;
+            let mut s = settings::load_settings();
+            s.keep_kernel_on_close = enabled;
+            settings::save_settings(&s).map_err(|e| CommandError::Io(e.to_string()))
+        }
+        "keep_kernel_on_exit" => {
+            let enabled = value
+                .as_bool()
+                .ok_or_else(|| CommandError::Validation("expected boolean".to_string()))?;
+            let mut s = settings::load_settings();
+            s.keep_kernel_on_exit = enabled;
+            settings::save_settings(&s).map_err(|e| CommandError::Io(e.to_string()))
+        }
+        "run_log" => {
+            let enabled = value
+                .as_bool()
+                .ok_or_else(|| CommandError::Validation("expected boolean".to_string()))?;
+            let mut s = settings::load_settings();
+            s.run_log = enabled;
+            settings::save_settings(&s).map_err(|e| CommandError::Io(e.to_string()))
+        }
+        "block_invalid_save" => {
+            let enabled = value
+                .as_bool()
+                .ok_or_else(|| CommandError::Validation("expected boolean".to_string()))?;
+            let mut s = settings::load_settings();
+            s.block_invalid_save = enabled;
+            settings::save_settings(&s).map_err(|e| CommandError::Io(e.to_string()))
+        }
+        "hide_install_suggestions" => {
+            let enabled = value
+                .as_bool()
+                .ok_or_else(|| CommandError::Validation("expected boolean".to_string()))?;
+            let mut s = settings::load_settings();
+            s.hide_install_suggestions = enabled;
+            settings::save_settings(&s).map_err(|e| CommandError::Io(e.to_string()))
+        }
+        "lsp.enabled" => {
+            let enabled = value
+                .as_bool()
+                .ok_or_else(|| CommandError::Validation("expected boolean".to_string()))?;
+            let mut s = settings::load_settings();
+            s.lsp.enabled = enabled;
+            settings::save_settings(&s).map_err(|e| CommandError::Io(e.to_string()))
         }
         _ => Ok(()),
     }
@@ -2915,7 +4995,7 @@ fn json_value_to_string_vec(value: &serde_json::Value) -> Vec<String> {
 
 /// Update a synced setting via the daemon and persist locally.
 #[tauri::command]
-async fn set_synced_setting(key: String, value: serde_json::Value) -> Result<(), String> {
+async fn set_synced_setting(key: String, value: serde_json::Value) -> Result<(), CommandError> {
     // Always persist to local settings.json so the menu handler can read it synchronously
     save_setting_locally(&key, &value)?;
 
@@ -2933,7 +5013,7 @@ async fn set_synced_setting(key: String, value: serde_json::Value) -> Result<(),
                 client
                     .put_value(&key, &value)
                     .await
-                    .map_err(|e| format!("sync error: {}", e))?;
+                    .map_err(|e| CommandError::NotConnected(format!("sync error: {}", e)))?;
             }
             Err(e) => {
                 log::warn!("[settings] Sync daemon unavailable ({}), local-only", e);
@@ -2954,7 +5034,7 @@ async fn set_synced_setting(key: String, value: serde_json::Value) -> Result<(),
                 client
                     .put_value(&key, &value)
                     .await
-                    .map_err(|e| format!("sync error: {}", e))?;
+                    .map_err(|e| CommandError::NotConnected(format!("sync error: {}", e)))?;
             }
             Err(e) => {
                 log::warn!("[settings] Sync daemon unavailable ({}), local-only", e);
@@ -2971,19 +5051,38 @@ fn focused_window(app: &tauri::AppHandle) -> Option<tauri::WebviewWindow> {
         .find(|window| window.is_focused().ok() == Some(true))
 }
 
+/// Flip the focused notebook's `runt.auto_launch` flag and sync it to the
+/// daemon, which consults it the next time the notebook is opened.
+async fn toggle_auto_launch(context: &WindowNotebookContext) {
+    let enabled = {
+        let Ok(mut state) = context.notebook_state.lock() else {
+            return;
+        };
+        let enabled = !state.auto_launch_enabled();
+        state.set_auto_launch(enabled);
+        enabled
+    };
+    info!(
+        "[menu] Kernel auto-launch {} for this notebook",
+        if enabled { "enabled" } else { "disabled" }
+    );
+    push_metadata_to_sync(&context.notebook_state, &context.notebook_sync).await;
+}
+
 /// Create a new notebook window with the specified runtime.
 fn spawn_new_notebook(
     app: &tauri::AppHandle,
     registry: &WindowNotebookRegistry,
     runtime: Runtime,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let state = NotebookState::new_empty_with_runtime(runtime);
     create_notebook_window(app, registry, state).map(|_| ())
 }
 
 /// Ensure ~/notebooks directory exists and return its path.
-fn ensure_notebooks_directory() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("Could not determine home directory")?;
+fn ensure_notebooks_directory() -> Result<PathBuf, CommandError> {
+    let home = dirs::home_dir()
+        .ok_or_else(|| CommandError::NotFound("Could not determine home directory".to_string()))?;
     let notebooks_dir = home.join("notebooks");
     match std::fs::create_dir(&notebooks_dir) {
         Ok(()) => Ok(notebooks_dir),
@@ -2992,19 +5091,22 @@ fn ensure_notebooks_directory() -> Result<PathBuf, String> {
             if notebooks_dir.is_dir() {
                 Ok(notebooks_dir)
             } else {
-                Err(format!(
+                Err(CommandError::Io(format!(
                     "~/notebooks exists but is not a directory: {}",
                     notebooks_dir.display()
-                ))
+                )))
             }
         }
-        Err(e) => Err(format!("Failed to create ~/notebooks directory: {}", e)),
+        Err(e) => Err(CommandError::Io(format!(
+            "Failed to create ~/notebooks directory: {}",
+            e
+        ))),
     }
 }
 
 /// Get the default directory for saving new notebooks.
 #[tauri::command]
-async fn get_default_save_directory() -> Result<String, String> {
+async fn get_default_save_directory() -> Result<String, CommandError> {
     ensure_notebooks_directory().map(|p| p.to_string_lossy().to_string())
 }
 
@@ -3029,6 +5131,8 @@ async fn run_settings_sync(app: tauri::AppHandle) {
                     settings.default_python_env
                 );
                 let _ = app.emit("settings:changed", &settings);
+                tools::set_tool_preferences(settings.tools.preferences());
+                let mut keybindings = settings.keybindings;
 
                 // Watch for changes
                 loop {
@@ -3036,6 +5140,11 @@ async fn run_settings_sync(app: tauri::AppHandle) {
                         Ok(settings) => {
                             log::info!("[settings-sync] Settings changed: {:?}", settings);
                             let _ = app.emit("settings:changed", &settings);
+                            tools::set_tool_preferences(settings.tools.preferences());
+                            if settings.keybindings != keybindings {
+                                keybindings = settings.keybindings;
+                                refresh_menu(&app, keybindings.clone());
+                            }
                         }
                         Err(e) => {
                             log::warn!("[settings-sync] Disconnected: {}", e);
@@ -3069,6 +5178,8 @@ async fn run_settings_sync(app: tauri::AppHandle) {
                 // Emit initial settings
                 let settings = client.get_all();
                 let _ = app.emit("settings:changed", &settings);
+                tools::set_tool_preferences(settings.tools.preferences());
+                let mut keybindings = settings.keybindings;
 
                 // Watch for changes
                 loop {
@@ -3076,6 +5187,11 @@ async fn run_settings_sync(app: tauri::AppHandle) {
                         Ok(settings) => {
                             log::info!("[settings-sync] Settings changed: {:?}", settings);
                             let _ = app.emit("settings:changed", &settings);
+                            tools::set_tool_preferences(settings.tools.preferences());
+                            if settings.keybindings != keybindings {
+                                keybindings = settings.keybindings;
+                                refresh_menu(&app, keybindings.clone());
+                            }
                         }
                         Err(e) => {
                             log::warn!("[settings-sync] Disconnected: {}", e);
@@ -3139,20 +5255,22 @@ fn create_new_notebook_state(path: &Path, runtime: Runtime) -> NotebookState {
     state
 }
 
-fn load_notebook_state_for_path(path: &Path, runtime: Runtime) -> Result<NotebookState, String> {
+fn load_notebook_state_for_path(
+    path: &Path,
+    runtime: Runtime,
+) -> Result<NotebookState, CommandError> {
     if path.exists() {
-        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
-        let nb = nbformat::parse_notebook(&content).map_err(|e| e.to_string())?;
+        let content = std::fs::read_to_string(path)?;
+        let nb = nbformat::parse_notebook(&content).map_err(|e| CommandError::Io(e.to_string()))?;
         let mut nb_v4 = match nb {
             nbformat::Notebook::V4(nb) => nb,
-            nbformat::Notebook::Legacy(legacy) => {
-                nbformat::upgrade_legacy_notebook(legacy).map_err(|e| e.to_string())?
-            }
+            nbformat::Notebook::Legacy(legacy) => nbformat::upgrade_legacy_notebook(legacy)
+                .map_err(|e| CommandError::Io(e.to_string()))?,
             nbformat::Notebook::V3(v3) => {
-                nbformat::upgrade_v3_notebook(v3).map_err(|e| e.to_string())?
+                nbformat::upgrade_v3_notebook(v3).map_err(|e| CommandError::Io(e.to_string()))?
             }
         };
-        notebook_state::migrate_legacy_metadata(&mut nb_v4.metadata.additional);
+        metadata_migration::migrate_notebook_metadata(&mut nb_v4.metadata);
         Ok(NotebookState::from_notebook(nb_v4, path.to_path_buf()))
     } else {
         Ok(create_new_notebook_state(path, runtime))
@@ -3169,7 +5287,8 @@ fn create_window_context(state: NotebookState) -> WindowNotebookContext {
 
 /// Run the notebook Tauri app.
 ///
-/// If `notebook_path` is Some, opens that file. If None, creates a new empty notebook.
+/// If `notebook_path` is Some, opens that file, or for a directory, starts with a picker
+/// of the notebooks inside it. If None, creates a new empty notebook.
 /// The `runtime` parameter specifies which runtime to use for new notebooks.
 /// If None, falls back to user's default runtime from settings.
 pub fn run(
@@ -3180,8 +5299,11 @@ pub fn run(
     env_logger::init();
     shell_env::load_shell_environment();
 
-    // Use provided runtime or fall back to user's default from settings
-    let runtime = runtime.unwrap_or_else(|| settings::load_settings().default_runtime);
+    // Use provided runtime or fall back to user's default from settings, and
+    // resolve ruff/deno the way the settings ask until the daemon syncs them
+    let app_settings = settings::load_settings();
+    tools::set_tool_preferences(app_settings.tools.preferences());
+    let runtime = runtime.unwrap_or(app_settings.default_runtime);
 
     // Try to restore session if no notebook path provided
     let restored_session = if notebook_path.is_none() {
@@ -3191,7 +5313,20 @@ pub fn run(
     };
 
     // Determine initial state for main window
+    // A directory opens as a picker over its notebooks, with an untitled
+    // notebook behind it until one is chosen
+    let mut initial_listing = None;
     let initial_state = match notebook_path.as_ref() {
+        Some(path) if path.is_dir() => {
+            let listing = notebook_directory::scan_notebooks(path);
+            info!(
+                "[open] Found {} notebooks in {}",
+                listing.notebooks.len(),
+                path.display()
+            );
+            initial_listing = Some(listing);
+            NotebookState::new_empty_with_runtime(runtime)
+        }
         Some(path) => load_notebook_state_for_path(path, runtime).map_err(anyhow::Error::msg)?,
         None => {
             // Try to restore from session
@@ -3225,6 +5360,13 @@ pub fn run(
         None => "Untitled.ipynb".to_string(),
     };
 
+    let directory_listings = DirectoryListings(Arc::new(Mutex::new(
+        initial_listing
+            .map(|listing| ("main".to_string(), listing))
+            .into_iter()
+            .collect(),
+    )));
+
     let window_registry = WindowNotebookRegistry::default();
     let main_context = create_window_context(initial_state);
     window_registry
@@ -3255,6 +5397,8 @@ pub fn run(
         .plugin(tauri_plugin_window_state::Builder::default().build())
         .manage(window_registry.clone())
         .manage(reconnect_in_progress)
+        .manage(CloneCopyCancel(Arc::new(AtomicBool::new(false))))
+        .manage(directory_listings.clone())
         .invoke_handler(tauri::generate_handler![
             // Notebook file operations
             load_notebook,
@@ -3262,29 +5406,72 @@ pub fn run(
             get_notebook_path,
             save_notebook,
             save_notebook_as,
+            redetect_environment,
             get_default_save_directory,
             clone_notebook_to_path,
+            save_notebook_copy_clean,
+            validate_notebook,
+            get_raw_metadata,
+            set_raw_metadata,
+            regenerate_env_id,
+            detect_shared_env_id,
+            cancel_clone_environment_copy,
             open_notebook_in_new_window,
+            get_directory_listing,
+            open_notebook_in_current_window,
             // Cell operations
             update_cell_source,
             add_cell,
+            duplicate_cell,
             delete_cell,
             // Daemon kernel operations (all kernel ops go through daemon)
             launch_kernel_via_daemon,
             execute_cell_via_daemon,
             clear_outputs_via_daemon,
+            clear_all_outputs,
             interrupt_via_daemon,
             shutdown_kernel_via_daemon,
             sync_environment_via_daemon,
+            repair_environment,
+            pin_environment,
+            unpin_environment,
+            get_dependency_tree,
+            list_available_environments,
+            list_recent_notebooks,
+            search_outputs,
+            get_cell_outputs,
+            get_cell_timings,
+            get_run_log,
+            get_kernel_launch_spec,
+            stream_env_build_log,
+            open_sidecar,
+            set_output_collapsed,
+            set_output_scrolled,
+            snapshot_kernel_state,
+            restore_kernel_state,
+            debug_initialize,
+            debug_set_breakpoints,
+            debug_continue,
+            debug_stack_trace,
+            get_recent_notebooks,
+            shutdown_all_kernels,
+            get_env_cache_info,
+            clear_env_cache,
+            switch_environment,
             get_daemon_kernel_info,
+            get_kernel_env_source,
             is_daemon_connected,
             get_daemon_queue_state,
             run_all_cells_via_daemon,
+            cancel_run_all,
+            execute_cells_via_daemon,
             send_comm_via_daemon,
             get_history_via_daemon,
             complete_via_daemon,
+            inspect_via_daemon,
             reconnect_to_daemon,
             refresh_from_automerge,
+            reconcile_with_daemon,
             debug_get_automerge_state,
             debug_get_local_state,
             // Kernelspec discovery (used by UI)
@@ -3295,15 +5482,21 @@ pub fn run(
             get_notebook_dependencies,
             set_notebook_dependencies,
             add_dependency,
+            import_requirements_text,
             remove_dependency,
             clear_dependency_section,
+            migrate_dependencies,
+            detect_env_conflict,
             // Conda dependency management
             get_conda_dependencies,
             set_conda_dependencies,
             add_conda_dependency,
             remove_conda_dependency,
+            add_conda_pip_dependency,
+            remove_conda_pip_dependency,
             // pyproject.toml discovery
             detect_pyproject,
+            reveal_in_file_manager,
             get_pyproject_dependencies,
             import_pyproject_dependencies,
             // pixi.toml support
@@ -3315,6 +5508,9 @@ pub fn run(
             // Trust verification
             verify_notebook_trust,
             approve_notebook_trust,
+            trust_directory,
+            revoke_directory_trust,
+            list_trusted_directories,
             check_typosquats,
             // Deno kernel support
             check_deno_available,
@@ -3327,6 +5523,7 @@ pub fn run(
             set_deno_flexible_npm_imports,
             // Code formatting
             format_cell,
+            copy_cell_as_markdown,
             check_formatter_available,
             // Settings
             get_settings,
@@ -3339,6 +5536,7 @@ pub fn run(
             get_git_info,
             get_daemon_info,
             get_blob_port,
+            get_blob_token,
         ])
         .setup(move |app| {
             let setup_start = std::time::Instant::now();
@@ -3372,8 +5570,11 @@ pub fn run(
             }
 
             // Set up native menu bar
-            let menu = crate::menu::create_menu(app.handle())?;
+            let keybindings = settings::load_settings().keybindings;
+            let accelerators = crate::menu::MenuAccelerators::from_keybindings(&keybindings);
+            let menu = crate::menu::create_menu(app.handle(), &[], &[], &accelerators)?;
             app.set_menu(menu)?;
+            refresh_menu(app.handle(), keybindings);
 
             // Restore additional windows from session (main window already restored above)
             if let Some(session) = &restored_session {
@@ -3592,6 +5793,12 @@ pub fn run(
                         let _ = emit_to_label::<_, _, _>(&window, window.label(), "menu:clone", ());
                     }
                 }
+                crate::menu::MENU_SAVE_COPY_CLEAN => {
+                    // Emit event to frontend to pick a path for the clean copy
+                    if let Some(window) = focused_window(app) {
+                        let _ = emit_to_label::<_, _, _>(&window, window.label(), "menu:save-copy-clean", ());
+                    }
+                }
                 crate::menu::MENU_ZOOM_IN => {
                     if let Some(window) = focused_window(app) {
                         let _ = emit_to_label::<_, _, _>(&window, window.label(), "menu:zoom-in", ());
@@ -3612,6 +5819,16 @@ pub fn run(
                         let _ = emit_to_label::<_, _, _>(&window, window.label(), "menu:run-all", ());
                     }
                 }
+                crate::menu::MENU_CLEAR_ALL_OUTPUTS => {
+                    if let Some(window) = focused_window(app) {
+                        let _ = emit_to_label::<_, _, _>(
+                            &window,
+                            window.label(),
+                            "menu:clear-all-outputs",
+                            (),
+                        );
+                    }
+                }
                 crate::menu::MENU_RESTART_AND_RUN_ALL => {
                     if let Some(window) = focused_window(app) {
                         let _ = emit_to_label::<_, _, _>(
@@ -3622,6 +5839,57 @@ pub fn run(
                         );
                     }
                 }
+                crate::menu::MENU_RESTART_AND_RESTORE => {
+                    if let Some(window) = focused_window(app) {
+                        let _ = emit_to_label::<_, _, _>(
+                            &window,
+                            window.label(),
+                            "menu:restart-and-restore",
+                            (),
+                        );
+                    }
+                }
+                crate::menu::MENU_TOGGLE_AUTO_LAUNCH => {
+                    if let Some(window) = focused_window(app) {
+                        if let Ok(context) = registry.get(window.label()) {
+                            tauri::async_runtime::spawn(async move {
+                                toggle_auto_launch(&context).await;
+                            });
+                        }
+                    }
+                }
+                crate::menu::MENU_SHUTDOWN_ALL_KERNELS => {
+                    let app_handle = app.clone();
+                    tauri_plugin_dialog::DialogExt::dialog(app)
+                        .message("Shut down the kernels of all open notebooks? Variables and other kernel state will be lost.")
+                        .title("Shut Down All Kernels")
+                        .kind(tauri_plugin_dialog::MessageDialogKind::Warning)
+                        .buttons(tauri_plugin_dialog::MessageDialogButtons::OkCancelCustom(
+                            "Shut Down".to_string(),
+                            "Cancel".to_string(),
+                        ))
+                        .show(move |confirmed| {
+                            if !confirmed {
+                                return;
+                            }
+                            tauri::async_runtime::spawn(async move {
+                                match shutdown_all_kernels().await {
+                                    Ok(notebook_ids) => log::info!(
+                                        "[menu] Shut down {} kernel(s)",
+                                        notebook_ids.len()
+                                    ),
+                                    Err(e) => {
+                                        log::error!("[menu] Failed to shut down kernels: {}", e);
+                                        let _ = tauri_plugin_dialog::DialogExt::dialog(&app_handle)
+                                            .message(format!("Failed to shut down kernels: {}", e))
+                                            .title("Shut Down Failed")
+                                            .kind(tauri_plugin_dialog::MessageDialogKind::Error)
+                                            .blocking_show();
+                                    }
+                                }
+                            });
+                        });
+                }
                 crate::menu::MENU_INSTALL_CLI => {
                     let app_handle = app.clone();
                     match crate::cli_install::install_cli(&app_handle) {
@@ -3650,7 +5918,24 @@ pub fn run(
                     }
                 }
                 _ => {
-                    if let Some(sample) = crate::menu::sample_for_menu_item_id(menu_id) {
+                    if let Some(name) = crate::menu::kernelspec_for_menu_item_id(menu_id) {
+                        let runtime = Runtime::Other(name.to_string());
+                        if let Err(e) = spawn_new_notebook(app, registry.inner(), runtime) {
+                            log::error!(
+                                "[menu] Failed to create {} notebook: {}",
+                                name,
+                                e
+                            );
+                        }
+                    } else if let Some(path) = crate::menu::recent_path_for_menu_item_id(menu_id) {
+                        if let Err(e) = open_notebook_window(app, registry.inner(), &path) {
+                            log::error!(
+                                "[menu] Failed to open recent notebook {}: {}",
+                                path.display(),
+                                e
+                            );
+                        }
+                    } else if let Some(sample) = crate::menu::sample_for_menu_item_id(menu_id) {
                         if let Err(e) = open_bundled_sample_notebook(app, registry.inner(), sample) {
                             log::error!(
                                 "[sample_notebooks] Failed to open sample {}: {}",
@@ -3678,8 +5963,11 @@ pub fn run(
 
     #[cfg(any(target_os = "macos", target_os = "ios"))]
     let registry_for_open = window_registry.clone();
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    let listings_for_open = directory_listings.clone();
     let registry_for_session = window_registry.clone();
     let registry_for_window_close = window_registry.clone();
+    let listings_for_window_close = directory_listings.clone();
     app.run(move |_app_handle, _event| {
         // Clean up registry entries when windows are destroyed
         if let RunEvent::WindowEvent {
@@ -3698,6 +5986,9 @@ pub fn run(
                         );
                     }
                 }
+                if let Ok(mut listings) = listings_for_window_close.0.lock() {
+                    listings.remove(label);
+                }
             }
         }
 
@@ -3710,6 +6001,11 @@ pub fn run(
             } else {
                 log::info!("[session] Session saved successfully");
             }
+
+            if shutdown_kernels_on_exit(&settings::load_settings()) {
+                log::info!("[session] Shutting down daemon kernels on exit");
+                shutdown_daemon_kernels(&registry_for_session);
+            }
         }
 
         // Handle file associations (macOS only)
@@ -3721,7 +6017,8 @@ pub fn run(
                     _ => None,
                 };
                 let Some(path) = path else { continue };
-                if path.extension().and_then(|e| e.to_str()) != Some("ipynb") {
+                let is_dir = path.is_dir();
+                if !is_dir && path.extension().and_then(|e| e.to_str()) != Some("ipynb") {
                     continue;
                 }
 
@@ -3737,7 +6034,37 @@ pub fn run(
                     })
                     .unwrap_or(false);
 
-                if main_is_empty {
+                if is_dir {
+                    let listing = notebook_directory::scan_notebooks(&path);
+                    let label = if main_is_empty {
+                        "main".to_string()
+                    } else {
+                        match create_notebook_window(
+                            _app_handle,
+                            &registry_for_open,
+                            NotebookState::new_empty_with_runtime(
+                                settings::load_settings().default_runtime,
+                            ),
+                        ) {
+                            Ok(label) => label,
+                            Err(e) => {
+                                log::error!("Failed to open directory in new window: {}", e);
+                                continue;
+                            }
+                        }
+                    };
+                    if let Ok(mut listings) = listings_for_open.0.lock() {
+                        listings.insert(label.clone(), listing.clone());
+                    }
+                    if let Some(window) = _app_handle.get_webview_window(&label) {
+                        let _ = emit_to_label::<_, _, _>(
+                            &window,
+                            &label,
+                            "notebook:directory-listing",
+                            &listing,
+                        );
+                    }
+                } else if main_is_empty {
                     match load_notebook_state_for_path(
                         &path,
                         settings::load_settings().default_runtime,
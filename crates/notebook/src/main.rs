@@ -5,7 +5,7 @@ use std::path::PathBuf;
 #[derive(Parser, Debug)]
 #[command(name = "notebook", about = "Open notebooks")]
 struct Args {
-    /// Path to notebook file to open or create
+    /// Path to notebook file to open or create, or a directory to pick one from
     path: Option<PathBuf>,
 
     /// Runtime for new notebooks (python, deno). Falls back to user settings if not specified.
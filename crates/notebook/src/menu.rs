@@ -1,3 +1,6 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
 use tauri::menu::{Menu, MenuItem, PredefinedMenuItem, Submenu};
 use tauri::{AppHandle, Wry};
 
@@ -12,10 +15,13 @@ pub struct BundledSampleNotebook {
 pub const MENU_NEW_NOTEBOOK: &str = "new_notebook";
 pub const MENU_NEW_PYTHON_NOTEBOOK: &str = "new_python_notebook";
 pub const MENU_NEW_DENO_NOTEBOOK: &str = "new_deno_notebook";
+pub const MENU_NEW_KERNELSPEC_PREFIX: &str = "new_kernelspec:";
 pub const MENU_OPEN: &str = "open";
 pub const MENU_OPEN_SAMPLE_PREFIX: &str = "open_sample:";
+pub const MENU_OPEN_RECENT_PREFIX: &str = "open_recent:";
 pub const MENU_SAVE: &str = "save";
 pub const MENU_CLONE_NOTEBOOK: &str = "clone_notebook";
+pub const MENU_SAVE_COPY_CLEAN: &str = "save_copy_clean";
 
 // Menu item IDs for zoom
 pub const MENU_ZOOM_IN: &str = "zoom_in";
@@ -25,6 +31,10 @@ pub const MENU_ZOOM_RESET: &str = "zoom_reset";
 // Menu item IDs for kernel operations
 pub const MENU_RUN_ALL_CELLS: &str = "run_all_cells";
 pub const MENU_RESTART_AND_RUN_ALL: &str = "restart_and_run_all";
+pub const MENU_RESTART_AND_RESTORE: &str = "restart_and_restore";
+pub const MENU_CLEAR_ALL_OUTPUTS: &str = "clear_all_outputs";
+pub const MENU_SHUTDOWN_ALL_KERNELS: &str = "shutdown_all_kernels";
+pub const MENU_TOGGLE_AUTO_LAUNCH: &str = "toggle_auto_launch";
 
 // Menu item IDs for CLI installation
 pub const MENU_INSTALL_CLI: &str = "install_cli";
@@ -61,8 +71,252 @@ pub fn sample_for_menu_item_id(menu_id: &str) -> Option<&'static BundledSampleNo
         .find(|sample| sample.id == sample_id)
 }
 
+/// Built-in keyboard accelerators, keyed by menu item ID.
+const DEFAULT_ACCELERATORS: &[(&str, &str)] = &[
+    (MENU_NEW_NOTEBOOK, "CmdOrCtrl+N"),
+    (MENU_OPEN, "CmdOrCtrl+O"),
+    (MENU_SAVE, "CmdOrCtrl+S"),
+    (MENU_ZOOM_IN, "CmdOrCtrl+="),
+    (MENU_ZOOM_OUT, "CmdOrCtrl+-"),
+    (MENU_ZOOM_RESET, "CmdOrCtrl+0"),
+];
+
+/// Menu items whose accelerator can be set through `settings.keybindings`.
+const CUSTOMIZABLE_MENU_IDS: &[&str] = &[
+    MENU_NEW_NOTEBOOK,
+    MENU_NEW_PYTHON_NOTEBOOK,
+    MENU_NEW_DENO_NOTEBOOK,
+    MENU_OPEN,
+    MENU_SAVE,
+    MENU_CLONE_NOTEBOOK,
+    MENU_SAVE_COPY_CLEAN,
+    MENU_ZOOM_IN,
+    MENU_ZOOM_OUT,
+    MENU_ZOOM_RESET,
+    MENU_RUN_ALL_CELLS,
+    MENU_RESTART_AND_RUN_ALL,
+    MENU_RESTART_AND_RESTORE,
+    MENU_CLEAR_ALL_OUTPUTS,
+    MENU_SHUTDOWN_ALL_KERNELS,
+    MENU_TOGGLE_AUTO_LAUNCH,
+    MENU_INSTALL_CLI,
+];
+
+const ACCELERATOR_MODIFIERS: &[&str] = &[
+    "cmdorctrl",
+    "commandorcontrol",
+    "cmd",
+    "command",
+    "super",
+    "ctrl",
+    "control",
+    "alt",
+    "option",
+    "shift",
+    "meta",
+];
+
+const ACCELERATOR_NAMED_KEYS: &[&str] = &[
+    "plus",
+    "space",
+    "tab",
+    "enter",
+    "return",
+    "escape",
+    "esc",
+    "backspace",
+    "delete",
+    "insert",
+    "home",
+    "end",
+    "pageup",
+    "pagedown",
+    "up",
+    "down",
+    "left",
+    "right",
+    "arrowup",
+    "arrowdown",
+    "arrowleft",
+    "arrowright",
+];
+
+/// Keyboard accelerators for the menu bar after applying user keybindings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuAccelerators {
+    by_id: HashMap<&'static str, String>,
+}
+
+impl Default for MenuAccelerators {
+    fn default() -> Self {
+        Self::from_keybindings(&BTreeMap::new())
+    }
+}
+
+impl MenuAccelerators {
+    /// Resolve accelerators from the `keybindings` setting (menu ID → accelerator).
+    ///
+    /// An empty string removes the item's shortcut. Unknown menu IDs and
+    /// accelerators that don't parse are logged and the default is kept.
+    pub fn from_keybindings(keybindings: &BTreeMap<String, String>) -> Self {
+        let mut by_id: HashMap<&'static str, String> = DEFAULT_ACCELERATORS
+            .iter()
+            .map(|(id, accel)| (*id, accel.to_string()))
+            .collect();
+
+        for (menu_id, accelerator) in keybindings {
+            let Some(id) = CUSTOMIZABLE_MENU_IDS.iter().find(|id| **id == menu_id) else {
+                log::warn!("[menu] Ignoring keybinding for unknown menu item {menu_id:?}");
+                continue;
+            };
+            let accelerator = accelerator.trim();
+            if accelerator.is_empty() {
+                by_id.remove(id);
+            } else if is_valid_accelerator(accelerator) {
+                by_id.insert(id, accelerator.to_string());
+            } else {
+                log::warn!(
+                    "[menu] Invalid accelerator {accelerator:?} for {menu_id:?}, keeping default"
+                );
+            }
+        }
+
+        Self { by_id }
+    }
+
+    /// The accelerator for a menu item, if it has one.
+    pub fn get(&self, menu_id: &str) -> Option<&str> {
+        self.by_id.get(menu_id).map(String::as_str)
+    }
+}
+
+/// Check an accelerator string such as `CmdOrCtrl+Shift+S`.
+///
+/// Tauri drops accelerators it can't parse without reporting anything, so
+/// overrides are validated up front to fall back to the default instead.
+fn is_valid_accelerator(accelerator: &str) -> bool {
+    let mut parts: Vec<&str> = accelerator.split('+').map(str::trim).collect();
+    let Some(key) = parts.pop() else {
+        return false;
+    };
+    let modifiers_ok = parts
+        .iter()
+        .all(|m| ACCELERATOR_MODIFIERS.contains(&m.to_ascii_lowercase().as_str()));
+    modifiers_ok && is_valid_accelerator_key(key)
+}
+
+fn is_valid_accelerator_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return c.is_ascii_alphanumeric() || ",.-=/\\;'[]`".contains(c);
+    }
+    let lower = key.to_ascii_lowercase();
+    if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+        return (1..=24).contains(&n);
+    }
+    ACCELERATOR_NAMED_KEYS.contains(&lower.as_str())
+}
+
+/// Most entries shown under File > Open > Open Recent.
+const MAX_RECENT_NOTEBOOKS: usize = 10;
+
+/// A notebook listed under File > Open > Open Recent.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct RecentNotebook {
+    pub path: PathBuf,
+    /// Path shortened for display (`~/...` under the home directory).
+    pub label: String,
+}
+
+/// Build the Open Recent entries from notebook paths, most recent first.
+///
+/// Paths that no longer exist on disk are pruned, duplicates are dropped, and
+/// the list is capped at `MAX_RECENT_NOTEBOOKS`.
+pub fn build_recent_list(
+    paths: impl IntoIterator<Item = PathBuf>,
+    home: Option<&Path>,
+) -> Vec<RecentNotebook> {
+    let mut recent: Vec<RecentNotebook> = Vec::new();
+    for path in paths {
+        if recent.len() == MAX_RECENT_NOTEBOOKS {
+            break;
+        }
+        if !path.is_file() || recent.iter().any(|r| r.path == path) {
+            continue;
+        }
+        let label = shorten_path(&path, home);
+        recent.push(RecentNotebook { path, label });
+    }
+    recent
+}
+
+fn shorten_path(path: &Path, home: Option<&Path>) -> String {
+    match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// An installed Jupyter kernelspec listed under File > New Notebook As...
+#[derive(Debug, Clone, PartialEq)]
+pub struct KernelspecMenuEntry {
+    pub name: String,
+    pub display_name: String,
+}
+
+/// Build the New Notebook As... kernelspec entries from `(name, display_name,
+/// language)` triples, sorted by display name.
+///
+/// Python and Deno kernelspecs are left out since they already have
+/// dedicated entries backed by the managed environments.
+pub fn build_kernelspec_list(
+    specs: impl IntoIterator<Item = (String, String, String)>,
+) -> Vec<KernelspecMenuEntry> {
+    // A name found in several Jupyter data dirs resolves to the first one
+    let mut seen = std::collections::HashSet::new();
+    let mut entries: Vec<KernelspecMenuEntry> = specs
+        .into_iter()
+        .filter(|(name, _, language)| !is_builtin_runtime_kernelspec(name, language))
+        .filter(|(name, _, _)| seen.insert(name.clone()))
+        .map(|(name, display_name, _)| KernelspecMenuEntry { name, display_name })
+        .collect();
+    entries.sort_by_key(|entry| entry.display_name.to_lowercase());
+    entries
+}
+
+fn is_builtin_runtime_kernelspec(name: &str, language: &str) -> bool {
+    let name = name.to_lowercase();
+    let language = language.to_lowercase();
+    name.contains("python")
+        || name.contains("deno")
+        || matches!(language.as_str(), "python" | "typescript" | "javascript")
+}
+
+pub fn kernelspec_menu_item_id(name: &str) -> String {
+    format!("{MENU_NEW_KERNELSPEC_PREFIX}{name}")
+}
+
+pub fn kernelspec_for_menu_item_id(menu_id: &str) -> Option<&str> {
+    menu_id.strip_prefix(MENU_NEW_KERNELSPEC_PREFIX)
+}
+
+pub fn recent_menu_item_id(path: &Path) -> String {
+    format!("{MENU_OPEN_RECENT_PREFIX}{}", path.display())
+}
+
+pub fn recent_path_for_menu_item_id(menu_id: &str) -> Option<PathBuf> {
+    menu_id
+        .strip_prefix(MENU_OPEN_RECENT_PREFIX)
+        .map(PathBuf::from)
+}
+
 /// Build the application menu bar
-pub fn create_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+pub fn create_menu(
+    app: &AppHandle,
+    recent: &[RecentNotebook],
+    kernelspecs: &[KernelspecMenuEntry],
+    accelerators: &MenuAccelerators,
+) -> tauri::Result<Menu<Wry>> {
     let menu = Menu::new(app)?;
 
     // App menu (macOS standard - shows app name)
@@ -74,7 +328,7 @@ pub fn create_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
         MENU_INSTALL_CLI,
         "Install 'runt' Command in PATH...",
         true,
-        None::<&str>,
+        accelerators.get(MENU_INSTALL_CLI),
     )?)?;
     app_menu.append(&PredefinedMenuItem::separator(app)?)?;
     app_menu.append(&PredefinedMenuItem::services(app, None)?)?;
@@ -95,7 +349,7 @@ pub fn create_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
         MENU_NEW_NOTEBOOK,
         "New Notebook",
         true,
-        Some("CmdOrCtrl+N"),
+        accelerators.get(MENU_NEW_NOTEBOOK),
     )?)?;
 
     // Explicit runtime overrides in a submenu
@@ -105,15 +359,27 @@ pub fn create_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
         MENU_NEW_PYTHON_NOTEBOOK,
         "Python",
         true,
-        None::<&str>,
+        accelerators.get(MENU_NEW_PYTHON_NOTEBOOK),
     )?)?;
     new_notebook_submenu.append(&MenuItem::with_id(
         app,
         MENU_NEW_DENO_NOTEBOOK,
         "Deno (TypeScript)",
         true,
-        None::<&str>,
+        accelerators.get(MENU_NEW_DENO_NOTEBOOK),
     )?)?;
+    if !kernelspecs.is_empty() {
+        new_notebook_submenu.append(&PredefinedMenuItem::separator(app)?)?;
+    }
+    for spec in kernelspecs {
+        new_notebook_submenu.append(&MenuItem::with_id(
+            app,
+            kernelspec_menu_item_id(&spec.name),
+            &spec.display_name,
+            true,
+            None::<&str>,
+        )?)?;
+    }
     file_menu.append(&new_notebook_submenu)?;
 
     let open_submenu = Submenu::new(app, "Open", true)?;
@@ -122,10 +388,30 @@ pub fn create_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
         MENU_OPEN,
         "Open...",
         true,
-        Some("CmdOrCtrl+O"),
+        accelerators.get(MENU_OPEN),
     )?)?;
     open_submenu.append(&PredefinedMenuItem::separator(app)?)?;
 
+    let recent_submenu = Submenu::new(app, "Open Recent", true)?;
+    if recent.is_empty() {
+        recent_submenu.append(&MenuItem::new(
+            app,
+            "No Recent Notebooks",
+            false,
+            None::<&str>,
+        )?)?;
+    }
+    for notebook in recent {
+        recent_submenu.append(&MenuItem::with_id(
+            app,
+            recent_menu_item_id(&notebook.path),
+            &notebook.label,
+            true,
+            None::<&str>,
+        )?)?;
+    }
+    open_submenu.append(&recent_submenu)?;
+
     let sample_submenu = Submenu::new(app, "Sample Notebooks", true)?;
     for sample in BUNDLED_SAMPLE_NOTEBOOKS {
         sample_submenu.append(&MenuItem::with_id(
@@ -144,14 +430,21 @@ pub fn create_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
         MENU_SAVE,
         "Save",
         true,
-        Some("CmdOrCtrl+S"),
+        accelerators.get(MENU_SAVE),
     )?)?;
     file_menu.append(&MenuItem::with_id(
         app,
         MENU_CLONE_NOTEBOOK,
         "Clone Notebook...",
         true,
-        None::<&str>,
+        accelerators.get(MENU_CLONE_NOTEBOOK),
+    )?)?;
+    file_menu.append(&MenuItem::with_id(
+        app,
+        MENU_SAVE_COPY_CLEAN,
+        "Save a Copy Without Outputs...",
+        true,
+        accelerators.get(MENU_SAVE_COPY_CLEAN),
     )?)?;
     menu.append(&file_menu)?;
 
@@ -173,14 +466,43 @@ pub fn create_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
         MENU_RUN_ALL_CELLS,
         "Run All Cells",
         true,
-        None::<&str>,
+        accelerators.get(MENU_RUN_ALL_CELLS),
     )?)?;
     kernel_menu.append(&MenuItem::with_id(
         app,
         MENU_RESTART_AND_RUN_ALL,
         "Restart & Run All Cells",
         true,
-        None::<&str>,
+        accelerators.get(MENU_RESTART_AND_RUN_ALL),
+    )?)?;
+    kernel_menu.append(&MenuItem::with_id(
+        app,
+        MENU_RESTART_AND_RESTORE,
+        "Restart & Restore Variables",
+        true,
+        accelerators.get(MENU_RESTART_AND_RESTORE),
+    )?)?;
+    kernel_menu.append(&MenuItem::with_id(
+        app,
+        MENU_CLEAR_ALL_OUTPUTS,
+        "Clear All Outputs",
+        true,
+        accelerators.get(MENU_CLEAR_ALL_OUTPUTS),
+    )?)?;
+    kernel_menu.append(&PredefinedMenuItem::separator(app)?)?;
+    kernel_menu.append(&MenuItem::with_id(
+        app,
+        MENU_SHUTDOWN_ALL_KERNELS,
+        "Shut Down All Kernels...",
+        true,
+        accelerators.get(MENU_SHUTDOWN_ALL_KERNELS),
+    )?)?;
+    kernel_menu.append(&MenuItem::with_id(
+        app,
+        MENU_TOGGLE_AUTO_LAUNCH,
+        "Toggle Auto-Launch for This Notebook",
+        true,
+        accelerators.get(MENU_TOGGLE_AUTO_LAUNCH),
     )?)?;
     menu.append(&kernel_menu)?;
 
@@ -191,21 +513,21 @@ pub fn create_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
         MENU_ZOOM_IN,
         "Zoom In",
         true,
-        Some("CmdOrCtrl+="),
+        accelerators.get(MENU_ZOOM_IN),
     )?)?;
     view_menu.append(&MenuItem::with_id(
         app,
         MENU_ZOOM_OUT,
         "Zoom Out",
         true,
-        Some("CmdOrCtrl+-"),
+        accelerators.get(MENU_ZOOM_OUT),
     )?)?;
     view_menu.append(&MenuItem::with_id(
         app,
         MENU_ZOOM_RESET,
         "Actual Size",
         true,
-        Some("CmdOrCtrl+0"),
+        accelerators.get(MENU_ZOOM_RESET),
     )?)?;
     menu.append(&view_menu)?;
 
@@ -220,8 +542,16 @@ pub fn create_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
 
 #[cfg(test)]
 mod tests {
-    use super::{sample_for_menu_item_id, sample_menu_item_id, BUNDLED_SAMPLE_NOTEBOOKS};
-    use std::collections::HashSet;
+    use super::{
+        build_kernelspec_list, build_recent_list, is_valid_accelerator,
+        kernelspec_for_menu_item_id, kernelspec_menu_item_id, recent_menu_item_id,
+        recent_path_for_menu_item_id, sample_for_menu_item_id, sample_menu_item_id,
+        MenuAccelerators, BUNDLED_SAMPLE_NOTEBOOKS, CUSTOMIZABLE_MENU_IDS, DEFAULT_ACCELERATORS,
+        MAX_RECENT_NOTEBOOKS, MENU_OPEN, MENU_RUN_ALL_CELLS, MENU_SAVE, MENU_ZOOM_IN,
+    };
+    use std::collections::{BTreeMap, HashSet};
+    use std::path::PathBuf;
+    use tempfile::TempDir;
 
     #[test]
     fn bundled_sample_ids_are_unique() {
@@ -260,4 +590,155 @@ mod tests {
                 .unwrap_or_else(|e| panic!("{} should parse: {}", sample.file_name, e));
         }
     }
+
+    #[test]
+    fn recent_list_prunes_missing_files() {
+        let home = TempDir::new().unwrap();
+        let kept = home.path().join("work").join("analysis.ipynb");
+        std::fs::create_dir_all(kept.parent().unwrap()).unwrap();
+        std::fs::write(&kept, "{}").unwrap();
+        let deleted = home.path().join("deleted.ipynb");
+
+        let recent =
+            build_recent_list(vec![deleted, kept.clone(), kept.clone()], Some(home.path()));
+
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].path, kept);
+        assert_eq!(
+            recent[0].label,
+            format!(
+                "~/{}",
+                PathBuf::from("work").join("analysis.ipynb").display()
+            )
+        );
+    }
+
+    #[test]
+    fn recent_list_is_capped_and_keeps_order() {
+        let dir = TempDir::new().unwrap();
+        let paths: Vec<PathBuf> = (0..MAX_RECENT_NOTEBOOKS + 3)
+            .map(|i| {
+                let path = dir.path().join(format!("nb{i}.ipynb"));
+                std::fs::write(&path, "{}").unwrap();
+                path
+            })
+            .collect();
+
+        let recent = build_recent_list(paths.clone(), None);
+
+        assert_eq!(recent.len(), MAX_RECENT_NOTEBOOKS);
+        assert_eq!(recent[0].path, paths[0]);
+        assert_eq!(recent[0].label, paths[0].display().to_string());
+    }
+
+    #[test]
+    fn recent_menu_ids_round_trip() {
+        let path = PathBuf::from("/work/my notebook.ipynb");
+        let menu_id = recent_menu_item_id(&path);
+        assert_eq!(recent_path_for_menu_item_id(&menu_id), Some(path));
+        assert_eq!(recent_path_for_menu_item_id("open_sample:x"), None);
+    }
+
+    #[test]
+    fn kernelspec_menu_ids_round_trip() {
+        let menu_id = kernelspec_menu_item_id("julia-1.10");
+        assert_eq!(kernelspec_for_menu_item_id(&menu_id), Some("julia-1.10"));
+        assert_eq!(kernelspec_for_menu_item_id("open_recent:/x.ipynb"), None);
+    }
+
+    #[test]
+    fn kernelspec_list_skips_builtin_runtimes_and_sorts() {
+        let spec = |name: &str, display: &str, language: &str| {
+            (name.to_string(), display.to_string(), language.to_string())
+        };
+        let entries = build_kernelspec_list([
+            spec("python3", "Python 3", "python"),
+            spec("julia-1.10", "Julia 1.10", "julia"),
+            spec("deno", "Deno", "typescript"),
+            spec("conda-env-ml-py", "Python [conda env:ml]", "python"),
+            spec("ir", "R", "R"),
+            spec("ir", "R (system)", "R"),
+        ]);
+
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["julia-1.10", "ir"]);
+        assert_eq!(entries[1].display_name, "R");
+    }
+
+    fn keybindings(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(id, accel)| (id.to_string(), accel.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn default_accelerators_are_valid_and_customizable() {
+        for (id, accel) in DEFAULT_ACCELERATORS {
+            assert!(is_valid_accelerator(accel), "invalid default for {id}");
+            assert!(CUSTOMIZABLE_MENU_IDS.contains(id));
+        }
+        assert_eq!(
+            MenuAccelerators::default().get(MENU_SAVE),
+            Some("CmdOrCtrl+S")
+        );
+    }
+
+    #[test]
+    fn keybindings_override_defaults() {
+        let accelerators = MenuAccelerators::from_keybindings(&keybindings(&[
+            (MENU_SAVE, "CmdOrCtrl+Shift+S"),
+            (MENU_RUN_ALL_CELLS, "CmdOrCtrl+Alt+F5"),
+            (MENU_OPEN, ""),
+        ]));
+
+        assert_eq!(accelerators.get(MENU_SAVE), Some("CmdOrCtrl+Shift+S"));
+        assert_eq!(
+            accelerators.get(MENU_RUN_ALL_CELLS),
+            Some("CmdOrCtrl+Alt+F5")
+        );
+        assert_eq!(accelerators.get(MENU_OPEN), None);
+        assert_eq!(accelerators.get(MENU_ZOOM_IN), Some("CmdOrCtrl+="));
+    }
+
+    #[test]
+    fn invalid_keybindings_keep_defaults() {
+        let accelerators = MenuAccelerators::from_keybindings(&keybindings(&[
+            (MENU_SAVE, "Hyper+S"),
+            (MENU_ZOOM_IN, "CmdOrCtrl+"),
+            (MENU_RUN_ALL_CELLS, "CmdOrCtrl+F25"),
+            ("not_a_menu_item", "CmdOrCtrl+K"),
+        ]));
+
+        assert_eq!(accelerators, MenuAccelerators::default());
+        assert_eq!(accelerators.get(MENU_RUN_ALL_CELLS), None);
+        assert_eq!(accelerators.get("not_a_menu_item"), None);
+    }
+
+    #[test]
+    fn accelerator_validation() {
+        for valid in [
+            "CmdOrCtrl+N",
+            "cmdorctrl+shift+p",
+            "Alt+Enter",
+            "F5",
+            "Ctrl+Plus",
+            "Shift+/",
+        ] {
+            assert!(is_valid_accelerator(valid), "{valid} should be valid");
+        }
+        for invalid in [
+            "",
+            "CmdOrCtrl+",
+            "Ctrl+Shift",
+            "Ctrl+Foo",
+            "Ctrl++",
+            "Ctrl+F0",
+        ] {
+            assert!(
+                !is_valid_accelerator(invalid),
+                "{invalid} should be invalid"
+            );
+        }
+    }
 }
@@ -0,0 +1,297 @@
+//! Bringing older notebook metadata up to the current `runt` layout.
+//!
+//! Notebooks written by earlier versions keep their environment config at the
+//! top level (`metadata.uv`, `metadata.conda`, `metadata.deno`), lack an
+//! `env_id`, or carry sections in shapes the readers no longer expect.
+//! [`migrate_notebook_metadata`] runs once when a notebook is loaded and fixes
+//! all of that in one place, so the kernel start paths can read
+//! `metadata.runt` as-is. It reports each [`MetadataChange`] it made.
+
+use log::info;
+use nbformat::v4::Metadata;
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+/// The `runt.metadata_version` this module migrates notebooks to.
+pub const CURRENT_METADATA_VERSION: u64 = 1;
+
+/// Environment sections that older notebooks kept at the top level.
+const LEGACY_SECTIONS: [&str; 3] = ["uv", "conda", "deno"];
+
+/// One change made by [`migrate_notebook_metadata`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetadataChange {
+    /// `metadata.<section>` moved to `metadata.runt.<section>`
+    MovedToRunt { section: &'static str },
+    /// `metadata.<section>` dropped because `metadata.runt.<section>` exists
+    DroppedLegacySection { section: &'static str },
+    /// `runt.schema_version` was missing
+    AddedSchemaVersion,
+    /// `runt.env_id` was missing or empty
+    AddedEnvId { env_id: String },
+    /// `runt.<section>` rewritten into the shape the readers expect
+    NormalizedSection { section: &'static str },
+    /// An empty `runt.<section>` removed because the other environment
+    /// section lists packages
+    RemovedEmptySection { section: &'static str },
+    /// `runt.metadata_version` raised to [`CURRENT_METADATA_VERSION`]
+    BumpedMetadataVersion { from: Option<u64> },
+}
+
+impl std::fmt::Display for MetadataChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MovedToRunt { section } => {
+                write!(f, "moved metadata.{section} to metadata.runt.{section}")
+            }
+            Self::DroppedLegacySection { section } => write!(
+                f,
+                "dropped metadata.{section} in favor of metadata.runt.{section}"
+            ),
+            Self::AddedSchemaVersion => write!(f, "added runt.schema_version"),
+            Self::AddedEnvId { env_id } => write!(f, "added runt.env_id {env_id}"),
+            Self::NormalizedSection { section } => write!(f, "normalized runt.{section}"),
+            Self::RemovedEmptySection { section } => {
+                write!(f, "removed empty runt.{section}")
+            }
+            Self::BumpedMetadataVersion { from } => match from {
+                Some(from) => write!(
+                    f,
+                    "bumped runt.metadata_version from {from} to {CURRENT_METADATA_VERSION}"
+                ),
+                None => write!(f, "set runt.metadata_version to {CURRENT_METADATA_VERSION}"),
+            },
+        }
+    }
+}
+
+/// Migrate `metadata` to the current `runt` layout, returning what changed
+/// (empty if it was already current).
+///
+/// Moves legacy top-level environment sections under `runt`, ensures
+/// `schema_version` and `env_id`, normalizes the `uv` and `conda` sections,
+/// drops an empty section that conflicts with one listing packages, and sets
+/// `runt.metadata_version`. A `runt` value that isn't an object is left alone.
+pub fn migrate_notebook_metadata(metadata: &mut Metadata) -> Vec<MetadataChange> {
+    let additional = &mut metadata.additional;
+    let mut changes = Vec::new();
+
+    let runt = additional
+        .entry("runt".to_string())
+        .or_insert_with(|| Value::Object(Map::new()));
+    if !runt.is_object() {
+        return changes;
+    }
+
+    for section in LEGACY_SECTIONS {
+        let Some(legacy) = additional.remove(section) else {
+            continue;
+        };
+        let Some(runt) = additional.get_mut("runt").and_then(Value::as_object_mut) else {
+            continue;
+        };
+        if runt.contains_key(section) {
+            changes.push(MetadataChange::DroppedLegacySection { section });
+        } else {
+            runt.insert(section.to_string(), legacy);
+            changes.push(MetadataChange::MovedToRunt { section });
+        }
+    }
+
+    let Some(runt) = additional.get_mut("runt").and_then(Value::as_object_mut) else {
+        return changes;
+    };
+
+    if !runt.contains_key("schema_version") {
+        runt.insert("schema_version".to_string(), Value::from("1"));
+        changes.push(MetadataChange::AddedSchemaVersion);
+    }
+
+    let has_env_id = runt
+        .get("env_id")
+        .and_then(Value::as_str)
+        .is_some_and(|id| !id.is_empty());
+    if !has_env_id {
+        let env_id = Uuid::new_v4().to_string();
+        runt.insert("env_id".to_string(), Value::from(env_id.clone()));
+        changes.push(MetadataChange::AddedEnvId { env_id });
+    }
+
+    if let Some(uv) = runt.get_mut("uv").and_then(Value::as_object_mut) {
+        if normalize_uv(uv) {
+            changes.push(MetadataChange::NormalizedSection { section: "uv" });
+        }
+    }
+    if let Some(conda) = runt.get_mut("conda").and_then(Value::as_object_mut) {
+        if normalize_conda(conda) {
+            changes.push(MetadataChange::NormalizedSection { section: "conda" });
+        }
+    }
+
+    let uv_empty = runt
+        .get("uv")
+        .map(|uv| !lists_packages(uv, &["dependencies"]));
+    let conda_empty = runt
+        .get("conda")
+        .map(|conda| !lists_packages(conda, &["dependencies", "pip_dependencies"]));
+    let empty_section = match (uv_empty, conda_empty) {
+        (Some(true), Some(false)) => Some("uv"),
+        (Some(false), Some(true)) => Some("conda"),
+        _ => None,
+    };
+    if let Some(section) = empty_section {
+        runt.remove(section);
+        changes.push(MetadataChange::RemovedEmptySection { section });
+    }
+
+    let version = runt.get("metadata_version").and_then(Value::as_u64);
+    if version.is_none_or(|version| version < CURRENT_METADATA_VERSION) {
+        runt.insert(
+            "metadata_version".to_string(),
+            Value::from(CURRENT_METADATA_VERSION),
+        );
+        changes.push(MetadataChange::BumpedMetadataVersion { from: version });
+    }
+
+    for change in &changes {
+        info!("[metadata-migration] {}", change);
+    }
+    changes
+}
+
+/// Give `key` an empty list if it's missing or null. Returns whether it did.
+fn ensure_list(section: &mut Map<String, Value>, key: &str) -> bool {
+    match section.get(key) {
+        None | Some(Value::Null) => {
+            section.insert(key.to_string(), Value::Array(Vec::new()));
+            true
+        }
+        Some(_) => false,
+    }
+}
+
+/// `dependencies` as a list, and `requires-python` spelled the way
+/// `uv` (and the readers) expect.
+fn normalize_uv(uv: &mut Map<String, Value>) -> bool {
+    let mut changed = ensure_list(uv, "dependencies");
+    if let Some(requires_python) = uv.remove("requires_python") {
+        if !uv.contains_key("requires-python") {
+            uv.insert("requires-python".to_string(), requires_python);
+        }
+        changed = true;
+    }
+    changed
+}
+
+/// `dependencies` and `channels` as lists.
+fn normalize_conda(conda: &mut Map<String, Value>) -> bool {
+    let dependencies = ensure_list(conda, "dependencies");
+    let channels = ensure_list(conda, "channels");
+    dependencies || channels
+}
+
+/// Whether any of the `keys` lists of `section` is non-empty.
+fn lists_packages(section: &Value, keys: &[&str]) -> bool {
+    keys.iter().any(|key| {
+        section
+            .get(key)
+            .and_then(Value::as_array)
+            .is_some_and(|packages| !packages.is_empty())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metadata(value: Value) -> Metadata {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn runt(metadata: &Metadata) -> &Value {
+        &metadata.additional["runt"]
+    }
+
+    #[test]
+    fn test_legacy_notebook_gains_env_id_and_normalized_sections() {
+        let mut metadata = metadata(json!({
+            "kernelspec": {"name": "python3", "display_name": "Python 3", "language": "python"},
+            "uv": {"dependencies": ["pandas"], "requires_python": ">=3.10"},
+            "conda": {"dependencies": null},
+        }));
+
+        let changes = migrate_notebook_metadata(&mut metadata);
+
+        let runt = runt(&metadata);
+        let env_id = runt["env_id"].as_str().unwrap().to_string();
+        assert_eq!(
+            changes,
+            vec![
+                MetadataChange::MovedToRunt { section: "uv" },
+                MetadataChange::MovedToRunt { section: "conda" },
+                MetadataChange::AddedSchemaVersion,
+                MetadataChange::AddedEnvId { env_id },
+                MetadataChange::NormalizedSection { section: "uv" },
+                MetadataChange::NormalizedSection { section: "conda" },
+                MetadataChange::RemovedEmptySection { section: "conda" },
+                MetadataChange::BumpedMetadataVersion { from: None },
+            ]
+        );
+        assert_eq!(
+            runt["uv"],
+            json!({"dependencies": ["pandas"], "requires-python": ">=3.10"})
+        );
+        assert!(runt.get("conda").is_none());
+        assert_eq!(runt["schema_version"], json!("1"));
+        assert_eq!(runt["metadata_version"], json!(CURRENT_METADATA_VERSION));
+        assert!(!metadata.additional.contains_key("uv"));
+        assert!(!metadata.additional.contains_key("conda"));
+        assert!(metadata.kernelspec.is_some());
+    }
+
+    #[test]
+    fn test_current_metadata_is_unchanged() {
+        let value = json!({
+            "runt": {
+                "schema_version": "1",
+                "env_id": "existing-env",
+                "metadata_version": CURRENT_METADATA_VERSION,
+                "uv": {"dependencies": ["numpy"]},
+                "conda": {"dependencies": ["scipy"], "channels": ["conda-forge"]},
+                "trust_signature": "hmac-sha256:abc",
+            }
+        });
+        let mut metadata = metadata(value.clone());
+
+        assert_eq!(migrate_notebook_metadata(&mut metadata), vec![]);
+        assert_eq!(runt(&metadata), &value["runt"]);
+    }
+
+    #[test]
+    fn test_runt_section_wins_over_legacy_duplicate() {
+        let mut metadata = metadata(json!({
+            "deno": {"permissions": ["--allow-read"]},
+            "runt": {
+                "schema_version": "1",
+                "env_id": "existing-env",
+                "deno": {"permissions": ["--allow-net"]},
+            }
+        }));
+
+        let changes = migrate_notebook_metadata(&mut metadata);
+
+        assert_eq!(
+            changes,
+            vec![
+                MetadataChange::DroppedLegacySection { section: "deno" },
+                MetadataChange::BumpedMetadataVersion { from: None },
+            ]
+        );
+        assert_eq!(
+            runt(&metadata)["deno"],
+            json!({"permissions": ["--allow-net"]})
+        );
+        assert!(!metadata.additional.contains_key("deno"));
+    }
+}
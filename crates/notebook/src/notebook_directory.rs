@@ -0,0 +1,209 @@
+//! Finding the notebooks in a directory opened with the app.
+//!
+//! When the app is launched with a directory instead of a notebook file, the
+//! window starts out as a picker: [`scan_notebooks`] walks the directory for
+//! `.ipynb` files and the frontend lists them for the user to open one.
+//! The walk honours `.gitignore` files at every level, including those in
+//! parent directories, so virtualenvs, build output and other ignored trees
+//! don't flood the list.
+
+use ignore::WalkBuilder;
+use serde::Serialize;
+use std::path::Path;
+
+/// Stop scanning after this many notebooks; the picker isn't meant for more.
+pub const MAX_NOTEBOOKS: usize = 1000;
+
+/// Directories never worth descending into, ignored or not.
+const SKIPPED_DIRS: [&str; 2] = [".git", ".ipynb_checkpoints"];
+
+/// A notebook found by [`scan_notebooks`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NotebookEntry {
+    /// Absolute path to the notebook.
+    pub path: String,
+    /// Path relative to the scanned directory, `/`-separated.
+    pub relative_path: String,
+    /// File name of the notebook.
+    pub name: String,
+}
+
+/// The notebooks in a directory, sorted by relative path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DirectoryListing {
+    /// The scanned directory.
+    pub directory: String,
+    pub notebooks: Vec<NotebookEntry>,
+    /// Whether the scan stopped at [`MAX_NOTEBOOKS`].
+    pub truncated: bool,
+}
+
+/// Find the `.ipynb` files under `dir`, skipping anything excluded by the
+/// `.gitignore` files in it or in its parent directories.
+///
+/// Ignored directories aren't descended into, so a negated rule can't bring
+/// back a notebook inside one (git behaves the same way). Symlinked
+/// directories are skipped to avoid cycles.
+pub fn scan_notebooks(dir: &Path) -> DirectoryListing {
+    let walker = WalkBuilder::new(dir)
+        .hidden(false)
+        // Honour .gitignore files in directories that aren't git checkouts
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(|entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            !(is_dir && entry.depth() > 0 && SKIPPED_DIRS.iter().any(|d| entry.file_name() == *d))
+        })
+        .build();
+
+    let mut notebooks = Vec::new();
+    let mut truncated = false;
+    for entry in walker.filter_map(Result::ok) {
+        let path = entry.path();
+        if entry.file_type().is_some_and(|t| t.is_dir())
+            || path.extension().and_then(|ext| ext.to_str()) != Some("ipynb")
+            // Follows symlinks, so a linked notebook still counts
+            || !path.is_file()
+        {
+            continue;
+        }
+        let (Some(name), Ok(relative)) = (entry.file_name().to_str(), path.strip_prefix(dir))
+        else {
+            continue;
+        };
+        if notebooks.len() >= MAX_NOTEBOOKS {
+            truncated = true;
+            break;
+        }
+        let relative_path = relative
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        notebooks.push(NotebookEntry {
+            path: path.to_string_lossy().into_owned(),
+            relative_path,
+            name: name.to_string(),
+        });
+    }
+    notebooks.sort_by(|a: &NotebookEntry, b| a.relative_path.cmp(&b.relative_path));
+
+    DirectoryListing {
+        directory: dir.to_string_lossy().into_owned(),
+        notebooks,
+        truncated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn touch(root: &Path, relative: &str) {
+        let path = root.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "").unwrap();
+    }
+
+    fn relative_paths(listing: &DirectoryListing) -> Vec<&str> {
+        listing
+            .notebooks
+            .iter()
+            .map(|entry| entry.relative_path.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_scan_applies_ignore_rules() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        for file in [
+            "analysis.ipynb",
+            "README.md",
+            "scratch.ipynb",
+            "keep-scratch.ipynb",
+            "build/report.ipynb",
+            ".venv/lib/site-packages/demo.ipynb",
+            "docs/intro.ipynb",
+            "docs/draft.ipynb",
+            "docs/tmp/notes.ipynb",
+            "src/tmp/model.ipynb",
+            "src/.ipynb_checkpoints/model-checkpoint.ipynb",
+            ".git/objects/stray.ipynb",
+        ] {
+            touch(root, file);
+        }
+        std::fs::write(
+            root.join(".gitignore"),
+            "# generated\nbuild/\n.venv\n*scratch.ipynb\n!keep-scratch.ipynb\n/docs/tmp\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("docs/.gitignore"), "draft.ipynb\n").unwrap();
+
+        let listing = scan_notebooks(root);
+
+        assert_eq!(
+            relative_paths(&listing),
+            vec![
+                "analysis.ipynb",
+                "docs/intro.ipynb",
+                "keep-scratch.ipynb",
+                "src/tmp/model.ipynb",
+            ]
+        );
+        assert!(!listing.truncated);
+        let first = &listing.notebooks[0];
+        assert_eq!(first.name, "analysis.ipynb");
+        assert_eq!(
+            Path::new(&first.path),
+            root.join("analysis.ipynb").as_path()
+        );
+    }
+
+    #[test]
+    fn test_nested_gitignore_only_applies_below_it() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        for file in ["out.ipynb", "a/out.ipynb", "a/b/out.ipynb", "c/out.ipynb"] {
+            touch(root, file);
+        }
+        std::fs::write(root.join("a/.gitignore"), "out.ipynb\n").unwrap();
+        // A deeper file can re-include what a shallower one ignored
+        std::fs::write(root.join("a/b/.gitignore"), "!out.ipynb\n").unwrap();
+
+        let listing = scan_notebooks(root);
+
+        assert_eq!(
+            relative_paths(&listing),
+            vec!["a/b/out.ipynb", "c/out.ipynb", "out.ipynb"]
+        );
+    }
+
+    #[test]
+    fn test_parent_gitignore_applies() {
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().join("project");
+        for file in ["project/main.ipynb", "project/generated/out.ipynb"] {
+            touch(dir.path(), file);
+        }
+        std::fs::write(dir.path().join(".gitignore"), "generated/\n").unwrap();
+
+        let listing = scan_notebooks(&project);
+
+        assert_eq!(relative_paths(&listing), vec!["main.ipynb"]);
+    }
+
+    #[test]
+    fn test_scan_stops_at_limit() {
+        let dir = TempDir::new().unwrap();
+        for i in 0..=MAX_NOTEBOOKS {
+            touch(dir.path(), &format!("nb-{i:04}.ipynb"));
+        }
+
+        let listing = scan_notebooks(dir.path());
+
+        assert_eq!(listing.notebooks.len(), MAX_NOTEBOOKS);
+        assert!(listing.truncated);
+    }
+}
@@ -1,85 +1,11 @@
 use crate::runtime::Runtime;
-use crate::settings::{self, PythonEnvType};
-use log::info;
+use crate::settings::{self, PythonEnvType, TemplateCell, TemplateCellType};
 use nbformat::v4::{Cell, CellId, CellMetadata, Notebook, Output};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use uuid::Uuid;
 
-/// Migrate legacy metadata format to the new `runt` namespace structure.
-///
-/// Old format:
-///   metadata.uv.dependencies, metadata.conda.dependencies, metadata.deno
-///
-/// New format:
-///   metadata.runt.uv.dependencies, metadata.runt.conda.dependencies, metadata.runt.deno
-///
-/// Returns `true` if any migration was performed.
-pub fn migrate_legacy_metadata(additional: &mut HashMap<String, serde_json::Value>) -> bool {
-    let mut migrated = false;
-
-    // Extract legacy keys first (before borrowing runt mutably)
-    let legacy_uv = additional.remove("uv");
-    let legacy_conda = additional.remove("conda");
-    let legacy_deno = additional.remove("deno");
-
-    if legacy_uv.is_none() && legacy_conda.is_none() && legacy_deno.is_none() {
-        return false;
-    }
-
-    // Get or create runt namespace
-    let runt = additional
-        .entry("runt".to_string())
-        .or_insert_with(|| serde_json::json!({"schema_version": "1"}));
-
-    let runt_obj = match runt.as_object_mut() {
-        Some(obj) => obj,
-        None => {
-            // Put the keys back if we can't migrate
-            if let Some(uv) = legacy_uv {
-                additional.insert("uv".to_string(), uv);
-            }
-            if let Some(conda) = legacy_conda {
-                additional.insert("conda".to_string(), conda);
-            }
-            if let Some(deno) = legacy_deno {
-                additional.insert("deno".to_string(), deno);
-            }
-            return false;
-        }
-    };
-
-    // Migrate uv if present and not already in runt
-    if let Some(uv) = legacy_uv {
-        if !runt_obj.contains_key("uv") {
-            runt_obj.insert("uv".to_string(), uv);
-            migrated = true;
-            info!("[metadata-migration] Migrated metadata.uv -> metadata.runt.uv");
-        }
-    }
-
-    // Migrate conda if present and not already in runt
-    if let Some(conda) = legacy_conda {
-        if !runt_obj.contains_key("conda") {
-            runt_obj.insert("conda".to_string(), conda);
-            migrated = true;
-            info!("[metadata-migration] Migrated metadata.conda -> metadata.runt.conda");
-        }
-    }
-
-    // Migrate deno if present and not already in runt
-    if let Some(deno) = legacy_deno {
-        if !runt_obj.contains_key("deno") {
-            runt_obj.insert("deno".to_string(), deno);
-            migrated = true;
-            info!("[metadata-migration] Migrated metadata.deno -> metadata.runt.deno");
-        }
-    }
-
-    migrated
-}
-
 /// Flattened cell representation for the frontend.
 /// Converts nbformat's tagged enum into something JS-friendly.
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -154,6 +80,29 @@ pub struct NotebookState {
     pub dirty: bool,
 }
 
+/// `runt` metadata for a new Python notebook using `env_type`.
+///
+/// Conda notebooks get no `channels`: an empty list defers to the
+/// `conda.channels` setting when the environment is built.
+fn python_runt_metadata(env_type: &PythonEnvType, env_id: &str) -> serde_json::Value {
+    match env_type {
+        PythonEnvType::Uv | PythonEnvType::Other(_) => serde_json::json!({
+            "schema_version": "1",
+            "env_id": env_id,
+            "uv": {
+                "dependencies": Vec::<String>::new(),
+            }
+        }),
+        PythonEnvType::Conda => serde_json::json!({
+            "schema_version": "1",
+            "env_id": env_id,
+            "conda": {
+                "dependencies": Vec::<String>::new(),
+            }
+        }),
+    }
+}
+
 impl NotebookState {
     pub fn new_empty() -> Self {
         // Generate unique environment ID for this notebook
@@ -164,27 +113,7 @@ impl NotebookState {
         let mut additional = HashMap::new();
 
         // Build runt metadata with nested uv/conda based on user's preference
-        let runt_meta = match app_settings.default_python_env {
-            PythonEnvType::Uv | PythonEnvType::Other(_) => {
-                serde_json::json!({
-                    "schema_version": "1",
-                    "env_id": env_id,
-                    "uv": {
-                        "dependencies": Vec::<String>::new(),
-                    }
-                })
-            }
-            PythonEnvType::Conda => {
-                serde_json::json!({
-                    "schema_version": "1",
-                    "env_id": env_id,
-                    "conda": {
-                        "dependencies": Vec::<String>::new(),
-                        "channels": vec!["conda-forge"],
-                    }
-                })
-            }
-        };
+        let runt_meta = python_runt_metadata(&app_settings.default_python_env, &env_id);
 
         additional.insert("runt".to_string(), runt_meta);
 
@@ -223,33 +152,13 @@ impl NotebookState {
     pub fn new_empty_with_runtime(runtime: Runtime) -> Self {
         let env_id = Uuid::new_v4().to_string();
         let mut additional = HashMap::new();
+        let app_settings = settings::load_settings();
 
         // Build runt metadata with nested env config based on runtime
         let runt_meta = match &runtime {
             Runtime::Python => {
-                // Load user's preferred Python environment type from settings
-                let app_settings = settings::load_settings();
-                match app_settings.default_python_env {
-                    PythonEnvType::Uv | PythonEnvType::Other(_) => {
-                        serde_json::json!({
-                            "schema_version": "1",
-                            "env_id": env_id,
-                            "uv": {
-                                "dependencies": Vec::<String>::new(),
-                            }
-                        })
-                    }
-                    PythonEnvType::Conda => {
-                        serde_json::json!({
-                            "schema_version": "1",
-                            "env_id": env_id,
-                            "conda": {
-                                "dependencies": Vec::<String>::new(),
-                                "channels": vec!["conda-forge"],
-                            }
-                        })
-                    }
-                }
+                // Use the user's preferred Python environment type
+                python_runt_metadata(&app_settings.default_python_env, &env_id)
             }
             Runtime::Deno => {
                 // Deno setup with default permissions
@@ -309,13 +218,7 @@ impl NotebookState {
                 },
                 nbformat: 4,
                 nbformat_minor: 5,
-                cells: vec![Cell::Code {
-                    id: CellId::from(Uuid::new_v4()),
-                    metadata: empty_cell_metadata(),
-                    execution_count: None,
-                    source: Vec::new(),
-                    outputs: Vec::new(),
-                }],
+                cells: initial_cells(&app_settings.new_notebook_template, &runtime),
             },
             path: None,
             dirty: false,
@@ -332,11 +235,8 @@ impl NotebookState {
         let env_id = Uuid::new_v4().to_string();
         let mut additional = HashMap::new();
 
-        let channels = if config.channels.is_empty() {
-            vec!["conda-forge".to_string()]
-        } else {
-            config.channels.clone()
-        };
+        // An environment.yml without channels leaves them to the settings
+        let channels = config.channels.clone();
 
         additional.insert(
             "runt".to_string(),
@@ -484,10 +384,75 @@ impl NotebookState {
         Runtime::Python
     }
 
+    /// Whether the daemon should start a kernel when this notebook is opened.
+    /// On unless `metadata.runt.auto_launch` is explicitly `false`.
+    pub fn auto_launch_enabled(&self) -> bool {
+        self.notebook
+            .metadata
+            .additional
+            .get("runt")
+            .and_then(|runt| runt.get("auto_launch"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true)
+    }
+
+    /// Record the auto-launch preference in `metadata.runt.auto_launch`.
+    ///
+    /// Written explicitly in both directions so that re-enabling survives the
+    /// deep merge of the `runt` namespace in `merge_snapshot_into_nbformat`.
+    pub fn set_auto_launch(&mut self, enabled: bool) {
+        let runt = self
+            .notebook
+            .metadata
+            .additional
+            .entry("runt".to_string())
+            .or_insert_with(|| serde_json::json!({"schema_version": "1"}));
+        if let Some(runt_obj) = runt.as_object_mut() {
+            runt_obj.insert("auto_launch".to_string(), serde_json::json!(enabled));
+            self.dirty = true;
+        }
+    }
+
     pub fn cells_for_frontend(&self) -> Vec<FrontendCell> {
         self.notebook.cells.iter().map(cell_to_frontend).collect()
     }
 
+    /// Whether the outputs saved in this notebook render unsanitized. Live
+    /// kernel output is judged by the daemon instead.
+    pub fn renders_trusted_outputs(&self) -> bool {
+        let status = crate::trust::verify_notebook_trust(
+            &self.notebook.metadata.additional,
+            self.path.as_deref(),
+        )
+        .map(|info| info.status)
+        .unwrap_or(crate::trust::TrustStatus::Untrusted);
+        runtimed::output_sanitize::renders_trusted(&status, self.path.as_deref())
+    }
+
+    /// Sanitize the outputs of `cells` unless the notebook is trusted.
+    /// Outputs read from disk reach the frontend without passing through the
+    /// daemon's manifest builder, so they're cleaned here instead.
+    pub fn sanitize_frontend_outputs(
+        &self,
+        cells: &mut [FrontendCell],
+        settings: &settings::OutputSettings,
+    ) {
+        if self.renders_trusted_outputs() {
+            return;
+        }
+        for cell in cells {
+            if let FrontendCell::Code { outputs, .. } = cell {
+                for output in outputs {
+                    runtimed::output_sanitize::sanitize_output(
+                        output,
+                        settings.allow_html,
+                        settings.allow_javascript,
+                    );
+                }
+            }
+        }
+    }
+
     /// Get the ordered list of code cell IDs (skipping markdown and raw cells)
     pub fn get_code_cell_ids(&self) -> Vec<String> {
         self.notebook
@@ -575,6 +540,40 @@ impl NotebookState {
         Some(frontend_cell)
     }
 
+    /// Insert a copy of a cell right after it, with a fresh id. The copy
+    /// keeps the source and metadata; a code cell's outputs and execution
+    /// count are copied only when `include_outputs` is set. Returns the new
+    /// cell's index and the cell.
+    pub fn duplicate_cell(
+        &mut self,
+        cell_id: &str,
+        include_outputs: bool,
+    ) -> Option<(usize, FrontendCell)> {
+        let idx = self.find_cell_index(cell_id)?;
+        let mut cell = self.notebook.cells[idx].clone();
+        let new_id = CellId::from(Uuid::new_v4());
+        match &mut cell {
+            Cell::Code {
+                id,
+                outputs,
+                execution_count,
+                ..
+            } => {
+                *id = new_id;
+                if !include_outputs {
+                    outputs.clear();
+                    *execution_count = None;
+                }
+            }
+            Cell::Markdown { id, .. } | Cell::Raw { id, .. } => *id = new_id,
+        }
+
+        let frontend_cell = cell_to_frontend(&cell);
+        self.notebook.cells.insert(idx + 1, cell);
+        self.dirty = true;
+        Some((idx + 1, frontend_cell))
+    }
+
     pub fn delete_cell(&mut self, cell_id: &str) -> bool {
         // Don't delete the last cell
         if self.notebook.cells.len() <= 1 {
@@ -622,23 +621,53 @@ impl NotebookState {
         }
     }
 
+    /// Check the notebook against nbformat v4 (see [`crate::validation`]).
+    pub fn validate(&self) -> Vec<crate::validation::ValidationIssue> {
+        crate::validation::validate(&self.notebook)
+    }
+
     pub fn serialize(&self) -> Result<String, String> {
         let nb = nbformat::Notebook::V4(self.notebook.clone());
         nbformat::serialize_notebook(&nb).map_err(|e| e.to_string())
     }
+
+    /// Serialize a copy of the notebook with every code cell's outputs and
+    /// execution count cleared. Sources and metadata are kept, and the
+    /// in-memory notebook is left untouched.
+    pub fn serialize_without_outputs(&self) -> Result<String, String> {
+        let mut notebook = self.notebook.clone();
+        clear_all_outputs(&mut notebook.cells);
+        nbformat::serialize_notebook(&nbformat::Notebook::V4(notebook)).map_err(|e| e.to_string())
+    }
+}
+
+/// Clear outputs and execution counts from all code cells.
+pub fn clear_all_outputs(cells: &mut [Cell]) {
+    for cell in cells {
+        if let Cell::Code {
+            outputs,
+            execution_count,
+            ..
+        } = cell
+        {
+            outputs.clear();
+            *execution_count = None;
+        }
+    }
 }
 
 // ── Conversions between nbformat Metadata and NotebookMetadataSnapshot ──
 
 use runtimed::notebook_metadata::{
-    CondaInlineMetadata, DenoMetadata, KernelspecSnapshot, LanguageInfoSnapshot,
-    NotebookMetadataSnapshot, RuntMetadata, UvInlineMetadata,
+    DenoMetadata, KernelspecSnapshot, LanguageInfoSnapshot, NotebookMetadataSnapshot, RuntMetadata,
 };
 
 /// Extract a `NotebookMetadataSnapshot` from nbformat metadata.
 ///
 /// Reads `kernelspec`, `language_info`, and `runt` from the nbformat struct.
-/// Falls back to legacy `uv`/`conda` top-level keys if `runt` is absent.
+/// Legacy top-level `uv`/`conda` keys are moved into `runt` when a notebook is
+/// loaded (see [`crate::metadata_migration`]); a top-level `deno` key is still
+/// read, since new Deno notebooks keep their permissions there.
 pub fn snapshot_from_nbformat(metadata: &nbformat::v4::Metadata) -> NotebookMetadataSnapshot {
     let kernelspec = metadata.kernelspec.as_ref().map(|ks| KernelspecSnapshot {
         name: ks.name.clone(),
@@ -675,6 +704,7 @@ pub fn snapshot_from_nbformat(metadata: &nbformat::v4::Metadata) -> NotebookMeta
                 uv: None,
                 conda: None,
                 deno: None,
+                auto_launch: None,
             });
 
         // Also check legacy top-level "deno" key - this is where the Tauri commands write
@@ -691,15 +721,7 @@ pub fn snapshot_from_nbformat(metadata: &nbformat::v4::Metadata) -> NotebookMeta
 
         runt_meta
     } else {
-        // Fallback: legacy top-level uv/conda/deno keys
-        let uv = metadata
-            .additional
-            .get("uv")
-            .and_then(|v| serde_json::from_value::<UvInlineMetadata>(v.clone()).ok());
-        let conda = metadata
-            .additional
-            .get("conda")
-            .and_then(|v| serde_json::from_value::<CondaInlineMetadata>(v.clone()).ok());
+        // No runt namespace: only a top-level deno key can hold config
         let deno = metadata
             .additional
             .get("deno")
@@ -711,9 +733,10 @@ pub fn snapshot_from_nbformat(metadata: &nbformat::v4::Metadata) -> NotebookMeta
         RuntMetadata {
             schema_version: "1".to_string(),
             env_id: None,
-            uv,
-            conda,
+            uv: None,
+            conda: None,
             deno,
+            auto_launch: None,
         }
     };
 
@@ -784,6 +807,56 @@ pub fn merge_snapshot_into_nbformat(
     }
 }
 
+/// Starting cells for a new notebook: the runtime's template from settings,
+/// or a single empty code cell when it has none.
+fn initial_cells(templates: &BTreeMap<String, Vec<TemplateCell>>, runtime: &Runtime) -> Vec<Cell> {
+    let template = templates
+        .get(&runtime.to_string())
+        .filter(|cells| !cells.is_empty());
+    let Some(template) = template else {
+        return vec![Cell::Code {
+            id: CellId::from(Uuid::new_v4()),
+            metadata: empty_cell_metadata(),
+            execution_count: None,
+            source: Vec::new(),
+            outputs: Vec::new(),
+        }];
+    };
+
+    template
+        .iter()
+        .map(|cell| {
+            let id = CellId::from(Uuid::new_v4());
+            let metadata = empty_cell_metadata();
+            let source = cell
+                .source
+                .split_inclusive('\n')
+                .map(String::from)
+                .collect();
+            match cell.cell_type {
+                TemplateCellType::Markdown => Cell::Markdown {
+                    id,
+                    metadata,
+                    source,
+                    attachments: None,
+                },
+                TemplateCellType::Raw => Cell::Raw {
+                    id,
+                    metadata,
+                    source,
+                },
+                TemplateCellType::Code => Cell::Code {
+                    id,
+                    metadata,
+                    execution_count: None,
+                    source,
+                    outputs: Vec::new(),
+                },
+            }
+        })
+        .collect()
+}
+
 fn empty_cell_metadata() -> CellMetadata {
     CellMetadata {
         id: None,
@@ -829,6 +902,27 @@ mod tests {
         assert!(has_env);
     }
 
+    #[test]
+    fn test_new_conda_notebook_leaves_channels_to_settings() {
+        let runt = python_runt_metadata(&PythonEnvType::Conda, "env-1");
+        assert_eq!(runt["env_id"], "env-1");
+        assert!(runt["conda"]["dependencies"].is_array());
+        assert!(runt["conda"].get("channels").is_none());
+
+        // With no channels of its own, the notebook solves against the
+        // configured ones
+        let snapshot: runtimed::notebook_metadata::RuntMetadata =
+            serde_json::from_value(runt).unwrap();
+        let defaults = runtimed::settings_doc::CondaDefaults {
+            channels: vec!["bioconda".to_string(), "conda-forge".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            snapshot.conda.unwrap().resolved_channels(&defaults),
+            vec!["bioconda", "conda-forge"]
+        );
+    }
+
     #[test]
     fn test_new_empty_with_runtime_python() {
         let state = NotebookState::new_empty_with_runtime(Runtime::Python);
@@ -859,6 +953,53 @@ mod tests {
         assert_eq!(ks.name, "deno");
     }
 
+    #[test]
+    fn test_initial_cells_from_runtime_template() {
+        let templates = BTreeMap::from([(
+            "python".to_string(),
+            vec![
+                TemplateCell {
+                    cell_type: TemplateCellType::Markdown,
+                    source: "# Report".into(),
+                },
+                TemplateCell {
+                    cell_type: TemplateCellType::Code,
+                    source: "import numpy as np\nimport pandas as pd".into(),
+                },
+            ],
+        )]);
+
+        let cells = initial_cells(&templates, &Runtime::Python);
+        assert_eq!(cells.len(), 2);
+        assert!(matches!(
+            &cells[0],
+            Cell::Markdown { source, .. } if source.join("") == "# Report"
+        ));
+        match &cells[1] {
+            Cell::Code {
+                source, outputs, ..
+            } => {
+                assert_eq!(source, &vec!["import numpy as np\n", "import pandas as pd"]);
+                assert!(outputs.is_empty());
+            }
+            other => panic!("expected code cell, got {:?}", other),
+        }
+        assert_ne!(cells[0].id(), cells[1].id());
+
+        // Other runtimes keep the single empty code cell
+        let cells = initial_cells(&templates, &Runtime::Deno);
+        assert_eq!(cells.len(), 1);
+        assert!(matches!(&cells[0], Cell::Code { source, .. } if source.is_empty()));
+    }
+
+    #[test]
+    fn test_initial_cells_empty_template_keeps_empty_code_cell() {
+        let templates = BTreeMap::from([("python".to_string(), Vec::new())]);
+        let cells = initial_cells(&templates, &Runtime::Python);
+        assert_eq!(cells.len(), 1);
+        assert!(matches!(&cells[0], Cell::Code { source, .. } if source.is_empty()));
+    }
+
     #[test]
     fn test_get_runtime_returns_python_by_default() {
         let state = NotebookState::new_empty();
@@ -918,6 +1059,31 @@ mod tests {
         assert_eq!(state.get_runtime(), Runtime::Deno);
     }
 
+    #[test]
+    fn test_set_auto_launch_reaches_snapshot() {
+        let mut state = NotebookState::new_empty();
+        assert!(state.auto_launch_enabled());
+        assert_eq!(
+            snapshot_from_nbformat(&state.notebook.metadata)
+                .runt
+                .auto_launch,
+            None
+        );
+
+        state.set_auto_launch(false);
+        assert!(!state.auto_launch_enabled());
+        assert!(state.dirty);
+        assert_eq!(
+            snapshot_from_nbformat(&state.notebook.metadata)
+                .runt
+                .auto_launch,
+            Some(false)
+        );
+
+        state.set_auto_launch(true);
+        assert!(state.auto_launch_enabled());
+    }
+
     #[test]
     fn test_find_cell_index_returns_correct_position() {
         let state = NotebookState::new_empty();
@@ -1045,6 +1211,91 @@ mod tests {
         assert!(state.dirty);
     }
 
+    fn state_with_executed_cell() -> (NotebookState, String) {
+        let mut state = NotebookState::new_empty();
+        let cell_id = state.notebook.cells[0].id().to_string();
+        state.update_cell_source(&cell_id, "print('hi')");
+        state.set_cell_execution_count(&cell_id, 3);
+        state.append_cell_output(
+            &cell_id,
+            serde_json::from_value::<Output>(serde_json::json!({
+                "output_type": "stream",
+                "name": "stdout",
+                "text": "hi\n",
+            }))
+            .unwrap(),
+        );
+        state.add_cell("markdown", Some(&cell_id));
+        state.dirty = false;
+        (state, cell_id)
+    }
+
+    #[test]
+    fn test_duplicate_cell_inserts_copy_after_source() {
+        let (mut state, cell_id) = state_with_executed_cell();
+        let markdown_id = state.notebook.cells[1].id().to_string();
+
+        let (index, copy) = state.duplicate_cell(&cell_id, true).unwrap();
+
+        assert_eq!(index, 1);
+        assert_ne!(copy.id(), cell_id);
+        let ids: Vec<String> = state
+            .notebook
+            .cells
+            .iter()
+            .map(|c| c.id().to_string())
+            .collect();
+        assert_eq!(ids, vec![cell_id, copy.id().to_string(), markdown_id]);
+        assert!(state.dirty);
+
+        let FrontendCell::Code {
+            source,
+            execution_count,
+            outputs,
+            ..
+        } = copy
+        else {
+            panic!("expected a code cell");
+        };
+        assert_eq!(source, "print('hi')");
+        assert_eq!(execution_count, Some(3));
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0]["text"], serde_json::json!(["hi\n"]));
+    }
+
+    #[test]
+    fn test_duplicate_cell_without_outputs() {
+        let (mut state, cell_id) = state_with_executed_cell();
+
+        let (_, copy) = state.duplicate_cell(&cell_id, false).unwrap();
+
+        let FrontendCell::Code {
+            source,
+            execution_count,
+            outputs,
+            ..
+        } = copy
+        else {
+            panic!("expected a code cell");
+        };
+        assert_eq!(source, "print('hi')");
+        assert_eq!(execution_count, None);
+        assert!(outputs.is_empty());
+        // The original keeps its outputs
+        let Cell::Code { outputs, .. } = &state.notebook.cells[0] else {
+            panic!("expected a code cell");
+        };
+        assert_eq!(outputs.len(), 1);
+    }
+
+    #[test]
+    fn test_duplicate_cell_missing_returns_none() {
+        let mut state = NotebookState::new_empty();
+
+        assert!(state.duplicate_cell("nonexistent", true).is_none());
+        assert_eq!(state.notebook.cells.len(), 1);
+    }
+
     #[test]
     fn test_delete_cell_removes_cell() {
         let mut state = NotebookState::new_empty();
@@ -1144,6 +1395,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sanitize_frontend_outputs_of_untrusted_notebook() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("downloaded.ipynb");
+        std::fs::write(&path, "{}").unwrap();
+        let cells = || {
+            vec![FrontendCell::Code {
+                id: "c1".to_string(),
+                source: String::new(),
+                execution_count: Some(1),
+                outputs: vec![serde_json::json!({
+                    "output_type": "display_data",
+                    "data": {
+                        "text/html": "<b>x</b><img src=x onerror=alert(1)>",
+                        "application/javascript": "alert(2)"
+                    },
+                    "metadata": {}
+                })],
+            }]
+        };
+        let settings = settings::OutputSettings::default();
+
+        // Never saved: its outputs came from this session's kernel
+        let mut state = NotebookState::new_empty();
+        let mut unsaved = cells();
+        state.sanitize_frontend_outputs(&mut unsaved, &settings);
+        let FrontendCell::Code { outputs, .. } = &unsaved[0] else {
+            panic!("Expected code cell");
+        };
+        assert_eq!(outputs[0]["data"]["application/javascript"], "alert(2)");
+
+        // A file with no trust signature
+        state.path = Some(path);
+        let mut loaded = cells();
+        state.sanitize_frontend_outputs(&mut loaded, &settings);
+        let FrontendCell::Code { outputs, .. } = &loaded[0] else {
+            panic!("Expected code cell");
+        };
+        assert_eq!(
+            outputs[0]["data"],
+            serde_json::json!({ "text/html": "<b>x</b><img src=\"x\">" })
+        );
+    }
+
     #[test]
     fn test_serialize_produces_valid_json() {
         let state = NotebookState::new_empty();
@@ -1157,6 +1452,46 @@ mod tests {
         assert!(parsed["cells"].is_array());
     }
 
+    #[test]
+    fn test_copy_without_outputs_leaves_state_unchanged() {
+        let mut state = NotebookState::new_empty();
+        state.path = Some(PathBuf::from("/tmp/original.ipynb"));
+        let cell_id = state.notebook.cells[0].id().to_string();
+        state.update_cell_source(&cell_id, "print('hi')");
+        state.set_cell_execution_count(&cell_id, 3);
+        state.append_cell_output(
+            &cell_id,
+            serde_json::from_value::<Output>(serde_json::json!({
+                "output_type": "stream",
+                "name": "stdout",
+                "text": "hi\n",
+            }))
+            .unwrap(),
+        );
+        state.dirty = false;
+        let before = state.serialize().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let copy_path = dir.path().join("clean.ipynb");
+        std::fs::write(&copy_path, state.serialize_without_outputs().unwrap()).unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&copy_path).unwrap()).unwrap();
+        let cell = &written["cells"][0];
+        assert_eq!(cell["outputs"], serde_json::json!([]));
+        assert!(cell["execution_count"].is_null());
+        assert_eq!(cell["source"], serde_json::json!(["print('hi')"]));
+        assert_eq!(
+            written["metadata"]["runt"],
+            serde_json::to_value(&state.notebook.metadata.additional["runt"]).unwrap()
+        );
+
+        // The open notebook keeps its outputs, path and clean flag
+        assert_eq!(state.serialize().unwrap(), before);
+        assert_eq!(state.path, Some(PathBuf::from("/tmp/original.ipynb")));
+        assert!(!state.dirty);
+    }
+
     #[test]
     fn test_source_to_lines_handles_empty_string() {
         let lines = source_to_lines("");
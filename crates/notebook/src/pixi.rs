@@ -299,8 +299,10 @@ pub fn convert_to_conda_dependencies(config: &PixiConfig) -> CondaDependencies {
     CondaDependencies {
         dependencies: config.dependencies.clone(),
         channels: config.channels.clone(),
+        channel_priority: None,
         python: config.python.clone(),
         env_id: None,
+        pip_dependencies: config.pypi_dependencies.clone(),
     }
 }
 
@@ -490,6 +492,40 @@ numpy = "*"
         assert_eq!(conda_deps.python, config.python);
     }
 
+    #[test]
+    fn test_convert_carries_pypi_dependencies() {
+        let temp = TempDir::new().unwrap();
+        create_pixi_toml(
+            temp.path(),
+            r#"
+[workspace]
+name = "web"
+channels = ["conda-forge"]
+
+[dependencies]
+python = ">=3.11"
+
+[pypi-dependencies]
+fastapi = ">=0.100"
+requests = "*"
+"#,
+        );
+
+        let config = parse_pixi_toml(&temp.path().join("pixi.toml")).unwrap();
+        let conda_deps = convert_to_conda_dependencies(&config);
+
+        assert_eq!(conda_deps.pip_dependencies.len(), 2);
+        assert!(conda_deps
+            .pip_dependencies
+            .iter()
+            .any(|d| d.starts_with("fastapi")));
+        assert!(conda_deps.pip_dependencies.iter().any(|d| d == "requests"));
+
+        // pip deps reach the env spec handed to kernel-env's pip-install step
+        let env_deps: kernel_env::CondaDependencies = conda_deps.into();
+        assert_eq!(env_deps.pip_dependencies.len(), 2);
+    }
+
     #[test]
     fn test_create_pixi_info() {
         let temp = TempDir::new().unwrap();
@@ -8,6 +8,8 @@
 
 use std::path::{Path, PathBuf};
 
+use serde::Serialize;
+
 /// The type of project file detected.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ProjectFileKind {
@@ -23,6 +25,26 @@ pub struct DetectedProjectFile {
     pub kind: ProjectFileKind,
 }
 
+impl DetectedProjectFile {
+    /// The env_source the daemon launches a kernel with for this file.
+    pub fn to_env_source(&self) -> &'static str {
+        match self.kind {
+            ProjectFileKind::PyprojectToml => "uv:pyproject",
+            ProjectFileKind::PixiToml => "conda:pixi",
+            ProjectFileKind::EnvironmentYml => "conda:env_yml",
+        }
+    }
+}
+
+/// The environment source detected for a notebook's current location.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DetectedEnvironment {
+    /// env_source to launch the kernel with, e.g. `"uv:pyproject"`.
+    pub env_source: String,
+    /// The project file the source comes from.
+    pub project_file: PathBuf,
+}
+
 /// Mapping from filename to project file kind, in tiebreaker priority order.
 const ALL_CANDIDATES: &[(&str, ProjectFileKind)] = &[
     ("pyproject.toml", ProjectFileKind::PyprojectToml),
@@ -90,6 +112,23 @@ pub fn find_nearest_project_file(
     }
 }
 
+/// Re-run project file detection for a notebook at `notebook_path`.
+///
+/// Detection normally happens when a notebook is opened; this is for when
+/// the notebook has since moved (e.g. Save As into a project directory).
+/// Returns `None` when no project file is found from the new location.
+pub fn redetect_environment(notebook_path: &Path) -> Option<DetectedEnvironment> {
+    let all_kinds = [
+        ProjectFileKind::PyprojectToml,
+        ProjectFileKind::PixiToml,
+        ProjectFileKind::EnvironmentYml,
+    ];
+    find_nearest_project_file(notebook_path, &all_kinds).map(|detected| DetectedEnvironment {
+        env_source: detected.to_env_source().to_string(),
+        project_file: detected.path,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -310,4 +349,53 @@ mod tests {
         assert!(found.is_some());
         assert_eq!(found.unwrap().kind, ProjectFileKind::PyprojectToml);
     }
+
+    #[test]
+    fn test_redetect_after_move_into_pyproject_dir() {
+        let temp = TempDir::new().unwrap();
+        let scratch = temp.path().join("scratch");
+        let project = temp.path().join("project");
+        std::fs::create_dir_all(&scratch).unwrap();
+        std::fs::create_dir_all(project.join("notebooks")).unwrap();
+        // Keep the walk-up inside the temp dir
+        std::fs::create_dir_all(temp.path().join(".git")).unwrap();
+        write_file(&project, "pyproject.toml", "[project]\nname = \"test\"");
+
+        write_file(&scratch, "analysis.ipynb", "{}");
+        assert_eq!(redetect_environment(&scratch.join("analysis.ipynb")), None);
+
+        // Save As into the project tree
+        let moved = project.join("notebooks").join("analysis.ipynb");
+        std::fs::copy(scratch.join("analysis.ipynb"), &moved).unwrap();
+
+        let detected = redetect_environment(&moved).unwrap();
+        assert_eq!(detected.env_source, "uv:pyproject");
+        assert_eq!(detected.project_file, project.join("pyproject.toml"));
+    }
+
+    #[test]
+    fn test_redetect_picks_up_closer_project_file() {
+        let temp = TempDir::new().unwrap();
+        let project = temp.path().join("project");
+        let analysis = project.join("analysis");
+        std::fs::create_dir_all(&analysis).unwrap();
+        write_file(&project, "pyproject.toml", "[project]\nname = \"test\"");
+        write_file(&analysis, "pixi.toml", "[project]\nname = \"test\"");
+
+        write_file(&project, "notebook.ipynb", "{}");
+        assert_eq!(
+            redetect_environment(&project.join("notebook.ipynb"))
+                .unwrap()
+                .env_source,
+            "uv:pyproject"
+        );
+
+        write_file(&analysis, "notebook.ipynb", "{}");
+        assert_eq!(
+            redetect_environment(&analysis.join("notebook.ipynb"))
+                .unwrap()
+                .env_source,
+            "conda:pixi"
+        );
+    }
 }
@@ -0,0 +1,212 @@
+//! Hand-editing a notebook's raw metadata.
+//!
+//! Power users sometimes need notebook-level metadata the UI doesn't expose.
+//! [`raw_metadata`] returns `metadata.additional` (everything besides the
+//! kernelspec, language info and authors) as one JSON object, and
+//! [`set_raw_metadata`] replaces it after checking the edit won't break the
+//! `runt` sections the app reads. Accepted edits go through
+//! [`migrate_notebook_metadata`], so legacy layouts pasted in are brought up
+//! to date like a freshly opened notebook.
+
+use nbformat::v4::Metadata;
+use serde_json::{Map, Value};
+
+use crate::metadata_migration::{migrate_notebook_metadata, MetadataChange};
+use crate::validation::{validate_runt_metadata, ValidationIssue};
+
+/// `runt` keys that can only change with explicit confirmation: editing
+/// them by hand forges or discards the notebook's trust approval.
+pub const PROTECTED_RUNT_KEYS: [&str; 2] = ["trust_signature", "trust_timestamp"];
+
+/// Why [`set_raw_metadata`] refused an edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawMetadataError {
+    /// The new metadata isn't a JSON object
+    NotAnObject,
+    /// The edit would leave `runt` in a shape the app can't read
+    InvalidShape(Vec<ValidationIssue>),
+    /// The edit changes protected keys and wasn't confirmed
+    NeedsConfirmation { keys: Vec<String> },
+}
+
+impl std::fmt::Display for RawMetadataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotAnObject => write!(f, "notebook metadata must be a JSON object"),
+            Self::InvalidShape(issues) => {
+                let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+                write!(f, "invalid notebook metadata: {}", issues.join("; "))
+            }
+            Self::NeedsConfirmation { keys } => write!(
+                f,
+                "changing {} requires confirmation",
+                keys.iter()
+                    .map(|key| format!("runt.{key}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RawMetadataError {}
+
+/// `metadata.additional` as a JSON object.
+pub fn raw_metadata(metadata: &Metadata) -> Value {
+    Value::Object(
+        metadata
+            .additional
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect(),
+    )
+}
+
+/// Replace `metadata.additional` with `value`, returning what the migration
+/// changed on top of it.
+///
+/// Leaves `metadata` untouched and fails if `value` isn't an object, if the
+/// migrated result has invalid `runt` sections, or if it changes one of the
+/// [`PROTECTED_RUNT_KEYS`] without `confirm`.
+pub fn set_raw_metadata(
+    metadata: &mut Metadata,
+    value: Value,
+    confirm: bool,
+) -> Result<Vec<MetadataChange>, RawMetadataError> {
+    let Value::Object(additional) = value else {
+        return Err(RawMetadataError::NotAnObject);
+    };
+
+    let mut edited = metadata.clone();
+    edited.additional = additional.into_iter().collect();
+    let changes = migrate_notebook_metadata(&mut edited);
+
+    let mut issues = Vec::new();
+    if let Some(runt) = edited.additional.get("runt") {
+        validate_runt_metadata(runt, &mut issues);
+    }
+    issues.retain(ValidationIssue::is_error);
+    if !issues.is_empty() {
+        return Err(RawMetadataError::InvalidShape(issues));
+    }
+
+    if !confirm {
+        let keys = changed_protected_keys(metadata, &edited);
+        if !keys.is_empty() {
+            return Err(RawMetadataError::NeedsConfirmation { keys });
+        }
+    }
+
+    *metadata = edited;
+    Ok(changes)
+}
+
+fn runt_object(metadata: &Metadata) -> Option<&Map<String, Value>> {
+    metadata.additional.get("runt").and_then(Value::as_object)
+}
+
+/// The [`PROTECTED_RUNT_KEYS`] added, removed or changed between `before`
+/// and `after`.
+fn changed_protected_keys(before: &Metadata, after: &Metadata) -> Vec<String> {
+    let before = runt_object(before);
+    let after = runt_object(after);
+    PROTECTED_RUNT_KEYS
+        .iter()
+        .filter(|key| {
+            before.and_then(|runt| runt.get(**key)) != after.and_then(|runt| runt.get(**key))
+        })
+        .map(|key| key.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn signed_metadata() -> Metadata {
+        serde_json::from_value(json!({
+            "runt": {
+                "schema_version": "1",
+                "env_id": "existing-env",
+                "metadata_version": 1,
+                "uv": {"dependencies": ["pandas"]},
+                "trust_signature": "hmac-sha256:abc",
+                "trust_timestamp": "2025-01-01T00:00:00Z",
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_valid_edit_applies_and_migrates() {
+        let mut metadata = signed_metadata();
+        let mut raw = raw_metadata(&metadata);
+        raw["runt"]["uv"]["dependencies"] = json!(["pandas", "polars"]);
+        raw["jupytext"] = json!({"formats": "ipynb,py"});
+        raw["conda"] = json!({"dependencies": null});
+
+        let changes = set_raw_metadata(&mut metadata, raw, false).unwrap();
+
+        assert!(changes.contains(&MetadataChange::MovedToRunt { section: "conda" }));
+        let runt = &metadata.additional["runt"];
+        assert_eq!(runt["uv"]["dependencies"], json!(["pandas", "polars"]));
+        // The empty conda section lost out to uv's packages
+        assert!(runt.get("conda").is_none());
+        assert_eq!(runt["trust_signature"], json!("hmac-sha256:abc"));
+        assert_eq!(
+            metadata.additional["jupytext"],
+            json!({"formats": "ipynb,py"})
+        );
+    }
+
+    #[test]
+    fn test_shape_violations_are_rejected() {
+        let original = signed_metadata();
+
+        for bad in [
+            json!(["not", "an", "object"]),
+            json!({"runt": "not an object"}),
+            json!({"runt": {"schema_version": "1", "uv": {"dependencies": "pandas"}}}),
+            json!({"runt": {"schema_version": "1", "conda": {"channels": [1]}}}),
+            json!({"uv": ["pandas"]}),
+        ] {
+            let mut metadata = original.clone();
+            let err = set_raw_metadata(&mut metadata, bad.clone(), true).unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    RawMetadataError::NotAnObject | RawMetadataError::InvalidShape(_)
+                ),
+                "{bad}: {err}"
+            );
+            assert_eq!(raw_metadata(&metadata), raw_metadata(&original));
+        }
+    }
+
+    #[test]
+    fn test_trust_keys_need_confirmation() {
+        let mut metadata = signed_metadata();
+        let mut raw = raw_metadata(&metadata);
+        raw["runt"]
+            .as_object_mut()
+            .unwrap()
+            .remove("trust_signature");
+        raw["runt"]["trust_timestamp"] = json!("2030-01-01T00:00:00Z");
+
+        let err = set_raw_metadata(&mut metadata, raw.clone(), false).unwrap_err();
+        assert_eq!(
+            err,
+            RawMetadataError::NeedsConfirmation {
+                keys: vec!["trust_signature".into(), "trust_timestamp".into()]
+            }
+        );
+        assert_eq!(
+            metadata.additional["runt"]["trust_signature"],
+            json!("hmac-sha256:abc")
+        );
+
+        set_raw_metadata(&mut metadata, raw, true).unwrap();
+        assert!(metadata.additional["runt"].get("trust_signature").is_none());
+    }
+}
@@ -0,0 +1,347 @@
+//! Bringing local notebook state back in line with the daemon's doc.
+//!
+//! The local [`NotebookState`](crate::notebook_state::NotebookState) and the
+//! daemon's Automerge doc can drift apart: a change that failed to sync, or a
+//! reconnect that raced an edit. [`plan`] computes the smallest set of
+//! [`CellOp`]s that turns the local cells into the daemon's, and [`apply`]
+//! performs them. Cells that are only out of order are moved, not re-added,
+//! and as few cells as possible move.
+
+use std::collections::{HashMap, HashSet};
+
+use nbformat::v4::Cell;
+use serde::Serialize;
+
+/// One change to the local cells.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum CellOp {
+    /// Remove a cell the daemon doesn't have.
+    Delete { cell_id: String },
+    /// Move a cell to `index` in the daemon's order.
+    Move { cell_id: String, index: usize },
+    /// Insert the daemon's cell at `index`.
+    Add { cell_id: String, index: usize },
+    /// Replace a cell's content (type, source, outputs, execution count)
+    /// with the daemon's.
+    Update { cell_id: String },
+}
+
+fn cell_id(cell: &Cell) -> String {
+    cell.id().to_string()
+}
+
+/// Whether two cells with the same id show different content.
+fn content_differs(local: &Cell, daemon: &Cell) -> bool {
+    match (local, daemon) {
+        (
+            Cell::Code {
+                source: a,
+                execution_count: count_a,
+                outputs: outputs_a,
+                ..
+            },
+            Cell::Code {
+                source: b,
+                execution_count: count_b,
+                outputs: outputs_b,
+                ..
+            },
+        ) => {
+            a.concat() != b.concat()
+                || count_a != count_b
+                || serde_json::to_value(outputs_a).ok() != serde_json::to_value(outputs_b).ok()
+        }
+        (Cell::Markdown { source: a, .. }, Cell::Markdown { source: b, .. })
+        | (Cell::Raw { source: a, .. }, Cell::Raw { source: b, .. }) => a.concat() != b.concat(),
+        _ => true,
+    }
+}
+
+/// Indices into `seq` of a longest strictly increasing subsequence.
+fn longest_increasing_subsequence(seq: &[usize]) -> Vec<usize> {
+    // tails[k]: index into seq of the smallest tail of an increasing run of
+    // length k + 1
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = vec![None; seq.len()];
+    for (i, &value) in seq.iter().enumerate() {
+        let k = tails.partition_point(|&t| seq[t] < value);
+        previous[i] = k.checked_sub(1).map(|p| tails[p]);
+        if k == tails.len() {
+            tails.push(i);
+        } else {
+            tails[k] = i;
+        }
+    }
+
+    let mut run = Vec::with_capacity(tails.len());
+    let mut next = tails.last().copied();
+    while let Some(i) = next {
+        run.push(i);
+        next = previous[i];
+    }
+    run.reverse();
+    run
+}
+
+/// The operations that turn `local` into `daemon`, in the order
+/// deletes, moves, adds, updates. Empty when they already match.
+pub fn plan(local: &[Cell], daemon: &[Cell]) -> Vec<CellOp> {
+    let daemon_index: HashMap<String, usize> = daemon
+        .iter()
+        .enumerate()
+        .map(|(i, cell)| (cell_id(cell), i))
+        .collect();
+    let local_by_id: HashMap<String, &Cell> =
+        local.iter().map(|cell| (cell_id(cell), cell)).collect();
+
+    let mut ops = Vec::new();
+
+    // Cells only the local side has
+    let mut kept = Vec::new();
+    for cell in local {
+        let id = cell_id(cell);
+        match daemon_index.get(&id) {
+            Some(&index) => kept.push((id, index)),
+            None => ops.push(CellOp::Delete { cell_id: id }),
+        }
+    }
+
+    // Cells on both sides: the longest run already in the daemon's order
+    // stays put, the rest move
+    let positions: Vec<usize> = kept.iter().map(|(_, index)| *index).collect();
+    let in_order: HashSet<usize> = longest_increasing_subsequence(&positions)
+        .into_iter()
+        .collect();
+    let mut moves: Vec<(String, usize)> = kept
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !in_order.contains(i))
+        .map(|(_, (id, index))| (id.clone(), *index))
+        .collect();
+    moves.sort_by_key(|(_, index)| *index);
+    ops.extend(
+        moves
+            .into_iter()
+            .map(|(cell_id, index)| CellOp::Move { cell_id, index }),
+    );
+
+    // Cells only the daemon has, and content that differs
+    let mut updates = Vec::new();
+    for (index, cell) in daemon.iter().enumerate() {
+        let id = cell_id(cell);
+        match local_by_id.get(&id) {
+            None => ops.push(CellOp::Add { cell_id: id, index }),
+            Some(local_cell) if content_differs(local_cell, cell) => {
+                updates.push(CellOp::Update { cell_id: id })
+            }
+            Some(_) => {}
+        }
+    }
+    ops.extend(updates);
+
+    ops
+}
+
+/// Apply `ops` (from [`plan`]) to `local`, leaving it matching `daemon`.
+pub fn apply(local: &mut Vec<Cell>, daemon: &[Cell], ops: &[CellOp]) {
+    if ops.is_empty() {
+        return;
+    }
+
+    // Cells added or updated take the daemon's version; moves fall out of
+    // rebuilding in the daemon's order
+    let replaced: HashSet<&str> = ops
+        .iter()
+        .filter_map(|op| match op {
+            CellOp::Add { cell_id, .. } | CellOp::Update { cell_id } => Some(cell_id.as_str()),
+            CellOp::Delete { .. } | CellOp::Move { .. } => None,
+        })
+        .collect();
+
+    let mut existing: HashMap<String, Cell> = std::mem::take(local)
+        .into_iter()
+        .map(|cell| (cell_id(&cell), cell))
+        .collect();
+    *local = daemon
+        .iter()
+        .map(|cell| {
+            let id = cell_id(cell);
+            match existing.remove(&id) {
+                Some(local_cell) if !replaced.contains(id.as_str()) => local_cell,
+                _ => cell.clone(),
+            }
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn code(id: &str, source: &str) -> Cell {
+        serde_json::from_value(json!({
+            "cell_type": "code",
+            "id": id,
+            "metadata": {},
+            "execution_count": null,
+            "source": source,
+            "outputs": []
+        }))
+        .unwrap()
+    }
+
+    fn markdown(id: &str, source: &str) -> Cell {
+        serde_json::from_value(json!({
+            "cell_type": "markdown",
+            "id": id,
+            "metadata": {},
+            "source": source
+        }))
+        .unwrap()
+    }
+
+    fn ids(cells: &[Cell]) -> Vec<String> {
+        cells.iter().map(cell_id).collect()
+    }
+
+    fn sources(cells: &[Cell]) -> Vec<String> {
+        cells.iter().map(|cell| cell.source().concat()).collect()
+    }
+
+    #[test]
+    fn test_matching_cells_need_no_ops() {
+        let cells = vec![code("a", "x = 1"), markdown("b", "# Title")];
+        assert_eq!(plan(&cells, &cells.clone()), vec![]);
+    }
+
+    #[test]
+    fn test_divergent_cells_converge_with_minimal_ops() {
+        // Local missed a delete of "c" and an insert of "e", has "d" and "a"
+        // swapped, and a stale source for "b"
+        let mut local = vec![
+            code("d", "plot()"),
+            code("a", "import pandas"),
+            code("b", "df = load()"),
+            markdown("c", "notes"),
+        ];
+        let daemon = vec![
+            code("a", "import pandas"),
+            code("b", "df = load('data.csv')"),
+            code("e", "df.head()"),
+            code("d", "plot()"),
+        ];
+
+        let ops = plan(&local, &daemon);
+        assert_eq!(
+            ops,
+            vec![
+                CellOp::Delete {
+                    cell_id: "c".into()
+                },
+                CellOp::Move {
+                    cell_id: "d".into(),
+                    index: 3
+                },
+                CellOp::Add {
+                    cell_id: "e".into(),
+                    index: 2
+                },
+                CellOp::Update {
+                    cell_id: "b".into()
+                },
+            ]
+        );
+
+        apply(&mut local, &daemon, &ops);
+        assert_eq!(ids(&local), vec!["a", "b", "e", "d"]);
+        assert_eq!(sources(&local), sources(&daemon));
+        assert_eq!(plan(&local, &daemon), vec![]);
+    }
+
+    #[test]
+    fn test_reordering_moves_fewest_cells() {
+        // Moving "e" to the front is one move, not four
+        let mut local = ["a", "b", "c", "d", "e"].map(|id| code(id, id)).to_vec();
+        let daemon = ["e", "a", "b", "c", "d"].map(|id| code(id, id)).to_vec();
+
+        let ops = plan(&local, &daemon);
+        assert_eq!(
+            ops,
+            vec![CellOp::Move {
+                cell_id: "e".into(),
+                index: 0
+            }]
+        );
+
+        apply(&mut local, &daemon, &ops);
+        assert_eq!(ids(&local), ids(&daemon));
+    }
+
+    fn with_outputs(cell: Cell, outputs: serde_json::Value) -> Cell {
+        let mut json = serde_json::to_value(cell).unwrap();
+        json["outputs"] = outputs;
+        json["execution_count"] = json!(1);
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_resolved_outputs_match_local_outputs() {
+        // Local outputs as read from disk (multiline arrays); daemon outputs
+        // as resolved from their manifests (plain strings)
+        let local = vec![with_outputs(
+            code("a", "print('hi')"),
+            json!([{"output_type": "stream", "name": "stdout", "text": ["hi\n", "there\n"]}]),
+        )];
+        let daemon = vec![with_outputs(
+            code("a", "print('hi')"),
+            json!([{"output_type": "stream", "name": "stdout", "text": "hi\nthere\n"}]),
+        )];
+        assert_eq!(plan(&local, &daemon), vec![]);
+    }
+
+    #[test]
+    fn test_new_daemon_outputs_replace_local_ones() {
+        let mut local = vec![with_outputs(code("a", "x"), json!([]))];
+        let daemon = vec![with_outputs(
+            code("a", "x"),
+            json!([{
+                "output_type": "execute_result",
+                "execution_count": 1,
+                "data": {"text/plain": "42"},
+                "metadata": {}
+            }]),
+        )];
+
+        let ops = plan(&local, &daemon);
+        assert_eq!(
+            ops,
+            vec![CellOp::Update {
+                cell_id: "a".into()
+            }]
+        );
+        apply(&mut local, &daemon, &ops);
+        let Cell::Code { outputs, .. } = &local[0] else {
+            panic!("Expected code cell");
+        };
+        assert_eq!(outputs.len(), 1);
+    }
+
+    #[test]
+    fn test_changed_cell_type_is_updated() {
+        let mut local = vec![code("a", "# Notes")];
+        let daemon = vec![markdown("a", "# Notes")];
+
+        let ops = plan(&local, &daemon);
+        assert_eq!(
+            ops,
+            vec![CellOp::Update {
+                cell_id: "a".into()
+            }]
+        );
+
+        apply(&mut local, &daemon, &ops);
+        assert!(matches!(local[0], Cell::Markdown { .. }));
+    }
+}
@@ -0,0 +1,94 @@
+//! Showing files in the OS file manager.
+//!
+//! Used to jump from a detected project file (`pyproject.toml`, `pixi.toml`,
+//! `environment.yml`) to where it lives on disk.
+
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::Command;
+
+/// A file manager invocation: program and arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevealCommand {
+    pub program: &'static str,
+    pub args: Vec<OsString>,
+}
+
+/// The command that shows `path` in the file manager on `os` (one of
+/// [`std::env::consts::OS`]).
+///
+/// macOS and Windows select the file in its folder. Elsewhere `xdg-open`
+/// can only open a folder, so a file's containing directory is opened.
+pub fn reveal_command(os: &str, path: &Path, is_dir: bool) -> RevealCommand {
+    match os {
+        "macos" => RevealCommand {
+            program: "open",
+            args: vec!["-R".into(), path.into()],
+        },
+        "windows" => {
+            // explorer wants the switch and path as a single argument
+            let mut select = OsString::from("/select,");
+            select.push(path);
+            RevealCommand {
+                program: "explorer",
+                args: vec![select],
+            }
+        }
+        _ => {
+            let dir = if is_dir {
+                path
+            } else {
+                path.parent().unwrap_or(path)
+            };
+            RevealCommand {
+                program: "xdg-open",
+                args: vec![dir.into()],
+            }
+        }
+    }
+}
+
+/// Show `path` in the file manager. The file manager is spawned, not waited
+/// for.
+pub fn reveal(path: &Path) -> std::io::Result<()> {
+    let command = reveal_command(std::env::consts::OS, path, path.is_dir());
+    Command::new(command.program).args(&command.args).spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &RevealCommand) -> Vec<String> {
+        command
+            .args
+            .iter()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_macos_selects_file() {
+        let command = reveal_command("macos", Path::new("/work/pyproject.toml"), false);
+        assert_eq!(command.program, "open");
+        assert_eq!(args(&command), vec!["-R", "/work/pyproject.toml"]);
+    }
+
+    #[test]
+    fn test_windows_selects_file_in_one_argument() {
+        let command = reveal_command("windows", Path::new(r"C:\work\pixi.toml"), false);
+        assert_eq!(command.program, "explorer");
+        assert_eq!(args(&command), vec![r"/select,C:\work\pixi.toml"]);
+    }
+
+    #[test]
+    fn test_linux_opens_containing_directory() {
+        let file = reveal_command("linux", Path::new("/work/env/environment.yml"), false);
+        assert_eq!(file.program, "xdg-open");
+        assert_eq!(args(&file), vec!["/work/env"]);
+
+        let dir = reveal_command("linux", Path::new("/work/env"), true);
+        assert_eq!(args(&dir), vec!["/work/env"]);
+    }
+}
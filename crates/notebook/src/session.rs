@@ -172,7 +172,7 @@ pub fn load_window_session_state(session: &WindowSession) -> Result<NotebookStat
         Some(path) if path.exists() => {
             // Load saved notebook
             info!("[session] Loading notebook from {}", path.display());
-            crate::load_notebook_state_for_path(path, runtime)
+            crate::load_notebook_state_for_path(path, runtime).map_err(|e| e.to_string())
         }
         Some(path) => {
             // File doesn't exist anymore - create new notebook
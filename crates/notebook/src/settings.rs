@@ -13,7 +13,10 @@ use std::path::PathBuf;
 
 // Re-export types that notebook code uses from runtimed
 pub use runtimed::runtime::Runtime;
-pub use runtimed::settings_doc::{CondaDefaults, PythonEnvType, ThemeMode, UvDefaults};
+pub use runtimed::settings_doc::{
+    CondaDefaults, LspSettings, OutputSettings, PythonEnvType, TemplateCell, TemplateCellType,
+    ThemeMode, UvDefaults,
+};
 
 /// Get the path to the settings file
 fn settings_path() -> PathBuf {
@@ -67,6 +70,54 @@ pub fn load_settings() -> SyncedSettings {
             .get("conda")
             .and_then(|v| serde_json::from_value(v.clone()).ok())
             .unwrap_or(defaults.conda),
+        ansi_spans: json
+            .get("ansi_spans")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(defaults.ansi_spans),
+        keep_kernel_on_close: json
+            .get("keep_kernel_on_close")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(defaults.keep_kernel_on_close),
+        keep_kernel_on_exit: json
+            .get("keep_kernel_on_exit")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(defaults.keep_kernel_on_exit),
+        run_log: json
+            .get("run_log")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(defaults.run_log),
+        block_invalid_save: json
+            .get("block_invalid_save")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(defaults.block_invalid_save),
+        hide_install_suggestions: json
+            .get("hide_install_suggestions")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(defaults.hide_install_suggestions),
+        lsp: json
+            .get("lsp")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or(defaults.lsp),
+        kernel_startup: json
+            .get("kernel_startup")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or(defaults.kernel_startup),
+        tools: json
+            .get("tools")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or(defaults.tools),
+        keybindings: json
+            .get("keybindings")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or(defaults.keybindings),
+        outputs: json
+            .get("outputs")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or(defaults.outputs),
+        new_notebook_template: json
+            .get("new_notebook_template")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or(defaults.new_notebook_template),
     }
 }
 
@@ -113,8 +164,21 @@ mod tests {
             default_python_env: PythonEnvType::Uv,
             uv: UvDefaults {
                 default_packages: vec!["numpy".into(), "pandas".into()],
+                default_index_url: String::new(),
             },
             conda: CondaDefaults::default(),
+            ansi_spans: false,
+            keep_kernel_on_close: true,
+            keep_kernel_on_exit: false,
+            run_log: false,
+            block_invalid_save: false,
+            hide_install_suggestions: false,
+            lsp: LspSettings::default(),
+            kernel_startup: Default::default(),
+            tools: Default::default(),
+            keybindings: Default::default(),
+            outputs: Default::default(),
+            new_notebook_template: Default::default(),
         };
 
         let json = serde_json::to_string(&settings).unwrap();
@@ -124,6 +188,7 @@ mod tests {
         assert_eq!(parsed.default_runtime, Runtime::Deno);
         assert_eq!(parsed.default_python_env, PythonEnvType::Uv);
         assert_eq!(parsed.uv.default_packages, vec!["numpy", "pandas"]);
+        assert!(parsed.keep_kernel_on_close);
     }
 
     #[test]
@@ -249,6 +314,54 @@ mod tests {
                 .get("conda")
                 .and_then(|v| serde_json::from_value(v.clone()).ok())
                 .unwrap_or(defaults.conda),
+            ansi_spans: json_val
+                .get("ansi_spans")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(defaults.ansi_spans),
+            keep_kernel_on_close: json_val
+                .get("keep_kernel_on_close")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(defaults.keep_kernel_on_close),
+            keep_kernel_on_exit: json_val
+                .get("keep_kernel_on_exit")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(defaults.keep_kernel_on_exit),
+            run_log: json_val
+                .get("run_log")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(defaults.run_log),
+            block_invalid_save: json_val
+                .get("block_invalid_save")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(defaults.block_invalid_save),
+            hide_install_suggestions: json_val
+                .get("hide_install_suggestions")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(defaults.hide_install_suggestions),
+            lsp: json_val
+                .get("lsp")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or(defaults.lsp),
+            kernel_startup: json_val
+                .get("kernel_startup")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or(defaults.kernel_startup),
+            tools: json_val
+                .get("tools")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or(defaults.tools),
+            keybindings: json_val
+                .get("keybindings")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or(defaults.keybindings),
+            outputs: json_val
+                .get("outputs")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or(defaults.outputs),
+            new_notebook_template: json_val
+                .get("new_notebook_template")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or(defaults.new_notebook_template),
         };
         // Valid fields are preserved
         assert_eq!(settings.theme, ThemeMode::Dark);
@@ -5,8 +5,12 @@
 //! creation to `kernel_env::uv`.
 
 use anyhow::Result;
+use pep508_rs::{Requirement, VerbatimUrl};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use tauri::{AppHandle, Runtime};
 
 // Re-export core types from kernel-env for backward compatibility
 pub use kernel_env::uv::UvEnvironment;
@@ -27,6 +31,7 @@ impl From<NotebookDependencies> for kernel_env::UvDependencies {
         Self {
             dependencies: deps.dependencies,
             requires_python: deps.requires_python,
+            index_url: None,
         }
     }
 }
@@ -44,21 +49,12 @@ impl From<kernel_env::UvDependencies> for NotebookDependencies {
 // Metadata operations (notebook-specific, depend on nbformat)
 // =====================================================================
 
-/// Extract dependencies from notebook metadata.
+/// Extract dependencies from notebook metadata (`runt.uv`).
 ///
-/// Looks for uv config in the new nested path `runt.uv` first,
-/// then falls back to legacy `uv` for backward compatibility.
+/// Notebooks with a legacy top-level `uv` section are migrated on load by
+/// [`crate::metadata_migration`].
 pub fn extract_dependencies(metadata: &nbformat::v4::Metadata) -> Option<NotebookDependencies> {
-    // New format: metadata.runt.uv
-    if let Some(runt_value) = metadata.additional.get("runt") {
-        if let Some(uv_value) = runt_value.get("uv") {
-            if let Ok(deps) = serde_json::from_value(uv_value.clone()) {
-                return Some(deps);
-            }
-        }
-    }
-    // Legacy format: metadata.uv (fallback for unmigrated notebooks)
-    let uv_value = metadata.additional.get("uv")?;
+    let uv_value = metadata.additional.get("runt")?.get("uv")?;
     serde_json::from_value(uv_value.clone()).ok()
 }
 
@@ -105,6 +101,187 @@ pub fn extract_env_id(metadata: &nbformat::v4::Metadata) -> Option<String> {
     runt_value.get("env_id")?.as_str().map(|s| s.to_string())
 }
 
+// =====================================================================
+// Requirement validation
+// =====================================================================
+
+/// A dependency specifier that passed validation, ready to write to metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedReq {
+    /// Normalized package name (lowercase, runs of `-`, `_`, `.` become `-`)
+    pub name: String,
+    /// The specifier to store: trimmed, with the name normalized and the
+    /// rest (extras, versions, markers) kept as written
+    pub spec: String,
+}
+
+/// Why a dependency specifier was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecError {
+    pub spec: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SpecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid dependency \"{}\": {}", self.spec, self.message)
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+impl SpecError {
+    pub(crate) fn new(spec: &str, message: impl Into<String>) -> Self {
+        Self {
+            spec: spec.trim().to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Validate a PEP 508 requirement (e.g. `pandas>=2`, `requests[socks]`)
+/// and normalize its package name.
+pub fn parse_requirement(spec: &str) -> Result<NormalizedReq, SpecError> {
+    let trimmed = spec.trim();
+    if trimmed.is_empty() {
+        return Err(SpecError::new(spec, "package name is empty"));
+    }
+
+    let requirement = Requirement::<VerbatimUrl>::from_str(trimmed).map_err(|e| {
+        // pep508 errors carry a caret diagram on later lines; keep the summary
+        let summary = e.to_string().lines().next().unwrap_or_default().to_string();
+        match single_equals_fix(trimmed) {
+            Some(fixed) => SpecError::new(spec, format!("{summary} (did you mean \"{fixed}\"?)")),
+            None => SpecError::new(spec, summary),
+        }
+    })?;
+
+    let name = requirement.name.to_string();
+    let name_len = trimmed
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .unwrap_or(trimmed.len());
+    Ok(NormalizedReq {
+        spec: format!("{}{}", name, &trimmed[name_len..]),
+        name,
+    })
+}
+
+/// The normalized package name of a stored dependency, falling back to the
+/// text before any version operator for entries that don't parse.
+pub fn requirement_name(spec: &str) -> String {
+    match parse_requirement(spec) {
+        Ok(req) => req.name,
+        Err(_) => spec
+            .split(&['>', '<', '=', '!', '~', '['][..])
+            .next()
+            .unwrap_or(spec)
+            .trim()
+            .to_lowercase(),
+    }
+}
+
+/// Add `req` to `deps`, or update the entry for the same package.
+///
+/// A bare package name never overwrites an existing entry's constraints;
+/// anything else replaces the existing specifier. Returns whether `deps`
+/// changed, so re-adding an identical spec is a no-op.
+pub fn upsert_dependency(
+    deps: &mut Vec<String>,
+    req: &NormalizedReq,
+    name_of: fn(&str) -> String,
+) -> bool {
+    let Some(existing) = deps.iter_mut().find(|d| name_of(d) == req.name) else {
+        deps.push(req.spec.clone());
+        return true;
+    };
+    if *existing == req.spec || req.spec == req.name {
+        return false;
+    }
+    *existing = req.spec.clone();
+    true
+}
+
+/// [`upsert_dependency`] for PEP 508 requirements.
+pub fn upsert_requirement(deps: &mut Vec<String>, req: &NormalizedReq) -> bool {
+    upsert_dependency(deps, req, requirement_name)
+}
+
+/// A pasted `requirements.txt` block, split into what can go in notebook
+/// metadata and what can't.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequirementsText {
+    /// Valid requirements, in the order written
+    pub requirements: Vec<NormalizedReq>,
+    /// Lines that aren't valid PEP 508 requirements
+    pub rejected: Vec<SpecError>,
+    /// pip directives (`-r`, `-e`, `--index-url`, ...), which notebook
+    /// metadata has no place for
+    pub skipped: Vec<String>,
+}
+
+/// Parse requirement lines in `requirements.txt` syntax: one requirement per
+/// line, `#` comments, and `\` line continuations. Per-requirement options
+/// such as `--hash` are dropped.
+pub fn parse_requirements_text(text: &str) -> RequirementsText {
+    let mut parsed = RequirementsText::default();
+    let mut logical = String::new();
+    for line in text.lines() {
+        let mut line = strip_requirements_comment(line);
+        if !logical.is_empty() {
+            line = line.trim_start();
+        }
+        if let Some(continued) = line.strip_suffix('\\') {
+            logical.push_str(continued.trim_end());
+            logical.push(' ');
+            continue;
+        }
+        logical.push_str(line);
+        let entry = std::mem::take(&mut logical);
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        if entry.starts_with('-') {
+            log::warn!("[uv] Skipping requirements directive: {}", entry);
+            parsed.skipped.push(entry.to_string());
+            continue;
+        }
+        let spec = entry.split(" --").next().unwrap_or(entry);
+        match parse_requirement(spec) {
+            Ok(req) => parsed.requirements.push(req),
+            Err(e) => parsed.rejected.push(e),
+        }
+    }
+    parsed
+}
+
+/// Strip a `#` comment, which pip recognizes at the start of a line or
+/// after whitespace (so URL fragments like `#egg=` survive).
+fn strip_requirements_comment(line: &str) -> &str {
+    let line = line.trim_end();
+    if line.trim_start().starts_with('#') {
+        return "";
+    }
+    match line.find(" #").or_else(|| line.find("\t#")) {
+        Some(pos) => line[..pos].trim_end(),
+        None => line,
+    }
+}
+
+/// Turn a conda-style `name=1.2` into `name==1.2` if that parses, for the
+/// error hint.
+fn single_equals_fix(spec: &str) -> Option<String> {
+    let pos = spec.find('=')?;
+    let before = spec[..pos].chars().last();
+    let after = spec[pos + 1..].chars().next();
+    if matches!(before, Some('=' | '<' | '>' | '!' | '~')) || after == Some('=') {
+        return None;
+    }
+    let fixed = format!("{}=={}", &spec[..pos], &spec[pos + 1..]);
+    Requirement::<VerbatimUrl>::from_str(&fixed).ok()?;
+    Some(fixed)
+}
+
 // =====================================================================
 // Environment operations (delegating to kernel-env)
 // =====================================================================
@@ -119,20 +296,24 @@ pub fn compute_env_hash(deps: &NotebookDependencies, env_id: Option<&str>) -> St
     kernel_env::uv::compute_env_hash(&deps.clone().into(), env_id)
 }
 
-/// Prepare a virtual environment with the given dependencies.
-pub async fn prepare_environment(
+/// Prepare a virtual environment with the given dependencies, reporting
+/// progress to `app` when given.
+pub async fn prepare_environment<R: Runtime>(
     deps: &NotebookDependencies,
     env_id: Option<&str>,
+    app: Option<&AppHandle<R>>,
 ) -> Result<UvEnvironment> {
-    let handler: Arc<dyn kernel_env::ProgressHandler> = Arc::new(kernel_env::LogHandler);
+    let handler = crate::conda_env::progress_handler(app);
     kernel_env::uv::prepare_environment(&deps.clone().into(), env_id, handler).await
 }
 
 /// Create a prewarmed environment with ipykernel, ipywidgets, and
 /// user-configured default packages.
-pub async fn create_prewarmed_environment() -> Result<UvEnvironment> {
+pub async fn create_prewarmed_environment<R: Runtime>(
+    app: Option<&AppHandle<R>>,
+) -> Result<UvEnvironment> {
     let extra: Vec<String> = crate::settings::load_settings().uv.default_packages;
-    let handler: Arc<dyn kernel_env::ProgressHandler> = Arc::new(kernel_env::LogHandler);
+    let handler = crate::conda_env::progress_handler(app);
     kernel_env::uv::create_prewarmed_environment(&extra, handler).await
 }
 
@@ -159,14 +340,29 @@ pub fn is_environment_warmed(env: &UvEnvironment) -> bool {
     kernel_env::uv::is_environment_warmed(env)
 }
 
-/// Copy an existing UV environment to a new location.
-pub async fn copy_environment(source: &UvEnvironment, new_env_id: &str) -> Result<UvEnvironment> {
-    kernel_env::uv::copy_environment(source, new_env_id).await
+/// Copy an existing UV environment to where launch looks up `deps` for
+/// `new_env_id`.
+///
+/// Returns `Ok(None)` if `cancel` was set before the copy finished.
+pub async fn copy_environment(
+    source: &UvEnvironment,
+    deps: &NotebookDependencies,
+    new_env_id: &str,
+    handler: Arc<dyn kernel_env::ProgressHandler>,
+    cancel: &AtomicBool,
+) -> Result<Option<UvEnvironment>> {
+    kernel_env::uv::copy_environment(source, &deps.clone().into(), new_env_id, handler, cancel)
+        .await
 }
 
-/// Install additional dependencies into an existing environment.
-pub async fn sync_dependencies(env: &UvEnvironment, deps: &[String]) -> Result<()> {
-    kernel_env::uv::sync_dependencies(env, deps).await
+/// Install `added` into and uninstall `removed` from an existing environment.
+pub async fn sync_dependencies(
+    env: &UvEnvironment,
+    added: &[String],
+    removed: &[String],
+    handler: Arc<dyn kernel_env::ProgressHandler>,
+) -> Result<kernel_env::SyncReport> {
+    kernel_env::uv::sync_dependencies(env, added, removed, handler).await
 }
 
 #[cfg(test)]
@@ -220,4 +416,122 @@ mod tests {
             compute_env_hash(&deps2, None)
         );
     }
+
+    #[test]
+    fn test_parse_requirement_valid() {
+        for (spec, name) in [
+            ("numpy", "numpy"),
+            ("pandas>=2.0,<3", "pandas"),
+            ("requests[socks]", "requests"),
+            ("polars; python_version >= '3.9'", "polars"),
+        ] {
+            let req = parse_requirement(spec).unwrap();
+            assert_eq!(req.name, name);
+            assert_eq!(req.spec, spec);
+        }
+    }
+
+    #[test]
+    fn test_parse_requirement_normalizes() {
+        let req = parse_requirement("  Scikit_Learn >= 1.3 ").unwrap();
+        assert_eq!(req.name, "scikit-learn");
+        assert_eq!(req.spec, "scikit-learn >= 1.3");
+        assert_eq!(requirement_name("Foo.Bar==1"), "foo-bar");
+    }
+
+    #[test]
+    fn test_parse_requirement_invalid() {
+        let err = parse_requirement("numpy=1.2").unwrap_err();
+        assert_eq!(err.spec, "numpy=1.2");
+        assert!(err.message.contains("numpy==1.2"), "{}", err.message);
+        assert!(!err.to_string().contains('\n'));
+
+        for spec in ["", "   ", "numpy==", "-e foo", "numpy >= 1.2 extra"] {
+            assert!(
+                parse_requirement(spec).is_err(),
+                "{spec:?} should be rejected"
+            );
+        }
+    }
+
+    fn upsert(deps: &mut Vec<String>, spec: &str) -> bool {
+        upsert_requirement(deps, &parse_requirement(spec).unwrap())
+    }
+
+    #[test]
+    fn test_upsert_requirement_adds_new() {
+        let mut deps = vec!["pandas".to_string()];
+        assert!(upsert(&mut deps, "numpy>=1.26"));
+        assert_eq!(deps, vec!["pandas", "numpy>=1.26"]);
+    }
+
+    #[test]
+    fn test_upsert_requirement_updates_existing_version() {
+        let mut deps = vec!["NumPy>=1.20".to_string(), "pandas".to_string()];
+        assert!(upsert(&mut deps, "numpy>=2,<3"));
+        assert_eq!(deps, vec!["numpy>=2,<3", "pandas"]);
+
+        // Adding a version to an unconstrained entry works too
+        assert!(upsert(&mut deps, "pandas==2.2"));
+        assert_eq!(deps, vec!["numpy>=2,<3", "pandas==2.2"]);
+    }
+
+    #[test]
+    fn test_upsert_requirement_duplicate_is_noop() {
+        let mut deps = vec!["numpy>=2".to_string()];
+        assert!(!upsert(&mut deps, "numpy>=2"));
+        // A bare name keeps the existing constraint
+        assert!(!upsert(&mut deps, "NumPy"));
+        assert_eq!(deps, vec!["numpy>=2"]);
+    }
+
+    #[test]
+    fn test_parse_requirements_text() {
+        let text = "\
+# Core stack
+pandas>=2.0  # dataframes
+NumPy
+
+-r base.txt
+-e ./local-package
+--index-url https://example.com/simple
+requests[socks] \\
+    ; python_version >= \"3.9\"
+numpy=1.2
+scipy==1.13 --hash=sha256:abc123
+";
+        let parsed = parse_requirements_text(text);
+        let specs: Vec<_> = parsed
+            .requirements
+            .iter()
+            .map(|r| r.spec.as_str())
+            .collect();
+        assert_eq!(
+            specs,
+            vec![
+                "pandas>=2.0",
+                "numpy",
+                "requests[socks] ; python_version >= \"3.9\"",
+                "scipy==1.13",
+            ]
+        );
+        assert_eq!(parsed.rejected.len(), 1);
+        assert_eq!(parsed.rejected[0].spec, "numpy=1.2");
+        assert_eq!(
+            parsed.skipped,
+            vec![
+                "-r base.txt",
+                "-e ./local-package",
+                "--index-url https://example.com/simple"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_requirements_text_empty() {
+        assert_eq!(
+            parse_requirements_text("\n  # nothing here\n\n"),
+            RequirementsText::default()
+        );
+    }
 }
@@ -0,0 +1,397 @@
+//! Checking a notebook against nbformat v4 before it's written.
+//!
+//! Hand-edited metadata or buggy tools can leave a notebook that deserializes
+//! fine here but that other tools reject: an empty kernelspec name, a cell id
+//! used twice, a stream output that isn't stdout or stderr. [`validate`]
+//! walks the notebook and reports each problem as a [`ValidationIssue`].
+//!
+//! Errors break the nbformat schema (or the `runt` metadata namespace this
+//! app relies on). Warnings are tolerated by nbformat but usually mean a tool
+//! wrote the notebook incorrectly. With the `block_invalid_save` setting on,
+//! saving refuses a notebook that has errors.
+
+use std::collections::HashSet;
+
+use nbformat::v4::{Cell, Notebook, Output};
+use serde::Serialize;
+use serde_json::Value;
+
+/// How serious a [`ValidationIssue`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Breaks the nbformat schema; other tools may refuse the file
+    Error,
+    /// Allowed by the schema, but likely a mistake
+    Warning,
+}
+
+/// A problem found in a notebook.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    /// Where the problem is, e.g. `metadata.kernelspec.name` or
+    /// `cells[2].outputs[0].name`.
+    pub path: String,
+    /// The cell the problem is in, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cell_id: Option<String>,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            path: path.into(),
+            cell_id: None,
+            message: message.into(),
+        }
+    }
+
+    fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(path, message)
+        }
+    }
+
+    fn in_cell(mut self, cell_id: &str) -> Self {
+        self.cell_id = Some(cell_id.to_string());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Check `notebook` against nbformat v4, returning every issue found in
+/// document order. An empty list means the notebook is valid.
+pub fn validate(notebook: &Notebook) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+
+    if notebook.nbformat != 4 {
+        issues.push(ValidationIssue::error(
+            "nbformat",
+            format!("expected nbformat 4, found {}", notebook.nbformat),
+        ));
+    }
+    if notebook.nbformat_minor < 0 {
+        issues.push(ValidationIssue::error(
+            "nbformat_minor",
+            "must not be negative",
+        ));
+    }
+
+    validate_metadata(notebook, &mut issues);
+
+    let mut seen_ids = HashSet::new();
+    for (index, cell) in notebook.cells.iter().enumerate() {
+        validate_cell(index, cell, &mut seen_ids, &mut issues);
+    }
+
+    issues
+}
+
+fn missing(path: &str) -> ValidationIssue {
+    ValidationIssue::error(path, "missing required field")
+}
+
+fn validate_metadata(notebook: &Notebook, issues: &mut Vec<ValidationIssue>) {
+    let metadata = &notebook.metadata;
+
+    if let Some(kernelspec) = &metadata.kernelspec {
+        if kernelspec.name.is_empty() {
+            issues.push(missing("metadata.kernelspec.name"));
+        }
+        if kernelspec.display_name.is_empty() {
+            issues.push(missing("metadata.kernelspec.display_name"));
+        }
+    }
+    if let Some(language_info) = &metadata.language_info {
+        if language_info.name.is_empty() {
+            issues.push(missing("metadata.language_info.name"));
+        }
+    }
+    for (index, author) in metadata.authors.iter().flatten().enumerate() {
+        if author.name.is_empty() {
+            issues.push(missing(&format!("metadata.authors[{index}].name")));
+        }
+    }
+
+    if let Some(runt) = metadata.additional.get("runt") {
+        validate_runt_metadata(runt, issues);
+    }
+}
+
+/// The `runt` namespace: `schema_version`, plus optional `uv`/`conda`
+/// sections whose package and channel lists are lists of strings.
+pub(crate) fn validate_runt_metadata(runt: &Value, issues: &mut Vec<ValidationIssue>) {
+    let Some(runt) = runt.as_object() else {
+        issues.push(ValidationIssue::error("metadata.runt", "must be an object"));
+        return;
+    };
+
+    match runt.get("schema_version") {
+        None => issues.push(missing("metadata.runt.schema_version")),
+        Some(Value::String(_)) => {}
+        Some(_) => issues.push(ValidationIssue::error(
+            "metadata.runt.schema_version",
+            "must be a string",
+        )),
+    }
+
+    for section in ["uv", "conda", "deno"] {
+        let Some(value) = runt.get(section) else {
+            continue;
+        };
+        let path = format!("metadata.runt.{section}");
+        let Some(object) = value.as_object() else {
+            issues.push(ValidationIssue::error(path, "must be an object"));
+            continue;
+        };
+        for key in ["dependencies", "pip_dependencies", "channels"] {
+            let Some(list) = object.get(key) else {
+                continue;
+            };
+            let is_string_list = list
+                .as_array()
+                .is_some_and(|items| items.iter().all(Value::is_string));
+            if !is_string_list {
+                issues.push(ValidationIssue::error(
+                    format!("{path}.{key}"),
+                    "must be a list of strings",
+                ));
+            }
+        }
+    }
+}
+
+fn validate_cell(
+    index: usize,
+    cell: &Cell,
+    seen_ids: &mut HashSet<String>,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let id = cell.id().to_string();
+    let path = format!("cells[{index}]");
+
+    if !seen_ids.insert(id.clone()) {
+        issues.push(
+            ValidationIssue::error(format!("{path}.id"), format!("duplicate cell id {id:?}"))
+                .in_cell(&id),
+        );
+    }
+
+    // Multiline strings are stored as lines that each keep their newline
+    let source = cell.source();
+    let last = source.len().saturating_sub(1);
+    let broken_line = source.iter().enumerate().find(|(i, line)| {
+        let newline = line.find('\n');
+        match newline {
+            Some(at) => at != line.len() - 1,
+            None => *i != last,
+        }
+    });
+    if let Some((line, _)) = broken_line {
+        issues.push(
+            ValidationIssue::warning(
+                format!("{path}.source[{line}]"),
+                "source lines must end with a single newline, except the last",
+            )
+            .in_cell(&id),
+        );
+    }
+
+    match cell {
+        Cell::Code {
+            execution_count,
+            outputs,
+            ..
+        } => {
+            if execution_count.is_some_and(|count| count < 0) {
+                issues.push(
+                    ValidationIssue::error(
+                        format!("{path}.execution_count"),
+                        "must be null or a non-negative integer",
+                    )
+                    .in_cell(&id),
+                );
+            }
+            for (output_index, output) in outputs.iter().enumerate() {
+                let output_path = format!("{path}.outputs[{output_index}]");
+                if let Some(issue) = validate_output(&output_path, output) {
+                    issues.push(issue.in_cell(&id));
+                }
+            }
+        }
+        Cell::Markdown { attachments, .. } => {
+            let valid = match attachments {
+                None => true,
+                Some(Value::Object(files)) => files.values().all(Value::is_object),
+                Some(_) => false,
+            };
+            if !valid {
+                issues.push(
+                    ValidationIssue::error(
+                        format!("{path}.attachments"),
+                        "must map file names to MIME bundles",
+                    )
+                    .in_cell(&id),
+                );
+            }
+        }
+        Cell::Raw { .. } => {}
+    }
+}
+
+fn validate_output(path: &str, output: &Output) -> Option<ValidationIssue> {
+    match output {
+        Output::Stream { name, .. } if name != "stdout" && name != "stderr" => {
+            Some(ValidationIssue::error(
+                format!("{path}.name"),
+                format!("stream name must be \"stdout\" or \"stderr\", found {name:?}"),
+            ))
+        }
+        Output::Error(error) if error.ename.is_empty() => Some(missing(&format!("{path}.ename"))),
+        Output::DisplayData(_) | Output::ExecuteResult(_) => {
+            let value = serde_json::to_value(output).ok()?;
+            let bad_mime = value
+                .get("data")
+                .and_then(Value::as_object)?
+                .keys()
+                .find(|mime| !mime.contains('/'))?
+                .clone();
+            Some(ValidationIssue::error(
+                format!("{path}.data"),
+                format!("{bad_mime:?} is not a MIME type"),
+            ))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn notebook(value: Value) -> Notebook {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn code_cell(id: &str) -> Value {
+        json!({
+            "cell_type": "code",
+            "id": id,
+            "metadata": {},
+            "execution_count": 1,
+            "source": ["x = 1\n", "x"],
+            "outputs": [{
+                "output_type": "execute_result",
+                "execution_count": 1,
+                "data": {"text/plain": "1"},
+                "metadata": {}
+            }]
+        })
+    }
+
+    fn valid_notebook() -> Value {
+        json!({
+            "nbformat": 4,
+            "nbformat_minor": 5,
+            "metadata": {
+                "kernelspec": {"name": "python3", "display_name": "Python 3", "language": "python"},
+                "language_info": {"name": "python"},
+                "runt": {"schema_version": "1", "uv": {"dependencies": ["pandas"]}}
+            },
+            "cells": [code_cell("a"), code_cell("b")]
+        })
+    }
+
+    fn paths(issues: &[ValidationIssue]) -> Vec<&str> {
+        issues.iter().map(|issue| issue.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_valid_notebook_has_no_issues() {
+        assert_eq!(validate(&notebook(valid_notebook())), vec![]);
+    }
+
+    #[test]
+    fn test_flags_missing_required_fields() {
+        let mut value = valid_notebook();
+        value["metadata"]["kernelspec"]["name"] = json!("");
+        value["metadata"]["runt"] = json!({"uv": {"dependencies": "pandas"}});
+
+        let issues = validate(&notebook(value));
+        assert_eq!(
+            paths(&issues),
+            vec![
+                "metadata.kernelspec.name",
+                "metadata.runt.schema_version",
+                "metadata.runt.uv.dependencies",
+            ]
+        );
+        assert!(issues.iter().all(ValidationIssue::is_error));
+        assert_eq!(issues[0].message, "missing required field");
+        assert_eq!(issues[0].cell_id, None);
+    }
+
+    #[test]
+    fn test_flags_malformed_cell() {
+        let mut value = valid_notebook();
+        value["cells"][1] = json!({
+            "cell_type": "code",
+            "id": "b",
+            "metadata": {},
+            "execution_count": -3,
+            "source": ["x = 1", "print(x)\n"],
+            "outputs": [{"output_type": "stream", "name": "stdlog", "text": "1\n"}]
+        });
+        let mut nb = notebook(value);
+        // Parsing rejects duplicate ids, but edits can still introduce one
+        nb.cells.push(nb.cells[0].clone());
+
+        let issues = validate(&nb);
+        assert_eq!(
+            paths(&issues),
+            vec![
+                "cells[1].source[0]",
+                "cells[1].execution_count",
+                "cells[1].outputs[0].name",
+                "cells[2].id",
+            ]
+        );
+        assert!(issues[..3]
+            .iter()
+            .all(|issue| issue.cell_id.as_deref() == Some("b")));
+        assert_eq!(issues[0].severity, Severity::Warning);
+        assert!(issues[1].is_error());
+        assert_eq!(issues[3].cell_id.as_deref(), Some("a"));
+        assert!(issues[3].message.contains("duplicate"));
+    }
+
+    #[test]
+    fn test_flags_malformed_attachments() {
+        let mut value = valid_notebook();
+        value["cells"][1] = json!({
+            "cell_type": "markdown",
+            "id": "c",
+            "metadata": {},
+            "source": "![img](attachment:a.png)",
+            "attachments": {"a.png": "not a bundle"}
+        });
+
+        let issues = validate(&notebook(value));
+        assert_eq!(paths(&issues), vec!["cells[1].attachments"]);
+        assert_eq!(issues[0].cell_id.as_deref(), Some("c"));
+    }
+}
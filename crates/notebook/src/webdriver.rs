@@ -6,7 +6,9 @@
 //! via HTTP (the JS bridge fetches back to the server), enabling E2E tests
 //! to run without Docker or tauri-driver.
 //!
-//! Only the subset of WebDriver endpoints used by the E2E test suite is implemented.
+//! Only the subset of WebDriver endpoints used by the E2E test suite is implemented,
+//! plus one runt extension command, `POST /session/{id}/runt/cell/{cell_id}/execute`,
+//! which runs a cell and waits for its outputs.
 
 use axum::{
     extract::{Path, State},
@@ -15,6 +17,8 @@ use axum::{
     routing::{delete, get, post},
     Router,
 };
+use runtimed::notebook_doc::CellSnapshot;
+use runtimed::protocol::{NotebookRequest, NotebookResponse};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -113,6 +117,76 @@ impl WebDriverState {
         }
     }
 
+    /// Execute a cell through the main window's daemon connection and wait
+    /// for the kernel to go idle on it, returning the cell's outputs.
+    ///
+    /// Completion is the cell's execution timing changing in the synced doc:
+    /// the daemon records it on `status: idle`, after every output of the
+    /// run has been appended. A cell that prints nothing returns no outputs.
+    async fn execute_cell_and_wait(&self, cell_id: &str) -> Result<Value, String> {
+        let registry = self.app_handle.state::<crate::WindowNotebookRegistry>();
+        let notebook_sync = registry
+            .get("main")
+            .map_err(|e| e.message().to_string())?
+            .notebook_sync;
+        // Clone the handle so the window's commands aren't blocked while polling
+        let handle = notebook_sync
+            .lock()
+            .await
+            .clone()
+            .ok_or("not connected to daemon")?;
+
+        let execution_of = |cells: Vec<CellSnapshot>| {
+            cells
+                .into_iter()
+                .find(|cell| cell.id == cell_id)
+                .map(|cell| (cell.execution, cell.execution_count))
+        };
+        let (before, _) = execution_of(handle.get_cells().await.map_err(|e| e.to_string())?)
+            .ok_or_else(|| format!("no cell with id {}", cell_id))?;
+
+        let response = handle
+            .send_request(NotebookRequest::ExecuteCell {
+                cell_id: cell_id.to_string(),
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        if let NotebookResponse::Error { error } = response {
+            return Err(error);
+        }
+
+        let execution_count = loop {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            let cells = handle.get_cells().await.map_err(|e| e.to_string())?;
+            match execution_of(cells) {
+                Some((execution, count)) if execution.is_some() && execution != before => {
+                    break count;
+                }
+                Some(_) => {}
+                None => return Err(format!("cell {} was deleted", cell_id)),
+            }
+        };
+
+        let outputs = match handle
+            .send_request(NotebookRequest::GetCellOutputs {
+                cell_id: cell_id.to_string(),
+            })
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            NotebookResponse::CellOutputs { outputs, .. } => outputs,
+            NotebookResponse::Error { error } => return Err(error),
+            other => return Err(format!("unexpected response: {:?}", other)),
+        };
+
+        Ok(json!({
+            "cellId": cell_id,
+            "executionCount": serde_json::from_str::<Value>(&execution_count)
+                .unwrap_or(Value::Null),
+            "outputs": outputs,
+        }))
+    }
+
     /// Called by the Tauri IPC handler when the JS bridge sends a result
     pub async fn handle_result(&self, request_id: String, result: String) {
         let mut pending = self.pending.lock().await;
@@ -798,6 +872,37 @@ async fn get_active_element(
     }
 }
 
+/// POST /session/{session_id}/runt/cell/{cell_id}/execute — Execute a cell
+/// and wait for it to finish (runt extension command).
+///
+/// Body: `{ "timeout": ms }` (default 60000). Returns
+/// `{ cellId, executionCount, outputs }`, with outputs as manifests.
+async fn execute_cell(
+    State(state): State<SharedState>,
+    Path((_session_id, cell_id)): Path<(String, String)>,
+    Json(body): Json<Value>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let timeout_ms = body
+        .get("timeout")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(60_000);
+
+    let result = tokio::time::timeout(
+        std::time::Duration::from_millis(timeout_ms),
+        state.execute_cell_and_wait(&cell_id),
+    )
+    .await
+    .map_err(|_| {
+        w3c_error(
+            "timeout",
+            &format!("cell {} did not finish within {}ms", cell_id, timeout_ms),
+        )
+    })?
+    .map_err(|e| w3c_error("unknown error", &e))?;
+
+    Ok(w3c_value(result))
+}
+
 // ============================================================
 // Router and server startup
 // ============================================================
@@ -924,6 +1029,11 @@ fn build_router(state: SharedState) -> Router {
         // Script execution (W3C and legacy JSONWP)
         .route("/session/:session_id/execute/sync", post(execute_script))
         .route("/session/:session_id/execute", post(execute_script))
+        // runt extension: execute a cell and wait for its outputs
+        .route(
+            "/session/:session_id/runt/cell/:cell_id/execute",
+            post(execute_cell),
+        )
         .fallback(fallback_handler)
         .layer(cors)
         .with_state(state)
@@ -17,6 +17,11 @@
 use notebook::{conda_env, uv_env};
 use serial_test::serial;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::Listener;
+
+/// No app to report progress to; progress is only logged.
+const NO_APP: Option<&tauri::AppHandle> = None;
 
 // =============================================================================
 // UV Availability Detection Tests
@@ -80,7 +85,7 @@ async fn test_uv_environment_creation_with_no_deps() {
         requires_python: None,
     };
 
-    let result = uv_env::prepare_environment(&deps, None).await;
+    let result = uv_env::prepare_environment(&deps, None, NO_APP).await;
 
     assert!(
         result.is_ok(),
@@ -124,10 +129,10 @@ async fn test_uv_environment_uses_cache_correctly() {
     };
 
     // Create environment twice
-    let env1 = uv_env::prepare_environment(&deps, None)
+    let env1 = uv_env::prepare_environment(&deps, None, NO_APP)
         .await
         .expect("First prepare should succeed");
-    let env2 = uv_env::prepare_environment(&deps, None)
+    let env2 = uv_env::prepare_environment(&deps, None, NO_APP)
         .await
         .expect("Second prepare should succeed");
 
@@ -138,6 +143,46 @@ async fn test_uv_environment_uses_cache_correctly() {
     );
 }
 
+/// Test that UV environment creation reports its progress to the app.
+#[tokio::test]
+#[serial]
+async fn test_uv_environment_creation_emits_progress() {
+    if !uv_env::check_uv_available().await {
+        println!("Skipping test: uv not installed on this system");
+        return;
+    }
+
+    let app = tauri::test::mock_app();
+    let phases = Arc::new(Mutex::new(Vec::<String>::new()));
+    let sink = phases.clone();
+    app.listen_any("env:progress", move |event| {
+        let payload: serde_json::Value = serde_json::from_str(event.payload()).unwrap();
+        sink.lock()
+            .unwrap()
+            .push(payload["phase"].as_str().unwrap().to_string());
+    });
+
+    let deps = uv_env::NotebookDependencies {
+        dependencies: vec![],
+        requires_python: None,
+    };
+    // A unique env_id so the env is created rather than found in the cache
+    let env_id = format!("test-{}", uuid::Uuid::new_v4());
+    let env = uv_env::prepare_environment(&deps, Some(&env_id), Some(app.handle()))
+        .await
+        .expect("prepare_environment should succeed");
+
+    let phases = phases.lock().unwrap();
+    assert_eq!(phases.first().map(String::as_str), Some("starting"));
+    assert!(
+        phases.iter().any(|phase| phase == "creating_venv"),
+        "{phases:?}"
+    );
+    assert_eq!(phases.last().map(String::as_str), Some("ready"));
+
+    std::fs::remove_dir_all(&env.venv_path).ok();
+}
+
 // =============================================================================
 // Conda Environment Creation Tests (Happy Path)
 // =============================================================================
@@ -152,8 +197,10 @@ async fn test_conda_environment_creation_with_ipykernel() {
     let deps = conda_env::CondaDependencies {
         dependencies: vec![],
         channels: vec!["conda-forge".to_string()],
+        channel_priority: None,
         python: Some("3.11".to_string()),
         env_id: Some(env_id),
+        pip_dependencies: vec![],
     };
 
     // Note: We pass None for AppHandle since we're not emitting frontend events
@@ -195,8 +242,10 @@ async fn test_conda_environment_uses_cache_correctly() {
     let deps = conda_env::CondaDependencies {
         dependencies: vec![],
         channels: vec!["conda-forge".to_string()],
+        channel_priority: None,
         python: Some("3.11".to_string()),
         env_id: Some(env_id),
+        pip_dependencies: vec![],
     };
 
     // Create environment twice
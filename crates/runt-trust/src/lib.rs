@@ -12,12 +12,21 @@
 //! Key insight: we sign ONLY the dependency metadata, not cell contents. This means:
 //! - Editing code in cells: notebook stays trusted
 //! - External modification of dependencies: requires re-approval
+//!
+//! # Trusted directories
+//!
+//! For a project where every notebook is trusted, approving each one is
+//! tedious. A directory can be trusted instead: notebooks anywhere under it
+//! are trusted without a per-notebook signature. Each [`TrustScope`] record is
+//! signed with the same key, so a scope added by editing the store by hand is
+//! ignored. A scope never covers dependencies installed from outside a package
+//! index (URLs, VCS and local paths); those still need per-notebook approval.
 
 use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 type HmacSha256 = Hmac<Sha256>;
 
@@ -49,6 +58,21 @@ pub struct TrustInfo {
     pub conda_dependencies: Vec<String>,
     /// Conda channels configured.
     pub conda_channels: Vec<String>,
+    /// The trusted directory that made this notebook trusted, when it has no
+    /// valid signature of its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trusted_scope: Option<PathBuf>,
+}
+
+/// A directory whose notebooks are trusted without per-notebook approval.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrustScope {
+    /// Canonical path of the directory.
+    pub path: PathBuf,
+    /// When the directory was trusted (RFC 3339).
+    pub trusted_at: String,
+    /// HMAC over the path, made with the trust key.
+    pub signature: String,
 }
 
 /// Path to the trust key file.
@@ -96,6 +120,152 @@ pub fn get_or_create_trust_key() -> Result<[u8; 32], String> {
     }
 }
 
+/// Path to the trusted directory store, kept with the daemon's state in the
+/// cache directory.
+///
+/// In tests, this can be overridden by setting RUNT_TRUST_SCOPES_PATH environment variable.
+fn trust_scopes_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var("RUNT_TRUST_SCOPES_PATH") {
+        return Some(PathBuf::from(path));
+    }
+    dirs::cache_dir().map(|d| d.join("runt").join("trusted-directories.json"))
+}
+
+fn hmac_hex(key: &[u8; 32], content: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can accept any key size");
+    mac.update(content);
+    format!("hmac-sha256:{}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn verify_hmac(key: &[u8; 32], content: &[u8], signature: &str) -> bool {
+    let Some(expected_hex) = signature.strip_prefix("hmac-sha256:") else {
+        return false;
+    };
+    let Ok(expected_bytes) = hex::decode(expected_hex) else {
+        return false;
+    };
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can accept any key size");
+    mac.update(content);
+    // Constant-time comparison
+    mac.verify_slice(&expected_bytes).is_ok()
+}
+
+fn scope_content(path: &Path) -> Vec<u8> {
+    format!("trust-scope:{}", path.to_string_lossy()).into_bytes()
+}
+
+fn read_trust_scopes(store: &Path) -> Result<Vec<TrustScope>, String> {
+    match std::fs::read_to_string(store) {
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse trusted directories: {}", e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to read trusted directories: {}", e)),
+    }
+}
+
+fn write_trust_scopes(store: &Path, scopes: &[TrustScope]) -> Result<(), String> {
+    if let Some(parent) = store.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(scopes).unwrap_or_default();
+    std::fs::write(store, content)
+        .map_err(|e| format!("Failed to write trusted directories: {}", e))
+}
+
+fn scopes_store() -> Result<PathBuf, String> {
+    trust_scopes_path().ok_or_else(|| "Could not determine cache directory".to_string())
+}
+
+/// Trust every notebook under `dir`. Trusting a directory again re-signs its
+/// record.
+///
+/// A filesystem root or the home directory can't be trusted, since that
+/// would vouch for nearly every notebook on the machine.
+pub fn trust_directory(dir: &Path) -> Result<TrustScope, String> {
+    let path = dir
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", dir.display(), e))?;
+    if !path.is_dir() {
+        return Err(format!("{} is not a directory", path.display()));
+    }
+    let home = dirs::home_dir().and_then(|home| home.canonicalize().ok());
+    if path.parent().is_none() || home.as_deref() == Some(path.as_path()) {
+        return Err(format!(
+            "Refusing to trust {}: it covers too many notebooks",
+            path.display()
+        ));
+    }
+
+    let key = get_or_create_trust_key()?;
+    let scope = TrustScope {
+        signature: hmac_hex(&key, &scope_content(&path)),
+        path,
+        trusted_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let store = scopes_store()?;
+    let mut scopes = read_trust_scopes(&store)?;
+    scopes.retain(|s| s.path != scope.path);
+    scopes.push(scope.clone());
+    write_trust_scopes(&store, &scopes)?;
+    Ok(scope)
+}
+
+/// Stop trusting notebooks under `dir`. Returns whether a scope was removed.
+///
+/// Notebooks that were approved individually stay trusted.
+pub fn revoke_directory_trust(dir: &Path) -> Result<bool, String> {
+    // The directory may have been deleted since it was trusted
+    let path = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+
+    let store = scopes_store()?;
+    let mut scopes = read_trust_scopes(&store)?;
+    let before = scopes.len();
+    scopes.retain(|s| s.path != path);
+    if scopes.len() == before {
+        return Ok(false);
+    }
+    write_trust_scopes(&store, &scopes)?;
+    Ok(true)
+}
+
+/// All trusted directories with a valid signature.
+pub fn trusted_directories() -> Result<Vec<TrustScope>, String> {
+    let key = get_or_create_trust_key()?;
+    let scopes = read_trust_scopes(&scopes_store()?)?;
+    Ok(scopes
+        .into_iter()
+        .filter(|s| verify_hmac(&key, &scope_content(&s.path), &s.signature))
+        .collect())
+}
+
+/// The trusted directory covering `notebook_path`, if any.
+pub fn trust_scope_for(notebook_path: &Path) -> Result<Option<TrustScope>, String> {
+    let Ok(path) = notebook_path.canonicalize() else {
+        // Unsaved notebooks can't be under a trusted directory
+        return Ok(None);
+    };
+    Ok(trusted_directories()?
+        .into_iter()
+        .find(|scope| path.starts_with(&scope.path)))
+}
+
+/// Whether a dependency is installed from outside a package index: a URL,
+/// a VCS checkout or a local path. A trusted directory doesn't vouch for
+/// these, since they can point anywhere.
+pub fn is_direct_reference(dependency: &str) -> bool {
+    let spec = dependency.trim();
+    spec.contains("://")
+        || spec.contains(" @ ")
+        || spec.starts_with("git+")
+        || spec.starts_with("file:")
+        || spec.starts_with('/')
+        || spec.starts_with("./")
+        || spec.starts_with("../")
+        || spec.starts_with('~')
+}
+
 /// Extract the dependency-related fields from notebook metadata for signing.
 ///
 /// We sign a canonical JSON representation of:
@@ -149,13 +319,7 @@ pub fn get_conda_metadata(
 /// Compute HMAC signature over dependency metadata.
 pub fn compute_signature(key: &[u8; 32], metadata: &HashMap<String, serde_json::Value>) -> String {
     let content = extract_signable_content(metadata);
-
-    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can accept any key size");
-    mac.update(content.as_bytes());
-    let result = mac.finalize();
-
-    // Encode as hex
-    format!("hmac-sha256:{}", hex::encode(result.into_bytes()))
+    hmac_hex(key, content.as_bytes())
 }
 
 /// Verify a signature against the current dependency metadata.
@@ -164,26 +328,8 @@ pub fn verify_signature(
     metadata: &HashMap<String, serde_json::Value>,
     signature: &str,
 ) -> bool {
-    // Parse the signature format
-    let expected_prefix = "hmac-sha256:";
-    if !signature.starts_with(expected_prefix) {
-        return false;
-    }
-
-    let expected_hex = &signature[expected_prefix.len()..];
-    let expected_bytes = match hex::decode(expected_hex) {
-        Ok(b) => b,
-        Err(_) => return false,
-    };
-
-    // Compute current signature
     let content = extract_signable_content(metadata);
-
-    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can accept any key size");
-    mac.update(content.as_bytes());
-
-    // Constant-time comparison
-    mac.verify_slice(&expected_bytes).is_ok()
+    verify_hmac(key, content.as_bytes(), signature)
 }
 
 /// Check if a notebook has any dependencies configured.
@@ -211,9 +357,14 @@ pub fn has_dependencies(metadata: &HashMap<String, serde_json::Value>) -> bool {
 
 /// Verify the trust status of a notebook.
 ///
+/// A notebook without a valid signature of its own is still trusted when
+/// `notebook_path` is under a trusted directory, unless one of its
+/// dependencies is a direct reference (see [`is_direct_reference`]).
+///
 /// Returns the trust status and information about what dependencies would be installed.
 pub fn verify_notebook_trust(
     metadata: &HashMap<String, serde_json::Value>,
+    notebook_path: Option<&Path>,
 ) -> Result<TrustInfo, String> {
     // Extract dependencies for the response (check new paths first, then legacy)
     let uv_dependencies: Vec<String> = get_uv_metadata(metadata)
@@ -257,6 +408,7 @@ pub fn verify_notebook_trust(
             uv_dependencies,
             conda_dependencies,
             conda_channels,
+            trusted_scope: None,
         });
     }
 
@@ -269,7 +421,7 @@ pub fn verify_notebook_trust(
         .and_then(|v| v.get("trust_signature"))
         .and_then(|v| v.as_str());
 
-    let status = match signature {
+    let mut status = match signature {
         None => TrustStatus::Untrusted,
         Some(sig) => {
             if verify_signature(&key, metadata, sig) {
@@ -280,11 +432,32 @@ pub fn verify_notebook_trust(
         }
     };
 
+    // Fall back to a trusted directory
+    let mut trusted_scope = None;
+    if status != TrustStatus::Trusted {
+        let direct_reference = uv_dependencies
+            .iter()
+            .chain(&conda_dependencies)
+            .any(|dep| is_direct_reference(dep));
+        if let (Some(path), false) = (notebook_path, direct_reference) {
+            // An unreadable store only means no directory vouches for the notebook
+            let scope = trust_scope_for(path).unwrap_or_else(|e| {
+                log::warn!("Ignoring trusted directories: {}", e);
+                None
+            });
+            if let Some(scope) = scope {
+                status = TrustStatus::Trusted;
+                trusted_scope = Some(scope.path);
+            }
+        }
+    }
+
     Ok(TrustInfo {
         status,
         uv_dependencies,
         conda_dependencies,
         conda_channels,
+        trusted_scope,
     })
 }
 
@@ -315,6 +488,21 @@ mod tests {
     /// Clean up test trust key path.
     fn teardown_test_trust_key() {
         std::env::remove_var("RUNT_TRUST_KEY_PATH");
+        std::env::remove_var("RUNT_TRUST_SCOPES_PATH");
+    }
+
+    /// Set up a trust key and trusted directory store, plus a project with a
+    /// notebook in a subdirectory. Returns the temp dir, project and notebook.
+    fn setup_test_project() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let temp = setup_test_trust_key();
+        let scopes_path = temp.path().join("trusted-directories.json");
+        std::env::set_var("RUNT_TRUST_SCOPES_PATH", scopes_path.to_str().unwrap());
+
+        let project = temp.path().join("project");
+        let notebook = project.join("analysis").join("explore.ipynb");
+        std::fs::create_dir_all(notebook.parent().unwrap()).unwrap();
+        std::fs::write(&notebook, "{}").unwrap();
+        (temp, project, notebook)
     }
 
     fn make_test_metadata(
@@ -348,7 +536,7 @@ mod tests {
     #[test]
     fn test_no_dependencies_is_trusted() {
         let metadata = HashMap::new();
-        let info = verify_notebook_trust(&metadata).unwrap();
+        let info = verify_notebook_trust(&metadata, None).unwrap();
         assert_eq!(info.status, TrustStatus::NoDependencies);
     }
 
@@ -357,7 +545,7 @@ mod tests {
     fn test_unsigned_notebook_is_untrusted() {
         let _temp = setup_test_trust_key();
         let metadata = make_test_metadata(vec!["pandas"], vec![]);
-        let info = verify_notebook_trust(&metadata).unwrap();
+        let info = verify_notebook_trust(&metadata, None).unwrap();
         teardown_test_trust_key();
         assert_eq!(info.status, TrustStatus::Untrusted);
     }
@@ -381,7 +569,7 @@ mod tests {
         );
 
         // Verify it's now trusted
-        let info = verify_notebook_trust(&signed_metadata).unwrap();
+        let info = verify_notebook_trust(&signed_metadata, None).unwrap();
         teardown_test_trust_key();
         assert_eq!(info.status, TrustStatus::Trusted);
     }
@@ -413,7 +601,7 @@ mod tests {
         );
 
         // Verify signature is now invalid
-        let info = verify_notebook_trust(&signed_metadata).unwrap();
+        let info = verify_notebook_trust(&signed_metadata, None).unwrap();
         teardown_test_trust_key();
         assert_eq!(info.status, TrustStatus::SignatureInvalid);
     }
@@ -436,6 +624,7 @@ mod tests {
             uv_dependencies: vec![],
             conda_dependencies: vec![],
             conda_channels: vec![],
+            trusted_scope: None,
         };
 
         let json = serde_json::to_value(&info).unwrap();
@@ -444,4 +633,98 @@ mod tests {
         assert_eq!(json["status"], "no_dependencies");
         assert!(json["status"].is_string());
     }
+
+    #[test]
+    #[serial]
+    fn test_notebook_inherits_directory_trust() {
+        let (temp, project, notebook) = setup_test_project();
+        let outside = temp.path().join("elsewhere.ipynb");
+        std::fs::write(&outside, "{}").unwrap();
+        let metadata = make_test_metadata(vec!["pandas"], vec![]);
+
+        let before = verify_notebook_trust(&metadata, Some(&notebook)).unwrap();
+        let scope = trust_directory(&project).unwrap();
+        let inside = verify_notebook_trust(&metadata, Some(&notebook)).unwrap();
+        let elsewhere = verify_notebook_trust(&metadata, Some(&outside)).unwrap();
+        let git_dep = make_test_metadata(vec!["git+https://example.com/pkg.git"], vec![]);
+        let direct = verify_notebook_trust(&git_dep, Some(&notebook)).unwrap();
+        teardown_test_trust_key();
+
+        assert_eq!(before.status, TrustStatus::Untrusted);
+        assert_eq!(scope.path, project.canonicalize().unwrap());
+        assert_eq!(inside.status, TrustStatus::Trusted);
+        assert_eq!(inside.trusted_scope, Some(scope.path));
+        assert_eq!(elsewhere.status, TrustStatus::Untrusted);
+        assert_eq!(direct.status, TrustStatus::Untrusted);
+    }
+
+    #[test]
+    #[serial]
+    fn test_revoke_directory_trust() {
+        let (_temp, project, notebook) = setup_test_project();
+        let metadata = make_test_metadata(vec!["pandas"], vec![]);
+
+        trust_directory(&project).unwrap();
+        let revoked = revoke_directory_trust(&project).unwrap();
+        let revoked_again = revoke_directory_trust(&project).unwrap();
+        let info = verify_notebook_trust(&metadata, Some(&notebook)).unwrap();
+        let remaining = trusted_directories().unwrap();
+        teardown_test_trust_key();
+
+        assert!(revoked);
+        assert!(!revoked_again);
+        assert_eq!(info.status, TrustStatus::Untrusted);
+        assert_eq!(info.trusted_scope, None);
+        assert!(remaining.is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_unsigned_scope_is_ignored() {
+        let (_temp, project, notebook) = setup_test_project();
+        let scope = TrustScope {
+            path: project.canonicalize().unwrap(),
+            trusted_at: "2026-01-01T00:00:00Z".to_string(),
+            signature: "hmac-sha256:00".to_string(),
+        };
+        write_trust_scopes(&scopes_store().unwrap(), &[scope]).unwrap();
+
+        let metadata = make_test_metadata(vec!["pandas"], vec![]);
+        let info = verify_notebook_trust(&metadata, Some(&notebook)).unwrap();
+        teardown_test_trust_key();
+
+        assert_eq!(info.status, TrustStatus::Untrusted);
+    }
+
+    #[test]
+    #[serial]
+    fn test_corrupt_scope_store_is_not_trusted() {
+        let (_temp, project, notebook) = setup_test_project();
+        trust_directory(&project).unwrap();
+        std::fs::write(scopes_store().unwrap(), "{not json").unwrap();
+
+        let metadata = make_test_metadata(vec!["pandas"], vec![]);
+        let info = verify_notebook_trust(&metadata, Some(&notebook));
+        teardown_test_trust_key();
+
+        let info = info.unwrap();
+        assert_eq!(info.status, TrustStatus::Untrusted);
+        assert_eq!(info.trusted_scope, None);
+    }
+
+    #[test]
+    #[serial]
+    fn test_root_and_home_cannot_be_trusted() {
+        let (_temp, _project, _notebook) = setup_test_project();
+        let root = trust_directory(Path::new("/"));
+        let home = dirs::home_dir().map(|home| trust_directory(&home));
+        let remaining = trusted_directories().unwrap();
+        teardown_test_trust_key();
+
+        assert!(root.is_err());
+        if let Some(home) = home {
+            assert!(home.is_err());
+        }
+        assert!(remaining.is_empty());
+    }
 }
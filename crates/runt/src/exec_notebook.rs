@@ -0,0 +1,528 @@
+//! `runt exec-notebook`: run a notebook headlessly, papermill style.
+//!
+//! Values passed with `--param name=value` go into a new code cell tagged
+//! `injected-parameters`, inserted right after the cell tagged `parameters`
+//! (or at the top if there is none) so they override its defaults. Cells
+//! then run in order on a fresh kernel, and the notebook with its outputs is
+//! written to the output path, including when a cell fails.
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use jupyter_protocol::{
+    ExecuteRequest, ExecutionState, JupyterMessage, JupyterMessageContent, KernelInfoRequest,
+    ReplyStatus,
+};
+use kernel_client::KernelClient;
+use runtimed::cell_tags::{INJECTED_PARAMETERS, PARAMETERS, RAISES_EXCEPTION, SKIP_EXECUTION};
+use runtimed::kernel_manager::message_content_to_nbformat;
+use runtimelib::{
+    create_client_iopub_connection, create_client_shell_connection_with_identity, find_kernelspec,
+    peer_identity_for_session, ClientIoPubConnection, ClientShellConnection, ConnectionInfo,
+};
+use serde_json::{json, Value};
+
+/// How long to wait for a new kernel to answer `kernel_info_request`.
+const KERNEL_READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Parse a `name=value` parameter.
+///
+/// The value is read as JSON when it parses (numbers, `true`/`false`,
+/// `null`, quoted strings, lists and objects), and Python's `True`, `False`
+/// and `None` are accepted too. Anything else is a plain string.
+pub fn parse_param(arg: &str) -> Result<(String, Value)> {
+    let (name, raw) = arg
+        .split_once('=')
+        .with_context(|| format!("Parameter '{}' should be name=value", arg))?;
+    let name = name.trim();
+    let is_identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if !is_identifier {
+        anyhow::bail!("Parameter name '{}' is not a valid identifier", name);
+    }
+    let value = match raw {
+        "True" => Value::Bool(true),
+        "False" => Value::Bool(false),
+        "None" => Value::Null,
+        _ => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+    };
+    Ok((name.to_string(), value))
+}
+
+/// Render a JSON value as a Python literal.
+pub fn python_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "None".to_string(),
+        Value::Bool(true) => "True".to_string(),
+        Value::Bool(false) => "False".to_string(),
+        Value::Number(n) => n.to_string(),
+        // JSON string escapes are valid in Python string literals
+        Value::String(s) => Value::String(s.clone()).to_string(),
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(python_literal).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Object(entries) => {
+            let entries: Vec<String> = entries
+                .iter()
+                .map(|(k, v)| format!("{}: {}", Value::String(k.clone()), python_literal(v)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+    }
+}
+
+pub fn has_tag(cell: &Value, tag: &str) -> bool {
+    cell["metadata"]["tags"]
+        .as_array()
+        .is_some_and(|tags| tags.iter().any(|t| t == tag))
+}
+
+/// Insert the `injected-parameters` cell after the `parameters` cell,
+/// replacing one left by an earlier run. Does nothing without parameters.
+pub fn inject_parameters(notebook: &mut Value, params: &[(String, Value)]) -> Result<()> {
+    if params.is_empty() {
+        return Ok(());
+    }
+    let cells = notebook["cells"]
+        .as_array_mut()
+        .context("Notebook has no cells list")?;
+    cells.retain(|cell| !has_tag(cell, INJECTED_PARAMETERS));
+    let index = cells
+        .iter()
+        .position(|cell| has_tag(cell, PARAMETERS))
+        .map_or(0, |i| i + 1);
+
+    let mut source = vec!["# Parameters\n".to_string()];
+    source.extend(
+        params
+            .iter()
+            .map(|(name, value)| format!("{} = {}\n", name, python_literal(value))),
+    );
+    cells.insert(
+        index,
+        json!({
+            "cell_type": "code",
+            "id": uuid::Uuid::new_v4().to_string(),
+            "metadata": { "tags": [INJECTED_PARAMETERS] },
+            "execution_count": null,
+            "outputs": [],
+            "source": source,
+        }),
+    );
+    Ok(())
+}
+
+/// The kernelspec to run a notebook with: `--kernel`, else the notebook's
+/// own kernelspec, else `python3`.
+fn kernel_name(notebook: &Value, kernel: Option<&str>) -> String {
+    kernel
+        .or_else(|| notebook["metadata"]["kernelspec"]["name"].as_str())
+        .unwrap_or("python3")
+        .to_string()
+}
+
+fn notebook_language(notebook: &Value) -> Option<&str> {
+    notebook["metadata"]["kernelspec"]["language"]
+        .as_str()
+        .or_else(|| notebook["metadata"]["language_info"]["name"].as_str())
+}
+
+pub async fn read_notebook(path: &Path) -> Result<Value> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("{} is not a notebook", path.display()))
+}
+
+/// Run the notebook at `path` with `params` injected and write the executed
+/// notebook to `output`.
+pub async fn exec_notebook(
+    path: &Path,
+    params: &[(String, Value)],
+    output: &Path,
+    kernel: Option<&str>,
+) -> Result<()> {
+    let mut notebook = read_notebook(path).await?;
+    if !params.is_empty() {
+        let language = notebook_language(&notebook).unwrap_or("python");
+        if language != "python" {
+            anyhow::bail!(
+                "Parameters can only be injected into Python notebooks, not {}",
+                language
+            );
+        }
+    }
+    inject_parameters(&mut notebook, params)?;
+
+    let result = execute(&mut notebook, kernel).await;
+    let serialized = serde_json::to_string_pretty(&notebook)? + "\n";
+    tokio::fs::write(output, serialized)
+        .await
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    result
+}
+
+/// Run the notebook's code cells on a fresh kernel, replacing their outputs.
+/// On error the notebook keeps the outputs recorded up to the failing cell.
+pub async fn execute(notebook: &mut Value, kernel: Option<&str>) -> Result<()> {
+    let name = kernel_name(notebook, kernel);
+    let kernelspec = find_kernelspec(&name).await?;
+    let mut client = KernelClient::start_from_kernelspec(kernelspec).await?;
+    let result = execute_on(client.connection_info(), client.session_id(), notebook).await;
+    client.shutdown(false).await?;
+    result
+}
+
+/// Run the notebook's code cells on an already running kernel.
+pub(crate) async fn execute_on(
+    connection_info: &ConnectionInfo,
+    session_id: &str,
+    notebook: &mut Value,
+) -> Result<()> {
+    let result = run_cells(connection_info, session_id, notebook).await;
+    strip_transient(notebook);
+    result
+}
+
+/// Run every code cell in order, recording outputs and execution counts.
+/// Stops at the first error, unless the cell is tagged `raises-exception`.
+async fn run_cells(
+    connection_info: &ConnectionInfo,
+    session_id: &str,
+    notebook: &mut Value,
+) -> Result<()> {
+    let identity = peer_identity_for_session(session_id)?;
+    let mut shell =
+        create_client_shell_connection_with_identity(connection_info, session_id, identity).await?;
+    let mut iopub = create_client_iopub_connection(connection_info, "", session_id).await?;
+    tokio::time::timeout(KERNEL_READY_TIMEOUT, wait_for_kernel(&mut shell))
+        .await
+        .context("Kernel did not start in time")??;
+
+    let cells = notebook["cells"]
+        .as_array_mut()
+        .context("Notebook has no cells list")?;
+    for cell in cells.iter_mut().filter(|cell| cell["cell_type"] == "code") {
+        cell["outputs"] = json!([]);
+        cell["execution_count"] = Value::Null;
+    }
+
+    for index in 0..cells.len() {
+        let cell = &cells[index];
+        if cell["cell_type"] != "code" || has_tag(cell, SKIP_EXECUTION) {
+            continue;
+        }
+        let code = match &cell["source"] {
+            Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+            Value::String(source) => source.clone(),
+            _ => String::new(),
+        };
+        let expects_error = has_tag(cell, RAISES_EXCEPTION);
+
+        let mut request = ExecuteRequest::new(code);
+        request.stop_on_error = !expects_error;
+        let message: JupyterMessage = request.into();
+        let msg_id = message.header.msg_id.clone();
+        shell.send(message).await?;
+
+        let status = collect_execution(&mut shell, &mut iopub, &msg_id, cells, index).await?;
+        if status != ReplyStatus::Ok && !expects_error {
+            anyhow::bail!("Cell {} raised an error; stopped there", index + 1);
+        }
+    }
+    Ok(())
+}
+
+async fn wait_for_kernel(shell: &mut ClientShellConnection) -> Result<()> {
+    let message: JupyterMessage = KernelInfoRequest::default().into();
+    let msg_id = message.header.msg_id.clone();
+    shell.send(message).await?;
+    loop {
+        let reply = shell.read().await?;
+        if is_reply_to(&reply, &msg_id) {
+            return Ok(());
+        }
+    }
+}
+
+fn is_reply_to(message: &JupyterMessage, msg_id: &str) -> bool {
+    message.parent_header.as_ref().map(|h| h.msg_id.as_str()) == Some(msg_id)
+}
+
+/// Read messages for the execute request `msg_id` into `cells[index]` until
+/// both its `execute_reply` and its `status: idle` have arrived.
+async fn collect_execution(
+    shell: &mut ClientShellConnection,
+    iopub: &mut ClientIoPubConnection,
+    msg_id: &str,
+    cells: &mut [Value],
+    index: usize,
+) -> Result<ReplyStatus> {
+    let mut status = None;
+    let mut idle = false;
+    let mut clear_on_next_output = false;
+    while status.is_none() || !idle {
+        tokio::select! {
+            reply = shell.read(), if status.is_none() => {
+                let reply = reply?;
+                if !is_reply_to(&reply, msg_id) {
+                    continue;
+                }
+                if let JupyterMessageContent::ExecuteReply(reply) = reply.content {
+                    cells[index]["execution_count"] = json!(reply.execution_count.0);
+                    status = Some(reply.status);
+                }
+            }
+            message = iopub.read(), if !idle => {
+                let message = message?;
+                if !is_reply_to(&message, msg_id) {
+                    continue;
+                }
+                match &message.content {
+                    JupyterMessageContent::Status(s) => {
+                        idle = s.execution_state == ExecutionState::Idle;
+                    }
+                    JupyterMessageContent::ClearOutput(clear) => {
+                        if clear.wait {
+                            clear_on_next_output = true;
+                        } else {
+                            cells[index]["outputs"] = json!([]);
+                        }
+                    }
+                    JupyterMessageContent::UpdateDisplayData(update) => {
+                        if let Some(display_id) = &update.transient.display_id {
+                            update_display(cells, display_id, &update.data, &update.metadata);
+                        }
+                    }
+                    content => {
+                        if let Some(output) = message_content_to_nbformat(content) {
+                            if std::mem::take(&mut clear_on_next_output) {
+                                cells[index]["outputs"] = json!([]);
+                            }
+                            if let Some(outputs) = cells[index]["outputs"].as_array_mut() {
+                                outputs.push(output);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(status.unwrap_or_default())
+}
+
+/// Replace the data of every output displayed with `display_id`.
+fn update_display(
+    cells: &mut [Value],
+    display_id: &str,
+    data: &jupyter_protocol::Media,
+    metadata: &serde_json::Map<String, Value>,
+) {
+    for cell in cells.iter_mut() {
+        let Some(outputs) = cell["outputs"].as_array_mut() else {
+            continue;
+        };
+        for output in outputs {
+            if output["transient"]["display_id"] == display_id {
+                output["data"] = json!(data);
+                output["metadata"] = Value::Object(metadata.clone());
+            }
+        }
+    }
+}
+
+/// Drop the `transient` display ids kept while running; nbformat doesn't
+/// allow them in saved outputs.
+fn strip_transient(notebook: &mut Value) {
+    let Some(cells) = notebook["cells"].as_array_mut() else {
+        return;
+    };
+    for output in cells
+        .iter_mut()
+        .filter_map(|cell| cell["outputs"].as_array_mut())
+        .flatten()
+    {
+        if let Some(output) = output.as_object_mut() {
+            output.remove("transient");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runtimed::mock_kernel::{MockExecution, MockKernel, MockScript};
+
+    fn notebook(cells: Vec<Value>) -> Value {
+        json!({
+            "cells": cells,
+            "metadata": {
+                "kernelspec": { "name": "python3", "language": "python", "display_name": "Python 3" }
+            },
+            "nbformat": 4,
+            "nbformat_minor": 5,
+        })
+    }
+
+    fn code_cell(source: &str, tags: &[&str]) -> Value {
+        json!({
+            "cell_type": "code",
+            "id": uuid::Uuid::new_v4().to_string(),
+            "metadata": { "tags": tags },
+            "execution_count": null,
+            "outputs": [],
+            "source": source,
+        })
+    }
+
+    fn source(cell: &Value) -> String {
+        cell["source"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|line| line.as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_param_types() {
+        let params: Vec<(String, Value)> = [
+            "alpha=0.5",
+            "n=3",
+            "name=foo",
+            "quoted=\"1\"",
+            "flag=true",
+            "py_flag=False",
+            "nothing=None",
+            "sizes=[1, 2]",
+            "opts={\"a\": null}",
+        ]
+        .into_iter()
+        .map(|arg| parse_param(arg).unwrap())
+        .collect();
+        let literals: Vec<String> = params
+            .iter()
+            .map(|(name, value)| format!("{} = {}", name, python_literal(value)))
+            .collect();
+        assert_eq!(
+            literals,
+            vec![
+                "alpha = 0.5",
+                "n = 3",
+                "name = \"foo\"",
+                "quoted = \"1\"",
+                "flag = True",
+                "py_flag = False",
+                "nothing = None",
+                "sizes = [1, 2]",
+                "opts = {\"a\": None}",
+            ]
+        );
+
+        assert!(parse_param("alpha").is_err());
+        assert!(parse_param("2fast=1").is_err());
+        assert!(parse_param("a-b=1").is_err());
+        // Only the first = separates the name
+        assert_eq!(
+            parse_param("expr=a=b").unwrap().1,
+            Value::String("a=b".to_string())
+        );
+    }
+
+    #[test]
+    fn test_injected_cell_follows_parameters_cell() {
+        let mut nb = notebook(vec![
+            code_cell("import math", &[]),
+            code_cell("alpha = 0.1\nname = \"default\"", &[PARAMETERS]),
+            code_cell("print(alpha, name)", &[]),
+        ]);
+        let params = vec![
+            parse_param("alpha=0.5").unwrap(),
+            parse_param("name=foo").unwrap(),
+        ];
+        inject_parameters(&mut nb, &params).unwrap();
+
+        let cells = nb["cells"].as_array().unwrap();
+        assert_eq!(cells.len(), 4);
+        assert!(has_tag(&cells[2], INJECTED_PARAMETERS));
+        assert_eq!(
+            source(&cells[2]),
+            "# Parameters\nalpha = 0.5\nname = \"foo\"\n"
+        );
+        assert_eq!(cells[3]["source"], "print(alpha, name)");
+
+        // A second run replaces the injected cell instead of stacking another
+        inject_parameters(&mut nb, &[parse_param("alpha=1").unwrap()]).unwrap();
+        let cells = nb["cells"].as_array().unwrap();
+        assert_eq!(cells.len(), 4);
+        assert_eq!(source(&cells[2]), "# Parameters\nalpha = 1\n");
+    }
+
+    #[test]
+    fn test_injected_cell_without_parameters_cell_goes_first() {
+        let mut nb = notebook(vec![code_cell("print(alpha)", &[])]);
+        inject_parameters(&mut nb, &[parse_param("alpha=2").unwrap()]).unwrap();
+        assert!(has_tag(&nb["cells"][0], INJECTED_PARAMETERS));
+
+        // Nothing to inject leaves the notebook alone
+        let mut untouched = notebook(vec![code_cell("x = 1", &[PARAMETERS])]);
+        inject_parameters(&mut untouched, &[]).unwrap();
+        assert_eq!(untouched["cells"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_kernel_name() {
+        let nb = notebook(vec![]);
+        assert_eq!(kernel_name(&nb, None), "python3");
+        assert_eq!(kernel_name(&nb, Some("other")), "other");
+        assert_eq!(kernel_name(&json!({"metadata": {}}), None), "python3");
+    }
+
+    #[tokio::test]
+    async fn test_injected_parameters_override_defaults() {
+        let mock = MockKernel::start(MockScript::new().on_execute(
+            "print(repr(alpha), repr(name), repr(flag))",
+            MockExecution::new().stdout("0.5 'foo' True\n"),
+        ))
+        .await
+        .unwrap();
+        let mut nb = notebook(vec![
+            code_cell("alpha = 0.1\nname = 'default'\nflag = False", &[PARAMETERS]),
+            code_cell("print(repr(alpha), repr(name), repr(flag))", &[]),
+        ]);
+        let params = vec![
+            parse_param("alpha=0.5").unwrap(),
+            parse_param("name=foo").unwrap(),
+            parse_param("flag=true").unwrap(),
+        ];
+        inject_parameters(&mut nb, &params).unwrap();
+
+        let session_id = uuid::Uuid::new_v4().to_string();
+        execute_on(mock.connection_info(), &session_id, &mut nb)
+            .await
+            .unwrap();
+
+        let cells = nb["cells"].as_array().unwrap();
+        assert_eq!(cells.len(), 3);
+        assert!(has_tag(&cells[1], INJECTED_PARAMETERS));
+        assert_eq!(cells[2]["outputs"][0]["text"], "0.5 'foo' True\n");
+        assert!(cells[2]["execution_count"].is_number());
+
+        let executed: Vec<String> = mock
+            .received()
+            .into_iter()
+            .filter_map(|message| match message.content {
+                JupyterMessageContent::ExecuteRequest(request) => Some(request.code),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(executed.len(), 3);
+        assert_eq!(executed[1], source(&cells[1]));
+        assert_eq!(executed[2], "print(repr(alpha), repr(name), repr(flag))");
+    }
+}
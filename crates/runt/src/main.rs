@@ -5,9 +5,11 @@ use jupyter_protocol::{JupyterMessage, JupyterMessageContent, KernelInfoRequest}
 use serde::Serialize;
 use std::time::Duration;
 use tabled::{settings::Style, Table, Tabled};
-mod kernel_client;
+mod exec_notebook;
+mod rpc;
+mod test_notebook;
 
-use crate::kernel_client::KernelClient;
+use kernel_client::KernelClient;
 use runtimelib::{
     create_client_heartbeat_connection, create_client_shell_connection_with_identity,
     find_kernelspec, peer_identity_for_session, runtime_dir, ConnectionInfo,
@@ -55,6 +57,32 @@ struct UnifiedKernelInfo {
     connection_file: Option<PathBuf>,
     #[serde(skip_serializing_if = "Option::is_none")]
     env_source: Option<String>,
+    /// Cell the daemon kernel is running (daemon kernels only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    executing_cell: Option<String>,
+    /// Cells queued behind the executing one (daemon kernels only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue_depth: Option<usize>,
+}
+
+impl UnifiedKernelInfo {
+    /// Unified entry for a daemon room's kernel.
+    fn from_room(room: runtimed::protocol::RoomInfo) -> Self {
+        UnifiedKernelInfo {
+            name: room
+                .kernel_type
+                .clone()
+                .unwrap_or_else(|| "unknown".to_string()),
+            language: room.kernel_type,
+            status: room.kernel_status.unwrap_or_else(|| "unknown".to_string()),
+            source: "runtimed".to_string(),
+            notebook: Some(room.notebook_id),
+            connection_file: None,
+            env_source: room.env_source,
+            executing_cell: room.executing_cell,
+            queue_depth: Some(room.queue_depth),
+        }
+    }
 }
 
 #[derive(Tabled)]
@@ -65,6 +93,8 @@ struct KernelTableRow {
     language: String,
     #[tabled(rename = "STATUS")]
     status: String,
+    #[tabled(rename = "QUEUE")]
+    queue: String,
     #[tabled(rename = "SOURCE")]
     source: String,
     #[tabled(rename = "NOTEBOOK")]
@@ -77,6 +107,10 @@ impl From<&UnifiedKernelInfo> for KernelTableRow {
             name: info.name.clone(),
             language: info.language.clone().unwrap_or_else(|| "-".to_string()),
             status: info.status.clone(),
+            queue: info
+                .queue_depth
+                .map(|depth| depth.to_string())
+                .unwrap_or_else(|| "-".to_string()),
             source: info.source.clone(),
             notebook: info
                 .notebook
@@ -130,6 +164,9 @@ enum Commands {
         /// Show verbose output including port numbers
         #[arg(short, long)]
         verbose: bool,
+        /// Keep running and report connection-file kernels as they start and stop
+        #[arg(short, long)]
+        watch: bool,
     },
     /// Open the notebook application
     Notebook {
@@ -154,6 +191,33 @@ enum Commands {
         /// Output in JSON format
         #[arg(long)]
         json: bool,
+        /// Also list recently opened notebooks that are no longer open
+        #[arg(long)]
+        recent: bool,
+    },
+    /// Serve JSON-RPC requests on stdin/stdout for editor integrations
+    Rpc,
+    /// Run a notebook headlessly with injected parameters (papermill-style)
+    ExecNotebook {
+        /// Path to the notebook to run
+        path: PathBuf,
+        /// Parameter as name=value, injected after the `parameters` cell (repeatable)
+        #[arg(long = "param", value_name = "NAME=VALUE")]
+        params: Vec<String>,
+        /// Where to write the executed notebook
+        #[arg(long, short)]
+        output: PathBuf,
+        /// Kernelspec to use instead of the notebook's own
+        #[arg(long)]
+        kernel: Option<String>,
+    },
+    /// Run a notebook and fail if any cell's outputs differ from the saved ones
+    TestNotebook {
+        /// Path to the notebook with recorded outputs
+        path: PathBuf,
+        /// Kernelspec to use instead of the notebook's own
+        #[arg(long)]
+        kernel: Option<String>,
     },
     /// Inspect the Automerge state for a notebook (debug command)
     #[command(hide = true)]
@@ -257,9 +321,12 @@ enum JupyterCommands {
     Stop {
         /// The ID of the kernel to stop (required unless --all is used)
         id: Option<String>,
-        /// Stop all running kernels
+        /// Stop all running kernels, including those owned by the daemon
         #[arg(long)]
         all: bool,
+        /// Which kernels --all stops
+        #[arg(long, value_enum, default_value_t = KernelSource::All, requires = "all")]
+        source: KernelSource,
     },
     /// Interrupt a kernel given an ID
     Interrupt {
@@ -337,9 +404,27 @@ enum DaemonCommands {
         /// Number of lines to show
         #[arg(short = 'n', long, default_value = "50")]
         lines: usize,
+        /// Print JSON log records (RUNTIMED_LOG_FORMAT=json) as-is instead of as text
+        #[arg(long)]
+        raw: bool,
     },
     /// Flush all pooled environments and rebuild
     Flush,
+    /// Kill kernel processes the daemon started that no notebook owns any more
+    ReapKernels,
+    /// Write the synced settings as JSON, without machine-specific tool paths
+    ExportSettings {
+        /// File to write (stdout if omitted)
+        output: Option<PathBuf>,
+    },
+    /// Apply settings JSON from `export-settings`, merged over the current settings
+    ImportSettings {
+        /// File to read (stdin if omitted)
+        input: Option<PathBuf>,
+        /// Reset settings missing from the file to their defaults instead of keeping them
+        #[arg(long)]
+        replace: bool,
+    },
     /// Request daemon shutdown (stops the daemon process)
     Shutdown,
     /// Check if the daemon is running (returns exit code)
@@ -474,11 +559,36 @@ fn open_notebook(path: Option<PathBuf>, runtime: Option<String>) -> Result<()> {
 async fn async_main(command: Option<Commands>) -> Result<()> {
     match command {
         // Primary commands
-        Some(Commands::Ps { json, verbose }) => list_kernels(json, verbose).await?,
+        Some(Commands::Ps {
+            json,
+            verbose,
+            watch,
+        }) => {
+            list_kernels(json, verbose).await?;
+            if watch {
+                watch_kernel_events(json).await?;
+            }
+        }
         Some(Commands::Notebook { .. }) => unreachable!(), // handled in main()
         Some(Commands::Jupyter { command }) => jupyter_command(command).await?,
         Some(Commands::Daemon { command }) => daemon_command(command).await?,
-        Some(Commands::Notebooks { json }) => list_notebooks(json).await?,
+        Some(Commands::Notebooks { json, recent }) => list_notebooks(json, recent).await?,
+        Some(Commands::Rpc) => rpc::serve().await?,
+        Some(Commands::ExecNotebook {
+            path,
+            params,
+            output,
+            kernel,
+        }) => {
+            let params = params
+                .iter()
+                .map(|p| exec_notebook::parse_param(p))
+                .collect::<Result<Vec<_>>>()?;
+            exec_notebook::exec_notebook(&path, &params, &output, kernel.as_deref()).await?
+        }
+        Some(Commands::TestNotebook { path, kernel }) => {
+            test_notebook::test_notebook(&path, kernel.as_deref()).await?
+        }
         Some(Commands::Inspect {
             path,
             full_outputs,
@@ -508,7 +618,7 @@ async fn async_main(command: Option<Commands>) -> Result<()> {
         }
         Some(Commands::Stop { id, all }) => {
             eprintln!("Warning: 'runt stop' is deprecated. Use 'runt jupyter stop' instead.");
-            stop_kernels(id.as_deref(), all).await?
+            stop_kernels(id.as_deref(), all, KernelSource::All).await?
         }
         Some(Commands::Interrupt { id }) => {
             eprintln!(
@@ -539,7 +649,7 @@ async fn async_main(command: Option<Commands>) -> Result<()> {
         }
         Some(Commands::Rooms { json }) => {
             eprintln!("Warning: 'runt rooms' is deprecated. Use 'runt notebooks' instead.");
-            list_notebooks(json).await?
+            list_notebooks(json, false).await?
         }
 
         None => println!("No command specified. Use --help for usage information."),
@@ -551,7 +661,7 @@ async fn async_main(command: Option<Commands>) -> Result<()> {
 async fn jupyter_command(command: JupyterCommands) -> Result<()> {
     match command {
         JupyterCommands::Start { name } => start_kernel(&name).await,
-        JupyterCommands::Stop { id, all } => stop_kernels(id.as_deref(), all).await,
+        JupyterCommands::Stop { id, all, source } => stop_kernels(id.as_deref(), all, source).await,
         JupyterCommands::Interrupt { id } => interrupt_kernel(&id).await,
         JupyterCommands::Exec { id, code } => execute_code(&id, code.as_deref()).await,
         JupyterCommands::Console {
@@ -565,14 +675,95 @@ async fn jupyter_command(command: JupyterCommands) -> Result<()> {
 }
 
 async fn list_kernels(json_output: bool, verbose: bool) -> Result<()> {
-    use runtimed::client::PoolClient;
+    let (connection_file_kernels, unified_kernels) =
+        gather_kernels(&runtime_dir(), &runtimed::client::PoolClient::default()).await;
 
-    let runtime_dir = runtime_dir();
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&unified_kernels)?);
+    } else if verbose {
+        // Verbose mode shows connection-file kernels with full details
+        if !connection_file_kernels.is_empty() {
+            println!("Connection-file kernels:");
+            print_verbose_kernel_table(&connection_file_kernels);
+        }
+        // Also show daemon-managed kernels
+        let daemon_rows: Vec<KernelTableRow> = unified_kernels
+            .iter()
+            .filter(|k| k.source == "runtimed")
+            .map(KernelTableRow::from)
+            .collect();
+        if !daemon_rows.is_empty() {
+            if !connection_file_kernels.is_empty() {
+                println!();
+            }
+            println!("Daemon-managed kernels:");
+            let table = Table::new(daemon_rows).with(Style::rounded()).to_string();
+            println!("{}", table);
+        }
+        if connection_file_kernels.is_empty()
+            && unified_kernels.iter().all(|k| k.source != "runtimed")
+        {
+            println!("No running kernels found.");
+        }
+    } else {
+        print_unified_kernel_table(&unified_kernels);
+    }
+
+    Ok(())
+}
+
+/// Print connection-file kernels as they appear and disappear, until Ctrl+C.
+async fn watch_kernel_events(json_output: bool) -> Result<()> {
+    let mut watcher = kernel_client::watch_kernels()?;
+    if !json_output {
+        println!("Watching for kernels (Ctrl+C to stop)...");
+    }
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            event = watcher.next() => {
+                let Some(event) = event else { break };
+                let (action, marker) = match event {
+                    kernel_client::KernelEvent::Added { .. } => ("added", "+"),
+                    kernel_client::KernelEvent::Removed { .. } => ("removed", "-"),
+                };
+                if json_output {
+                    let line = serde_json::json!({
+                        "event": action,
+                        "kernel_id": event.kernel_id(),
+                        "connection_file": event.connection_file(),
+                    });
+                    println!("{}", line);
+                } else {
+                    println!(
+                        "{} {} ({})",
+                        marker,
+                        event.kernel_id(),
+                        event.connection_file().display()
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect running kernels from connection files in `runtime_dir` and from
+/// the daemon's rooms.
+///
+/// Returns the connection-file kernels with full details alongside the
+/// unified list of both kinds (daemon kernels first).
+async fn gather_kernels(
+    runtime_dir: &std::path::Path,
+    daemon: &runtimed::client::PoolClient,
+) -> (Vec<KernelInfo>, Vec<UnifiedKernelInfo>) {
     let timeout = Duration::from_secs(2);
 
     // 1. Gather connection-file kernels (standalone Jupyter kernels)
     let mut connection_file_kernels = Vec::new();
-    if let Ok(mut entries) = fs::read_dir(&runtime_dir).await {
+    if let Ok(mut entries) = fs::read_dir(runtime_dir).await {
         let mut connection_files: Vec<PathBuf> = Vec::new();
         while let Some(entry) = entries.next_entry().await.ok().flatten() {
             let path = entry.path();
@@ -599,22 +790,10 @@ async fn list_kernels(json_output: bool, verbose: bool) -> Result<()> {
 
     // 2. Gather daemon-managed kernels
     let mut daemon_kernels: Vec<UnifiedKernelInfo> = Vec::new();
-    let client = PoolClient::default();
-    if let Ok(rooms) = client.list_rooms().await {
+    if let Ok(rooms) = daemon.list_rooms().await {
         for room in rooms {
             if room.has_kernel {
-                daemon_kernels.push(UnifiedKernelInfo {
-                    name: room
-                        .kernel_type
-                        .clone()
-                        .unwrap_or_else(|| "unknown".to_string()),
-                    language: room.kernel_type.clone(),
-                    status: room.kernel_status.unwrap_or_else(|| "unknown".to_string()),
-                    source: "runtimed".to_string(),
-                    notebook: Some(room.notebook_id.clone()),
-                    connection_file: None,
-                    env_source: room.env_source,
-                });
+                daemon_kernels.push(UnifiedKernelInfo::from_room(room));
             }
         }
     }
@@ -630,6 +809,8 @@ async fn list_kernels(json_output: bool, verbose: bool) -> Result<()> {
             notebook: None,
             connection_file: Some(k.connection_file.clone()),
             env_source: None,
+            executing_cell: None,
+            queue_depth: None,
         })
         .collect();
 
@@ -647,38 +828,7 @@ async fn list_kernels(json_output: bool, verbose: bool) -> Result<()> {
         }
     });
 
-    if json_output {
-        println!("{}", serde_json::to_string_pretty(&unified_kernels)?);
-    } else if verbose {
-        // Verbose mode shows connection-file kernels with full details
-        if !connection_file_kernels.is_empty() {
-            println!("Connection-file kernels:");
-            print_verbose_kernel_table(&connection_file_kernels);
-        }
-        // Also show daemon-managed kernels
-        let daemon_rows: Vec<KernelTableRow> = unified_kernels
-            .iter()
-            .filter(|k| k.source == "runtimed")
-            .map(KernelTableRow::from)
-            .collect();
-        if !daemon_rows.is_empty() {
-            if !connection_file_kernels.is_empty() {
-                println!();
-            }
-            println!("Daemon-managed kernels:");
-            let table = Table::new(daemon_rows).with(Style::rounded()).to_string();
-            println!("{}", table);
-        }
-        if connection_file_kernels.is_empty()
-            && unified_kernels.iter().all(|k| k.source != "runtimed")
-        {
-            println!("No running kernels found.");
-        }
-    } else {
-        print_unified_kernel_table(&unified_kernels);
-    }
-
-    Ok(())
+    (connection_file_kernels, unified_kernels)
 }
 
 fn print_unified_kernel_table(kernels: &[UnifiedKernelInfo]) {
@@ -831,47 +981,57 @@ async fn start_kernel(name: &str) -> Result<()> {
     Ok(())
 }
 
-async fn stop_kernels(id: Option<&str>, all: bool) -> Result<()> {
-    if all {
-        // Stop all running kernels
-        let runtime_dir = runtime_dir();
-        let mut entries = fs::read_dir(&runtime_dir).await?;
-        let mut stopped = 0;
+/// Where a kernel stopped by `runt jupyter stop --all` comes from.
+#[derive(clap::ValueEnum, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum KernelSource {
+    /// Kernels with a connection file in the Jupyter runtime dir
+    Jupyter,
+    /// Kernels the runtimed daemon runs for open notebooks
+    Runtimed,
+    /// Both of the above
+    All,
+}
 
-        while let Some(entry) = entries.next_entry().await? {
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) != Some("json") {
-                continue;
-            }
-            let file_name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
-            if !file_name.starts_with("runt-kernel-") {
-                continue;
-            }
+impl KernelSource {
+    fn includes_jupyter(self) -> bool {
+        matches!(self, KernelSource::Jupyter | KernelSource::All)
+    }
 
-            let kernel_id = file_name
-                .strip_prefix("runt-kernel-")
-                .and_then(|s| s.strip_suffix(".json"))
-                .unwrap_or("unknown");
+    fn includes_runtimed(self) -> bool {
+        matches!(self, KernelSource::Runtimed | KernelSource::All)
+    }
+}
 
-            match KernelClient::from_connection_file(&path).await {
-                Ok(mut client) => {
-                    if client.shutdown(false).await.is_ok() {
-                        println!("Stopped {}", kernel_id);
-                        stopped += 1;
-                    } else {
-                        eprintln!("Failed to stop {}", kernel_id);
-                    }
-                }
-                Err(_) => {
-                    eprintln!("Failed to connect to {}", kernel_id);
-                }
-            }
-        }
+/// Kernels stopped by `stop_all_kernels`, per source.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+struct StopSummary {
+    /// IDs of the stopped connection-file kernels
+    jupyter: Vec<String>,
+    /// Notebook IDs whose daemon kernel was stopped
+    runtimed: Vec<String>,
+}
 
+async fn stop_kernels(id: Option<&str>, all: bool, source: KernelSource) -> Result<()> {
+    if all {
+        let daemon = runtimed::client::PoolClient::default();
+        let summary = stop_all_kernels(&runtime_dir(), &daemon, source).await?;
+        for kernel_id in &summary.jupyter {
+            println!("Stopped {}", kernel_id);
+        }
+        for notebook_id in &summary.runtimed {
+            println!("Stopped daemon kernel for {}", notebook_id);
+        }
+        let stopped = summary.jupyter.len() + summary.runtimed.len();
         if stopped == 0 {
             println!("No running kernels found.");
         } else {
-            println!("\nStopped {} kernel(s)", stopped);
+            println!(
+                "\nStopped {} kernel(s): {} Jupyter, {} runtimed",
+                stopped,
+                summary.jupyter.len(),
+                summary.runtimed.len()
+            );
         }
     } else if let Some(id) = id {
         let connection_file = runtime_dir().join(format!("runt-kernel-{}.json", id));
@@ -884,6 +1044,78 @@ async fn stop_kernels(id: Option<&str>, all: bool) -> Result<()> {
     Ok(())
 }
 
+async fn stop_all_kernels(
+    runtime_dir: &std::path::Path,
+    daemon: &runtimed::client::PoolClient,
+    source: KernelSource,
+) -> Result<StopSummary> {
+    let mut summary = StopSummary::default();
+    if source.includes_jupyter() {
+        summary.jupyter = stop_connection_file_kernels(runtime_dir).await?;
+    }
+    if source.includes_runtimed() {
+        summary.runtimed = stop_daemon_kernels(daemon, source == KernelSource::Runtimed).await?;
+    }
+    Ok(summary)
+}
+
+async fn stop_connection_file_kernels(runtime_dir: &std::path::Path) -> Result<Vec<String>> {
+    let mut entries = fs::read_dir(runtime_dir).await?;
+    let mut stopped = Vec::new();
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+        let file_name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+        if !file_name.starts_with("runt-kernel-") {
+            continue;
+        }
+
+        let kernel_id = file_name
+            .strip_prefix("runt-kernel-")
+            .and_then(|s| s.strip_suffix(".json"))
+            .unwrap_or("unknown");
+
+        match KernelClient::from_connection_file(&path).await {
+            Ok(mut client) => {
+                if client.shutdown(false).await.is_ok() {
+                    stopped.push(kernel_id.to_string());
+                } else {
+                    eprintln!("Failed to stop {}", kernel_id);
+                }
+            }
+            Err(_) => {
+                eprintln!("Failed to connect to {}", kernel_id);
+            }
+        }
+    }
+
+    Ok(stopped)
+}
+
+/// Stop every kernel the daemon runs for open notebooks.
+///
+/// Kernels owned by the daemon have no connection file in the runtime dir, so
+/// they are found through its rooms instead. An unreachable daemon has no
+/// kernels to stop, which is only an error when `required` is set.
+async fn stop_daemon_kernels(
+    daemon: &runtimed::client::PoolClient,
+    required: bool,
+) -> Result<Vec<String>> {
+    let rooms = match daemon.list_rooms().await {
+        Ok(rooms) => rooms,
+        Err(e) if required => anyhow::bail!("Cannot reach runtimed: {}", e),
+        Err(_) => return Ok(vec![]),
+    };
+    if !rooms.iter().any(|room| room.has_kernel) {
+        return Ok(vec![]);
+    }
+
+    Ok(daemon.shutdown_all_kernels().await?)
+}
+
 async fn interrupt_kernel(id: &str) -> Result<()> {
     let connection_file = runtime_dir().join(format!("runt-kernel-{}.json", id));
     let mut client = KernelClient::from_connection_file(&connection_file).await?;
@@ -1233,11 +1465,17 @@ async fn pool_command(command: PoolCommands) -> Result<()> {
                     println!("UV environments:");
                     println!("  Available: {}", stats.uv_available);
                     println!("  Warming:   {}", stats.uv_warming);
+                    if stats.uv_pinned > 0 {
+                        println!("  Pinned:    {}", stats.uv_pinned);
+                    }
                     if let Some(ref err) = stats.uv_error {
                         println!("  ERROR:     {}", truncate_error(&err.message, 60));
                         if let Some(ref pkg) = err.failed_package {
                             println!("  Failed package: {}", pkg);
                         }
+                        if !err.skipped_packages.is_empty() {
+                            println!("  Skipped:   {}", err.skipped_packages.join(", "));
+                        }
                         println!(
                             "  Failures:  {} (retry in {}s)",
                             err.consecutive_failures, err.retry_in_secs
@@ -1246,11 +1484,17 @@ async fn pool_command(command: PoolCommands) -> Result<()> {
                     println!("Conda environments:");
                     println!("  Available: {}", stats.conda_available);
                     println!("  Warming:   {}", stats.conda_warming);
+                    if stats.conda_pinned > 0 {
+                        println!("  Pinned:    {}", stats.conda_pinned);
+                    }
                     if let Some(ref err) = stats.conda_error {
                         println!("  ERROR:     {}", truncate_error(&err.message, 60));
                         if let Some(ref pkg) = err.failed_package {
                             println!("  Failed package: {}", pkg);
                         }
+                        if !err.skipped_packages.is_empty() {
+                            println!("  Skipped:   {}", err.skipped_packages.join(", "));
+                        }
                         println!(
                             "  Failures:  {} (retry in {}s)",
                             err.consecutive_failures, err.retry_in_secs
@@ -1292,6 +1536,9 @@ async fn pool_command(command: PoolCommands) -> Result<()> {
                         if let Some(port) = info.blob_port {
                             println!("Blob port:  {}", port);
                             println!("Blob URL:   http://127.0.0.1:{}/blob/{{hash}}", port);
+                            if info.blob_token.is_some() {
+                                println!("Blob auth:  bearer token required");
+                            }
                         }
                         let uptime = chrono::Utc::now() - info.started_at;
                         let hours = uptime.num_hours();
@@ -1530,7 +1777,7 @@ async fn daemon_command(command: DaemonCommands) -> Result<()> {
             manager.uninstall()?;
             println!("Service uninstalled.");
         }
-        DaemonCommands::Logs { follow, lines } => {
+        DaemonCommands::Logs { follow, lines, raw } => {
             let log_path = runtimed::default_log_path();
 
             if !log_path.exists() {
@@ -1539,7 +1786,7 @@ async fn daemon_command(command: DaemonCommands) -> Result<()> {
             }
 
             // Native Rust implementation for cross-platform support
-            tail_log_file(&log_path, lines, follow).await?;
+            tail_log_file(&log_path, lines, follow, raw).await?;
         }
         DaemonCommands::Flush => match client.flush_pool().await {
             Ok(()) => {
@@ -1550,6 +1797,54 @@ async fn daemon_command(command: DaemonCommands) -> Result<()> {
                 std::process::exit(1);
             }
         },
+        DaemonCommands::ReapKernels => match client.reap_kernels().await {
+            Ok(reaped) if reaped.is_empty() => {
+                println!("No orphaned kernels found");
+            }
+            Ok(reaped) => {
+                for kernel in &reaped {
+                    println!(
+                        "Killed {} kernel (pid {}) started {} for {}",
+                        kernel.kernel_type,
+                        kernel.pid,
+                        kernel.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                        kernel.notebook_id
+                    );
+                }
+                println!("Reaped {} orphaned kernel(s)", reaped.len());
+            }
+            Err(e) => {
+                eprintln!("Failed to reap kernels: {}", e);
+                std::process::exit(1);
+            }
+        },
+        DaemonCommands::ExportSettings { output } => match client.export_settings().await {
+            Ok(json) => match output {
+                Some(path) => {
+                    std::fs::write(&path, format!("{json}\n"))?;
+                    eprintln!("Settings exported to {}", path.display());
+                }
+                None => println!("{json}"),
+            },
+            Err(e) => {
+                eprintln!("Failed to export settings: {}", e);
+                std::process::exit(1);
+            }
+        },
+        DaemonCommands::ImportSettings { input, replace } => {
+            let json = match input {
+                Some(path) => std::fs::read_to_string(&path)?,
+                None => std::io::read_to_string(std::io::stdin())?,
+            };
+            match client.import_settings(json, replace).await {
+                Ok(true) => println!("Settings imported"),
+                Ok(false) => println!("Settings already up to date"),
+                Err(e) => {
+                    eprintln!("Failed to import settings: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
         DaemonCommands::Shutdown => match client.shutdown().await {
             Ok(()) => {
                 println!("Shutdown request sent");
@@ -1577,7 +1872,7 @@ async fn daemon_command(command: DaemonCommands) -> Result<()> {
 }
 
 /// Native log file tailing implementation
-async fn tail_log_file(path: &PathBuf, lines: usize, follow: bool) -> Result<()> {
+async fn tail_log_file(path: &PathBuf, lines: usize, follow: bool, raw: bool) -> Result<()> {
     use std::collections::VecDeque;
     use std::io::{BufRead, BufReader, Seek, SeekFrom};
 
@@ -1594,8 +1889,16 @@ async fn tail_log_file(path: &PathBuf, lines: usize, follow: bool) -> Result<()>
         last_lines.push_back(line);
     }
 
+    // JSON records are shown in the text format unless --raw
+    let render = |line: &str| {
+        if raw {
+            return line.to_string();
+        }
+        runtimed::log_format::render_json_line(line).unwrap_or_else(|| line.to_string())
+    };
+
     for line in &last_lines {
-        println!("{}", line);
+        println!("{}", render(line));
     }
 
     if follow {
@@ -1627,7 +1930,7 @@ async fn tail_log_file(path: &PathBuf, lines: usize, follow: bool) -> Result<()>
                 _ = rx.recv() => {
                     // Read any new lines
                     while reader.read_line(&mut line)? > 0 {
-                        print!("{}", line);
+                        println!("{}", render(line.trim_end_matches(['\r', '\n'])));
                         line.clear();
                     }
                 }
@@ -1766,9 +2069,25 @@ struct NotebookTableRow {
     status: String,
     #[tabled(rename = "PEERS")]
     peers: String,
+    #[tabled(rename = "OPENED")]
+    opened: String,
+}
+
+/// Render a timestamp as a coarse "time ago" string for tables.
+fn format_ago(at: chrono::DateTime<chrono::Utc>) -> String {
+    let elapsed = chrono::Utc::now() - at;
+    if elapsed.num_days() > 0 {
+        format!("{}d ago", elapsed.num_days())
+    } else if elapsed.num_hours() > 0 {
+        format!("{}h ago", elapsed.num_hours())
+    } else if elapsed.num_minutes() > 0 {
+        format!("{}m ago", elapsed.num_minutes())
+    } else {
+        "just now".to_string()
+    }
 }
 
-async fn list_notebooks(json_output: bool) -> Result<()> {
+async fn list_notebooks(json_output: bool, include_recent: bool) -> Result<()> {
     use runtimed::client::PoolClient;
     use runtimed::singleton::get_running_daemon_info;
 
@@ -1778,33 +2097,64 @@ async fn list_notebooks(json_output: bool) -> Result<()> {
         None => PoolClient::default(),
     };
 
-    match client.list_rooms().await {
-        Ok(rooms) => {
-            if json_output {
-                println!("{}", serde_json::to_string_pretty(&rooms)?);
-            } else if rooms.is_empty() {
-                println!("No open notebooks.");
-            } else {
-                let rows: Vec<NotebookTableRow> = rooms
-                    .iter()
-                    .map(|r| NotebookTableRow {
-                        notebook: shorten_path(&PathBuf::from(&r.notebook_id)),
-                        kernel: r.kernel_type.clone().unwrap_or_else(|| "-".to_string()),
-                        env: r.env_source.clone().unwrap_or_else(|| "-".to_string()),
-                        status: r.kernel_status.clone().unwrap_or_else(|| "-".to_string()),
-                        peers: r.active_peers.to_string(),
-                    })
-                    .collect();
-
-                let table = Table::new(rows).with(Style::rounded()).to_string();
-                println!("{}", table);
-            }
-        }
+    let rooms = match client.list_rooms().await {
+        Ok(rooms) => rooms,
         Err(e) => {
             eprintln!("Failed to list notebooks: {}", e);
             eprintln!("Is the daemon running? Try 'runt daemon status'");
             std::process::exit(1)
         }
+    };
+
+    // Registry entries for notebooks that aren't currently open
+    let recent = if include_recent {
+        match client.list_notebooks().await {
+            Ok(notebooks) => notebooks
+                .into_iter()
+                .filter(|n| !rooms.iter().any(|r| r.notebook_id == n.notebook_id))
+                .collect(),
+            Err(e) => {
+                eprintln!("Failed to list recent notebooks: {}", e);
+                std::process::exit(1)
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    if json_output {
+        if include_recent {
+            let output = serde_json::json!({ "open": rooms, "recent": recent });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else {
+            println!("{}", serde_json::to_string_pretty(&rooms)?);
+        }
+    } else if rooms.is_empty() && recent.is_empty() {
+        println!("No open notebooks.");
+    } else {
+        let open_rows = rooms.iter().map(|r| NotebookTableRow {
+            notebook: shorten_path(&PathBuf::from(&r.notebook_id)),
+            kernel: r.kernel_type.clone().unwrap_or_else(|| "-".to_string()),
+            env: r.env_source.clone().unwrap_or_else(|| "-".to_string()),
+            status: r.kernel_status.clone().unwrap_or_else(|| "-".to_string()),
+            peers: r.active_peers.to_string(),
+            opened: r
+                .last_opened
+                .map(format_ago)
+                .unwrap_or_else(|| "-".to_string()),
+        });
+        let recent_rows = recent.iter().map(|n| NotebookTableRow {
+            notebook: shorten_path(&PathBuf::from(&n.notebook_id)),
+            kernel: "-".to_string(),
+            env: n.last_env_source.clone().unwrap_or_else(|| "-".to_string()),
+            status: "closed".to_string(),
+            peers: "0".to_string(),
+            opened: format_ago(n.last_opened),
+        });
+        let rows: Vec<NotebookTableRow> = open_rows.chain(recent_rows).collect();
+
+        let table = Table::new(rows).with(Style::rounded()).to_string();
+        println!("{}", table);
     }
 
     Ok(())
@@ -2104,3 +2454,157 @@ async fn debug_session(
 
     Ok(())
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use runtimed::client::PoolClient;
+    use runtimed::connection::{recv_json_frame, send_json_frame};
+    use runtimed::mock_kernel::{MockKernel, MockScript};
+    use runtimed::protocol::{Request, Response, RoomInfo};
+    use std::sync::{Arc, Mutex};
+    use tokio::net::UnixListener;
+
+    fn room(notebook_id: &str) -> RoomInfo {
+        RoomInfo {
+            notebook_id: notebook_id.to_string(),
+            active_peers: 1,
+            has_kernel: true,
+            kernel_type: Some("python".to_string()),
+            env_source: Some("uv:prewarmed".to_string()),
+            kernel_status: Some("idle".to_string()),
+            executing_cell: None,
+            queue_depth: 0,
+            last_opened: None,
+        }
+    }
+
+    /// A daemon with two notebook kernels that records the requests it gets.
+    fn fake_daemon(socket_path: &std::path::Path) -> Arc<Mutex<Vec<String>>> {
+        let listener = UnixListener::bind(socket_path).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            let mut running = vec!["/work/a.ipynb".to_string(), "/work/b.ipynb".to_string()];
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let _handshake: Option<serde_json::Value> =
+                    recv_json_frame(&mut stream).await.unwrap();
+                let Some(request) = recv_json_frame::<_, Request>(&mut stream).await.unwrap()
+                else {
+                    continue;
+                };
+                seen.lock().unwrap().push(format!("{:?}", request));
+                let response = match request {
+                    Request::ListRooms => Response::RoomsList {
+                        rooms: running.iter().map(|id| room(id)).collect(),
+                    },
+                    Request::ShutdownAllKernels => Response::KernelsShutDown {
+                        notebook_ids: std::mem::take(&mut running),
+                    },
+                    _ => Response::Error {
+                        message: "unexpected request".to_string(),
+                    },
+                };
+                send_json_frame(&mut stream, &response).await.unwrap();
+            }
+        });
+        requests
+    }
+
+    #[tokio::test]
+    async fn test_stop_all_covers_jupyter_and_daemon_kernels() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let socket_path = tmp.path().join("runtimed.sock");
+        let requests = fake_daemon(&socket_path);
+        let runtime_dir = tmp.path().join("runtime");
+        std::fs::create_dir(&runtime_dir).unwrap();
+        // A stale connection file with nothing listening isn't counted
+        std::fs::write(runtime_dir.join("runt-kernel-stale.json"), "{}").unwrap();
+        let kernel = MockKernel::start(MockScript::new()).await.unwrap();
+        let connection_file = runtime_dir.join("runt-kernel-mock.json");
+        std::fs::write(
+            &connection_file,
+            serde_json::to_string(kernel.connection_info()).unwrap(),
+        )
+        .unwrap();
+
+        let daemon = PoolClient::new(socket_path);
+        let summary = stop_all_kernels(&runtime_dir, &daemon, KernelSource::All)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            summary,
+            StopSummary {
+                jupyter: vec!["mock".to_string()],
+                runtimed: vec!["/work/a.ipynb".to_string(), "/work/b.ipynb".to_string()],
+            }
+        );
+        let control = kernel.received_control();
+        assert_eq!(control.len(), 1);
+        assert_eq!(control[0].header.msg_type, "shutdown_request");
+        assert!(!connection_file.exists());
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec!["ListRooms", "ShutdownAllKernels"]
+        );
+
+        // Nothing left to stop the second time around
+        let summary = stop_all_kernels(&runtime_dir, &daemon, KernelSource::Runtimed)
+            .await
+            .unwrap();
+        assert_eq!(summary, StopSummary::default());
+    }
+
+    #[tokio::test]
+    async fn test_ps_reports_daemon_queue_backlog() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let socket_path = tmp.path().join("runtimed.sock");
+        let _requests = fake_daemon(&socket_path);
+        let daemon = PoolClient::new(socket_path);
+
+        let (_, kernels) = gather_kernels(tmp.path(), &daemon).await;
+        assert_eq!(kernels.len(), 2);
+        assert!(kernels.iter().all(|k| k.queue_depth == Some(0)));
+
+        let backed_up = UnifiedKernelInfo::from_room(RoomInfo {
+            kernel_status: Some("busy".to_string()),
+            executing_cell: Some("cell-1".to_string()),
+            queue_depth: 4,
+            ..room("/work/stuck.ipynb")
+        });
+        assert_eq!(backed_up.status, "busy");
+        assert_eq!(backed_up.executing_cell.as_deref(), Some("cell-1"));
+        assert_eq!(backed_up.queue_depth, Some(4));
+        assert_eq!(KernelTableRow::from(&backed_up).queue, "4");
+        let json = serde_json::to_value(&backed_up).unwrap();
+        assert_eq!(json["queue_depth"], 4);
+        assert_eq!(json["executing_cell"], "cell-1");
+    }
+
+    #[tokio::test]
+    async fn test_stop_all_source_filter() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let socket_path = tmp.path().join("runtimed.sock");
+        let requests = fake_daemon(&socket_path);
+        let daemon = PoolClient::new(socket_path);
+
+        let summary = stop_all_kernels(tmp.path(), &daemon, KernelSource::Jupyter)
+            .await
+            .unwrap();
+        assert_eq!(summary, StopSummary::default());
+        assert!(requests.lock().unwrap().is_empty());
+
+        // Asking for daemon kernels explicitly fails when there is no daemon
+        let missing = PoolClient::new(tmp.path().join("missing.sock"));
+        assert!(
+            stop_all_kernels(tmp.path(), &missing, KernelSource::Runtimed)
+                .await
+                .is_err()
+        );
+        let summary = stop_all_kernels(tmp.path(), &missing, KernelSource::All)
+            .await
+            .unwrap();
+        assert_eq!(summary, StopSummary::default());
+    }
+}
@@ -0,0 +1,401 @@
+//! JSON-RPC 2.0 interface for editor integrations (`runt rpc`).
+//!
+//! Requests are read from stdin and responses written to stdout, one JSON
+//! message per line. Requests without an `id` are notifications and get no
+//! response; batches (JSON arrays) are supported.
+//!
+//! Methods are a stable subset of the CLI, independent of its table output:
+//!
+//! | Method              | Params                                   | Result                          |
+//! |---------------------|------------------------------------------|---------------------------------|
+//! | `version`           | —                                        | `{protocol_version, runt_version}` |
+//! | `kernels.list`      | —                                        | kernels, as in `runt ps --json` |
+//! | `kernels.start`     | `{name}`                                 | `{id, connection_file}`         |
+//! | `kernels.stop`      | `{id}` or `{all: true, source?}`         | `null`, or `{jupyter, runtimed}` for `all` |
+//! | `kernels.interrupt` | `{id}`                                   | `null`                          |
+//! | `kernels.exec`      | `{id, code}`                             | `{status, execution_count, outputs}` |
+//! | `daemon.status`     | —                                        | pool statistics                 |
+//!
+//! `PROTOCOL_VERSION` is bumped whenever a method or result shape changes
+//! incompatibly; clients should check it with `version` first.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use jupyter_protocol::JupyterMessageContent;
+use kernel_client::KernelClient;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use crate::{gather_kernels, stop_all_kernels, KernelSource};
+
+/// Version of the method set and result shapes described above.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The method ran but failed (unknown kernel, daemon unreachable, ...).
+const METHOD_FAILED: i64 = -32000;
+
+/// Where methods look for kernels.
+struct Context {
+    runtime_dir: PathBuf,
+    daemon: runtimed::client::PoolClient,
+}
+
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn failed(e: impl std::fmt::Display) -> Self {
+        Self {
+            code: METHOD_FAILED,
+            message: e.to_string(),
+        }
+    }
+
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    method: String,
+    #[serde(default)]
+    params: Value,
+    #[serde(default)]
+    id: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct StartParams {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct IdParams {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct StopParams {
+    id: Option<String>,
+    #[serde(default)]
+    all: bool,
+    source: Option<KernelSource>,
+}
+
+#[derive(Deserialize)]
+struct ExecParams {
+    id: String,
+    code: String,
+}
+
+/// Serve requests from stdin until it is closed.
+pub async fn serve() -> Result<()> {
+    let daemon = match runtimed::singleton::get_running_daemon_info() {
+        Some(info) => runtimed::client::PoolClient::new(PathBuf::from(&info.endpoint)),
+        None => runtimed::client::PoolClient::default(),
+    };
+    let ctx = Context {
+        runtime_dir: runtimelib::runtime_dir(),
+        daemon,
+    };
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_line(&ctx, &line).await {
+            let mut out = serde_json::to_string(&response)?;
+            out.push('\n');
+            stdout.write_all(out.as_bytes()).await?;
+            stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+/// Handle one line of input: a request, a notification, or a batch.
+async fn handle_line(ctx: &Context, line: &str) -> Option<Value> {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                PARSE_ERROR,
+                &format!("Parse error: {}", e),
+            ))
+        }
+    };
+
+    match message {
+        Value::Array(batch) if !batch.is_empty() => {
+            let mut responses = Vec::new();
+            for message in batch {
+                responses.extend(dispatch(ctx, message).await);
+            }
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        message => dispatch(ctx, message).await,
+    }
+}
+
+/// Run a single request, returning its response (`None` for notifications).
+async fn dispatch(ctx: &Context, message: Value) -> Option<Value> {
+    let request = match serde_json::from_value::<Request>(message) {
+        Ok(request) if request.jsonrpc == "2.0" => request,
+        _ => {
+            return Some(error_response(
+                Value::Null,
+                INVALID_REQUEST,
+                "Invalid request",
+            ))
+        }
+    };
+
+    let result = call(ctx, &request.method, request.params).await;
+    let id = request.id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => error_response(id, e.code, &e.message),
+    })
+}
+
+async fn call(ctx: &Context, method: &str, params: Value) -> Result<Value, RpcError> {
+    match method {
+        "version" => Ok(json!({
+            "protocol_version": PROTOCOL_VERSION,
+            "runt_version": env!("CARGO_PKG_VERSION"),
+        })),
+
+        "kernels.list" => {
+            let (_, kernels) = gather_kernels(&ctx.runtime_dir, &ctx.daemon).await;
+            serde_json::to_value(kernels).map_err(RpcError::failed)
+        }
+
+        "kernels.start" => {
+            let StartParams { name } = parse_params(params)?;
+            let kernelspec = runtimelib::find_kernelspec(&name)
+                .await
+                .map_err(RpcError::failed)?;
+            let client = KernelClient::start_from_kernelspec(kernelspec)
+                .await
+                .map_err(RpcError::failed)?;
+            Ok(json!({
+                "id": client.kernel_id(),
+                "connection_file": client.connection_file(),
+            }))
+        }
+
+        "kernels.stop" => {
+            let params: StopParams = parse_params(params)?;
+            if params.all {
+                let source = params.source.unwrap_or(KernelSource::All);
+                let summary = stop_all_kernels(&ctx.runtime_dir, &ctx.daemon, source)
+                    .await
+                    .map_err(RpcError::failed)?;
+                return serde_json::to_value(summary).map_err(RpcError::failed);
+            }
+            let id = params
+                .id
+                .ok_or_else(|| RpcError::invalid_params("either `id` or `all` is required"))?;
+            let mut client = connect(ctx, &id).await?;
+            client.shutdown(false).await.map_err(RpcError::failed)?;
+            Ok(Value::Null)
+        }
+
+        "kernels.interrupt" => {
+            let IdParams { id } = parse_params(params)?;
+            let mut client = connect(ctx, &id).await?;
+            client.interrupt().await.map_err(RpcError::failed)?;
+            Ok(Value::Null)
+        }
+
+        "kernels.exec" => {
+            let ExecParams { id, code } = parse_params(params)?;
+            let client = connect(ctx, &id).await?;
+            let mut outputs = Vec::new();
+            let reply = client
+                .execute(&code, |content| outputs.extend(output_json(content)))
+                .await
+                .map_err(RpcError::failed)?;
+            Ok(json!({
+                "status": reply.status,
+                "execution_count": reply.execution_count,
+                "outputs": outputs,
+            }))
+        }
+
+        "daemon.status" => {
+            let stats = ctx.daemon.status().await.map_err(RpcError::failed)?;
+            serde_json::to_value(stats).map_err(RpcError::failed)
+        }
+
+        _ => Err(RpcError {
+            code: METHOD_NOT_FOUND,
+            message: format!("Method not found: {}", method),
+        }),
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::invalid_params(e.to_string()))
+}
+
+async fn connect(ctx: &Context, id: &str) -> Result<KernelClient, RpcError> {
+    let connection_file = ctx.runtime_dir.join(format!("runt-kernel-{}.json", id));
+    KernelClient::from_connection_file(&connection_file)
+        .await
+        .map_err(|e| RpcError::failed(format!("kernel {}: {}", id, e)))
+}
+
+/// Convert an iopub message into an nbformat-style output, if it is one.
+fn output_json(content: JupyterMessageContent) -> Option<Value> {
+    match content {
+        JupyterMessageContent::StreamContent(stream) => Some(json!({
+            "output_type": "stream",
+            "name": stream.name,
+            "text": stream.text,
+        })),
+        JupyterMessageContent::ExecuteResult(result) => Some(json!({
+            "output_type": "execute_result",
+            "execution_count": result.execution_count,
+            "data": result.data,
+            "metadata": result.metadata,
+        })),
+        JupyterMessageContent::DisplayData(display) => Some(json!({
+            "output_type": "display_data",
+            "data": display.data,
+            "metadata": display.metadata,
+        })),
+        JupyterMessageContent::ErrorOutput(error) => Some(json!({
+            "output_type": "error",
+            "ename": error.ename,
+            "evalue": error.evalue,
+            "traceback": error.traceback,
+        })),
+        _ => None,
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A context with no kernels and no daemon.
+    fn empty_context(tmp: &tempfile::TempDir) -> Context {
+        Context {
+            runtime_dir: tmp.path().to_path_buf(),
+            daemon: runtimed::client::PoolClient::new(tmp.path().join("missing.sock")),
+        }
+    }
+
+    async fn request(ctx: &Context, body: Value) -> Value {
+        handle_line(ctx, &body.to_string()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_version_and_kernels_list() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let ctx = empty_context(&tmp);
+
+        let response = request(
+            &ctx,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "version"}),
+        )
+        .await;
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["protocol_version"], PROTOCOL_VERSION);
+
+        let response = request(
+            &ctx,
+            json!({"jsonrpc": "2.0", "id": "list", "method": "kernels.list"}),
+        )
+        .await;
+        assert_eq!(response["id"], "list");
+        assert_eq!(response["result"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_errors_use_json_rpc_codes() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let ctx = empty_context(&tmp);
+
+        let response = handle_line(&ctx, "{not json").await.unwrap();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert_eq!(response["id"], Value::Null);
+
+        let response = request(&ctx, json!({"id": 1, "method": "version"})).await;
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+
+        let response = request(&ctx, json!({"jsonrpc": "2.0", "id": 2, "method": "nope"})).await;
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        let response = request(
+            &ctx,
+            json!({"jsonrpc": "2.0", "id": 3, "method": "kernels.stop", "params": {}}),
+        )
+        .await;
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+
+        let response = request(
+            &ctx,
+            json!({"jsonrpc": "2.0", "id": 4, "method": "kernels.interrupt", "params": {"id": "gone"}}),
+        )
+        .await;
+        assert_eq!(response["error"]["code"], METHOD_FAILED);
+
+        let response = request(
+            &ctx,
+            json!({"jsonrpc": "2.0", "id": 5, "method": "daemon.status"}),
+        )
+        .await;
+        assert_eq!(response["error"]["code"], METHOD_FAILED);
+    }
+
+    #[tokio::test]
+    async fn test_notifications_and_batches() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let ctx = empty_context(&tmp);
+
+        let notification = json!({"jsonrpc": "2.0", "method": "version"});
+        assert!(handle_line(&ctx, &notification.to_string()).await.is_none());
+
+        let batch = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "version"},
+            notification,
+            {"jsonrpc": "2.0", "id": 2, "method": "kernels.stop", "params": {"all": true, "source": "jupyter"}},
+        ]);
+        let responses = request(&ctx, batch).await;
+        let responses = responses.as_array().unwrap();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(
+            responses[1]["result"],
+            json!({"jupyter": [], "runtimed": []})
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! `runt test-notebook`: use a notebook as a regression test.
+//!
+//! The notebook is run headlessly and each code cell's new outputs are
+//! compared with the outputs saved in the file, in the spirit of nbval.
+//! Volatile details are normalized first: execution counts, output metadata,
+//! tracebacks, timestamps and memory addresses. Images only need to be
+//! present, since their bytes vary between library versions. Cells tagged
+//! `nbval-ignore-output` or `skip-execution` are not compared.
+
+use std::path::Path;
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use regex::Regex;
+use runtimed::cell_tags::SKIP_EXECUTION;
+use serde_json::Value;
+
+use crate::exec_notebook::{execute, has_tag, read_notebook};
+
+/// Tag for cells whose outputs aren't compared (nbval's name for it).
+pub const IGNORE_OUTPUT: &str = "nbval-ignore-output";
+
+static TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:?\d{2})?").unwrap()
+});
+
+static ADDRESS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"0x[0-9a-fA-F]{6,}").unwrap());
+
+/// A code cell whose new outputs differ from the recorded ones.
+#[derive(Debug)]
+pub struct Mismatch {
+    pub index: usize,
+    pub cell_id: Option<String>,
+    pub expected: Vec<String>,
+    pub actual: Vec<String>,
+}
+
+fn normalize(text: &str) -> String {
+    let text = TIMESTAMP.replace_all(text, "<timestamp>");
+    ADDRESS.replace_all(&text, "<address>").into_owned()
+}
+
+/// Multiline nbformat strings are either a string or a list of lines.
+fn multiline_text(value: &Value) -> String {
+    match value {
+        Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn push_text(lines: &mut Vec<String>, text: &str) {
+    lines.extend(normalize(text).lines().map(str::to_string));
+}
+
+/// A cell's outputs as comparable lines, with volatile details normalized.
+pub fn normalized_outputs(cell: &Value) -> Vec<String> {
+    let mut lines = Vec::new();
+    let outputs = cell["outputs"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+    let mut stream: Option<(&str, String)> = None;
+    for output in outputs {
+        // Kernels split stream text into messages arbitrarily, so compare
+        // consecutive writes to the same stream as one
+        if output["output_type"] == "stream" {
+            let name = output["name"].as_str().unwrap_or("stream");
+            let text = multiline_text(&output["text"]);
+            match &mut stream {
+                Some((current, buffer)) if *current == name => buffer.push_str(&text),
+                _ => {
+                    if let Some((name, buffer)) = stream.replace((name, text)) {
+                        lines.push(format!("[{}]", name));
+                        push_text(&mut lines, &buffer);
+                    }
+                }
+            }
+            continue;
+        }
+        if let Some((name, buffer)) = stream.take() {
+            lines.push(format!("[{}]", name));
+            push_text(&mut lines, &buffer);
+        }
+
+        if output["output_type"] == "error" {
+            let ename = output["ename"].as_str().unwrap_or_default();
+            let evalue = output["evalue"].as_str().unwrap_or_default();
+            lines.push(format!("[error] {}: {}", ename, normalize(evalue)));
+            continue;
+        }
+        let Some(data) = output["data"].as_object() else {
+            continue;
+        };
+        let mut mimetypes: Vec<&String> = data.keys().collect();
+        mimetypes.sort();
+        for mimetype in mimetypes {
+            lines.push(format!("[{}]", mimetype));
+            let value = &data[mimetype.as_str()];
+            if mimetype.starts_with("image/") {
+                continue;
+            }
+            match value {
+                Value::String(_) | Value::Array(_) => push_text(&mut lines, &multiline_text(value)),
+                other => push_text(&mut lines, &other.to_string()),
+            }
+        }
+    }
+    if let Some((name, buffer)) = stream {
+        lines.push(format!("[{}]", name));
+        push_text(&mut lines, &buffer);
+    }
+    lines
+}
+
+/// Compare the executed notebook's code cells with the baseline's.
+///
+/// Comparison stops at the first code cell that never ran: after a cell
+/// raises, the rest have only their cleared outputs to show.
+pub fn compare(baseline: &Value, executed: &Value) -> Vec<Mismatch> {
+    let empty = Vec::new();
+    let baseline_cells = baseline["cells"].as_array().unwrap_or(&empty);
+    let executed_cells = executed["cells"].as_array().unwrap_or(&empty);
+    baseline_cells
+        .iter()
+        .zip(executed_cells)
+        .enumerate()
+        .take_while(|(_, (cell, actual))| {
+            cell["cell_type"] != "code"
+                || has_tag(cell, SKIP_EXECUTION)
+                || !actual["execution_count"].is_null()
+        })
+        .filter(|(_, (cell, _))| {
+            cell["cell_type"] == "code"
+                && !has_tag(cell, SKIP_EXECUTION)
+                && !has_tag(cell, IGNORE_OUTPUT)
+        })
+        .filter_map(|(index, (expected, actual))| {
+            let expected_lines = normalized_outputs(expected);
+            let actual_lines = normalized_outputs(actual);
+            (expected_lines != actual_lines).then(|| Mismatch {
+                index,
+                cell_id: expected["id"].as_str().map(str::to_string),
+                expected: expected_lines,
+                actual: actual_lines,
+            })
+        })
+        .collect()
+}
+
+/// Line diff of `expected` against `actual`, with `-`, `+` and ` ` prefixes.
+pub fn diff_lines(expected: &[String], actual: &[String]) -> Vec<String> {
+    // Longest common subsequence table, filled from the end
+    let (n, m) = (expected.len(), actual.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == actual[j] {
+            diff.push(format!("  {}", expected[i]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(format!("- {}", expected[i]));
+            i += 1;
+        } else {
+            diff.push(format!("+ {}", actual[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// Run the notebook at `path` and fail if any cell's outputs changed.
+pub async fn test_notebook(path: &Path, kernel: Option<&str>) -> Result<()> {
+    let baseline = read_notebook(path).await?;
+    let mut executed = baseline.clone();
+    let run = execute(&mut executed, kernel).await;
+    report_mismatches(path, &baseline, &executed, run)
+}
+
+/// Print a diff for each cell of `executed` whose outputs differ from
+/// `baseline`, and fail if there are any or if the run itself failed.
+fn report_mismatches(
+    path: &Path,
+    baseline: &Value,
+    executed: &Value,
+    run: Result<()>,
+) -> Result<()> {
+    let mismatches = compare(baseline, executed);
+    for mismatch in &mismatches {
+        match &mismatch.cell_id {
+            Some(id) => println!("Cell {} ({}) outputs differ:", mismatch.index + 1, id),
+            None => println!("Cell {} outputs differ:", mismatch.index + 1),
+        }
+        for line in diff_lines(&mismatch.expected, &mismatch.actual) {
+            println!("  {}", line);
+        }
+        println!();
+    }
+
+    if !mismatches.is_empty() {
+        match run {
+            Ok(()) => anyhow::bail!(
+                "{} cell(s) in {} differ from the recorded outputs",
+                mismatches.len(),
+                path.display()
+            ),
+            Err(e) => anyhow::bail!(
+                "{} cell(s) in {} differ from the recorded outputs ({})",
+                mismatches.len(),
+                path.display(),
+                e
+            ),
+        }
+    }
+    run?;
+    println!("{}: outputs match", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec_notebook::execute_on;
+    use runtimed::mock_kernel::{MockExecution, MockKernel, MockScript};
+    use serde_json::json;
+
+    fn code_cell(source: &str, tags: &[&str], outputs: Value) -> Value {
+        json!({
+            "cell_type": "code",
+            "id": uuid::Uuid::new_v4().to_string(),
+            "metadata": { "tags": tags },
+            "execution_count": 1,
+            "outputs": outputs,
+            "source": source,
+        })
+    }
+
+    fn notebook(cells: Vec<Value>) -> Value {
+        json!({
+            "cells": cells,
+            "metadata": {
+                "kernelspec": { "name": "python3", "language": "python", "display_name": "Python 3" }
+            },
+            "nbformat": 4,
+            "nbformat_minor": 5,
+        })
+    }
+
+    fn session_id() -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+
+    fn deterministic() -> Value {
+        notebook(vec![
+            code_cell(
+                "print('sum', 1 + 2)",
+                &[],
+                json!([{ "output_type": "stream", "name": "stdout", "text": ["sum 3\n"] }]),
+            ),
+            code_cell(
+                "6 * 7",
+                &[],
+                json!([{
+                    "output_type": "execute_result",
+                    "execution_count": 2,
+                    "data": { "text/plain": "42" },
+                    "metadata": {}
+                }]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_volatile_details_are_normalized() {
+        let baseline = notebook(vec![code_cell(
+            "print(now, obj)",
+            &[],
+            json!([
+                { "output_type": "stream", "name": "stdout", "text": "at 2024-01-02 03:04:05.678 " },
+                { "output_type": "stream", "name": "stdout", "text": "<Obj at 0x7f00deadbeef>\n" },
+                {
+                    "output_type": "execute_result",
+                    "execution_count": 4,
+                    "data": { "text/plain": "1", "image/png": "aGVsbG8=" },
+                    "metadata": { "width": 10 }
+                }
+            ]),
+        )]);
+        let executed = notebook(vec![code_cell(
+            "print(now, obj)",
+            &[],
+            json!([
+                {
+                    "output_type": "stream",
+                    "name": "stdout",
+                    "text": "at 2026-10-17T09:00:00Z <Obj at 0x7fffcafe1234>\n"
+                },
+                {
+                    "output_type": "execute_result",
+                    "execution_count": 1,
+                    "data": { "text/plain": "1", "image/png": "d29ybGQ=" },
+                    "metadata": {}
+                }
+            ]),
+        )]);
+        assert!(compare(&baseline, &executed).is_empty());
+        assert_eq!(
+            normalized_outputs(&executed["cells"][0]),
+            vec![
+                "[stdout]",
+                "at <timestamp> <Obj at <address>>",
+                "[image/png]",
+                "[text/plain]",
+                "1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deterministic_notebook_passes_and_mutated_fails() {
+        let baseline = deterministic();
+        let mut executed = deterministic();
+        executed["cells"][1]["outputs"][0]["execution_count"] = json!(7);
+        assert!(compare(&baseline, &executed).is_empty());
+
+        executed["cells"][0]["outputs"][0]["text"] = json!("sum 4\n");
+        let mismatches = compare(&baseline, &executed);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].index, 0);
+        assert_eq!(
+            diff_lines(&mismatches[0].expected, &mismatches[0].actual),
+            vec!["  [stdout]", "- sum 3", "+ sum 4"]
+        );
+    }
+
+    #[test]
+    fn test_ignored_and_skipped_cells_are_not_compared() {
+        let baseline = notebook(vec![
+            code_cell(
+                "import random; random.random()",
+                &[IGNORE_OUTPUT],
+                json!([{ "output_type": "execute_result", "data": { "text/plain": "0.1" } }]),
+            ),
+            code_cell(
+                "slow()",
+                &[SKIP_EXECUTION],
+                json!([{ "output_type": "stream", "name": "stdout", "text": "done\n" }]),
+            ),
+        ]);
+        let mut executed = baseline.clone();
+        executed["cells"][0]["outputs"][0]["data"]["text/plain"] = json!("0.9");
+        executed["cells"][1]["outputs"] = json!([]);
+        assert!(compare(&baseline, &executed).is_empty());
+    }
+
+    #[test]
+    fn test_errors_compare_by_name_and_value() {
+        let error = |traceback: &str| {
+            json!([{
+                "output_type": "error",
+                "ename": "ValueError",
+                "evalue": "bad",
+                "traceback": [traceback]
+            }])
+        };
+        let baseline = notebook(vec![code_cell("f()", &[], error("File /tmp/a.py"))]);
+        let executed = notebook(vec![code_cell("f()", &[], error("File /tmp/b.py"))]);
+        assert!(compare(&baseline, &executed).is_empty());
+        assert_eq!(
+            normalized_outputs(&baseline["cells"][0]),
+            vec!["[error] ValueError: bad"]
+        );
+    }
+
+    #[test]
+    fn test_cells_after_a_mid_run_error_are_not_compared() {
+        let stdout =
+            |text: &str| json!([{ "output_type": "stream", "name": "stdout", "text": text }]);
+        let baseline = notebook(vec![
+            code_cell("print('a')", &[], stdout("a\n")),
+            code_cell("print('b')", &[], stdout("b\n")),
+            code_cell("print('c')", &[], stdout("c\n")),
+        ]);
+        // The second cell raised, so the third was cleared and never ran
+        let mut executed = baseline.clone();
+        executed["cells"][1]["outputs"] = json!([{
+            "output_type": "error",
+            "ename": "NameError",
+            "evalue": "name 'b' is not defined",
+            "traceback": []
+        }]);
+        executed["cells"][2]["outputs"] = json!([]);
+        executed["cells"][2]["execution_count"] = Value::Null;
+
+        let mismatches = compare(&baseline, &executed);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].index, 1);
+    }
+
+    #[tokio::test]
+    async fn test_notebook_against_recorded_outputs() {
+        let mock = MockKernel::start(
+            MockScript::new()
+                .on_execute(
+                    "print('sum', 1 + 2)",
+                    MockExecution::new().stdout("sum 3\n"),
+                )
+                .on_execute("6 * 7", MockExecution::new().result("42")),
+        )
+        .await
+        .unwrap();
+        let path = Path::new("deterministic.ipynb");
+
+        let baseline = deterministic();
+        let mut executed = baseline.clone();
+        let run = execute_on(mock.connection_info(), &session_id(), &mut executed).await;
+        report_mismatches(path, &baseline, &executed, run).unwrap();
+
+        let mut mutated = deterministic();
+        mutated["cells"][1]["outputs"][0]["data"]["text/plain"] = json!("41");
+        let mut executed = mutated.clone();
+        let run = execute_on(mock.connection_info(), &session_id(), &mut executed).await;
+        assert!(report_mismatches(path, &mutated, &executed, run).is_err());
+    }
+}
@@ -970,6 +970,7 @@ async fn collect_outputs_async(
                         cell_id: msg_cell_id,
                         output_type,
                         output_json,
+                        ..
                     } => {
                         if msg_cell_id == cell_id {
                             if let Some(output) = parse_output_async(
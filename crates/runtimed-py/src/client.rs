@@ -57,6 +57,8 @@ impl DaemonClient {
     ///   - conda_available: number of prewarmed Conda environments
     ///   - uv_warming: number of UV environments being created
     ///   - conda_warming: number of Conda environments being created
+    ///   - uv_pinned: number of UV environments pinned to notebooks
+    ///   - conda_pinned: number of Conda environments pinned to notebooks
     fn status<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
         let stats = self
             .runtime
@@ -68,6 +70,8 @@ impl DaemonClient {
         dict.set_item("conda_available", stats.conda_available)?;
         dict.set_item("uv_warming", stats.uv_warming)?;
         dict.set_item("conda_warming", stats.conda_warming)?;
+        dict.set_item("uv_pinned", stats.uv_pinned)?;
+        dict.set_item("conda_pinned", stats.conda_pinned)?;
         Ok(dict)
     }
 
@@ -720,6 +720,7 @@ impl Session {
                             cell_id: msg_cell_id,
                             output_type,
                             output_json,
+                            ..
                         } => {
                             log::debug!(
                                 "[session] Output broadcast: type={}, cell_id={}",
@@ -54,10 +54,14 @@ async fn main() -> anyhow::Result<()> {
         cache_dir: temp_dir.path().join("envs"),
         blob_store_dir: temp_dir.path().join("blobs"),
         notebook_docs_dir: temp_dir.path().join("notebook-docs"),
+        notebook_registry_path: temp_dir.path().join("notebooks.json"),
+        kernel_pids_path: temp_dir.path().join("kernel-pids.json"),
+        env_pins_path: temp_dir.path().join("env-pins.json"),
         uv_pool_size: 0, // Don't create real envs
         conda_pool_size: 0,
         max_age_secs: 3600,
         lock_dir: Some(temp_dir.path().to_path_buf()),
+        blob_server: Default::default(),
     };
     let socket_path = config.socket_path.clone();
     println!("Socket path: {:?}", socket_path);
@@ -1,19 +1,25 @@
 //! HTTP read server for the blob store.
 //!
-//! Serves blobs by hash over unauthenticated localhost HTTP. This is safe
-//! because blobs are content-addressed (256-bit hashes are not guessable),
-//! the endpoint is read-only, and the data is non-secret (notebook outputs
-//! the user produced locally).
+//! Serves blobs by hash over HTTP. By default the server binds localhost and
+//! is unauthenticated. This is safe because blobs are content-addressed
+//! (256-bit hashes are not guessable), the endpoint is read-only, and the
+//! data is non-secret (notebook outputs the user produced locally).
+//!
+//! For remote or dev-container setups the server can bind another interface
+//! (see [`BlobServerConfig`]). Set an `auth_token` in that case: blob
+//! requests must then carry `Authorization: Bearer <token>` or get a 401.
 //!
 //! Endpoints:
 //! - `GET /blob/{hash}` — raw bytes with `Content-Type` from metadata
-//! - `GET /health` — 200 OK
+//! - `GET /health` — 200 OK (never requires the token)
+//! - `OPTIONS *` — CORS preflight, so browsers can send `Authorization`
 //!
-//! The server binds `127.0.0.1:0` (OS-assigned random port) and runs on
-//! the caller's tokio runtime. It shuts down when the process exits; no
-//! explicit cancellation is implemented yet.
+//! The server binds `127.0.0.1:0` (OS-assigned random port) unless
+//! configured otherwise, and runs on the caller's tokio runtime. It shuts
+//! down when the process exits; no explicit cancellation is implemented yet.
 
 use std::convert::Infallible;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 
 use http_body_util::Full;
@@ -22,29 +28,99 @@ use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper::{Method, Request, Response, StatusCode};
 use hyper_util::rt::TokioIo;
-use log::{error, info};
+use log::{error, info, warn};
 use tokio::net::TcpListener;
 
 use crate::blob_store::BlobStore;
 
+/// Where the blob server listens and whether it requires a token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobServerConfig {
+    /// Address to bind; port 0 picks a random free port.
+    pub bind_addr: SocketAddr,
+    /// Bearer token required on blob requests, if set.
+    pub auth_token: Option<String>,
+}
+
+impl Default for BlobServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0),
+            auth_token: None,
+        }
+    }
+}
+
+/// Parse a bind address: `host:port`, `[v6]:port`, or a bare IP (random port).
+/// `localhost` is accepted as a host and means `127.0.0.1`.
+pub fn parse_bind_addr(s: &str) -> Result<SocketAddr, String> {
+    let s = s.trim();
+    if let Ok(addr) = s.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    if let Ok(ip) = s
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+    {
+        return Ok(SocketAddr::new(ip, 0));
+    }
+    let (host, port) = s.rsplit_once(':').unwrap_or((s, "0"));
+    if host != "localhost" {
+        return Err(format!(
+            "invalid bind address {:?}: expected IP[:PORT] or localhost[:PORT]",
+            s
+        ));
+    }
+    let port = port
+        .parse::<u16>()
+        .map_err(|_| format!("invalid port in bind address {:?}", s))?;
+    Ok(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port))
+}
+
 /// Start the blob HTTP server on a random localhost port.
 ///
 /// Returns the port the server is listening on. The server runs as a
 /// spawned task on the current tokio runtime.
 pub async fn start_blob_server(store: Arc<BlobStore>) -> std::io::Result<u16> {
-    let listener = TcpListener::bind("127.0.0.1:0").await?;
-    let port = listener.local_addr()?.port();
+    start_blob_server_with_config(store, &BlobServerConfig::default()).await
+}
+
+/// Start the blob HTTP server with an explicit [`BlobServerConfig`].
+///
+/// If the configured port is already in use, falls back to an OS-assigned
+/// port on the same interface. Returns the port actually bound, which the
+/// daemon records in `daemon.json` for clients to discover.
+pub async fn start_blob_server_with_config(
+    store: Arc<BlobStore>,
+    config: &BlobServerConfig,
+) -> std::io::Result<u16> {
+    let listener = bind_with_fallback(config.bind_addr).await?;
+    let local_addr = listener.local_addr()?;
+    let port = local_addr.port();
+    let auth_token: Option<Arc<str>> = config.auth_token.as_deref().map(Arc::from);
 
-    info!("[blob-server] Listening on http://127.0.0.1:{}", port);
+    info!(
+        "[blob-server] Listening on http://{}{}",
+        local_addr,
+        if auth_token.is_some() {
+            " (token required)"
+        } else {
+            ""
+        }
+    );
 
     tokio::spawn(async move {
         loop {
             match listener.accept().await {
                 Ok((stream, _)) => {
                     let store = store.clone();
+                    let auth_token = auth_token.clone();
                     let io = TokioIo::new(stream);
                     tokio::spawn(async move {
-                        let service = service_fn(move |req| handle_request(req, store.clone()));
+                        let service = service_fn(move |req| {
+                            handle_request(req, store.clone(), auth_token.clone())
+                        });
                         if let Err(e) = http1::Builder::new().serve_connection(io, service).await {
                             if !e.is_incomplete_message() && !e.is_canceled() {
                                 error!("[blob-server] Connection error: {}", e);
@@ -62,18 +138,43 @@ pub async fn start_blob_server(store: Arc<BlobStore>) -> std::io::Result<u16> {
     Ok(port)
 }
 
+/// Bind `addr`, retrying on an ephemeral port if the preferred one is taken.
+async fn bind_with_fallback(addr: SocketAddr) -> std::io::Result<TcpListener> {
+    match TcpListener::bind(addr).await {
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse && addr.port() != 0 => {
+            warn!(
+                "[blob-server] Port {} is in use, falling back to a random port on {}",
+                addr.port(),
+                addr.ip()
+            );
+            TcpListener::bind(SocketAddr::new(addr.ip(), 0)).await
+        }
+        result => result,
+    }
+}
+
 /// Handle a single HTTP request.
 async fn handle_request(
     req: Request<hyper::body::Incoming>,
     store: Arc<BlobStore>,
+    auth_token: Option<Arc<str>>,
 ) -> Result<Response<Full<Bytes>>, Infallible> {
     let path = req.uri().path();
     let method = req.method();
 
-    let response = if method != Method::GET {
+    let response = if method == Method::OPTIONS {
+        preflight_response()
+    } else if method != Method::GET {
         text_response(StatusCode::METHOD_NOT_ALLOWED, "Method Not Allowed")
     } else if path == "/health" {
         text_response(StatusCode::OK, "OK")
+    } else if !is_authorized(&req, auth_token.as_deref()) {
+        let mut response = text_response(StatusCode::UNAUTHORIZED, "Unauthorized");
+        response.headers_mut().insert(
+            "WWW-Authenticate",
+            hyper::header::HeaderValue::from_static("Bearer"),
+        );
+        response
     } else if let Some(hash) = path.strip_prefix("/blob/") {
         serve_blob(&store, hash).await
     } else {
@@ -83,6 +184,37 @@ async fn handle_request(
     Ok(response)
 }
 
+/// Whether the request carries the expected bearer token (always true when
+/// no token is configured).
+fn is_authorized<B>(req: &Request<B>, expected: Option<&str>) -> bool {
+    let Some(expected) = expected else {
+        return true;
+    };
+    req.headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(token.trim().as_bytes(), expected.as_bytes()))
+}
+
+/// Compare without short-circuiting so response timing doesn't leak how
+/// much of the token matched.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Answer a CORS preflight so browsers may send the `Authorization` header.
+fn preflight_response() -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Access-Control-Allow-Methods", "GET, OPTIONS")
+        .header("Access-Control-Allow-Headers", "Authorization")
+        .header("Access-Control-Max-Age", "86400")
+        .body(Full::new(Bytes::new()))
+        .expect("response builder should not fail")
+}
+
 /// Serve a blob by hash with correct Content-Type.
 async fn serve_blob(store: &BlobStore, hash: &str) -> Response<Full<Bytes>> {
     let (blob_result, meta_result) = tokio::join!(store.get(hash), store.get_meta(hash));
@@ -135,15 +267,28 @@ mod tests {
     }
 
     async fn get(port: u16, path: &str) -> (StatusCode, Vec<(String, String)>, Vec<u8>) {
+        request(port, "GET", path, &[]).await
+    }
+
+    async fn request(
+        port: u16,
+        method: &str,
+        path: &str,
+        extra_headers: &[(&str, &str)],
+    ) -> (StatusCode, Vec<(String, String)>, Vec<u8>) {
         use tokio::io::{AsyncReadExt, AsyncWriteExt};
         use tokio::net::TcpStream;
 
         let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port))
             .await
             .unwrap();
+        let extra: String = extra_headers
+            .iter()
+            .map(|(name, value)| format!("{}: {}\r\n", name, value))
+            .collect();
         let request = format!(
-            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
-            path
+            "{} {} HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n",
+            method, path, extra
         );
         stream.write_all(request.as_bytes()).await.unwrap();
 
@@ -238,4 +383,96 @@ mod tests {
         let port2 = start_blob_server(store.clone()).await.unwrap();
         assert_ne!(port1, port2);
     }
+
+    #[tokio::test]
+    async fn test_taken_port_falls_back_to_random_port() {
+        let dir = TempDir::new().unwrap();
+        let store = Arc::new(BlobStore::new(dir.path().join("blobs")));
+        let squatter = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let taken = squatter.local_addr().unwrap();
+        let config = BlobServerConfig {
+            bind_addr: taken,
+            ..Default::default()
+        };
+
+        let port = start_blob_server_with_config(store.clone(), &config)
+            .await
+            .unwrap();
+        assert_ne!(port, taken.port());
+
+        let (status, _, _) = get(port, "/health").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_auth_token_required_when_set() {
+        let dir = TempDir::new().unwrap();
+        let store = Arc::new(BlobStore::new(dir.path().join("blobs")));
+        let config = BlobServerConfig {
+            auth_token: Some("s3cret".to_string()),
+            ..Default::default()
+        };
+        let port = start_blob_server_with_config(store.clone(), &config)
+            .await
+            .unwrap();
+        let hash = store.put(b"secret output", "text/plain").await.unwrap();
+        let path = format!("/blob/{}", hash);
+
+        let (status, headers, _) = get(port, &path).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            header_value(&headers, "www-authenticate"),
+            Some("Bearer".into())
+        );
+
+        let (status, _, _) =
+            request(port, "GET", &path, &[("Authorization", "Bearer wrong")]).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _, body) =
+            request(port, "GET", &path, &[("Authorization", "Bearer s3cret")]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"secret output");
+
+        // Health checks and CORS preflights don't need the token
+        let (status, _, _) = get(port, "/health").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, headers, _) = request(port, "OPTIONS", &path, &[]).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(
+            header_value(&headers, "access-control-allow-headers"),
+            Some("Authorization".into())
+        );
+    }
+
+    #[test]
+    fn test_parse_bind_addr() {
+        assert_eq!(
+            parse_bind_addr("0.0.0.0:8765").unwrap(),
+            "0.0.0.0:8765".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            parse_bind_addr("192.168.1.20").unwrap(),
+            "192.168.1.20:0".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            parse_bind_addr("[::1]:9000").unwrap(),
+            "[::1]:9000".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            parse_bind_addr("::").unwrap(),
+            "[::]:0".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            parse_bind_addr("localhost:7000").unwrap(),
+            "127.0.0.1:7000".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            parse_bind_addr("localhost").unwrap(),
+            BlobServerConfig::default().bind_addr
+        );
+        assert!(parse_bind_addr("example.com:80").is_err());
+        assert!(parse_bind_addr("127.0.0.1:99999").is_err());
+        assert!(parse_bind_addr("localhost:http").is_err());
+    }
 }
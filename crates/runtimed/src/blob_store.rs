@@ -13,9 +13,16 @@
 //!
 //! All writes are atomic: data is written to a temp file in the shard
 //! directory and renamed into place, so readers never see partial writes.
+//!
+//! Identical bytes always map to one blob. The metadata sidecar carries a
+//! reference count: every `put()` of the same content adds a reference and
+//! `release()` drops one, deleting the blob when none remain. Callers that
+//! already hold a reference can compare `content_hash()` against it to skip
+//! a `put()` that would change nothing.
 
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -30,12 +37,27 @@ pub struct BlobMeta {
     pub media_type: String,
     pub size: u64,
     pub created_at: DateTime<Utc>,
+    /// Number of `put()`s of these bytes not yet `release()`d. Sidecars
+    /// written before reference counting existed read as [`UNCOUNTED_REFS`].
+    #[serde(default = "default_ref_count")]
+    pub ref_count: u64,
+}
+
+/// Reference count of a blob stored before counting existed. Any number of
+/// saved notebooks may point at it, so it is never released.
+pub const UNCOUNTED_REFS: u64 = u64::MAX;
+
+fn default_ref_count() -> u64 {
+    UNCOUNTED_REFS
 }
 
 /// Content-addressed on-disk blob store.
 #[derive(Debug, Clone)]
 pub struct BlobStore {
     root: PathBuf,
+    /// Serializes metadata read-modify-writes so concurrent puts and releases
+    /// of the same content never lose a reference.
+    meta_lock: Arc<tokio::sync::Mutex<()>>,
 }
 
 impl BlobStore {
@@ -43,18 +65,22 @@ impl BlobStore {
     ///
     /// The directory is created lazily on first `put()`.
     pub fn new(root: PathBuf) -> Self {
-        Self { root }
+        Self {
+            root,
+            meta_lock: Arc::new(tokio::sync::Mutex::new(())),
+        }
     }
 
     /// Store `data` with the given `media_type`.
     ///
-    /// Returns the SHA-256 hex hash of the raw bytes.
+    /// Returns the SHA-256 hex hash of the raw bytes, computed once per call.
     /// Rejects data larger than 100 MiB.
-    /// Idempotent: if the blob already exists, returns the hash without writing.
+    /// If the blob already exists its bytes are not rewritten; the put only
+    /// adds a reference to the existing blob.
     ///
     /// Concurrent puts of identical content are safe: if another writer places
-    /// the blob or metadata first (e.g. `rename` fails with `AlreadyExists` on
-    /// Windows), we detect the existing file and return `Ok(hash)`.
+    /// the blob first (e.g. `rename` fails with `AlreadyExists` on Windows), we
+    /// detect the existing file and count our reference against it.
     pub async fn put(&self, data: &[u8], media_type: &str) -> io::Result<String> {
         if data.len() > MAX_BLOB_SIZE {
             return Err(io::Error::new(
@@ -67,12 +93,17 @@ impl BlobStore {
             ));
         }
 
-        let hash = hex::encode(Sha256::digest(data));
+        let hash = Self::content_hash(data);
         let (shard_dir, blob_path, meta_path) = self.paths(&hash);
 
-        // Fast path: both files already present — nothing to do.
-        if blob_path.exists() && meta_path.exists() {
-            return Ok(hash);
+        // Fast path: content already stored — just add a reference.
+        if blob_path.exists() {
+            let _guard = self.meta_lock.lock().await;
+            if let Some(mut meta) = Self::read_meta(&meta_path).await? {
+                meta.ref_count = meta.ref_count.saturating_add(1);
+                Self::write_meta(&shard_dir, &meta_path, &meta).await?;
+                return Ok(hash);
+            }
         }
 
         tokio::fs::create_dir_all(&shard_dir).await?;
@@ -106,37 +137,80 @@ impl BlobStore {
         }
 
         // --- Metadata sidecar ---
-        let meta = BlobMeta {
-            media_type: media_type.to_string(),
-            size: data.len() as u64,
-            created_at: Utc::now(),
+        let _guard = self.meta_lock.lock().await;
+        let meta = match Self::read_meta(&meta_path).await? {
+            // Concurrent writer placed metadata first — add our reference.
+            Some(existing) => BlobMeta {
+                ref_count: existing.ref_count.saturating_add(1),
+                ..existing
+            },
+            None => BlobMeta {
+                media_type: media_type.to_string(),
+                size: data.len() as u64,
+                created_at: Utc::now(),
+                ref_count: 1,
+            },
         };
-        let meta_json = serde_json::to_string(&meta).map_err(io::Error::other)?;
+        if let Err(e) = Self::write_meta(&shard_dir, &meta_path, &meta).await {
+            // Metadata write failed. If *we* created the blob (not a
+            // concurrent writer), remove it to avoid leaving orphaned data.
+            if we_wrote_blob && meta.ref_count == 1 {
+                tokio::fs::remove_file(&blob_path).await.ok();
+            }
+            return Err(e);
+        }
 
-        let tmp_meta = shard_dir.join(format!(".tmp.{}.meta", uuid::Uuid::new_v4()));
-        match async {
-            tokio::fs::write(&tmp_meta, meta_json).await?;
-            tokio::fs::rename(&tmp_meta, &meta_path).await
+        Ok(hash)
+    }
+
+    /// The hash `put()` would store `data` under, without touching the store.
+    pub fn content_hash(data: &[u8]) -> String {
+        hex::encode(Sha256::digest(data))
+    }
+
+    /// Add a reference to a stored blob, for an output copied to another
+    /// cell. Returns `false` if the blob doesn't exist.
+    pub async fn retain(&self, hash: &str) -> io::Result<bool> {
+        if !Self::validate_hash(hash) {
+            return Ok(false);
         }
-        .await
-        {
-            Ok(()) => {}
-            Err(e) => {
-                tokio::fs::remove_file(&tmp_meta).await.ok();
-                if meta_path.exists() {
-                    // Concurrent writer placed metadata — done.
-                    return Ok(hash);
-                }
-                // Metadata write truly failed. If *we* created the blob (not a
-                // concurrent writer), remove it to avoid leaving orphaned data.
-                if we_wrote_blob {
-                    tokio::fs::remove_file(&blob_path).await.ok();
-                }
-                return Err(e);
+        let (shard_dir, blob_path, meta_path) = self.paths(hash);
+        let _guard = self.meta_lock.lock().await;
+        match Self::read_meta(&meta_path).await? {
+            Some(mut meta) if blob_path.exists() => {
+                meta.ref_count = meta.ref_count.saturating_add(1);
+                Self::write_meta(&shard_dir, &meta_path, &meta).await?;
+                Ok(true)
             }
+            _ => Ok(false),
         }
+    }
 
-        Ok(hash)
+    /// Drop one reference to a blob, deleting it once no references remain.
+    ///
+    /// Returns `true` if this released the last reference and the blob was
+    /// deleted, `false` if references remain or the blob doesn't exist.
+    /// Blobs with [`UNCOUNTED_REFS`] are kept.
+    pub async fn release(&self, hash: &str) -> io::Result<bool> {
+        if !Self::validate_hash(hash) {
+            return Ok(false);
+        }
+        let (shard_dir, blob_path, meta_path) = self.paths(hash);
+        let _guard = self.meta_lock.lock().await;
+        match Self::read_meta(&meta_path).await? {
+            Some(meta) if meta.ref_count == UNCOUNTED_REFS => Ok(false),
+            Some(mut meta) if meta.ref_count > 1 => {
+                meta.ref_count -= 1;
+                Self::write_meta(&shard_dir, &meta_path, &meta).await?;
+                Ok(false)
+            }
+            _ if blob_path.exists() => {
+                tokio::fs::remove_file(&blob_path).await.ok();
+                tokio::fs::remove_file(&meta_path).await.ok();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
     }
 
     /// Retrieve blob bytes by hash. Returns `None` if not found.
@@ -158,15 +232,7 @@ impl BlobStore {
             return Ok(None);
         }
         let (_, _, meta_path) = self.paths(hash);
-        match tokio::fs::read_to_string(&meta_path).await {
-            Ok(json) => {
-                let meta: BlobMeta = serde_json::from_str(&json)
-                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-                Ok(Some(meta))
-            }
-            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
-            Err(e) => Err(e),
-        }
+        Self::read_meta(&meta_path).await
     }
 
     /// Check if a blob exists (without reading it).
@@ -178,12 +244,14 @@ impl BlobStore {
         blob_path.exists()
     }
 
-    /// Delete a blob and its metadata. Returns `true` if the blob existed.
+    /// Delete a blob and its metadata regardless of its reference count.
+    /// Returns `true` if the blob existed.
     pub async fn delete(&self, hash: &str) -> io::Result<bool> {
         if !Self::validate_hash(hash) {
             return Ok(false);
         }
         let (_, blob_path, meta_path) = self.paths(hash);
+        let _guard = self.meta_lock.lock().await;
         let existed = blob_path.exists();
         if existed {
             tokio::fs::remove_file(&blob_path).await.ok();
@@ -229,6 +297,33 @@ impl BlobStore {
         Ok(hashes)
     }
 
+    async fn read_meta(meta_path: &Path) -> io::Result<Option<BlobMeta>> {
+        match tokio::fs::read_to_string(meta_path).await {
+            Ok(json) => {
+                let meta: BlobMeta = serde_json::from_str(&json)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                Ok(Some(meta))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Atomically replace a metadata sidecar (temp file + rename).
+    async fn write_meta(shard_dir: &Path, meta_path: &Path, meta: &BlobMeta) -> io::Result<()> {
+        let meta_json = serde_json::to_string(meta).map_err(io::Error::other)?;
+        let tmp_meta = shard_dir.join(format!(".tmp.{}.meta", uuid::Uuid::new_v4()));
+        let result = async {
+            tokio::fs::write(&tmp_meta, meta_json).await?;
+            tokio::fs::rename(&tmp_meta, meta_path).await
+        }
+        .await;
+        if result.is_err() {
+            tokio::fs::remove_file(&tmp_meta).await.ok();
+        }
+        result
+    }
+
     /// Compute shard dir, blob path, and meta path for a given hash.
     fn paths(&self, hash: &str) -> (PathBuf, PathBuf, PathBuf) {
         let shard = &hash[..2];
@@ -278,6 +373,82 @@ mod tests {
         assert_eq!(hash1, hash2);
     }
 
+    #[tokio::test]
+    async fn test_identical_puts_share_one_blob() {
+        let dir = TempDir::new().unwrap();
+        let store = test_store(&dir);
+
+        let data = vec![7u8; 4096];
+        let hash1 = store.put(&data, "image/png").await.unwrap();
+        let hash2 = store.put(&data, "image/png").await.unwrap();
+        assert_eq!(hash1, hash2);
+
+        assert_eq!(store.list().await.unwrap(), vec![hash1.clone()]);
+        let meta = store.get_meta(&hash1).await.unwrap().unwrap();
+        assert_eq!(meta.ref_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_release_deletes_after_last_reference() {
+        let dir = TempDir::new().unwrap();
+        let store = test_store(&dir);
+
+        let hash = store.put(b"shared", "text/plain").await.unwrap();
+        store.put(b"shared", "text/plain").await.unwrap();
+
+        assert!(!store.release(&hash).await.unwrap());
+        assert!(store.exists(&hash));
+        assert_eq!(store.get_meta(&hash).await.unwrap().unwrap().ref_count, 1);
+
+        assert!(store.release(&hash).await.unwrap());
+        assert!(!store.exists(&hash));
+        assert!(store.get_meta(&hash).await.unwrap().is_none());
+
+        // Releasing a missing blob is a no-op
+        assert!(!store.release(&hash).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_retain_adds_a_reference() {
+        let dir = TempDir::new().unwrap();
+        let store = test_store(&dir);
+
+        let hash = store.put(b"copied", "text/plain").await.unwrap();
+        assert!(store.retain(&hash).await.unwrap());
+        assert_eq!(store.get_meta(&hash).await.unwrap().unwrap().ref_count, 2);
+
+        assert!(!store.release(&hash).await.unwrap());
+        assert!(store.release(&hash).await.unwrap());
+        // Nothing to retain once it's gone
+        assert!(!store.retain(&hash).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_meta_is_never_released() {
+        let dir = TempDir::new().unwrap();
+        let store = test_store(&dir);
+
+        let hash = store.put(b"legacy", "text/plain").await.unwrap();
+        let (_, _, meta_path) = store.paths(&hash);
+        std::fs::write(
+            &meta_path,
+            r#"{"media_type":"text/plain","size":6,"created_at":"2024-01-01T00:00:00Z"}"#,
+        )
+        .unwrap();
+
+        // Other saved notebooks may still point at it
+        assert!(!store.release(&hash).await.unwrap());
+        assert!(store.exists(&hash));
+
+        // New references don't make it countable
+        store.put(b"legacy", "text/plain").await.unwrap();
+        assert!(store.retain(&hash).await.unwrap());
+        let meta = store.get_meta(&hash).await.unwrap().unwrap();
+        assert_eq!(meta.ref_count, UNCOUNTED_REFS);
+        assert!(!store.release(&hash).await.unwrap());
+        assert!(store.exists(&hash));
+    }
+
     #[tokio::test]
     async fn test_same_bytes_different_media_type() {
         let dir = TempDir::new().unwrap();
@@ -417,5 +588,7 @@ mod tests {
 
         assert_eq!(hash1, hash2);
         assert_eq!(store.get(&hash1).await.unwrap().unwrap(), data);
+        let meta = store.get_meta(&hash1).await.unwrap().unwrap();
+        assert_eq!(meta.ref_count, 2);
     }
 }
@@ -0,0 +1,203 @@
+//! Coalescing of high-frequency kernel broadcasts for one connected peer.
+//!
+//! Progress bars and chatty streams produce a burst of broadcasts that each
+//! replace the previous one: `update_display_data` for the same display_id,
+//! or a stream upsert for the same output slot. Forwarding every one of them
+//! makes a slow window fall behind. Instead, these are held for a short
+//! window and only the latest per key is sent.
+//!
+//! Every other broadcast is sent immediately, after flushing anything
+//! pending, so peers still see events in the order the kernel produced them.
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::protocol::NotebookBroadcast;
+
+/// How long superseding broadcasts are held before being forwarded.
+pub const COALESCE_WINDOW: Duration = Duration::from_millis(32);
+
+/// What a coalescable broadcast replaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CoalesceKey {
+    /// Stream output at `output_index` in a cell.
+    Output {
+        cell_id: String,
+        output_index: usize,
+    },
+    /// Display output with this display_id.
+    Display(String),
+}
+
+fn coalesce_key(broadcast: &NotebookBroadcast) -> Option<CoalesceKey> {
+    match broadcast {
+        NotebookBroadcast::Output {
+            cell_id,
+            output_index: Some(output_index),
+            ..
+        } => Some(CoalesceKey::Output {
+            cell_id: cell_id.clone(),
+            output_index: *output_index,
+        }),
+        NotebookBroadcast::DisplayUpdate { display_id, .. } => {
+            Some(CoalesceKey::Display(display_id.clone()))
+        }
+        _ => None,
+    }
+}
+
+/// Holds superseding broadcasts for up to [`COALESCE_WINDOW`].
+///
+/// The number of pending broadcasts is bounded by the number of distinct
+/// outputs being updated, not by how fast the kernel sends.
+#[derive(Debug, Default)]
+pub struct BroadcastCoalescer {
+    pending: Vec<(CoalesceKey, NotebookBroadcast)>,
+    flush_at: Option<Instant>,
+}
+
+impl BroadcastCoalescer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a broadcast received at `now`.
+    ///
+    /// Returns the broadcasts to forward immediately, in order: empty if
+    /// this one was held, otherwise everything pending followed by it.
+    pub fn push(&mut self, broadcast: NotebookBroadcast, now: Instant) -> Vec<NotebookBroadcast> {
+        let Some(key) = coalesce_key(&broadcast) else {
+            let mut ready = self.flush();
+            ready.push(broadcast);
+            return ready;
+        };
+
+        if let Some(slot) = self.pending.iter_mut().find(|(k, _)| *k == key) {
+            slot.1 = broadcast;
+        } else {
+            self.pending.push((key, broadcast));
+        }
+        // Not extended by later updates, so a steady stream still gets
+        // forwarded once per window
+        self.flush_at.get_or_insert(now + COALESCE_WINDOW);
+        Vec::new()
+    }
+
+    /// When pending broadcasts are due to be flushed, if any are held.
+    pub fn flush_at(&self) -> Option<Instant> {
+        self.flush_at
+    }
+
+    /// Take everything pending, in the order first received.
+    pub fn flush(&mut self) -> Vec<NotebookBroadcast> {
+        self.flush_at = None;
+        self.pending.drain(..).map(|(_, b)| b).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(cell_id: &str, output_index: usize, text: &str) -> NotebookBroadcast {
+        NotebookBroadcast::Output {
+            cell_id: cell_id.to_string(),
+            output_type: "stream".to_string(),
+            output_json: text.to_string(),
+            output_index: Some(output_index),
+        }
+    }
+
+    fn display_update(display_id: &str, n: usize) -> NotebookBroadcast {
+        NotebookBroadcast::DisplayUpdate {
+            display_id: display_id.to_string(),
+            data: serde_json::json!({ "text/plain": n.to_string() }),
+            metadata: serde_json::Map::new(),
+        }
+    }
+
+    #[test]
+    fn test_burst_is_coalesced_to_latest_per_output() {
+        let mut coalescer = BroadcastCoalescer::new();
+        let now = Instant::now();
+
+        for n in 0..1000 {
+            assert!(coalescer
+                .push(display_update("progress", n), now)
+                .is_empty());
+            assert!(coalescer
+                .push(stream("c1", 0, &format!("line {}", n)), now)
+                .is_empty());
+        }
+        assert_eq!(coalescer.flush_at(), Some(now + COALESCE_WINDOW));
+
+        let flushed = coalescer.flush();
+        assert_eq!(flushed.len(), 2);
+        assert!(matches!(
+            &flushed[0],
+            NotebookBroadcast::DisplayUpdate { data, .. } if data["text/plain"] == "999"
+        ));
+        assert!(matches!(
+            &flushed[1],
+            NotebookBroadcast::Output { output_json, .. } if output_json == "line 999"
+        ));
+        assert_eq!(coalescer.flush_at(), None);
+    }
+
+    #[test]
+    fn test_distinct_outputs_are_kept_apart() {
+        let mut coalescer = BroadcastCoalescer::new();
+        let now = Instant::now();
+
+        coalescer.push(stream("c1", 0, "stdout"), now);
+        coalescer.push(stream("c1", 1, "stderr"), now);
+        coalescer.push(stream("c2", 0, "other cell"), now);
+        coalescer.push(display_update("a", 1), now);
+        coalescer.push(display_update("b", 1), now);
+
+        assert_eq!(coalescer.flush().len(), 5);
+    }
+
+    #[test]
+    fn test_other_broadcasts_flush_pending_first() {
+        let mut coalescer = BroadcastCoalescer::new();
+        let now = Instant::now();
+
+        coalescer.push(stream("c1", 0, "partial"), now);
+        coalescer.push(stream("c1", 0, "final"), now);
+        let ready = coalescer.push(
+            NotebookBroadcast::ExecutionDone {
+                cell_id: "c1".to_string(),
+            },
+            now,
+        );
+
+        assert_eq!(ready.len(), 2);
+        assert!(matches!(
+            &ready[0],
+            NotebookBroadcast::Output { output_json, .. } if output_json == "final"
+        ));
+        assert!(matches!(&ready[1], NotebookBroadcast::ExecutionDone { .. }));
+        assert_eq!(coalescer.flush_at(), None);
+
+        // Appended outputs (no index) are never held
+        let appended = NotebookBroadcast::Output {
+            cell_id: "c1".to_string(),
+            output_type: "display_data".to_string(),
+            output_json: "{}".to_string(),
+            output_index: None,
+        };
+        assert_eq!(coalescer.push(appended, now).len(), 1);
+    }
+
+    #[test]
+    fn test_window_is_not_extended_by_later_updates() {
+        let mut coalescer = BroadcastCoalescer::new();
+        let start = Instant::now();
+
+        coalescer.push(display_update("progress", 0), start);
+        coalescer.push(display_update("progress", 1), start + COALESCE_WINDOW / 2);
+        assert_eq!(coalescer.flush_at(), Some(start + COALESCE_WINDOW));
+    }
+}
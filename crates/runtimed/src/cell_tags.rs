@@ -0,0 +1,109 @@
+//! Cell metadata tags from the wider Jupyter ecosystem that change how cells
+//! run, following nbclient, JupyterLab and papermill:
+//!
+//! - `skip-execution`: left out of Run All
+//! - `raises-exception`: the cell is expected to fail, so its error doesn't
+//!   stop the rest of a batch
+//! - `parameters`: the papermill parameters cell, where injected values go
+//! - `injected-parameters`: the cell papermill inserts after it
+
+use crate::kernel_manager::QueuedCell;
+use crate::notebook_doc::CellSnapshot;
+
+pub const SKIP_EXECUTION: &str = "skip-execution";
+pub const RAISES_EXCEPTION: &str = "raises-exception";
+pub const PARAMETERS: &str = "parameters";
+pub const INJECTED_PARAMETERS: &str = "injected-parameters";
+
+fn has_tag(cell: &CellSnapshot, tag: &str) -> bool {
+    cell.tags.iter().any(|t| t == tag)
+}
+
+/// Queue entry for a code cell. Cells tagged `raises-exception` are sent
+/// with `stop_on_error` off so the kernel keeps running the cells after them.
+pub fn queued_cell(cell: &CellSnapshot) -> QueuedCell {
+    QueuedCell {
+        cell_id: cell.id.clone(),
+        code: cell.source.clone(),
+        stop_on_error: !has_tag(cell, RAISES_EXCEPTION),
+    }
+}
+
+/// The cells Run All executes, in document order: every code cell not
+/// tagged `skip-execution`.
+pub fn run_all_cells(cells: &[CellSnapshot]) -> Vec<QueuedCell> {
+    cells
+        .iter()
+        .filter(|cell| cell.cell_type == "code" && !has_tag(cell, SKIP_EXECUTION))
+        .map(queued_cell)
+        .collect()
+}
+
+/// The notebook's `parameters` cell, if one is tagged. Papermill uses the
+/// first.
+pub fn parameters_cell(cells: &[CellSnapshot]) -> Option<&CellSnapshot> {
+    cells
+        .iter()
+        .find(|cell| cell.cell_type == "code" && has_tag(cell, PARAMETERS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(id: &str, cell_type: &str, tags: &[&str]) -> CellSnapshot {
+        CellSnapshot {
+            id: id.to_string(),
+            cell_type: cell_type.to_string(),
+            source: format!("# {id}"),
+            execution_count: "null".to_string(),
+            outputs: vec![],
+            execution: None,
+            collapsed: None,
+            scrolled: None,
+            collapsed_outputs: vec![],
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_run_all_skips_skip_execution_cells() {
+        let cells = vec![
+            cell("a", "code", &[]),
+            cell("b", "code", &[SKIP_EXECUTION]),
+            cell("c", "markdown", &[]),
+            cell("d", "code", &["slow"]),
+        ];
+        let ids: Vec<_> = run_all_cells(&cells)
+            .into_iter()
+            .map(|c| c.cell_id)
+            .collect();
+        assert_eq!(ids, vec!["a", "d"]);
+    }
+
+    #[test]
+    fn test_raises_exception_cells_do_not_stop_on_error() {
+        let cells = vec![
+            cell("a", "code", &[RAISES_EXCEPTION]),
+            cell("b", "code", &[]),
+        ];
+        let batch = run_all_cells(&cells);
+        assert!(!batch[0].stop_on_error);
+        assert!(batch[1].stop_on_error);
+        assert_eq!(batch[0].code, "# a");
+    }
+
+    #[test]
+    fn test_parameters_cell() {
+        let cells = vec![
+            cell("a", "markdown", &[PARAMETERS]),
+            cell("b", "code", &[]),
+            cell("c", "code", &[PARAMETERS]),
+            cell("d", "code", &[PARAMETERS]),
+        ];
+        assert_eq!(parameters_cell(&cells).map(|c| c.id.as_str()), Some("c"));
+        // Recognized, but otherwise run like any other cell
+        assert_eq!(run_all_cells(&cells).len(), 3);
+        assert_eq!(parameters_cell(&cells[..2]), None);
+    }
+}
@@ -13,6 +13,8 @@ use tokio::io::{AsyncRead, AsyncWrite};
 use serde::Serialize;
 
 use crate::connection::{self, Handshake};
+use crate::env_cache::{EnvCacheCleared, EnvCacheInfo};
+use crate::kernel_pids::TrackedKernel;
 use crate::protocol::{Request, Response};
 use crate::{default_socket_path, EnvType, PoolStats, PooledEnv};
 
@@ -225,6 +227,105 @@ impl PoolClient {
         }
     }
 
+    /// List notebooks from the daemon's registry, most recently opened first.
+    pub async fn list_notebooks(
+        &self,
+    ) -> Result<Vec<crate::notebook_registry::NotebookRecord>, ClientError> {
+        let response = self.send_request(Request::ListNotebooks).await?;
+        match response {
+            Response::NotebooksList { notebooks } => Ok(notebooks),
+            Response::Error { message } => Err(ClientError::DaemonError(message)),
+            _ => Err(ClientError::ProtocolError(
+                "Unexpected response".to_string(),
+            )),
+        }
+    }
+
+    /// Shut down the kernel of every notebook the daemon has open.
+    ///
+    /// Returns the IDs of the notebooks whose kernel was stopped.
+    pub async fn shutdown_all_kernels(&self) -> Result<Vec<String>, ClientError> {
+        let response = self.send_request(Request::ShutdownAllKernels).await?;
+        match response {
+            Response::KernelsShutDown { notebook_ids } => Ok(notebook_ids),
+            Response::Error { message } => Err(ClientError::DaemonError(message)),
+            _ => Err(ClientError::ProtocolError(
+                "Unexpected response".to_string(),
+            )),
+        }
+    }
+
+    /// List cached environments with their disk usage.
+    pub async fn get_env_cache_info(&self) -> Result<EnvCacheInfo, ClientError> {
+        let response = self.send_request(Request::GetEnvCacheInfo).await?;
+        match response {
+            Response::EnvCacheInfo { info } => Ok(info),
+            Response::Error { message } => Err(ClientError::DaemonError(message)),
+            _ => Err(ClientError::ProtocolError(
+                "Unexpected response".to_string(),
+            )),
+        }
+    }
+
+    /// Delete the named cached environments. Envs a running kernel uses or
+    /// that are pinned to a notebook are left alone and reported in
+    /// [`EnvCacheCleared::in_use`] and [`EnvCacheCleared::pinned`].
+    pub async fn clear_env_cache(
+        &self,
+        env_ids: Vec<String>,
+    ) -> Result<EnvCacheCleared, ClientError> {
+        let response = self
+            .send_request(Request::ClearEnvCache { env_ids })
+            .await?;
+        match response {
+            Response::EnvCacheCleared { cleared } => Ok(cleared),
+            Response::Error { message } => Err(ClientError::DaemonError(message)),
+            _ => Err(ClientError::ProtocolError(
+                "Unexpected response".to_string(),
+            )),
+        }
+    }
+
+    /// Kill kernel processes no open room owns. Returns the kernels killed.
+    pub async fn reap_kernels(&self) -> Result<Vec<TrackedKernel>, ClientError> {
+        let response = self.send_request(Request::ReapKernels).await?;
+        match response {
+            Response::KernelsReaped { reaped } => Ok(reaped),
+            Response::Error { message } => Err(ClientError::DaemonError(message)),
+            _ => Err(ClientError::ProtocolError(
+                "Unexpected response".to_string(),
+            )),
+        }
+    }
+
+    /// Export the synced settings as JSON, without machine-specific paths.
+    pub async fn export_settings(&self) -> Result<String, ClientError> {
+        let response = self.send_request(Request::ExportSettings).await?;
+        match response {
+            Response::SettingsExported { json } => Ok(json),
+            Response::Error { message } => Err(ClientError::DaemonError(message)),
+            _ => Err(ClientError::ProtocolError(
+                "Unexpected response".to_string(),
+            )),
+        }
+    }
+
+    /// Apply settings JSON from [`export_settings`](Self::export_settings),
+    /// merged over the current settings unless `replace` is set. Returns
+    /// whether any setting changed.
+    pub async fn import_settings(&self, json: String, replace: bool) -> Result<bool, ClientError> {
+        let response = self
+            .send_request(Request::ImportSettings { json, replace })
+            .await?;
+        match response {
+            Response::SettingsImported { changed } => Ok(changed),
+            Response::Error { message } => Err(ClientError::DaemonError(message)),
+            _ => Err(ClientError::ProtocolError(
+                "Unexpected response".to_string(),
+            )),
+        }
+    }
+
     /// Send a request to the daemon and receive a response.
     async fn send_request(&self, request: Request) -> Result<Response, ClientError> {
         #[cfg(unix)]
@@ -3,16 +3,17 @@
 //! The daemon manages prewarmed environment pools and handles requests from
 //! notebook windows via IPC (Unix domain sockets on Unix, named pipes on Windows).
 
-use std::collections::{HashMap, VecDeque};
-use std::path::PathBuf;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
 use std::time::Instant;
 
+use kernel_env::dep_tree::package_name;
 use log::{error, info, warn};
 use notify_debouncer_mini::DebounceEventResult;
 use tokio::io::{AsyncRead, AsyncWrite};
-use tokio::sync::{Mutex, Notify};
+use tokio::sync::{Mutex, MutexGuard, Notify};
 
 #[cfg(unix)]
 use tokio::net::UnixListener;
@@ -25,6 +26,11 @@ use tokio::sync::RwLock;
 use crate::blob_server;
 use crate::blob_store::BlobStore;
 use crate::connection::{self, Handshake};
+use crate::env_cache::EnvLeases;
+use crate::env_claims::EnvIdClaims;
+use crate::env_pins::{EnvPins, PinnedEnv};
+use crate::kernel_pids::{KernelPidState, KernelPids, TrackedKernel};
+use crate::notebook_registry::{NotebookRecord, NotebookRegistry};
 use crate::notebook_sync_server::NotebookRooms;
 use crate::protocol::{BlobRequest, BlobResponse, DaemonBroadcast, Request, Response};
 use crate::settings_doc::SettingsDoc;
@@ -45,6 +51,12 @@ pub struct DaemonConfig {
     pub blob_store_dir: PathBuf,
     /// Directory for persisted notebook Automerge documents.
     pub notebook_docs_dir: PathBuf,
+    /// JSON file recording previously opened notebooks.
+    pub notebook_registry_path: PathBuf,
+    /// JSON file recording spawned kernel processes, for reaping orphans.
+    pub kernel_pids_path: PathBuf,
+    /// JSON file recording prewarmed envs pinned to notebooks.
+    pub env_pins_path: PathBuf,
     /// Target number of UV environments to maintain.
     pub uv_pool_size: usize,
     /// Target number of Conda environments to maintain.
@@ -53,6 +65,8 @@ pub struct DaemonConfig {
     pub max_age_secs: u64,
     /// Optional custom directory for lock files (used in tests).
     pub lock_dir: Option<PathBuf>,
+    /// Bind address and auth token for the blob HTTP server.
+    pub blob_server: blob_server::BlobServerConfig,
 }
 
 impl Default for DaemonConfig {
@@ -62,10 +76,14 @@ impl Default for DaemonConfig {
             cache_dir: default_cache_dir(),
             blob_store_dir: default_blob_store_dir(),
             notebook_docs_dir: crate::default_notebook_docs_dir(),
+            notebook_registry_path: crate::default_notebook_registry_path(),
+            kernel_pids_path: crate::default_kernel_pids_path(),
+            env_pins_path: crate::default_env_pins_path(),
             uv_pool_size: 3,
             conda_pool_size: 3,
             max_age_secs: 172800, // 2 days
             lock_dir: None,
+            blob_server: Default::default(),
         }
     }
 }
@@ -149,6 +167,17 @@ fn parse_uv_error(stderr: &str) -> Option<PackageInstallError> {
     None
 }
 
+/// Parse a rattler solve error to identify a package with no candidates.
+///
+/// The resolvo solver reports an unknown package as
+/// "No candidates were found for foo *."
+fn parse_conda_solve_error(message: &str) -> Option<String> {
+    let re = regex::Regex::new(r"no candidates were found for ([a-z0-9_.-]+)").ok()?;
+    let message = message.to_lowercase();
+    let caps = re.captures(&message)?;
+    Some(caps.get(1)?.as_str().to_string())
+}
+
 /// Internal pool state.
 struct Pool {
     /// Available environments ready for use.
@@ -161,6 +190,9 @@ struct Pool {
     max_age_secs: u64,
     /// Failure tracking for exponential backoff.
     failure_state: FailureState,
+    /// Default packages from settings left out of new environments because
+    /// they failed to install. Cleared when settings change.
+    skipped_packages: BTreeSet<String>,
 }
 
 impl Pool {
@@ -171,6 +203,7 @@ impl Pool {
             target,
             max_age_secs,
             failure_state: FailureState::default(),
+            skipped_packages: BTreeSet::new(),
         }
     }
 
@@ -185,6 +218,16 @@ impl Pool {
         }
     }
 
+    /// Empty the pool so the warming loop rebuilds it, returning the env
+    /// directories to delete. Pinned envs are dropped from the pool but kept.
+    fn flush(&mut self, pinned: &HashSet<PathBuf>) -> Vec<PathBuf> {
+        self.available
+            .drain(..)
+            .map(|entry| entry.env.venv_path)
+            .filter(|path| !pinned.contains(path))
+            .collect()
+    }
+
     /// Take an environment from the pool.
     fn take(&mut self) -> Option<PooledEnv> {
         self.prune_stale();
@@ -226,9 +269,35 @@ impl Pool {
         }
     }
 
-    /// Reset failure state (called on settings change).
+    /// Reset failure state and skipped packages (called on settings change).
     fn reset_failure_state(&mut self) {
         self.failure_state = FailureState::default();
+        self.skipped_packages.clear();
+    }
+
+    /// Whether warming is failing or has had to skip packages.
+    fn has_errors(&self) -> bool {
+        self.failure_state.consecutive_failures > 0 || !self.skipped_packages.is_empty()
+    }
+
+    /// Leave `package` out of environments created from now on.
+    ///
+    /// Called when a default package from settings can't be installed, so the
+    /// rest of the pool keeps warming instead of failing on it every time. The
+    /// next attempt doesn't wait out the backoff, since it installs a
+    /// different set of packages.
+    fn skip_package(&mut self, package: &str) {
+        if self.skipped_packages.insert(package_name(package)) {
+            self.failure_state.last_failure = None;
+        }
+    }
+
+    /// `configured` default packages minus any that were skipped.
+    fn default_packages(&self, configured: Vec<String>) -> Vec<String> {
+        configured
+            .into_iter()
+            .filter(|spec| !self.skipped_packages.contains(&package_name(spec)))
+            .collect()
     }
 
     /// Calculate backoff delay based on consecutive failures.
@@ -279,9 +348,22 @@ impl Pool {
 
     /// Get error info for status reporting.
     fn get_error(&self) -> Option<PoolError> {
-        if self.failure_state.consecutive_failures == 0 {
+        if !self.has_errors() {
             return None;
         }
+        let skipped_packages: Vec<String> = self.skipped_packages.iter().cloned().collect();
+        if self.failure_state.consecutive_failures == 0 {
+            return Some(PoolError {
+                message: format!(
+                    "Skipped default packages that failed to install: {}",
+                    skipped_packages.join(", ")
+                ),
+                failed_package: None,
+                consecutive_failures: 0,
+                retry_in_secs: 0,
+                skipped_packages,
+            });
+        }
 
         let retry_in_secs = self
             .failure_state
@@ -302,6 +384,7 @@ impl Pool {
             failed_package: self.failure_state.failed_package.clone(),
             consecutive_failures: self.failure_state.consecutive_failures,
             retry_in_secs,
+            skipped_packages,
         })
     }
 }
@@ -328,13 +411,25 @@ pub struct Daemon {
     blob_port: Mutex<Option<u16>>,
     /// Per-notebook Automerge sync rooms.
     notebook_rooms: NotebookRooms,
+    /// Notebooks opened across daemon restarts (path, last env, last opened).
+    notebook_registry: Mutex<NotebookRegistry>,
+    /// Serializes registry writes, which happen outside `notebook_registry`.
+    notebook_registry_save: Mutex<()>,
+    /// env_ids claimed by running kernels, for collision warnings.
+    env_claims: EnvIdClaims,
+    /// Kernel processes spawned by this or an earlier daemon.
+    kernel_pids: KernelPids,
+    /// Prewarmed envs pinned to notebooks, kept out of the pool.
+    env_pins: Mutex<EnvPins>,
+    /// Envs being built or claimed for a launch, which mustn't be cleared.
+    env_leases: EnvLeases,
 }
 
 /// Error returned when another daemon is already running.
 #[derive(Debug, thiserror::Error)]
 #[error("Another daemon is already running: {info:?}")]
 pub struct DaemonAlreadyRunning {
-    pub info: DaemonInfo,
+    pub info: Box<DaemonInfo>,
 }
 
 impl Daemon {
@@ -360,6 +455,12 @@ impl Daemon {
         let (pool_state_changed, _) = tokio::sync::broadcast::channel(16);
 
         let blob_store = Arc::new(BlobStore::new(config.blob_store_dir.clone()));
+        let notebook_registry = NotebookRegistry::load(&config.notebook_registry_path);
+        let kernel_pids = KernelPids::load(&config.kernel_pids_path);
+        if let Some(boot) = crate::kernel_pids::boot_time() {
+            kernel_pids.forget_started_before(boot);
+        }
+        let env_pins = EnvPins::load(&config.env_pins_path);
 
         Ok(Arc::new(Self {
             uv_pool: Mutex::new(Pool::new(config.uv_pool_size, config.max_age_secs)),
@@ -374,9 +475,84 @@ impl Daemon {
             blob_store,
             blob_port: Mutex::new(None),
             notebook_rooms: Arc::new(Mutex::new(HashMap::new())),
+            notebook_registry: Mutex::new(notebook_registry),
+            notebook_registry_save: Mutex::new(()),
+            env_claims: EnvIdClaims::new(),
+            kernel_pids,
+            env_pins: Mutex::new(env_pins),
+            env_leases: EnvLeases::new(),
         }))
     }
 
+    /// Record that a client opened `notebook_id`, and persist the registry.
+    async fn record_notebook_opened(&self, notebook_id: &str) {
+        let path = Path::new(notebook_id);
+        let is_file = tokio::fs::metadata(path)
+            .await
+            .is_ok_and(|metadata| metadata.is_file());
+        let mut registry = self.notebook_registry.lock().await;
+        registry.record_opened(notebook_id, is_file.then_some(path));
+        self.save_notebook_registry(registry).await;
+    }
+
+    /// Record the env source of a kernel launched for `notebook_id`.
+    pub(crate) async fn record_notebook_env_source(&self, notebook_id: &str, env_source: &str) {
+        let mut registry = self.notebook_registry.lock().await;
+        registry.record_env_source(notebook_id, env_source);
+        self.save_notebook_registry(registry).await;
+    }
+
+    /// Write the registry to disk without holding its lock during the IO.
+    ///
+    /// The save lock is taken before `registry` is released, so writes land
+    /// in the order the snapshots were taken.
+    async fn save_notebook_registry(&self, registry: MutexGuard<'_, NotebookRegistry>) {
+        let snapshot = registry.snapshot();
+        let _save = self.notebook_registry_save.lock().await;
+        drop(registry);
+        let result = match snapshot {
+            Ok(snapshot) => tokio::task::spawn_blocking(move || snapshot.write())
+                .await
+                .unwrap_or_else(|e| Err(std::io::Error::other(e))),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!("[runtimed] Failed to save notebook registry: {}", e);
+        }
+    }
+
+    /// Notebooks from the registry, most recently opened first.
+    pub(crate) async fn registered_notebooks(&self) -> Vec<NotebookRecord> {
+        self.notebook_registry.lock().await.list()
+    }
+
+    /// Base URL of the blob HTTP server, e.g. `http://127.0.0.1:54321`.
+    /// `None` if the blob server isn't running.
+    pub(crate) async fn blob_base_url(&self) -> Option<String> {
+        let port = (*self.blob_port.lock().await)?;
+        let mut addr = self.config.blob_server.bind_addr;
+        if addr.ip().is_unspecified() {
+            addr.set_ip(std::net::Ipv4Addr::LOCALHOST.into());
+        }
+        addr.set_port(port);
+        Some(format!("http://{}", addr))
+    }
+
+    /// env_ids claimed by running kernels across all rooms.
+    pub(crate) fn env_claims(&self) -> &EnvIdClaims {
+        &self.env_claims
+    }
+
+    /// Kernel processes spawned by this or an earlier daemon.
+    pub(crate) fn kernel_pids(&self) -> &KernelPids {
+        &self.kernel_pids
+    }
+
+    /// Snapshot of the current synced settings.
+    pub(crate) async fn synced_settings(&self) -> crate::settings_doc::SyncedSettings {
+        self.settings.read().await.get_all()
+    }
+
     /// Trigger a graceful shutdown of the daemon.
     ///
     /// Sets the shutdown flag and notifies all waiting tasks.
@@ -410,7 +586,12 @@ impl Daemon {
         }
 
         // Start the blob HTTP server
-        let blob_port = match blob_server::start_blob_server(self.blob_store.clone()).await {
+        let blob_port = match blob_server::start_blob_server_with_config(
+            self.blob_store.clone(),
+            &self.config.blob_server,
+        )
+        .await
+        {
             Ok(port) => {
                 info!("[runtimed] Blob server started on port {}", port);
                 *self.blob_port.lock().await = Some(port);
@@ -423,16 +604,29 @@ impl Daemon {
         };
 
         // Write daemon info so clients can discover us
-        if let Err(e) = self
-            ._lock
-            .write_info(&self.config.socket_path.to_string_lossy(), blob_port)
-        {
+        if let Err(e) = self._lock.write_info(
+            &self.config.socket_path.to_string_lossy(),
+            blob_port,
+            self.config.blob_server.auth_token.as_deref(),
+        ) {
             error!("[runtimed] Failed to write daemon info: {}", e);
         }
 
         // Find and reuse existing environments from previous runs
         self.find_existing_environments().await;
 
+        // Resolve uv/deno per the tools settings, now and after every change
+        let mut settings_rx = self.settings_changed.subscribe();
+        self.apply_tool_preferences().await;
+        let tools_daemon = self.clone();
+        tokio::spawn(async move {
+            while let Ok(()) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) =
+                settings_rx.recv().await
+            {
+                tools_daemon.apply_tool_preferences().await;
+            }
+        });
+
         // Spawn the warming loops
         let uv_daemon = self.clone();
         tokio::spawn(async move {
@@ -563,6 +757,12 @@ impl Daemon {
         Ok(())
     }
 
+    /// Point `kernel_launch::tools` at the binaries the `tools` settings ask for.
+    async fn apply_tool_preferences(&self) {
+        let tools = self.settings.read().await.get_all().tools;
+        kernel_launch::tools::set_tool_preferences(tools.preferences());
+    }
+
     /// Watch `settings.json` for external changes and apply them to the Automerge doc.
     ///
     /// Uses the `notify` crate with a 500ms debouncer. When changes are detected,
@@ -681,7 +881,7 @@ impl Daemon {
                                 let mut had_errors = false;
                                 {
                                     let mut uv_pool = self.uv_pool.lock().await;
-                                    if uv_pool.failure_state.consecutive_failures > 0 {
+                                    if uv_pool.has_errors() {
                                         info!(
                                             "[settings-watch] Resetting UV pool backoff (was {} failures)",
                                             uv_pool.failure_state.consecutive_failures
@@ -692,7 +892,7 @@ impl Daemon {
                                 }
                                 {
                                     let mut conda_pool = self.conda_pool.lock().await;
-                                    if conda_pool.failure_state.consecutive_failures > 0 {
+                                    if conda_pool.has_errors() {
                                         info!(
                                             "[settings-watch] Resetting Conda pool backoff (was {} failures)",
                                             conda_pool.failure_state.consecutive_failures
@@ -738,11 +938,17 @@ impl Daemon {
 
         let mut uv_found = 0;
         let mut conda_found = 0;
+        let pinned = self.env_pins.lock().await.paths();
 
         while let Ok(Some(entry)) = entries.next_entry().await {
             let name = entry.file_name().to_string_lossy().to_string();
             let env_path = entry.path();
 
+            // Pinned envs belong to their notebook, not the pool
+            if pinned.contains(&env_path) {
+                continue;
+            }
+
             // Check for runtimed-uv-* directories
             if name.starts_with("runtimed-uv-") {
                 #[cfg(target_os = "windows")]
@@ -839,38 +1045,44 @@ impl Daemon {
                 notebook_id,
                 protocol,
             } => {
-                let use_typed_frames = protocol.as_deref() == Some(connection::PROTOCOL_V2);
-                info!(
-                    "[runtimed] NotebookSync requested for {} (protocol: {})",
-                    notebook_id,
-                    protocol.as_deref().unwrap_or("v1")
-                );
-                let docs_dir = self.config.notebook_docs_dir.clone();
-                let room = {
-                    let mut rooms = self.notebook_rooms.lock().await;
-                    crate::notebook_sync_server::get_or_create_room(
-                        &mut rooms,
-                        &notebook_id,
-                        &docs_dir,
-                        self.blob_store.clone(),
+                // Every log line from this room's tasks carries notebook_id
+                let scope_id = notebook_id.clone();
+                crate::log_format::in_notebook_scope(&scope_id, async move {
+                    let use_typed_frames = protocol.as_deref() == Some(connection::PROTOCOL_V2);
+                    info!(
+                        "[runtimed] NotebookSync requested for {} (protocol: {})",
+                        notebook_id,
+                        protocol.as_deref().unwrap_or("v1")
+                    );
+                    self.record_notebook_opened(&notebook_id).await;
+                    let docs_dir = self.config.notebook_docs_dir.clone();
+                    let room = {
+                        let mut rooms = self.notebook_rooms.lock().await;
+                        crate::notebook_sync_server::get_or_create_room(
+                            &mut rooms,
+                            &notebook_id,
+                            &docs_dir,
+                            self.blob_store.clone(),
+                        )
+                    };
+                    let (reader, writer) = tokio::io::split(stream);
+                    // Get user's default runtime and Python env preference for auto-launch
+                    let settings = self.settings.read().await.get_all();
+                    let default_runtime = settings.default_runtime;
+                    let default_python_env = settings.default_python_env;
+                    crate::notebook_sync_server::handle_notebook_sync_connection(
+                        reader,
+                        writer,
+                        room,
+                        self.notebook_rooms.clone(),
+                        notebook_id,
+                        use_typed_frames,
+                        default_runtime,
+                        default_python_env,
+                        self.clone(),
                     )
-                };
-                let (reader, writer) = tokio::io::split(stream);
-                // Get user's default runtime and Python env preference for auto-launch
-                let settings = self.settings.read().await.get_all();
-                let default_runtime = settings.default_runtime;
-                let default_python_env = settings.default_python_env;
-                crate::notebook_sync_server::handle_notebook_sync_connection(
-                    reader,
-                    writer,
-                    room,
-                    self.notebook_rooms.clone(),
-                    notebook_id,
-                    use_typed_frames,
-                    default_runtime,
-                    default_python_env,
-                    self.clone(),
-                )
+                    .await
+                })
                 .await
             }
             Handshake::Blob => self.handle_blob_connection(stream).await,
@@ -1004,15 +1216,20 @@ impl Daemon {
 
     /// Take a UV environment from the pool for kernel launching.
     ///
+    /// The env pinned to `env_id`, if there is one, is reattached instead.
     /// Returns `Some(PooledEnv)` if an environment is available, `None` otherwise.
     /// Automatically triggers replenishment when an environment is taken.
-    pub async fn take_uv_env(self: &Arc<Self>) -> Option<PooledEnv> {
+    pub async fn take_uv_env(self: &Arc<Self>, env_id: Option<&str>) -> Option<PooledEnv> {
+        if let Some(env) = self.pinned_env(env_id, EnvType::Uv).await {
+            return Some(env);
+        }
         let env = self.uv_pool.lock().await.take();
         if let Some(ref e) = env {
             info!(
                 "[runtimed] Took UV env for kernel launch: {:?}",
                 e.venv_path
             );
+            self.env_leases.claim(&e.venv_path);
             // Spawn replenishment
             let daemon = self.clone();
             tokio::spawn(async move {
@@ -1024,15 +1241,20 @@ impl Daemon {
 
     /// Take a Conda environment from the pool for kernel launching.
     ///
+    /// The env pinned to `env_id`, if there is one, is reattached instead.
     /// Returns `Some(PooledEnv)` if an environment is available, `None` otherwise.
     /// Automatically triggers replenishment when an environment is taken.
-    pub async fn take_conda_env(self: &Arc<Self>) -> Option<PooledEnv> {
+    pub async fn take_conda_env(self: &Arc<Self>, env_id: Option<&str>) -> Option<PooledEnv> {
+        if let Some(env) = self.pinned_env(env_id, EnvType::Conda).await {
+            return Some(env);
+        }
         let env = self.conda_pool.lock().await.take();
         if let Some(ref e) = env {
             info!(
                 "[runtimed] Took Conda env for kernel launch: {:?}",
                 e.venv_path
             );
+            self.env_leases.claim(&e.venv_path);
             // Spawn replenishment
             let daemon = self.clone();
             tokio::spawn(async move {
@@ -1042,6 +1264,109 @@ impl Daemon {
         env
     }
 
+    /// The env of `env_type` pinned to `env_id`, if it's still on disk.
+    async fn pinned_env(&self, env_id: Option<&str>, env_type: EnvType) -> Option<PooledEnv> {
+        let env_id = env_id?;
+        let pins = self.env_pins.lock().await;
+        let pin = pins
+            .get(env_id)
+            .filter(|pin| pin.env.env_type == env_type)?;
+        if !pin.env.python_path.exists() {
+            warn!(
+                "[runtimed] Env pinned to {} is missing at {:?}, taking one from the pool",
+                env_id, pin.env.venv_path
+            );
+            return None;
+        }
+        info!(
+            "[runtimed] Reattaching {} env pinned to {}: {:?}",
+            env_type, env_id, pin.env.venv_path
+        );
+        Some(pin.env.clone())
+    }
+
+    /// Pin the prewarmed env at `venv_path` to `env_id`, so launches with
+    /// that `env_id` reattach it and nothing recycles or deletes it.
+    ///
+    /// Only envs from the prewarmed pool's cache directory can be pinned;
+    /// inline envs are already keyed by their dependencies.
+    pub(crate) async fn pin_env(
+        &self,
+        env_id: &str,
+        notebook_id: &str,
+        venv_path: &Path,
+        python_path: &Path,
+    ) -> Result<PooledEnv, String> {
+        let name = venv_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let env_type = if venv_path.parent() != Some(self.config.cache_dir.as_path()) {
+            None
+        } else if name.starts_with("runtimed-uv-") {
+            Some(EnvType::Uv)
+        } else if name.starts_with("runtimed-conda-") {
+            Some(EnvType::Conda)
+        } else {
+            None
+        };
+        let Some(env_type) = env_type else {
+            return Err(format!(
+                "{} is not a prewarmed environment",
+                venv_path.display()
+            ));
+        };
+        let env = PooledEnv {
+            env_type,
+            venv_path: venv_path.to_path_buf(),
+            python_path: python_path.to_path_buf(),
+        };
+
+        // A pinned env must never be handed to another notebook
+        let pool = match env_type {
+            EnvType::Uv => &self.uv_pool,
+            EnvType::Conda => &self.conda_pool,
+        };
+        pool.lock()
+            .await
+            .available
+            .retain(|entry| entry.env.venv_path != venv_path);
+
+        let mut pins = self.env_pins.lock().await;
+        if let Some(previous) = pins.pin(env_id, notebook_id, env.clone()) {
+            if previous.env != env {
+                info!(
+                    "[runtimed] Unpinned {:?} from {}, replaced by {:?}",
+                    previous.env.venv_path, env_id, venv_path
+                );
+            }
+        }
+        if let Err(e) = pins.save() {
+            warn!("[runtimed] Failed to save env pins: {}", e);
+        }
+        info!(
+            "[runtimed] Pinned {} env {:?} to {} ({})",
+            env_type, venv_path, env_id, notebook_id
+        );
+        Ok(env)
+    }
+
+    /// Remove the pin for `env_id`, returning it if there was one. The env
+    /// stays on disk; once no kernel uses it, it can be cleared or reused by
+    /// the pool again after a restart.
+    pub(crate) async fn unpin_env(&self, env_id: &str) -> Option<PinnedEnv> {
+        let mut pins = self.env_pins.lock().await;
+        let pin = pins.unpin(env_id)?;
+        if let Err(e) = pins.save() {
+            warn!("[runtimed] Failed to save env pins: {}", e);
+        }
+        info!(
+            "[runtimed] Unpinned {:?} from {}",
+            pin.env.venv_path, env_id
+        );
+        Some(pin)
+    }
+
     /// Handle a single request.
     async fn handle_request(self: Arc<Self>, request: Request) -> Response {
         match request {
@@ -1120,12 +1445,18 @@ impl Daemon {
                     let (avail, warm) = pool.stats();
                     (avail, warm, pool.get_error())
                 };
+                let (uv_pinned, conda_pinned) = {
+                    let pins = self.env_pins.lock().await;
+                    (pins.count(EnvType::Uv), pins.count(EnvType::Conda))
+                };
                 Response::Stats {
                     stats: PoolStats {
                         uv_available,
                         uv_warming,
                         conda_available,
                         conda_warming,
+                        uv_pinned,
+                        conda_pinned,
                         uv_error,
                         conda_error,
                     },
@@ -1142,24 +1473,20 @@ impl Daemon {
             Request::FlushPool => {
                 info!("[runtimed] Flushing all pooled environments");
 
-                // Drain UV pool and delete env directories
-                {
-                    let mut pool = self.uv_pool.lock().await;
-                    let entries: Vec<_> = pool.available.drain(..).collect();
-                    for entry in entries {
-                        info!("[runtimed] Removing UV env: {:?}", entry.env.venv_path);
-                        tokio::fs::remove_dir_all(&entry.env.venv_path).await.ok();
-                    }
+                let pinned = self.env_pins.lock().await.paths();
+
+                // Drain UV pool and delete env directories, keeping pinned ones
+                let removed = self.uv_pool.lock().await.flush(&pinned);
+                for path in removed {
+                    info!("[runtimed] Removing UV env: {:?}", path);
+                    tokio::fs::remove_dir_all(&path).await.ok();
                 }
 
-                // Drain Conda pool and delete env directories
-                {
-                    let mut pool = self.conda_pool.lock().await;
-                    let entries: Vec<_> = pool.available.drain(..).collect();
-                    for entry in entries {
-                        info!("[runtimed] Removing Conda env: {:?}", entry.env.venv_path);
-                        tokio::fs::remove_dir_all(&entry.env.venv_path).await.ok();
-                    }
+                // Drain Conda pool and delete env directories, keeping pinned ones
+                let removed = self.conda_pool.lock().await.flush(&pinned);
+                for path in removed {
+                    info!("[runtimed] Removing Conda env: {:?}", path);
+                    tokio::fs::remove_dir_all(&path).await.ok();
                 }
 
                 // Warming loops will detect the deficit and rebuild on their next iteration
@@ -1225,27 +1552,168 @@ impl Daemon {
 
             Request::ListRooms => {
                 let rooms = self.notebook_rooms.lock().await;
+                let registry = self.notebook_registry.lock().await;
                 let mut room_infos = Vec::new();
                 for (notebook_id, room) in rooms.iter() {
-                    // Get kernel info if available
-                    let (kernel_type, env_source, kernel_status) = room
-                        .kernel_info()
-                        .await
-                        .map(|(kt, es, st)| (Some(kt), Some(es), Some(st)))
-                        .unwrap_or((None, None, None));
-
                     room_infos.push(crate::protocol::RoomInfo {
-                        notebook_id: notebook_id.clone(),
-                        active_peers: room.active_peers.load(std::sync::atomic::Ordering::Relaxed),
-                        has_kernel: room.has_kernel().await,
-                        kernel_type,
-                        env_source,
-                        kernel_status,
+                        last_opened: registry.get(notebook_id).map(|r| r.last_opened),
+                        ..room.room_info(notebook_id).await
                     });
                 }
                 Response::RoomsList { rooms: room_infos }
             }
+
+            Request::ListNotebooks => Response::NotebooksList {
+                notebooks: self.registered_notebooks().await,
+            },
+
+            Request::ShutdownAllKernels => {
+                let notebook_ids =
+                    crate::notebook_sync_server::shutdown_all_kernels(&self.notebook_rooms).await;
+                info!(
+                    "[runtimed] Shut down {} kernel(s) on request",
+                    notebook_ids.len()
+                );
+                Response::KernelsShutDown { notebook_ids }
+            }
+
+            Request::GetEnvCacheInfo => {
+                let cache_dirs = self.env_cache_dirs();
+                let mut in_use =
+                    crate::notebook_sync_server::kernel_env_paths(&self.notebook_rooms).await;
+                in_use.extend(self.env_leases.paths());
+                let pinned = self.env_pins.lock().await.paths();
+                match tokio::task::spawn_blocking(move || {
+                    crate::env_cache::scan(&cache_dirs, &in_use, &pinned)
+                })
+                .await
+                {
+                    Ok(info) => Response::EnvCacheInfo { info },
+                    Err(e) => Response::Error {
+                        message: format!("Failed to scan environment cache: {}", e),
+                    },
+                }
+            }
+
+            Request::ClearEnvCache { env_ids } => {
+                // Stop handing out pooled envs that are about to be deleted;
+                // the warming loops replace them
+                let pinned = self.env_pins.lock().await.paths();
+                let doomed = |path: &Path| {
+                    !pinned.contains(path)
+                        && path
+                            .file_name()
+                            .is_some_and(|name| env_ids.iter().any(|id| name == id.as_str()))
+                };
+                self.uv_pool
+                    .lock()
+                    .await
+                    .available
+                    .retain(|entry| !doomed(&entry.env.venv_path));
+                self.conda_pool
+                    .lock()
+                    .await
+                    .available
+                    .retain(|entry| !doomed(&entry.env.venv_path));
+
+                let cache_dirs = self.env_cache_dirs();
+                let mut in_use =
+                    crate::notebook_sync_server::kernel_env_paths(&self.notebook_rooms).await;
+                in_use.extend(self.env_leases.paths());
+                match tokio::task::spawn_blocking(move || {
+                    crate::env_cache::clear(&cache_dirs, &env_ids, &in_use, &pinned)
+                })
+                .await
+                {
+                    Ok(cleared) => {
+                        info!(
+                            "[runtimed] Cleared {} cached env(s), freeing {} bytes",
+                            cleared.removed.len(),
+                            cleared.freed_bytes
+                        );
+                        Response::EnvCacheCleared { cleared }
+                    }
+                    Err(e) => Response::Error {
+                        message: format!("Failed to clear environment cache: {}", e),
+                    },
+                }
+            }
+
+            Request::ReapKernels => Response::KernelsReaped {
+                reaped: self.reap_orphaned_kernels().await,
+            },
+
+            Request::ExportSettings => Response::SettingsExported {
+                json: self.settings.read().await.export_json(),
+            },
+
+            Request::ImportSettings { json, replace } => {
+                let result = {
+                    let mut doc = self.settings.write().await;
+                    let result = doc.import_json(&json, replace);
+                    if let Ok(true) = result {
+                        crate::sync_server::persist_settings(&mut doc);
+                    }
+                    result
+                };
+                match result {
+                    Ok(changed) => {
+                        if changed {
+                            info!("[runtimed] Imported settings (replace: {})", replace);
+                            let _ = self.settings_changed.send(());
+                        }
+                        Response::SettingsImported { changed }
+                    }
+                    Err(message) => Response::Error { message },
+                }
+            }
+        }
+    }
+
+    /// Kill tracked kernel processes that no open room owns and forget the
+    /// ones that have already exited. Returns the kernels killed.
+    async fn reap_orphaned_kernels(&self) -> Vec<TrackedKernel> {
+        // Snapshot the tracked kernels before asking rooms for theirs: a
+        // launch holds its room's kernel lock from spawn until the kernel is
+        // stored, so any kernel tracked by now is found in its room
+        let tracked = self.kernel_pids.tracked();
+        let room_pids = crate::notebook_sync_server::kernel_pids(&self.notebook_rooms).await;
+
+        let mut reaped = Vec::new();
+        for kernel in tracked {
+            match crate::kernel_pids::classify(
+                &kernel,
+                &room_pids,
+                crate::kernel_pids::is_kernel_process,
+            ) {
+                KernelPidState::InRoom => {}
+                KernelPidState::Gone => self.kernel_pids.untrack(kernel.pid),
+                KernelPidState::Orphaned => match crate::kernel_pids::terminate(kernel.pid) {
+                    Ok(()) => {
+                        info!(
+                            "[runtimed] Reaped orphaned {} kernel (pid {}) of {}",
+                            kernel.kernel_type, kernel.pid, kernel.notebook_id
+                        );
+                        self.kernel_pids.untrack(kernel.pid);
+                        reaped.push(kernel);
+                    }
+                    Err(e) => warn!(
+                        "[runtimed] Failed to reap kernel (pid {}): {}",
+                        kernel.pid, e
+                    ),
+                },
+            }
         }
+        reaped
+    }
+
+    /// Directories holding cached environments: the prewarmed pool and
+    /// inline-dependency envs.
+    fn env_cache_dirs(&self) -> Vec<PathBuf> {
+        vec![
+            self.config.cache_dir.clone(),
+            crate::inline_env::get_inline_cache_dir(),
+        ]
     }
 
     /// UV warming loop - maintains the UV pool.
@@ -1415,6 +1883,7 @@ impl Daemon {
 
         let temp_id = format!("runtimed-conda-{}", uuid::Uuid::new_v4());
         let env_path = self.config.cache_dir.join(&temp_id);
+        let _lease = self.env_leases.building(&env_path);
 
         #[cfg(target_os = "windows")]
         let python_path = env_path.join("python.exe");
@@ -1440,17 +1909,31 @@ impl Daemon {
         // Setup channel configuration
         let channel_config = ChannelConfig::default_with_root_dir(self.config.cache_dir.clone());
 
-        // Parse channels
-        let channels = match Channel::from_str("conda-forge", &channel_config) {
-            Ok(ch) => vec![ch],
+        // Parse channels from synced settings, defaulting to conda-forge
+        let channel_names = self
+            .settings
+            .read()
+            .await
+            .get_all()
+            .conda
+            .default_channels();
+        let channels = match channel_names
+            .iter()
+            .map(|name| Channel::from_str(name, &channel_config))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(channels) => channels,
             Err(e) => {
-                error!("[runtimed] Failed to parse conda-forge channel: {}", e);
+                error!(
+                    "[runtimed] Failed to parse conda channels {:?}: {}",
+                    channel_names, e
+                );
                 self.conda_pool
                     .lock()
                     .await
                     .warming_failed_with_error(Some(PackageInstallError {
                         failed_package: None,
-                        error_message: format!("Failed to parse conda-forge channel: {}", e),
+                        error_message: format!("Failed to parse conda channels: {}", e),
                     }));
                 self.broadcast_pool_state().await;
                 return;
@@ -1461,7 +1944,10 @@ impl Daemon {
         let extra_conda_packages: Vec<String> = {
             let settings = self.settings.read().await;
             let synced = settings.get_all();
-            synced.conda.default_packages
+            self.conda_pool
+                .lock()
+                .await
+                .default_packages(synced.conda.default_packages)
         };
 
         if !extra_conda_packages.is_empty() {
@@ -1621,13 +2107,25 @@ impl Daemon {
             Ok(result) => result.records,
             Err(e) => {
                 error!("[runtimed] Failed to solve dependencies: {}", e);
-                self.conda_pool
-                    .lock()
-                    .await
-                    .warming_failed_with_error(Some(PackageInstallError {
-                        failed_package: None,
-                        error_message: format!("Failed to solve dependencies: {}", e),
-                    }));
+                let failed_package = parse_conda_solve_error(&e.to_string());
+                let mut pool = self.conda_pool.lock().await;
+                pool.warming_failed_with_error(Some(PackageInstallError {
+                    failed_package: failed_package.clone(),
+                    error_message: format!("Failed to solve dependencies: {}", e),
+                }));
+                if let Some(pkg) = failed_package.filter(|pkg| {
+                    extra_conda_packages
+                        .iter()
+                        .any(|spec| package_name(spec) == *pkg)
+                }) {
+                    warn!(
+                        "[runtimed] Skipping conda package '{}' from default_packages setting. \
+                         Check conda.default_packages in settings for typos.",
+                        pkg
+                    );
+                    pool.skip_package(&pkg);
+                }
+                drop(pool);
                 self.broadcast_pool_state().await;
                 return;
             }
@@ -1776,6 +2274,22 @@ print("warmup complete")
         let _ = self.pool_state_changed.send(broadcast);
     }
 
+    /// Whether the pool for `env_type` can produce environments: it isn't
+    /// disabled, its tool is installed (uv must be on PATH), and its last
+    /// attempt to create one didn't fail.
+    pub async fn env_manager_available(&self, env_type: EnvType) -> bool {
+        let (pool_size, pool) = match env_type {
+            EnvType::Uv => {
+                if !self.check_uv_available().await {
+                    return false;
+                }
+                (self.config.uv_pool_size, &self.uv_pool)
+            }
+            EnvType::Conda => (self.config.conda_pool_size, &self.conda_pool),
+        };
+        pool_size > 0 && pool.lock().await.failure_state.consecutive_failures == 0
+    }
+
     /// Check if uv is available on PATH.
     async fn check_uv_available(&self) -> bool {
         tokio::process::Command::new("uv")
@@ -1792,6 +2306,7 @@ print("warmup complete")
     async fn create_uv_env(&self) {
         let temp_id = format!("runtimed-uv-{}", uuid::Uuid::new_v4());
         let venv_path = self.config.cache_dir.join(&temp_id);
+        let _lease = self.env_leases.building(&venv_path);
 
         #[cfg(target_os = "windows")]
         let python_path = venv_path.join("Scripts").join("python.exe");
@@ -1875,16 +2390,22 @@ print("warmup complete")
             "uv".to_string(), // For %uv magic in notebooks
         ];
 
-        // Read default uv packages from synced settings
-        {
+        // Read default uv packages and package index from synced settings
+        let index_url = {
             let settings = self.settings.read().await;
             let synced = settings.get_all();
-            let extra = synced.uv.default_packages;
+            let index_url = synced.uv.index_url().map(str::to_string);
+            let extra = self
+                .uv_pool
+                .lock()
+                .await
+                .default_packages(synced.uv.default_packages);
             if !extra.is_empty() {
                 info!("[runtimed] Including default uv packages: {:?}", extra);
                 install_packages.extend(extra);
             }
-        }
+            index_url
+        };
 
         // Install packages (120 second timeout)
         let mut install_args = vec![
@@ -1894,6 +2415,10 @@ print("warmup complete")
             python_path.to_string_lossy().to_string(),
         ];
         install_args.extend(install_packages.clone());
+        if let Some(url) = index_url {
+            install_args.push("--index-url".to_string());
+            install_args.push(url);
+        }
 
         let install_result = tokio::time::timeout(
             std::time::Duration::from_secs(120),
@@ -1910,21 +2435,23 @@ print("warmup complete")
             Ok(Ok(output)) => {
                 let stderr = String::from_utf8_lossy(&output.stderr);
                 let parsed_error = parse_uv_error(&stderr);
+                let mut skip_package = None;
 
                 if let Some(ref err) = parsed_error {
                     if let Some(pkg) = &err.failed_package {
                         // Check if this is a user-specified package (not ipykernel/ipywidgets)
                         let is_user_package = install_packages
                             .iter()
-                            .skip(2) // Skip ipykernel and ipywidgets
-                            .any(|p| p == pkg);
+                            .skip(3) // Skip ipykernel, ipywidgets and uv
+                            .any(|p| package_name(p) == package_name(pkg));
 
                         if is_user_package {
                             error!(
-                                "[runtimed] Failed to install user package '{}' from default_packages setting. \
-                                 Check uv.default_packages in settings for typos.",
+                                "[runtimed] Failed to install user package '{}' from default_packages setting, \
+                                 skipping it. Check uv.default_packages in settings for typos.",
                                 pkg
                             );
+                            skip_package = Some(pkg.clone());
                         } else {
                             error!(
                                 "[runtimed] Failed to install package '{}': {}",
@@ -1946,10 +2473,13 @@ print("warmup complete")
                 }
 
                 tokio::fs::remove_dir_all(&venv_path).await.ok();
-                self.uv_pool
-                    .lock()
-                    .await
-                    .warming_failed_with_error(parsed_error);
+                {
+                    let mut pool = self.uv_pool.lock().await;
+                    pool.warming_failed_with_error(parsed_error);
+                    if let Some(pkg) = skip_package {
+                        pool.skip_package(&pkg);
+                    }
+                }
                 self.broadcast_pool_state().await;
                 return;
             }
@@ -2041,7 +2571,11 @@ mod tests {
     use tempfile::TempDir;
 
     fn create_test_env(temp_dir: &TempDir, name: &str) -> PooledEnv {
-        let venv_path = temp_dir.path().join(name);
+        create_test_env_in(temp_dir.path(), name)
+    }
+
+    fn create_test_env_in(dir: &Path, name: &str) -> PooledEnv {
+        let venv_path = dir.join(name);
         std::fs::create_dir_all(&venv_path).unwrap();
 
         #[cfg(windows)]
@@ -2087,6 +2621,210 @@ mod tests {
         assert_eq!(pool.available.len(), 0);
     }
 
+    #[test]
+    fn test_flush_keeps_pinned_env() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut pool = Pool::new(2, 3600);
+        let pinned_env = create_test_env(&temp_dir, "runtimed-uv-pinned");
+        let unpinned_env = create_test_env(&temp_dir, "runtimed-uv-unpinned");
+        pool.add(pinned_env.clone());
+        pool.add(unpinned_env.clone());
+
+        let mut pins = EnvPins::load(&temp_dir.path().join("env-pins.json"));
+        pins.pin("env-1", "/work/a.ipynb", pinned_env.clone());
+
+        let removed = pool.flush(&pins.paths());
+
+        // Only the unpinned env is deleted; both leave the pool, which the
+        // warming loop then refills to its target
+        assert_eq!(removed, vec![unpinned_env.venv_path]);
+        assert!(pool.available.is_empty());
+        assert_eq!(pool.deficit(), 2);
+        assert!(pins.get("env-1").is_some());
+        assert!(pool.take().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_opened_notebooks_are_saved_to_the_registry() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("notebooks.json");
+        let daemon = Daemon::new(DaemonConfig {
+            socket_path: temp_dir.path().join("runtimed.sock"),
+            cache_dir: temp_dir.path().join("envs"),
+            blob_store_dir: temp_dir.path().join("blobs"),
+            notebook_docs_dir: temp_dir.path().join("notebook-docs"),
+            notebook_registry_path: registry_path.clone(),
+            kernel_pids_path: temp_dir.path().join("kernel-pids.json"),
+            env_pins_path: temp_dir.path().join("env-pins.json"),
+            uv_pool_size: 0,
+            conda_pool_size: 0,
+            max_age_secs: 3600,
+            lock_dir: Some(temp_dir.path().to_path_buf()),
+            blob_server: Default::default(),
+        })
+        .unwrap();
+        let notebook = temp_dir.path().join("analysis.ipynb");
+        std::fs::write(&notebook, "{}").unwrap();
+        let notebook_id = notebook.to_string_lossy().into_owned();
+
+        daemon.record_notebook_opened(&notebook_id).await;
+        daemon.record_notebook_opened("untitled-1").await;
+        daemon
+            .record_notebook_env_source(&notebook_id, "uv:inline")
+            .await;
+
+        let saved = NotebookRegistry::load(&registry_path);
+        let record = saved.get(&notebook_id).unwrap();
+        assert_eq!(record.path.as_deref(), Some(notebook_id.as_str()));
+        assert_eq!(record.last_env_source.as_deref(), Some("uv:inline"));
+        assert_eq!(saved.get("untitled-1").unwrap().path, None);
+    }
+
+    #[tokio::test]
+    async fn test_clear_env_cache_keeps_building_and_claimed_envs() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path().join("envs");
+        let daemon = Daemon::new(DaemonConfig {
+            socket_path: temp_dir.path().join("runtimed.sock"),
+            cache_dir: cache_dir.clone(),
+            blob_store_dir: temp_dir.path().join("blobs"),
+            notebook_docs_dir: temp_dir.path().join("notebook-docs"),
+            notebook_registry_path: temp_dir.path().join("notebooks.json"),
+            kernel_pids_path: temp_dir.path().join("kernel-pids.json"),
+            env_pins_path: temp_dir.path().join("env-pins.json"),
+            uv_pool_size: 0,
+            conda_pool_size: 0,
+            max_age_secs: 3600,
+            lock_dir: Some(temp_dir.path().to_path_buf()),
+            blob_server: Default::default(),
+        })
+        .unwrap();
+
+        let building = create_test_env_in(&cache_dir, "runtimed-conda-building");
+        let claimed = create_test_env_in(&cache_dir, "runtimed-uv-claimed");
+        let unused = create_test_env_in(&cache_dir, "runtimed-uv-unused");
+        let _lease = daemon.env_leases.building(&building.venv_path);
+        daemon.uv_pool.lock().await.add(claimed.clone());
+        let taken = daemon.take_uv_env(None).await.unwrap();
+        assert_eq!(taken.venv_path, claimed.venv_path);
+
+        let info = match daemon
+            .clone()
+            .handle_request(Request::GetEnvCacheInfo)
+            .await
+        {
+            Response::EnvCacheInfo { info } => info,
+            other => panic!("unexpected response: {:?}", other),
+        };
+        let in_use: Vec<_> = info
+            .entries
+            .iter()
+            .filter(|e| e.path.starts_with(&cache_dir))
+            .map(|e| (e.env_id.as_str(), e.in_use))
+            .collect();
+        assert!(in_use.contains(&("runtimed-conda-building", true)));
+        assert!(in_use.contains(&("runtimed-uv-claimed", true)));
+        assert!(in_use.contains(&("runtimed-uv-unused", false)));
+
+        let env_ids = vec![
+            "runtimed-conda-building".to_string(),
+            "runtimed-uv-claimed".to_string(),
+            "runtimed-uv-unused".to_string(),
+        ];
+        let cleared = match daemon
+            .clone()
+            .handle_request(Request::ClearEnvCache { env_ids })
+            .await
+        {
+            Response::EnvCacheCleared { cleared } => cleared,
+            other => panic!("unexpected response: {:?}", other),
+        };
+        assert_eq!(cleared.removed, vec!["runtimed-uv-unused"]);
+        assert!(building.venv_path.exists());
+        assert!(claimed.venv_path.exists());
+        assert!(!unused.venv_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_pinned_env_survives_flush_and_is_reattached() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path().join("envs");
+        let daemon = Daemon::new(DaemonConfig {
+            socket_path: temp_dir.path().join("runtimed.sock"),
+            cache_dir: cache_dir.clone(),
+            blob_store_dir: temp_dir.path().join("blobs"),
+            notebook_docs_dir: temp_dir.path().join("notebook-docs"),
+            notebook_registry_path: temp_dir.path().join("notebooks.json"),
+            kernel_pids_path: temp_dir.path().join("kernel-pids.json"),
+            env_pins_path: temp_dir.path().join("env-pins.json"),
+            uv_pool_size: 0,
+            conda_pool_size: 0,
+            max_age_secs: 3600,
+            lock_dir: Some(temp_dir.path().to_path_buf()),
+            blob_server: Default::default(),
+        })
+        .unwrap();
+
+        let pinned = create_test_env_in(&cache_dir, "runtimed-uv-pinned");
+        let unpinned = create_test_env_in(&cache_dir, "runtimed-uv-unpinned");
+        {
+            let mut pool = daemon.uv_pool.lock().await;
+            pool.add(pinned.clone());
+            pool.add(unpinned.clone());
+        }
+
+        daemon
+            .pin_env(
+                "env-1",
+                "/work/a.ipynb",
+                &pinned.venv_path,
+                &pinned.python_path,
+            )
+            .await
+            .unwrap();
+        // Envs outside the pool's cache directory can't be pinned
+        assert!(daemon
+            .pin_env(
+                "env-2",
+                "/work/b.ipynb",
+                temp_dir.path(),
+                &pinned.python_path
+            )
+            .await
+            .is_err());
+
+        assert!(matches!(
+            daemon.clone().handle_request(Request::FlushPool).await,
+            Response::Flushed
+        ));
+        assert!(pinned.venv_path.exists());
+        assert!(!unpinned.venv_path.exists());
+
+        match daemon.clone().handle_request(Request::Status).await {
+            Response::Stats { stats } => {
+                assert_eq!(stats.uv_available, 0);
+                assert_eq!(stats.uv_pinned, 1);
+            }
+            other => panic!("unexpected response: {:?}", other),
+        }
+
+        // The notebook gets its pinned env back; others get the (empty) pool
+        assert_eq!(
+            daemon.pinned_env(Some("env-1"), EnvType::Uv).await,
+            Some(pinned.clone())
+        );
+        assert_eq!(daemon.pinned_env(Some("env-1"), EnvType::Conda).await, None);
+        assert_eq!(daemon.pinned_env(Some("env-3"), EnvType::Uv).await, None);
+
+        // Pins persist across daemon restarts
+        let reloaded = EnvPins::load(&temp_dir.path().join("env-pins.json"));
+        assert_eq!(reloaded.get("env-1").unwrap().env, pinned);
+
+        assert!(daemon.unpin_env("env-1").await.is_some());
+        assert!(daemon.unpin_env("env-1").await.is_none());
+        assert_eq!(daemon.pinned_env(Some("env-1"), EnvType::Uv).await, None);
+    }
+
     #[test]
     fn test_pool_take_empty() {
         let mut pool = Pool::new(3, 3600);
@@ -2319,6 +3057,77 @@ mod tests {
         assert!(err.message.contains("scitkit-learn"));
     }
 
+    #[test]
+    fn test_pool_skips_failed_default_package() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut pool = Pool::new(3, 3600);
+        let configured = vec![
+            "pandas".to_string(),
+            "scitkit_learn>=1.0".to_string(),
+            "numpy".to_string(),
+        ];
+
+        pool.warming_failed_with_error(Some(PackageInstallError {
+            failed_package: Some("scitkit-learn".to_string()),
+            error_message: "Because scitkit-learn was not found".to_string(),
+        }));
+        assert!(!pool.should_retry());
+
+        pool.skip_package("scitkit-learn");
+        // The next attempt installs different packages, so it doesn't wait
+        assert!(pool.should_retry());
+        assert_eq!(
+            pool.default_packages(configured.clone()),
+            vec!["pandas".to_string(), "numpy".to_string()]
+        );
+
+        let err = pool.get_error().unwrap();
+        assert_eq!(err.consecutive_failures, 1);
+        assert_eq!(err.skipped_packages, vec!["scitkit-learn".to_string()]);
+
+        // Warming with the rest succeeds, but the skip is still reported
+        pool.add(create_test_env(&temp_dir, "env1"));
+        let err = pool.get_error().unwrap();
+        assert_eq!(err.consecutive_failures, 0);
+        assert_eq!(err.retry_in_secs, 0);
+        assert_eq!(err.skipped_packages, vec!["scitkit-learn".to_string()]);
+        assert!(err.message.contains("scitkit-learn"));
+        assert_eq!(pool.default_packages(configured.clone()).len(), 2);
+
+        // A settings change gives the package another chance
+        pool.reset_failure_state();
+        assert!(pool.get_error().is_none());
+        assert_eq!(pool.default_packages(configured.clone()), configured);
+    }
+
+    #[test]
+    fn test_pool_backoff_continues_after_skip() {
+        let mut pool = Pool::new(3, 3600);
+
+        pool.warming_failed_with_error(None);
+        pool.skip_package("bad-pkg");
+        // Another failure after skipping backs off at the next step
+        pool.warming_failed_with_error(None);
+        assert_eq!(pool.failure_state.consecutive_failures, 2);
+        assert_eq!(pool.backoff_delay(), std::time::Duration::from_secs(60));
+        assert!(!pool.should_retry());
+
+        // Skipping an already skipped package doesn't bypass the backoff
+        pool.skip_package("Bad_Pkg");
+        assert!(!pool.should_retry());
+    }
+
+    #[test]
+    fn test_parse_conda_solve_error() {
+        let message =
+            "Cannot solve the request because of: No candidates were found for nonexistent-pkg *.";
+        assert_eq!(
+            parse_conda_solve_error(message),
+            Some("nonexistent-pkg".to_string())
+        );
+        assert!(parse_conda_solve_error("Cannot solve the request because of: conflict").is_none());
+    }
+
     #[test]
     fn test_parse_uv_error_package_not_found() {
         let stderr = r#"error: No solution found when resolving dependencies:
@@ -0,0 +1,427 @@
+//! Disk usage of cached environments, and clearing them.
+//!
+//! Environments pile up in two daemon-owned directories: prewarmed pool envs
+//! under [`default_cache_dir`](crate::default_cache_dir) and inline-dependency
+//! envs, keyed by a hash of their dependencies, under the inline cache. Each
+//! env is a directory, identified by its name. [`scan`] lists and sizes them;
+//! [`clear`] deletes chosen ones, skipping any a running kernel was launched
+//! from or that is pinned to a notebook (see [`crate::env_pins`]). Envs the
+//! daemon is still building, or has taken from the pool for a kernel that
+//! hasn't launched yet, are in use too; [`EnvLeases`] tracks those.
+//!
+//! The daemon records a kernel launch in an env by touching a marker file in
+//! it ([`mark_used`]), which is what "last used" reports.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+/// File touched inside an env each time a kernel launches from it.
+const LAST_USED_MARKER: &str = ".runt-last-used";
+
+/// How long an env taken from the pool stays leased. By then its kernel has
+/// launched, and shows up as using it, or the launch has failed.
+const CLAIM_LEASE: Duration = Duration::from_secs(600);
+
+/// Envs that no running kernel uses yet but mustn't be cleared: ones being
+/// built, and ones taken from the pool for a kernel that is launching.
+/// Cheap to clone; clones share the same leases.
+#[derive(Clone, Default)]
+pub struct EnvLeases {
+    /// Env path -> when its claim lapses, or `None` while it is being built
+    inner: Arc<Mutex<HashMap<PathBuf, Option<Instant>>>>,
+}
+
+impl EnvLeases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lease the env being built at `path` until the returned guard drops.
+    pub fn building(&self, path: &Path) -> BuildLease {
+        self.inner.lock().unwrap().insert(path.to_path_buf(), None);
+        BuildLease {
+            leases: self.clone(),
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Lease the env at `path`, just taken for a kernel launch.
+    pub fn claim(&self, path: &Path) {
+        self.inner
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), Some(Instant::now() + CLAIM_LEASE));
+    }
+
+    /// Paths of the envs leased now.
+    pub fn paths(&self) -> HashSet<PathBuf> {
+        let now = Instant::now();
+        let mut leases = self.inner.lock().unwrap();
+        leases.retain(|_, expires| expires.is_none_or(|expires| expires > now));
+        leases.keys().cloned().collect()
+    }
+}
+
+/// Keeps an env leased while it is being built. See [`EnvLeases::building`].
+pub struct BuildLease {
+    leases: EnvLeases,
+    path: PathBuf,
+}
+
+impl Drop for BuildLease {
+    fn drop(&mut self) {
+        self.leases.inner.lock().unwrap().remove(&self.path);
+    }
+}
+
+/// One cached environment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvCacheEntry {
+    /// Directory name of the env, which identifies it to [`clear`].
+    pub env_id: String,
+    pub path: PathBuf,
+    /// Total size of the env's files (symlinks are not followed).
+    pub size_bytes: u64,
+    /// When a kernel last launched from the env, or when the env was last
+    /// modified if no launch has been recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<DateTime<Utc>>,
+    /// Hash of the dependencies the env was built for. `None` for prewarmed
+    /// pool envs, which aren't keyed by their contents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deps_hash: Option<String>,
+    /// A running or launching kernel uses this env, or it is still being
+    /// built, so it can't be cleared.
+    pub in_use: bool,
+    /// The env is pinned to a notebook, so it can't be cleared.
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// Every cached environment, largest first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EnvCacheInfo {
+    pub entries: Vec<EnvCacheEntry>,
+    pub total_bytes: u64,
+}
+
+/// Outcome of [`clear`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EnvCacheCleared {
+    /// Envs that were deleted.
+    pub removed: Vec<String>,
+    /// Requested envs left alone because they are in use (see
+    /// [`EnvCacheEntry::in_use`]).
+    pub in_use: Vec<String>,
+    /// Requested envs left alone because they are pinned to a notebook.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pinned: Vec<String>,
+    /// Bytes freed by the deletions.
+    pub freed_bytes: u64,
+}
+
+/// Record that a kernel is launching from the env at `env_path`.
+pub fn mark_used(env_path: &Path) {
+    if let Err(e) = std::fs::write(env_path.join(LAST_USED_MARKER), b"") {
+        warn!(
+            "[env-cache] Failed to record use of {}: {}",
+            env_path.display(),
+            e
+        );
+    }
+}
+
+/// List the envs in `cache_dirs`. `in_use` holds the env paths running
+/// kernels were launched from, plus any [`EnvLeases`], and `pinned` those
+/// pinned to notebooks.
+pub fn scan(
+    cache_dirs: &[PathBuf],
+    in_use: &HashSet<PathBuf>,
+    pinned: &HashSet<PathBuf>,
+) -> EnvCacheInfo {
+    let mut entries: Vec<EnvCacheEntry> = env_dirs(cache_dirs)
+        .map(|(env_id, path)| EnvCacheEntry {
+            size_bytes: dir_size(&path),
+            last_used: last_used(&path),
+            deps_hash: is_deps_hash(&env_id).then(|| env_id.clone()),
+            in_use: in_use.contains(&path),
+            pinned: pinned.contains(&path),
+            env_id,
+            path,
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        b.size_bytes
+            .cmp(&a.size_bytes)
+            .then_with(|| a.env_id.cmp(&b.env_id))
+    });
+    EnvCacheInfo {
+        total_bytes: entries.iter().map(|e| e.size_bytes).sum(),
+        entries,
+    }
+}
+
+/// Delete the envs named by `env_ids` from `cache_dirs`, except those in
+/// `in_use` or `pinned`. Unknown ids are ignored.
+pub fn clear(
+    cache_dirs: &[PathBuf],
+    env_ids: &[String],
+    in_use: &HashSet<PathBuf>,
+    pinned: &HashSet<PathBuf>,
+) -> EnvCacheCleared {
+    let mut cleared = EnvCacheCleared::default();
+    for (env_id, path) in env_dirs(cache_dirs) {
+        if !env_ids.contains(&env_id) {
+            continue;
+        }
+        if in_use.contains(&path) {
+            info!("[env-cache] Keeping {}: in use by a kernel", env_id);
+            cleared.in_use.push(env_id);
+            continue;
+        }
+        if pinned.contains(&path) {
+            info!("[env-cache] Keeping {}: pinned to a notebook", env_id);
+            cleared.pinned.push(env_id);
+            continue;
+        }
+        let size = dir_size(&path);
+        match std::fs::remove_dir_all(&path) {
+            Ok(()) => {
+                info!("[env-cache] Removed {} ({} bytes)", path.display(), size);
+                cleared.freed_bytes += size;
+                cleared.removed.push(env_id);
+            }
+            Err(e) => warn!("[env-cache] Failed to remove {}: {}", path.display(), e),
+        }
+    }
+    cleared
+}
+
+/// `(name, path)` of every env directory in `cache_dirs`.
+fn env_dirs(cache_dirs: &[PathBuf]) -> impl Iterator<Item = (String, PathBuf)> + '_ {
+    cache_dirs
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|entry| {
+            (
+                entry.file_name().to_string_lossy().into_owned(),
+                entry.path(),
+            )
+        })
+}
+
+/// Whether `name` is a dependency hash as produced by
+/// `kernel_env::uv::compute_env_hash` (16 lowercase hex digits).
+fn is_deps_hash(name: &str) -> bool {
+    name.len() == 16
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+fn last_used(path: &Path) -> Option<DateTime<Utc>> {
+    std::fs::metadata(path.join(LAST_USED_MARKER))
+        .or_else(|_| std::fs::metadata(path))
+        .and_then(|m| m.modified())
+        .ok()
+        .map(DateTime::<Utc>::from)
+}
+
+/// Total size of the files under `path`, without following symlinks.
+fn dir_size(path: &Path) -> u64 {
+    let mut total = 0;
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.path().symlink_metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                total += metadata.len();
+            }
+        }
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_env(root: &Path, name: &str, files: &[(&str, usize)]) -> PathBuf {
+        let env = root.join(name);
+        for (file, len) in files {
+            let path = env.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, vec![0u8; *len]).unwrap();
+        }
+        env
+    }
+
+    #[test]
+    fn test_scan_sizes_envs_across_cache_dirs() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let pool = tmp.path().join("envs");
+        let inline = tmp.path().join("inline-envs");
+        write_env(&pool, "runtimed-uv-abc", &[("bin/python", 100)]);
+        let hashed = write_env(
+            &inline,
+            "0123456789abcdef",
+            &[
+                ("bin/python", 100),
+                ("lib/site-packages/numpy/core.so", 4000),
+            ],
+        );
+        // Stray files next to envs aren't envs
+        std::fs::write(inline.join("notes.txt"), "x").unwrap();
+
+        let in_use = HashSet::from([hashed]);
+        let pinned = HashSet::from([pool.join("runtimed-uv-abc")]);
+        let info = scan(
+            &[pool, inline, tmp.path().join("missing")],
+            &in_use,
+            &pinned,
+        );
+
+        assert_eq!(info.total_bytes, 4200);
+        let summary: Vec<_> = info
+            .entries
+            .iter()
+            .map(|e| {
+                (
+                    e.env_id.as_str(),
+                    e.size_bytes,
+                    e.deps_hash.as_deref(),
+                    e.in_use,
+                    e.pinned,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "0123456789abcdef",
+                    4100,
+                    Some("0123456789abcdef"),
+                    true,
+                    false
+                ),
+                ("runtimed-uv-abc", 100, None, false, true),
+            ]
+        );
+        assert!(info.entries.iter().all(|e| e.last_used.is_some()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_not_followed() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let outside = write_env(tmp.path(), "base-python", &[("python3", 5000)]);
+        let env = write_env(
+            &tmp.path().join("envs"),
+            "runtimed-uv-abc",
+            &[("pyvenv.cfg", 10)],
+        );
+        std::fs::create_dir_all(env.join("bin")).unwrap();
+        std::os::unix::fs::symlink(outside.join("python3"), env.join("bin/python")).unwrap();
+
+        assert_eq!(
+            dir_size(&env),
+            10 + env.join("bin/python").symlink_metadata().unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_mark_used_updates_last_used() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let env = write_env(tmp.path(), "0123456789abcdef", &[("bin/python", 1)]);
+        let before = last_used(&env).unwrap();
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        mark_used(&env);
+        assert!(last_used(&env).unwrap() > before);
+        // The marker itself is not an env
+        assert_eq!(
+            scan(
+                &[tmp.path().to_path_buf()],
+                &HashSet::new(),
+                &HashSet::new()
+            )
+            .entries
+            .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_clear_refuses_in_use_and_pinned_envs() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let cache = tmp.path().join("envs");
+        let busy = write_env(&cache, "aaaaaaaaaaaaaaaa", &[("bin/python", 300)]);
+        let idle = write_env(&cache, "bbbbbbbbbbbbbbbb", &[("bin/python", 200)]);
+        let kept = write_env(&cache, "cccccccccccccccc", &[("bin/python", 100)]);
+        let pinned = write_env(&cache, "runtimed-uv-abc", &[("bin/python", 50)]);
+
+        let in_use = HashSet::from([busy.clone()]);
+        let requested = vec![
+            "aaaaaaaaaaaaaaaa".to_string(),
+            "bbbbbbbbbbbbbbbb".to_string(),
+            "runtimed-uv-abc".to_string(),
+            "not-an-env".to_string(),
+        ];
+        let cleared = clear(
+            std::slice::from_ref(&cache),
+            &requested,
+            &in_use,
+            &HashSet::from([pinned.clone()]),
+        );
+
+        assert_eq!(cleared.removed, vec!["bbbbbbbbbbbbbbbb"]);
+        assert_eq!(cleared.in_use, vec!["aaaaaaaaaaaaaaaa"]);
+        assert_eq!(cleared.pinned, vec!["runtimed-uv-abc"]);
+        assert_eq!(cleared.freed_bytes, 200);
+        assert!(busy.exists());
+        assert!(!idle.exists());
+        assert!(kept.exists());
+        assert!(pinned.exists());
+    }
+
+    #[test]
+    fn test_leases_cover_builds_and_claims() {
+        let leases = EnvLeases::new();
+        let building = PathBuf::from("/cache/runtimed-conda-1");
+        let claimed = PathBuf::from("/cache/runtimed-uv-2");
+
+        let lease = leases.building(&building);
+        leases.claim(&claimed);
+        assert_eq!(
+            leases.paths(),
+            HashSet::from([building.clone(), claimed.clone()])
+        );
+
+        // A finished build is no longer leased; a claim lasts until it lapses
+        drop(lease);
+        assert_eq!(leases.paths(), HashSet::from([claimed.clone()]));
+        leases
+            .inner
+            .lock()
+            .unwrap()
+            .insert(claimed, Some(Instant::now()));
+        assert!(leases.paths().is_empty());
+    }
+}
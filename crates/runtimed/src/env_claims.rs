@@ -0,0 +1,152 @@
+//! Which notebooks' kernels are using which `env_id`.
+//!
+//! A notebook's `env_id` (in its `runt` metadata) keys its cached inline
+//! environment. When a notebook file is copied instead of cloned, both copies
+//! keep the same `env_id`, and dependency syncs from one show up in the
+//! other. The daemon tracks the `env_id` each running kernel was launched
+//! with and, when two rooms claim the same one, sends every room involved a
+//! [`NotebookBroadcast::EnvIdCollision`] so the frontend can offer to
+//! regenerate the id.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use log::warn;
+use tokio::sync::broadcast;
+
+use crate::protocol::NotebookBroadcast;
+
+/// Rooms claiming each env_id, by notebook ID.
+type Claims = HashMap<String, BTreeMap<String, broadcast::Sender<NotebookBroadcast>>>;
+
+/// Daemon-wide registry of env_ids in active use. Cheap to clone; clones
+/// share the same registry.
+#[derive(Clone, Default)]
+pub struct EnvIdClaims {
+    inner: Arc<Mutex<Claims>>,
+}
+
+impl EnvIdClaims {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that `notebook_id`'s kernel uses `env_id`, replacing any
+    /// earlier claim by the same notebook. If other notebooks already claim
+    /// `env_id`, each room involved is sent a collision warning naming the
+    /// others. Returns the other notebooks sharing `env_id`.
+    pub fn claim(
+        &self,
+        env_id: &str,
+        notebook_id: &str,
+        broadcast_tx: broadcast::Sender<NotebookBroadcast>,
+    ) -> Vec<String> {
+        let mut claims = self.inner.lock().unwrap();
+        release_locked(&mut claims, notebook_id);
+
+        let claimants = claims.entry(env_id.to_string()).or_default();
+        claimants.insert(notebook_id.to_string(), broadcast_tx);
+        if claimants.len() < 2 {
+            return Vec::new();
+        }
+
+        warn!(
+            "[env-claims] env_id {} is shared by {} notebooks: {:?}",
+            env_id,
+            claimants.len(),
+            claimants.keys().collect::<Vec<_>>()
+        );
+        for (id, tx) in claimants.iter() {
+            let _ = tx.send(NotebookBroadcast::EnvIdCollision {
+                env_id: env_id.to_string(),
+                other_notebooks: claimants
+                    .keys()
+                    .filter(|other| *other != id)
+                    .cloned()
+                    .collect(),
+            });
+        }
+        claimants
+            .keys()
+            .filter(|id| *id != notebook_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Drop `notebook_id`'s claim, e.g. when its kernel shuts down.
+    pub fn release(&self, notebook_id: &str) {
+        release_locked(&mut self.inner.lock().unwrap(), notebook_id);
+    }
+
+    /// Notebooks currently claiming `env_id`.
+    pub fn claimants(&self, env_id: &str) -> Vec<String> {
+        self.inner
+            .lock()
+            .unwrap()
+            .get(env_id)
+            .map(|claimants| claimants.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+fn release_locked(claims: &mut Claims, notebook_id: &str) {
+    claims.retain(|_, claimants| {
+        claimants.remove(notebook_id);
+        !claimants.is_empty()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_rooms_with_same_env_id_collide() {
+        let claims = EnvIdClaims::new();
+        let (tx_a, mut rx_a) = broadcast::channel(4);
+        let (tx_b, mut rx_b) = broadcast::channel(4);
+
+        assert!(claims.claim("env-1", "/notebooks/a.ipynb", tx_a).is_empty());
+        assert!(rx_a.try_recv().is_err(), "a lone claim shouldn't warn");
+
+        let others = claims.claim("env-1", "/notebooks/b.ipynb", tx_b);
+        assert_eq!(others, vec!["/notebooks/a.ipynb"]);
+
+        match rx_a.try_recv().unwrap() {
+            NotebookBroadcast::EnvIdCollision {
+                env_id,
+                other_notebooks,
+            } => {
+                assert_eq!(env_id, "env-1");
+                assert_eq!(other_notebooks, vec!["/notebooks/b.ipynb"]);
+            }
+            other => panic!("expected EnvIdCollision, got {:?}", other),
+        }
+        match rx_b.try_recv().unwrap() {
+            NotebookBroadcast::EnvIdCollision {
+                other_notebooks, ..
+            } => assert_eq!(other_notebooks, vec!["/notebooks/a.ipynb"]),
+            other => panic!("expected EnvIdCollision, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reclaim_and_release() {
+        let claims = EnvIdClaims::new();
+        let (tx_a, mut rx_a) = broadcast::channel(4);
+        let (tx_b, _rx_b) = broadcast::channel(4);
+
+        claims.claim("env-1", "a", tx_a.clone());
+        claims.claim("env-2", "b", tx_b);
+
+        // Relaunching after regenerating the id moves the claim
+        claims.claim("env-3", "a", tx_a);
+        assert!(claims.claimants("env-1").is_empty());
+        assert_eq!(claims.claimants("env-3"), vec!["a"]);
+        assert!(rx_a.try_recv().is_err());
+
+        claims.release("a");
+        assert!(claims.claimants("env-3").is_empty());
+        assert_eq!(claims.claimants("env-2"), vec!["b"]);
+    }
+}
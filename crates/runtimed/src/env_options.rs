@@ -0,0 +1,297 @@
+//! Enumerate the environments a notebook's kernel can be launched with.
+//!
+//! Options are listed in the same priority `LaunchKernel` applies to
+//! `env_source: "auto"` (inline deps, then the closest project file, then
+//! the prewarmed pools), so the first option is always the one auto-detection
+//! would pick.
+
+use std::path::Path;
+
+use crate::notebook_metadata::NotebookMetadataSnapshot;
+use crate::project_file::detect_project_file;
+use crate::protocol::{EnvOption, StartupCost};
+use crate::settings_doc::{CondaDefaults, UvDefaults};
+
+/// Build the list of environment options for a notebook.
+///
+/// `inline_cache_dir` is where inline-dependency environments are cached; an
+/// inline option whose environment already exists there is reported as fast.
+pub fn list_env_options(
+    kernel_type: &str,
+    snapshot: Option<&NotebookMetadataSnapshot>,
+    notebook_path: Option<&Path>,
+    inline_cache_dir: &Path,
+    uv_defaults: &UvDefaults,
+    conda_defaults: &CondaDefaults,
+) -> Vec<EnvOption> {
+    let mut options = Vec::new();
+
+    if kernel_type == "deno" {
+        options.push(option("deno", "Deno".to_string(), StartupCost::Instant));
+        return with_default(options);
+    }
+
+    if kernel_type != "python" {
+        // Kernelspec kernels run as installed; there is nothing to choose
+        options.push(option(
+            "kernelspec",
+            format!("Kernelspec ({})", kernel_type),
+            StartupCost::Instant,
+        ));
+        return with_default(options);
+    }
+
+    if let Some(runt) = snapshot.map(|s| &s.runt) {
+        if let Some(uv) = runt.uv.as_ref().filter(|uv| !uv.dependencies.is_empty()) {
+            let hash = kernel_env::uv::compute_env_hash(
+                &crate::inline_env::uv_inline_deps(&uv.dependencies, uv_defaults.index_url()),
+                None,
+            );
+            options.push(option(
+                "uv:inline",
+                format!("Inline uv dependencies ({})", uv.dependencies.len()),
+                inline_cost(inline_cache_dir, &hash),
+            ));
+        }
+
+        if let Some(conda) = runt
+            .conda
+            .as_ref()
+            .filter(|conda| !conda.dependencies.is_empty())
+        {
+            let hash = kernel_env::conda::compute_env_hash(&kernel_env::CondaDependencies {
+                dependencies: conda.dependencies.clone(),
+                channels: conda.resolved_channels(conda_defaults),
+                channel_priority: conda.resolved_channel_priority(conda_defaults),
+                python: None,
+                env_id: None,
+                pip_dependencies: conda.pip_dependencies.clone(),
+            });
+            options.push(option(
+                "conda:inline",
+                format!("Inline conda dependencies ({})", conda.dependencies.len()),
+                inline_cost(inline_cache_dir, &hash),
+            ));
+        }
+    }
+
+    if let Some(detected) = notebook_path.and_then(detect_project_file) {
+        let name = detected
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        options.push(option(
+            detected.to_env_source(),
+            format!("{} ({})", name, detected.path.display()),
+            StartupCost::Fast,
+        ));
+    }
+
+    options.push(option(
+        "uv:prewarmed",
+        "Prewarmed uv environment".to_string(),
+        StartupCost::Instant,
+    ));
+    options.push(option(
+        "conda:prewarmed",
+        "Prewarmed conda environment".to_string(),
+        StartupCost::Instant,
+    ));
+
+    with_default(options)
+}
+
+fn option(env_source: &str, label: String, startup_cost: StartupCost) -> EnvOption {
+    EnvOption {
+        id: env_source.to_string(),
+        label,
+        env_source: env_source.to_string(),
+        startup_cost,
+        is_default: false,
+    }
+}
+
+fn inline_cost(cache_dir: &Path, hash: &str) -> StartupCost {
+    if cache_dir.join(hash).exists() {
+        StartupCost::Fast
+    } else {
+        StartupCost::Slow
+    }
+}
+
+fn with_default(mut options: Vec<EnvOption>) -> Vec<EnvOption> {
+    if let Some(first) = options.first_mut() {
+        first.is_default = true;
+    }
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn ids(options: &[EnvOption]) -> Vec<&str> {
+        options.iter().map(|o| o.id.as_str()).collect()
+    }
+
+    fn snapshot(runt: serde_json::Value) -> NotebookMetadataSnapshot {
+        NotebookMetadataSnapshot::from_metadata_value(&json!({ "runt": runt }))
+    }
+
+    #[test]
+    fn test_no_metadata_lists_prewarmed_pools() {
+        let cache = TempDir::new().unwrap();
+        let options = list_env_options(
+            "python",
+            None,
+            None,
+            cache.path(),
+            &UvDefaults::default(),
+            &CondaDefaults::default(),
+        );
+
+        assert_eq!(ids(&options), vec!["uv:prewarmed", "conda:prewarmed"]);
+        assert!(options[0].is_default);
+        assert!(!options[1].is_default);
+        assert_eq!(options[0].startup_cost, StartupCost::Instant);
+    }
+
+    #[test]
+    fn test_kernelspec_kernel_ignores_python_envs() {
+        let cache = TempDir::new().unwrap();
+        let meta = snapshot(json!({
+            "schema_version": "1",
+            "uv": { "dependencies": ["pandas"] }
+        }));
+        let options = list_env_options(
+            "ir",
+            Some(&meta),
+            None,
+            cache.path(),
+            &UvDefaults::default(),
+            &CondaDefaults::default(),
+        );
+
+        assert_eq!(ids(&options), vec!["kernelspec"]);
+        assert_eq!(options[0].label, "Kernelspec (ir)");
+        assert!(options[0].is_default);
+    }
+
+    #[test]
+    fn test_inline_deps_and_project_file() {
+        let project = TempDir::new().unwrap();
+        std::fs::create_dir(project.path().join(".git")).unwrap();
+        std::fs::write(project.path().join("pyproject.toml"), "[project]\n").unwrap();
+        let notebook = project.path().join("analysis.ipynb");
+        std::fs::write(&notebook, "{}").unwrap();
+        let cache = TempDir::new().unwrap();
+
+        let meta = snapshot(json!({
+            "schema_version": "1",
+            "uv": { "dependencies": ["pandas", "numpy"] }
+        }));
+        let options = list_env_options(
+            "python",
+            Some(&meta),
+            Some(&notebook),
+            cache.path(),
+            &UvDefaults::default(),
+            &CondaDefaults::default(),
+        );
+
+        assert_eq!(
+            ids(&options),
+            vec![
+                "uv:inline",
+                "uv:pyproject",
+                "uv:prewarmed",
+                "conda:prewarmed"
+            ]
+        );
+        assert!(options[0].is_default);
+        assert!(options[1..].iter().all(|o| !o.is_default));
+        assert_eq!(options[0].startup_cost, StartupCost::Slow);
+        assert_eq!(options[1].env_source, "uv:pyproject");
+        assert!(options[1].label.starts_with("pyproject.toml ("));
+    }
+
+    #[test]
+    fn test_cached_inline_env_is_fast() {
+        let cache = TempDir::new().unwrap();
+        let deps = vec!["pandas".to_string()];
+        let hash =
+            kernel_env::uv::compute_env_hash(&crate::inline_env::uv_inline_deps(&deps, None), None);
+        std::fs::create_dir(cache.path().join(hash)).unwrap();
+
+        let meta = snapshot(json!({
+            "schema_version": "1",
+            "uv": { "dependencies": deps }
+        }));
+        let options = list_env_options(
+            "python",
+            Some(&meta),
+            None,
+            cache.path(),
+            &UvDefaults::default(),
+            &CondaDefaults::default(),
+        );
+
+        assert_eq!(options[0].id, "uv:inline");
+        assert_eq!(options[0].startup_cost, StartupCost::Fast);
+    }
+
+    #[test]
+    fn test_conda_inline_with_environment_yml() {
+        let project = TempDir::new().unwrap();
+        std::fs::create_dir(project.path().join(".git")).unwrap();
+        std::fs::write(
+            project.path().join("environment.yml"),
+            "dependencies:\n  - numpy\n",
+        )
+        .unwrap();
+        let notebook = project.path().join("nb.ipynb");
+        let cache = TempDir::new().unwrap();
+
+        let meta = snapshot(json!({
+            "schema_version": "1",
+            "conda": { "dependencies": ["scipy"], "channels": [] }
+        }));
+        let options = list_env_options(
+            "python",
+            Some(&meta),
+            Some(&notebook),
+            cache.path(),
+            &UvDefaults::default(),
+            &CondaDefaults::default(),
+        );
+
+        assert_eq!(
+            ids(&options),
+            vec![
+                "conda:inline",
+                "conda:env_yml",
+                "uv:prewarmed",
+                "conda:prewarmed"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deno_has_single_option() {
+        let cache = TempDir::new().unwrap();
+        let options = list_env_options(
+            "deno",
+            None,
+            None,
+            cache.path(),
+            &UvDefaults::default(),
+            &CondaDefaults::default(),
+        );
+
+        assert_eq!(ids(&options), vec!["deno"]);
+        assert!(options[0].is_default);
+    }
+}
@@ -0,0 +1,190 @@
+//! Prewarmed environments pinned to notebooks.
+//!
+//! A kernel launched from the prewarmed pool keeps its env only until the
+//! next launch, which takes a fresh one, and a daemon restart folds the old
+//! env back into the pool for any notebook to take. Pinning records the env
+//! against the notebook's `env_id` instead: prewarmed launches with that
+//! `env_id` reattach it, and flushing the pool, clearing the env cache and
+//! the startup scan for reusable envs all leave it alone.
+//!
+//! Stored as JSON at `daemon_base_dir()/env-pins.json`.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{EnvType, PooledEnv};
+
+/// A prewarmed env reserved for one notebook.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PinnedEnv {
+    /// The notebook's `env_id`, which the pin is keyed by.
+    pub env_id: String,
+    /// The notebook that pinned the env (file path or UUID).
+    pub notebook_id: String,
+    pub env: PooledEnv,
+    pub pinned_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PinsFile {
+    pins: Vec<PinnedEnv>,
+}
+
+/// Pinned envs keyed by `env_id`, backed by a JSON file.
+#[derive(Debug)]
+pub struct EnvPins {
+    path: PathBuf,
+    pins: BTreeMap<String, PinnedEnv>,
+}
+
+impl EnvPins {
+    /// Load the pins from `path`. A missing or unreadable file yields no
+    /// pins; it is rewritten on the next update.
+    pub fn load(path: &Path) -> Self {
+        let pins = match std::fs::read_to_string(path) {
+            Ok(content) => match serde_json::from_str::<PinsFile>(&content) {
+                Ok(file) => file
+                    .pins
+                    .into_iter()
+                    .map(|pin| (pin.env_id.clone(), pin))
+                    .collect(),
+                Err(e) => {
+                    warn!("[env-pins] Ignoring corrupt {:?}: {}", path, e);
+                    BTreeMap::new()
+                }
+            },
+            Err(_) => BTreeMap::new(),
+        };
+
+        Self {
+            path: path.to_path_buf(),
+            pins,
+        }
+    }
+
+    /// Pin `env` to `env_id`, returning the pin it replaces, if any.
+    pub fn pin(&mut self, env_id: &str, notebook_id: &str, env: PooledEnv) -> Option<PinnedEnv> {
+        self.pins.insert(
+            env_id.to_string(),
+            PinnedEnv {
+                env_id: env_id.to_string(),
+                notebook_id: notebook_id.to_string(),
+                env,
+                pinned_at: Utc::now(),
+            },
+        )
+    }
+
+    /// Remove the pin for `env_id`, returning it if there was one.
+    pub fn unpin(&mut self, env_id: &str) -> Option<PinnedEnv> {
+        self.pins.remove(env_id)
+    }
+
+    /// The pin for `env_id`, if any.
+    pub fn get(&self, env_id: &str) -> Option<&PinnedEnv> {
+        self.pins.get(env_id)
+    }
+
+    /// Whether the env at `venv_path` is pinned.
+    pub fn is_pinned(&self, venv_path: &Path) -> bool {
+        self.pins.values().any(|pin| pin.env.venv_path == venv_path)
+    }
+
+    /// Directories of every pinned env.
+    pub fn paths(&self) -> HashSet<PathBuf> {
+        self.pins
+            .values()
+            .map(|pin| pin.env.venv_path.clone())
+            .collect()
+    }
+
+    /// Number of pinned envs of `env_type`.
+    pub fn count(&self, env_type: EnvType) -> usize {
+        self.pins
+            .values()
+            .filter(|pin| pin.env.env_type == env_type)
+            .count()
+    }
+
+    /// Write the pins back to their file.
+    pub fn save(&self) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(&PinsFile {
+            pins: self.pins.values().cloned().collect(),
+        })?;
+        // Write-then-rename so a crash mid-write can't truncate the file
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn pooled(root: &Path, name: &str, env_type: EnvType) -> PooledEnv {
+        let venv_path = root.join(name);
+        PooledEnv {
+            env_type,
+            python_path: venv_path.join("bin").join("python"),
+            venv_path,
+        }
+    }
+
+    #[test]
+    fn test_pins_round_trip_through_file() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("env-pins.json");
+        let uv = pooled(tmp.path(), "runtimed-uv-a", EnvType::Uv);
+        let conda = pooled(tmp.path(), "runtimed-conda-b", EnvType::Conda);
+
+        let mut pins = EnvPins::load(&path);
+        assert!(pins.pin("env-1", "/work/a.ipynb", uv.clone()).is_none());
+        pins.pin("env-2", "/work/b.ipynb", conda.clone());
+        pins.save().unwrap();
+
+        let loaded = EnvPins::load(&path);
+        assert_eq!(loaded.get("env-1").unwrap().env, uv);
+        assert_eq!(loaded.get("env-1").unwrap().notebook_id, "/work/a.ipynb");
+        assert!(loaded.is_pinned(&conda.venv_path));
+        assert_eq!(loaded.count(EnvType::Uv), 1);
+        assert_eq!(loaded.count(EnvType::Conda), 1);
+        assert_eq!(
+            loaded.paths(),
+            HashSet::from([uv.venv_path, conda.venv_path])
+        );
+    }
+
+    #[test]
+    fn test_repin_replaces_and_unpin_releases() {
+        let tmp = TempDir::new().unwrap();
+        let first = pooled(tmp.path(), "runtimed-uv-a", EnvType::Uv);
+        let second = pooled(tmp.path(), "runtimed-uv-b", EnvType::Uv);
+
+        let mut pins = EnvPins::load(&tmp.path().join("env-pins.json"));
+        pins.pin("env-1", "nb", first.clone());
+        let replaced = pins.pin("env-1", "nb", second.clone()).unwrap();
+        assert_eq!(replaced.env, first);
+        assert!(!pins.is_pinned(&first.venv_path));
+
+        assert_eq!(pins.unpin("env-1").unwrap().env, second);
+        assert!(pins.unpin("env-1").is_none());
+        assert!(!pins.is_pinned(&second.venv_path));
+    }
+
+    #[test]
+    fn test_corrupt_file_yields_no_pins() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("env-pins.json");
+        std::fs::write(&path, "not json").unwrap();
+        assert!(EnvPins::load(&path).paths().is_empty());
+    }
+}
@@ -0,0 +1,225 @@
+//! Pieces of environment.yml handling shared by the daemon and the app.
+//!
+//! Lines carrying a conda-build style selector comment (`- pywin32  # [win]`)
+//! are dropped before parsing when the selector doesn't match the current
+//! platform. The `variables:` block is set in the environment of kernels
+//! launched from the file.
+
+use std::path::Path;
+
+/// Read the `variables:` block of an environment.yml, after applying
+/// selectors.
+///
+/// These are set in the kernel's environment when launching with
+/// `conda:env_yml`. Non-scalar values are skipped; an unreadable file yields
+/// no variables rather than failing the launch.
+pub fn variables(path: &Path) -> Vec<(String, String)> {
+    #[derive(serde::Deserialize)]
+    struct Raw {
+        variables: Option<serde_yaml::Mapping>,
+    }
+
+    let raw: Option<Raw> = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_yaml::from_str(&apply_selectors(&content)).ok());
+
+    raw.and_then(|raw| raw.variables)
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| {
+            Some((yaml_scalar_to_string(&key)?, yaml_scalar_to_string(&value)?))
+        })
+        .collect()
+}
+
+/// Render a scalar YAML value (`FOO: 1`, `DEBUG: true`) as an env var string.
+pub fn yaml_scalar_to_string(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// The platform that `# [selector]` comments are evaluated against.
+#[derive(Debug, Clone, Copy)]
+struct SelectorPlatform {
+    /// `std::env::consts::OS` value: "linux", "macos", "windows", ...
+    os: &'static str,
+    /// `std::env::consts::ARCH` value: "x86_64", "aarch64", ...
+    arch: &'static str,
+}
+
+impl SelectorPlatform {
+    fn current() -> Self {
+        Self {
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+        }
+    }
+
+    /// Whether a single selector name holds on this platform.
+    ///
+    /// Returns `None` for names we don't know, so the caller can keep the line
+    /// rather than silently dropping a dependency.
+    fn matches(&self, name: &str) -> Option<bool> {
+        let os = match self.os {
+            "macos" => "osx",
+            "windows" => "win",
+            other => other,
+        };
+        let is_64 = matches!(self.arch, "x86_64" | "aarch64");
+        let is_arm64 = self.arch == "aarch64";
+        Some(match name {
+            "linux" | "osx" | "win" => os == name,
+            "unix" => os != "win",
+            "x86_64" => self.arch == "x86_64",
+            "arm64" | "aarch64" => is_arm64,
+            "linux64" => os == "linux" && is_64,
+            "osx64" => os == "osx" && self.arch == "x86_64",
+            "win64" => os == "win" && is_64,
+            _ => return None,
+        })
+    }
+
+    /// Evaluate a selector expression: names joined by `and`/`or`, each
+    /// optionally negated with `not` (`and` binds tighter than `or`).
+    fn evaluate(&self, expr: &str) -> Option<bool> {
+        let mut any = false;
+        for clause in expr.split(" or ") {
+            let mut all = true;
+            for term in clause.split(" and ") {
+                let term = term.trim();
+                let value = match term.strip_prefix("not ") {
+                    Some(name) => !self.matches(name.trim())?,
+                    None => self.matches(term)?,
+                };
+                all &= value;
+            }
+            any |= all;
+        }
+        Some(any)
+    }
+}
+
+/// Drop lines whose trailing `# [selector]` comment doesn't match the
+/// current platform.
+///
+/// Lines with unrecognised selectors are kept, with a warning.
+pub fn apply_selectors(content: &str) -> String {
+    apply_selectors_for(content, SelectorPlatform::current())
+}
+
+fn apply_selectors_for(content: &str, platform: SelectorPlatform) -> String {
+    let mut out = String::with_capacity(content.len());
+    for line in content.lines() {
+        let selector = line.rsplit_once('#').and_then(|(_, comment)| {
+            comment
+                .trim()
+                .strip_prefix('[')
+                .and_then(|c| c.strip_suffix(']'))
+        });
+        if let Some(selector) = selector {
+            match platform.evaluate(selector) {
+                Some(false) => continue,
+                Some(true) => {}
+                None => log::warn!(
+                    "[environment_yml] Unknown selector [{}], keeping line",
+                    selector
+                ),
+            }
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const LINUX_X64: SelectorPlatform = SelectorPlatform {
+        os: "linux",
+        arch: "x86_64",
+    };
+    const MACOS_ARM: SelectorPlatform = SelectorPlatform {
+        os: "macos",
+        arch: "aarch64",
+    };
+    const WINDOWS_X64: SelectorPlatform = SelectorPlatform {
+        os: "windows",
+        arch: "x86_64",
+    };
+
+    const SELECTOR_YML: &str = r#"
+dependencies:
+  - numpy
+  - cudatoolkit  # [linux]
+  - libcxx  # [osx]
+  - pywin32  # [win]
+  - uvloop  # [not win]
+  - jax-metal  # [osx and arm64]
+  - mkl  # [linux64 or win64]
+  - mystery  # [beos]
+"#;
+
+    fn selected(platform: SelectorPlatform) -> Vec<String> {
+        let content = apply_selectors_for(SELECTOR_YML, platform);
+        let raw: serde_yaml::Value = serde_yaml::from_str(&content).unwrap();
+        raw["dependencies"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn test_selectors_filter_by_platform() {
+        assert_eq!(
+            selected(LINUX_X64),
+            vec!["numpy", "cudatoolkit", "uvloop", "mkl", "mystery"]
+        );
+        assert_eq!(
+            selected(MACOS_ARM),
+            vec!["numpy", "libcxx", "uvloop", "jax-metal", "mystery"]
+        );
+        assert_eq!(
+            selected(WINDOWS_X64),
+            vec!["numpy", "pywin32", "mkl", "mystery"]
+        );
+    }
+
+    #[test]
+    fn test_selector_ignores_plain_comments() {
+        let content = "dependencies:\n  - numpy  # pinned for reasons\n";
+        assert_eq!(apply_selectors_for(content, WINDOWS_X64), content);
+    }
+
+    #[test]
+    fn test_variables() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("environment.yml");
+        std::fs::write(
+            &path,
+            "name: test\nvariables:\n  DATA_DIR: /data\n  THREADS: 4\n  NESTED: [1, 2]\n  DEBUG: true\n  NEVER: 1  # [linux and not linux]\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            variables(&path),
+            vec![
+                ("DATA_DIR".to_string(), "/data".to_string()),
+                ("THREADS".to_string(), "4".to_string()),
+                ("DEBUG".to_string(), "true".to_string()),
+            ]
+        );
+
+        let broken = temp.path().join("broken.yml");
+        std::fs::write(&broken, "variables: [").unwrap();
+        assert!(variables(&broken).is_empty());
+    }
+}
@@ -4,13 +4,15 @@
 //! providing a [`BroadcastProgressHandler`] that forwards progress events
 //! to connected notebook clients via the broadcast channel.
 
-use std::sync::Arc;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
 
 use anyhow::Result;
-use kernel_env::progress::{EnvProgressPhase, ProgressHandler};
+use kernel_env::progress::{EnvProgressPhase, LogStream, ProgressHandler};
+use kernel_env::ChannelPriority;
 use tokio::sync::broadcast;
 
-use crate::protocol::NotebookBroadcast;
+use crate::protocol::{EnvBuildLogLine, NotebookBroadcast};
 
 // Re-export the PreparedEnv-equivalent types for callers that still
 // use the old `inline_env::PreparedEnv` pattern.
@@ -24,20 +26,87 @@ pub struct PreparedEnv {
     pub python_path: std::path::PathBuf,
 }
 
+/// Keep at most this many lines of a build's output for late subscribers.
+const MAX_BUILD_LOG_LINES: usize = 5000;
+
+/// Output of a room's current or most recent environment build.
+///
+/// Lines are kept so a client that subscribes mid-build also gets what was
+/// written before it asked, and are streamed to subscribers as they arrive.
+pub struct EnvBuildLog {
+    inner: Mutex<BuildLogState>,
+    tx: broadcast::Sender<EnvBuildLogLine>,
+}
+
+#[derive(Default)]
+struct BuildLogState {
+    lines: VecDeque<EnvBuildLogLine>,
+    next_seq: u64,
+}
+
+impl Default for EnvBuildLog {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::default(),
+            tx: broadcast::channel(1024).0,
+        }
+    }
+}
+
+impl EnvBuildLog {
+    /// Drop the previous build's lines. Sequence numbers keep counting up.
+    pub fn clear(&self) {
+        let mut state = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        state.lines.clear();
+    }
+
+    /// Record a line and send it to subscribers.
+    pub fn push(&self, env_type: &str, stream: LogStream, text: &str) {
+        let mut state = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let line = EnvBuildLogLine {
+            seq: state.next_seq,
+            env_type: env_type.to_string(),
+            stream,
+            text: text.to_string(),
+        };
+        state.next_seq += 1;
+        if state.lines.len() == MAX_BUILD_LOG_LINES {
+            state.lines.pop_front();
+        }
+        state.lines.push_back(line.clone());
+        // Sent under the lock so subscribe() can't see a line twice or miss it
+        let _ = self.tx.send(line);
+    }
+
+    /// The lines recorded so far, and a receiver for every line after them.
+    pub fn subscribe(&self) -> (Vec<EnvBuildLogLine>, broadcast::Receiver<EnvBuildLogLine>) {
+        let state = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        (state.lines.iter().cloned().collect(), self.tx.subscribe())
+    }
+}
+
 /// Progress handler that broadcasts [`EnvProgressPhase`] events to all
 /// connected notebook clients via a [`broadcast::Sender`].
+///
+/// Build output lines go to the room's [`EnvBuildLog`], which streams them
+/// to clients subscribed with `StreamEnvBuildLog`.
 pub struct BroadcastProgressHandler {
     tx: broadcast::Sender<NotebookBroadcast>,
+    build_log: Arc<EnvBuildLog>,
 }
 
 impl BroadcastProgressHandler {
-    pub fn new(tx: broadcast::Sender<NotebookBroadcast>) -> Self {
-        Self { tx }
+    pub fn new(tx: broadcast::Sender<NotebookBroadcast>, build_log: Arc<EnvBuildLog>) -> Self {
+        Self { tx, build_log }
     }
 }
 
 impl ProgressHandler for BroadcastProgressHandler {
     fn on_progress(&self, env_type: &str, phase: EnvProgressPhase) {
+        if matches!(phase, EnvProgressPhase::Starting { .. }) {
+            self.build_log.clear();
+        }
+
         // Log all phases
         kernel_env::LogHandler.on_progress(env_type, phase.clone());
 
@@ -47,10 +116,16 @@ impl ProgressHandler for BroadcastProgressHandler {
             phase,
         });
     }
+
+    fn on_log_line(&self, env_type: &str, stream: LogStream, text: &str) {
+        kernel_env::LogHandler.on_log_line(env_type, stream, text);
+
+        self.build_log.push(env_type, stream, text);
+    }
 }
 
 /// Get the cache directory for inline dependency environments.
-fn get_inline_cache_dir() -> std::path::PathBuf {
+pub(crate) fn get_inline_cache_dir() -> std::path::PathBuf {
     dirs::cache_dir()
         .unwrap_or_else(|| std::path::PathBuf::from("/tmp"))
         .join("runt")
@@ -63,16 +138,16 @@ fn get_inline_cache_dir() -> std::path::PathBuf {
 /// immediately. Otherwise creates a new environment with uv venv + uv pip install.
 pub async fn prepare_uv_inline_env(
     deps: &[String],
+    index_url: Option<&str>,
     handler: Arc<dyn ProgressHandler>,
 ) -> Result<PreparedEnv> {
-    let uv_deps = kernel_env::UvDependencies {
-        dependencies: deps.to_vec(),
-        requires_python: None,
-    };
-
-    let env =
-        kernel_env::uv::prepare_environment_in(&uv_deps, None, &get_inline_cache_dir(), handler)
-            .await?;
+    let env = kernel_env::uv::prepare_environment_in(
+        &uv_inline_deps(deps, index_url),
+        None,
+        &get_inline_cache_dir(),
+        handler,
+    )
+    .await?;
 
     Ok(PreparedEnv {
         env_path: env.venv_path,
@@ -87,25 +162,204 @@ pub async fn prepare_uv_inline_env(
 pub async fn prepare_conda_inline_env(
     deps: &[String],
     channels: &[String],
+    channel_priority: ChannelPriority,
+    pip_deps: &[String],
     handler: Arc<dyn ProgressHandler>,
 ) -> Result<PreparedEnv> {
-    let conda_deps = kernel_env::CondaDependencies {
+    let env = kernel_env::conda::prepare_environment_in(
+        &conda_inline_deps(deps, channels, channel_priority, pip_deps),
+        &get_inline_cache_dir(),
+        handler,
+    )
+    .await?;
+
+    Ok(PreparedEnv {
+        env_path: env.env_path,
+        python_path: env.python_path,
+    })
+}
+
+/// Rebuild the cached UV environment for these inline deps if it is broken.
+///
+/// Returns whether the environment had to be rebuilt.
+pub async fn repair_uv_inline_env(
+    deps: &[String],
+    index_url: Option<&str>,
+    handler: Arc<dyn ProgressHandler>,
+) -> Result<bool> {
+    kernel_env::uv::repair_environment_in(
+        &uv_inline_deps(deps, index_url),
+        None,
+        &get_inline_cache_dir(),
+        handler,
+    )
+    .await
+}
+
+/// Rebuild the cached Conda environment for these inline deps if it is broken.
+///
+/// Returns whether the environment had to be rebuilt.
+pub async fn repair_conda_inline_env(
+    deps: &[String],
+    channels: &[String],
+    channel_priority: ChannelPriority,
+    pip_deps: &[String],
+    handler: Arc<dyn ProgressHandler>,
+) -> Result<bool> {
+    kernel_env::conda::repair_environment_in(
+        &conda_inline_deps(deps, channels, channel_priority, pip_deps),
+        &get_inline_cache_dir(),
+        handler,
+    )
+    .await
+}
+
+/// UV dependency spec for inline deps, installed from `index_url` when set
+/// and PyPI otherwise.
+pub(crate) fn uv_inline_deps(
+    deps: &[String],
+    index_url: Option<&str>,
+) -> kernel_env::UvDependencies {
+    kernel_env::UvDependencies {
+        dependencies: deps.to_vec(),
+        requires_python: None,
+        index_url: index_url.map(str::to_string),
+    }
+}
+
+/// Conda dependency spec for inline deps, defaulting to conda-forge.
+/// `pip_deps` are pip-installed into the env after the conda solve.
+fn conda_inline_deps(
+    deps: &[String],
+    channels: &[String],
+    channel_priority: ChannelPriority,
+    pip_deps: &[String],
+) -> kernel_env::CondaDependencies {
+    kernel_env::CondaDependencies {
         dependencies: deps.to_vec(),
         channels: if channels.is_empty() {
             vec!["conda-forge".to_string()]
         } else {
             channels.to_vec()
         },
+        channel_priority,
         python: None,
         env_id: None,
-    };
+        pip_dependencies: pip_deps.to_vec(),
+    }
+}
 
-    let env =
-        kernel_env::conda::prepare_environment_in(&conda_deps, &get_inline_cache_dir(), handler)
-            .await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(PreparedEnv {
-        env_path: env.env_path,
-        python_path: env.python_path,
-    })
+    #[test]
+    fn test_inline_deps_use_configured_defaults() {
+        let uv_defaults = crate::settings_doc::UvDefaults {
+            default_index_url: " https://pypi.example.com/simple ".to_string(),
+            ..Default::default()
+        };
+        let uv_deps = uv_inline_deps(&["pandas".to_string()], uv_defaults.index_url());
+        assert_eq!(
+            uv_deps.index_url.as_deref(),
+            Some("https://pypi.example.com/simple")
+        );
+        // An unset index leaves uv on PyPI
+        let pypi = uv_inline_deps(
+            &["pandas".to_string()],
+            crate::settings_doc::UvDefaults::default().index_url(),
+        );
+        assert_eq!(pypi.index_url, None);
+
+        let conda_defaults = crate::settings_doc::CondaDefaults {
+            channels: vec!["bioconda".to_string(), "conda-forge".to_string()],
+            ..Default::default()
+        };
+        let conda_deps = conda_inline_deps(
+            &["samtools".to_string()],
+            &crate::notebook_metadata::CondaInlineMetadata::default()
+                .resolved_channels(&conda_defaults),
+            ChannelPriority::default(),
+            &[],
+        );
+        assert_eq!(conda_deps.channels, vec!["bioconda", "conda-forge"]);
+        assert_eq!(
+            conda_inline_deps(
+                &[],
+                &crate::settings_doc::CondaDefaults::default().default_channels(),
+                ChannelPriority::default(),
+                &[]
+            )
+            .channels,
+            vec!["conda-forge"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_build_output_reaches_subscriber_in_order() {
+        let (tx, _) = broadcast::channel(16);
+        let build_log = Arc::new(EnvBuildLog::default());
+        let handler = BroadcastProgressHandler::new(tx, build_log.clone());
+
+        // A previous build's output is dropped when the next one starts
+        handler.on_log_line("uv", LogStream::Stdout, "stale");
+        handler.on_progress(
+            "uv",
+            EnvProgressPhase::Starting {
+                env_hash: "abc".into(),
+            },
+        );
+        handler.on_log_line("uv", LogStream::Stderr, "Resolved 3 packages");
+
+        // Subscribing mid-build returns what was written so far...
+        let (backlog, mut rx) = build_log.subscribe();
+        assert_eq!(
+            backlog
+                .iter()
+                .map(|line| line.text.as_str())
+                .collect::<Vec<_>>(),
+            ["Resolved 3 packages"]
+        );
+
+        // ...and then every later line, as the subprocess writes it
+        let output = kernel_env::progress::output_with_log(
+            tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg("for i in 1 2 3 4 5; do echo \"Installed pkg$i\"; done"),
+            "uv",
+            &handler,
+        )
+        .await
+        .unwrap();
+        assert!(output.status.success());
+
+        let mut streamed = Vec::new();
+        while let Ok(line) = rx.try_recv() {
+            streamed.push(line);
+        }
+        assert_eq!(
+            streamed
+                .iter()
+                .map(|line| line.text.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "Installed pkg1",
+                "Installed pkg2",
+                "Installed pkg3",
+                "Installed pkg4",
+                "Installed pkg5"
+            ]
+        );
+        assert!(streamed
+            .iter()
+            .all(|line| line.env_type == "uv" && line.stream == LogStream::Stdout));
+        // No gap or overlap between the backlog and the stream
+        let seqs: Vec<u64> = backlog
+            .iter()
+            .chain(&streamed)
+            .map(|line| line.seq)
+            .collect();
+        assert!(seqs.windows(2).all(|pair| pair[1] == pair[0] + 1));
+    }
 }
@@ -8,29 +8,35 @@
 //! - Daemon tracks msg_id → cell_id perfectly
 //! - Outputs broadcast to all windows showing the same notebook
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::Ipv4Addr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::{Arc, Mutex as StdMutex};
 
 use anyhow::Result;
 use bytes::Bytes;
+use chrono::{DateTime, SecondsFormat, Utc};
 use jupyter_protocol::{
-    CompleteRequest, ConnectionInfo, ExecuteRequest, HistoryRequest, InterruptRequest,
-    JupyterMessage, JupyterMessageContent, KernelInfoRequest, ShutdownRequest,
+    CompleteRequest, ConnectionInfo, DebugRequest, ExecuteRequest, HistoryRequest, InspectRequest,
+    InterruptRequest, JupyterMessage, JupyterMessageContent, KernelInfoRequest, ShutdownRequest,
 };
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
+use tauri_jupyter::BufferLimits;
 use tokio::sync::{broadcast, mpsc, oneshot, RwLock};
 use uuid::Uuid;
 
 use crate::blob_store::BlobStore;
 use crate::comm_state::CommState;
-use crate::notebook_doc::NotebookDoc;
+use crate::env_claims::EnvIdClaims;
+use crate::kernel_pids::{KernelPids, TrackedKernel};
+use crate::kernel_snapshot::{self, SnapshotReport};
+use crate::notebook_doc::{CellExecutionTiming, NotebookDoc};
 use crate::notebook_sync_server::persist_notebook_bytes;
-use crate::output_store::{self, DEFAULT_INLINE_THRESHOLD};
-use crate::protocol::{CompletionItem, HistoryEntry, NotebookBroadcast};
+use crate::output_store::{self, ManifestOptions};
+use crate::protocol::{CompletionItem, HistoryEntry, KernelPayload, NotebookBroadcast};
+use crate::run_log::{self, RunLog, RunLogEntry, RunStatus};
 use crate::stream_terminal::{StreamOutputState, StreamTerminals};
 use crate::terminal_size::{TERMINAL_COLUMNS_STR, TERMINAL_LINES_STR};
 use crate::{EnvType, PooledEnv};
@@ -53,6 +59,14 @@ pub struct LaunchedEnvConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub conda_channels: Option<Vec<String>>,
 
+    /// Conda channel priority (if env_source is "conda:inline")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conda_channel_priority: Option<kernel_env::ChannelPriority>,
+
+    /// PyPI packages pip-installed after the conda solve (if env_source is "conda:inline")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conda_pip_deps: Option<Vec<String>>,
+
     /// Deno config (if kernel_type is "deno")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub deno_config: Option<DenoLaunchedConfig>,
@@ -65,12 +79,71 @@ pub struct LaunchedEnvConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub python_path: Option<PathBuf>,
 
+    /// The notebook's `env_id` at launch, if it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_id: Option<String>,
+
     /// Unique identifier for this kernel launch session.
     /// Used to detect if kernel was swapped during async operations (e.g., hot-sync).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub launch_id: Option<String>,
 }
 
+/// The exact command a kernel was spawned with, for reproducing a failed
+/// launch in a terminal.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LaunchSpec {
+    /// Program followed by its arguments
+    pub argv: Vec<String>,
+    /// Working directory
+    pub cwd: Option<PathBuf>,
+    /// Variables set (or, when `None`, removed) on top of the daemon's own
+    /// environment. Values of secret-looking names are redacted.
+    pub env: std::collections::BTreeMap<String, Option<String>>,
+}
+
+/// Env var name fragments whose values are kept out of [`LaunchSpec`].
+const SECRET_ENV_MARKERS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "API_KEY",
+    "ACCESS_KEY",
+    "PRIVATE_KEY",
+    "CREDENTIAL",
+];
+
+impl LaunchSpec {
+    fn from_command(cmd: &tokio::process::Command) -> Self {
+        let cmd = cmd.as_std();
+        let argv = std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        let env = cmd
+            .get_envs()
+            .map(|(name, value)| {
+                let name = name.to_string_lossy().into_owned();
+                let upper = name.to_ascii_uppercase();
+                let value = value.map(|v| {
+                    if SECRET_ENV_MARKERS.iter().any(|m| upper.contains(m)) {
+                        "<redacted>".to_string()
+                    } else {
+                        v.to_string_lossy().into_owned()
+                    }
+                });
+                (name, value)
+            })
+            .collect();
+        Self {
+            argv,
+            cwd: cmd.get_current_dir().map(Path::to_path_buf),
+            env,
+        }
+    }
+}
+
 /// Deno configuration captured at kernel launch time.
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 pub struct DenoLaunchedConfig {
@@ -101,7 +174,7 @@ fn default_flexible_npm() -> bool {
 ///
 /// jupyter_protocol serializes as: `{"ExecuteResult": {"data": {...}, ...}}`
 /// nbformat expects: `{"output_type": "execute_result", "data": {...}, ...}`
-fn message_content_to_nbformat(content: &JupyterMessageContent) -> Option<serde_json::Value> {
+pub fn message_content_to_nbformat(content: &JupyterMessageContent) -> Option<serde_json::Value> {
     use serde_json::json;
 
     match content {
@@ -158,9 +231,183 @@ fn media_to_display_data(media: &jupyter_protocol::Media) -> serde_json::Value {
     })
 }
 
-/// Check if a string looks like a manifest hash (64-char hex).
-fn is_manifest_hash(s: &str) -> bool {
-    s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit())
+/// The payloads of an `execute_reply` that frontends act on, in order.
+/// `edit_magic` asks a terminal client to open an editor, which has no
+/// notebook equivalent, so it is dropped.
+fn kernel_payloads(payloads: &[jupyter_protocol::Payload]) -> Vec<KernelPayload> {
+    payloads
+        .iter()
+        .filter_map(|payload| match payload {
+            jupyter_protocol::Payload::Page { data, start } => Some(KernelPayload::Page {
+                data: serde_json::to_value(data).ok()?,
+                start: *start,
+            }),
+            jupyter_protocol::Payload::SetNextInput { text, replace } => {
+                Some(KernelPayload::SetNextInput {
+                    text: text.clone(),
+                    replace: *replace,
+                })
+            }
+            jupyter_protocol::Payload::AskExit { keepkernel } => Some(KernelPayload::AskExit {
+                keepkernel: *keepkernel,
+            }),
+            jupyter_protocol::Payload::EditMagic { .. } => None,
+        })
+        .collect()
+}
+
+/// Apply a `set_next_input` payload from a run of `cell_id`: replace the
+/// cell's source with `text`, or insert a code cell holding `text` after it.
+/// Returns `false` if the cell is no longer in the doc.
+fn apply_set_next_input(
+    doc: &mut NotebookDoc,
+    cell_id: &str,
+    text: &str,
+    replace: bool,
+) -> Result<bool> {
+    if replace {
+        return Ok(doc.update_source(cell_id, text)?);
+    }
+    let Some(index) = doc.get_cells().iter().position(|cell| cell.id == cell_id) else {
+        return Ok(false);
+    };
+    let next_id = Uuid::new_v4().to_string();
+    doc.add_cell(index + 1, &next_id, "code")?;
+    doc.update_source(&next_id, text)?;
+    Ok(true)
+}
+
+/// Resolve the cell a kernel message belongs to via its `parent_header.msg_id`.
+///
+/// The msg_id → cell_id map is the only source of truth: it is populated when
+/// an execute_request is sent and a cell's previous entry is dropped on
+/// re-execution, so late messages from a superseded run resolve to `None`.
+fn cell_for_message(
+    cell_id_map: &StdMutex<HashMap<String, String>>,
+    message: &JupyterMessage,
+) -> Option<String> {
+    let parent = message.parent_header.as_ref()?;
+    cell_id_map.lock().ok()?.get(&parent.msg_id).cloned()
+}
+
+/// Debug-log an output that could not be routed to any cell.
+fn log_unrouted(message: &JupyterMessage) {
+    debug!(
+        "[kernel-manager] Dropping {} with unknown parent msg_id={:?}",
+        message.header.msg_type,
+        message.parent_header.as_ref().map(|h| &h.msg_id)
+    );
+}
+
+/// Start times of in-flight executions, keyed by execute_request msg_id.
+///
+/// Started on the kernel's `execute_input` and finished on the `status: idle`
+/// with the same parent, which is what JupyterLab's execution-time extension
+/// measures. Idle messages for other requests (completions, kernel_info) have
+/// no entry and are ignored.
+#[derive(Debug, Default)]
+struct ExecutionTimer {
+    started: HashMap<String, StartedExecution>,
+}
+
+#[derive(Debug)]
+struct StartedExecution {
+    cell_id: String,
+    at: DateTime<Utc>,
+    source_hash: String,
+    /// `ename: evalue` of the first error the execution raised
+    error: Option<String>,
+}
+
+/// An execution that went idle, with what the run log records about it.
+#[derive(Debug)]
+struct FinishedExecution {
+    cell_id: String,
+    timing: CellExecutionTiming,
+    source_hash: String,
+    error: Option<String>,
+}
+
+impl FinishedExecution {
+    fn run_log_entry(&self) -> RunLogEntry {
+        RunLogEntry {
+            cell_id: self.cell_id.clone(),
+            source_hash: self.source_hash.clone(),
+            started_at: self.timing.started_at.clone(),
+            duration_ms: self.timing.duration_ms,
+            status: if self.error.is_some() {
+                RunStatus::Error
+            } else {
+                RunStatus::Ok
+            },
+            error: self.error.clone(),
+        }
+    }
+}
+
+impl ExecutionTimer {
+    fn start(&mut self, msg_id: &str, cell_id: &str, code: &str, at: DateTime<Utc>) {
+        self.started.insert(
+            msg_id.to_string(),
+            StartedExecution {
+                cell_id: cell_id.to_string(),
+                at,
+                source_hash: run_log::source_hash(code),
+                error: None,
+            },
+        );
+    }
+
+    /// Note that the execution for `msg_id` raised `ename: evalue`.
+    fn record_error(&mut self, msg_id: &str, ename: &str, evalue: &str) {
+        if let Some(started) = self.started.get_mut(msg_id) {
+            started
+                .error
+                .get_or_insert_with(|| format!("{}: {}", ename, evalue));
+        }
+    }
+
+    /// Finish the execution for `msg_id`, returning its cell and timing.
+    fn finish(&mut self, msg_id: &str, at: DateTime<Utc>) -> Option<FinishedExecution> {
+        let started = self.started.remove(msg_id)?;
+        let duration_ms = (at - started.at).num_milliseconds().max(0) as u64;
+        let timing = CellExecutionTiming {
+            started_at: started.at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            completed_at: at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            duration_ms,
+        };
+        Some(FinishedExecution {
+            cell_id: started.cell_id,
+            timing,
+            source_hash: started.source_hash,
+            error: started.error,
+        })
+    }
+}
+
+/// Store a finished execution's timing in the doc so every window sees it.
+async fn record_execution_timing(
+    doc: &RwLock<NotebookDoc>,
+    persist_path: &std::path::Path,
+    changed_tx: &broadcast::Sender<()>,
+    cell_id: &str,
+    timing: &CellExecutionTiming,
+) {
+    let persist_bytes = {
+        let mut doc_guard = doc.write().await;
+        match doc_guard.set_execution_timing(cell_id, timing) {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(e) => {
+                warn!("[kernel-manager] Failed to record execution timing: {}", e);
+                return;
+            }
+        }
+        let bytes = doc_guard.save();
+        let _ = changed_tx.send(());
+        bytes
+    };
+    persist_notebook_bytes(&persist_bytes, persist_path);
 }
 
 /// Update an output by display_id when outputs are manifest hashes.
@@ -176,13 +423,14 @@ async fn update_output_by_display_id_with_manifests(
     new_data: &serde_json::Value,
     new_metadata: &serde_json::Map<String, serde_json::Value>,
     blob_store: &BlobStore,
+    manifest_options: &ManifestOptions,
 ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
     // Get all outputs from the document
     let outputs = doc.get_all_outputs();
 
     for (cell_id, output_idx, output_str) in outputs {
         // Check if it's a manifest hash or raw JSON
-        if is_manifest_hash(&output_str) {
+        if output_store::is_manifest_hash(&output_str) {
             // Fetch manifest from blob store
             let manifest_bytes = match blob_store.get(&output_str).await? {
                 Some(bytes) => bytes,
@@ -197,15 +445,19 @@ async fn update_output_by_display_id_with_manifests(
                 new_data,
                 new_metadata,
                 blob_store,
-                DEFAULT_INLINE_THRESHOLD,
+                manifest_options,
             )
             .await?
             {
-                // Store the updated manifest and get new hash
-                let new_hash = output_store::store_manifest(&updated_manifest, blob_store).await?;
+                // Store the updated manifest, releasing the one it replaces
+                let new_hash =
+                    output_store::replace_manifest(&updated_manifest, &output_str, blob_store)
+                        .await?;
 
                 // Replace the hash in the document
-                doc.replace_output(&cell_id, output_idx, &new_hash)?;
+                if new_hash != output_str {
+                    doc.replace_output(&cell_id, output_idx, &new_hash)?;
+                }
                 return Ok(true);
             }
         } else {
@@ -242,6 +494,22 @@ async fn update_output_by_display_id_with_manifests(
 pub struct QueuedCell {
     pub cell_id: String,
     pub code: String,
+    /// Whether an error in this cell stops the cells queued after it. Off
+    /// for cells tagged `raises-exception`.
+    pub stop_on_error: bool,
+}
+
+/// A run-all batch in progress: the cells it sent that haven't finished.
+#[derive(Debug)]
+struct RunAllBatch {
+    pending: HashSet<String>,
+    total: usize,
+}
+
+impl RunAllBatch {
+    fn completed(&self) -> usize {
+        self.total - self.pending.len()
+    }
 }
 
 /// Kernel status.
@@ -258,6 +526,8 @@ pub enum KernelStatus {
     Error,
     /// Kernel is shutting down
     ShuttingDown,
+    /// Kernel process exited unexpectedly
+    Dead,
 }
 
 impl std::fmt::Display for KernelStatus {
@@ -268,6 +538,7 @@ impl std::fmt::Display for KernelStatus {
             KernelStatus::Busy => write!(f, "busy"),
             KernelStatus::Error => write!(f, "error"),
             KernelStatus::ShuttingDown => write!(f, "shutdown"),
+            KernelStatus::Dead => write!(f, "dead"),
         }
     }
 }
@@ -278,6 +549,98 @@ impl std::fmt::Display for KernelStatus {
 type PendingCompletions =
     Arc<StdMutex<HashMap<String, oneshot::Sender<(Vec<CompletionItem>, usize, usize)>>>>;
 
+/// Type alias for pending inspection response channels: (found, text/plain).
+type PendingInspections = Arc<StdMutex<HashMap<String, oneshot::Sender<(bool, Option<String>)>>>>;
+
+/// Type alias for pending silent execution channels: `Err` carries
+/// `"ename: evalue"` when the code raised.
+type PendingSilent = Arc<StdMutex<HashMap<String, oneshot::Sender<Result<(), String>>>>>;
+
+/// A silent execute_request that has been sent to the kernel.
+pub struct SilentExecution {
+    msg_id: String,
+    rx: oneshot::Receiver<Result<(), String>>,
+    pending: PendingSilent,
+}
+
+impl SilentExecution {
+    /// Wait for the execute_reply; an error if the code raised.
+    ///
+    /// Times out just before the client's 30 second request timeout.
+    pub async fn wait(self) -> Result<()> {
+        match tokio::time::timeout(std::time::Duration::from_secs(25), self.rx).await {
+            Ok(Ok(result)) => result.map_err(|e| anyhow::anyhow!(e)),
+            Ok(Err(_)) => Err(anyhow::anyhow!("Silent execution cancelled")),
+            Err(_) => {
+                if let Ok(mut pending) = self.pending.lock() {
+                    pending.remove(&self.msg_id);
+                }
+                Err(anyhow::anyhow!("Silent execution timed out"))
+            }
+        }
+    }
+}
+
+/// A kernel state snapshot or restore in progress.
+pub struct KernelStateRun {
+    execution: SilentExecution,
+    report_path: PathBuf,
+}
+
+impl KernelStateRun {
+    /// Wait for the kernel to finish and read its report.
+    pub async fn finish(self) -> Result<SnapshotReport> {
+        self.execution.wait().await?;
+        kernel_snapshot::take_report(&self.report_path)
+    }
+}
+
+/// A `debug_request` that has been sent to the kernel on its own control
+/// connection.
+pub struct DebugExchange {
+    control: runtimelib::ClientControlConnection,
+    msg_id: String,
+    command: String,
+}
+
+impl DebugExchange {
+    /// Wait for the kernel's `debug_reply` and return the body of its DAP
+    /// response. A response with `success: false` becomes an error carrying
+    /// its `message`. Times out after 10 seconds.
+    pub async fn response(mut self) -> Result<serde_json::Value> {
+        let deadline = tokio::time::Instant::now() + DEBUG_REPLY_TIMEOUT;
+        let response = loop {
+            let reply = tokio::time::timeout_at(deadline, self.control.read())
+                .await
+                .map_err(|_| anyhow::anyhow!("Debug request timed out"))??;
+            let answers_request = reply
+                .parent_header
+                .as_ref()
+                .is_some_and(|parent| parent.msg_id == self.msg_id);
+            if let JupyterMessageContent::DebugReply(reply) = reply.content {
+                if answers_request {
+                    break reply.content;
+                }
+            }
+        };
+
+        if response.get("success").and_then(serde_json::Value::as_bool) != Some(true) {
+            let message = response
+                .get("message")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or("no reason given");
+            anyhow::bail!("Debug request {} failed: {}", self.command, message);
+        }
+        Ok(response
+            .get("body")
+            .cloned()
+            .unwrap_or(serde_json::Value::Null))
+    }
+}
+
+/// Type alias for pending `kernel_info_request` pings.
+type PendingKernelInfo = Arc<StdMutex<HashMap<String, oneshot::Sender<()>>>>;
+
 /// Unlike the notebook app's `NotebookKernel`, this broadcasts outputs
 /// to all connected peers rather than emitting Tauri events.
 pub struct RoomKernel {
@@ -299,17 +662,24 @@ pub struct RoomKernel {
     shell_reader_task: Option<tokio::task::JoinHandle<()>>,
     /// Shell writer for sending execute requests
     shell_writer: Option<runtimelib::DealerSendConnection>,
-    /// The kernel process
-    process: Option<tokio::process::Child>,
+    /// Task owning the kernel process; reports unexpected exits
+    process_monitor: Option<ProcessMonitor>,
     /// Process group ID for cleanup (Unix only)
     #[cfg(unix)]
     process_group_id: Option<i32>,
-    /// Mapping from msg_id → cell_id for routing iopub messages
+    /// Authoritative msg_id → cell_id mapping for routing iopub and shell replies
     cell_id_map: Arc<StdMutex<HashMap<String, String>>>,
-    /// Execution queue (pending cells)
+    /// Execution queue (pending cells, execute_request not yet sent)
     queue: VecDeque<QueuedCell>,
+    /// Cells whose execute_request was already sent by `execute_batch` and
+    /// are waiting in the kernel's shell queue behind the executing cell
+    in_flight: VecDeque<String>,
+    /// Queued or executing cells whose errors don't stop the queue
+    raises_exception: HashSet<String>,
     /// Currently executing cell
     executing: Option<String>,
+    /// The run-all batch being executed, for progress reporting
+    run_all: Option<RunAllBatch>,
     /// Current kernel status
     status: KernelStatus,
     /// Broadcast channel for sending outputs to peers
@@ -332,8 +702,41 @@ pub struct RoomKernel {
     pending_history: Arc<StdMutex<HashMap<String, oneshot::Sender<Vec<HistoryEntry>>>>>,
     /// Pending completion requests: msg_id → response channel
     pending_completions: PendingCompletions,
+    /// Pending inspect requests: msg_id → response channel
+    pending_inspections: PendingInspections,
+    /// Pending silent executions (kernel state snapshots): msg_id → response channel
+    pending_silent: PendingSilent,
+    /// Pending pings: msg_id → response channel
+    pending_kernel_info: PendingKernelInfo,
+    /// How long the queue waits for a ping before giving up on the kernel
+    ping_timeout: std::time::Duration,
+    /// Caps on the buffers of comm messages from the frontend
+    comm_buffer_limits: BufferLimits,
+    /// Whether the kernel answered its startup handshake or latest ping
+    responsive: bool,
     /// Terminal emulators for stream outputs (stdout/stderr)
     stream_terminals: Arc<tokio::sync::Mutex<StreamTerminals>>,
+    /// Options used when building output manifests (inlining, ANSI spans)
+    manifest_options: ManifestOptions,
+    /// Startup timeout and retry
+    startup_policy: StartupPolicy,
+    /// How `interrupt()` reaches the kernel
+    interrupt_mode: InterruptMode,
+    /// Whether the kernel reported `debugger: true` in its kernel_info reply
+    debugger: bool,
+    /// `seq` of the next Debug Adapter Protocol request
+    debug_seq: u64,
+    /// Where finished executions are logged, if the `run_log` setting is on
+    run_log: Option<RunLog>,
+    /// Whether failed imports offer to install the missing package
+    install_suggestions: bool,
+    /// env_id registry and this room's notebook ID, for collision warnings
+    env_claims: Option<(EnvIdClaims, String)>,
+    /// Spawned-kernel registry and this room's notebook ID, for reaping
+    /// kernels a crashed daemon leaves behind
+    kernel_pids: Option<(KernelPids, String)>,
+    /// Command line of the most recent spawn, successful or not
+    last_launch_spec: Option<LaunchSpec>,
 }
 
 /// Commands from iopub/shell handlers for queue state management.
@@ -346,6 +749,321 @@ pub enum QueueCommand {
     ExecutionDone { cell_id: String },
     /// A cell produced an error (for stop-on-error behavior)
     CellError { cell_id: String },
+    /// The kernel process exited without being shut down
+    KernelDied {
+        exit_code: Option<i32>,
+        stderr_tail: String,
+    },
+    /// Interrupt the executing cell and drop the rest of the queue
+    /// (cancelling a run-all)
+    InterruptAndClear,
+}
+
+/// Number of trailing stderr lines kept for crash reports.
+const STDERR_TAIL_LINES: usize = 40;
+
+/// The last lines a kernel process wrote to stderr, drained into a ring
+/// buffer by a background task.
+struct StderrTail {
+    lines: Arc<StdMutex<VecDeque<String>>>,
+    reader: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl StderrTail {
+    /// Start draining the process's stderr, if it was piped.
+    fn capture(process: &mut tokio::process::Child) -> Self {
+        use tokio::io::{AsyncBufReadExt, BufReader};
+
+        let lines = Arc::new(StdMutex::new(VecDeque::with_capacity(STDERR_TAIL_LINES)));
+        let reader = process.stderr.take().map(|stderr| {
+            let tail = lines.clone();
+            crate::log_format::spawn_in_scope(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let mut tail = tail.lock().unwrap();
+                    if tail.len() == STDERR_TAIL_LINES {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line);
+                }
+            })
+        });
+        Self { lines, reader }
+    }
+
+    /// The lines captured so far, joined with newlines.
+    fn snapshot(&self) -> String {
+        self.lines
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Wait briefly for the reader to reach the end of the pipe after the
+    /// process exits, then take what was captured.
+    async fn drain(&mut self) -> String {
+        // Grandchildren may hold the pipe open, so don't wait for EOF forever
+        if let Some(task) = self.reader.as_mut() {
+            let _ = tokio::time::timeout(std::time::Duration::from_millis(500), &mut *task).await;
+            task.abort();
+        }
+        self.snapshot()
+    }
+}
+
+/// How long `shutdown()` waits for a kernel to exit after `shutdown_request`
+/// before killing it. Short enough that window close and app exit never
+/// stall on a hung kernel.
+const SHUTDOWN_GRACE: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// How long a newly launched kernel has to answer a `kernel_info_request`
+/// before its first cell is sent.
+const KERNEL_PING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// How long `debug_request()` waits for the kernel's `debug_reply`.
+const DEBUG_REPLY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// A task that owns the kernel process and reports when it exits.
+struct ProcessMonitor {
+    /// Resolves to whether the process had to be killed
+    task: tokio::task::JoinHandle<bool>,
+    /// Tells the task the exit is expected, and how long to wait for it
+    /// before killing the process
+    stop_tx: Option<oneshot::Sender<std::time::Duration>>,
+    /// When [`join`](Self::join) gives up on the task
+    deadline: Option<tokio::time::Instant>,
+}
+
+impl ProcessMonitor {
+    /// Stop the monitor without waiting. Dropping the child kills it
+    /// (`kill_on_drop`), and no crash is reported.
+    fn abort(self) {
+        self.task.abort();
+    }
+
+    /// Treat the process exiting as intentional rather than a crash, and
+    /// kill it if it's still running after `grace`.
+    fn expect_exit(&mut self, grace: std::time::Duration) {
+        if let Some(stop_tx) = self.stop_tx.take() {
+            let _ = stop_tx.send(grace);
+            // The task kills and reaps the child itself; the extra second
+            // only guards against a wait that never returns.
+            self.deadline =
+                Some(tokio::time::Instant::now() + grace + std::time::Duration::from_secs(1));
+        }
+    }
+
+    /// Wait for the process to exit after [`expect_exit`](Self::expect_exit).
+    /// Returns whether it had to be killed.
+    async fn join(mut self) -> bool {
+        let deadline = self.deadline.unwrap_or_else(tokio::time::Instant::now);
+        match tokio::time::timeout_at(deadline, &mut self.task).await {
+            Ok(Ok(killed)) => killed,
+            _ => {
+                self.task.abort();
+                true
+            }
+        }
+    }
+}
+
+/// Take ownership of the kernel process and report when it exits.
+///
+/// The last lines of `stderr` are attached to the crash report. An
+/// intentional shutdown goes through [`ProcessMonitor::expect_exit`] or
+/// [`ProcessMonitor::abort`] and never reports a crash.
+fn spawn_process_monitor(
+    mut process: tokio::process::Child,
+    mut stderr: StderrTail,
+    cmd_tx: mpsc::Sender<QueueCommand>,
+) -> ProcessMonitor {
+    let (stop_tx, stop_rx) = oneshot::channel::<std::time::Duration>();
+    let task = crate::log_format::spawn_in_scope(async move {
+        let status = tokio::select! {
+            status = process.wait() => status,
+            Ok(grace) = stop_rx => {
+                if tokio::time::timeout(grace, process.wait()).await.is_ok() {
+                    return false;
+                }
+                warn!(
+                    "[kernel-manager] Kernel didn't exit within {:?} of shutdown; killing it",
+                    grace
+                );
+                if let Err(e) = process.kill().await {
+                    error!("[kernel-manager] Failed to kill kernel process: {}", e);
+                }
+                return true;
+            }
+        };
+        let stderr_tail = stderr.drain().await;
+
+        let exit_code = match status {
+            Ok(status) => {
+                warn!("[kernel-manager] Kernel process exited: {}", status);
+                status.code()
+            }
+            Err(e) => {
+                error!("[kernel-manager] Failed to wait on kernel process: {}", e);
+                None
+            }
+        };
+        let _ = cmd_tx
+            .send(QueueCommand::KernelDied {
+                exit_code,
+                stderr_tail,
+            })
+            .await;
+        false
+    });
+    ProcessMonitor {
+        task,
+        stop_tx: Some(stop_tx),
+        deadline: None,
+    }
+}
+
+/// How a kernel expects to be interrupted, from its kernelspec's
+/// `interrupt_mode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InterruptMode {
+    /// SIGINT to the kernel's process group (the Jupyter default)
+    #[default]
+    Signal,
+    /// `interrupt_request` on the control channel, for kernels that don't
+    /// handle signals
+    Message,
+}
+
+impl InterruptMode {
+    /// Parse a kernelspec's `interrupt_mode`; missing or unknown values
+    /// mean signal, as in jupyter_client.
+    pub fn from_kernelspec(interrupt_mode: Option<&str>) -> Self {
+        match interrupt_mode {
+            Some("message") => Self::Message,
+            _ => Self::Signal,
+        }
+    }
+}
+
+/// How long to wait for a launched kernel to answer `kernel_info`, and
+/// whether to try again if it doesn't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StartupPolicy {
+    pub timeout: std::time::Duration,
+    /// Launch once more after a [`KernelStartupError`]
+    pub retry: bool,
+}
+
+impl Default for StartupPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: std::time::Duration::from_secs(60),
+            retry: true,
+        }
+    }
+}
+
+/// A kernel process that was spawned but never became ready.
+///
+/// Each variant carries the tail of the kernel's stderr, which is usually
+/// where the real cause is (e.g. `No module named ipykernel_launcher`).
+#[derive(Debug, thiserror::Error)]
+pub enum KernelStartupError {
+    #[error("Kernel did not respond within {}s", .timeout.as_secs())]
+    TimedOut {
+        timeout: std::time::Duration,
+        stderr_tail: String,
+    },
+    #[error("Kernel exited during startup{}", describe_exit_code(*.exit_code))]
+    Exited {
+        exit_code: Option<i32>,
+        stderr_tail: String,
+    },
+    #[error("Kernel did not respond: {reason}")]
+    NoReply { reason: String, stderr_tail: String },
+}
+
+impl KernelStartupError {
+    /// The last lines the kernel wrote to stderr before startup failed.
+    pub fn stderr_tail(&self) -> &str {
+        match self {
+            Self::TimedOut { stderr_tail, .. }
+            | Self::Exited { stderr_tail, .. }
+            | Self::NoReply { stderr_tail, .. } => stderr_tail,
+        }
+    }
+
+    /// Exit code of a kernel that exited during startup.
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            Self::Exited { exit_code, .. } => *exit_code,
+            _ => None,
+        }
+    }
+}
+
+/// Connect iopub and shell to the kernel at `connection_info` and wait for its
+/// reply to a `kernel_info_request` (connecting retries until the kernel binds
+/// its ports). Also returns whether the reply advertises the debugger.
+async fn connect_kernel(
+    connection_info: &ConnectionInfo,
+    session_id: &str,
+) -> Result<(
+    runtimelib::ClientIoPubConnection,
+    runtimelib::ClientShellConnection,
+    bool,
+)> {
+    let iopub = runtimelib::create_client_iopub_connection(connection_info, "", session_id).await?;
+    let identity = runtimelib::peer_identity_for_session(session_id)?;
+    let mut shell = runtimelib::create_client_shell_connection_with_identity(
+        connection_info,
+        session_id,
+        identity,
+    )
+    .await?;
+    let request: JupyterMessage = KernelInfoRequest::default().into();
+    shell.send(request).await?;
+    let msg = shell.read().await?;
+    info!(
+        "[kernel-manager] Kernel alive: got {} reply",
+        msg.header.msg_type
+    );
+    let debugger = matches!(
+        msg.content,
+        JupyterMessageContent::KernelInfoReply(ref reply) if reply.debugger
+    );
+    Ok((iopub, shell, debugger))
+}
+
+/// Buffers of a comm message from the frontend, sent as JSON byte arrays.
+/// Their count and size are checked against `limits` before any is copied.
+fn comm_buffers(raw_message: &serde_json::Value, limits: BufferLimits) -> Result<Vec<Bytes>> {
+    let Some(buffers) = raw_message.get("buffers").and_then(|v| v.as_array()) else {
+        return Ok(Vec::new());
+    };
+    limits.check_sizes(buffers.iter().map(|buf| buf.as_array().map_or(0, Vec::len)))?;
+    Ok(buffers
+        .iter()
+        .filter_map(|buf| {
+            buf.as_array().map(|bytes| {
+                let bytes: Vec<u8> = bytes
+                    .iter()
+                    .filter_map(|b| b.as_u64().map(|n| n as u8))
+                    .collect();
+                Bytes::from(bytes)
+            })
+        })
+        .collect())
+}
+
+fn describe_exit_code(exit_code: Option<i32>) -> String {
+    match exit_code {
+        Some(code) => format!(" with code {}", code),
+        None => String::new(),
+    }
 }
 
 /// Prepend a directory to the PATH environment variable.
@@ -377,12 +1095,15 @@ impl RoomKernel {
             iopub_task: None,
             shell_reader_task: None,
             shell_writer: None,
-            process: None,
+            process_monitor: None,
             #[cfg(unix)]
             process_group_id: None,
             cell_id_map: Arc::new(StdMutex::new(HashMap::new())),
             queue: VecDeque::new(),
+            in_flight: VecDeque::new(),
+            raises_exception: HashSet::new(),
             executing: None,
+            run_all: None,
             status: KernelStatus::Starting,
             broadcast_tx,
             cmd_tx: None,
@@ -394,10 +1115,79 @@ impl RoomKernel {
             comm_state,
             pending_history: Arc::new(StdMutex::new(HashMap::new())),
             pending_completions: Arc::new(StdMutex::new(HashMap::new())),
+            pending_inspections: Arc::new(StdMutex::new(HashMap::new())),
+            pending_silent: Arc::new(StdMutex::new(HashMap::new())),
+            pending_kernel_info: Arc::new(StdMutex::new(HashMap::new())),
+            ping_timeout: KERNEL_PING_TIMEOUT,
+            comm_buffer_limits: BufferLimits::DEFAULT,
+            responsive: false,
             stream_terminals: Arc::new(tokio::sync::Mutex::new(StreamTerminals::new())),
+            manifest_options: ManifestOptions::default(),
+            startup_policy: StartupPolicy::default(),
+            interrupt_mode: InterruptMode::default(),
+            debugger: false,
+            debug_seq: 1,
+            run_log: None,
+            install_suggestions: true,
+            env_claims: None,
+            kernel_pids: None,
+            last_launch_spec: None,
         }
     }
 
+    /// Set the options used when building output manifests.
+    ///
+    /// Must be called before `launch()`; the iopub task captures a copy.
+    pub fn set_manifest_options(&mut self, options: ManifestOptions) {
+        self.manifest_options = options;
+    }
+
+    /// Set how long `launch()` waits for the kernel and whether it retries.
+    pub fn set_startup_policy(&mut self, policy: StartupPolicy) {
+        self.startup_policy = policy;
+    }
+
+    /// How `interrupt()` reaches the running kernel.
+    pub fn interrupt_mode(&self) -> InterruptMode {
+        self.interrupt_mode
+    }
+
+    /// Whether the running kernel implements the Jupyter debug protocol.
+    pub fn supports_debugger(&self) -> bool {
+        self.debugger
+    }
+
+    /// Log finished executions to `run_log`.
+    ///
+    /// Must be called before `launch()`; the iopub task captures a copy.
+    pub fn set_run_log(&mut self, run_log: Option<RunLog>) {
+        self.run_log = run_log;
+    }
+
+    /// Offer to install the package behind a failed import (on by default).
+    ///
+    /// Must be called before `launch()`; the iopub task captures a copy.
+    pub fn set_install_suggestions(&mut self, enabled: bool) {
+        self.install_suggestions = enabled;
+    }
+
+    /// Register this kernel's env_id in `claims` under `notebook_id` on
+    /// launch, so rooms sharing an env_id get an `EnvIdCollision` warning.
+    pub fn set_env_claims(&mut self, claims: EnvIdClaims, notebook_id: &str) {
+        self.env_claims = Some((claims, notebook_id.to_string()));
+    }
+
+    /// Record spawned kernel processes in `pids` until they exit.
+    pub fn set_kernel_pids(&mut self, pids: KernelPids, notebook_id: &str) {
+        self.kernel_pids = Some((pids, notebook_id.to_string()));
+    }
+
+    /// A sender for queueing commands alongside the kernel's own, if a kernel
+    /// is running.
+    pub fn command_sender(&self) -> Option<mpsc::Sender<QueueCommand>> {
+        self.cmd_tx.clone()
+    }
+
     /// Take the command receiver for polling by the sync server.
     ///
     /// This should be called after `launch()` and polled in the sync server's
@@ -417,11 +1207,37 @@ impl RoomKernel {
         &self.env_source
     }
 
+    /// The environment source the running kernel was launched with (e.g.
+    /// `"uv:prewarmed"`, `"conda:inline"`), or `None` if no kernel is running.
+    pub fn running_env_source(&self) -> Option<&str> {
+        (self.is_running() && !self.env_source.is_empty()).then_some(self.env_source.as_str())
+    }
+
+    /// PID of the kernel process, if one was spawned and is still owned.
+    pub fn pid(&self) -> Option<u32> {
+        #[cfg(unix)]
+        return self.process_group_id.map(|pgid| pgid as u32);
+        #[cfg(not(unix))]
+        None
+    }
+
     /// Get the environment configuration used at launch (for sync detection).
     pub fn launched_config(&self) -> &LaunchedEnvConfig {
         &self.launched_config
     }
 
+    /// Connection file of the running kernel, for attaching other Jupyter
+    /// clients such as the sidecar viewer.
+    pub fn connection_file(&self) -> Option<&Path> {
+        self.connection_file.as_deref()
+    }
+
+    /// The command line, working directory and environment changes of the
+    /// most recent kernel spawn, including one that failed to start.
+    pub fn last_launch_spec(&self) -> Option<&LaunchSpec> {
+        self.last_launch_spec.as_ref()
+    }
+
     /// Update the UV deps in the launched config after hot-sync.
     /// This ensures future sync checks know about the newly installed packages.
     pub fn update_launched_uv_deps(&mut self, deps: Vec<String>) {
@@ -445,20 +1261,30 @@ impl RoomKernel {
 
     /// Get the queued cell IDs.
     pub fn queued_cells(&self) -> Vec<String> {
-        self.queue.iter().map(|c| c.cell_id.clone()).collect()
+        self.in_flight
+            .iter()
+            .cloned()
+            .chain(self.queue.iter().map(|c| c.cell_id.clone()))
+            .collect()
     }
 
     /// Launch a kernel for this room.
     ///
     /// If `env` is provided (prewarmed pool environment), launches using that environment's
     /// Python directly. For `uv:inline` sources, uses `uv run --with` with the provided deps.
-    /// For `uv:pyproject`, uses `uv run` in the project directory.
+    /// For `uv:pyproject`, uses `uv run` in the project directory. Kernel types other
+    /// than `python` and `deno` are launched from the installed kernelspec of that name.
     ///
     /// Note: `conda:inline` currently falls back to prewarmed pool (inline deps not installed).
     /// TODO: Implement on-demand conda env creation for conda:inline deps.
     ///
     /// The `launched_config` captures the environment configuration used at launch time,
     /// enabling detection of metadata drift (e.g., user added deps while kernel running).
+    ///
+    /// The kernel must answer `kernel_info` within the [`StartupPolicy`] timeout. If it
+    /// doesn't, or exits first, the launch fails with a [`KernelStartupError`] (retried
+    /// once if the policy allows) and a `KernelLifecycle` "error" event carrying the tail
+    /// of the kernel's stderr is broadcast.
     pub async fn launch(
         &mut self,
         kernel_type: &str,
@@ -472,10 +1298,95 @@ impl RoomKernel {
             self.shutdown().await.ok();
         }
 
+        let mut result = self
+            .launch_once(
+                kernel_type,
+                env_source,
+                notebook_path,
+                env.clone(),
+                launched_config.clone(),
+            )
+            .await;
+        if let Err(e) = &result {
+            self.abort_launch();
+            if self.startup_policy.retry && e.is::<KernelStartupError>() {
+                warn!("[kernel-manager] {}; retrying once", e);
+                result = self
+                    .launch_once(kernel_type, env_source, notebook_path, env, launched_config)
+                    .await;
+                if result.is_err() {
+                    self.abort_launch();
+                }
+            }
+        }
+
+        if let (Ok(()), Some(venv_path)) = (&result, &self.launched_config.venv_path) {
+            crate::env_cache::mark_used(venv_path);
+        }
+
+        if let (Ok(()), Some((claims, notebook_id))) = (&result, &self.env_claims) {
+            match &self.launched_config.env_id {
+                Some(env_id) => {
+                    claims.claim(env_id, notebook_id, self.broadcast_tx.clone());
+                }
+                None => claims.release(notebook_id),
+            }
+        }
+
+        if let Err(e) = &result {
+            self.status = KernelStatus::Error;
+            let startup = e.downcast_ref::<KernelStartupError>();
+            let _ = self.broadcast_tx.send(NotebookBroadcast::KernelLifecycle {
+                state: "error".to_string(),
+                kernel_type: Some(self.kernel_type.clone()),
+                env_source: Some(self.env_source.clone()),
+                exit_code: startup.and_then(|s| s.exit_code()),
+                stderr_tail: startup
+                    .map(|s| s.stderr_tail().to_string())
+                    .filter(|s| !s.is_empty()),
+                cancelled_cells: vec![],
+                reason: Some(e.to_string()),
+            });
+        }
+        result
+    }
+
+    /// Clean up after a launch attempt that failed partway through: stop the
+    /// iopub listener, kill whatever the kernel process started and remove
+    /// the connection file.
+    fn abort_launch(&mut self) {
+        if let Some(task) = self.iopub_task.take() {
+            task.abort();
+        }
+
+        #[cfg(unix)]
+        if let Some(pgid) = self.take_process_group() {
+            use nix::sys::signal::{killpg, Signal};
+            use nix::unistd::Pid;
+            let _ = killpg(Pid::from_raw(pgid), Signal::SIGKILL);
+        }
+
+        if let Some(path) = self.connection_file.take() {
+            let _ = std::fs::remove_file(path);
+        }
+        self.cmd_tx = None;
+        self.cmd_rx = None;
+    }
+
+    /// One attempt at starting the kernel; see [`RoomKernel::launch`].
+    async fn launch_once(
+        &mut self,
+        kernel_type: &str,
+        env_source: &str,
+        notebook_path: Option<&std::path::Path>,
+        env: Option<PooledEnv>,
+        launched_config: LaunchedEnvConfig,
+    ) -> Result<()> {
         self.kernel_type = kernel_type.to_string();
         self.env_source = env_source.to_string();
         self.launched_config = launched_config;
         self.status = KernelStatus::Starting;
+        self.responsive = false;
 
         // Broadcast starting status
         let _ = self.broadcast_tx.send(NotebookBroadcast::KernelStatus {
@@ -520,6 +1431,7 @@ impl RoomKernel {
             serde_json::to_string_pretty(&connection_info)?,
         )
         .await?;
+        self.connection_file = Some(connection_file_path.clone());
 
         // Determine working directory
         let cwd = if let Some(path) = notebook_path {
@@ -548,6 +1460,11 @@ impl RoomKernel {
             }
         };
 
+        // ipykernel and deno both handle interrupt_request, which also
+        // reaches them through the `uv run` wrapper; installed kernelspecs
+        // say how they want to be interrupted
+        self.interrupt_mode = InterruptMode::Message;
+
         // Build kernel command based on kernel type
         let mut cmd = match kernel_type {
             "python" => {
@@ -624,6 +1541,21 @@ impl RoomKernel {
                         cmd.stderr(Stdio::null());
                         cmd
                     }
+                    crate::system_python::SYSTEM_PYTHON_SOURCE => {
+                        // No env manager available: ipykernel from the Python on PATH
+                        let python = crate::system_python::find_system_python()
+                            .ok_or_else(|| anyhow::anyhow!("No Python found on PATH"))?;
+                        info!(
+                            "[kernel-manager] Starting Python kernel with system Python at {:?}",
+                            python
+                        );
+                        let mut cmd = tokio::process::Command::new(&python);
+                        cmd.args(["-Xfrozen_modules=off", "-m", "ipykernel_launcher", "-f"]);
+                        cmd.arg(&connection_file_path);
+                        cmd.stdout(Stdio::null());
+                        cmd.stderr(Stdio::null());
+                        cmd
+                    }
                     _ => {
                         // Prewarmed - use pooled environment
                         let pooled_env = env.ok_or_else(|| {
@@ -666,27 +1598,73 @@ impl RoomKernel {
                 cmd.stderr(Stdio::null());
                 cmd
             }
-            _ => {
-                return Err(anyhow::anyhow!(
-                    "Unsupported kernel type: {}. Supported types: python, deno",
-                    kernel_type
-                ));
+            other => {
+                // Any other kernel type names an installed Jupyter kernelspec
+                // (e.g. "ir", "julia-1.10"). Launch it exactly as its kernel.json
+                // describes - no uv/conda environment handling applies.
+                let kernelspec = runtimelib::find_kernelspec(other).await?;
+                info!(
+                    "[kernel-manager] Starting {} kernel from kernelspec at {:?}",
+                    other, kernelspec.path
+                );
+                self.interrupt_mode =
+                    InterruptMode::from_kernelspec(kernelspec.kernelspec.interrupt_mode.as_deref());
+                kernelspec.command(
+                    &connection_file_path,
+                    Some(Stdio::null()),
+                    Some(Stdio::null()),
+                )?
             }
         };
         cmd.current_dir(&cwd);
 
+        // Capture stderr for crash reports (drained by the process monitor)
+        cmd.stderr(Stdio::piped());
+
         // Set terminal size for consistent output formatting
         cmd.env("COLUMNS", TERMINAL_COLUMNS_STR);
         cmd.env("LINES", TERMINAL_LINES_STR);
 
-        #[cfg(unix)]
-        cmd.process_group(0);
-
-        let process = cmd.kill_on_drop(true).spawn()?;
-
-        #[cfg(unix)]
-        {
+        // environment.yml `variables:` apply to kernels launched from that file
+        if env_source == "conda:env_yml" {
+            if let Some(detected) = notebook_path.and_then(crate::project_file::detect_project_file)
+            {
+                let vars = crate::environment_yml::variables(&detected.path);
+                if !vars.is_empty() {
+                    info!(
+                        "[kernel-manager] Setting {} variables from {:?}",
+                        vars.len(),
+                        detected.path
+                    );
+                    cmd.envs(vars);
+                }
+            }
+        }
+
+        let spec = LaunchSpec::from_command(&cmd);
+        debug!(
+            "[kernel-manager] Launching {:?} in {:?} with env {:?}",
+            spec.argv, spec.cwd, spec.env
+        );
+        self.last_launch_spec = Some(spec);
+
+        #[cfg(unix)]
+        cmd.process_group(0);
+
+        let mut process = cmd.kill_on_drop(true).spawn()?;
+        let mut stderr = StderrTail::capture(&mut process);
+
+        #[cfg(unix)]
+        {
             self.process_group_id = process.id().map(|pid| pid as i32);
+            if let (Some(pid), Some((pids, notebook_id))) = (process.id(), &self.kernel_pids) {
+                pids.track(TrackedKernel {
+                    pid,
+                    notebook_id: notebook_id.clone(),
+                    kernel_type: kernel_type.to_string(),
+                    started_at: Utc::now(),
+                });
+            }
         }
 
         // Small delay to let the kernel start
@@ -694,11 +1672,53 @@ impl RoomKernel {
 
         self.session_id = Uuid::new_v4().to_string();
 
-        // Create iopub connection and spawn listener
-        let mut iopub =
-            runtimelib::create_client_iopub_connection(&connection_info, "", &self.session_id)
-                .await?;
+        // Connect and verify the kernel is alive, giving up if it exits or
+        // takes too long (connecting retries until the kernel binds its ports)
+        let timeout = self.startup_policy.timeout;
+        let handshake = connect_kernel(&connection_info, &self.session_id);
+        let startup = tokio::select! {
+            reply = tokio::time::timeout(timeout, handshake) => match reply {
+                Ok(Ok(connections)) => Ok(connections),
+                Ok(Err(e)) => Err(KernelStartupError::NoReply {
+                    reason: e.to_string(),
+                    stderr_tail: stderr.snapshot(),
+                }),
+                Err(_) => Err(KernelStartupError::TimedOut {
+                    timeout,
+                    stderr_tail: stderr.snapshot(),
+                }),
+            },
+            status = process.wait() => Err(KernelStartupError::Exited {
+                exit_code: status.ok().and_then(|s| s.code()),
+                stderr_tail: stderr.drain().await,
+            }),
+        };
+        let (iopub, shell, debugger) = match startup {
+            Ok(connections) => connections,
+            Err(e) => {
+                error!("[kernel-manager] {}", e);
+                return Err(e.into());
+            }
+        };
+
+        self.debugger = debugger;
+        self.responsive = true;
+        let cmd_tx = self.spawn_io_tasks(iopub, shell);
+        self.connection_info = Some(connection_info);
+        self.process_monitor = Some(spawn_process_monitor(process, stderr, cmd_tx));
+        self.announce_launched();
+
+        info!("[kernel-manager] Kernel started: {}", kernel_id);
+        Ok(())
+    }
 
+    /// Start the tasks that read iopub and shell replies from a connected
+    /// kernel. Returns the sender for the queue commands they emit.
+    fn spawn_io_tasks(
+        &mut self,
+        mut iopub: runtimelib::ClientIoPubConnection,
+        shell: runtimelib::ClientShellConnection,
+    ) -> mpsc::Sender<QueueCommand> {
         // Create command channel for queue processing
         let (cmd_tx, cmd_rx) = mpsc::channel::<QueueCommand>(100);
         self.cmd_tx = Some(cmd_tx.clone());
@@ -712,8 +1732,12 @@ impl RoomKernel {
         let blob_store = self.blob_store.clone();
         let comm_state = self.comm_state.clone();
         let stream_terminals = self.stream_terminals.clone();
+        let manifest_options = self.manifest_options.clone();
+        let run_log = self.run_log.clone();
+        let install_suggestions = self.install_suggestions;
 
-        let iopub_task = tokio::spawn(async move {
+        let iopub_task = crate::log_format::spawn_in_scope(async move {
+            let mut execution_timer = ExecutionTimer::default();
             loop {
                 match iopub.read().await {
                     Ok(message) => {
@@ -723,11 +1747,8 @@ impl RoomKernel {
                             message.parent_header.as_ref().map(|h| &h.msg_id)
                         );
 
-                        // Look up cell_id from msg_id
-                        let cell_id = message
-                            .parent_header
-                            .as_ref()
-                            .and_then(|h| cell_id_map.lock().ok()?.get(&h.msg_id).cloned());
+                        // Route strictly by parent msg_id, never by the executing cell
+                        let cell_id = cell_for_message(&cell_id_map, &message);
 
                         // Handle different message types
                         match &message.content {
@@ -750,6 +1771,22 @@ impl RoomKernel {
                                 // Signal execution done when idle
                                 if status.execution_state == jupyter_protocol::ExecutionState::Idle
                                 {
+                                    let finished = message.parent_header.as_ref().and_then(|h| {
+                                        execution_timer.finish(&h.msg_id, Utc::now())
+                                    });
+                                    if let Some(finished) = finished {
+                                        record_execution_timing(
+                                            &doc,
+                                            &persist_path,
+                                            &changed_tx,
+                                            &finished.cell_id,
+                                            &finished.timing,
+                                        )
+                                        .await;
+                                        if let Some(ref run_log) = run_log {
+                                            run_log.append(&finished.run_log_entry());
+                                        }
+                                    }
                                     if let Some(cid) = cell_id {
                                         let _ = iopub_cmd_tx
                                             .try_send(QueueCommand::ExecutionDone { cell_id: cid });
@@ -759,11 +1796,21 @@ impl RoomKernel {
 
                             JupyterMessageContent::ExecuteInput(input) => {
                                 if let Some(ref cid) = cell_id {
+                                    if let Some(parent) = message.parent_header.as_ref() {
+                                        execution_timer.start(
+                                            &parent.msg_id,
+                                            cid,
+                                            &input.code,
+                                            Utc::now(),
+                                        );
+                                    }
                                     let _ =
                                         broadcast_tx.send(NotebookBroadcast::ExecutionStarted {
                                             cell_id: cid.clone(),
                                             execution_count: input.execution_count.0 as i64,
                                         });
+                                } else {
+                                    log_unrouted(&message);
                                 }
                             }
 
@@ -825,47 +1872,74 @@ impl RoomKernel {
                                     };
 
                                     // Create nbformat JSON with rendered text
-                                    let nbformat_value = serde_json::json!({
-                                        "output_type": "stream",
-                                        "name": stream_name,
-                                        "text": rendered_text
-                                    });
+                                    let Some(nbformat_value) = output_store::transform_output(
+                                        serde_json::json!({
+                                            "output_type": "stream",
+                                            "name": stream_name,
+                                            "text": rendered_text
+                                        }),
+                                        &manifest_options,
+                                    ) else {
+                                        continue;
+                                    };
 
-                                    // Create and store manifest
-                                    let output_ref = match output_store::create_manifest(
-                                        &nbformat_value,
-                                        &blob_store,
-                                        DEFAULT_INLINE_THRESHOLD,
-                                    )
-                                    .await
-                                    {
-                                        Ok(manifest_json) => {
-                                            match output_store::store_manifest(
-                                                &manifest_json,
-                                                &blob_store,
-                                            )
-                                            .await
-                                            {
-                                                Ok(hash) => hash,
-                                                Err(e) => {
-                                                    warn!(
+                                    // Create and store manifest, releasing the one
+                                    // it replaces
+                                    let previous_ref =
+                                        known_state.as_ref().map(|s| s.manifest_hash.clone());
+                                    let output_ref =
+                                        match output_store::create_manifest_with_options(
+                                            &nbformat_value,
+                                            &blob_store,
+                                            &manifest_options,
+                                        )
+                                        .await
+                                        {
+                                            Ok(manifest_json) => {
+                                                let stored = match &previous_ref {
+                                                    Some(previous) => {
+                                                        output_store::replace_manifest(
+                                                            &manifest_json,
+                                                            previous,
+                                                            &blob_store,
+                                                        )
+                                                        .await
+                                                    }
+                                                    None => {
+                                                        output_store::store_manifest(
+                                                            &manifest_json,
+                                                            &blob_store,
+                                                        )
+                                                        .await
+                                                    }
+                                                };
+                                                match stored {
+                                                    Ok(hash) => hash,
+                                                    Err(e) => {
+                                                        warn!(
                                                         "[kernel-manager] Failed to store stream manifest: {}",
                                                         e
                                                     );
-                                                    nbformat_value.to_string()
+                                                        nbformat_value.to_string()
+                                                    }
                                                 }
                                             }
-                                        }
-                                        Err(e) => {
-                                            warn!(
+                                            Err(e) => {
+                                                warn!(
                                                 "[kernel-manager] Failed to create stream manifest: {}",
                                                 e
                                             );
-                                            nbformat_value.to_string()
-                                        }
-                                    };
+                                                nbformat_value.to_string()
+                                            }
+                                        };
+                                    // The rendered stream didn't change; the
+                                    // document already shows it
+                                    if previous_ref.as_ref() == Some(&output_ref) {
+                                        continue;
+                                    }
 
                                     // Upsert stream output (update if validated, append if not)
+                                    let mut stream_index = None;
                                     let persist_bytes = {
                                         let mut doc_guard = doc.write().await;
                                         match doc_guard.upsert_stream_output(
@@ -875,6 +1949,7 @@ impl RoomKernel {
                                             known_state.as_ref(),
                                         ) {
                                             Ok((_updated, output_index)) => {
+                                                stream_index = Some(output_index);
                                                 // Store new state (index + hash) for future validation
                                                 let mut terminals = stream_terminals.lock().await;
                                                 terminals.set_output_state(
@@ -903,7 +1978,10 @@ impl RoomKernel {
                                         cell_id: cid.clone(),
                                         output_type: "stream".to_string(),
                                         output_json: output_ref,
+                                        output_index: stream_index,
                                     });
+                                } else {
+                                    log_unrouted(&message);
                                 }
                             }
 
@@ -957,44 +2035,48 @@ impl RoomKernel {
                                     }
 
                                     // Convert to nbformat JSON for storage
-                                    if let Some(nbformat_value) =
-                                        message_content_to_nbformat(&message.content)
-                                    {
+                                    if let Some(nbformat_value) = message_content_to_nbformat(
+                                        &message.content,
+                                    )
+                                    .and_then(|output| {
+                                        output_store::transform_output(output, &manifest_options)
+                                    }) {
                                         // Create manifest (inlines small data, blobs large data)
-                                        let output_ref = match output_store::create_manifest(
-                                            &nbformat_value,
-                                            &blob_store,
-                                            DEFAULT_INLINE_THRESHOLD,
-                                        )
-                                        .await
-                                        {
-                                            Ok(manifest_json) => {
-                                                // Store manifest in blob store, get hash
-                                                match output_store::store_manifest(
-                                                    &manifest_json,
-                                                    &blob_store,
-                                                )
-                                                .await
-                                                {
-                                                    Ok(hash) => hash,
-                                                    Err(e) => {
-                                                        warn!(
+                                        let output_ref =
+                                            match output_store::create_manifest_with_options(
+                                                &nbformat_value,
+                                                &blob_store,
+                                                &manifest_options,
+                                            )
+                                            .await
+                                            {
+                                                Ok(manifest_json) => {
+                                                    // Store manifest in blob store, get hash
+                                                    match output_store::store_manifest(
+                                                        &manifest_json,
+                                                        &blob_store,
+                                                    )
+                                                    .await
+                                                    {
+                                                        Ok(hash) => hash,
+                                                        Err(e) => {
+                                                            warn!(
                                                             "[kernel-manager] Failed to store manifest: {}",
                                                             e
                                                         );
-                                                        nbformat_value.to_string()
-                                                        // Fallback to raw JSON
+                                                            nbformat_value.to_string()
+                                                            // Fallback to raw JSON
+                                                        }
                                                     }
                                                 }
-                                            }
-                                            Err(e) => {
-                                                warn!(
+                                                Err(e) => {
+                                                    warn!(
                                                     "[kernel-manager] Failed to create manifest: {}",
                                                     e
                                                 );
-                                                nbformat_value.to_string() // Fallback to raw JSON
-                                            }
-                                        };
+                                                    nbformat_value.to_string() // Fallback to raw JSON
+                                                }
+                                            };
 
                                         // Append hash (or fallback JSON) to Automerge doc
                                         let persist_bytes = {
@@ -1017,8 +2099,11 @@ impl RoomKernel {
                                             cell_id: cid.clone(),
                                             output_type: output_type.to_string(),
                                             output_json: output_ref,
+                                            output_index: None,
                                         });
                                     }
+                                } else {
+                                    log_unrouted(&message);
                                 }
                             }
 
@@ -1027,14 +2112,33 @@ impl RoomKernel {
                             // Supports both manifest hashes and raw JSON (backward compatibility).
                             JupyterMessageContent::UpdateDisplayData(update) => {
                                 if let Some(ref display_id) = update.transient.display_id {
+                                    // Updates go through the transformers like new outputs;
+                                    // a dropped update leaves the display as it was
+                                    let Some(updated) = output_store::transform_output(
+                                        serde_json::json!({
+                                            "output_type": "display_data",
+                                            "data": update.data,
+                                            "metadata": update.metadata,
+                                        }),
+                                        &manifest_options,
+                                    ) else {
+                                        continue;
+                                    };
+                                    let data = updated["data"].clone();
+                                    let metadata = updated["metadata"]
+                                        .as_object()
+                                        .cloned()
+                                        .unwrap_or_default();
+
                                     let persist_bytes = {
                                         let mut doc_guard = doc.write().await;
                                         match update_output_by_display_id_with_manifests(
                                             &mut doc_guard,
                                             display_id,
-                                            &serde_json::to_value(&update.data).unwrap_or_default(),
-                                            &update.metadata,
+                                            &data,
+                                            &metadata,
                                             &blob_store,
+                                            &manifest_options,
                                         )
                                         .await
                                         {
@@ -1064,17 +2168,31 @@ impl RoomKernel {
                                     persist_notebook_bytes(&persist_bytes, &persist_path);
 
                                     // Broadcast for immediate UI update
-                                    // Frontend will receive via Automerge sync, but broadcast for speed
-                                    let _ = broadcast_tx.send(NotebookBroadcast::DisplayUpdate {
-                                        display_id: display_id.clone(),
-                                        data: serde_json::to_value(&update.data)
-                                            .unwrap_or_default(),
-                                        metadata: update.metadata.clone(),
-                                    });
+                                    // Frontend will receive via Automerge sync, but broadcast for speed.
+                                    // Data big enough to be blobbed only goes through sync.
+                                    if output_store::bundle_fits_inline(
+                                        &data,
+                                        manifest_options.inline_threshold,
+                                    ) {
+                                        let _ =
+                                            broadcast_tx.send(NotebookBroadcast::DisplayUpdate {
+                                                display_id: display_id.clone(),
+                                                data,
+                                                metadata,
+                                            });
+                                    }
                                 }
                             }
 
-                            JupyterMessageContent::ErrorOutput(_) => {
+                            JupyterMessageContent::ErrorOutput(ref error) => {
+                                if let Some(parent) = message.parent_header.as_ref() {
+                                    execution_timer.record_error(
+                                        &parent.msg_id,
+                                        &error.ename,
+                                        &error.evalue,
+                                    );
+                                }
+
                                 // Check if this error should go to an Output widget
                                 let parent_msg_id = message
                                     .parent_header
@@ -1115,42 +2233,46 @@ impl RoomKernel {
                                     }
 
                                     // Convert error to nbformat JSON
-                                    if let Some(nbformat_value) =
-                                        message_content_to_nbformat(&message.content)
-                                    {
+                                    if let Some(nbformat_value) = message_content_to_nbformat(
+                                        &message.content,
+                                    )
+                                    .and_then(|output| {
+                                        output_store::transform_output(output, &manifest_options)
+                                    }) {
                                         // Create manifest for error output
-                                        let output_ref = match output_store::create_manifest(
-                                            &nbformat_value,
-                                            &blob_store,
-                                            DEFAULT_INLINE_THRESHOLD,
-                                        )
-                                        .await
-                                        {
-                                            Ok(manifest_json) => {
-                                                match output_store::store_manifest(
-                                                    &manifest_json,
-                                                    &blob_store,
-                                                )
-                                                .await
-                                                {
-                                                    Ok(hash) => hash,
-                                                    Err(e) => {
-                                                        warn!(
+                                        let output_ref =
+                                            match output_store::create_manifest_with_options(
+                                                &nbformat_value,
+                                                &blob_store,
+                                                &manifest_options,
+                                            )
+                                            .await
+                                            {
+                                                Ok(manifest_json) => {
+                                                    match output_store::store_manifest(
+                                                        &manifest_json,
+                                                        &blob_store,
+                                                    )
+                                                    .await
+                                                    {
+                                                        Ok(hash) => hash,
+                                                        Err(e) => {
+                                                            warn!(
                                                             "[kernel-manager] Failed to store error manifest: {}",
                                                             e
                                                         );
-                                                        nbformat_value.to_string()
+                                                            nbformat_value.to_string()
+                                                        }
                                                     }
                                                 }
-                                            }
-                                            Err(e) => {
-                                                warn!(
+                                                Err(e) => {
+                                                    warn!(
                                                     "[kernel-manager] Failed to create error manifest: {}",
                                                     e
                                                 );
-                                                nbformat_value.to_string()
-                                            }
-                                        };
+                                                    nbformat_value.to_string()
+                                                }
+                                            };
 
                                         // Write error output to Automerge doc before broadcasting
                                         let persist_bytes = {
@@ -1173,13 +2295,30 @@ impl RoomKernel {
                                             cell_id: cid.clone(),
                                             output_type: "error".to_string(),
                                             output_json: output_ref,
+                                            output_index: None,
                                         });
                                     }
 
+                                    // Offer to install the package behind a failed import
+                                    let suggestion = install_suggestions
+                                        .then(|| {
+                                            crate::missing_import::suggest_install(
+                                                cid,
+                                                &error.ename,
+                                                &error.evalue,
+                                            )
+                                        })
+                                        .flatten();
+                                    if let Some(suggestion) = suggestion {
+                                        let _ = broadcast_tx.send(suggestion);
+                                    }
+
                                     // Signal cell error for stop-on-error
                                     let _ = iopub_cmd_tx.try_send(QueueCommand::CellError {
                                         cell_id: cid.clone(),
                                     });
+                                } else {
+                                    log_unrouted(&message);
                                 }
                             }
 
@@ -1309,36 +2448,7 @@ impl RoomKernel {
             }
         });
 
-        // Create shell connection
-        let identity = runtimelib::peer_identity_for_session(&self.session_id)?;
-        let mut shell = runtimelib::create_client_shell_connection_with_identity(
-            &connection_info,
-            &self.session_id,
-            identity,
-        )
-        .await?;
-
-        // Verify kernel is alive
-        let request: JupyterMessage = KernelInfoRequest::default().into();
-        shell.send(request).await?;
-
-        let reply = tokio::time::timeout(std::time::Duration::from_secs(30), shell.read()).await;
-        match reply {
-            Ok(Ok(msg)) => {
-                info!(
-                    "[kernel-manager] Kernel alive: got {} reply",
-                    msg.header.msg_type
-                );
-            }
-            Ok(Err(e)) => {
-                error!("[kernel-manager] Error reading kernel_info_reply: {}", e);
-                return Err(anyhow::anyhow!("Kernel did not respond: {}", e));
-            }
-            Err(_) => {
-                error!("[kernel-manager] Timeout waiting for kernel_info_reply");
-                return Err(anyhow::anyhow!("Kernel did not respond within 30s"));
-            }
-        }
+        self.iopub_task = Some(iopub_task);
 
         // Split shell into reader/writer
         let (shell_writer, mut shell_reader) = shell.split();
@@ -1348,13 +2458,17 @@ impl RoomKernel {
         let shell_cell_id_map = self.cell_id_map.clone();
         let shell_pending_history = self.pending_history.clone();
         let shell_pending_completions = self.pending_completions.clone();
+        let shell_pending_inspections = self.pending_inspections.clone();
+        let shell_pending_silent = self.pending_silent.clone();
+        let shell_pending_kernel_info = self.pending_kernel_info.clone();
         // Additional resources for handling page payloads (IPython ? and ?? help)
         let shell_doc = self.doc.clone();
         let shell_blob_store = self.blob_store.clone();
         let shell_persist_path = self.persist_path.clone();
         let shell_changed_tx = self.changed_tx.clone();
+        let shell_manifest_options = self.manifest_options.clone();
 
-        let shell_reader_task = tokio::spawn(async move {
+        let shell_reader_task = crate::log_format::spawn_in_scope(async move {
             loop {
                 match shell_reader.read().await {
                     Ok(msg) => {
@@ -1362,10 +2476,27 @@ impl RoomKernel {
 
                         match msg.content {
                             JupyterMessageContent::ExecuteReply(ref reply) => {
+                                // Silent executions belong to the daemon, not a cell
+                                if let Some(ref parent) = msg.parent_header {
+                                    let tx = shell_pending_silent
+                                        .lock()
+                                        .ok()
+                                        .and_then(|mut p| p.remove(&parent.msg_id));
+                                    if let Some(tx) = tx {
+                                        let result = match (&reply.status, &reply.error) {
+                                            (jupyter_protocol::ReplyStatus::Ok, _) => Ok(()),
+                                            (_, Some(err)) => {
+                                                Err(format!("{}: {}", err.ename, err.evalue))
+                                            }
+                                            (status, None) => Err(format!("{:?}", status)),
+                                        };
+                                        let _ = tx.send(result);
+                                        continue;
+                                    }
+                                }
+
                                 // Get cell_id from msg_id mapping
-                                let cell_id = msg.parent_header.as_ref().and_then(|h| {
-                                    shell_cell_id_map.lock().ok()?.get(&h.msg_id).cloned()
-                                });
+                                let cell_id = cell_for_message(&shell_cell_id_map, &msg);
 
                                 // Process page payloads - convert to display_data outputs
                                 // This handles IPython's ? and ?? help commands
@@ -1375,41 +2506,49 @@ impl RoomKernel {
                                             payload
                                         {
                                             // Convert Media to nbformat display_data
-                                            let nbformat_value = media_to_display_data(data);
+                                            let Some(nbformat_value) =
+                                                output_store::transform_output(
+                                                    media_to_display_data(data),
+                                                    &shell_manifest_options,
+                                                )
+                                            else {
+                                                continue;
+                                            };
 
                                             // Create manifest and store (same pattern as iopub_task)
-                                            let output_ref = match output_store::create_manifest(
-                                                &nbformat_value,
-                                                &shell_blob_store,
-                                                DEFAULT_INLINE_THRESHOLD,
-                                            )
-                                            .await
-                                            {
-                                                Ok(manifest_json) => {
-                                                    match output_store::store_manifest(
-                                                        &manifest_json,
-                                                        &shell_blob_store,
-                                                    )
-                                                    .await
-                                                    {
-                                                        Ok(hash) => hash,
-                                                        Err(e) => {
-                                                            warn!(
+                                            let output_ref =
+                                                match output_store::create_manifest_with_options(
+                                                    &nbformat_value,
+                                                    &shell_blob_store,
+                                                    &shell_manifest_options,
+                                                )
+                                                .await
+                                                {
+                                                    Ok(manifest_json) => {
+                                                        match output_store::store_manifest(
+                                                            &manifest_json,
+                                                            &shell_blob_store,
+                                                        )
+                                                        .await
+                                                        {
+                                                            Ok(hash) => hash,
+                                                            Err(e) => {
+                                                                warn!(
                                                                 "[kernel-manager] Failed to store page manifest: {}",
                                                                 e
                                                             );
-                                                            nbformat_value.to_string()
+                                                                nbformat_value.to_string()
+                                                            }
                                                         }
                                                     }
-                                                }
-                                                Err(e) => {
-                                                    warn!(
+                                                    Err(e) => {
+                                                        warn!(
                                                         "[kernel-manager] Failed to create page manifest: {}",
                                                         e
                                                     );
-                                                    nbformat_value.to_string()
-                                                }
-                                            };
+                                                        nbformat_value.to_string()
+                                                    }
+                                                };
 
                                             // Append to Automerge doc
                                             let persist_bytes = {
@@ -1437,12 +2576,52 @@ impl RoomKernel {
                                                     cell_id: cid.clone(),
                                                     output_type: "display_data".to_string(),
                                                     output_json: output_ref,
+                                                    output_index: None,
                                                 },
                                             );
                                         }
                                     }
                                 }
 
+                                // Apply `%load`-style next inputs to the doc here,
+                                // once, rather than in every window, then let the
+                                // frontends know about each payload
+                                if let Some(ref cid) = cell_id {
+                                    for payload in kernel_payloads(&reply.payload) {
+                                        if let KernelPayload::SetNextInput { ref text, replace } =
+                                            payload
+                                        {
+                                            let persist_bytes = {
+                                                let mut doc_guard = shell_doc.write().await;
+                                                if let Err(e) = apply_set_next_input(
+                                                    &mut doc_guard,
+                                                    cid,
+                                                    text,
+                                                    replace,
+                                                ) {
+                                                    warn!(
+                                                        "[kernel-manager] Failed to apply set_next_input: {}",
+                                                        e
+                                                    );
+                                                }
+                                                let bytes = doc_guard.save();
+                                                let _ = shell_changed_tx.send(());
+                                                bytes
+                                            };
+                                            persist_notebook_bytes(
+                                                &persist_bytes,
+                                                &shell_persist_path,
+                                            );
+                                        }
+                                        let _ = shell_broadcast_tx.send(
+                                            NotebookBroadcast::KernelPayload {
+                                                cell_id: cid.clone(),
+                                                payload,
+                                            },
+                                        );
+                                    }
+                                }
+
                                 // Broadcast execution done for error status
                                 if reply.status != jupyter_protocol::ReplyStatus::Ok {
                                     if let Some(ref cid) = cell_id {
@@ -1533,6 +2712,34 @@ impl RoomKernel {
                                     }
                                 }
                             }
+                            JupyterMessageContent::KernelInfoReply(_) => {
+                                if let Some(ref parent) = msg.parent_header {
+                                    let tx = shell_pending_kernel_info
+                                        .lock()
+                                        .ok()
+                                        .and_then(|mut p| p.remove(&parent.msg_id));
+                                    if let Some(tx) = tx {
+                                        let _ = tx.send(());
+                                    }
+                                }
+                            }
+                            JupyterMessageContent::InspectReply(ref reply) => {
+                                if let Some(ref parent) = msg.parent_header {
+                                    let tx = shell_pending_inspections
+                                        .lock()
+                                        .ok()
+                                        .and_then(|mut p| p.remove(&parent.msg_id));
+                                    if let Some(tx) = tx {
+                                        let text =
+                                            serde_json::to_value(&reply.data).ok().and_then(|v| {
+                                                v.get("text/plain")
+                                                    .and_then(|t| t.as_str())
+                                                    .map(String::from)
+                                            });
+                                        let _ = tx.send((reply.found, text));
+                                    }
+                                }
+                            }
                             _ => {
                                 debug!(
                                     "[kernel-manager] shell reply: type={}",
@@ -1553,13 +2760,13 @@ impl RoomKernel {
         // (the sync server will call execution_done when it receives ExecutionDone)
         self.cmd_rx = Some(cmd_rx);
 
-        // Store state
-        self.connection_info = Some(connection_info);
-        self.connection_file = Some(connection_file_path);
-        self.iopub_task = Some(iopub_task);
         self.shell_reader_task = Some(shell_reader_task);
         self.shell_writer = Some(shell_writer);
-        self.process = Some(process);
+        cmd_tx
+    }
+
+    /// Mark a freshly connected kernel idle and tell clients it launched.
+    fn announce_launched(&mut self) {
         self.status = KernelStatus::Idle;
 
         // Broadcast idle status
@@ -1567,10 +2774,16 @@ impl RoomKernel {
             status: "idle".to_string(),
             cell_id: None,
         });
-
-        info!("[kernel-manager] Kernel started: {}", kernel_id);
-        Ok(())
-    }
+        let _ = self.broadcast_tx.send(NotebookBroadcast::KernelLifecycle {
+            state: "launched".to_string(),
+            kernel_type: Some(self.kernel_type.clone()),
+            env_source: Some(self.env_source.clone()),
+            exit_code: None,
+            stderr_tail: None,
+            cancelled_cells: vec![],
+            reason: None,
+        });
+    }
 
     /// Queue a cell for execution.
     ///
@@ -1585,7 +2798,7 @@ impl RoomKernel {
             );
             return Ok(());
         }
-        if self.queue.iter().any(|c| c.cell_id == cell_id) {
+        if self.in_flight.contains(&cell_id) || self.queue.iter().any(|c| c.cell_id == cell_id) {
             info!("[kernel-manager] Cell {} already queued, skipping", cell_id);
             return Ok(());
         }
@@ -1593,9 +2806,11 @@ impl RoomKernel {
         info!("[kernel-manager] Queuing cell: {}", cell_id);
 
         // Add to queue
+        self.raises_exception.remove(&cell_id);
         self.queue.push_back(QueuedCell {
             cell_id: cell_id.clone(),
             code,
+            stop_on_error: true,
         });
 
         // Broadcast queue state
@@ -1604,10 +2819,81 @@ impl RoomKernel {
             queued: self.queued_cells(),
         });
 
+        self.ensure_responsive().await?;
+
         // Try to process if nothing executing
         self.process_next().await
     }
 
+    /// Check that the kernel answers on its shell channel: send a
+    /// `kernel_info_request` and wait up to `timeout` for the reply.
+    ///
+    /// A live process isn't enough; a kernel that failed to import ipykernel,
+    /// or is wedged, never replies. The result is remembered, so a kernel
+    /// that misses a ping is pinged again before its next cell.
+    pub async fn ping(&mut self, timeout: std::time::Duration) -> bool {
+        let Some(shell) = self.shell_writer.as_mut() else {
+            return false;
+        };
+        let message: JupyterMessage = KernelInfoRequest::default().into();
+        let msg_id = message.header.msg_id.clone();
+
+        let (tx, rx) = oneshot::channel();
+        if let Ok(mut pending) = self.pending_kernel_info.lock() {
+            pending.insert(msg_id.clone(), tx);
+        }
+        let replied = match shell.send(message).await {
+            Ok(()) => matches!(tokio::time::timeout(timeout, rx).await, Ok(Ok(()))),
+            Err(e) => {
+                warn!("[kernel-manager] Failed to send kernel_info_request: {}", e);
+                false
+            }
+        };
+        if !replied {
+            if let Ok(mut pending) = self.pending_kernel_info.lock() {
+                pending.remove(&msg_id);
+            }
+        }
+        self.responsive = replied;
+        replied
+    }
+
+    /// Unless the kernel has already answered on the shell channel it runs
+    /// cells on (the startup handshake counts), ping it before sending the
+    /// next cell. If it doesn't answer, the queued cells are
+    /// cancelled and an `unresponsive` lifecycle event is broadcast instead
+    /// of leaving them waiting on a kernel that will never reply.
+    async fn ensure_responsive(&mut self) -> Result<()> {
+        if self.responsive || self.shell_writer.is_none() {
+            return Ok(());
+        }
+        if self.ping(self.ping_timeout).await {
+            return Ok(());
+        }
+
+        let reason = format!(
+            "Kernel did not answer kernel_info_request within {}s",
+            self.ping_timeout.as_secs()
+        );
+        warn!("[kernel-manager] {}", reason);
+        let cancelled_cells: Vec<String> = self.queue.drain(..).map(|c| c.cell_id).collect();
+        self.status = KernelStatus::Error;
+        let _ = self.broadcast_tx.send(NotebookBroadcast::QueueChanged {
+            executing: None,
+            queued: vec![],
+        });
+        let _ = self.broadcast_tx.send(NotebookBroadcast::KernelLifecycle {
+            state: "unresponsive".to_string(),
+            kernel_type: Some(self.kernel_type.clone()),
+            env_source: Some(self.env_source.clone()),
+            exit_code: None,
+            stderr_tail: None,
+            cancelled_cells,
+            reason: Some(reason.clone()),
+        });
+        Err(anyhow::anyhow!(reason))
+    }
+
     /// Clear outputs for a cell (before re-execution).
     pub async fn clear_outputs(&self, cell_id: &str) {
         info!("[kernel-manager] Clearing outputs for cell: {}", cell_id);
@@ -1616,6 +2902,118 @@ impl RoomKernel {
         terminals.clear(cell_id);
     }
 
+    /// Queue several cells at once and pipeline their execute requests.
+    ///
+    /// Unlike calling `queue_cell` per cell, every execute_request is sent up
+    /// front (kernels run shell requests in order), the msg_id → cell_id map
+    /// is updated under a single lock, and one `QueueChanged` is broadcast for
+    /// the whole batch. Outputs and replies are still routed per cell by
+    /// `parent_header.msg_id`.
+    ///
+    /// Cells still waiting in the unsent queue are pipelined first so order is
+    /// preserved. Cells already executing or queued are skipped. Returns the
+    /// `(cell_id, msg_id)` pairs that were sent.
+    pub async fn execute_batch(&mut self, cells: &[QueuedCell]) -> Result<Vec<(String, String)>> {
+        if self.shell_writer.is_none() {
+            return Err(anyhow::anyhow!("No kernel running"));
+        }
+        self.ensure_responsive().await?;
+
+        let mut pending: Vec<QueuedCell> = self.queue.drain(..).collect();
+        for cell in cells {
+            let cell_id = &cell.cell_id;
+            let duplicate = self.executing.as_ref() == Some(cell_id)
+                || self.in_flight.contains(cell_id)
+                || pending.iter().any(|c| &c.cell_id == cell_id);
+            if duplicate {
+                info!("[kernel-manager] Cell {} already queued, skipping", cell_id);
+                continue;
+            }
+            if cell.stop_on_error {
+                self.raises_exception.remove(cell_id);
+            } else {
+                self.raises_exception.insert(cell_id.clone());
+            }
+            pending.push(cell.clone());
+        }
+
+        let requests = self.register_execute_requests(&pending);
+        let mut sent = Vec::with_capacity(requests.len());
+        let shell = self.shell_writer.as_mut().unwrap();
+        for (cell_id, message) in requests {
+            let msg_id = message.header.msg_id.clone();
+            shell.send(message).await?;
+            self.in_flight.push_back(cell_id.clone());
+            sent.push((cell_id, msg_id));
+        }
+        info!("[kernel-manager] Pipelined {} execute_requests", sent.len());
+
+        if self.executing.is_none() {
+            if let Some(cell_id) = self.in_flight.pop_front() {
+                self.executing = Some(cell_id);
+                self.status = KernelStatus::Busy;
+            }
+        }
+
+        let _ = self.broadcast_tx.send(NotebookBroadcast::QueueChanged {
+            executing: self.executing.clone(),
+            queued: self.queued_cells(),
+        });
+
+        Ok(sent)
+    }
+
+    /// Run all of a notebook's cells: pipeline `cells` like
+    /// [`execute_batch`](Self::execute_batch), then broadcast `RunAllProgress`
+    /// as each cell sent finishes. Returns how many cells were sent.
+    pub async fn run_all(&mut self, cells: &[QueuedCell]) -> Result<usize> {
+        let sent = self.execute_batch(cells).await?;
+        let total = sent.len();
+        self.run_all = (total > 0).then(|| RunAllBatch {
+            pending: sent.into_iter().map(|(cell_id, _)| cell_id).collect(),
+            total,
+        });
+        self.broadcast_run_all_progress();
+        Ok(total)
+    }
+
+    /// Broadcast how far the run-all batch has got, and forget it once every
+    /// cell has finished.
+    fn broadcast_run_all_progress(&mut self) {
+        let Some(batch) = &self.run_all else {
+            return;
+        };
+        let completed = batch.completed();
+        let total = batch.total;
+        let _ = self.broadcast_tx.send(NotebookBroadcast::RunAllProgress {
+            completed,
+            total,
+            current_cell: self.executing.clone(),
+        });
+        if completed == total {
+            self.run_all = None;
+        }
+    }
+
+    /// Build execute requests for `cells` and register their msg_ids.
+    ///
+    /// Old mappings for the same cells are dropped so the map stays bounded
+    /// to one entry per cell.
+    fn register_execute_requests(&self, cells: &[QueuedCell]) -> Vec<(String, JupyterMessage)> {
+        let mut map = self.cell_id_map.lock().unwrap();
+        cells
+            .iter()
+            .map(|cell| {
+                let mut request = ExecuteRequest::new(cell.code.clone());
+                request.stop_on_error = cell.stop_on_error;
+                let message: JupyterMessage = request.into();
+                map.retain(|_, v| v != &cell.cell_id);
+                map.insert(message.header.msg_id.clone(), cell.cell_id.clone());
+                (cell.cell_id.clone(), message)
+            })
+            .collect()
+    }
+
     /// Process the next cell in the queue.
     async fn process_next(&mut self) -> Result<()> {
         // Already executing?
@@ -1623,6 +3021,17 @@ impl RoomKernel {
             return Ok(());
         }
 
+        // A pipelined cell is already at the kernel; just mark it executing
+        if let Some(cell_id) = self.in_flight.pop_front() {
+            self.executing = Some(cell_id);
+            self.status = KernelStatus::Busy;
+            let _ = self.broadcast_tx.send(NotebookBroadcast::QueueChanged {
+                executing: self.executing.clone(),
+                queued: self.queued_cells(),
+            });
+            return Ok(());
+        }
+
         // Get next cell
         let Some(cell) = self.queue.pop_front() else {
             return Ok(());
@@ -1646,7 +3055,8 @@ impl RoomKernel {
             .send(NotebookBroadcast::QueueChanged { executing, queued });
 
         // Send execute request
-        let request = ExecuteRequest::new(cell.code.clone());
+        let mut request = ExecuteRequest::new(cell.code.clone());
+        request.stop_on_error = cell.stop_on_error;
         let message: JupyterMessage = request.into();
         let msg_id = message.header.msg_id.clone();
 
@@ -1693,26 +3103,24 @@ impl RoomKernel {
                 queued: self.queued_cells(),
             });
 
+            let in_run_all = self
+                .run_all
+                .as_mut()
+                .is_some_and(|batch| batch.pending.remove(cell_id));
+
             // Process next
-            self.process_next().await?;
+            let next = self.process_next().await;
+            if in_run_all {
+                self.broadcast_run_all_progress();
+            }
+            next?;
         }
         Ok(())
     }
 
     /// Interrupt the currently executing cell and clear the execution queue.
     pub async fn interrupt(&mut self) -> Result<()> {
-        let connection_info = self
-            .connection_info
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("No kernel running"))?;
-
-        let mut control =
-            runtimelib::create_client_control_connection(connection_info, &self.session_id).await?;
-
-        let request: JupyterMessage = InterruptRequest {}.into();
-        control.send(request).await?;
-
-        info!("[kernel-manager] Sent interrupt_request");
+        self.send_interrupt().await?;
 
         // Clear the execution queue - interrupt semantically means "stop all pending work"
         let cleared = self.clear_queue();
@@ -1726,6 +3134,72 @@ impl RoomKernel {
         Ok(())
     }
 
+    /// Cancel everything: interrupt the executing cell, clear the queue and
+    /// end any run-all batch. Broadcasts `RunAllCancelled` with the number
+    /// of cells skipped, and returns it.
+    pub async fn interrupt_and_clear(&mut self) -> Result<usize> {
+        self.send_interrupt().await?;
+        let skipped = self.clear_queue().len();
+        info!(
+            "[kernel-manager] Cancelled run, skipped {} queued cells",
+            skipped
+        );
+        let _ = self
+            .broadcast_tx
+            .send(NotebookBroadcast::RunAllCancelled { skipped });
+        Ok(skipped)
+    }
+
+    /// Interrupt the executing cell, leaving the queue alone.
+    ///
+    /// Signal-mode kernels get SIGINT; message-mode kernels, and any kernel
+    /// without a process group to signal, get an `interrupt_request` on the
+    /// control channel.
+    async fn send_interrupt(&mut self) -> Result<()> {
+        let connection_info = self
+            .connection_info
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No kernel running"))?;
+
+        let signalled = self.interrupt_mode == InterruptMode::Signal && self.signal_interrupt()?;
+        if !signalled {
+            let mut control =
+                runtimelib::create_client_control_connection(connection_info, &self.session_id)
+                    .await?;
+
+            let request: JupyterMessage = InterruptRequest {}.into();
+            control.send(request).await?;
+
+            info!("[kernel-manager] Sent interrupt_request");
+        }
+        Ok(())
+    }
+
+    /// Forget the kernel's process group, which is about to be (or has been)
+    /// killed, and stop tracking it.
+    #[cfg(unix)]
+    fn take_process_group(&mut self) -> Option<i32> {
+        let pgid = self.process_group_id.take();
+        if let (Some(pgid), Some((pids, _))) = (pgid, &self.kernel_pids) {
+            pids.untrack(pgid as u32);
+        }
+        pgid
+    }
+
+    /// Send SIGINT to the kernel's process group. Returns false if there is
+    /// no process group to signal.
+    fn signal_interrupt(&self) -> Result<bool> {
+        #[cfg(unix)]
+        if let Some(pgid) = self.process_group_id {
+            use nix::sys::signal::{killpg, Signal};
+            use nix::unistd::Pid;
+            killpg(Pid::from_raw(pgid), Signal::SIGINT)?;
+            info!("[kernel-manager] Sent SIGINT to process group {}", pgid);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
     /// Send a comm message to the kernel (for widget interactions).
     ///
     /// Accepts the full Jupyter message envelope from the frontend to preserve
@@ -1771,24 +3245,7 @@ impl RoomKernel {
         let message_content =
             JupyterMessageContent::from_type_and_content(&msg_type, content_value)?;
 
-        // Parse buffers from Vec<Vec<u8>> (JSON number arrays)
-        let buffers: Vec<Bytes> = raw_message
-            .get("buffers")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|buf| {
-                        buf.as_array().map(|bytes| {
-                            let bytes: Vec<u8> = bytes
-                                .iter()
-                                .filter_map(|b| b.as_u64().map(|n| n as u8))
-                                .collect();
-                            Bytes::from(bytes)
-                        })
-                    })
-                    .collect()
-            })
-            .unwrap_or_default();
+        let buffers = comm_buffers(&raw_message, self.comm_buffer_limits)?;
 
         // Construct the JupyterMessage with the frontend's original header
         let message = JupyterMessage {
@@ -1922,9 +3379,255 @@ impl RoomKernel {
         }
     }
 
-    /// Clear the execution queue.
+    /// Request documentation for the symbol at `cursor_pos` from the kernel.
+    ///
+    /// Returns `(found, text/plain)`. Times out after 5 seconds.
+    pub async fn inspect(
+        &mut self,
+        code: String,
+        cursor_pos: usize,
+        detail_level: usize,
+    ) -> Result<(bool, Option<String>)> {
+        let shell = self
+            .shell_writer
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("No kernel running"))?;
+
+        let request = InspectRequest {
+            code,
+            cursor_pos,
+            detail_level: Some(detail_level),
+        };
+        let message: JupyterMessage = request.into();
+        let msg_id = message.header.msg_id.clone();
+
+        let (tx, rx) = oneshot::channel();
+        self.pending_inspections
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Lock poisoned"))?
+            .insert(msg_id.clone(), tx);
+
+        if let Err(e) = shell.send(message).await {
+            if let Ok(mut pending) = self.pending_inspections.lock() {
+                pending.remove(&msg_id);
+            }
+            return Err(e.into());
+        }
+        debug!("[kernel-manager] Sent inspect_request: msg_id={}", msg_id);
+
+        match tokio::time::timeout(std::time::Duration::from_secs(5), rx).await {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(_)) => Err(anyhow::anyhow!("Inspect request cancelled")),
+            Err(_) => {
+                if let Ok(mut pending) = self.pending_inspections.lock() {
+                    pending.remove(&msg_id);
+                }
+                Err(anyhow::anyhow!("Inspect request timed out"))
+            }
+        }
+    }
+
+    /// Send the DAP request `command` to the kernel in a `debug_request` on
+    /// the control channel.
+    ///
+    /// Fails if the kernel didn't advertise `debugger: true` in its
+    /// kernel_info reply. Only sends the request; await
+    /// [`DebugExchange::response`] for the reply, which doesn't need the
+    /// kernel borrowed.
+    pub async fn debug_command(
+        &mut self,
+        command: &str,
+        arguments: serde_json::Value,
+    ) -> Result<DebugExchange> {
+        let connection_info = self
+            .connection_info
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No kernel running"))?;
+        if !self.debugger {
+            anyhow::bail!(
+                "The {} kernel does not support debugging (its kernel_info reports debugger: false)",
+                self.kernel_type
+            );
+        }
+
+        let mut control =
+            runtimelib::create_client_control_connection(connection_info, &self.session_id).await?;
+        let seq = self.debug_seq;
+        self.debug_seq += 1;
+        let content = serde_json::json!({
+            "type": "request",
+            "seq": seq,
+            "command": command,
+            "arguments": arguments,
+        });
+        let message: JupyterMessage = DebugRequest { content }.into();
+        let msg_id = message.header.msg_id.clone();
+        control.send(message).await?;
+        debug!("[kernel-manager] Sent debug_request: msg_id={}", msg_id);
+
+        Ok(DebugExchange {
+            control,
+            msg_id,
+            command: command.to_string(),
+        })
+    }
+
+    /// Start a debug session (DAP `initialize`). Returns the debugger's
+    /// capabilities.
+    pub async fn debug_initialize(&mut self) -> Result<DebugExchange> {
+        let arguments = serde_json::json!({
+            "clientID": "runt",
+            "clientName": "runt",
+            "adapterID": self.kernel_type,
+            "pathFormat": "path",
+            "linesStartAt1": true,
+            "columnsStartAt1": true,
+            "supportsVariableType": true,
+            "supportsVariablePaging": true,
+            "supportsRunInTerminalRequest": false,
+            "locale": "en",
+        });
+        self.debug_command("initialize", arguments).await
+    }
+
+    /// Replace the breakpoints in the source file at `path` with breakpoints
+    /// on `lines` (1-based). Returns the breakpoints the debugger verified.
+    pub async fn debug_set_breakpoints(
+        &mut self,
+        path: &str,
+        lines: &[u32],
+    ) -> Result<DebugExchange> {
+        let breakpoints: Vec<serde_json::Value> = lines
+            .iter()
+            .map(|line| serde_json::json!({ "line": line }))
+            .collect();
+        let arguments = serde_json::json!({
+            "source": { "path": path },
+            "breakpoints": breakpoints,
+            "sourceModified": false,
+        });
+        self.debug_command("setBreakpoints", arguments).await
+    }
+
+    /// Resume `thread_id` after it stopped at a breakpoint.
+    pub async fn debug_continue(&mut self, thread_id: i64) -> Result<DebugExchange> {
+        self.debug_command("continue", serde_json::json!({ "threadId": thread_id }))
+            .await
+    }
+
+    /// The call stack of stopped `thread_id`, innermost frame first.
+    pub async fn debug_stack_trace(&mut self, thread_id: i64) -> Result<DebugExchange> {
+        self.debug_command("stackTrace", serde_json::json!({ "threadId": thread_id }))
+            .await
+    }
+
+    /// Run code in the kernel without a cell, outputs, or history entry.
+    ///
+    /// Only sends the request; await [`SilentExecution::wait`] for the
+    /// execute_reply, which doesn't need the kernel borrowed.
+    async fn send_silent(&mut self, code: String) -> Result<SilentExecution> {
+        let shell = self
+            .shell_writer
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("No kernel running"))?;
+
+        let request = ExecuteRequest {
+            silent: true,
+            store_history: false,
+            ..ExecuteRequest::new(code)
+        };
+        let message: JupyterMessage = request.into();
+        let msg_id = message.header.msg_id.clone();
+
+        let (tx, rx) = oneshot::channel();
+        self.pending_silent
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Lock poisoned"))?
+            .insert(msg_id.clone(), tx);
+
+        if let Err(e) = shell.send(message).await {
+            if let Ok(mut pending) = self.pending_silent.lock() {
+                pending.remove(&msg_id);
+            }
+            return Err(e.into());
+        }
+        debug!(
+            "[kernel-manager] Sent silent execute_request: msg_id={}",
+            msg_id
+        );
+
+        Ok(SilentExecution {
+            msg_id,
+            rx,
+            pending: self.pending_silent.clone(),
+        })
+    }
+
+    /// Save the user namespace to `path` with dill (Python kernels only).
+    ///
+    /// Variables that can't be pickled are skipped and listed in the report.
+    /// Only sends the code; await [`KernelStateRun::finish`] for the report.
+    pub async fn snapshot_state(&mut self, path: &Path) -> Result<KernelStateRun> {
+        self.run_kernel_state_code(path, kernel_snapshot::snapshot_code)
+            .await
+    }
+
+    /// Load a snapshot written by [`Self::snapshot_state`] into the user
+    /// namespace (Python kernels only).
+    pub async fn restore_state(&mut self, path: &Path) -> Result<KernelStateRun> {
+        if !path.exists() {
+            anyhow::bail!("No kernel state snapshot for this notebook");
+        }
+        self.run_kernel_state_code(path, kernel_snapshot::restore_code)
+            .await
+    }
+
+    async fn run_kernel_state_code(
+        &mut self,
+        path: &Path,
+        code: fn(&Path, &Path) -> String,
+    ) -> Result<KernelStateRun> {
+        if self.kernel_type != "python" {
+            anyhow::bail!(
+                "Kernel state snapshots are only supported for Python kernels, not {}",
+                self.kernel_type
+            );
+        }
+        let report_path = kernel_snapshot::report_path(path);
+        // A stale report would be mistaken for this run's if the code fails
+        // before writing one
+        let _ = std::fs::remove_file(&report_path);
+        let execution = self.send_silent(code(path, &report_path)).await?;
+        Ok(KernelStateRun {
+            execution,
+            report_path,
+        })
+    }
+
+    /// Stop-on-error: clear the queue after `cell_id` raised, unless the cell
+    /// was queued as `raises-exception`. Returns the cleared cell ids.
+    pub fn handle_cell_error(&mut self, cell_id: &str) -> Vec<String> {
+        if self.raises_exception.contains(cell_id) {
+            info!(
+                "[kernel-manager] Cell {} raised as expected, continuing",
+                cell_id
+            );
+            return vec![];
+        }
+        self.clear_queue()
+    }
+
+    /// Clear the execution queue, ending any run-all batch.
     pub fn clear_queue(&mut self) -> Vec<String> {
-        let cleared: Vec<String> = self.queue.drain(..).map(|c| c.cell_id).collect();
+        self.run_all = None;
+
+        // Pipelined requests are already at the kernel; with stop_on_error
+        // (the default) the kernel aborts them after an error or interrupt.
+        let cleared: Vec<String> = self
+            .in_flight
+            .drain(..)
+            .chain(self.queue.drain(..).map(|c| c.cell_id))
+            .collect();
 
         // Broadcast queue state
         let _ = self.broadcast_tx.send(NotebookBroadcast::QueueChanged {
@@ -1935,6 +3638,73 @@ impl RoomKernel {
         cleared
     }
 
+    /// Handle an unexpected exit of the kernel process.
+    ///
+    /// Tears down the connections so `is_running()` reports false, releases
+    /// the kernel's env_id claim, cancels the executing and queued cells, and
+    /// broadcasts a `crashed` lifecycle event
+    /// so the frontend can offer a restart. Returns the cancelled cell ids.
+    pub fn handle_process_exit(
+        &mut self,
+        exit_code: Option<i32>,
+        stderr_tail: String,
+    ) -> Vec<String> {
+        warn!(
+            "[kernel-manager] Kernel died unexpectedly (exit code {:?})",
+            exit_code
+        );
+
+        self.process_monitor = None;
+        if let Some(task) = self.iopub_task.take() {
+            task.abort();
+        }
+        if let Some(task) = self.shell_reader_task.take() {
+            task.abort();
+        }
+        self.shell_writer = None;
+        #[cfg(unix)]
+        self.take_process_group();
+        if let Some(ref path) = self.connection_file.take() {
+            let _ = std::fs::remove_file(path);
+        }
+        self.connection_info = None;
+        self.cmd_tx = None;
+        self.cell_id_map.lock().unwrap().clear();
+        self.status = KernelStatus::Dead;
+        self.run_all = None;
+        if let Some((claims, notebook_id)) = &self.env_claims {
+            claims.release(notebook_id);
+        }
+
+        let cancelled: Vec<String> = self
+            .executing
+            .take()
+            .into_iter()
+            .chain(self.in_flight.drain(..))
+            .chain(self.queue.drain(..).map(|c| c.cell_id))
+            .collect();
+
+        let _ = self.broadcast_tx.send(NotebookBroadcast::QueueChanged {
+            executing: None,
+            queued: vec![],
+        });
+        let _ = self.broadcast_tx.send(NotebookBroadcast::KernelStatus {
+            status: KernelStatus::Dead.to_string(),
+            cell_id: None,
+        });
+        let _ = self.broadcast_tx.send(NotebookBroadcast::KernelLifecycle {
+            state: "crashed".to_string(),
+            kernel_type: Some(self.kernel_type.clone()),
+            env_source: Some(self.env_source.clone()),
+            exit_code,
+            stderr_tail: Some(stderr_tail).filter(|s| !s.is_empty()),
+            cancelled_cells: cancelled.clone(),
+            reason: Some("kernel died".to_string()),
+        });
+
+        cancelled
+    }
+
     /// Shutdown the kernel.
     pub async fn shutdown(&mut self) -> Result<()> {
         info!("[kernel-manager] Shutting down kernel");
@@ -1947,7 +3717,7 @@ impl RoomKernel {
             cell_id: None,
         });
 
-        // Abort tasks
+        // Abort tasks, closing their sockets
         if let Some(task) = self.iopub_task.take() {
             task.abort();
         }
@@ -1955,15 +3725,30 @@ impl RoomKernel {
             task.abort();
         }
 
-        // Try graceful shutdown via shell
+        // Stop watching for crashes first so the exit isn't reported as one.
+        // The monitor kills the kernel if it hasn't exited after the grace
+        // period.
+        let mut monitor = self.process_monitor.take();
+        if let Some(monitor) = monitor.as_mut() {
+            monitor.expect_exit(SHUTDOWN_GRACE);
+        }
+
+        // Try graceful shutdown via shell. A hung kernel may never read it,
+        // so the send is bounded too.
         if let Some(mut shell) = self.shell_writer.take() {
             let request: JupyterMessage = ShutdownRequest { restart: false }.into();
-            let _ = shell.send(request).await;
+            let _ = tokio::time::timeout(SHUTDOWN_GRACE, shell.send(request)).await;
         }
 
-        // Kill process group on Unix
+        if let Some(monitor) = monitor {
+            if monitor.join().await {
+                warn!("[kernel-manager] Kernel ignored shutdown_request and was killed");
+            }
+        }
+
+        // Kill the rest of the process group on Unix
         #[cfg(unix)]
-        if let Some(pgid) = self.process_group_id.take() {
+        if let Some(pgid) = self.take_process_group() {
             use nix::sys::signal::{killpg, Signal};
             use nix::unistd::Pid;
             if let Err(e) = killpg(Pid::from_raw(pgid), Signal::SIGKILL) {
@@ -1976,9 +3761,6 @@ impl RoomKernel {
             }
         }
 
-        // Clean up process
-        self.process = None;
-
         // Clean up connection file
         if let Some(ref path) = self.connection_file {
             let _ = std::fs::remove_file(path);
@@ -1989,18 +3771,125 @@ impl RoomKernel {
         self.connection_file = None;
         self.cell_id_map.lock().unwrap().clear();
         self.queue.clear();
+        self.in_flight.clear();
         self.executing = None;
+        self.run_all = None;
         self.cmd_tx = None;
+        if let Some((claims, notebook_id)) = &self.env_claims {
+            claims.release(notebook_id);
+        }
 
         info!("[kernel-manager] Kernel shutdown complete");
         Ok(())
     }
 }
 
-impl Drop for RoomKernel {
-    fn drop(&mut self) {
-        // Abort any running tasks
-        if let Some(task) = self.iopub_task.take() {
+#[cfg(any(test, feature = "mock-kernel"))]
+impl RoomKernel {
+    /// Attach to a kernel that is already listening at `connection_info`,
+    /// such as a [`MockKernel`](crate::mock_kernel::MockKernel), instead of
+    /// launching a process.
+    ///
+    /// Performs the same `kernel_info` handshake and starts the same iopub and
+    /// shell readers as [`RoomKernel::launch`], so queueing, routing and
+    /// lifecycle behave as they do for a launched kernel. With no process to
+    /// monitor, a kernel that goes away only shows up as socket errors.
+    pub async fn attach(
+        &mut self,
+        connection_info: ConnectionInfo,
+        kernel_type: &str,
+        env_source: &str,
+    ) -> Result<()> {
+        if self.is_running() {
+            self.shutdown().await.ok();
+        }
+
+        self.kernel_type = kernel_type.to_string();
+        self.env_source = env_source.to_string();
+        self.status = KernelStatus::Starting;
+        self.responsive = false;
+        self.session_id = Uuid::new_v4().to_string();
+        // There is no process to signal
+        self.interrupt_mode = InterruptMode::Message;
+
+        let timeout = self.startup_policy.timeout;
+        let (iopub, shell, debugger) =
+            tokio::time::timeout(timeout, connect_kernel(&connection_info, &self.session_id))
+                .await
+                .map_err(|_| anyhow::anyhow!("kernel did not reply within {:?}", timeout))??;
+
+        self.debugger = debugger;
+        self.responsive = true;
+        self.spawn_io_tasks(iopub, shell);
+        self.connection_info = Some(connection_info);
+        self.announce_launched();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl RoomKernel {
+    /// Connect the shell to an in-process socket so tests can drive
+    /// running-kernel paths without spawning a kernel. The returned
+    /// kernel-side connection receives whatever this kernel sends.
+    pub(crate) async fn attach_test_shell(
+        &mut self,
+        kernel_type: &str,
+        env_source: &str,
+    ) -> runtimelib::KernelShellConnection {
+        let ip = std::net::IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let ports = runtimelib::peek_ports(ip, 5).await.unwrap();
+        let connection_info = ConnectionInfo {
+            transport: jupyter_protocol::connection_info::Transport::TCP,
+            ip: ip.to_string(),
+            stdin_port: ports[0],
+            control_port: ports[1],
+            hb_port: ports[2],
+            shell_port: ports[3],
+            iopub_port: ports[4],
+            signature_scheme: "hmac-sha256".to_string(),
+            key: Uuid::new_v4().to_string(),
+            kernel_name: None,
+        };
+        let kernel_shell =
+            runtimelib::create_kernel_shell_connection(&connection_info, &self.session_id)
+                .await
+                .unwrap();
+        let identity = runtimelib::peer_identity_for_session(&self.session_id).unwrap();
+        let shell = runtimelib::create_client_shell_connection_with_identity(
+            &connection_info,
+            &self.session_id,
+            identity,
+        )
+        .await
+        .unwrap();
+        let (shell_writer, _shell_reader) = shell.split();
+
+        self.kernel_type = kernel_type.to_string();
+        self.env_source = env_source.to_string();
+        self.shell_writer = Some(shell_writer);
+        self.status = KernelStatus::Idle;
+        self.responsive = true;
+        kernel_shell
+    }
+
+    /// Record the environment a test kernel was "launched" with.
+    pub(crate) fn set_test_launched_config(&mut self, launched_config: LaunchedEnvConfig) {
+        self.launched_config = launched_config;
+    }
+}
+
+impl Drop for RoomKernel {
+    fn drop(&mut self) {
+        if let Some((claims, notebook_id)) = &self.env_claims {
+            claims.release(notebook_id);
+        }
+
+        // Abort any running tasks
+        if let Some(monitor) = self.process_monitor.take() {
+            monitor.abort();
+        }
+        if let Some(task) = self.iopub_task.take() {
             task.abort();
         }
         if let Some(task) = self.shell_reader_task.take() {
@@ -2009,7 +3898,7 @@ impl Drop for RoomKernel {
 
         // Kill process group on Unix
         #[cfg(unix)]
-        if let Some(pgid) = self.process_group_id.take() {
+        if let Some(pgid) = self.take_process_group() {
             use nix::sys::signal::{killpg, Signal};
             use nix::unistd::Pid;
             let _ = killpg(Pid::from_raw(pgid), Signal::SIGKILL);
@@ -2027,6 +3916,8 @@ impl Drop for RoomKernel {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock_kernel::{MockExecution, MockKernel, MockScript};
+    use jupyter_protocol::{Status, StreamContent};
 
     #[test]
     fn test_kernel_status_display() {
@@ -2035,6 +3926,7 @@ mod tests {
         assert_eq!(KernelStatus::Busy.to_string(), "busy");
         assert_eq!(KernelStatus::Error.to_string(), "error");
         assert_eq!(KernelStatus::ShuttingDown.to_string(), "shutdown");
+        assert_eq!(KernelStatus::Dead.to_string(), "dead");
     }
 
     #[test]
@@ -2059,4 +3951,1589 @@ mod tests {
         assert!(kernel.queued_cells().is_empty());
         assert_eq!(kernel.status(), KernelStatus::Starting);
     }
+
+    fn test_kernel(tmp: &tempfile::TempDir) -> RoomKernel {
+        let (tx, _rx) = broadcast::channel(16);
+        test_kernel_with_broadcast(tmp, tx)
+    }
+
+    fn test_kernel_with_broadcast(
+        tmp: &tempfile::TempDir,
+        tx: broadcast::Sender<NotebookBroadcast>,
+    ) -> RoomKernel {
+        let (changed_tx, _changed_rx) = broadcast::channel(16);
+        let doc = Arc::new(RwLock::new(NotebookDoc::new("test-notebook")));
+        let persist_path = tmp.path().join("test.automerge");
+        let blob_store = Arc::new(BlobStore::new(tmp.path().join("blobs")));
+        let comm_state = Arc::new(CommState::new());
+        RoomKernel::new(tx, doc, persist_path, changed_tx, blob_store, comm_state)
+    }
+
+    fn queued(cell_id: &str, code: &str) -> QueuedCell {
+        QueuedCell {
+            cell_id: cell_id.to_string(),
+            code: code.to_string(),
+            stop_on_error: true,
+        }
+    }
+
+    #[test]
+    fn test_batch_msg_id_correlation() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let kernel = test_kernel(&tmp);
+
+        let cells = vec![queued("a", "1"), queued("b", "2"), queued("c", "3")];
+        let requests = kernel.register_execute_requests(&cells);
+        assert_eq!(requests.len(), 3);
+
+        let map = kernel.cell_id_map.lock().unwrap().clone();
+        assert_eq!(map.len(), 3);
+        for (cell_id, message) in &requests {
+            assert_eq!(map.get(&message.header.msg_id), Some(cell_id));
+        }
+        let order: Vec<_> = requests.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(order, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_batch_reexecution_replaces_mapping() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let kernel = test_kernel(&tmp);
+
+        let first = kernel.register_execute_requests(&[queued("a", "1"), queued("b", "2")]);
+        let old_b = first[1].1.header.msg_id.clone();
+        let second = kernel.register_execute_requests(&[queued("b", "2")]);
+        let new_b = second[0].1.header.msg_id.clone();
+
+        let map = kernel.cell_id_map.lock().unwrap();
+        assert_eq!(map.len(), 2);
+        assert!(!map.contains_key(&old_b));
+        assert_eq!(map.get(&new_b).map(String::as_str), Some("b"));
+    }
+
+    #[test]
+    fn test_interleaved_outputs_route_by_parent() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let kernel = test_kernel(&tmp);
+
+        let requests = kernel.register_execute_requests(&[queued("a", "1"), queued("b", "2")]);
+        let (ref cell_a, ref req_a) = requests[0];
+        let (ref cell_b, ref req_b) = requests[1];
+
+        // Outputs from both executions arrive interleaved on iopub
+        let outputs = [
+            (
+                JupyterMessage::new(StreamContent::stdout("a1"), Some(req_a)),
+                cell_a,
+            ),
+            (
+                JupyterMessage::new(StreamContent::stdout("b1"), Some(req_b)),
+                cell_b,
+            ),
+            (JupyterMessage::new(Status::idle(), Some(req_b)), cell_b),
+            (
+                JupyterMessage::new(StreamContent::stderr("a2"), Some(req_a)),
+                cell_a,
+            ),
+            (JupyterMessage::new(Status::idle(), Some(req_a)), cell_a),
+        ];
+        for (message, expected) in &outputs {
+            assert_eq!(
+                cell_for_message(&kernel.cell_id_map, message).as_ref(),
+                Some(*expected)
+            );
+        }
+    }
+
+    fn parse_reply_payloads(payload: serde_json::Value) -> Vec<KernelPayload> {
+        let reply: jupyter_protocol::ExecuteReply = serde_json::from_value(serde_json::json!({
+            "status": "ok",
+            "execution_count": 3,
+            "payload": [payload],
+            "user_expressions": {}
+        }))
+        .unwrap();
+        kernel_payloads(&reply.payload)
+    }
+
+    #[test]
+    fn test_execute_reply_page_payload() {
+        let payloads = parse_reply_payloads(serde_json::json!({
+            "source": "page",
+            "data": {"text/plain": "Docstring: Return the sum"},
+            "start": 0
+        }));
+        assert_eq!(
+            payloads,
+            vec![KernelPayload::Page {
+                data: serde_json::json!({"text/plain": "Docstring: Return the sum"}),
+                start: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_execute_reply_set_next_input_payload() {
+        let payloads = parse_reply_payloads(serde_json::json!({
+            "source": "set_next_input",
+            "text": "print('loaded')",
+            "replace": true
+        }));
+        assert_eq!(
+            payloads,
+            vec![KernelPayload::SetNextInput {
+                text: "print('loaded')".to_string(),
+                replace: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_execute_reply_ask_exit_payload() {
+        let payloads = parse_reply_payloads(serde_json::json!({
+            "source": "ask_exit",
+            "keepkernel": false
+        }));
+        assert_eq!(payloads, vec![KernelPayload::AskExit { keepkernel: false }]);
+    }
+
+    #[test]
+    fn test_execute_reply_edit_magic_payload_is_dropped() {
+        let payloads = parse_reply_payloads(serde_json::json!({
+            "source": "edit_magic",
+            "filename": "script.py",
+            "line_number": 1
+        }));
+        assert!(payloads.is_empty());
+    }
+
+    #[test]
+    fn test_apply_set_next_input() {
+        let mut doc = NotebookDoc::new("test-notebook");
+        doc.add_cell(0, "a", "code").unwrap();
+        doc.add_cell(1, "b", "code").unwrap();
+        doc.update_source("a", "%load script.py").unwrap();
+
+        assert!(apply_set_next_input(&mut doc, "a", "x = 1", false).unwrap());
+        let cells = doc.get_cells();
+        assert_eq!(cells.len(), 3);
+        assert_eq!(cells[0].source, "%load script.py");
+        assert_eq!(cells[1].source, "x = 1");
+        assert_eq!(cells[1].cell_type, "code");
+        assert_eq!(cells[2].id, "b");
+
+        assert!(apply_set_next_input(&mut doc, "a", "y = 2", true).unwrap());
+        assert_eq!(doc.get_cell("a").unwrap().source, "y = 2");
+        assert_eq!(doc.cell_count(), 3);
+
+        assert!(!apply_set_next_input(&mut doc, "gone", "z = 3", false).unwrap());
+        assert_eq!(doc.cell_count(), 3);
+    }
+
+    #[test]
+    fn test_stale_and_unknown_parents_are_dropped() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let kernel = test_kernel(&tmp);
+
+        let first = kernel.register_execute_requests(&[queued("a", "1")]);
+        let stale = JupyterMessage::new(StreamContent::stdout("old"), Some(&first[0].1));
+        let second = kernel.register_execute_requests(&[queued("a", "1")]);
+        let fresh = JupyterMessage::new(StreamContent::stdout("new"), Some(&second[0].1));
+
+        // A late output from the superseded run must not land in the new one
+        assert_eq!(cell_for_message(&kernel.cell_id_map, &stale), None);
+        assert_eq!(
+            cell_for_message(&kernel.cell_id_map, &fresh).as_deref(),
+            Some("a")
+        );
+
+        let foreign: JupyterMessage = ExecuteRequest::new("x".into()).into();
+        let unknown = JupyterMessage::new(StreamContent::stdout("?"), Some(&foreign));
+        assert_eq!(cell_for_message(&kernel.cell_id_map, &unknown), None);
+
+        let orphan = JupyterMessage::new(StreamContent::stdout("?"), None);
+        assert_eq!(cell_for_message(&kernel.cell_id_map, &orphan), None);
+    }
+
+    #[tokio::test]
+    async fn test_completed_execution_records_timing() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let kernel = test_kernel(&tmp);
+        {
+            let mut doc = kernel.doc.write().await;
+            doc.add_cell(0, "a", "code").unwrap();
+            doc.add_cell(1, "b", "code").unwrap();
+        }
+
+        let requests = kernel.register_execute_requests(&[queued("a", "1"), queued("b", "2")]);
+        let req_a = &requests[0].1.header.msg_id;
+        let req_b = &requests[1].1.header.msg_id;
+
+        let mut timer = ExecutionTimer::default();
+        let start = Utc::now();
+        timer.start(req_a, "a", "1", start);
+        let finished = timer
+            .finish(req_a, start + chrono::Duration::milliseconds(250))
+            .unwrap();
+        let (cell, timing) = (finished.cell_id, finished.timing);
+        assert_eq!(cell, "a");
+        assert_eq!(timing.duration_ms, 250);
+
+        // Idle for a request that never started (e.g. kernel_info) is ignored
+        assert!(timer.finish(req_b, start).is_none());
+        assert!(timer.finish(req_a, start).is_none());
+
+        let (changed_tx, _changed_rx) = broadcast::channel(16);
+        let persist_path = tmp.path().join("timing.automerge");
+        record_execution_timing(&kernel.doc, &persist_path, &changed_tx, &cell, &timing).await;
+
+        let doc = kernel.doc.read().await;
+        assert_eq!(doc.get_execution_timings(), vec![("a".to_string(), timing)]);
+        assert!(doc.get_cell("b").unwrap().execution.is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_killed_process_reports_crash() {
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (tx, mut rx) = broadcast::channel(16);
+        let (changed_tx, _changed_rx) = broadcast::channel(16);
+        let doc = Arc::new(RwLock::new(NotebookDoc::new("test-notebook")));
+        let blob_store = Arc::new(BlobStore::new(tmp.path().join("blobs")));
+        let mut kernel = RoomKernel::new(
+            tx,
+            doc,
+            tmp.path().join("test.automerge"),
+            changed_tx,
+            blob_store,
+            Arc::new(CommState::new()),
+        );
+        kernel.executing = Some("a".to_string());
+        kernel.queue.push_back(queued("b", "2"));
+
+        let mut child = tokio::process::Command::new("sh")
+            .args(["-c", "echo 'kernel stderr' >&2; exec sleep 30"])
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        let pid = child.id().unwrap() as i32;
+        let (cmd_tx, mut cmd_rx) = mpsc::channel(4);
+        let stderr = StderrTail::capture(&mut child);
+        kernel.process_monitor = Some(spawn_process_monitor(child, stderr, cmd_tx));
+
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        kill(Pid::from_raw(pid), Signal::SIGKILL).unwrap();
+
+        let cmd = tokio::time::timeout(std::time::Duration::from_secs(5), cmd_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let QueueCommand::KernelDied {
+            exit_code,
+            stderr_tail,
+        } = cmd
+        else {
+            panic!("expected KernelDied, got {:?}", cmd);
+        };
+        assert_eq!(exit_code, None); // killed by signal
+        assert_eq!(stderr_tail, "kernel stderr");
+
+        let cancelled = kernel.handle_process_exit(exit_code, stderr_tail);
+        assert_eq!(cancelled, vec!["a", "b"]);
+        assert!(!kernel.is_running());
+        assert_eq!(kernel.status(), KernelStatus::Dead);
+        assert!(kernel.executing_cell().is_none());
+        assert!(kernel.queued_cells().is_empty());
+
+        let mut crashed = None;
+        while let Ok(event) = rx.try_recv() {
+            if let NotebookBroadcast::KernelLifecycle {
+                state,
+                stderr_tail,
+                cancelled_cells,
+                ..
+            } = event
+            {
+                crashed = Some((state, stderr_tail, cancelled_cells));
+            }
+        }
+        let (state, stderr_tail, cancelled_cells) = crashed.expect("no lifecycle event");
+        assert_eq!(state, "crashed");
+        assert_eq!(stderr_tail.as_deref(), Some("kernel stderr"));
+        assert_eq!(cancelled_cells, vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_process_exit_releases_env_claim() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let mut kernel = test_kernel(&tmp);
+        let claims = EnvIdClaims::new();
+        kernel.set_env_claims(claims.clone(), "nb-1");
+        claims.claim("env-1", "nb-1", kernel.broadcast_tx.clone());
+
+        kernel.handle_process_exit(Some(1), String::new());
+
+        assert!(claims.claimants("env-1").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_running_env_source_follows_launch() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let mut kernel = test_kernel(&tmp);
+        assert_eq!(kernel.running_env_source(), None);
+
+        let _kernel_shell = kernel.attach_test_shell("python", "conda:prewarmed").await;
+        assert_eq!(kernel.running_env_source(), Some("conda:prewarmed"));
+
+        kernel.shutdown().await.unwrap();
+        assert_eq!(kernel.running_env_source(), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shutdown_kills_kernel_ignoring_shutdown_request() {
+        use nix::sys::signal::kill;
+        use nix::unistd::Pid;
+
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (tx, _rx) = broadcast::channel(16);
+        let mut kernel = test_kernel_with_broadcast(&tmp, tx);
+        // The kernel side of the shell reads the request but never answers
+        let mut kernel_shell = kernel.attach_test_shell("python", "uv:prewarmed").await;
+
+        let mut child = tokio::process::Command::new("sleep")
+            .arg("30")
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        let pid = Pid::from_raw(child.id().unwrap() as i32);
+        let (cmd_tx, mut cmd_rx) = mpsc::channel(4);
+        let stderr = StderrTail::capture(&mut child);
+        kernel.process_monitor = Some(spawn_process_monitor(child, stderr, cmd_tx));
+
+        let started = std::time::Instant::now();
+        kernel.shutdown().await.unwrap();
+        let elapsed = started.elapsed();
+
+        assert!(
+            elapsed >= SHUTDOWN_GRACE,
+            "killed before the grace period ({:?})",
+            elapsed
+        );
+        assert!(
+            elapsed < SHUTDOWN_GRACE + std::time::Duration::from_secs(1),
+            "shutdown took {:?}",
+            elapsed
+        );
+        let request = kernel_shell.read().await.unwrap();
+        assert_eq!(request.header.msg_type, "shutdown_request");
+        // Killed and reaped, and not reported as a crash
+        assert_eq!(kill(pid, None), Err(nix::errno::Errno::ESRCH));
+        assert!(cmd_rx.try_recv().is_err());
+        assert!(!kernel.is_running());
+    }
+
+    /// `JUPYTER_PATH` is process-wide, so tests that point it at a temporary
+    /// kernelspec take turns.
+    static JUPYTER_PATH_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    /// A test's turn with `JUPYTER_PATH`; puts back the value it had before
+    /// when dropped.
+    struct JupyterPathGuard {
+        previous: Option<std::ffi::OsString>,
+        _turn: tokio::sync::MutexGuard<'static, ()>,
+    }
+
+    impl JupyterPathGuard {
+        async fn lock() -> Self {
+            let turn = JUPYTER_PATH_LOCK.lock().await;
+            Self {
+                previous: std::env::var_os("JUPYTER_PATH"),
+                _turn: turn,
+            }
+        }
+    }
+
+    impl Drop for JupyterPathGuard {
+        fn drop(&mut self) {
+            match &self.previous {
+                Some(path) => std::env::set_var("JUPYTER_PATH", path),
+                None => std::env::remove_var("JUPYTER_PATH"),
+            }
+        }
+    }
+
+    /// Install a kernelspec named `name` whose "kernel" is a shell script
+    /// that never speaks the Jupyter protocol. Callers hold a
+    /// [`JupyterPathGuard`] so `JUPYTER_PATH` is restored afterwards.
+    fn install_fake_kernelspec(tmp: &tempfile::TempDir, name: &str, script: &str) {
+        let spec_dir = tmp.path().join("jupyter/kernels").join(name);
+        std::fs::create_dir_all(&spec_dir).unwrap();
+        let kernelspec = serde_json::json!({
+            "argv": ["sh", "-c", script, "{connection_file}"],
+            "display_name": "Fake",
+            "language": "fake",
+        });
+        std::fs::write(spec_dir.join("kernel.json"), kernelspec.to_string()).unwrap();
+        std::env::set_var("JUPYTER_PATH", tmp.path().join("jupyter"));
+    }
+
+    /// Install a kernelspec whose process copies its connection file to the
+    /// returned path and stays alive, so the test can play the kernel side
+    /// of the protocol.
+    fn install_protocol_kernelspec(tmp: &tempfile::TempDir, name: &str) -> PathBuf {
+        let conn_copy = tmp.path().join(format!("{name}-connection.json"));
+        let script = format!("cp \"$0\" '{}' && exec sleep 30", conn_copy.display());
+        install_fake_kernelspec(tmp, name, &script);
+        conn_copy
+    }
+
+    /// Bind the kernel's sockets once the connection file appears at
+    /// `conn_copy` and answer the daemon's startup `kernel_info_request`.
+    async fn fake_kernel_handshake(
+        conn_copy: PathBuf,
+    ) -> (
+        runtimelib::KernelIoPubConnection,
+        runtimelib::KernelShellConnection,
+    ) {
+        let connection_info: ConnectionInfo = loop {
+            if let Ok(contents) = tokio::fs::read_to_string(&conn_copy).await {
+                if let Ok(info) = serde_json::from_str(&contents) {
+                    break info;
+                }
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        };
+        let session_id = Uuid::new_v4().to_string();
+        let iopub = runtimelib::create_kernel_iopub_connection(&connection_info, &session_id)
+            .await
+            .unwrap();
+        let mut shell = runtimelib::create_kernel_shell_connection(&connection_info, &session_id)
+            .await
+            .unwrap();
+        let request = shell.read().await.unwrap();
+        assert_eq!(request.header.msg_type, "kernel_info_request");
+        shell.send(kernel_info_reply(&request)).await.unwrap();
+        (iopub, shell)
+    }
+
+    fn kernel_info_reply(request: &JupyterMessage) -> JupyterMessage {
+        let reply = jupyter_protocol::KernelInfoReply {
+            status: jupyter_protocol::ReplyStatus::Ok,
+            protocol_version: "5.3".to_string(),
+            implementation: "fake".to_string(),
+            implementation_version: "0.1".to_string(),
+            language_info: jupyter_protocol::LanguageInfo {
+                name: "fake".to_string(),
+                version: "0.1".to_string(),
+                mimetype: None,
+                file_extension: None,
+                pygments_lexer: None,
+                codemirror_mode: None,
+                nbconvert_exporter: None,
+            },
+            banner: String::new(),
+            help_links: vec![],
+            debugger: false,
+            error: None,
+        };
+        reply.as_child_of(request)
+    }
+
+    /// Launch the `name` kernelspec installed by [`install_protocol_kernelspec`].
+    async fn launch_fake(kernel: &mut RoomKernel, tmp: &tempfile::TempDir, name: &str) {
+        kernel
+            .launch(
+                name,
+                "kernelspec",
+                Some(&tmp.path().join("notebook.ipynb")),
+                None,
+                LaunchedEnvConfig::default(),
+            )
+            .await
+            .unwrap();
+    }
+
+    /// Statuses and the lifecycle "error" event broadcast during a launch.
+    fn launch_events(
+        rx: &mut broadcast::Receiver<NotebookBroadcast>,
+    ) -> (Vec<String>, Option<NotebookBroadcast>) {
+        let mut statuses = vec![];
+        let mut error = None;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                NotebookBroadcast::KernelStatus { status, .. } => statuses.push(status),
+                NotebookBroadcast::KernelLifecycle { ref state, .. } if state == "error" => {
+                    error = Some(event)
+                }
+                _ => {}
+            }
+        }
+        (statuses, error)
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unresponsive_kernel_times_out_and_retries() {
+        let _jupyter_path = JupyterPathGuard::lock().await;
+        let tmp = tempfile::TempDir::new().unwrap();
+        install_fake_kernelspec(
+            &tmp,
+            "never-connects",
+            "echo 'loading fake kernel' >&2; echo 'stuck binding sockets' >&2; exec sleep 30",
+        );
+
+        let (tx, mut rx) = broadcast::channel(16);
+        let mut kernel = test_kernel_with_broadcast(&tmp, tx);
+        kernel.set_startup_policy(StartupPolicy {
+            timeout: std::time::Duration::from_secs(1),
+            retry: true,
+        });
+
+        let err = kernel
+            .launch(
+                "never-connects",
+                "kernelspec",
+                Some(&tmp.path().join("notebook.ipynb")),
+                None,
+                LaunchedEnvConfig::default(),
+            )
+            .await
+            .unwrap_err();
+        let startup = err.downcast_ref::<KernelStartupError>().unwrap();
+        assert!(
+            matches!(startup, KernelStartupError::TimedOut { timeout, .. } if timeout.as_secs() == 1),
+            "{startup:?}"
+        );
+        assert_eq!(
+            startup.stderr_tail(),
+            "loading fake kernel\nstuck binding sockets"
+        );
+        assert!(!kernel.is_running());
+        assert_eq!(kernel.status(), KernelStatus::Error);
+
+        // One retry, then a single error event with the stderr tail
+        let (statuses, error) = launch_events(&mut rx);
+        assert_eq!(statuses, vec!["starting", "starting"]);
+        let Some(NotebookBroadcast::KernelLifecycle {
+            exit_code,
+            stderr_tail,
+            reason,
+            ..
+        }) = error
+        else {
+            panic!("no lifecycle error event");
+        };
+        assert_eq!(exit_code, None);
+        assert_eq!(
+            stderr_tail.as_deref(),
+            Some("loading fake kernel\nstuck binding sockets")
+        );
+        assert_eq!(reason.as_deref(), Some("Kernel did not respond within 1s"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_kernel_exiting_during_startup_reports_stderr() {
+        let _jupyter_path = JupyterPathGuard::lock().await;
+        let tmp = tempfile::TempDir::new().unwrap();
+        install_fake_kernelspec(
+            &tmp,
+            "exits-early",
+            "echo \"ModuleNotFoundError: No module named 'ipykernel'\" >&2; exit 3",
+        );
+
+        let (tx, mut rx) = broadcast::channel(16);
+        let mut kernel = test_kernel_with_broadcast(&tmp, tx);
+        kernel.set_startup_policy(StartupPolicy {
+            timeout: std::time::Duration::from_secs(30),
+            retry: false,
+        });
+
+        let started = std::time::Instant::now();
+        let err = kernel
+            .launch(
+                "exits-early",
+                "kernelspec",
+                Some(&tmp.path().join("notebook.ipynb")),
+                None,
+                LaunchedEnvConfig::default(),
+            )
+            .await
+            .unwrap_err();
+        // Reported as soon as the process exits, not after the timeout
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+        let startup = err.downcast_ref::<KernelStartupError>().unwrap();
+        assert_eq!(startup.exit_code(), Some(3));
+        assert_eq!(
+            startup.to_string(),
+            "Kernel exited during startup with code 3"
+        );
+
+        let (statuses, error) = launch_events(&mut rx);
+        assert_eq!(statuses, vec!["starting"]);
+        let Some(NotebookBroadcast::KernelLifecycle {
+            exit_code,
+            stderr_tail,
+            ..
+        }) = error
+        else {
+            panic!("no lifecycle error event");
+        };
+        assert_eq!(exit_code, Some(3));
+        assert_eq!(
+            stderr_tail.as_deref(),
+            Some("ModuleNotFoundError: No module named 'ipykernel'")
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_launch_records_launch_spec() {
+        let _jupyter_path = JupyterPathGuard::lock().await;
+        let tmp = tempfile::TempDir::new().unwrap();
+        install_fake_kernelspec(&tmp, "exits-at-once", "exit 1");
+
+        let mut kernel = test_kernel(&tmp);
+        assert!(kernel.last_launch_spec().is_none());
+        kernel.set_startup_policy(StartupPolicy {
+            timeout: std::time::Duration::from_secs(30),
+            retry: false,
+        });
+        let notebook_dir = tmp.path().join("project");
+        std::fs::create_dir_all(&notebook_dir).unwrap();
+
+        kernel
+            .launch(
+                "exits-at-once",
+                "kernelspec",
+                Some(&notebook_dir.join("notebook.ipynb")),
+                None,
+                LaunchedEnvConfig::default(),
+            )
+            .await
+            .unwrap_err();
+
+        // Still available after a failed launch
+        let spec = kernel.last_launch_spec().expect("launch spec recorded");
+        assert_eq!(&spec.argv[..3], &["sh", "-c", "exit 1"]);
+        assert_eq!(spec.argv.len(), 4);
+        assert!(spec.argv[3].ends_with(".json"), "{:?}", spec.argv);
+        assert_eq!(spec.cwd.as_deref(), Some(notebook_dir.as_path()));
+        assert_eq!(
+            spec.env.get("COLUMNS").cloned().flatten().as_deref(),
+            Some(TERMINAL_COLUMNS_STR)
+        );
+    }
+
+    #[test]
+    fn test_launch_spec_redacts_secret_looking_env() {
+        let mut cmd = tokio::process::Command::new("python");
+        cmd.args(["-m", "ipykernel_launcher"]);
+        cmd.env("OPENAI_API_KEY", "sk-123");
+        cmd.env("HF_TOKEN", "hf_abc");
+        cmd.env("VIRTUAL_ENV", "/envs/abc");
+        cmd.env_remove("PYTHONHOME");
+
+        let spec = LaunchSpec::from_command(&cmd);
+        assert_eq!(spec.argv, vec!["python", "-m", "ipykernel_launcher"]);
+        assert_eq!(spec.cwd, None);
+        assert_eq!(spec.env["OPENAI_API_KEY"].as_deref(), Some("<redacted>"));
+        assert_eq!(spec.env["HF_TOKEN"].as_deref(), Some("<redacted>"));
+        assert_eq!(spec.env["VIRTUAL_ENV"].as_deref(), Some("/envs/abc"));
+        assert_eq!(spec.env["PYTHONHOME"], None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_system_python_source_launches_python3_from_path() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        // Runs as `python3 -Xfrozen_modules=off -m ipykernel_launcher -f <file>`
+        let conn_copy = tmp.path().join("system-python-connection.json");
+        let fake = crate::system_python::FakeSystemPython::install(&format!(
+            "cp \"$5\" '{}' && exec sleep 30",
+            conn_copy.display()
+        ))
+        .await;
+        let fake_kernel = tokio::spawn(fake_kernel_handshake(conn_copy));
+
+        let mut kernel = test_kernel(&tmp);
+        kernel
+            .launch(
+                "python",
+                crate::system_python::SYSTEM_PYTHON_SOURCE,
+                Some(&tmp.path().join("notebook.ipynb")),
+                None,
+                LaunchedEnvConfig::default(),
+            )
+            .await
+            .unwrap();
+        let _kernel_side = fake_kernel.await.unwrap();
+        drop(fake);
+
+        assert!(kernel.is_running());
+        assert_eq!(
+            kernel.env_source(),
+            crate::system_python::SYSTEM_PYTHON_SOURCE
+        );
+        kernel.shutdown().await.ok();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_other_kernel_type_launches_from_kernelspec() {
+        let _jupyter_path = JupyterPathGuard::lock().await;
+        let tmp = tempfile::TempDir::new().unwrap();
+
+        let conn_copy = install_protocol_kernelspec(&tmp, "fake-lang");
+        let fake_kernel = tokio::spawn(fake_kernel_handshake(conn_copy));
+
+        let (tx, mut rx) = broadcast::channel(16);
+        let (changed_tx, _changed_rx) = broadcast::channel(16);
+        let doc = Arc::new(RwLock::new(NotebookDoc::new("test-notebook")));
+        let blob_store = Arc::new(BlobStore::new(tmp.path().join("blobs")));
+        let mut kernel = RoomKernel::new(
+            tx,
+            doc,
+            tmp.path().join("test.automerge"),
+            changed_tx,
+            blob_store,
+            Arc::new(CommState::new()),
+        );
+
+        kernel
+            .launch(
+                "fake-lang",
+                "kernelspec",
+                Some(&tmp.path().join("notebook.ipynb")),
+                None,
+                LaunchedEnvConfig::default(),
+            )
+            .await
+            .unwrap();
+        let _kernel_side = fake_kernel.await.unwrap();
+
+        assert!(kernel.is_running());
+        assert_eq!(kernel.kernel_type(), "fake-lang");
+        assert_eq!(kernel.env_source(), "kernelspec");
+        assert_eq!(kernel.running_env_source(), Some("kernelspec"));
+
+        let mut statuses = vec![];
+        let mut launched = None;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                NotebookBroadcast::KernelStatus { status, .. } => statuses.push(status),
+                NotebookBroadcast::KernelLifecycle {
+                    state,
+                    kernel_type,
+                    env_source,
+                    ..
+                } => launched = Some((state, kernel_type, env_source)),
+                _ => {}
+            }
+        }
+        assert_eq!(statuses, vec!["starting", "idle"]);
+        let (state, kernel_type, env_source) = launched.expect("no lifecycle event");
+        assert_eq!(state, "launched");
+        assert_eq!(kernel_type.as_deref(), Some("fake-lang"));
+        assert_eq!(env_source.as_deref(), Some("kernelspec"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_snapshot_state_returns_before_the_kernel_replies() {
+        let _jupyter_path = JupyterPathGuard::lock().await;
+        let tmp = tempfile::TempDir::new().unwrap();
+        let conn_copy = install_protocol_kernelspec(&tmp, "snapshots");
+        let fake_kernel = tokio::spawn(fake_kernel_handshake(conn_copy));
+
+        let mut kernel = test_kernel(&tmp);
+        launch_fake(&mut kernel, &tmp, "snapshots").await;
+        let (_iopub, mut shell) = fake_kernel.await.unwrap();
+        kernel.kernel_type = "python".to_string();
+
+        // Sending the code is all that needs the kernel
+        let path = tmp.path().join("state.pkl");
+        let run = kernel.snapshot_state(&path).await.unwrap();
+
+        let request = shell.read().await.unwrap();
+        assert_eq!(request.header.msg_type, "execute_request");
+        std::fs::write(
+            kernel_snapshot::report_path(&path),
+            serde_json::json!({"names": ["df"], "dropped": []}).to_string(),
+        )
+        .unwrap();
+        let reply: jupyter_protocol::ExecuteReply = serde_json::from_value(serde_json::json!({
+            "status": "ok",
+            "execution_count": 0,
+            "payload": [],
+            "user_expressions": {}
+        }))
+        .unwrap();
+        shell
+            .send(JupyterMessage::new(reply, Some(&request)))
+            .await
+            .unwrap();
+
+        let report = run.finish().await.unwrap();
+        assert_eq!(report.names, vec!["df".to_string()]);
+        assert!(report.dropped.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_alive_but_unresponsive_kernel_cancels_run() {
+        let _jupyter_path = JupyterPathGuard::lock().await;
+        let tmp = tempfile::TempDir::new().unwrap();
+        let conn_copy = install_protocol_kernelspec(&tmp, "goes-quiet");
+        // Answers the startup handshake, then never reads its shell again
+        let fake_kernel = tokio::spawn(fake_kernel_handshake(conn_copy));
+
+        let (tx, mut rx) = broadcast::channel(32);
+        let mut kernel = test_kernel_with_broadcast(&tmp, tx);
+        kernel.ping_timeout = std::time::Duration::from_millis(300);
+        launch_fake(&mut kernel, &tmp, "goes-quiet").await;
+        let _kernel_side = fake_kernel.await.unwrap();
+        while rx.try_recv().is_ok() {}
+
+        assert!(kernel.is_running());
+        // The missed ping marks the kernel unresponsive, so the cell below
+        // pings it again rather than being sent into the void
+        assert!(!kernel.ping(std::time::Duration::from_millis(300)).await);
+        assert!(kernel.pending_kernel_info.lock().unwrap().is_empty());
+
+        let err = kernel
+            .queue_cell("a".to_string(), "1".to_string())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("kernel_info_request"), "{err}");
+        assert!(kernel.queued_cells().is_empty());
+        assert!(kernel.executing_cell().is_none());
+        assert_eq!(kernel.status(), KernelStatus::Error);
+
+        let lifecycle = std::iter::from_fn(|| rx.try_recv().ok()).find_map(|event| match event {
+            NotebookBroadcast::KernelLifecycle {
+                state,
+                cancelled_cells,
+                ..
+            } => Some((state, cancelled_cells)),
+            _ => None,
+        });
+        assert_eq!(
+            lifecycle,
+            Some(("unresponsive".to_string(), vec!["a".to_string()]))
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_startup_handshake_spares_first_cell_a_ping() {
+        let _jupyter_path = JupyterPathGuard::lock().await;
+        let tmp = tempfile::TempDir::new().unwrap();
+        let conn_copy = install_protocol_kernelspec(&tmp, "answers");
+        let fake_kernel = tokio::spawn(async move {
+            let (iopub, mut shell) = fake_kernel_handshake(conn_copy).await;
+            // Answer pings; record requests up to the first execute_request
+            let mut received = vec![];
+            loop {
+                let request = shell.read().await.unwrap();
+                received.push(request.header.msg_type.clone());
+                match request.header.msg_type.as_str() {
+                    "kernel_info_request" => {
+                        shell.send(kernel_info_reply(&request)).await.unwrap();
+                    }
+                    "execute_request" => break,
+                    _ => {}
+                }
+            }
+            (iopub, shell, received)
+        });
+
+        let mut kernel = test_kernel(&tmp);
+        launch_fake(&mut kernel, &tmp, "answers").await;
+
+        kernel
+            .queue_cell("a".to_string(), "1".to_string())
+            .await
+            .unwrap();
+        assert_eq!(kernel.executing_cell(), Some(&"a".to_string()));
+
+        let (_iopub, _shell, received) =
+            tokio::time::timeout(std::time::Duration::from_secs(5), fake_kernel)
+                .await
+                .unwrap()
+                .unwrap();
+        // The startup handshake already proved the shell answers, so the
+        // first cell goes out without another kernel_info_request
+        assert_eq!(received, vec!["execute_request"]);
+    }
+
+    /// Install a protocol kernelspec declaring `interrupt_mode` whose process
+    /// touches the returned marker file when it receives SIGINT.
+    fn install_interruptible_kernelspec(
+        tmp: &tempfile::TempDir,
+        name: &str,
+        interrupt_mode: &str,
+    ) -> (PathBuf, PathBuf) {
+        let conn_copy = tmp.path().join(format!("{name}-connection.json"));
+        let marker = tmp.path().join(format!("{name}-sigint"));
+        let script = format!(
+            "trap \"touch '{}'\" INT; cp \"$0\" '{}'; while :; do sleep 0.1; done",
+            marker.display(),
+            conn_copy.display()
+        );
+        install_fake_kernelspec(tmp, name, &script);
+        let spec_path = tmp
+            .path()
+            .join("jupyter/kernels")
+            .join(name)
+            .join("kernel.json");
+        let mut spec: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&spec_path).unwrap()).unwrap();
+        spec["interrupt_mode"] = interrupt_mode.into();
+        std::fs::write(&spec_path, spec.to_string()).unwrap();
+        (conn_copy, marker)
+    }
+
+    #[test]
+    fn test_comm_buffers_checked_against_limits() {
+        let limits = BufferLimits {
+            max_buffers: 2,
+            max_total_bytes: 4,
+        };
+        let message = |buffers: serde_json::Value| serde_json::json!({ "buffers": buffers });
+
+        let buffers =
+            comm_buffers(&message(serde_json::json!([[104, 105], [33]])), limits).unwrap();
+        assert_eq!(buffers, vec![Bytes::from("hi"), Bytes::from("!")]);
+        assert!(comm_buffers(&serde_json::json!({}), limits)
+            .unwrap()
+            .is_empty());
+
+        let err = comm_buffers(&message(serde_json::json!([[1], [2], [3]])), limits).unwrap_err();
+        assert!(err.to_string().contains("more than 2 buffers"), "{err}");
+        let err =
+            comm_buffers(&message(serde_json::json!([[1, 2, 3], [4, 5]])), limits).unwrap_err();
+        assert!(err.to_string().contains("more than 4 bytes"), "{err}");
+    }
+
+    #[test]
+    fn test_interrupt_mode_from_kernelspec() {
+        assert_eq!(
+            InterruptMode::from_kernelspec(Some("message")),
+            InterruptMode::Message
+        );
+        assert_eq!(
+            InterruptMode::from_kernelspec(Some("signal")),
+            InterruptMode::Signal
+        );
+        assert_eq!(InterruptMode::from_kernelspec(None), InterruptMode::Signal);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_message_mode_kernel_gets_control_interrupt() {
+        let _jupyter_path = JupyterPathGuard::lock().await;
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (conn_copy, marker) = install_interruptible_kernelspec(&tmp, "by-message", "message");
+        let fake_kernel = tokio::spawn(fake_kernel_handshake(conn_copy.clone()));
+
+        let mut kernel = test_kernel(&tmp);
+        launch_fake(&mut kernel, &tmp, "by-message").await;
+        let _kernel_side = fake_kernel.await.unwrap();
+        assert_eq!(kernel.interrupt_mode(), InterruptMode::Message);
+
+        let connection_info: ConnectionInfo =
+            serde_json::from_str(&std::fs::read_to_string(&conn_copy).unwrap()).unwrap();
+        let mut control = runtimelib::create_kernel_control_connection(
+            &connection_info,
+            &Uuid::new_v4().to_string(),
+        )
+        .await
+        .unwrap();
+
+        kernel.interrupt().await.unwrap();
+        let request = tokio::time::timeout(std::time::Duration::from_secs(5), control.read())
+            .await
+            .expect("no control message")
+            .unwrap();
+        assert_eq!(request.header.msg_type, "interrupt_request");
+
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        assert!(!marker.exists(), "message-mode kernel was signalled");
+        kernel.shutdown().await.ok();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_signal_mode_kernel_gets_sigint() {
+        let _jupyter_path = JupyterPathGuard::lock().await;
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (conn_copy, marker) = install_interruptible_kernelspec(&tmp, "by-signal", "signal");
+        let fake_kernel = tokio::spawn(fake_kernel_handshake(conn_copy));
+
+        let mut kernel = test_kernel(&tmp);
+        launch_fake(&mut kernel, &tmp, "by-signal").await;
+        let _kernel_side = fake_kernel.await.unwrap();
+        assert_eq!(kernel.interrupt_mode(), InterruptMode::Signal);
+
+        kernel.interrupt().await.unwrap();
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !marker.exists() {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "kernel never got SIGINT"
+            );
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert!(kernel.is_running());
+        kernel.shutdown().await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_mock_kernel_interrupt_arrives_on_control() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (mut kernel, mock, _commands, _rx) = attach_mock(&tmp, MockScript::new()).await;
+
+        kernel.interrupt().await.unwrap();
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+        while mock.received_control().is_empty() {
+            assert!(tokio::time::Instant::now() < deadline, "no control message");
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let control = mock.received_control();
+        assert_eq!(control[0].header.msg_type, "interrupt_request");
+        assert!(mock
+            .received()
+            .iter()
+            .all(|m| m.header.msg_type != "interrupt_request"));
+    }
+
+    #[tokio::test]
+    async fn test_debug_initialize_on_debugger_kernel() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let script = MockScript::new().debugger().on_debug(
+            "initialize",
+            serde_json::json!({ "supportsConfigurationDoneRequest": true }),
+        );
+        let (mut kernel, mock, _commands, _rx) = attach_mock(&tmp, script).await;
+        assert!(kernel.supports_debugger());
+
+        let capabilities = kernel
+            .debug_initialize()
+            .await
+            .unwrap()
+            .response()
+            .await
+            .unwrap();
+        assert_eq!(
+            capabilities,
+            serde_json::json!({ "supportsConfigurationDoneRequest": true })
+        );
+
+        let control = mock.received_control();
+        assert_eq!(control[0].header.msg_type, "debug_request");
+        let JupyterMessageContent::DebugRequest(ref request) = control[0].content else {
+            panic!("expected a debug_request");
+        };
+        assert_eq!(request.content["command"], "initialize");
+        assert_eq!(request.content["seq"], 1);
+
+        // A failed DAP response surfaces its message
+        let err = kernel
+            .debug_stack_trace(1)
+            .await
+            .unwrap()
+            .response()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("stackTrace"), "{err}");
+        assert!(err.to_string().contains("unscripted"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_debug_request_needs_debugger_support() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (mut kernel, mock, _commands, _rx) = attach_mock(&tmp, MockScript::new()).await;
+        assert!(!kernel.supports_debugger());
+
+        let Err(err) = kernel.debug_initialize().await else {
+            panic!("sent a debug_request to a kernel without a debugger");
+        };
+        assert!(err.to_string().contains("debugger: false"), "{err}");
+        assert!(mock.received_control().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_kernelspec_fails_to_launch() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let mut kernel = test_kernel(&tmp);
+
+        let result = kernel
+            .launch(
+                "no-such-kernelspec-installed",
+                "kernelspec",
+                Some(&tmp.path().join("notebook.ipynb")),
+                None,
+                LaunchedEnvConfig::default(),
+            )
+            .await;
+        assert!(result.is_err());
+        assert!(!kernel.is_running());
+    }
+
+    #[tokio::test]
+    async fn test_execute_batch_requires_kernel() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let mut kernel = test_kernel(&tmp);
+
+        let result = kernel.execute_batch(&[queued("a", "1")]).await;
+        assert!(result.is_err());
+        assert!(kernel.queued_cells().is_empty());
+        assert!(kernel.cell_id_map.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_raises_exception_cell_does_not_stop_batch() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let mut kernel = test_kernel(&tmp);
+        let mut kernel_shell = kernel.attach_test_shell("python", "uv:prewarmed").await;
+
+        let expected_error = QueuedCell {
+            stop_on_error: false,
+            ..queued("a", "raise ValueError")
+        };
+        kernel
+            .execute_batch(&[expected_error, queued("b", "2"), queued("c", "3")])
+            .await
+            .unwrap();
+
+        // The kernel is told not to abort its queue after `a` raises
+        let mut stop_on_error = vec![];
+        for _ in 0..3 {
+            match kernel_shell.read().await.unwrap().content {
+                JupyterMessageContent::ExecuteRequest(request) => {
+                    stop_on_error.push(request.stop_on_error)
+                }
+                other => panic!("expected execute_request, got {:?}", other),
+            }
+        }
+        assert_eq!(stop_on_error, vec![false, true, true]);
+
+        assert!(kernel.handle_cell_error("a").is_empty());
+        assert_eq!(kernel.queued_cells(), vec!["b", "c"]);
+
+        // An unexpected error still stops the rest of the batch
+        assert_eq!(kernel.handle_cell_error("b"), vec!["b", "c"]);
+        assert!(kernel.queued_cells().is_empty());
+    }
+
+    /// A kernel attached to a [`MockKernel`] running `script`, with room in
+    /// the broadcast channel for every event a short run produces.
+    async fn attach_mock(
+        tmp: &tempfile::TempDir,
+        script: MockScript,
+    ) -> (
+        RoomKernel,
+        MockKernel,
+        mpsc::Receiver<QueueCommand>,
+        broadcast::Receiver<NotebookBroadcast>,
+    ) {
+        let (tx, rx) = broadcast::channel(256);
+        let mut kernel = test_kernel_with_broadcast(tmp, tx);
+        let mock = MockKernel::start(script).await.unwrap();
+        kernel
+            .attach(mock.connection_info().clone(), "python", "mock")
+            .await
+            .unwrap();
+        let commands = kernel.take_command_rx().unwrap();
+        (kernel, mock, commands, rx)
+    }
+
+    /// Apply queue commands the way the sync server does until `cell_id`
+    /// finishes executing.
+    async fn run_until_done(
+        kernel: &mut RoomKernel,
+        commands: &mut mpsc::Receiver<QueueCommand>,
+        cell_id: &str,
+    ) {
+        loop {
+            let command = tokio::time::timeout(std::time::Duration::from_secs(5), commands.recv())
+                .await
+                .expect("timed out waiting for a queue command")
+                .unwrap();
+            match command {
+                QueueCommand::ExecutionDone { cell_id: done } => {
+                    kernel.execution_done(&done).await.unwrap();
+                    if done == cell_id {
+                        return;
+                    }
+                }
+                QueueCommand::CellError { cell_id } => {
+                    kernel.handle_cell_error(&cell_id);
+                }
+                QueueCommand::KernelDied { .. } => panic!("mock kernel died"),
+                QueueCommand::InterruptAndClear => {
+                    kernel.interrupt_and_clear().await.unwrap();
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_kernel_outputs_route_by_msg_id() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let script = MockScript::new()
+            .on_execute("print('a')", MockExecution::new().stdout("a\n"))
+            .on_execute("1 + 1", MockExecution::new().result("2"));
+        let (mut kernel, mock, mut commands, mut rx) = attach_mock(&tmp, script).await;
+        {
+            let mut doc = kernel.doc.write().await;
+            doc.add_cell(0, "a", "code").unwrap();
+            doc.add_cell(1, "b", "code").unwrap();
+        }
+
+        kernel
+            .queue_cell("a".to_string(), "print('a')".to_string())
+            .await
+            .unwrap();
+        kernel
+            .queue_cell("b".to_string(), "1 + 1".to_string())
+            .await
+            .unwrap();
+        run_until_done(&mut kernel, &mut commands, "b").await;
+
+        // Each execute_request's msg_id maps back to the cell that sent it
+        let executes: Vec<_> = mock
+            .received()
+            .into_iter()
+            .filter(|m| m.header.msg_type == "execute_request")
+            .collect();
+        assert_eq!(executes.len(), 2);
+        let map = kernel.cell_id_map.lock().unwrap().clone();
+        assert_eq!(map.get(&executes[0].header.msg_id).unwrap(), "a");
+        assert_eq!(map.get(&executes[1].header.msg_id).unwrap(), "b");
+
+        let mut started = vec![];
+        let mut outputs = vec![];
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                NotebookBroadcast::ExecutionStarted {
+                    cell_id,
+                    execution_count,
+                } => started.push((cell_id, execution_count)),
+                NotebookBroadcast::Output {
+                    cell_id,
+                    output_type,
+                    ..
+                } => outputs.push((cell_id, output_type)),
+                _ => {}
+            }
+        }
+        assert_eq!(started, vec![("a".to_string(), 1), ("b".to_string(), 2)]);
+        assert_eq!(
+            outputs,
+            vec![
+                ("a".to_string(), "stream".to_string()),
+                ("b".to_string(), "execute_result".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_kernel_queue_advances_after_each_cell() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let script = MockScript::new().on_execute(
+            "slow",
+            MockExecution::new().delay(std::time::Duration::from_millis(200)),
+        );
+        let (mut kernel, mock, mut commands, _rx) = attach_mock(&tmp, script).await;
+
+        kernel
+            .queue_cell("a".to_string(), "slow".to_string())
+            .await
+            .unwrap();
+        kernel
+            .queue_cell("b".to_string(), "fast".to_string())
+            .await
+            .unwrap();
+        assert_eq!(kernel.executing_cell(), Some(&"a".to_string()));
+        assert_eq!(kernel.queued_cells(), vec!["b"]);
+
+        run_until_done(&mut kernel, &mut commands, "a").await;
+        assert_eq!(kernel.executing_cell(), Some(&"b".to_string()));
+        assert!(kernel.queued_cells().is_empty());
+
+        run_until_done(&mut kernel, &mut commands, "b").await;
+        assert!(kernel.executing_cell().is_none());
+        assert_eq!(kernel.status(), KernelStatus::Idle);
+
+        // "b" was only sent once "a" finished
+        let codes: Vec<_> = mock
+            .received()
+            .into_iter()
+            .filter_map(|m| match m.content {
+                JupyterMessageContent::ExecuteRequest(req) => Some(req.code),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(codes, vec!["slow", "fast"]);
+    }
+
+    #[tokio::test]
+    async fn test_mock_kernel_error_stops_queue() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let script =
+            MockScript::new().on_execute("boom", MockExecution::new().error("ValueError", "bad"));
+        let (mut kernel, mock, mut commands, _rx) = attach_mock(&tmp, script).await;
+
+        kernel
+            .queue_cell("a".to_string(), "boom".to_string())
+            .await
+            .unwrap();
+        kernel
+            .queue_cell("b".to_string(), "fine".to_string())
+            .await
+            .unwrap();
+        run_until_done(&mut kernel, &mut commands, "a").await;
+
+        assert!(kernel.executing_cell().is_none());
+        assert!(kernel.queued_cells().is_empty());
+        let executes = mock
+            .received()
+            .iter()
+            .filter(|m| m.header.msg_type == "execute_request")
+            .count();
+        assert_eq!(executes, 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_all_reports_progress() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (mut kernel, _mock, mut commands, mut rx) = attach_mock(&tmp, MockScript::new()).await;
+
+        let sent = kernel
+            .run_all(&[queued("a", "1"), queued("b", "2"), queued("c", "3")])
+            .await
+            .unwrap();
+        assert_eq!(sent, 3);
+        run_until_done(&mut kernel, &mut commands, "c").await;
+
+        let mut progress = vec![];
+        while let Ok(event) = rx.try_recv() {
+            if let NotebookBroadcast::RunAllProgress {
+                completed,
+                total,
+                current_cell,
+            } = event
+            {
+                progress.push((completed, total, current_cell));
+            }
+        }
+        assert_eq!(
+            progress,
+            vec![
+                (0, 3, Some("a".to_string())),
+                (1, 3, Some("b".to_string())),
+                (2, 3, Some("c".to_string())),
+                (3, 3, None),
+            ]
+        );
+        assert!(kernel.run_all.is_none());
+
+        // Cells run on their own afterwards report no progress
+        kernel
+            .queue_cell("a".to_string(), "1".to_string())
+            .await
+            .unwrap();
+        run_until_done(&mut kernel, &mut commands, "a").await;
+        while let Ok(event) = rx.try_recv() {
+            assert!(!matches!(event, NotebookBroadcast::RunAllProgress { .. }));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel_run_all_interrupts_and_clears() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let script = MockScript::new().on_execute(
+            "slow",
+            MockExecution::new().delay(std::time::Duration::from_millis(500)),
+        );
+        let (mut kernel, mock, mut commands, mut rx) = attach_mock(&tmp, script).await;
+
+        kernel
+            .run_all(&[queued("a", "slow"), queued("b", "2"), queued("c", "3")])
+            .await
+            .unwrap();
+        assert_eq!(kernel.executing_cell(), Some(&"a".to_string()));
+        assert_eq!(kernel.queued_cells(), vec!["b", "c"]);
+
+        // Cancelling goes through the queue, like the sync server's request
+        kernel
+            .command_sender()
+            .unwrap()
+            .send(QueueCommand::InterruptAndClear)
+            .await
+            .unwrap();
+        loop {
+            let command = commands.recv().await.unwrap();
+            if let QueueCommand::InterruptAndClear = command {
+                assert_eq!(kernel.interrupt_and_clear().await.unwrap(), 2);
+                break;
+            }
+        }
+
+        assert!(kernel.queued_cells().is_empty());
+        assert!(kernel.run_all.is_none());
+        let cancelled: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|event| match event {
+                NotebookBroadcast::RunAllCancelled { skipped } => Some(skipped),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(cancelled, vec![2]);
+
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+        while mock.received_control().is_empty() {
+            assert!(tokio::time::Instant::now() < deadline, "no interrupt");
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(
+            mock.received_control()[0].header.msg_type,
+            "interrupt_request"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_kernel_complete_and_inspect() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let script = MockScript::new()
+            .on_complete("x = pri", &["print", "private"])
+            .on_inspect("len", "Return the number of items in a container.");
+        let (mut kernel, _mock, _commands, _rx) = attach_mock(&tmp, script).await;
+
+        let (items, start, end) = kernel.complete("x = pri".to_string(), 7).await.unwrap();
+        let labels: Vec<_> = items.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, vec!["print", "private"]);
+        assert_eq!((start, end), (4, 7));
+
+        assert_eq!(
+            kernel.inspect("len".to_string(), 3, 0).await.unwrap(),
+            (
+                true,
+                Some("Return the number of items in a container.".to_string())
+            )
+        );
+        assert_eq!(
+            kernel.inspect("nope".to_string(), 4, 0).await.unwrap(),
+            (false, None)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_kernel_executions_are_run_logged() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let notebook = tmp.path().join("audit.ipynb");
+        let script = MockScript::new()
+            .on_execute("x = 1", MockExecution::new().result("1"))
+            .on_execute("boom", MockExecution::new().error("ValueError", "bad"));
+        let (tx, _rx) = broadcast::channel(256);
+        let mut kernel = test_kernel_with_broadcast(&tmp, tx);
+        kernel.set_run_log(Some(RunLog::for_notebook(&notebook)));
+        let mock = MockKernel::start(script).await.unwrap();
+        kernel
+            .attach(mock.connection_info().clone(), "python", "mock")
+            .await
+            .unwrap();
+        let mut commands = kernel.take_command_rx().unwrap();
+
+        for (cell, code) in [("a", "x = 1"), ("b", "boom"), ("a", "x = 2")] {
+            kernel
+                .queue_cell(cell.to_string(), code.to_string())
+                .await
+                .unwrap();
+            run_until_done(&mut kernel, &mut commands, cell).await;
+        }
+
+        let entries = run_log::read_recent(&notebook, 10);
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.cell_id.as_str(), e.status, e.error.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("a", RunStatus::Ok, None),
+                ("b", RunStatus::Error, Some("ValueError: bad")),
+                ("a", RunStatus::Ok, None),
+            ]
+        );
+        assert_eq!(entries[0].source_hash, run_log::source_hash("x = 1"));
+        assert_eq!(entries[2].source_hash, run_log::source_hash("x = 2"));
+        assert!(entries[0].started_at <= entries[1].started_at);
+        assert!(entries[1].started_at <= entries[2].started_at);
+    }
+
+    #[tokio::test]
+    async fn test_install_suggestions_follow_setting() {
+        for enabled in [true, false] {
+            let tmp = tempfile::TempDir::new().unwrap();
+            let script = MockScript::new().on_execute(
+                "import cv2",
+                MockExecution::new().error("ModuleNotFoundError", "No module named 'cv2'"),
+            );
+            let (tx, mut rx) = broadcast::channel(256);
+            let mut kernel = test_kernel_with_broadcast(&tmp, tx);
+            kernel.set_install_suggestions(enabled);
+            let mock = MockKernel::start(script).await.unwrap();
+            kernel
+                .attach(mock.connection_info().clone(), "python", "mock")
+                .await
+                .unwrap();
+            let mut commands = kernel.take_command_rx().unwrap();
+
+            kernel
+                .queue_cell("a".to_string(), "import cv2".to_string())
+                .await
+                .unwrap();
+            run_until_done(&mut kernel, &mut commands, "a").await;
+
+            let suggestion =
+                std::iter::from_fn(|| rx.try_recv().ok()).find_map(|event| match event {
+                    NotebookBroadcast::SuggestInstall {
+                        package,
+                        conda_package,
+                        ..
+                    } => Some((package, conda_package)),
+                    _ => None,
+                });
+            let expected = enabled.then(|| ("opencv-python".to_string(), "opencv".to_string()));
+            assert_eq!(suggestion, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_kernel_shutdown_stops_serving() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (mut kernel, mock, _commands, _rx) = attach_mock(&tmp, MockScript::new()).await;
+        assert!(kernel.is_running());
+
+        kernel.shutdown().await.unwrap();
+        assert!(!kernel.is_running());
+
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while !mock.is_finished() {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let last = mock.received().pop().unwrap();
+        assert_eq!(last.header.msg_type, "shutdown_request");
+    }
 }
@@ -0,0 +1,406 @@
+//! Persistent record of the kernel processes the daemon has spawned.
+//!
+//! Kernels are killed when their room shuts them down or is dropped, but a
+//! daemon that crashes (or is killed) leaves its kernels running with no room
+//! to own them, still holding memory and ports. Every launched kernel is
+//! recorded here until it exits, so a later daemon can find the ones nothing
+//! owns any more and terminate them (`runt daemon reap-kernels`).
+//!
+//! The file outlives the daemon and the machine's uptime, so a tracked PID
+//! may since have been reused by an unrelated process. A PID is only taken to
+//! be the kernel if it still leads its own process group (kernels are
+//! launched as group leaders) and the process started when the kernel was
+//! launched. Entries from before the last boot are dropped when the daemon
+//! starts.
+//!
+//! Stored as JSON at `daemon_base_dir()/kernel-pids.json`.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// A kernel process the daemon spawned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackedKernel {
+    /// PID of the kernel process, which is also its process group ID.
+    pub pid: u32,
+    /// The notebook the kernel was launched for.
+    pub notebook_id: String,
+    /// Kernel type (e.g., "python", "deno").
+    pub kernel_type: String,
+    pub started_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PidsFile {
+    kernels: Vec<TrackedKernel>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    path: PathBuf,
+    kernels: BTreeMap<u32, TrackedKernel>,
+}
+
+/// Daemon-wide registry of spawned kernel PIDs, backed by a JSON file. Cheap
+/// to clone; clones share the same registry.
+#[derive(Debug, Clone)]
+pub struct KernelPids {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl KernelPids {
+    /// Load the registry from `path`. A missing or unreadable file yields an
+    /// empty registry; it is rewritten on the next update.
+    pub fn load(path: &Path) -> Self {
+        let kernels = match std::fs::read_to_string(path) {
+            Ok(content) => match serde_json::from_str::<PidsFile>(&content) {
+                Ok(file) => file.kernels.into_iter().map(|k| (k.pid, k)).collect(),
+                Err(e) => {
+                    warn!("[kernel-pids] Ignoring corrupt {:?}: {}", path, e);
+                    BTreeMap::new()
+                }
+            },
+            Err(_) => BTreeMap::new(),
+        };
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                path: path.to_path_buf(),
+                kernels,
+            })),
+        }
+    }
+
+    /// Record a newly spawned kernel.
+    pub fn track(&self, kernel: TrackedKernel) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.kernels.insert(kernel.pid, kernel);
+        save(&inner);
+    }
+
+    /// Forget `pid`, once its kernel has exited or been killed.
+    pub fn untrack(&self, pid: u32) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.kernels.remove(&pid).is_some() {
+            save(&inner);
+        }
+    }
+
+    /// Forget kernels launched before `boot`; they died with the machine.
+    pub fn forget_started_before(&self, boot: DateTime<Utc>) {
+        let mut inner = self.inner.lock().unwrap();
+        let before = inner.kernels.len();
+        inner.kernels.retain(|_, kernel| kernel.started_at >= boot);
+        if inner.kernels.len() != before {
+            save(&inner);
+        }
+    }
+
+    /// Every recorded kernel, by PID.
+    pub fn tracked(&self) -> Vec<TrackedKernel> {
+        self.inner
+            .lock()
+            .unwrap()
+            .kernels
+            .values()
+            .cloned()
+            .collect()
+    }
+}
+
+fn save(inner: &Inner) {
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = inner.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(&PidsFile {
+            kernels: inner.kernels.values().cloned().collect(),
+        })?;
+        let tmp = inner.path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &inner.path)
+    };
+    if let Err(e) = write() {
+        warn!("[kernel-pids] Failed to save {:?}: {}", inner.path, e);
+    }
+}
+
+/// Whether a tracked kernel can be reaped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelPidState {
+    /// Owned by an open room's kernel
+    InRoom,
+    /// Still running, but no room owns it
+    Orphaned,
+    /// No longer running (or the PID now belongs to another process)
+    Gone,
+}
+
+/// Classify `kernel` given the PIDs of kernels open rooms own and whether
+/// it is still running as that PID.
+pub fn classify(
+    kernel: &TrackedKernel,
+    room_pids: &HashSet<u32>,
+    is_alive: impl Fn(&TrackedKernel) -> bool,
+) -> KernelPidState {
+    if room_pids.contains(&kernel.pid) {
+        KernelPidState::InRoom
+    } else if is_alive(kernel) {
+        KernelPidState::Orphaned
+    } else {
+        KernelPidState::Gone
+    }
+}
+
+/// How far a process's start time may be from the `started_at` recorded
+/// just after spawning it and still be the same process.
+const START_TIME_TOLERANCE_SECS: i64 = 5;
+
+/// Whether `kernel` is still running: its PID leads its own process group
+/// and started when the kernel was launched. A process whose start time
+/// can't be read is never taken to be the kernel.
+pub fn is_kernel_process(kernel: &TrackedKernel) -> bool {
+    leads_process_group(kernel.pid)
+        && process_start_time(kernel.pid).is_some_and(|started| {
+            (started - kernel.started_at).num_seconds().abs() <= START_TIME_TOLERANCE_SECS
+        })
+}
+
+#[cfg(unix)]
+fn leads_process_group(pid: u32) -> bool {
+    use nix::unistd::{getpgid, Pid};
+    let pid = Pid::from_raw(pid as i32);
+    getpgid(Some(pid)) == Ok(pid)
+}
+
+#[cfg(not(unix))]
+fn leads_process_group(_pid: u32) -> bool {
+    false
+}
+
+/// When the process `pid` started.
+#[cfg(target_os = "linux")]
+pub fn process_start_time(pid: u32) -> Option<DateTime<Utc>> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The command name may contain spaces and parentheses; fields after it
+    // start with the state (field 3), so starttime (field 22) is the 20th
+    let after_comm = &stat[stat.rfind(')')? + 1..];
+    let ticks: i64 = after_comm.split_whitespace().nth(19)?.parse().ok()?;
+    let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_sec <= 0 {
+        return None;
+    }
+    let millis = ticks * 1000 / ticks_per_sec as i64;
+    Some(boot_time()? + chrono::Duration::milliseconds(millis))
+}
+
+/// When the process `pid` started.
+#[cfg(target_os = "macos")]
+pub fn process_start_time(pid: u32) -> Option<DateTime<Utc>> {
+    let mut info: libc::proc_bsdinfo = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<libc::proc_bsdinfo>() as libc::c_int;
+    let written = unsafe {
+        libc::proc_pidinfo(
+            pid as libc::c_int,
+            libc::PROC_PIDTBSDINFO,
+            0,
+            &mut info as *mut _ as *mut libc::c_void,
+            size,
+        )
+    };
+    if written != size {
+        return None;
+    }
+    DateTime::from_timestamp(
+        info.pbi_start_tvsec as i64,
+        info.pbi_start_tvusec as u32 * 1000,
+    )
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn process_start_time(_pid: u32) -> Option<DateTime<Utc>> {
+    None
+}
+
+/// When the machine last booted.
+#[cfg(target_os = "linux")]
+pub fn boot_time() -> Option<DateTime<Utc>> {
+    let stat = std::fs::read_to_string("/proc/stat").ok()?;
+    let secs = stat
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
+    DateTime::from_timestamp(secs, 0)
+}
+
+/// When the machine last booted.
+#[cfg(target_os = "macos")]
+pub fn boot_time() -> Option<DateTime<Utc>> {
+    let mut mib = [libc::CTL_KERN, libc::KERN_BOOTTIME];
+    let mut boot: libc::timeval = unsafe { std::mem::zeroed() };
+    let mut size = std::mem::size_of::<libc::timeval>();
+    let result = unsafe {
+        libc::sysctl(
+            mib.as_mut_ptr(),
+            mib.len() as libc::c_uint,
+            &mut boot as *mut _ as *mut libc::c_void,
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if result != 0 {
+        return None;
+    }
+    DateTime::from_timestamp(boot.tv_sec, boot.tv_usec as u32 * 1000)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn boot_time() -> Option<DateTime<Utc>> {
+    None
+}
+
+/// Kill the kernel's whole process group.
+#[cfg(unix)]
+pub fn terminate(pid: u32) -> std::io::Result<()> {
+    use nix::sys::signal::{killpg, Signal};
+    use nix::unistd::Pid;
+    killpg(Pid::from_raw(pid as i32), Signal::SIGKILL).map_err(std::io::Error::from)
+}
+
+#[cfg(not(unix))]
+pub fn terminate(_pid: u32) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "reaping kernels is only supported on Unix",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kernel(pid: u32) -> TrackedKernel {
+        TrackedKernel {
+            pid,
+            notebook_id: format!("/work/{pid}.ipynb"),
+            kernel_type: "python".to_string(),
+            started_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_tracked_pid_with_no_room_is_reapable() {
+        let room_pids = HashSet::from([100]);
+        let alive = |kernel: &TrackedKernel| kernel.pid != 300;
+
+        assert_eq!(
+            classify(&kernel(100), &room_pids, alive),
+            KernelPidState::InRoom
+        );
+        assert_eq!(
+            classify(&kernel(200), &room_pids, alive),
+            KernelPidState::Orphaned
+        );
+        assert_eq!(
+            classify(&kernel(300), &room_pids, alive),
+            KernelPidState::Gone
+        );
+    }
+
+    #[test]
+    fn test_registry_persists_across_loads() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("kernel-pids.json");
+
+        let pids = KernelPids::load(&path);
+        pids.track(kernel(100));
+        pids.track(kernel(200));
+        pids.untrack(100);
+
+        let reloaded = KernelPids::load(&path);
+        assert_eq!(reloaded.tracked(), pids.tracked());
+        assert_eq!(reloaded.tracked()[0].pid, 200);
+    }
+
+    #[test]
+    fn test_forget_started_before_boot() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("kernel-pids.json");
+        let boot = Utc::now() - chrono::Duration::hours(1);
+
+        let pids = KernelPids::load(&path);
+        pids.track(TrackedKernel {
+            started_at: boot - chrono::Duration::days(2),
+            ..kernel(100)
+        });
+        pids.track(kernel(200));
+        pids.forget_started_before(boot);
+
+        let reloaded = KernelPids::load(&path);
+        let remaining: Vec<u32> = reloaded.tracked().iter().map(|k| k.pid).collect();
+        assert_eq!(remaining, vec![200]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_kernel_process_requires_group_leader() {
+        use std::os::unix::process::CommandExt;
+
+        let mut leader = std::process::Command::new("sleep")
+            .arg("30")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let mut member = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let tracked = |pid: u32| TrackedKernel {
+            started_at: Utc::now(),
+            ..kernel(pid)
+        };
+
+        assert!(is_kernel_process(&tracked(leader.id())));
+        // Shares the test's process group rather than leading its own
+        assert!(!is_kernel_process(&tracked(member.id())));
+
+        terminate(leader.id()).unwrap();
+        leader.wait().unwrap();
+        assert!(!is_kernel_process(&tracked(leader.id())));
+        member.kill().unwrap();
+        member.wait().unwrap();
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn test_is_kernel_process_rejects_reused_pid() {
+        use std::os::unix::process::CommandExt;
+
+        let mut leader = std::process::Command::new("sleep")
+            .arg("30")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+
+        // Same PID, but the tracked kernel was launched a day earlier
+        let stale = TrackedKernel {
+            started_at: Utc::now() - chrono::Duration::days(1),
+            ..kernel(leader.id())
+        };
+        assert!(!is_kernel_process(&stale));
+
+        let started = process_start_time(leader.id()).unwrap();
+        assert!((started - Utc::now()).num_seconds().abs() <= START_TIME_TOLERANCE_SECS);
+        assert!(boot_time().unwrap() < started);
+
+        terminate(leader.id()).unwrap();
+        leader.wait().unwrap();
+    }
+}
@@ -0,0 +1,225 @@
+//! Snapshot and restore of a Python kernel's user namespace.
+//!
+//! Restarting a kernel throws away every variable the notebook built up.
+//! For Python kernels the daemon can save the user namespace with `dill`
+//! before the restart and load it into the next kernel, so the user picks
+//! up where they left off without re-running slow cells.
+//!
+//! Each variable is pickled on its own: anything `dill` can't serialize
+//! (open sockets, generators, objects holding locks) is dropped and named in
+//! the [`SnapshotReport`] instead of failing the whole snapshot. The same
+//! goes for variables that fail to load in the new kernel.
+//!
+//! The snapshot lives next to the notebook's persisted Automerge doc, so it
+//! is keyed by notebook and survives daemon restarts.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// A variable left out of a snapshot or restore, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DroppedVariable {
+    pub name: String,
+    /// The exception raised while pickling or unpickling, e.g.
+    /// `"TypeError: cannot pickle '_thread.lock' object"`
+    pub reason: String,
+}
+
+/// What a snapshot or restore managed to carry over.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotReport {
+    /// Variables saved (for a snapshot) or loaded (for a restore)
+    pub names: Vec<String>,
+    /// Variables that couldn't be serialized or deserialized
+    pub dropped: Vec<DroppedVariable>,
+}
+
+/// Where the kernel state snapshot for the notebook persisted at
+/// `persist_path` is stored.
+pub fn snapshot_path(persist_path: &Path) -> PathBuf {
+    persist_path.with_extension("kernel-state.dill")
+}
+
+/// Where the kernel writes the report for a snapshot or restore.
+pub fn report_path(snapshot_path: &Path) -> PathBuf {
+    snapshot_path.with_extension("report.json")
+}
+
+/// Shared by both snapshot and restore. Runs inside a function so nothing
+/// but the target variables touches the user namespace, and deletes itself
+/// afterwards.
+const PYTHON_PRELUDE: &str = r#"
+def __runt_kernel_state(mode, path, report_path):
+    import json
+
+    try:
+        import dill
+    except ImportError:
+        raise ImportError(
+            "Kernel state snapshots need the 'dill' package. "
+            "Add it to the notebook's dependencies and try again."
+        ) from None
+
+    ip = get_ipython()
+    ns, hidden = ip.user_ns, ip.user_ns_hidden
+
+    names, dropped = [], []
+    if mode == "snapshot":
+        blobs = {}
+        for name, value in list(ns.items()):
+            if name.startswith("_") or name in hidden:
+                continue
+            try:
+                blobs[name] = dill.dumps(value)
+                names.append(name)
+            except Exception as e:
+                dropped.append({"name": name, "reason": f"{type(e).__name__}: {e}"})
+        with open(path, "wb") as f:
+            dill.dump(blobs, f)
+    else:
+        with open(path, "rb") as f:
+            blobs = dill.load(f)
+        for name, blob in blobs.items():
+            try:
+                ns[name] = dill.loads(blob)
+                names.append(name)
+            except Exception as e:
+                dropped.append({"name": name, "reason": f"{type(e).__name__}: {e}"})
+
+    with open(report_path, "w") as f:
+        json.dump({"names": names, "dropped": dropped}, f)
+"#;
+
+/// Python that pickles the kernel's user namespace to `path`.
+pub fn snapshot_code(path: &Path, report_path: &Path) -> String {
+    kernel_state_code("snapshot", path, report_path)
+}
+
+/// Python that loads a snapshot from `path` into the kernel's user namespace.
+pub fn restore_code(path: &Path, report_path: &Path) -> String {
+    kernel_state_code("restore", path, report_path)
+}
+
+fn kernel_state_code(mode: &str, path: &Path, report_path: &Path) -> String {
+    format!(
+        "{PYTHON_PRELUDE}\ntry:\n    __runt_kernel_state({}, {}, {})\nfinally:\n    del __runt_kernel_state\n",
+        python_str(mode),
+        python_str(&path.to_string_lossy()),
+        python_str(&report_path.to_string_lossy()),
+    )
+}
+
+/// Quote `s` as a Python string literal. JSON string escapes are a subset of
+/// Python's, so serde's output is valid Python.
+fn python_str(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_else(|_| "''".to_string())
+}
+
+/// Read and remove the report the kernel wrote to `report_path`.
+pub fn take_report(report_path: &Path) -> anyhow::Result<SnapshotReport> {
+    let contents = std::fs::read_to_string(report_path)?;
+    let _ = std::fs::remove_file(report_path);
+    Ok(serde_json::from_str(&contents)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_paths_sit_next_to_persisted_doc() {
+        let persist = Path::new("/state/notebook-docs/abc123.automerge");
+        let snapshot = snapshot_path(persist);
+        assert_eq!(
+            snapshot,
+            PathBuf::from("/state/notebook-docs/abc123.kernel-state.dill")
+        );
+        assert_eq!(
+            report_path(&snapshot),
+            PathBuf::from("/state/notebook-docs/abc123.kernel-state.report.json")
+        );
+    }
+
+    #[test]
+    fn test_paths_are_quoted_for_python() {
+        let code = snapshot_code(
+            Path::new("C:\\Users\\me\\it's.dill"),
+            Path::new("/tmp/report.json"),
+        );
+        assert!(code.contains(
+            r#"__runt_kernel_state("snapshot", "C:\\Users\\me\\it's.dill", "/tmp/report.json")"#
+        ));
+        assert!(code.contains("del __runt_kernel_state"));
+    }
+
+    #[test]
+    fn test_take_report_removes_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+        std::fs::write(
+            &path,
+            r#"{"names": ["df"], "dropped": [{"name": "sock", "reason": "TypeError: cannot pickle 'socket' object"}]}"#,
+        )
+        .unwrap();
+
+        let report = take_report(&path).unwrap();
+        assert_eq!(report.names, vec!["df"]);
+        assert_eq!(report.dropped[0].name, "sock");
+        assert!(!path.exists());
+    }
+
+    /// Run the snapshot code against one mock namespace and the restore code
+    /// against a fresh one. `pickle` stands in for `dill`, which can't
+    /// serialize lambdas, so they exercise the dropped-variable path.
+    #[test]
+    fn test_snapshot_and_restore_with_mock_namespace() {
+        if Command::new("python3").arg("--version").output().is_err() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot = dir.path().join("state.dill");
+        let report = report_path(&snapshot);
+
+        // A stand-in for IPython exposing just the namespace the code reads
+        let harness = |code: String, namespace: &str| {
+            let program = format!(
+                "import pickle, sys, types\n\
+                 sys.modules['dill'] = pickle\n\
+                 ns, hidden = {namespace}\n\
+                 get_ipython = lambda: types.SimpleNamespace(user_ns=ns, user_ns_hidden=hidden)\n\
+                 {code}\n\
+                 assert '__runt_kernel_state' not in globals()\n\
+                 print(sorted(k for k in ns if not k.startswith('_')))\n"
+            );
+            let output = Command::new("python3")
+                .arg("-c")
+                .arg(program)
+                .output()
+                .unwrap();
+            assert!(
+                output.status.success(),
+                "{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+
+        harness(
+            snapshot_code(&snapshot, &report),
+            "{'x': 41, 'data': [1, 2], 'square': lambda n: n * n, '_private': 1, 'In': []}, \
+             {'In': []}",
+        );
+        let saved = take_report(&report).unwrap();
+        assert_eq!(saved.names, vec!["x", "data"]);
+        assert_eq!(saved.dropped.len(), 1);
+        assert_eq!(saved.dropped[0].name, "square");
+
+        let restored_ns = harness(restore_code(&snapshot, &report), "{'x': 0}, {}");
+        assert_eq!(restored_ns, "['data', 'x']");
+        let restored = take_report(&report).unwrap();
+        assert_eq!(restored.names, vec!["x", "data"]);
+        assert!(restored.dropped.is_empty());
+    }
+}
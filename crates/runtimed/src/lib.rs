@@ -15,19 +15,38 @@ use sha2::{Digest, Sha256};
 
 pub mod blob_server;
 pub mod blob_store;
+pub mod broadcast_coalescer;
+pub mod cell_tags;
 pub mod client;
 pub mod comm_state;
 pub mod connection;
 pub mod daemon;
+pub mod env_cache;
+pub mod env_claims;
+pub mod env_options;
+pub mod env_pins;
+pub mod environment_yml;
 pub mod inline_env;
 pub mod kernel_manager;
+pub mod kernel_pids;
+pub mod kernel_snapshot;
+pub mod log_format;
+pub mod lsp_proxy;
+pub mod missing_import;
+#[cfg(any(test, feature = "mock-kernel"))]
+pub mod mock_kernel;
 pub mod notebook_doc;
 pub mod notebook_metadata;
+pub mod notebook_registry;
 pub mod notebook_sync_client;
 pub mod notebook_sync_server;
+pub mod output_sanitize;
+pub mod output_search;
 pub mod output_store;
+pub mod output_transform;
 pub mod project_file;
 pub mod protocol;
+pub mod run_log;
 pub mod runtime;
 pub mod service;
 pub mod settings_doc;
@@ -35,6 +54,7 @@ pub mod singleton;
 pub mod stream_terminal;
 pub mod sync_client;
 pub mod sync_server;
+pub mod system_python;
 pub mod terminal_size;
 
 // ============================================================================
@@ -157,7 +177,7 @@ impl std::fmt::Display for EnvType {
 }
 
 /// A prewarmed environment returned by the daemon.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PooledEnv {
     pub env_type: EnvType,
     pub venv_path: PathBuf,
@@ -171,6 +191,12 @@ pub struct PoolStats {
     pub uv_warming: usize,
     pub conda_available: usize,
     pub conda_warming: usize,
+    /// UV environments pinned to notebooks, outside the pool.
+    #[serde(default)]
+    pub uv_pinned: usize,
+    /// Conda environments pinned to notebooks, outside the pool.
+    #[serde(default)]
+    pub conda_pinned: usize,
     /// Error info for UV pool (if warming is failing).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub uv_error: Option<PoolError>,
@@ -191,6 +217,10 @@ pub struct PoolError {
     pub consecutive_failures: u32,
     /// Seconds until next retry (0 if retry is imminent).
     pub retry_in_secs: u64,
+    /// Default packages from settings left out of new environments because
+    /// they failed to install.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_packages: Vec<String>,
 }
 
 /// Get the default endpoint path for runtimed.
@@ -272,3 +302,18 @@ pub fn settings_schema_path() -> PathBuf {
 pub fn default_notebook_docs_dir() -> PathBuf {
     daemon_base_dir().join("notebook-docs")
 }
+
+/// Get the default path for the registry of previously opened notebooks.
+pub fn default_notebook_registry_path() -> PathBuf {
+    daemon_base_dir().join("notebooks.json")
+}
+
+/// Get the default path for the record of prewarmed envs pinned to notebooks.
+pub fn default_env_pins_path() -> PathBuf {
+    daemon_base_dir().join("env-pins.json")
+}
+
+/// Get the default path for the record of spawned kernel processes.
+pub fn default_kernel_pids_path() -> PathBuf {
+    daemon_base_dir().join("kernel-pids.json")
+}
@@ -0,0 +1,265 @@
+//! Daemon log line formatting.
+//!
+//! The daemon writes human-readable text lines by default. Setting
+//! `RUNTIMED_LOG_FORMAT=json` switches to JSON lines instead, one object per
+//! record:
+//!
+//! ```json
+//! {"timestamp":"2026-01-01T12:00:00.000Z","level":"INFO","target":"runtimed::notebook_sync_server","message":"[notebook-sync] Creating room for /a.ipynb","notebook_id":"/a.ipynb"}
+//! ```
+//!
+//! Key-value pairs attached to a log call (`info!(notebook_id = id; "...")`)
+//! become extra top-level fields. Records logged inside a notebook room's
+//! tasks (see [`in_notebook_scope`]) get a `notebook_id` field too.
+//! [`render_json_line`] turns a JSON record back into the text format for
+//! `runt daemon logs`.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use log::kv::{Key, Value, VisitSource};
+use serde_json::{Map, Value as JsonValue};
+
+tokio::task_local! {
+    /// The notebook whose room the current task is serving.
+    static NOTEBOOK_ID: Arc<str>;
+}
+
+/// Run `fut` with `notebook_id` attached to every JSON record it logs.
+pub async fn in_notebook_scope<F: Future>(notebook_id: &str, fut: F) -> F::Output {
+    NOTEBOOK_ID.scope(Arc::from(notebook_id), fut).await
+}
+
+/// `tokio::spawn` that keeps the caller's notebook scope. Task-locals don't
+/// follow spawned tasks, so room tasks spawn through this.
+pub fn spawn_in_scope<F>(fut: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match NOTEBOOK_ID.try_with(Arc::clone) {
+        Ok(notebook_id) => tokio::spawn(NOTEBOOK_ID.scope(notebook_id, fut)),
+        Err(_) => tokio::spawn(fut),
+    }
+}
+
+/// Environment variable selecting the log format (`text` or `json`).
+pub const LOG_FORMAT_ENV: &str = "RUNTIMED_LOG_FORMAT";
+
+/// Fields every JSON record has; key-value pairs can't override them.
+const RESERVED_FIELDS: &[&str] = &["timestamp", "level", "target", "message"];
+
+/// How the daemon formats log lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// `2026-01-01 12:00:00 [INFO] target: message`
+    #[default]
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+impl LogFormat {
+    /// Read the format from `RUNTIMED_LOG_FORMAT`, defaulting to text.
+    pub fn from_env() -> Self {
+        std::env::var(LOG_FORMAT_ENV)
+            .map(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    /// Parse a format name; anything other than `json` means text.
+    pub fn parse(value: &str) -> Self {
+        if value.trim().eq_ignore_ascii_case("json") {
+            Self::Json
+        } else {
+            Self::Text
+        }
+    }
+
+    /// Format a record as a single line, including the trailing newline.
+    pub fn format_record(self, record: &log::Record) -> String {
+        match self {
+            Self::Text => format!(
+                "{} [{}] {}: {}\n",
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                record.level(),
+                record.target(),
+                record.args()
+            ),
+            Self::Json => {
+                let timestamp =
+                    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+                format!("{}\n", json_record(record, &timestamp))
+            }
+        }
+    }
+}
+
+/// Build the JSON object for a record.
+fn json_record(record: &log::Record, timestamp: &str) -> JsonValue {
+    let mut fields = Map::new();
+    fields.insert("timestamp".into(), timestamp.into());
+    fields.insert("level".into(), record.level().as_str().into());
+    fields.insert("target".into(), record.target().into());
+    fields.insert("message".into(), record.args().to_string().into());
+
+    let mut visitor = FieldVisitor(&mut fields);
+    let _ = record.key_values().visit(&mut visitor);
+    if !fields.contains_key("notebook_id") {
+        if let Ok(notebook_id) = NOTEBOOK_ID.try_with(|id| id.to_string()) {
+            fields.insert("notebook_id".into(), notebook_id.into());
+        }
+    }
+    JsonValue::Object(fields)
+}
+
+/// Copies a record's key-value pairs into the JSON object.
+struct FieldVisitor<'a>(&'a mut Map<String, JsonValue>);
+
+impl<'kvs> VisitSource<'kvs> for FieldVisitor<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        if RESERVED_FIELDS.contains(&key.as_str()) {
+            return Ok(());
+        }
+        let value = if let Some(b) = value.to_bool() {
+            JsonValue::Bool(b)
+        } else if let Some(n) = value.to_i64() {
+            n.into()
+        } else if let Some(n) = value.to_u64() {
+            n.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.as_str().to_string(), value);
+        Ok(())
+    }
+}
+
+/// Render a JSON log line in the text format, with extra fields appended as
+/// `key=value`. Returns `None` for lines that aren't JSON records, such as
+/// text-format lines.
+pub fn render_json_line(line: &str) -> Option<String> {
+    let JsonValue::Object(mut fields) = serde_json::from_str(line).ok()? else {
+        return None;
+    };
+    let mut take = |key: &str| match fields.shift_remove(key)? {
+        JsonValue::String(s) => Some(s),
+        _ => None,
+    };
+    let timestamp = take("timestamp")?;
+    let level = take("level")?;
+    let target = take("target")?;
+    let message = take("message")?;
+
+    // Show the time in the same shape as text lines
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp)
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or(timestamp);
+
+    let mut rendered = format!("{} [{}] {}: {}", timestamp, level, target, message);
+    for (key, value) in fields {
+        let value = match value {
+            JsonValue::String(s) => s,
+            other => other.to_string(),
+        };
+        rendered.push_str(&format!(" {}={}", key, value));
+    }
+    Some(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(LogFormat::parse("json"), LogFormat::Json);
+        assert_eq!(LogFormat::parse(" JSON "), LogFormat::Json);
+        assert_eq!(LogFormat::parse("text"), LogFormat::Text);
+        assert_eq!(LogFormat::parse(""), LogFormat::Text);
+    }
+
+    #[test]
+    fn test_json_record_fields() {
+        let kvs: &[(&str, &str)] = &[("notebook_id", "/tmp/a.ipynb"), ("message", "ignored")];
+        let record = log::Record::builder()
+            .args(format_args!("[notebook-sync] Creating room"))
+            .level(log::Level::Info)
+            .target("runtimed::notebook_sync_server")
+            .key_values(&kvs)
+            .build();
+
+        let line = LogFormat::Json.format_record(&record);
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.trim_end().lines().count(), 1);
+
+        let parsed: JsonValue = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(parsed["level"], "INFO");
+        assert_eq!(parsed["target"], "runtimed::notebook_sync_server");
+        assert_eq!(parsed["message"], "[notebook-sync] Creating room");
+        assert_eq!(parsed["notebook_id"], "/tmp/a.ipynb");
+        let timestamp = parsed["timestamp"].as_str().unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+    }
+
+    #[test]
+    fn test_json_record_typed_values() {
+        let kvs: &[(&str, u64)] = &[("peers", 2)];
+        let record = log::Record::builder()
+            .args(format_args!("connected"))
+            .level(log::Level::Warn)
+            .key_values(&kvs)
+            .build();
+
+        let parsed = json_record(&record, "2026-01-01T00:00:00.000Z");
+        assert_eq!(parsed["peers"], 2);
+        assert_eq!(parsed["level"], "WARN");
+    }
+
+    #[tokio::test]
+    async fn test_notebook_scope_reaches_spawned_tasks() {
+        let record_line = || {
+            let record = log::Record::builder()
+                .args(format_args!("kernel launched"))
+                .level(log::Level::Info)
+                .build();
+            json_record(&record, "2026-01-01T00:00:00.000Z")
+        };
+
+        assert!(record_line().get("notebook_id").is_none());
+
+        let (scoped, spawned) = in_notebook_scope("/tmp/a.ipynb", async move {
+            let spawned = spawn_in_scope(async move { record_line() }).await.unwrap();
+            (record_line(), spawned)
+        })
+        .await;
+        assert_eq!(scoped["notebook_id"], "/tmp/a.ipynb");
+        assert_eq!(spawned["notebook_id"], "/tmp/a.ipynb");
+
+        // An explicit key-value pair wins over the scope
+        let kvs: &[(&str, &str)] = &[("notebook_id", "/tmp/b.ipynb")];
+        let explicit = in_notebook_scope("/tmp/a.ipynb", async {
+            let record = log::Record::builder()
+                .args(format_args!("room created"))
+                .key_values(&kvs)
+                .build();
+            json_record(&record, "2026-01-01T00:00:00.000Z")
+        })
+        .await;
+        assert_eq!(explicit["notebook_id"], "/tmp/b.ipynb");
+    }
+
+    #[test]
+    fn test_render_json_line() {
+        let line = r#"{"timestamp":"2026-01-01T12:00:00.000Z","level":"INFO","target":"runtimed","message":"hello","notebook_id":"abc","peers":2}"#;
+        let rendered = render_json_line(line).unwrap();
+        assert!(rendered.ends_with(" [INFO] runtimed: hello notebook_id=abc peers=2"));
+
+        assert!(render_json_line("2026-01-01 12:00:00 [INFO] runtimed: hello").is_none());
+        assert!(render_json_line(r#"{"level":"INFO"}"#).is_none());
+    }
+}
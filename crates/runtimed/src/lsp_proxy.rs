@@ -0,0 +1,612 @@
+//! Language server proxy for richer Python completions.
+//!
+//! Kernel completions (`complete_request`) only know about runtime state: names
+//! that exist because a cell has executed. A language server sees the code
+//! statically and knows about unexecuted imports, signatures and kinds. This
+//! module runs a Python language server (pyright) over stdio, rooted at the
+//! notebook's directory, and merges its results with the kernel's.
+//!
+//! The proxy is strictly best-effort. If the server can't be found, fails to
+//! initialize, or doesn't answer within [`DEFAULT_REQUEST_TIMEOUT`], callers
+//! fall back to kernel-only results via [`merge_completions`].
+//!
+//! Gated behind the `lsp.enabled` setting.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{oneshot, Mutex};
+
+use crate::protocol::CompletionItem;
+
+/// How long to wait for a language server response before giving up.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// File name of the virtual document used for the cell being edited.
+///
+/// Placed in the notebook directory so relative imports and the project's
+/// configuration resolve the same way they would for a real module.
+const VIRTUAL_CELL_FILE: &str = "__runt_cell__.py";
+
+type PendingRequests = Arc<StdMutex<HashMap<i64, oneshot::Sender<Value>>>>;
+type LspWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// State of a room's language server.
+pub enum LspState {
+    /// Not started yet (or disabled when the room was created).
+    NotStarted,
+    /// Start is in progress in a background task.
+    Starting,
+    /// Server is up and answering requests.
+    Ready(Arc<LspClient>),
+    /// Server could not be started; don't retry for this room.
+    Failed,
+}
+
+/// A JSON-RPC client for a language server speaking LSP over a byte stream.
+pub struct LspClient {
+    writer: Mutex<LspWriter>,
+    pending: PendingRequests,
+    next_id: AtomicI64,
+    /// Document version for the virtual cell (0 = not opened yet).
+    doc_version: Mutex<i32>,
+    doc_uri: String,
+    request_timeout: Duration,
+    reader_task: tokio::task::JoinHandle<()>,
+    /// The server process, if we spawned one (killed on drop).
+    _child: Option<tokio::process::Child>,
+}
+
+impl LspClient {
+    /// Find and spawn a Python language server rooted at `root`.
+    pub async fn start(root: &Path) -> Result<Self> {
+        let server = find_language_server().await?;
+        info!("[lsp] Starting {} in {}", server.display(), root.display());
+        let mut child = tokio::process::Command::new(&server)
+            .arg("--stdio")
+            .current_dir(root)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("language server stdin unavailable"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("language server stdout unavailable"))?;
+
+        let mut client = Self::connect(stdout, stdin, root, DEFAULT_REQUEST_TIMEOUT).await?;
+        client._child = Some(child);
+        Ok(client)
+    }
+
+    /// Connect to a language server over an existing reader/writer pair and
+    /// perform the `initialize` handshake.
+    pub async fn connect<R, W>(
+        reader: R,
+        writer: W,
+        root: &Path,
+        request_timeout: Duration,
+    ) -> Result<Self>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let pending: PendingRequests = Arc::new(StdMutex::new(HashMap::new()));
+        let reader_pending = pending.clone();
+        let reader_task = tokio::spawn(async move {
+            let mut reader = BufReader::new(reader);
+            loop {
+                match read_message(&mut reader).await {
+                    Ok(Some(message)) => {
+                        // Only responses carry a numeric id without a method;
+                        // server notifications and requests are ignored.
+                        if message.get("method").is_some() {
+                            continue;
+                        }
+                        let Some(id) = message.get("id").and_then(|v| v.as_i64()) else {
+                            continue;
+                        };
+                        let tx = reader_pending.lock().ok().and_then(|mut p| p.remove(&id));
+                        if let Some(tx) = tx {
+                            let _ = tx.send(message);
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        warn!("[lsp] Failed to read message: {}", e);
+                        break;
+                    }
+                }
+            }
+            debug!("[lsp] Reader loop ended");
+        });
+
+        let doc_uri = path_to_uri(&root.join(VIRTUAL_CELL_FILE));
+        let client = Self {
+            writer: Mutex::new(Box::new(writer)),
+            pending,
+            next_id: AtomicI64::new(1),
+            doc_version: Mutex::new(0),
+            doc_uri,
+            request_timeout,
+            reader_task,
+            _child: None,
+        };
+
+        client
+            .request(
+                "initialize",
+                json!({
+                    "processId": std::process::id(),
+                    "rootUri": path_to_uri(root),
+                    "capabilities": {
+                        "textDocument": {
+                            "completion": { "completionItem": { "snippetSupport": false } },
+                            "hover": { "contentFormat": ["plaintext", "markdown"] }
+                        }
+                    }
+                }),
+            )
+            .await?;
+        client.notify("initialized", json!({})).await?;
+
+        Ok(client)
+    }
+
+    /// Request completions for `code` at `cursor_pos` (in unicode characters).
+    pub async fn complete(&self, code: &str, cursor_pos: usize) -> Result<Vec<CompletionItem>> {
+        self.sync_document(code).await?;
+        let (line, character) = lsp_position(code, cursor_pos);
+        let response = self
+            .request(
+                "textDocument/completion",
+                json!({
+                    "textDocument": { "uri": self.doc_uri },
+                    "position": { "line": line, "character": character }
+                }),
+            )
+            .await?;
+        Ok(parse_completion_response(&response))
+    }
+
+    /// Request hover documentation for the symbol at `cursor_pos`.
+    pub async fn hover(&self, code: &str, cursor_pos: usize) -> Result<Option<String>> {
+        self.sync_document(code).await?;
+        let (line, character) = lsp_position(code, cursor_pos);
+        let response = self
+            .request(
+                "textDocument/hover",
+                json!({
+                    "textDocument": { "uri": self.doc_uri },
+                    "position": { "line": line, "character": character }
+                }),
+            )
+            .await?;
+        Ok(parse_hover_response(&response))
+    }
+
+    /// Open or update the virtual cell document with the full text.
+    async fn sync_document(&self, code: &str) -> Result<()> {
+        let mut version = self.doc_version.lock().await;
+        *version += 1;
+        if *version == 1 {
+            self.notify(
+                "textDocument/didOpen",
+                json!({
+                    "textDocument": {
+                        "uri": self.doc_uri,
+                        "languageId": "python",
+                        "version": *version,
+                        "text": code
+                    }
+                }),
+            )
+            .await
+        } else {
+            self.notify(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": self.doc_uri, "version": *version },
+                    "contentChanges": [{ "text": code }]
+                }),
+            )
+            .await
+        }
+    }
+
+    /// Send a request and wait for its result (with timeout).
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .map_err(|_| anyhow!("Lock poisoned"))?
+            .insert(id, tx);
+
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if let Err(e) = self.send(&message).await {
+            if let Ok(mut pending) = self.pending.lock() {
+                pending.remove(&id);
+            }
+            return Err(e);
+        }
+
+        match tokio::time::timeout(self.request_timeout, rx).await {
+            Ok(Ok(response)) => {
+                if let Some(error) = response.get("error") {
+                    return Err(anyhow!("{} failed: {}", method, error));
+                }
+                Ok(response.get("result").cloned().unwrap_or(Value::Null))
+            }
+            Ok(Err(_)) => Err(anyhow!("{} cancelled: language server exited", method)),
+            Err(_) => {
+                if let Ok(mut pending) = self.pending.lock() {
+                    pending.remove(&id);
+                }
+                Err(anyhow!("{} timed out", method))
+            }
+        }
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+            .await
+    }
+
+    async fn send(&self, message: &Value) -> Result<()> {
+        let body = serde_json::to_vec(message)?;
+        let mut writer = self.writer.lock().await;
+        writer
+            .write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+            .await?;
+        writer.write_all(&body).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        self.reader_task.abort();
+    }
+}
+
+/// Locate a Python language server binary.
+///
+/// Prefers servers already on PATH, then bootstraps pyright from conda-forge
+/// via `kernel_launch::tools`.
+async fn find_language_server() -> Result<PathBuf> {
+    for candidate in ["basedpyright-langserver", "pyright-langserver"] {
+        if let Ok(path) = which(candidate) {
+            return Ok(path);
+        }
+    }
+
+    let tool = kernel_launch::tools::bootstrap_tool("pyright", None).await?;
+    #[cfg(windows)]
+    let server = tool.env_path.join("Scripts").join("pyright-langserver.exe");
+    #[cfg(not(windows))]
+    let server = tool.env_path.join("bin").join("pyright-langserver");
+    if server.exists() {
+        Ok(server)
+    } else {
+        Err(anyhow!(
+            "pyright-langserver not found in {}",
+            tool.env_path.display()
+        ))
+    }
+}
+
+/// Minimal PATH lookup for an executable name.
+fn which(name: &str) -> Result<PathBuf> {
+    let path = std::env::var_os("PATH").ok_or_else(|| anyhow!("PATH not set"))?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| anyhow!("{} not found on PATH", name))
+}
+
+/// Read one `Content-Length` framed JSON-RPC message.
+async fn read_message<R: AsyncRead + Unpin>(reader: &mut BufReader<R>) -> Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            content_length = Some(value.trim().parse::<usize>()?);
+        }
+    }
+    let length = content_length.ok_or_else(|| anyhow!("missing Content-Length header"))?;
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        format!("file:///{}", path)
+    }
+}
+
+/// Convert a unicode-character offset into an LSP (line, UTF-16 column) position.
+fn lsp_position(code: &str, cursor_pos: usize) -> (u32, u32) {
+    let mut line = 0u32;
+    let mut character = 0u32;
+    for c in code.chars().take(cursor_pos) {
+        if c == '\n' {
+            line += 1;
+            character = 0;
+        } else {
+            character += c.len_utf16() as u32;
+        }
+    }
+    (line, character)
+}
+
+/// Map an LSP `CompletionItemKind` number to the names used by `CompletionItem::kind`.
+fn completion_kind_name(kind: u64) -> Option<&'static str> {
+    Some(match kind {
+        2 | 3 => "function",
+        4 => "constructor",
+        5 | 10 => "property",
+        6 => "variable",
+        7 | 22 => "class",
+        8 => "interface",
+        9 => "module",
+        12 => "value",
+        13 | 20 => "enum",
+        14 => "keyword",
+        21 => "constant",
+        _ => return None,
+    })
+}
+
+/// Parse a `textDocument/completion` result (either `CompletionItem[]` or a `CompletionList`).
+fn parse_completion_response(result: &Value) -> Vec<CompletionItem> {
+    let items = match result {
+        Value::Array(items) => items.as_slice(),
+        Value::Object(list) => list
+            .get("items")
+            .and_then(|v| v.as_array())
+            .map(|v| v.as_slice())
+            .unwrap_or_default(),
+        _ => &[],
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            let label = item.get("label")?.as_str()?.to_string();
+            Some(CompletionItem {
+                label,
+                kind: item
+                    .get("kind")
+                    .and_then(|k| k.as_u64())
+                    .and_then(completion_kind_name)
+                    .map(String::from),
+                detail: item
+                    .get("detail")
+                    .and_then(|d| d.as_str())
+                    .map(String::from),
+                source: Some("lsp".to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Extract plain text from a `textDocument/hover` result.
+fn parse_hover_response(result: &Value) -> Option<String> {
+    let contents = result.get("contents")?;
+    let text = match contents {
+        Value::String(s) => s.clone(),
+        Value::Object(markup) => markup.get("value")?.as_str()?.to_string(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| match p {
+                Value::String(s) => Some(s.clone()),
+                Value::Object(o) => o.get("value").and_then(|v| v.as_str()).map(String::from),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        _ => return None,
+    };
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// Merge kernel completions with language server completions.
+///
+/// Kernel items come first since they reflect live runtime state. When both
+/// sources offer the same label, the kernel item is kept and enriched with
+/// the server's `kind`/`detail`. Server-only items are appended.
+pub fn merge_completions(
+    kernel: Vec<CompletionItem>,
+    lsp: Vec<CompletionItem>,
+) -> Vec<CompletionItem> {
+    let mut lsp_by_label: HashMap<String, CompletionItem> = HashMap::new();
+    let mut lsp_order = Vec::new();
+    for item in lsp {
+        if !lsp_by_label.contains_key(&item.label) {
+            lsp_order.push(item.label.clone());
+            lsp_by_label.insert(item.label.clone(), item);
+        }
+    }
+
+    let mut merged = Vec::with_capacity(kernel.len() + lsp_order.len());
+    for mut item in kernel {
+        if let Some(static_item) = lsp_by_label.remove(&item.label) {
+            item.kind = item.kind.or(static_item.kind);
+            item.detail = item.detail.or(static_item.detail);
+        }
+        merged.push(item);
+    }
+    merged.extend(
+        lsp_order
+            .into_iter()
+            .filter_map(|label| lsp_by_label.remove(&label)),
+    );
+    merged
+}
+
+/// Compute the replacement range for server-only completions.
+///
+/// Kernels report `cursor_start`/`cursor_end`; when only the language server
+/// answered, the range covers the identifier immediately before the cursor.
+pub fn identifier_range(code: &str, cursor_pos: usize) -> (usize, usize) {
+    let before: Vec<char> = code.chars().take(cursor_pos).collect();
+    let start = before
+        .iter()
+        .rposition(|c| !(c.is_alphanumeric() || *c == '_'))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    (start, before.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{duplex, DuplexStream};
+
+    fn item(label: &str, source: &str) -> CompletionItem {
+        CompletionItem {
+            label: label.to_string(),
+            kind: None,
+            detail: None,
+            source: Some(source.to_string()),
+        }
+    }
+
+    async fn write_message(writer: &mut DuplexStream, message: &Value) {
+        let body = serde_json::to_vec(message).unwrap();
+        writer
+            .write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+            .await
+            .unwrap();
+        writer.write_all(&body).await.unwrap();
+    }
+
+    /// Spawn a mock language server. When `answer_completions` is false it
+    /// still completes the handshake but never answers completion requests.
+    fn spawn_mock_server(answer_completions: bool) -> (DuplexStream, DuplexStream) {
+        let (client_read, mut server_write) = duplex(64 * 1024);
+        let (server_read, client_write) = duplex(64 * 1024);
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(server_read);
+            while let Ok(Some(message)) = read_message(&mut reader).await {
+                let Some(id) = message.get("id").cloned() else {
+                    continue;
+                };
+                let result = match message["method"].as_str() {
+                    Some("initialize") => json!({ "capabilities": {} }),
+                    Some("textDocument/completion") if answer_completions => json!({
+                        "isIncomplete": false,
+                        "items": [
+                            { "label": "read_csv", "kind": 3, "detail": "def read_csv(...)" },
+                            { "label": "read_json", "kind": 3 }
+                        ]
+                    }),
+                    _ => continue,
+                };
+                write_message(
+                    &mut server_write,
+                    &json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                )
+                .await;
+            }
+        });
+        (client_read, client_write)
+    }
+
+    #[test]
+    fn test_merge_prefers_kernel_and_enriches() {
+        let kernel = vec![item("read_csv", "kernel"), item("df", "kernel")];
+        let mut lsp_item = item("read_csv", "lsp");
+        lsp_item.kind = Some("function".into());
+        lsp_item.detail = Some("def read_csv(...)".into());
+        let lsp = vec![lsp_item, item("read_json", "lsp")];
+
+        let merged = merge_completions(kernel, lsp);
+        let labels: Vec<_> = merged.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, vec!["read_csv", "df", "read_json"]);
+        assert_eq!(merged[0].source.as_deref(), Some("kernel"));
+        assert_eq!(merged[0].kind.as_deref(), Some("function"));
+        assert_eq!(merged[0].detail.as_deref(), Some("def read_csv(...)"));
+        assert_eq!(merged[2].source.as_deref(), Some("lsp"));
+    }
+
+    #[test]
+    fn test_merge_falls_back_to_kernel_only() {
+        let merged = merge_completions(vec![item("x", "kernel")], vec![]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].label, "x");
+    }
+
+    #[test]
+    fn test_lsp_position_and_identifier_range() {
+        let code = "import pandas as pd\npd.re";
+        assert_eq!(lsp_position(code, code.chars().count()), (1, 5));
+        assert_eq!(identifier_range(code, code.chars().count()), (23, 25));
+    }
+
+    #[tokio::test]
+    async fn test_mock_lsp_responding() {
+        let (reader, writer) = spawn_mock_server(true);
+        let client = LspClient::connect(reader, writer, Path::new("/tmp"), DEFAULT_REQUEST_TIMEOUT)
+            .await
+            .unwrap();
+
+        let lsp = client.complete("pd.re", 5).await.unwrap();
+        assert_eq!(lsp.len(), 2);
+        assert_eq!(lsp[0].kind.as_deref(), Some("function"));
+
+        let merged = merge_completions(vec![item("read_csv", "kernel")], lsp);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].detail.as_deref(), Some("def read_csv(...)"));
+    }
+
+    #[tokio::test]
+    async fn test_mock_lsp_not_responding_falls_back() {
+        let (reader, writer) = spawn_mock_server(false);
+        let client = LspClient::connect(
+            reader,
+            writer,
+            Path::new("/tmp"),
+            Duration::from_millis(100),
+        )
+        .await
+        .unwrap();
+
+        let lsp = client.complete("pd.re", 5).await;
+        assert!(lsp.is_err());
+
+        let kernel = vec![item("read_csv", "kernel")];
+        let merged = merge_completions(kernel, lsp.unwrap_or_default());
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].source.as_deref(), Some("kernel"));
+    }
+}
@@ -3,12 +3,15 @@
 //! This runs the runtime daemon as a standalone process that manages
 //! prewarmed Python environments for notebook windows.
 
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 use log::{error, info};
+use runtimed::blob_server::BlobServerConfig;
 use runtimed::client::PoolClient;
 use runtimed::daemon::{Daemon, DaemonConfig};
+use runtimed::log_format::LogFormat;
 use runtimed::service::ServiceManager;
 use runtimed::singleton::get_running_daemon_info;
 
@@ -55,6 +58,16 @@ enum Commands {
         /// Number of Conda environments to maintain
         #[arg(long, default_value = "3")]
         conda_pool_size: usize,
+
+        /// Address for the blob HTTP server, e.g. `0.0.0.0:8765` for a dev
+        /// container (default: 127.0.0.1 on a random port)
+        #[arg(long, value_parser = runtimed::blob_server::parse_bind_addr)]
+        blob_bind_addr: Option<SocketAddr>,
+
+        /// Bearer token required by the blob server (default: none; falls
+        /// back to $RUNTIMED_BLOB_AUTH_TOKEN)
+        #[arg(long)]
+        blob_auth_token: Option<String>,
     },
 
     /// Install daemon as a system service
@@ -139,6 +152,9 @@ async fn main() -> anyhow::Result<()> {
     let mut builder =
         env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(&cli.log_level));
 
+    // RUNTIMED_LOG_FORMAT=json switches to structured JSON lines
+    let log_format = LogFormat::from_env();
+
     // If we can open the log file, write to it; otherwise just use stderr
     if let Ok(file) = log_file {
         use std::io::Write;
@@ -146,13 +162,7 @@ async fn main() -> anyhow::Result<()> {
 
         let file = Arc::new(Mutex::new(file));
         builder.format(move |_buf, record| {
-            let formatted = format!(
-                "{} [{}] {}: {}\n",
-                chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
-                record.level(),
-                record.target(),
-                record.args()
-            );
+            let formatted = log_format.format_record(record);
             // Write to stderr (terminal)
             eprint!("{}", formatted);
             // Write to file
@@ -162,6 +172,11 @@ async fn main() -> anyhow::Result<()> {
             }
             Ok(())
         });
+    } else if log_format == LogFormat::Json {
+        builder.format(move |buf, record| {
+            use std::io::Write;
+            buf.write_all(log_format.format_record(record).as_bytes())
+        });
     }
     builder.init();
 
@@ -184,7 +199,7 @@ async fn main() -> anyhow::Result<()> {
     match cli.command {
         None | Some(Commands::Run { .. }) => {
             // Extract run args from command or use defaults
-            let (socket, cache_dir, blob_store_dir, uv_pool_size, conda_pool_size) =
+            let (socket, cache_dir, blob_store_dir, uv_pool_size, conda_pool_size, blob_args) =
                 match cli.command {
                     Some(Commands::Run {
                         socket,
@@ -192,22 +207,35 @@ async fn main() -> anyhow::Result<()> {
                         blob_store_dir,
                         uv_pool_size,
                         conda_pool_size,
+                        blob_bind_addr,
+                        blob_auth_token,
                     }) => (
                         socket,
                         cache_dir,
                         blob_store_dir,
                         uv_pool_size,
                         conda_pool_size,
+                        (blob_bind_addr, blob_auth_token),
                     ),
-                    _ => (None, None, None, 3, 3),
+                    _ => (None, None, None, 3, 3, (None, None)),
                 };
 
+            let (blob_bind_addr, blob_auth_token) = blob_args;
+            let defaults = BlobServerConfig::default();
+            let blob_server = BlobServerConfig {
+                bind_addr: blob_bind_addr.unwrap_or(defaults.bind_addr),
+                auth_token: blob_auth_token
+                    .or_else(|| std::env::var("RUNTIMED_BLOB_AUTH_TOKEN").ok())
+                    .filter(|token| !token.is_empty()),
+            };
+
             run_daemon(
                 socket,
                 cache_dir,
                 blob_store_dir,
                 uv_pool_size,
                 conda_pool_size,
+                blob_server,
             )
             .await
         }
@@ -248,6 +276,7 @@ async fn run_daemon(
     blob_store_dir: Option<PathBuf>,
     uv_pool_size: usize,
     conda_pool_size: usize,
+    blob_server: BlobServerConfig,
 ) -> anyhow::Result<()> {
     info!("runtimed starting...");
 
@@ -257,6 +286,7 @@ async fn run_daemon(
         blob_store_dir: blob_store_dir.unwrap_or_else(runtimed::default_blob_store_dir),
         uv_pool_size,
         conda_pool_size,
+        blob_server,
         ..Default::default()
     };
 
@@ -266,6 +296,15 @@ async fn run_daemon(
     info!("  Blob store: {:?}", config.blob_store_dir);
     info!("  UV pool size: {}", config.uv_pool_size);
     info!("  Conda pool size: {}", config.conda_pool_size);
+    info!(
+        "  Blob server: {}{}",
+        config.blob_server.bind_addr,
+        if config.blob_server.auth_token.is_some() {
+            " (token required)"
+        } else {
+            ""
+        }
+    );
 
     let daemon = match Daemon::new(config) {
         Ok(d) => d,
@@ -0,0 +1,186 @@
+//! Install suggestions for failed Python imports.
+//!
+//! When a cell fails with `ModuleNotFoundError: No module named 'seaborn'`,
+//! the kernel manager broadcasts a [`NotebookBroadcast::SuggestInstall`]
+//! naming the package that most likely provides the module, under both its
+//! PyPI and conda-forge names. The frontend offers to add the one matching
+//! the notebook's environment to its dependencies and sync the environment.
+//! The `hide_install_suggestions` setting turns the offer off.
+
+use crate::protocol::NotebookBroadcast;
+
+/// Import names whose PyPI package is named differently.
+const IMPORT_TO_PACKAGE: &[(&str, &str)] = &[
+    ("Bio", "biopython"),
+    ("Crypto", "pycryptodome"),
+    ("OpenSSL", "pyOpenSSL"),
+    ("PIL", "pillow"),
+    ("attr", "attrs"),
+    ("bs4", "beautifulsoup4"),
+    ("cv2", "opencv-python"),
+    ("dateutil", "python-dateutil"),
+    ("docx", "python-docx"),
+    ("dotenv", "python-dotenv"),
+    ("fitz", "pymupdf"),
+    ("jwt", "pyjwt"),
+    ("magic", "python-magic"),
+    ("pptx", "python-pptx"),
+    ("serial", "pyserial"),
+    ("skimage", "scikit-image"),
+    ("sklearn", "scikit-learn"),
+    ("yaml", "pyyaml"),
+    ("zmq", "pyzmq"),
+];
+
+/// Top-level module named by a "No module named ..." import error.
+///
+/// Returns `None` for other errors, including `ImportError`s raised for a
+/// missing name inside an installed module.
+pub fn missing_module(ename: &str, evalue: &str) -> Option<String> {
+    if ename != "ModuleNotFoundError" && ename != "ImportError" {
+        return None;
+    }
+    let rest = evalue.strip_prefix("No module named ")?;
+    let quote = rest.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+    let (module, trailer) = rest[1..].split_once(quote)?;
+    // "No module named 'a.b'; 'a' is not a package": `a` is installed
+    if !trailer.is_empty() {
+        return None;
+    }
+    let top_level = module.split('.').next()?;
+    is_identifier(top_level).then(|| top_level.to_string())
+}
+
+/// Whether `name` could be a Python module name.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// The PyPI package that most likely provides `module`.
+pub fn package_for_module(module: &str) -> String {
+    IMPORT_TO_PACKAGE
+        .iter()
+        .find(|(import, _)| *import == module)
+        .map(|(_, package)| package.to_string())
+        .unwrap_or_else(|| module.replace('_', "-"))
+}
+
+/// The conda-forge name of the PyPI package `package`.
+pub fn conda_package(package: &str) -> String {
+    let package = package.to_lowercase();
+    kernel_env::package_names::conda_rename(&package)
+        .map(String::from)
+        .unwrap_or(package)
+}
+
+/// Suggestion to install the package behind a cell's failed import, if the
+/// error was a missing module.
+pub fn suggest_install(cell_id: &str, ename: &str, evalue: &str) -> Option<NotebookBroadcast> {
+    let module = missing_module(ename, evalue)?;
+    let package = package_for_module(&module);
+    Some(NotebookBroadcast::SuggestInstall {
+        cell_id: cell_id.to_string(),
+        conda_package: conda_package(&package),
+        package,
+        module,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_module_from_error() {
+        assert_eq!(
+            missing_module("ModuleNotFoundError", "No module named 'seaborn'"),
+            Some("seaborn".to_string())
+        );
+        // Submodule imports suggest the top-level package
+        assert_eq!(
+            missing_module(
+                "ModuleNotFoundError",
+                "No module named 'sklearn.linear_model'"
+            ),
+            Some("sklearn".to_string())
+        );
+        // Missing names inside installed modules aren't install problems
+        assert_eq!(
+            missing_module(
+                "ImportError",
+                "cannot import name 'foo' from 'os' (/usr/lib/python3.12/os.py)"
+            ),
+            None
+        );
+        assert_eq!(missing_module("NameError", "name 'x' is not defined"), None);
+    }
+
+    #[test]
+    fn test_missing_module_rejects_malformed_values() {
+        assert_eq!(
+            missing_module("ModuleNotFoundError", "No module named \"seaborn\""),
+            Some("seaborn".to_string())
+        );
+        // The top-level module exists but isn't a package
+        assert_eq!(
+            missing_module(
+                "ModuleNotFoundError",
+                "No module named 'os.path.foo'; 'os.path' is not a package"
+            ),
+            None
+        );
+        assert_eq!(
+            missing_module("ModuleNotFoundError", "No module named seaborn"),
+            None
+        );
+        assert_eq!(
+            missing_module("ModuleNotFoundError", "No module named 'seaborn"),
+            None
+        );
+        assert_eq!(
+            missing_module("ModuleNotFoundError", "No module named '.utils'"),
+            None
+        );
+        assert_eq!(
+            missing_module("ModuleNotFoundError", "No module named 'a b'"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_package_for_module() {
+        assert_eq!(package_for_module("sklearn"), "scikit-learn");
+        assert_eq!(package_for_module("PIL"), "pillow");
+        assert_eq!(package_for_module("cv2"), "opencv-python");
+        assert_eq!(package_for_module("seaborn"), "seaborn");
+        assert_eq!(package_for_module("typing_extensions"), "typing-extensions");
+    }
+
+    #[test]
+    fn test_conda_package() {
+        assert_eq!(conda_package("opencv-python"), "opencv");
+        assert_eq!(conda_package("typing-extensions"), "typing_extensions");
+        assert_eq!(conda_package("pyOpenSSL"), "pyopenssl");
+        assert_eq!(conda_package("scikit-learn"), "scikit-learn");
+    }
+
+    #[test]
+    fn test_suggest_install_broadcast() {
+        let suggestion =
+            suggest_install("cell-1", "ModuleNotFoundError", "No module named 'cv2'").unwrap();
+        let json = serde_json::to_value(&suggestion).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "event": "suggest_install",
+                "cell_id": "cell-1",
+                "module": "cv2",
+                "package": "opencv-python",
+                "conda_package": "opencv",
+            })
+        );
+        assert!(suggest_install("cell-1", "ZeroDivisionError", "division by zero").is_none());
+    }
+}
@@ -0,0 +1,467 @@
+//! Scripted in-process Jupyter kernel for tests.
+//!
+//! [`MockKernel`] binds the kernel side of the shell, iopub and control
+//! sockets on localhost and answers each request from a [`MockScript`], so queueing,
+//! msg_id routing and lifecycle logic can be exercised without spawning a
+//! kernel process. Connect a [`RoomKernel`] to it with [`RoomKernel::attach`].
+//!
+//! Like a real kernel, every request is wrapped in busy/idle status messages
+//! on iopub and requests are answered one at a time, so a slow scripted
+//! execution holds up everything sent after it. Control requests such as
+//! `interrupt_request` and `debug_request` are answered separately, even
+//! mid-execution.
+//!
+//! Built for this crate's tests, and for other crates with the `mock-kernel`
+//! feature.
+//!
+//! [`RoomKernel`]: crate::kernel_manager::RoomKernel
+//! [`RoomKernel::attach`]: crate::kernel_manager::RoomKernel::attach
+
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use jupyter_protocol::{
+    CompleteReply, ConnectionInfo, DebugReply, ErrorOutput, ExecuteInput, ExecuteReply,
+    ExecuteResult, ExecutionCount, InspectReply, InterruptReply, JupyterMessage,
+    JupyterMessageContent, KernelInfoReply, LanguageInfo, Media, MediaType, ReplyError,
+    ReplyStatus, ShutdownReply, Status, StreamContent,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// The scripted outcome of running one piece of code.
+#[derive(Debug, Clone, Default)]
+pub struct MockExecution {
+    outputs: Vec<JupyterMessageContent>,
+    error: Option<ErrorOutput>,
+    delay: Duration,
+}
+
+impl MockExecution {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Print `text` to stdout.
+    pub fn stdout(self, text: &str) -> Self {
+        self.output(StreamContent::stdout(text))
+    }
+
+    /// Print `text` to stderr.
+    pub fn stderr(self, text: &str) -> Self {
+        self.output(StreamContent::stderr(text))
+    }
+
+    /// Produce an `execute_result` with `text/plain` data. Its execution
+    /// count is filled in when the code runs.
+    pub fn result(self, text: &str) -> Self {
+        self.output(ExecuteResult::new(
+            ExecutionCount::new(0),
+            Media::new(vec![MediaType::Plain(text.to_string())]),
+        ))
+    }
+
+    /// Publish any iopub content, in order with the other outputs.
+    pub fn output(mut self, content: impl Into<JupyterMessageContent>) -> Self {
+        self.outputs.push(content.into());
+        self
+    }
+
+    /// Raise `ename: evalue` after the outputs: publishes an error output
+    /// and replies with status "error".
+    pub fn error(mut self, ename: &str, evalue: &str) -> Self {
+        self.error = Some(ErrorOutput {
+            ename: ename.to_string(),
+            evalue: evalue.to_string(),
+            traceback: vec![format!("{ename}: {evalue}")],
+        });
+        self
+    }
+
+    /// Keep running for `delay` before producing any output.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+/// Replies for a [`MockKernel`], keyed by the exact code of each request.
+///
+/// Code without a scripted execution runs silently and succeeds; completion
+/// and inspection of unscripted code find nothing. Unscripted debug commands
+/// fail.
+#[derive(Debug, Clone, Default)]
+pub struct MockScript {
+    language: Option<String>,
+    debugger: bool,
+    executions: HashMap<String, MockExecution>,
+    completions: HashMap<String, Vec<String>>,
+    inspections: HashMap<String, String>,
+    debug_responses: HashMap<String, Value>,
+}
+
+impl MockScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report `language` in `kernel_info_reply` (default "python").
+    pub fn language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    /// Report `debugger: true` in `kernel_info_reply`.
+    pub fn debugger(mut self) -> Self {
+        self.debugger = true;
+        self
+    }
+
+    /// Answer the Debug Adapter Protocol request `command` with a successful
+    /// response carrying `body`.
+    pub fn on_debug(mut self, command: &str, body: Value) -> Self {
+        self.debug_responses.insert(command.to_string(), body);
+        self
+    }
+
+    /// Run `code` as `execution` describes.
+    pub fn on_execute(mut self, code: &str, execution: MockExecution) -> Self {
+        self.executions.insert(code.to_string(), execution);
+        self
+    }
+
+    /// Offer `matches` for the identifier before the cursor in `code`.
+    pub fn on_complete(mut self, code: &str, matches: &[&str]) -> Self {
+        self.completions.insert(
+            code.to_string(),
+            matches.iter().map(|m| m.to_string()).collect(),
+        );
+        self
+    }
+
+    /// Answer inspection of `code` with `text` as `text/plain`.
+    pub fn on_inspect(mut self, code: &str, text: &str) -> Self {
+        self.inspections.insert(code.to_string(), text.to_string());
+        self
+    }
+
+    fn kernel_info(&self) -> KernelInfoReply {
+        let language = self.language.as_deref().unwrap_or("python");
+        KernelInfoReply {
+            status: ReplyStatus::Ok,
+            protocol_version: "5.3".to_string(),
+            implementation: "mock".to_string(),
+            implementation_version: env!("CARGO_PKG_VERSION").to_string(),
+            language_info: LanguageInfo {
+                name: language.to_string(),
+                version: String::new(),
+                mimetype: None,
+                file_extension: None,
+                pygments_lexer: None,
+                codemirror_mode: None,
+                nbconvert_exporter: None,
+            },
+            banner: String::new(),
+            help_links: vec![],
+            debugger: self.debugger,
+            error: None,
+        }
+    }
+
+    /// The DAP response to the request in a `debug_request`.
+    fn debug_response(&self, request: &Value) -> Value {
+        let command = request.get("command").and_then(Value::as_str).unwrap_or("");
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request.get("seq").cloned().unwrap_or(Value::Null),
+            "seq": 0,
+            "command": command,
+        });
+        match self.debug_responses.get(command) {
+            Some(body) => {
+                response["success"] = json!(true);
+                response["body"] = body.clone();
+            }
+            None => {
+                response["success"] = json!(false);
+                response["message"] = json!(format!("unscripted command {command:?}"));
+            }
+        }
+        response
+    }
+}
+
+/// An in-process kernel answering requests from a [`MockScript`].
+///
+/// Serves until it receives a `shutdown_request` or is dropped.
+pub struct MockKernel {
+    connection_info: ConnectionInfo,
+    received: Arc<Mutex<Vec<JupyterMessage>>>,
+    received_control: Arc<Mutex<Vec<JupyterMessage>>>,
+    task: tokio::task::JoinHandle<()>,
+    control_task: tokio::task::JoinHandle<()>,
+}
+
+impl MockKernel {
+    /// Bind the kernel's sockets on free localhost ports and start serving.
+    pub async fn start(script: MockScript) -> Result<Self> {
+        let ip = std::net::IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let ports = runtimelib::peek_ports(ip, 5).await?;
+        let connection_info = ConnectionInfo {
+            transport: jupyter_protocol::connection_info::Transport::TCP,
+            ip: ip.to_string(),
+            stdin_port: ports[0],
+            control_port: ports[1],
+            hb_port: ports[2],
+            shell_port: ports[3],
+            iopub_port: ports[4],
+            signature_scheme: "hmac-sha256".to_string(),
+            key: Uuid::new_v4().to_string(),
+            kernel_name: Some("mock".to_string()),
+        };
+
+        let session_id = Uuid::new_v4().to_string();
+        let iopub =
+            runtimelib::create_kernel_iopub_connection(&connection_info, &session_id).await?;
+        let shell =
+            runtimelib::create_kernel_shell_connection(&connection_info, &session_id).await?;
+        let control =
+            runtimelib::create_kernel_control_connection(&connection_info, &session_id).await?;
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let task = tokio::spawn(serve(script.clone(), iopub, shell, received.clone()));
+        let received_control = Arc::new(Mutex::new(Vec::new()));
+        let control_task = tokio::spawn(serve_control(
+            script.clone(),
+            control,
+            received_control.clone(),
+        ));
+
+        Ok(Self {
+            connection_info,
+            received,
+            received_control,
+            task,
+            control_task,
+        })
+    }
+
+    /// Where a client should connect.
+    pub fn connection_info(&self) -> &ConnectionInfo {
+        &self.connection_info
+    }
+
+    /// Every shell request received so far, in arrival order.
+    pub fn received(&self) -> Vec<JupyterMessage> {
+        self.received.lock().unwrap().clone()
+    }
+
+    /// Every control request received so far, in arrival order.
+    pub fn received_control(&self) -> Vec<JupyterMessage> {
+        self.received_control.lock().unwrap().clone()
+    }
+
+    /// Whether the kernel has stopped serving.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl Drop for MockKernel {
+    fn drop(&mut self) {
+        self.task.abort();
+        self.control_task.abort();
+    }
+}
+
+async fn serve(
+    script: MockScript,
+    mut iopub: runtimelib::KernelIoPubConnection,
+    mut shell: runtimelib::KernelShellConnection,
+    received: Arc<Mutex<Vec<JupyterMessage>>>,
+) {
+    let mut execution_count = 0;
+    while let Ok(request) = shell.read().await {
+        received.lock().unwrap().push(request.clone());
+        let shutdown = matches!(request.content, JupyterMessageContent::ShutdownRequest(_));
+        let answered = answer(
+            &script,
+            &mut iopub,
+            &mut shell,
+            &request,
+            &mut execution_count,
+        )
+        .await;
+        if answered.is_err() || shutdown {
+            break;
+        }
+    }
+}
+
+/// Record control requests, acknowledging interrupts and answering debug
+/// requests from the script. Nothing is actually interrupted; tests check
+/// what arrived.
+async fn serve_control(
+    script: MockScript,
+    mut control: runtimelib::KernelControlConnection,
+    received: Arc<Mutex<Vec<JupyterMessage>>>,
+) {
+    while let Ok(request) = control.read().await {
+        received.lock().unwrap().push(request.clone());
+        let shutdown = matches!(request.content, JupyterMessageContent::ShutdownRequest(_));
+        let reply: JupyterMessageContent = match &request.content {
+            JupyterMessageContent::InterruptRequest(_) => InterruptReply::default().into(),
+            JupyterMessageContent::DebugRequest(debug) => DebugReply {
+                content: script.debug_response(&debug.content),
+            }
+            .into(),
+            JupyterMessageContent::ShutdownRequest(shutdown) => ShutdownReply {
+                restart: shutdown.restart,
+                ..Default::default()
+            }
+            .into(),
+            _ => continue,
+        };
+        if control
+            .send(JupyterMessage::new(reply, Some(&request)))
+            .await
+            .is_err()
+            || shutdown
+        {
+            break;
+        }
+    }
+}
+
+/// Reply to one shell request, bracketed by busy/idle status on iopub.
+async fn answer(
+    script: &MockScript,
+    iopub: &mut runtimelib::KernelIoPubConnection,
+    shell: &mut runtimelib::KernelShellConnection,
+    request: &JupyterMessage,
+    execution_count: &mut usize,
+) -> Result<()> {
+    iopub
+        .send(JupyterMessage::new(Status::busy(), Some(request)))
+        .await?;
+
+    let reply: Option<JupyterMessageContent> = match &request.content {
+        JupyterMessageContent::KernelInfoRequest(_) => Some(script.kernel_info().into()),
+        JupyterMessageContent::ExecuteRequest(execute) => {
+            if !execute.silent {
+                *execution_count += 1;
+                iopub
+                    .send(JupyterMessage::new(
+                        ExecuteInput {
+                            code: execute.code.clone(),
+                            execution_count: ExecutionCount::new(*execution_count),
+                        },
+                        Some(request),
+                    ))
+                    .await?;
+            }
+
+            let execution = script.executions.get(&execute.code);
+            let mut error = None;
+            if let Some(execution) = execution {
+                tokio::time::sleep(execution.delay).await;
+                for output in &execution.outputs {
+                    let mut output = output.clone();
+                    if let JupyterMessageContent::ExecuteResult(ref mut result) = output {
+                        result.execution_count = ExecutionCount::new(*execution_count);
+                    }
+                    iopub
+                        .send(JupyterMessage::new(output, Some(request)))
+                        .await?;
+                }
+                if let Some(err) = &execution.error {
+                    iopub
+                        .send(JupyterMessage::new(err.clone(), Some(request)))
+                        .await?;
+                    error = Some(Box::new(ReplyError {
+                        ename: err.ename.clone(),
+                        evalue: err.evalue.clone(),
+                        traceback: err.traceback.clone(),
+                    }));
+                }
+            }
+
+            Some(
+                ExecuteReply {
+                    status: if error.is_some() {
+                        ReplyStatus::Error
+                    } else {
+                        ReplyStatus::Ok
+                    },
+                    execution_count: ExecutionCount::new(*execution_count),
+                    payload: vec![],
+                    user_expressions: None,
+                    error,
+                }
+                .into(),
+            )
+        }
+        JupyterMessageContent::CompleteRequest(complete) => {
+            let matches = script
+                .completions
+                .get(&complete.code)
+                .cloned()
+                .unwrap_or_default();
+            let cursor_start = word_start(&complete.code, complete.cursor_pos);
+            Some(
+                CompleteReply {
+                    matches,
+                    cursor_start,
+                    cursor_end: complete.cursor_pos,
+                    ..Default::default()
+                }
+                .into(),
+            )
+        }
+        JupyterMessageContent::InspectRequest(inspect) => {
+            let reply = match script.inspections.get(&inspect.code) {
+                Some(text) => InspectReply {
+                    found: true,
+                    data: Media::new(vec![MediaType::Plain(text.clone())]),
+                    ..Default::default()
+                },
+                None => InspectReply::default(),
+            };
+            Some(reply.into())
+        }
+        JupyterMessageContent::ShutdownRequest(shutdown) => Some(
+            ShutdownReply {
+                restart: shutdown.restart,
+                ..Default::default()
+            }
+            .into(),
+        ),
+        // Unscripted requests go unanswered, as with a kernel that doesn't
+        // implement them
+        _ => None,
+    };
+
+    if let Some(reply) = reply {
+        shell
+            .send(JupyterMessage::new(reply, Some(request)))
+            .await?;
+    }
+    iopub
+        .send(JupyterMessage::new(Status::idle(), Some(request)))
+        .await?;
+    Ok(())
+}
+
+/// Character offset where the identifier ending at `cursor_pos` starts.
+fn word_start(code: &str, cursor_pos: usize) -> usize {
+    let before: Vec<char> = code.chars().take(cursor_pos).collect();
+    let word = before
+        .iter()
+        .rev()
+        .take_while(|c| c.is_alphanumeric() || **c == '_')
+        .count();
+    before.len() - word
+}
@@ -16,6 +16,7 @@
 //!       cell_type: Str            ← "code" | "markdown" | "raw"
 //!       source: Text              ← Automerge Text CRDT (character-level merging)
 //!       execution_count: Str      ← JSON-encoded i32 or "null"
+//!       execution: Str            ← JSON-encoded CellExecutionTiming (absent until run)
 //!       outputs/                  ← List of Str
 //!         [j]: Str                ← JSON-encoded Jupyter output (Phase 5: manifest hash)
 //!   metadata/                     ← Map
@@ -28,7 +29,7 @@ use std::path::Path;
 use automerge::sync;
 use automerge::sync::SyncDoc;
 use automerge::transaction::Transactable;
-use automerge::{AutoCommit, AutomergeError, ObjId, ObjType, ReadDoc};
+use automerge::{AutoCommit, AutomergeError, ChangeHash, ObjId, ObjType, ReadDoc};
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
 
@@ -45,11 +46,46 @@ pub struct CellSnapshot {
     pub execution_count: String,
     /// JSON-encoded Jupyter output objects (will become manifest hashes in Phase 5)
     pub outputs: Vec<String>,
+    /// Timing of the most recent execution, if the cell has run in this session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution: Option<CellExecutionTiming>,
+    /// nbformat `collapsed`: the cell's outputs are hidden
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collapsed: Option<bool>,
+    /// nbformat `scrolled`: the cell's outputs scroll in a fixed-height area
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scrolled: Option<bool>,
+    /// Indices of individual outputs the user collapsed, ascending
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collapsed_outputs: Vec<usize>,
+    /// nbformat cell `tags`, e.g. `skip-execution` (see [`crate::cell_tags`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
+/// Timing of a cell's most recent execution, from the kernel's
+/// `execute_input` to its `status: idle` for the same request.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CellExecutionTiming {
+    /// RFC 3339 time the kernel began executing the cell
+    pub started_at: String,
+    /// RFC 3339 time the kernel went idle after the cell
+    pub completed_at: String,
+    pub duration_ms: u64,
+}
+
+/// Number of changes after which an idle room's history is dropped.
+///
+/// Every keystroke in a cell is a change, so long editing sessions cross
+/// this quickly; the compacted doc holds only the current state.
+pub const COMPACT_CHANGE_THRESHOLD: usize = 2000;
+
 /// Wrapper around an Automerge document storing a notebook.
 pub struct NotebookDoc {
     doc: AutoCommit,
+    /// Changes counted so far, up to `counted_heads`
+    change_count: usize,
+    counted_heads: Vec<ChangeHash>,
 }
 
 impl NotebookDoc {
@@ -67,13 +103,21 @@ impl NotebookDoc {
             let _ = doc.put(&meta_id, "runtime", "python");
         }
 
-        Self { doc }
+        Self::from_doc(doc)
+    }
+
+    fn from_doc(doc: AutoCommit) -> Self {
+        Self {
+            doc,
+            change_count: 0,
+            counted_heads: Vec::new(),
+        }
     }
 
     /// Load a notebook document from saved bytes.
     pub fn load(data: &[u8]) -> Result<Self, AutomergeError> {
         let doc = AutoCommit::load(data)?;
-        Ok(Self { doc })
+        Ok(Self::from_doc(doc))
     }
 
     /// Load from file or create a new document if the file doesn't exist.
@@ -87,7 +131,7 @@ impl NotebookDoc {
                 Ok(data) => match AutoCommit::load(&data) {
                     Ok(doc) => {
                         info!("[notebook-doc] Loaded from {:?} for {}", path, notebook_id);
-                        return Self { doc };
+                        return Self::from_doc(doc);
                     }
                     Err(e) => {
                         warn!(
@@ -146,6 +190,42 @@ impl NotebookDoc {
         std::fs::write(path, data)
     }
 
+    // ── Compaction ──────────────────────────────────────────────────
+
+    /// Number of changes in the document's history.
+    ///
+    /// Only changes made since the last call are walked, so checking after
+    /// every write stays cheap however long the history grows.
+    pub fn change_count(&mut self) -> usize {
+        let heads = self.doc.get_heads();
+        if heads != self.counted_heads {
+            self.change_count += self.doc.get_changes(&self.counted_heads).len();
+            self.counted_heads = heads;
+        }
+        self.change_count
+    }
+
+    /// Whether the history has grown past [`COMPACT_CHANGE_THRESHOLD`].
+    pub fn needs_compaction(&mut self) -> bool {
+        self.change_count() > COMPACT_CHANGE_THRESHOLD
+    }
+
+    /// Build a fresh document holding the current state without history.
+    ///
+    /// The result shares no history with existing replicas, so it must only
+    /// replace a doc that no connected peer is syncing against.
+    pub fn compacted(&self) -> Result<Self, AutomergeError> {
+        let mut doc = AutoCommit::new();
+        copy_object(&self.doc, &automerge::ROOT, &mut doc, &automerge::ROOT)?;
+        Ok(Self::from_doc(doc))
+    }
+
+    /// Replace the document with its [`compacted`](Self::compacted) form.
+    pub fn compact(&mut self) -> Result<(), AutomergeError> {
+        *self = self.compacted()?;
+        Ok(())
+    }
+
     // ── Notebook ID ─────────────────────────────────────────────────
 
     /// Read the notebook ID from the document.
@@ -259,6 +339,32 @@ impl NotebookDoc {
         Ok(true)
     }
 
+    /// Apply a single edit to a cell's source: delete `delete_count`
+    /// characters at `index`, then insert `text` there.
+    ///
+    /// Positions count Unicode code points. Unlike [`Self::update_source`],
+    /// this needs neither the full source nor a diff, so the cost of an
+    /// edit doesn't grow with the size of the cell.
+    pub fn splice_source(
+        &mut self,
+        cell_id: &str,
+        index: usize,
+        delete_count: usize,
+        text: &str,
+    ) -> Result<bool, AutomergeError> {
+        let source_id = match self
+            .cell_obj(cell_id)
+            .and_then(|cell_obj| self.text_id(&cell_obj, "source"))
+        {
+            Some(id) => id,
+            None => return Ok(false),
+        };
+
+        self.doc
+            .splice_text(&source_id, index, delete_count as isize, text)?;
+        Ok(true)
+    }
+
     // ── Output management ───────────────────────────────────────────
 
     /// Replace all outputs for a cell.
@@ -280,8 +386,12 @@ impl NotebookDoc {
             None => return Ok(false),
         };
 
-        // Delete existing outputs and create fresh list
+        // Delete existing outputs and create fresh list; per-output
+        // collapse flags no longer point at the same outputs
         let _ = self.doc.delete(&cell_obj, "outputs");
+        if self.list_id(&cell_obj, "collapsed_outputs").is_some() {
+            self.doc.delete(&cell_obj, "collapsed_outputs")?;
+        }
         let list_id = self.doc.put_object(&cell_obj, "outputs", ObjType::List)?;
         for (i, output) in outputs.iter().enumerate() {
             self.doc.insert(&list_id, i, output.as_str())?;
@@ -458,6 +568,23 @@ impl NotebookDoc {
         self.set_outputs(cell_id, &[])
     }
 
+    /// Clear the outputs and execution count of every code cell.
+    ///
+    /// Returns each code cell's ID with the outputs it had, in notebook order.
+    pub fn clear_all_outputs(&mut self) -> Result<Vec<(String, Vec<String>)>, AutomergeError> {
+        let mut cleared = Vec::new();
+        for cell in self.get_cells() {
+            if cell.cell_type != "code" {
+                continue;
+            }
+            self.clear_outputs(&cell.id)?;
+            self.set_execution_count(&cell.id, "null")?;
+            self.clear_execution_timing(&cell.id)?;
+            cleared.push((cell.id, cell.outputs));
+        }
+        Ok(cleared)
+    }
+
     /// Get all outputs from all cells.
     ///
     /// Returns a list of (cell_id, output_index, output_string).
@@ -574,6 +701,76 @@ impl NotebookDoc {
         Ok(true)
     }
 
+    // ── Execution timing ────────────────────────────────────────────
+
+    /// Record the timing of a cell's most recent execution.
+    pub fn set_execution_timing(
+        &mut self,
+        cell_id: &str,
+        timing: &CellExecutionTiming,
+    ) -> Result<bool, AutomergeError> {
+        let Some(cell_obj) = self.cell_obj(cell_id) else {
+            return Ok(false);
+        };
+        let json = serde_json::to_string(timing).unwrap_or_default();
+        self.doc.put(&cell_obj, "execution", json)?;
+        Ok(true)
+    }
+
+    /// Remove a cell's recorded execution timing.
+    pub fn clear_execution_timing(&mut self, cell_id: &str) -> Result<bool, AutomergeError> {
+        let Some(cell_obj) = self.cell_obj(cell_id) else {
+            return Ok(false);
+        };
+        if self.doc.get(&cell_obj, "execution")?.is_some() {
+            self.doc.delete(&cell_obj, "execution")?;
+        }
+        Ok(true)
+    }
+
+    /// Execution timings of every cell that has one, in notebook order.
+    pub fn get_execution_timings(&self) -> Vec<(String, CellExecutionTiming)> {
+        self.get_cells()
+            .into_iter()
+            .filter_map(|cell| Some((cell.id, cell.execution?)))
+            .collect()
+    }
+
+    // ── Output layout ───────────────────────────────────────────────
+
+    /// Set or clear a cell's nbformat `collapsed` or `scrolled` flag.
+    ///
+    /// `None` removes the flag so the frontend falls back to its default.
+    pub fn set_cell_flag(
+        &mut self,
+        cell_id: &str,
+        key: &str,
+        value: Option<bool>,
+    ) -> Result<bool, AutomergeError> {
+        set_cell_flag_in_doc(&mut self.doc, cell_id, key, value)
+    }
+
+    /// Collapse or expand a single output of a cell.
+    ///
+    /// The flags are reset whenever the cell's outputs are replaced.
+    pub fn set_output_collapsed(
+        &mut self,
+        cell_id: &str,
+        output_index: usize,
+        collapsed: bool,
+    ) -> Result<bool, AutomergeError> {
+        set_output_collapsed_in_doc(&mut self.doc, cell_id, output_index, collapsed)
+    }
+
+    /// Replace a cell's tags.
+    pub fn set_cell_tags(
+        &mut self,
+        cell_id: &str,
+        tags: &[String],
+    ) -> Result<bool, AutomergeError> {
+        set_cell_tags_in_doc(&mut self.doc, cell_id, tags)
+    }
+
     // ── Metadata ────────────────────────────────────────────────────
 
     /// Read a metadata value.
@@ -662,6 +859,12 @@ impl NotebookDoc {
         None
     }
 
+    fn cell_obj(&self, cell_id: &str) -> Option<ObjId> {
+        let cells_id = self.cells_list_id()?;
+        let idx = self.find_cell_index(&cells_id, cell_id)?;
+        self.cell_at_index(&cells_id, idx)
+    }
+
     fn text_id(&self, parent: &ObjId, key: &str) -> Option<ObjId> {
         self.doc
             .get(parent, key)
@@ -713,12 +916,108 @@ impl NotebookDoc {
             source,
             execution_count,
             outputs,
+            execution: read_execution_timing(&self.doc, cell_obj),
+            collapsed: read_bool(&self.doc, cell_obj, "collapsed"),
+            scrolled: read_bool(&self.doc, cell_obj, "scrolled"),
+            collapsed_outputs: read_collapsed_outputs(&self.doc, cell_obj),
+            tags: read_tags(&self.doc, cell_obj),
         })
     }
 }
 
 // ── Free helpers ─────────────────────────────────────────────────────
 
+/// Recursively copy the contents of `from` in `src` into `to` in `dst`.
+fn copy_object(
+    src: &AutoCommit,
+    from: &ObjId,
+    dst: &mut AutoCommit,
+    to: &ObjId,
+) -> Result<(), AutomergeError> {
+    match src.object_type(from)? {
+        ObjType::Map | ObjType::Table => {
+            for key in src.keys(from) {
+                match src.get(from, key.as_str())? {
+                    Some((automerge::Value::Object(obj_type), child)) => {
+                        let copy = dst.put_object(to, key.as_str(), obj_type)?;
+                        copy_object(src, &child, dst, &copy)?;
+                    }
+                    Some((automerge::Value::Scalar(value), _)) => {
+                        dst.put(to, key.as_str(), value.into_owned())?;
+                    }
+                    None => {}
+                }
+            }
+        }
+        ObjType::List => {
+            for index in 0..src.length(from) {
+                match src.get(from, index)? {
+                    Some((automerge::Value::Object(obj_type), child)) => {
+                        let copy = dst.insert_object(to, index, obj_type)?;
+                        copy_object(src, &child, dst, &copy)?;
+                    }
+                    Some((automerge::Value::Scalar(value), _)) => {
+                        dst.insert(to, index, value.into_owned())?;
+                    }
+                    None => {}
+                }
+            }
+        }
+        ObjType::Text => {
+            dst.splice_text(to, 0, 0, &src.text(from)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read a cell's execution timing, ignoring values that don't parse.
+fn read_execution_timing(doc: &AutoCommit, cell_obj: &ObjId) -> Option<CellExecutionTiming> {
+    read_str(doc, cell_obj, "execution").and_then(|json| serde_json::from_str(&json).ok())
+}
+
+/// Read a cell's `collapsed` or `scrolled` flag.
+fn read_bool(doc: &AutoCommit, cell_obj: &ObjId, key: &str) -> Option<bool> {
+    match doc.get(cell_obj, key).ok().flatten()? {
+        (automerge::Value::Scalar(s), _) => match s.as_ref() {
+            automerge::ScalarValue::Boolean(b) => Some(*b),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Read the indices of a cell's collapsed outputs.
+fn read_collapsed_outputs(doc: &AutoCommit, cell_obj: &ObjId) -> Vec<usize> {
+    let Some((automerge::Value::Object(ObjType::List), list_id)) =
+        doc.get(cell_obj, "collapsed_outputs").ok().flatten()
+    else {
+        return vec![];
+    };
+    let mut indices: Vec<usize> = (0..doc.length(&list_id))
+        .filter_map(|i| match doc.get(&list_id, i).ok().flatten()? {
+            (automerge::Value::Scalar(s), _) => match s.as_ref() {
+                automerge::ScalarValue::Uint(n) => Some(*n as usize),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    // Concurrent collapses of the same output merge as duplicates
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
+
+/// Read a cell's tags.
+fn read_tags(doc: &AutoCommit, cell_obj: &ObjId) -> Vec<String> {
+    match doc.get(cell_obj, "tags").ok().flatten() {
+        Some((automerge::Value::Object(ObjType::List), list_id)) => (0..doc.length(&list_id))
+            .filter_map(|i| read_str(doc, &list_id, i))
+            .collect(),
+        _ => vec![],
+    }
+}
+
 /// Read a scalar string from any Automerge object by key.
 fn read_str<O: AsRef<automerge::ObjId>, P: Into<automerge::Prop>>(
     doc: &AutoCommit,
@@ -781,6 +1080,103 @@ pub fn set_metadata_in_doc(
     Ok(())
 }
 
+/// Find a cell's map by ID in a raw `AutoCommit` document.
+fn find_cell_obj(doc: &AutoCommit, cell_id: &str) -> Option<ObjId> {
+    let cells_id = match doc.get(automerge::ROOT, "cells").ok().flatten()? {
+        (automerge::Value::Object(ObjType::List), id) => id,
+        _ => return None,
+    };
+    (0..doc.length(&cells_id)).find_map(|i| match doc.get(&cells_id, i).ok().flatten()? {
+        (automerge::Value::Object(ObjType::Map), cell_obj)
+            if read_str(doc, &cell_obj, "id").as_deref() == Some(cell_id) =>
+        {
+            Some(cell_obj)
+        }
+        _ => None,
+    })
+}
+
+/// Set or clear a cell's `collapsed` or `scrolled` flag in a raw
+/// `AutoCommit` document. Free-function counterpart of
+/// `NotebookDoc::set_cell_flag`.
+pub fn set_cell_flag_in_doc(
+    doc: &mut AutoCommit,
+    cell_id: &str,
+    key: &str,
+    value: Option<bool>,
+) -> Result<bool, AutomergeError> {
+    let Some(cell_obj) = find_cell_obj(doc, cell_id) else {
+        return Ok(false);
+    };
+    match value {
+        Some(value) => doc.put(&cell_obj, key, value)?,
+        None => {
+            if doc.get(&cell_obj, key)?.is_some() {
+                doc.delete(&cell_obj, key)?;
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Collapse or expand one output of a cell in a raw `AutoCommit` document.
+/// Free-function counterpart of `NotebookDoc::set_output_collapsed`.
+pub fn set_output_collapsed_in_doc(
+    doc: &mut AutoCommit,
+    cell_id: &str,
+    output_index: usize,
+    collapsed: bool,
+) -> Result<bool, AutomergeError> {
+    let Some(cell_obj) = find_cell_obj(doc, cell_id) else {
+        return Ok(false);
+    };
+    let list_id = match doc.get(&cell_obj, "collapsed_outputs")? {
+        Some((automerge::Value::Object(ObjType::List), id)) => id,
+        _ => doc.put_object(&cell_obj, "collapsed_outputs", ObjType::List)?,
+    };
+    // Remove every entry for the index (concurrent collapses can leave
+    // duplicates), then add one back if collapsing
+    for i in (0..doc.length(&list_id)).rev() {
+        if let Some((automerge::Value::Scalar(s), _)) = doc.get(&list_id, i)? {
+            if matches!(s.as_ref(), automerge::ScalarValue::Uint(n) if *n == output_index as u64) {
+                doc.delete(&list_id, i)?;
+            }
+        }
+    }
+    if collapsed {
+        let len = doc.length(&list_id);
+        doc.insert(
+            &list_id,
+            len,
+            automerge::ScalarValue::Uint(output_index as u64),
+        )?;
+    }
+    Ok(true)
+}
+
+/// Replace a cell's tags in a raw `AutoCommit` document. An empty list
+/// removes them. Free-function counterpart of `NotebookDoc::set_cell_tags`.
+pub fn set_cell_tags_in_doc(
+    doc: &mut AutoCommit,
+    cell_id: &str,
+    tags: &[String],
+) -> Result<bool, AutomergeError> {
+    let Some(cell_obj) = find_cell_obj(doc, cell_id) else {
+        return Ok(false);
+    };
+    if tags.is_empty() {
+        if doc.get(&cell_obj, "tags")?.is_some() {
+            doc.delete(&cell_obj, "tags")?;
+        }
+        return Ok(true);
+    }
+    let list_id = doc.put_object(&cell_obj, "tags", ObjType::List)?;
+    for (i, tag) in tags.iter().enumerate() {
+        doc.insert(&list_id, i, tag.as_str())?;
+    }
+    Ok(true)
+}
+
 /// Compute a safe filename for persisting a notebook document.
 ///
 /// Hashes the notebook_id (which could be a file path with special characters)
@@ -837,6 +1233,11 @@ pub fn get_cells_from_doc(doc: &AutoCommit) -> Vec<CellSnapshot> {
                 source,
                 execution_count,
                 outputs,
+                execution: read_execution_timing(doc, &cell_obj),
+                collapsed: read_bool(doc, &cell_obj, "collapsed"),
+                scrolled: read_bool(doc, &cell_obj, "scrolled"),
+                collapsed_outputs: read_collapsed_outputs(doc, &cell_obj),
+                tags: read_tags(doc, &cell_obj),
             })
         })
         .collect()
@@ -855,6 +1256,84 @@ mod tests {
         assert_eq!(doc.get_metadata("runtime"), Some("python".to_string()));
     }
 
+    #[test]
+    fn test_compaction_preserves_cells_and_shrinks_doc() {
+        let mut doc = NotebookDoc::new("nb1");
+        doc.add_cell(0, "c1", "code").unwrap();
+        doc.add_cell(1, "c2", "markdown").unwrap();
+        doc.set_metadata("runtime", "deno").unwrap();
+        doc.set_execution_count("c1", "3").unwrap();
+        doc.append_output(
+            "c1",
+            r#"{"output_type":"stream","name":"stdout","text":"hi"}"#,
+        )
+        .unwrap();
+
+        // Simulate a long session: the cell is edited and re-run many times,
+        // each run replacing its outputs
+        for i in 0..200 {
+            doc.update_source("c1", &format!("x = {}", i)).unwrap();
+            let output = format!(
+                r#"{{"output_type":"stream","name":"stdout","text":"run {}"}}"#,
+                i
+            );
+            doc.set_outputs("c1", &[output]).unwrap();
+            doc.doc.commit();
+        }
+        doc.update_source("c1", "x = 1").unwrap();
+        doc.update_source("c2", "# Title").unwrap();
+        assert!(doc.change_count() > 200);
+
+        let full = doc.save();
+        let compacted_bytes = doc.compacted().unwrap().save();
+        assert!(
+            compacted_bytes.len() < full.len() / 4,
+            "compacted {} bytes vs full {} bytes",
+            compacted_bytes.len(),
+            full.len()
+        );
+
+        let mut reloaded = NotebookDoc::load(&compacted_bytes).unwrap();
+        assert_eq!(reloaded.get_cells(), doc.get_cells());
+        assert_eq!(reloaded.notebook_id(), Some("nb1".to_string()));
+        assert_eq!(reloaded.get_metadata("runtime"), Some("deno".to_string()));
+        assert_eq!(reloaded.change_count(), 1);
+
+        // Compacting in place keeps the state and stays editable
+        doc.compact().unwrap();
+        assert_eq!(doc.get_cells(), reloaded.get_cells());
+        doc.update_source("c1", "x = 2").unwrap();
+        assert_eq!(doc.get_cell("c1").unwrap().source, "x = 2");
+    }
+
+    #[test]
+    fn test_change_count_follows_local_and_synced_changes() {
+        let mut a = NotebookDoc::new("nb1");
+        a.add_cell(0, "c1", "code").unwrap();
+        let mut b = NotebookDoc::load(&a.save()).unwrap();
+        assert_eq!(b.change_count(), b.doc.get_changes(&[]).len());
+
+        for i in 0..5 {
+            a.update_source("c1", &format!("x = {}", i)).unwrap();
+            assert_eq!(a.change_count(), a.doc.get_changes(&[]).len());
+        }
+        b.update_source("c1", "y = 1").unwrap();
+        assert_eq!(b.change_count(), b.doc.get_changes(&[]).len());
+
+        let mut a_state = sync::State::new();
+        let mut b_state = sync::State::new();
+        for _ in 0..10 {
+            if let Some(msg) = a.generate_sync_message(&mut a_state) {
+                b.receive_sync_message(&mut b_state, msg).unwrap();
+            }
+            if let Some(msg) = b.generate_sync_message(&mut b_state) {
+                a.receive_sync_message(&mut a_state, msg).unwrap();
+            }
+        }
+        assert_eq!(a.change_count(), a.doc.get_changes(&[]).len());
+        assert_eq!(b.change_count(), a.change_count());
+    }
+
     #[test]
     fn test_add_and_get_cell() {
         let mut doc = NotebookDoc::new("nb1");
@@ -943,6 +1422,81 @@ mod tests {
         assert!(!result);
     }
 
+    #[test]
+    fn test_splice_source_matches_full_update() {
+        let source: String = (0..200).map(|i| format!("x_{i} = {i} * 2\n")).collect();
+        let mut base = NotebookDoc::new("nb1");
+        base.add_cell(0, "cell-1", "code").unwrap();
+        base.update_source("cell-1", &source).unwrap();
+        let saved = base.save();
+
+        // Replace "x_100 = 100" with "y = 100", somewhere in the middle
+        let index = source.find("x_100").unwrap();
+        let edited = source.replacen("x_100", "y", 1);
+
+        let mut full = NotebookDoc::load(&saved).unwrap();
+        full.doc.save_incremental();
+        assert!(full.update_source("cell-1", &edited).unwrap());
+        let full_change = full.doc.save_incremental();
+
+        let mut spliced = NotebookDoc::load(&saved).unwrap();
+        spliced.doc.save_incremental();
+        assert!(spliced.splice_source("cell-1", index, 5, "y").unwrap());
+        let splice_change = spliced.doc.save_incremental();
+
+        assert_eq!(spliced.get_cell("cell-1").unwrap().source, edited);
+        assert_eq!(full.get_cell("cell-1").unwrap().source, edited);
+        assert!(splice_change.len() <= full_change.len());
+        assert!(
+            splice_change.len() < source.len() / 10,
+            "a one-word edit shouldn't carry the cell: {} bytes",
+            splice_change.len()
+        );
+    }
+
+    #[test]
+    fn test_splice_source_edits() {
+        let mut doc = NotebookDoc::new("nb1");
+        doc.add_cell(0, "cell-1", "code").unwrap();
+
+        doc.splice_source("cell-1", 0, 0, "print('hi')").unwrap();
+        doc.splice_source("cell-1", 7, 2, "héllo").unwrap();
+        assert_eq!(doc.get_cell("cell-1").unwrap().source, "print('héllo')");
+
+        // Positions count characters, not bytes
+        doc.splice_source("cell-1", 9, 3, "y").unwrap();
+        assert_eq!(doc.get_cell("cell-1").unwrap().source, "print('héy')");
+
+        assert!(!doc.splice_source("nope", 0, 0, "x").unwrap());
+        assert!(doc.splice_source("cell-1", 100, 0, "x").is_err());
+    }
+
+    #[test]
+    fn test_concurrent_splices_merge() {
+        let mut a = NotebookDoc::new("nb1");
+        a.add_cell(0, "cell-1", "code").unwrap();
+        a.update_source("cell-1", "a = 1\nb = 2\n").unwrap();
+        let mut b = NotebookDoc::load(&a.save()).unwrap();
+
+        a.splice_source("cell-1", 4, 1, "10").unwrap();
+        b.splice_source("cell-1", 10, 1, "20").unwrap();
+
+        let mut a_state = sync::State::new();
+        let mut b_state = sync::State::new();
+        for _ in 0..10 {
+            if let Some(msg) = a.generate_sync_message(&mut a_state) {
+                b.receive_sync_message(&mut b_state, msg).unwrap();
+            }
+            if let Some(msg) = b.generate_sync_message(&mut b_state) {
+                a.receive_sync_message(&mut a_state, msg).unwrap();
+            }
+        }
+
+        let expected = "a = 10\nb = 20\n";
+        assert_eq!(a.get_cell("cell-1").unwrap().source, expected);
+        assert_eq!(b.get_cell("cell-1").unwrap().source, expected);
+    }
+
     #[test]
     fn test_set_outputs() {
         let mut doc = NotebookDoc::new("nb1");
@@ -986,6 +1540,35 @@ mod tests {
         assert!(cell.outputs.is_empty());
     }
 
+    #[test]
+    fn test_clear_all_outputs() {
+        let mut doc = NotebookDoc::new("nb1");
+        doc.add_cell(0, "cell-1", "code").unwrap();
+        doc.add_cell(1, "cell-2", "markdown").unwrap();
+        doc.add_cell(2, "cell-3", "code").unwrap();
+        doc.append_output("cell-1", "output1").unwrap();
+        doc.append_output("cell-3", "output2").unwrap();
+        doc.append_output("cell-3", "output3").unwrap();
+        doc.set_execution_count("cell-1", "1").unwrap();
+        doc.set_execution_count("cell-3", "2").unwrap();
+
+        let cleared = doc.clear_all_outputs().unwrap();
+        assert_eq!(
+            cleared,
+            vec![
+                ("cell-1".to_string(), vec!["output1".to_string()]),
+                (
+                    "cell-3".to_string(),
+                    vec!["output2".to_string(), "output3".to_string()]
+                ),
+            ]
+        );
+        for cell in doc.get_cells().iter().filter(|c| c.cell_type == "code") {
+            assert!(cell.outputs.is_empty());
+            assert_eq!(cell.execution_count, "null");
+        }
+    }
+
     #[test]
     fn test_set_execution_count() {
         let mut doc = NotebookDoc::new("nb1");
@@ -1000,6 +1583,89 @@ mod tests {
         assert_eq!(cell.execution_count, "null");
     }
 
+    #[test]
+    fn test_execution_timing() {
+        let mut doc = NotebookDoc::new("nb1");
+        doc.add_cell(0, "cell-1", "code").unwrap();
+        doc.add_cell(1, "cell-2", "code").unwrap();
+        assert!(doc.get_cell("cell-1").unwrap().execution.is_none());
+
+        let timing = CellExecutionTiming {
+            started_at: "2026-01-01T12:00:00.000Z".to_string(),
+            completed_at: "2026-01-01T12:00:01.500Z".to_string(),
+            duration_ms: 1500,
+        };
+        assert!(doc.set_execution_timing("cell-2", &timing).unwrap());
+        assert!(!doc.set_execution_timing("missing", &timing).unwrap());
+        assert_eq!(
+            doc.get_execution_timings(),
+            vec![("cell-2".to_string(), timing.clone())]
+        );
+
+        // Syncs to other replicas through the raw-doc reader too
+        let cells = get_cells_from_doc(&doc.doc);
+        assert_eq!(cells[1].execution.as_ref(), Some(&timing));
+
+        doc.clear_all_outputs().unwrap();
+        assert!(doc.get_execution_timings().is_empty());
+    }
+
+    #[test]
+    fn test_output_layout_flags() {
+        let mut doc = NotebookDoc::new("nb1");
+        doc.add_cell(0, "cell-1", "code").unwrap();
+        doc.set_outputs("cell-1", &["{}".into(), "{}".into(), "{}".into()])
+            .unwrap();
+
+        assert!(doc.set_cell_flag("cell-1", "scrolled", Some(true)).unwrap());
+        assert!(doc.set_output_collapsed("cell-1", 2, true).unwrap());
+        assert!(doc.set_output_collapsed("cell-1", 0, true).unwrap());
+        assert!(doc.set_output_collapsed("cell-1", 0, true).unwrap());
+        assert!(!doc.set_output_collapsed("missing", 0, true).unwrap());
+        let cell = doc.get_cell("cell-1").unwrap();
+        assert_eq!(cell.scrolled, Some(true));
+        assert_eq!(cell.collapsed, None);
+        assert_eq!(cell.collapsed_outputs, vec![0, 2]);
+
+        // Survives a save/load round-trip and the serialized snapshot
+        let mut loaded = NotebookDoc::load(&doc.save()).unwrap();
+        let cell = loaded.get_cell("cell-1").unwrap();
+        let json = serde_json::to_string(&cell).unwrap();
+        assert_eq!(serde_json::from_str::<CellSnapshot>(&json).unwrap(), cell);
+        assert_eq!(cell.collapsed_outputs, vec![0, 2]);
+
+        loaded.set_output_collapsed("cell-1", 2, false).unwrap();
+        loaded.set_cell_flag("cell-1", "scrolled", None).unwrap();
+        let cell = loaded.get_cell("cell-1").unwrap();
+        assert_eq!(cell.collapsed_outputs, vec![0]);
+        assert_eq!(cell.scrolled, None);
+
+        // New outputs start expanded
+        loaded.clear_outputs("cell-1").unwrap();
+        assert!(loaded
+            .get_cell("cell-1")
+            .unwrap()
+            .collapsed_outputs
+            .is_empty());
+    }
+
+    #[test]
+    fn test_cell_tags() {
+        let mut doc = NotebookDoc::new("nb1");
+        doc.add_cell(0, "cell-1", "code").unwrap();
+        assert!(doc.get_cell("cell-1").unwrap().tags.is_empty());
+
+        let tags = vec!["parameters".to_string(), "skip-execution".to_string()];
+        assert!(doc.set_cell_tags("cell-1", &tags).unwrap());
+        assert!(!doc.set_cell_tags("missing", &tags).unwrap());
+        let loaded = NotebookDoc::load(&doc.save()).unwrap();
+        assert_eq!(loaded.get_cell("cell-1").unwrap().tags, tags);
+        assert_eq!(get_cells_from_doc(&doc.doc)[0].tags, tags);
+
+        doc.set_cell_tags("cell-1", &[]).unwrap();
+        assert!(doc.get_cell("cell-1").unwrap().tags.is_empty());
+    }
+
     #[test]
     fn test_metadata() {
         let mut doc = NotebookDoc::new("nb1");
@@ -1102,9 +1768,7 @@ mod tests {
             .unwrap();
 
         // Client starts with an empty doc (like a new window joining)
-        let mut client = NotebookDoc {
-            doc: AutoCommit::new(),
-        };
+        let mut client = NotebookDoc::from_doc(AutoCommit::new());
 
         let mut server_state = sync::State::new();
         let mut client_state = sync::State::new();
@@ -1129,12 +1793,84 @@ mod tests {
         assert_eq!(cells[0].outputs.len(), 1);
     }
 
+    #[test]
+    fn test_outputs_written_while_disconnected_reach_reconnected_client() {
+        fn sync(server: &mut NotebookDoc, client: &mut NotebookDoc) {
+            // Each connection starts both sides with fresh sync state
+            let mut server_state = sync::State::new();
+            let mut client_state = sync::State::new();
+            for _ in 0..10 {
+                if let Some(msg) = client.generate_sync_message(&mut client_state) {
+                    server.receive_sync_message(&mut server_state, msg).unwrap();
+                }
+                if let Some(msg) = server.generate_sync_message(&mut server_state) {
+                    client.receive_sync_message(&mut client_state, msg).unwrap();
+                }
+            }
+        }
+
+        let mut server = NotebookDoc::new("reconnect-test");
+        server.add_cell(0, "cell-1", "code").unwrap();
+        let first = r#"{"output_type":"stream","name":"stdout","text":"1\n"}"#;
+        server.append_output("cell-1", first).unwrap();
+        let mut client = NotebookDoc::from_doc(AutoCommit::new());
+        sync(&mut server, &mut client);
+        assert_eq!(client.get_cell("cell-1").unwrap().outputs, vec![first]);
+
+        // The kernel keeps writing outputs while the window is disconnected
+        let second = r#"{"output_type":"stream","name":"stdout","text":"1\n2\n"}"#;
+        let result = r#"{"output_type":"execute_result","data":{"text/plain":"3"}}"#;
+        server.replace_output("cell-1", 0, second).unwrap();
+        server.append_output("cell-1", result).unwrap();
+        server.set_execution_count("cell-1", "1").unwrap();
+
+        sync(&mut server, &mut client);
+        let cell = client.get_cell("cell-1").unwrap();
+        assert_eq!(cell.outputs, vec![second, result]);
+        assert_eq!(cell.execution_count, "1");
+    }
+
+    #[test]
+    fn test_output_layout_syncs_to_peers() {
+        let mut server = NotebookDoc::new("layout-test");
+        server.add_cell(0, "cell-1", "code").unwrap();
+        server
+            .set_outputs("cell-1", &["{}".into(), "{}".into()])
+            .unwrap();
+        let mut window_a = NotebookDoc::load(&server.save()).unwrap();
+        let mut window_b = NotebookDoc::load(&server.save()).unwrap();
+
+        window_a
+            .set_cell_flag("cell-1", "collapsed", Some(true))
+            .unwrap();
+        window_a.set_output_collapsed("cell-1", 1, true).unwrap();
+        // The other window collapses the same output concurrently
+        window_b.set_output_collapsed("cell-1", 1, true).unwrap();
+
+        let mut a_state = sync::State::new();
+        let mut b_state = sync::State::new();
+        for _ in 0..10 {
+            if let Some(msg) = window_a.generate_sync_message(&mut a_state) {
+                window_b.receive_sync_message(&mut b_state, msg).unwrap();
+            }
+            if let Some(msg) = window_b.generate_sync_message(&mut b_state) {
+                window_a.receive_sync_message(&mut a_state, msg).unwrap();
+            }
+        }
+
+        for window in [&window_a, &window_b] {
+            let cell = window.get_cell("cell-1").unwrap();
+            assert_eq!(cell.collapsed, Some(true));
+            assert_eq!(cell.collapsed_outputs, vec![1]);
+        }
+        let cells = get_cells_from_doc(&window_b.doc);
+        assert_eq!(cells[0].collapsed_outputs, vec![1]);
+    }
+
     #[test]
     fn test_concurrent_cell_adds_merge() {
         let mut server = NotebookDoc::new("merge-test");
-        let mut client = NotebookDoc {
-            doc: AutoCommit::new(),
-        };
+        let mut client = NotebookDoc::from_doc(AutoCommit::new());
 
         let mut server_state = sync::State::new();
         let mut client_state = sync::State::new();
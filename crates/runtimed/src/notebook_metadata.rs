@@ -17,6 +17,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::settings_doc::CondaDefaults;
+
 // ── Runt namespace ───────────────────────────────────────────────────
 
 /// Typed representation of the `metadata.runt` namespace in a notebook.
@@ -44,6 +46,11 @@ pub struct RuntMetadata {
     /// Deno runtime configuration.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub deno: Option<DenoMetadata>,
+
+    /// Per-notebook auto-launch opt-out. When `Some(false)`, the daemon will
+    /// not start a kernel when the notebook is opened, even if it is trusted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_launch: Option<bool>,
 }
 
 /// UV inline dependency metadata (`metadata.runt.uv`).
@@ -63,19 +70,50 @@ pub struct UvInlineMetadata {
 }
 
 /// Conda inline dependency metadata (`metadata.runt.conda`).
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct CondaInlineMetadata {
     /// Conda package names (e.g. `["numpy", "scipy"]`).
     #[serde(default)]
     pub dependencies: Vec<String>,
 
-    /// Conda channels to search (e.g. `["conda-forge"]`).
+    /// Conda channels to search, highest priority first (e.g. `["conda-forge"]`).
     #[serde(default)]
     pub channels: Vec<String>,
 
+    /// How strictly channel order is enforced; `None` follows the
+    /// `conda.strict_channel_priority` setting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_priority: Option<kernel_env::ChannelPriority>,
+
     /// Explicit Python version for the conda environment.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub python: Option<String>,
+
+    /// PyPI packages pip-installed into the conda env after the solve.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pip_dependencies: Vec<String>,
+}
+
+impl CondaInlineMetadata {
+    /// Channels to solve against, highest priority first: the notebook's own,
+    /// else the `conda.channels` setting, else conda-forge.
+    pub fn resolved_channels(&self, defaults: &CondaDefaults) -> Vec<String> {
+        if self.channels.is_empty() {
+            defaults.default_channels()
+        } else {
+            self.channels.clone()
+        }
+    }
+
+    /// Channel priority to solve with: the notebook's own, else the
+    /// `conda.strict_channel_priority` setting.
+    pub fn resolved_channel_priority(
+        &self,
+        defaults: &CondaDefaults,
+    ) -> kernel_env::ChannelPriority {
+        self.channel_priority
+            .unwrap_or_else(|| defaults.channel_priority())
+    }
 }
 
 /// Deno runtime configuration (`metadata.runt.deno`).
@@ -181,6 +219,7 @@ impl NotebookMetadataSnapshot {
                     uv,
                     conda,
                     deno: None,
+                    auto_launch: None,
                 }
             });
 
@@ -267,6 +306,7 @@ impl RuntMetadata {
             }),
             conda: None,
             deno: None,
+            auto_launch: None,
         }
     }
 
@@ -279,9 +319,12 @@ impl RuntMetadata {
             conda: Some(CondaInlineMetadata {
                 dependencies: Vec::new(),
                 channels: vec!["conda-forge".to_string()],
+                channel_priority: None,
                 python: None,
+                pip_dependencies: Vec::new(),
             }),
             deno: None,
+            auto_launch: None,
         }
     }
 
@@ -298,6 +341,7 @@ impl RuntMetadata {
                 config: None,
                 flexible_npm_imports: None,
             }),
+            auto_launch: None,
         }
     }
 }
@@ -360,6 +404,7 @@ mod tests {
                 }),
                 conda: None,
                 deno: None,
+                auto_launch: None,
             },
         };
 
@@ -398,6 +443,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_snapshot_auto_launch_opt_out() {
+        let metadata = serde_json::json!({
+            "runt": { "schema_version": "1", "auto_launch": false }
+        });
+        let snapshot = NotebookMetadataSnapshot::from_metadata_value(&metadata);
+        assert_eq!(snapshot.runt.auto_launch, Some(false));
+
+        // Unset by default, and omitted when serialized
+        let meta = RuntMetadata::new_uv("test-env-id".to_string());
+        assert_eq!(meta.auto_launch, None);
+        assert!(!serde_json::to_string(&meta)
+            .unwrap()
+            .contains("auto_launch"));
+    }
+
     #[test]
     fn test_snapshot_from_legacy_metadata() {
         // Legacy format: uv at top level instead of inside runt
@@ -464,6 +525,7 @@ mod tests {
             uv: None,
             conda: None,
             deno: None,
+            auto_launch: None,
         };
         let json = serde_json::to_value(&meta).unwrap();
         // None fields should not appear in JSON
@@ -0,0 +1,259 @@
+//! Persistent registry of notebooks the daemon has opened.
+//!
+//! Rooms only exist while a notebook is open. The registry outlives them (and
+//! daemon restarts), recording each notebook's path, the env source its last
+//! kernel used, and when it was last opened. Clients use it for "recent
+//! notebooks" and to enrich `runt notebooks`.
+//!
+//! Stored as JSON at `daemon_base_dir()/notebooks.json`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// Upper bound on remembered notebooks; the least recently opened are dropped.
+const MAX_ENTRIES: usize = 200;
+
+/// What the registry remembers about one notebook.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotebookRecord {
+    /// The notebook ID (file path for saved notebooks, UUID for untitled ones).
+    pub notebook_id: String,
+    /// Path on disk, if the notebook is a saved file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Env source of the most recently launched kernel (e.g., "uv:inline").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_env_source: Option<String>,
+    /// When a client last connected to the notebook's room.
+    pub last_opened: DateTime<Utc>,
+}
+
+/// Registry of notebooks keyed by notebook ID, backed by a JSON file.
+#[derive(Debug)]
+pub struct NotebookRegistry {
+    path: PathBuf,
+    entries: HashMap<String, NotebookRecord>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RegistryFile {
+    notebooks: Vec<NotebookRecord>,
+}
+
+impl NotebookRegistry {
+    /// Load the registry from `path`.
+    ///
+    /// A missing or unreadable file yields an empty registry; it is rewritten
+    /// on the next update.
+    pub fn load(path: &Path) -> Self {
+        let entries = match std::fs::read_to_string(path) {
+            Ok(content) => match serde_json::from_str::<RegistryFile>(&content) {
+                Ok(file) => file
+                    .notebooks
+                    .into_iter()
+                    .map(|record| (record.notebook_id.clone(), record))
+                    .collect(),
+                Err(e) => {
+                    warn!("[notebook-registry] Ignoring corrupt {:?}: {}", path, e);
+                    HashMap::new()
+                }
+            },
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            path: path.to_path_buf(),
+            entries,
+        }
+    }
+
+    /// Record that `notebook_id` was opened now.
+    ///
+    /// `path` is the notebook's file on disk, or `None` for untitled notebooks.
+    pub fn record_opened(&mut self, notebook_id: &str, path: Option<&Path>) {
+        self.record_opened_at(notebook_id, path, Utc::now());
+    }
+
+    fn record_opened_at(&mut self, notebook_id: &str, path: Option<&Path>, at: DateTime<Utc>) {
+        let path = path.map(|p| p.to_string_lossy().into_owned());
+        self.entries
+            .entry(notebook_id.to_string())
+            .and_modify(|record| {
+                record.last_opened = at;
+                if path.is_some() {
+                    record.path = path.clone();
+                }
+            })
+            .or_insert_with(|| NotebookRecord {
+                notebook_id: notebook_id.to_string(),
+                path: path.clone(),
+                last_env_source: None,
+                last_opened: at,
+            });
+        self.evict_oldest();
+    }
+
+    /// Record the env source of a kernel just launched for `notebook_id`.
+    pub fn record_env_source(&mut self, notebook_id: &str, env_source: &str) {
+        let record = self
+            .entries
+            .entry(notebook_id.to_string())
+            .or_insert_with(|| NotebookRecord {
+                notebook_id: notebook_id.to_string(),
+                path: None,
+                last_env_source: None,
+                last_opened: Utc::now(),
+            });
+        record.last_env_source = Some(env_source.to_string());
+        self.evict_oldest();
+    }
+
+    /// Look up a single notebook.
+    pub fn get(&self, notebook_id: &str) -> Option<&NotebookRecord> {
+        self.entries.get(notebook_id)
+    }
+
+    /// All recorded notebooks, most recently opened first.
+    pub fn list(&self) -> Vec<NotebookRecord> {
+        let mut records: Vec<NotebookRecord> = self.entries.values().cloned().collect();
+        records.sort_by(|a, b| b.last_opened.cmp(&a.last_opened));
+        records
+    }
+
+    /// Write the registry back to its file.
+    pub fn save(&self) -> std::io::Result<()> {
+        self.snapshot()?.write()
+    }
+
+    /// Serialize the registry so it can be written after releasing whatever
+    /// lock guards it.
+    pub fn snapshot(&self) -> std::io::Result<RegistrySnapshot> {
+        let json = serde_json::to_string_pretty(&RegistryFile {
+            notebooks: self.list(),
+        })?;
+        Ok(RegistrySnapshot {
+            path: self.path.clone(),
+            json,
+        })
+    }
+
+    fn evict_oldest(&mut self) {
+        while self.entries.len() > MAX_ENTRIES {
+            let Some(oldest) = self
+                .entries
+                .values()
+                .min_by_key(|record| record.last_opened)
+                .map(|record| record.notebook_id.clone())
+            else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+/// A serialized [`NotebookRegistry`], ready to be written to its file.
+pub struct RegistrySnapshot {
+    path: PathBuf,
+    json: String,
+}
+
+impl RegistrySnapshot {
+    /// Write the snapshot to the registry file (blocking IO).
+    pub fn write(self) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write-then-rename so a crash mid-write can't truncate the registry
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, self.json)?;
+        std::fs::rename(&tmp, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_upsert_updates_existing_record() {
+        let tmp = TempDir::new().unwrap();
+        let mut registry = NotebookRegistry::load(&tmp.path().join("notebooks.json"));
+        let t0 = Utc::now() - Duration::hours(1);
+
+        registry.record_opened_at("/work/a.ipynb", Some(Path::new("/work/a.ipynb")), t0);
+        registry.record_env_source("/work/a.ipynb", "uv:inline");
+        registry.record_opened("/work/a.ipynb", None);
+
+        assert_eq!(registry.list().len(), 1);
+        let record = registry.get("/work/a.ipynb").unwrap();
+        assert_eq!(record.path.as_deref(), Some("/work/a.ipynb"));
+        assert_eq!(record.last_env_source.as_deref(), Some("uv:inline"));
+        assert!(record.last_opened > t0);
+    }
+
+    #[test]
+    fn test_list_is_most_recent_first() {
+        let tmp = TempDir::new().unwrap();
+        let mut registry = NotebookRegistry::load(&tmp.path().join("notebooks.json"));
+        let now = Utc::now();
+
+        registry.record_opened_at("old", None, now - Duration::days(2));
+        registry.record_opened_at("new", None, now);
+        registry.record_opened_at("mid", None, now - Duration::days(1));
+
+        let ids: Vec<String> = registry.list().into_iter().map(|r| r.notebook_id).collect();
+        assert_eq!(ids, vec!["new", "mid", "old"]);
+    }
+
+    #[test]
+    fn test_save_and_reload() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("nested").join("notebooks.json");
+        let mut registry = NotebookRegistry::load(&path);
+        registry.record_opened("/work/a.ipynb", Some(Path::new("/work/a.ipynb")));
+        registry.record_env_source("/work/a.ipynb", "conda:env_yml");
+        registry.save().unwrap();
+
+        let reloaded = NotebookRegistry::load(&path);
+        assert_eq!(reloaded.list(), registry.list());
+        assert_eq!(
+            reloaded
+                .get("/work/a.ipynb")
+                .unwrap()
+                .last_env_source
+                .as_deref(),
+            Some("conda:env_yml")
+        );
+    }
+
+    #[test]
+    fn test_corrupt_file_loads_empty() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("notebooks.json");
+        std::fs::write(&path, "not json").unwrap();
+
+        assert!(NotebookRegistry::load(&path).list().is_empty());
+    }
+
+    #[test]
+    fn test_evicts_least_recently_opened() {
+        let tmp = TempDir::new().unwrap();
+        let mut registry = NotebookRegistry::load(&tmp.path().join("notebooks.json"));
+        let start = Utc::now() - Duration::days(1);
+
+        for i in 0..=MAX_ENTRIES {
+            registry.record_opened_at(&format!("nb{i}"), None, start + Duration::seconds(i as i64));
+        }
+
+        assert_eq!(registry.list().len(), MAX_ENTRIES);
+        assert!(registry.get("nb0").is_none());
+        assert!(registry.get(&format!("nb{MAX_ENTRIES}")).is_some());
+    }
+}
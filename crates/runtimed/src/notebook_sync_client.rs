@@ -25,10 +25,42 @@ use tokio::sync::{mpsc, oneshot};
 
 use crate::connection::{self, Handshake, NotebookFrameType, ProtocolCapabilities, PROTOCOL_V2};
 use crate::notebook_doc::{
-    get_cells_from_doc, get_metadata_from_doc, set_metadata_in_doc, CellSnapshot,
+    get_cells_from_doc, get_metadata_from_doc, set_cell_flag_in_doc, set_cell_tags_in_doc,
+    set_metadata_in_doc, set_output_collapsed_in_doc, CellSnapshot,
 };
 use crate::notebook_metadata::NOTEBOOK_METADATA_KEY;
 use crate::protocol::{NotebookBroadcast, NotebookRequest, NotebookResponse};
+use crate::settings_doc::KernelStartupSettings;
+
+/// How often the background task pings the daemon to check the connection.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long to wait for a pong before treating the connection as dead.
+const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait for the daemon to answer a request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a launch request allows for building an environment.
+const ENV_BUILD_ALLOWANCE_SECS: u64 = 300;
+
+/// How long to wait for a request that launches a kernel. The daemon may
+/// build an environment first, then waits up to `kernel_startup.timeout_secs`
+/// for the kernel, twice if startup is retried. The setting is clamped, so
+/// this covers any value the user picks.
+const LAUNCH_REQUEST_TIMEOUT: Duration = Duration::from_secs(
+    ENV_BUILD_ALLOWANCE_SECS + 2 * KernelStartupSettings::MAX_TIMEOUT_SECS as u64,
+);
+
+/// The client-side timeout for `request`.
+fn request_timeout(request: &NotebookRequest) -> Duration {
+    match request {
+        NotebookRequest::LaunchKernel { .. }
+        | NotebookRequest::SwitchEnvironment { .. }
+        | NotebookRequest::RepairEnvironment {} => LAUNCH_REQUEST_TIMEOUT,
+        _ => REQUEST_TIMEOUT,
+    }
+}
 
 /// Error type for notebook sync client operations.
 #[derive(Debug, thiserror::Error)]
@@ -84,6 +116,24 @@ enum SyncCommand {
         count: String,
         reply: oneshot::Sender<Result<(), NotebookSyncError>>,
     },
+    /// Set or clear a cell's `collapsed` or `scrolled` flag.
+    SetCellFlag {
+        cell_id: String,
+        key: &'static str,
+        value: Option<bool>,
+        reply: oneshot::Sender<Result<(), NotebookSyncError>>,
+    },
+    SetOutputCollapsed {
+        cell_id: String,
+        output_index: usize,
+        collapsed: bool,
+        reply: oneshot::Sender<Result<(), NotebookSyncError>>,
+    },
+    SetCellTags {
+        cell_id: String,
+        tags: Vec<String>,
+        reply: oneshot::Sender<Result<(), NotebookSyncError>>,
+    },
     GetCells {
         reply: oneshot::Sender<Vec<CellSnapshot>>,
     },
@@ -244,6 +294,87 @@ impl NotebookSyncHandle {
             .map_err(|_| NotebookSyncError::ChannelClosed)?
     }
 
+    /// Collapse or expand a cell's outputs (nbformat `collapsed`).
+    pub async fn set_cell_collapsed(
+        &self,
+        cell_id: &str,
+        collapsed: Option<bool>,
+    ) -> Result<(), NotebookSyncError> {
+        self.set_cell_flag(cell_id, "collapsed", collapsed).await
+    }
+
+    /// Toggle scrolling of a cell's outputs (nbformat `scrolled`).
+    pub async fn set_cell_scrolled(
+        &self,
+        cell_id: &str,
+        scrolled: Option<bool>,
+    ) -> Result<(), NotebookSyncError> {
+        self.set_cell_flag(cell_id, "scrolled", scrolled).await
+    }
+
+    async fn set_cell_flag(
+        &self,
+        cell_id: &str,
+        key: &'static str,
+        value: Option<bool>,
+    ) -> Result<(), NotebookSyncError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx
+            .send(SyncCommand::SetCellFlag {
+                cell_id: cell_id.to_string(),
+                key,
+                value,
+                reply: reply_tx,
+            })
+            .await
+            .map_err(|_| NotebookSyncError::ChannelClosed)?;
+        reply_rx
+            .await
+            .map_err(|_| NotebookSyncError::ChannelClosed)?
+    }
+
+    /// Collapse or expand a single output of a cell.
+    pub async fn set_output_collapsed(
+        &self,
+        cell_id: &str,
+        output_index: usize,
+        collapsed: bool,
+    ) -> Result<(), NotebookSyncError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx
+            .send(SyncCommand::SetOutputCollapsed {
+                cell_id: cell_id.to_string(),
+                output_index,
+                collapsed,
+                reply: reply_tx,
+            })
+            .await
+            .map_err(|_| NotebookSyncError::ChannelClosed)?;
+        reply_rx
+            .await
+            .map_err(|_| NotebookSyncError::ChannelClosed)?
+    }
+
+    /// Replace a cell's tags (e.g. `skip-execution`, `raises-exception`).
+    pub async fn set_cell_tags(
+        &self,
+        cell_id: &str,
+        tags: Vec<String>,
+    ) -> Result<(), NotebookSyncError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx
+            .send(SyncCommand::SetCellTags {
+                cell_id: cell_id.to_string(),
+                tags,
+                reply: reply_tx,
+            })
+            .await
+            .map_err(|_| NotebookSyncError::ChannelClosed)?;
+        reply_rx
+            .await
+            .map_err(|_| NotebookSyncError::ChannelClosed)?
+    }
+
     /// Set a metadata value in the Automerge doc and sync to daemon.
     pub async fn set_metadata(&self, key: &str, value: &str) -> Result<(), NotebookSyncError> {
         let (reply_tx, reply_rx) = oneshot::channel();
@@ -356,6 +487,8 @@ pub struct NotebookSyncClient<S> {
     /// Broadcasts received during initial sync (before split).
     /// These are delivered immediately after into_split creates the channels.
     pending_broadcasts: Vec<NotebookBroadcast>,
+    /// Interval between keepalive pings, and how long to wait for each pong.
+    keepalive: (Duration, Duration),
 }
 
 #[cfg(unix)]
@@ -642,6 +775,7 @@ where
             notebook_id,
             use_typed_frames,
             pending_broadcasts,
+            keepalive: (KEEPALIVE_INTERVAL, KEEPALIVE_TIMEOUT),
         })
     }
 
@@ -650,6 +784,13 @@ where
         &self.notebook_id
     }
 
+    /// Change how often the background task started by `into_split` pings
+    /// the daemon, and how long it waits for each pong before giving up on
+    /// the connection.
+    pub fn set_keepalive(&mut self, interval: Duration, timeout: Duration) {
+        self.keepalive = (interval, timeout);
+    }
+
     // ── Read operations ─────────────────────────────────────────────
 
     /// Get all cells from the local replica.
@@ -676,6 +817,50 @@ where
         self.sync_to_daemon().await
     }
 
+    /// Set or clear a cell's `collapsed` or `scrolled` flag and sync to daemon.
+    pub async fn set_cell_flag(
+        &mut self,
+        cell_id: &str,
+        key: &str,
+        value: Option<bool>,
+    ) -> Result<(), NotebookSyncError> {
+        let found = set_cell_flag_in_doc(&mut self.doc, cell_id, key, value)
+            .map_err(|e| NotebookSyncError::SyncError(format!("set_cell_flag: {}", e)))?;
+        if !found {
+            return Err(NotebookSyncError::CellNotFound(cell_id.to_string()));
+        }
+        self.sync_to_daemon().await
+    }
+
+    /// Collapse or expand a single output and sync to daemon.
+    pub async fn set_output_collapsed(
+        &mut self,
+        cell_id: &str,
+        output_index: usize,
+        collapsed: bool,
+    ) -> Result<(), NotebookSyncError> {
+        let found = set_output_collapsed_in_doc(&mut self.doc, cell_id, output_index, collapsed)
+            .map_err(|e| NotebookSyncError::SyncError(format!("set_output_collapsed: {}", e)))?;
+        if !found {
+            return Err(NotebookSyncError::CellNotFound(cell_id.to_string()));
+        }
+        self.sync_to_daemon().await
+    }
+
+    /// Replace a cell's tags and sync to daemon.
+    pub async fn set_cell_tags(
+        &mut self,
+        cell_id: &str,
+        tags: &[String],
+    ) -> Result<(), NotebookSyncError> {
+        let found = set_cell_tags_in_doc(&mut self.doc, cell_id, tags)
+            .map_err(|e| NotebookSyncError::SyncError(format!("set_cell_tags: {}", e)))?;
+        if !found {
+            return Err(NotebookSyncError::CellNotFound(cell_id.to_string()));
+        }
+        self.sync_to_daemon().await
+    }
+
     /// Add a new cell at the given index and sync to daemon.
     pub async fn add_cell(
         &mut self,
@@ -786,6 +971,7 @@ where
         };
 
         let _ = self.doc.delete(&cell_obj, "outputs");
+        let _ = self.doc.delete(&cell_obj, "collapsed_outputs");
         let list_id = self
             .doc
             .put_object(&cell_obj, "outputs", ObjType::List)
@@ -847,6 +1033,7 @@ where
 
         // Replace outputs with a fresh empty list
         let _ = self.doc.delete(&cell_obj, "outputs");
+        let _ = self.doc.delete(&cell_obj, "collapsed_outputs");
         self.doc
             .put_object(&cell_obj, "outputs", ObjType::List)
             .map_err(|e| NotebookSyncError::SyncError(format!("put outputs: {}", e)))?;
@@ -1141,6 +1328,29 @@ where
     pub async fn send_request(
         &mut self,
         request: &NotebookRequest,
+    ) -> Result<NotebookResponse, NotebookSyncError> {
+        self.send_request_with_timeout(request, request_timeout(request))
+            .await
+    }
+
+    /// Ping the daemon and wait up to `timeout` for the pong.
+    async fn ping(&mut self, timeout: Duration) -> Result<(), NotebookSyncError> {
+        match self
+            .send_request_with_timeout(&NotebookRequest::Ping {}, timeout)
+            .await?
+        {
+            NotebookResponse::Pong {} => Ok(()),
+            other => Err(NotebookSyncError::SyncError(format!(
+                "unexpected response to ping: {:?}",
+                other
+            ))),
+        }
+    }
+
+    async fn send_request_with_timeout(
+        &mut self,
+        request: &NotebookRequest,
+        timeout: Duration,
     ) -> Result<NotebookResponse, NotebookSyncError> {
         if !self.use_typed_frames {
             return Err(NotebookSyncError::SyncError(
@@ -1156,7 +1366,7 @@ where
             .await?;
 
         // Wait for a Response frame (with timeout)
-        match tokio::time::timeout(Duration::from_secs(30), self.wait_for_response()).await {
+        match tokio::time::timeout(timeout, self.wait_for_response()).await {
             Ok(result) => result,
             Err(_) => Err(NotebookSyncError::Timeout),
         }
@@ -1407,7 +1617,7 @@ async fn run_sync_task<S>(
 ) where
     S: AsyncRead + AsyncWrite + Unpin,
 {
-    use tokio::time::{interval, Duration};
+    use tokio::time::{interval, interval_at, Duration, Instant, MissedTickBehavior};
 
     let notebook_id = client.notebook_id().to_string();
     info!(
@@ -1420,6 +1630,13 @@ async fn run_sync_task<S>(
     let mut loop_count = 0u64;
     // Track last metadata to only send updates when it actually changes
     let mut last_metadata: Option<String> = client.get_metadata(NOTEBOOK_METADATA_KEY);
+    // Periodic pings catch a daemon that stopped answering without closing
+    // the socket. Ending the task drops broadcast_tx, which the app treats
+    // as a disconnect. v1 daemons can't answer requests, so they aren't pinged.
+    let (keepalive_interval, keepalive_timeout) = client.keepalive;
+    let mut keepalive = interval_at(Instant::now() + keepalive_interval, keepalive_interval);
+    keepalive.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let use_keepalive = client.uses_typed_frames();
 
     loop {
         loop_count += 1;
@@ -1454,6 +1671,18 @@ async fn run_sync_task<S>(
                                 let result = client.set_execution_count(&cell_id, &count).await;
                                 let _ = reply.send(result);
                             }
+                            SyncCommand::SetCellFlag { cell_id, key, value, reply } => {
+                                let result = client.set_cell_flag(&cell_id, key, value).await;
+                                let _ = reply.send(result);
+                            }
+                            SyncCommand::SetOutputCollapsed { cell_id, output_index, collapsed, reply } => {
+                                let result = client.set_output_collapsed(&cell_id, output_index, collapsed).await;
+                                let _ = reply.send(result);
+                            }
+                            SyncCommand::SetCellTags { cell_id, tags, reply } => {
+                                let result = client.set_cell_tags(&cell_id, &tags).await;
+                                let _ = reply.send(result);
+                            }
                             SyncCommand::GetCells { reply } => {
                                 let cells = client.get_cells();
                                 let _ = reply.send(cells);
@@ -1483,6 +1712,30 @@ async fn run_sync_task<S>(
                 }
             }
 
+            _ = keepalive.tick(), if use_keepalive => {
+                let heads_before = client.doc.get_heads();
+                if let Err(e) = client.ping(keepalive_timeout).await {
+                    warn!(
+                        "[notebook-sync-task] Keepalive failed for {}: {}, loop_count={}",
+                        notebook_id, e, loop_count
+                    );
+                    break;
+                }
+                // Sync messages that arrived ahead of the pong were applied
+                // while waiting; pass them on like any other change
+                if client.doc.get_heads() != heads_before {
+                    let current_metadata = client.get_metadata(NOTEBOOK_METADATA_KEY);
+                    let metadata_changed = current_metadata != last_metadata;
+                    if metadata_changed {
+                        last_metadata = current_metadata.clone();
+                    }
+                    let _ = changes_tx.try_send(SyncUpdate {
+                        cells: client.get_cells(),
+                        notebook_metadata: if metadata_changed { current_metadata } else { None },
+                    });
+                }
+            }
+
             // Check for incoming changes
             // Note: recv_frame_any() has an internal 100ms timeout, so no outer timeout needed
             _ = poll_interval.tick() => {
@@ -1573,6 +1826,23 @@ enum ReceivedFrame {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_launch_requests_outlast_a_retried_startup() {
+        let longest_startup = u64::from(KernelStartupSettings::MAX_TIMEOUT_SECS);
+        let retried_startup = Duration::from_secs(longest_startup * 2);
+        let launch = NotebookRequest::LaunchKernel {
+            kernel_type: "python".to_string(),
+            env_source: "auto".to_string(),
+            notebook_path: None,
+        };
+        assert!(request_timeout(&launch) > retried_startup);
+        assert!(request_timeout(&NotebookRequest::RepairEnvironment {}) > retried_startup);
+        assert_eq!(
+            request_timeout(&NotebookRequest::GetKernelInfo {}),
+            REQUEST_TIMEOUT
+        );
+    }
+
     #[test]
     fn test_get_cells_from_empty_doc() {
         let doc = AutoCommit::new();
@@ -1608,4 +1878,81 @@ mod tests {
         assert_eq!(cells[0].execution_count, "1");
         assert_eq!(cells[0].outputs.len(), 1);
     }
+
+    /// A v2 client already past the handshake, talking to `stream`.
+    fn synced_client(
+        stream: tokio::io::DuplexStream,
+    ) -> NotebookSyncClient<tokio::io::DuplexStream> {
+        let mut client = NotebookSyncClient {
+            doc: AutoCommit::new(),
+            peer_state: sync::State::new(),
+            stream,
+            notebook_id: "keepalive-test".to_string(),
+            use_typed_frames: true,
+            pending_broadcasts: Vec::new(),
+            keepalive: (KEEPALIVE_INTERVAL, KEEPALIVE_TIMEOUT),
+        };
+        client.set_keepalive(Duration::from_millis(200), Duration::from_millis(200));
+        client
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_detects_unresponsive_daemon() {
+        // The daemon end stays open but never answers: a half-open connection
+        let (client_end, mut daemon_end) = tokio::io::duplex(64 * 1024);
+        let (_handle, _changes, mut broadcasts, _, _) = synced_client(client_end).into_split();
+
+        let frame = tokio::time::timeout(
+            Duration::from_secs(2),
+            connection::recv_typed_frame(&mut daemon_end),
+        )
+        .await
+        .expect("client should ping within the interval")
+        .unwrap()
+        .unwrap();
+        assert_eq!(frame.frame_type, NotebookFrameType::Request);
+        let request: NotebookRequest = serde_json::from_slice(&frame.payload).unwrap();
+        assert!(matches!(request, NotebookRequest::Ping {}));
+
+        // The missed pong ends the sync task, closing the broadcast channel
+        let closed = tokio::time::timeout(Duration::from_secs(2), broadcasts.recv())
+            .await
+            .expect("missed pong should disconnect within the interval");
+        assert!(closed.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_keeps_responsive_daemon_connected() {
+        let (client_end, mut daemon_end) = tokio::io::duplex(64 * 1024);
+        let (handle, _changes, mut broadcasts, _, _) = synced_client(client_end).into_split();
+
+        let daemon = tokio::spawn(async move {
+            let mut pings = 0;
+            while let Ok(Some(frame)) = connection::recv_typed_frame(&mut daemon_end).await {
+                if frame.frame_type == NotebookFrameType::Request {
+                    pings += 1;
+                    connection::send_typed_json_frame(
+                        &mut daemon_end,
+                        NotebookFrameType::Response,
+                        &NotebookResponse::Pong {},
+                    )
+                    .await
+                    .unwrap();
+                }
+            }
+            pings
+        });
+
+        // Several intervals pass without the connection being dropped
+        assert!(
+            tokio::time::timeout(Duration::from_secs(1), broadcasts.recv())
+                .await
+                .is_err()
+        );
+
+        // Dropping the handle stops the task and closes the connection
+        drop(handle);
+        let pings = daemon.await.unwrap();
+        assert!(pings >= 3, "expected several pings, got {}", pings);
+    }
 }
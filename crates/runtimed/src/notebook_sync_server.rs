@@ -14,7 +14,9 @@
 //! 4. Changes from any peer broadcast to all others in the room
 //! 5. When the last peer disconnects, the room is evicted from memory
 //!    (the doc is already persisted on every change)
-//! 6. Documents persist to `~/.cache/runt/notebook-docs/{hash}.automerge`
+//! 6. Documents persist to `~/.cache/runt/notebook-docs/{hash}.automerge`.
+//!    Once the last peer leaves, history past `COMPACT_CHANGE_THRESHOLD`
+//!    is dropped
 //!
 //! ## Phase 8: Daemon-owned kernel execution
 //!
@@ -24,23 +26,32 @@
 //! - Outputs are broadcast to all connected windows
 //! - Multiple windows share the same kernel
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use automerge::sync;
-use log::{error, info, warn};
+use log::{debug, error, info, warn};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::sync::{broadcast, Mutex, RwLock};
 
 use crate::blob_store::BlobStore;
+use crate::broadcast_coalescer::BroadcastCoalescer;
+use crate::cell_tags;
 use crate::comm_state::CommState;
 use crate::connection::{self, NotebookFrameType};
-use crate::kernel_manager::{DenoLaunchedConfig, LaunchedEnvConfig, RoomKernel};
-use crate::notebook_doc::{notebook_doc_filename, NotebookDoc};
-use crate::notebook_metadata::{NotebookMetadataSnapshot, NOTEBOOK_METADATA_KEY};
-use crate::protocol::{EnvSyncDiff, NotebookBroadcast, NotebookRequest, NotebookResponse};
+use crate::kernel_manager::{DenoLaunchedConfig, LaunchSpec, LaunchedEnvConfig, RoomKernel};
+use crate::kernel_snapshot;
+use crate::lsp_proxy::{self, LspClient, LspState};
+use crate::notebook_doc::{notebook_doc_filename, CellExecutionTiming, CellSnapshot, NotebookDoc};
+use crate::notebook_metadata::{
+    CondaInlineMetadata, NotebookMetadataSnapshot, NOTEBOOK_METADATA_KEY,
+};
+use crate::protocol::{
+    CellTiming, DebugCommand, EnvSyncDiff, NotebookBroadcast, NotebookRequest, NotebookResponse,
+};
+use crate::settings_doc::{CondaDefaults, UvDefaults};
 
 /// Trust state for a notebook room.
 /// Tracks whether the notebook's dependencies are trusted for auto-launch.
@@ -53,9 +64,29 @@ pub struct TrustState {
 }
 
 /// Detect the kernel type from a notebook's metadata snapshot.
-/// Returns "python" or "deno" based on the kernelspec and language_info.
+/// Returns "python" or "deno" based on the kernelspec and language_info, or the
+/// kernelspec name itself for any other installed kernel.
 /// This is the #1 priority - the notebook's kernelspec determines the runtime.
 fn detect_notebook_kernel_type(snapshot: &NotebookMetadataSnapshot) -> Option<String> {
+    detect_kernel_type_with(snapshot, kernelspec_installed)
+}
+
+/// Whether a Jupyter kernelspec named `name` is installed.
+fn kernelspec_installed(name: &str) -> bool {
+    runtimelib::dirs::data_dirs()
+        .iter()
+        .any(|dir| dir.join("kernels").join(name).join("kernel.json").is_file())
+}
+
+/// [`detect_notebook_kernel_type`], asking `installed` whether a custom
+/// kernelspec can be launched. A notebook from a machine with a kernel this
+/// one lacks falls back to its language_info, then to Python.
+fn detect_kernel_type_with(
+    snapshot: &NotebookMetadataSnapshot,
+    installed: impl Fn(&str) -> bool,
+) -> Option<String> {
+    let mut missing_kernelspec = false;
+
     // Check kernelspec.name first (most reliable)
     if let Some(ref kernelspec) = snapshot.kernelspec {
         let name_lower = kernelspec.name.to_lowercase();
@@ -75,6 +106,17 @@ fn detect_notebook_kernel_type(snapshot: &NotebookMetadataSnapshot) -> Option<St
                 return Some("python".to_string());
             }
         }
+        // Any other kernelspec is launched by name (e.g. "ir", "julia-1.10")
+        if !kernelspec.name.is_empty() {
+            if installed(&kernelspec.name) {
+                return Some(kernelspec.name.clone());
+            }
+            warn!(
+                "[notebook-sync] Kernelspec {:?} is not installed, falling back",
+                kernelspec.name
+            );
+            missing_kernelspec = true;
+        }
     }
 
     // Fallback: check language_info.name
@@ -88,6 +130,9 @@ fn detect_notebook_kernel_type(snapshot: &NotebookMetadataSnapshot) -> Option<St
         }
     }
 
+    if missing_kernelspec {
+        return Some("python".to_string());
+    }
     None // Unknown kernel type
 }
 
@@ -108,21 +153,21 @@ fn check_inline_deps(snapshot: &NotebookMetadataSnapshot) -> Option<String> {
         }
     }
 
-    // Check conda dependencies
-    if let Some(ref conda) = snapshot.runt.conda {
-        if !conda.dependencies.is_empty() {
-            return Some("conda:inline".to_string());
-        }
+    // Check conda dependencies (including pip packages layered on conda)
+    if get_inline_conda_deps(snapshot).is_some() {
+        return Some("conda:inline".to_string());
     }
 
     None
 }
 
 /// Extract inline conda dependencies from a metadata snapshot.
-/// Returns the list of dependency strings if conda deps are present.
+/// Returns the list of dependency strings if conda deps are present. A
+/// notebook listing only pip packages still gets an inline conda env (with
+/// an empty conda dependency list) to pip-install them into.
 fn get_inline_conda_deps(snapshot: &NotebookMetadataSnapshot) -> Option<Vec<String>> {
     if let Some(ref conda) = snapshot.runt.conda {
-        if !conda.dependencies.is_empty() {
+        if !conda.dependencies.is_empty() || !conda.pip_dependencies.is_empty() {
             return Some(conda.dependencies.clone());
         }
     }
@@ -140,15 +185,38 @@ fn get_inline_uv_deps(snapshot: &NotebookMetadataSnapshot) -> Option<Vec<String>
     None
 }
 
-/// Extract conda channels from a metadata snapshot.
-/// Returns the list of channel strings, or defaults to ["conda-forge"].
-fn get_inline_conda_channels(snapshot: &NotebookMetadataSnapshot) -> Vec<String> {
-    if let Some(ref conda) = snapshot.runt.conda {
-        if !conda.channels.is_empty() {
-            return conda.channels.clone();
-        }
+/// Extract conda channels from a metadata snapshot, highest priority first.
+/// Falls back to the `conda.channels` setting, then ["conda-forge"].
+fn get_inline_conda_channels(
+    snapshot: &NotebookMetadataSnapshot,
+    defaults: &CondaDefaults,
+) -> Vec<String> {
+    match snapshot.runt.conda {
+        Some(ref conda) => conda.resolved_channels(defaults),
+        None => CondaInlineMetadata::default().resolved_channels(defaults),
+    }
+}
+
+/// Extract the conda channel priority from a metadata snapshot, falling back
+/// to the `conda.strict_channel_priority` setting.
+fn get_inline_conda_channel_priority(
+    snapshot: &NotebookMetadataSnapshot,
+    defaults: &CondaDefaults,
+) -> kernel_env::ChannelPriority {
+    match snapshot.runt.conda {
+        Some(ref conda) => conda.resolved_channel_priority(defaults),
+        None => defaults.channel_priority(),
     }
-    vec!["conda-forge".to_string()]
+}
+
+/// Extract the PyPI packages to pip-install into an inline conda env.
+fn get_inline_conda_pip_deps(snapshot: &NotebookMetadataSnapshot) -> Vec<String> {
+    snapshot
+        .runt
+        .conda
+        .as_ref()
+        .map(|conda| conda.pip_dependencies.clone())
+        .unwrap_or_default()
 }
 
 /// Build a LaunchedEnvConfig from the current metadata snapshot.
@@ -158,23 +226,28 @@ fn build_launched_config(
     env_source: &str,
     inline_deps: Option<&[String]>,
     metadata_snapshot: Option<&NotebookMetadataSnapshot>,
+    conda_defaults: &CondaDefaults,
     venv_path: Option<PathBuf>,
     python_path: Option<PathBuf>,
 ) -> LaunchedEnvConfig {
-    let mut config = LaunchedEnvConfig::default();
+    // Recorded for every source: prewarmed envs need them for pinning
+    let mut config = LaunchedEnvConfig {
+        venv_path,
+        python_path,
+        ..Default::default()
+    };
 
     match env_source {
         "uv:inline" => {
             config.uv_deps = inline_deps.map(|d| d.to_vec());
-            config.venv_path = venv_path;
-            config.python_path = python_path;
         }
         "conda:inline" => {
             config.conda_deps = inline_deps.map(|d| d.to_vec());
-            config.venv_path = venv_path;
-            config.python_path = python_path;
             if let Some(snapshot) = metadata_snapshot {
-                config.conda_channels = Some(get_inline_conda_channels(snapshot));
+                config.conda_channels = Some(get_inline_conda_channels(snapshot, conda_defaults));
+                config.conda_channel_priority =
+                    Some(get_inline_conda_channel_priority(snapshot, conda_defaults));
+                config.conda_pip_deps = Some(get_inline_conda_pip_deps(snapshot));
             }
         }
         _ => {}
@@ -194,6 +267,8 @@ fn build_launched_config(
         }
     }
 
+    config.env_id = metadata_snapshot.and_then(|s| s.runt.env_id.clone());
+
     // Generate unique launch ID for this kernel session (for race detection during hot-sync)
     config.launch_id = Some(uuid::Uuid::new_v4().to_string());
 
@@ -252,6 +327,26 @@ fn compute_env_sync_diff(
             }
         }
 
+        // Pip packages installed on top of the solve
+        if let Some(ref launched_pip) = launched.conda_pip_deps {
+            let current_pip = current
+                .runt
+                .conda
+                .as_ref()
+                .map(|c| &c.pip_dependencies[..])
+                .unwrap_or(&[]);
+            for dep in current_pip {
+                if !launched_pip.contains(dep) {
+                    added.push(dep.clone());
+                }
+            }
+            for dep in launched_pip {
+                if !current_pip.contains(dep) {
+                    removed.push(dep.clone());
+                }
+            }
+        }
+
         // Check channels
         if let Some(ref launched_channels) = launched.conda_channels {
             let current_channels = current
@@ -266,6 +361,12 @@ fn compute_env_sync_diff(
                 channels_changed = true;
             }
         }
+
+        // An explicit per-notebook priority that differs needs a new solve
+        let current_priority = current.runt.conda.as_ref().and_then(|c| c.channel_priority);
+        if current_priority.is_some() && current_priority != launched.conda_channel_priority {
+            channels_changed = true;
+        }
     }
 
     // Check deno config
@@ -426,7 +527,7 @@ fn verify_trust_from_file(notebook_path: &Path) -> TrustState {
     };
 
     // Verify trust using the shared runt-trust crate
-    match runt_trust::verify_notebook_trust(&metadata) {
+    match runt_trust::verify_notebook_trust(&metadata, Some(notebook_path)) {
         Ok(info) => TrustState {
             status: info.status.clone(),
             info,
@@ -439,6 +540,7 @@ fn verify_trust_from_file(notebook_path: &Path) -> TrustState {
                 uv_dependencies: vec![],
                 conda_dependencies: vec![],
                 conda_channels: vec![],
+                trusted_scope: None,
             },
             pending_launch: false,
         },
@@ -465,6 +567,9 @@ pub struct NotebookRoom {
     pub blob_store: Arc<BlobStore>,
     /// Trust state for this notebook (for auto-launch decisions).
     pub trust_state: Arc<RwLock<TrustState>>,
+    /// Whether the kernel's outputs render unsanitized, shared with its
+    /// output readers. Refreshed from `trust_state` before each execution.
+    pub output_trust: crate::output_store::OutputTrust,
     /// The notebook file path (notebook_id is the path).
     pub notebook_path: PathBuf,
     /// Timestamp when auto-launch was triggered (for grace period on eviction).
@@ -474,6 +579,15 @@ pub struct NotebookRoom {
     /// Stores active comms so new windows can sync widget models.
     /// Arc-wrapped so it can be shared with the kernel's iopub task.
     pub comm_state: Arc<CommState>,
+    /// Language server used for static completions (when `lsp.enabled`).
+    /// Started lazily on the first completion request.
+    pub lsp: Arc<Mutex<LspState>>,
+    /// Command line of the room's most recent kernel spawn. Kept on the room
+    /// because a kernel that fails to start is dropped.
+    pub last_launch_spec: Arc<RwLock<Option<LaunchSpec>>>,
+    /// Output of the room's current or most recent environment build, for
+    /// `StreamEnvBuildLog` subscribers.
+    pub env_build_log: Arc<crate::inline_env::EnvBuildLog>,
 }
 
 impl NotebookRoom {
@@ -522,10 +636,14 @@ impl NotebookRoom {
             active_peers: AtomicUsize::new(0),
             kernel: Arc::new(Mutex::new(None)),
             blob_store,
+            output_trust: output_trust_for(&trust_state),
             trust_state: Arc::new(RwLock::new(trust_state)),
             notebook_path,
             auto_launch_at: Arc::new(RwLock::new(None)),
             comm_state: Arc::new(CommState::new()),
+            lsp: Arc::new(Mutex::new(LspState::NotStarted)),
+            last_launch_spec: Arc::new(RwLock::new(None)),
+            env_build_log: Arc::default(),
         }
     }
 
@@ -551,10 +669,14 @@ impl NotebookRoom {
             active_peers: AtomicUsize::new(0),
             kernel: Arc::new(Mutex::new(None)),
             blob_store,
+            output_trust: output_trust_for(&trust_state),
             trust_state: Arc::new(RwLock::new(trust_state)),
             notebook_path,
             auto_launch_at: Arc::new(RwLock::new(None)),
             comm_state: Arc::new(CommState::new()),
+            lsp: Arc::new(Mutex::new(LspState::NotStarted)),
+            last_launch_spec: Arc::new(RwLock::new(None)),
+            env_build_log: Arc::default(),
         }
     }
 
@@ -579,6 +701,72 @@ impl NotebookRoom {
             }
         })
     }
+
+    /// Summarize this room for `ListRooms`, including how backed up the
+    /// kernel's execution queue is. `last_opened` is left for the caller to
+    /// fill in from the notebook registry.
+    pub async fn room_info(&self, notebook_id: &str) -> crate::protocol::RoomInfo {
+        let kernel = self.kernel.lock().await;
+        let running = kernel.as_ref().filter(|k| k.is_running());
+        crate::protocol::RoomInfo {
+            notebook_id: notebook_id.to_string(),
+            active_peers: self.active_peers.load(std::sync::atomic::Ordering::Relaxed),
+            has_kernel: running.is_some(),
+            kernel_type: running.map(|k| k.kernel_type().to_string()),
+            env_source: running.map(|k| k.env_source().to_string()),
+            kernel_status: running.map(|k| k.status().to_string()),
+            executing_cell: running.and_then(|k| k.executing_cell().cloned()),
+            queue_depth: running.map_or(0, |k| k.queued_cells().len()),
+            last_opened: None,
+        }
+    }
+
+    /// Broadcasts that bring a newly connected peer up to date with a live
+    /// kernel: its status, the execution queue, and a `reattached` lifecycle
+    /// event. Empty if the room has no running kernel.
+    pub async fn reattach_broadcasts(&self) -> Vec<NotebookBroadcast> {
+        let kernel = self.kernel.lock().await;
+        let Some(k) = kernel.as_ref().filter(|k| k.is_running()) else {
+            return vec![];
+        };
+        vec![
+            NotebookBroadcast::KernelStatus {
+                status: k.status().to_string(),
+                cell_id: None,
+            },
+            NotebookBroadcast::QueueChanged {
+                executing: k.executing_cell().cloned(),
+                queued: k.queued_cells(),
+            },
+            NotebookBroadcast::KernelLifecycle {
+                state: "reattached".to_string(),
+                kernel_type: Some(k.kernel_type().to_string()),
+                env_source: Some(k.env_source().to_string()),
+                exit_code: None,
+                stderr_tail: None,
+                cancelled_cells: vec![],
+                reason: None,
+            },
+        ]
+    }
+
+    /// Lifecycle event telling a newly connected peer that no kernel was
+    /// started because the notebook sets `runt.auto_launch: false`. `None`
+    /// if a kernel is running or auto-launch is enabled.
+    pub async fn auto_launch_disabled_broadcast(&self) -> Option<NotebookBroadcast> {
+        if self.has_kernel().await || !auto_launch_disabled(self).await {
+            return None;
+        }
+        Some(NotebookBroadcast::KernelLifecycle {
+            state: "auto_launch_disabled".to_string(),
+            kernel_type: None,
+            env_source: None,
+            exit_code: None,
+            stderr_tail: None,
+            cancelled_cells: vec![],
+            reason: Some("auto-launch disabled".to_string()),
+        })
+    }
 }
 
 /// Thread-safe map of notebook rooms, keyed by notebook_id.
@@ -599,12 +787,242 @@ pub fn get_or_create_room(
     rooms
         .entry(notebook_id.to_string())
         .or_insert_with(|| {
-            info!("[notebook-sync] Creating room for {}", notebook_id);
+            info!(notebook_id; "[notebook-sync] Creating room for {}", notebook_id);
             Arc::new(NotebookRoom::new_fresh(notebook_id, docs_dir, blob_store))
         })
         .clone()
 }
 
+/// Evict a room whose last peer has left, shutting down its kernel.
+///
+/// With `keep_kernel_on_close`, a room with a live kernel is left in place
+/// instead so the kernel keeps running and the notebook can reattach to it.
+/// Returns `true` if the room was evicted.
+async fn evict_idle_room(
+    rooms: &NotebookRooms,
+    room: &Arc<NotebookRoom>,
+    notebook_id: &str,
+    keep_kernel_on_close: bool,
+) -> bool {
+    let mut rooms_guard = rooms.lock().await;
+    // Re-check under lock
+    if room.active_peers.load(Ordering::Relaxed) > 0 {
+        return false;
+    }
+
+    let mut kernel_guard = room.kernel.lock().await;
+    let kernel_running = kernel_guard.as_ref().is_some_and(|k| k.is_running());
+    if keep_kernel_on_close && kernel_running {
+        info!(
+            "[notebook-sync] Keeping kernel for {} running after close (keep_kernel_on_close)",
+            notebook_id
+        );
+        return false;
+    }
+
+    if let Some(mut kernel) = kernel_guard.take() {
+        info!(
+            "[notebook-sync] Shutting down idle kernel for {}",
+            notebook_id
+        );
+        if let Err(e) = kernel.shutdown().await {
+            warn!(
+                "[notebook-sync] Error shutting down kernel for {}: {}",
+                notebook_id, e
+            );
+        }
+    }
+    drop(kernel_guard);
+
+    rooms_guard.remove(notebook_id);
+    info!(
+        notebook_id;
+        "[notebook-sync] Evicted room {} (idle timeout)",
+        notebook_id
+    );
+    true
+}
+
+/// Shut down the kernel in every room, returning the IDs of the notebooks
+/// whose kernel was stopped (sorted).
+///
+/// Rooms are left open so connected windows can launch a fresh kernel; peers
+/// are told the kernel is gone through a `shutdown` status broadcast.
+pub async fn shutdown_all_kernels(rooms: &NotebookRooms) -> Vec<String> {
+    // Snapshot the rooms so the map isn't locked across kernel shutdowns
+    let rooms: Vec<(String, Arc<NotebookRoom>)> = rooms
+        .lock()
+        .await
+        .iter()
+        .map(|(id, room)| (id.clone(), room.clone()))
+        .collect();
+
+    let mut stopped = Vec::new();
+    for (notebook_id, room) in rooms {
+        let Some(mut kernel) = room.kernel.lock().await.take() else {
+            continue;
+        };
+        info!("[notebook-sync] Shutting down kernel for {}", notebook_id);
+        if let Err(e) = kernel.shutdown().await {
+            warn!(
+                "[notebook-sync] Error shutting down kernel for {}: {}",
+                notebook_id, e
+            );
+        }
+        room.comm_state.clear().await;
+        let _ = room
+            .kernel_broadcast_tx
+            .send(NotebookBroadcast::KernelStatus {
+                status: "shutdown".to_string(),
+                cell_id: None,
+            });
+        stopped.push(notebook_id);
+    }
+    stopped.sort();
+    stopped
+}
+
+/// Clear every code cell's outputs and execution count in `room`, then
+/// release the blobs those outputs referenced.
+///
+/// The blob store is shared across notebooks; a blob is only deleted once
+/// no output anywhere holds a reference to it. Returns the cleared cell IDs
+/// and the number of blobs deleted.
+pub async fn clear_all_outputs(room: &NotebookRoom) -> Result<(Vec<String>, usize), String> {
+    // 1. Mutate the Automerge document; peers pick this up via sync
+    let (cleared, persist_bytes) = {
+        let mut doc = room.doc.write().await;
+        let cleared = doc
+            .clear_all_outputs()
+            .map_err(|e| format!("Failed to clear outputs: {}", e))?;
+        let bytes = doc.save();
+        let _ = room.changed_tx.send(());
+        (cleared, bytes)
+    };
+    persist_notebook_bytes(&persist_bytes, &room.persist_path);
+
+    // 2. Broadcast for cross-window UI sync and reset kernel stream state
+    {
+        let kernel_guard = room.kernel.lock().await;
+        for (cell_id, _) in &cleared {
+            let _ = room
+                .kernel_broadcast_tx
+                .send(NotebookBroadcast::OutputsCleared {
+                    cell_id: cell_id.clone(),
+                });
+            if let Some(ref kernel) = *kernel_guard {
+                kernel.clear_outputs(cell_id).await;
+            }
+        }
+    }
+
+    // 3. Drop the cleared outputs' blob refs; blobs another output still
+    // refers to keep theirs
+    let dropped: Vec<String> = cleared
+        .iter()
+        .flat_map(|(_, outputs)| outputs.iter().cloned())
+        .collect();
+    let reclaimed = crate::output_store::release_outputs(&dropped, &room.blob_store).await;
+
+    let cell_ids: Vec<String> = cleared.into_iter().map(|(cell_id, _)| cell_id).collect();
+    info!(
+        "[notebook-sync] Cleared outputs of {} cells, reclaimed {} blobs",
+        cell_ids.len(),
+        reclaimed
+    );
+    Ok((cell_ids, reclaimed))
+}
+
+/// Copy the outputs and execution count of cell `from` onto cell `to`,
+/// returning how many outputs were copied.
+///
+/// The copies take their own blob references, so clearing or rerunning
+/// either cell leaves the other's outputs intact.
+pub async fn copy_outputs(room: &NotebookRoom, from: &str, to: &str) -> Result<usize, String> {
+    let (persist_bytes, count) = {
+        let mut doc = room.doc.write().await;
+        let source = doc
+            .get_cell(from)
+            .ok_or_else(|| format!("Cell not found: {}", from))?;
+        if doc.get_cell(to).is_none() {
+            return Err(format!("Cell not found: {}", to));
+        }
+        for output in &source.outputs {
+            doc.append_output(to, output)
+                .map_err(|e| format!("Failed to copy outputs: {}", e))?;
+        }
+        crate::output_store::retain_outputs(&source.outputs, &room.blob_store).await;
+        doc.set_execution_count(to, &source.execution_count)
+            .map_err(|e| format!("Failed to copy execution count: {}", e))?;
+        let _ = room.changed_tx.send(());
+        (doc.save(), source.outputs.len())
+    };
+    persist_notebook_bytes(&persist_bytes, &room.persist_path);
+    Ok(count)
+}
+
+/// Env paths the kernels in `rooms` were launched from, which must not be
+/// deleted from the env cache.
+pub async fn kernel_env_paths(rooms: &NotebookRooms) -> HashSet<PathBuf> {
+    let rooms: Vec<Arc<NotebookRoom>> = rooms.lock().await.values().cloned().collect();
+    let mut paths = HashSet::new();
+    for room in rooms {
+        if let Some(kernel) = room.kernel.lock().await.as_ref() {
+            paths.extend(kernel.launched_config().venv_path.clone());
+        }
+    }
+    paths
+}
+
+/// PIDs of the kernel processes owned by `rooms`, which must not be reaped.
+pub async fn kernel_pids(rooms: &NotebookRooms) -> HashSet<u32> {
+    let rooms: Vec<Arc<NotebookRoom>> = rooms.lock().await.values().cloned().collect();
+    let mut pids = HashSet::new();
+    for room in rooms {
+        if let Some(kernel) = room.kernel.lock().await.as_ref() {
+            pids.extend(kernel.pid());
+        }
+    }
+    pids
+}
+
+/// Whether `notebook_id` names a new unsaved notebook (a UUID with no file).
+fn is_new_notebook(room: &NotebookRoom, notebook_id: &str) -> bool {
+    !room.notebook_path.exists() && uuid::Uuid::parse_str(notebook_id).is_ok()
+}
+
+/// Whether the notebook opts out of auto-launch with `runt.auto_launch: false`.
+async fn auto_launch_disabled(room: &NotebookRoom) -> bool {
+    resolve_metadata_snapshot(room, Some(&room.notebook_path))
+        .await
+        .is_some_and(|snapshot| snapshot.runt.auto_launch == Some(false))
+}
+
+/// Decide whether the first peer to join a room should launch a kernel.
+///
+/// A room that already has a running kernel (e.g. kept alive across a
+/// window close) is reattached instead of relaunched. Notebooks that set
+/// `runt.auto_launch: false` never auto-launch, regardless of trust.
+async fn should_auto_launch(
+    room: &NotebookRoom,
+    notebook_id: &str,
+) -> (bool, runt_trust::TrustStatus) {
+    let opted_out = auto_launch_disabled(room).await;
+    let trust_state = room.trust_state.read().await;
+    let has_kernel = room.has_kernel().await;
+    let status = trust_state.status.clone();
+    let should_launch = !has_kernel
+        && !opted_out
+        && matches!(
+            status,
+            runt_trust::TrustStatus::Trusted | runt_trust::TrustStatus::NoDependencies
+        )
+        // For existing files: trust must be verified (Trusted or NoDependencies)
+        // For new notebooks (UUID, no file): NoDependencies is safe to auto-launch
+        && (room.notebook_path.exists() || is_new_notebook(room, notebook_id));
+    (should_launch, status)
+}
+
 /// Handle a single notebook sync client connection.
 ///
 /// The caller has already consumed the handshake frame and resolved the room.
@@ -639,6 +1057,7 @@ where
     room.active_peers.fetch_add(1, Ordering::Relaxed);
     let peers = room.active_peers.load(Ordering::Relaxed);
     info!(
+        notebook_id = notebook_id.as_str(), peers;
         "[notebook-sync] Client connected to room {} ({} peer{}, protocol {})",
         notebook_id,
         peers,
@@ -648,24 +1067,8 @@ where
 
     // Auto-launch kernel if this is the first peer and notebook is trusted
     if peers == 1 {
-        // Check if notebook_id is a UUID (new unsaved notebook) vs a file path
-        let is_new_notebook =
-            !room.notebook_path.exists() && uuid::Uuid::parse_str(&notebook_id).is_ok();
-
-        let (should_auto_launch, trust_status) = {
-            let trust_state = room.trust_state.read().await;
-            let has_kernel = room.has_kernel().await;
-            let status = trust_state.status.clone();
-            let should_launch = !has_kernel
-                && matches!(
-                    status,
-                    runt_trust::TrustStatus::Trusted | runt_trust::TrustStatus::NoDependencies
-                )
-                // For existing files: trust must be verified (Trusted or NoDependencies)
-                // For new notebooks (UUID, no file): NoDependencies is safe to auto-launch
-                && (room.notebook_path.exists() || is_new_notebook);
-            (should_launch, status)
-        };
+        let is_new_notebook = is_new_notebook(&room, &notebook_id);
+        let (should_auto_launch, trust_status) = should_auto_launch(&room, &notebook_id).await;
 
         if should_auto_launch {
             info!(
@@ -681,7 +1084,7 @@ where
             let room_clone = room.clone();
             let notebook_id_clone = notebook_id.clone();
             let daemon_clone = daemon.clone();
-            tokio::spawn(async move {
+            crate::log_format::spawn_in_scope(async move {
                 auto_launch_kernel(
                     &room_clone,
                     &notebook_id_clone,
@@ -691,6 +1094,11 @@ where
                 )
                 .await;
             });
+        } else if auto_launch_disabled(&room).await {
+            info!(
+                "[notebook-sync] Auto-launch disabled for notebook {}, skipping auto-launch",
+                notebook_id
+            );
         } else if !matches!(
             trust_status,
             runt_trust::TrustStatus::Trusted | runt_trust::TrustStatus::NoDependencies
@@ -711,7 +1119,7 @@ where
     }
 
     let result = if use_typed_frames {
-        run_sync_loop_v2(&mut reader, &mut writer, &room, daemon).await
+        run_sync_loop_v2(&mut reader, &mut writer, &room, daemon.clone()).await
     } else {
         run_sync_loop_v1(&mut reader, &mut writer, &room).await
     };
@@ -719,6 +1127,8 @@ where
     // Peer disconnected — decrement and possibly evict the room
     let remaining = room.active_peers.fetch_sub(1, Ordering::Relaxed) - 1;
     if remaining == 0 {
+        compact_idle_room(&room, &notebook_id).await;
+
         // Schedule delayed eviction check. This handles:
         // 1. Grace period during auto-launch (client may reconnect)
         // 2. Kernel running with no peers (idle timeout)
@@ -734,7 +1144,7 @@ where
             eviction_delay.as_secs()
         );
 
-        tokio::spawn(async move {
+        crate::log_format::spawn_in_scope(async move {
             tokio::time::sleep(eviction_delay).await;
 
             // Check if peers reconnected during the delay
@@ -746,29 +1156,14 @@ where
                 return;
             }
 
-            // Evict the room and shut down kernel if running
-            let mut rooms_guard = rooms_for_eviction.lock().await;
-            // Re-check under lock
-            if room_for_eviction.active_peers.load(Ordering::Relaxed) == 0 {
-                // Shutdown kernel if running
-                if let Some(mut kernel) = room_for_eviction.kernel.lock().await.take() {
-                    info!(
-                        "[notebook-sync] Shutting down idle kernel for {}",
-                        notebook_id_for_eviction
-                    );
-                    if let Err(e) = kernel.shutdown().await {
-                        warn!(
-                            "[notebook-sync] Error shutting down kernel for {}: {}",
-                            notebook_id_for_eviction, e
-                        );
-                    }
-                }
-                rooms_guard.remove(&notebook_id_for_eviction);
-                info!(
-                    "[notebook-sync] Evicted room {} (idle timeout)",
-                    notebook_id_for_eviction
-                );
-            }
+            let keep_kernel_on_close = daemon.synced_settings().await.keep_kernel_on_close;
+            evict_idle_room(
+                &rooms_for_eviction,
+                &room_for_eviction,
+                &notebook_id_for_eviction,
+                keep_kernel_on_close,
+            )
+            .await;
         });
     } else {
         info!(
@@ -782,6 +1177,36 @@ where
     result
 }
 
+/// Drop the room doc's history once the last peer has left.
+///
+/// Windows start from an empty replica when they connect, so no one holds
+/// the old history. A room that lingers with a running kernel keeps taking
+/// output writes, so this bounds its growth between sessions.
+async fn compact_idle_room(room: &NotebookRoom, notebook_id: &str) {
+    let persist_bytes = {
+        let mut doc = room.doc.write().await;
+        // Re-check under the lock: a peer that connected meanwhile may
+        // already be syncing against the current history
+        if room.active_peers.load(Ordering::Relaxed) > 0 || !doc.needs_compaction() {
+            return;
+        }
+        let before = doc.change_count();
+        if let Err(e) = doc.compact() {
+            warn!(
+                "[notebook-sync] Failed to compact room {}: {}",
+                notebook_id, e
+            );
+            return;
+        }
+        info!(
+            "[notebook-sync] Compacted room {} ({} changes dropped)",
+            notebook_id, before
+        );
+        doc.save()
+    };
+    persist_notebook_bytes(&persist_bytes, &room.persist_path);
+}
+
 /// Protocol v1: Raw Automerge frames (legacy, for backwards compatibility).
 ///
 /// This is the original sync protocol used by older clients. It only supports
@@ -806,6 +1231,15 @@ where
         }
     }
 
+    // Phase 1.6: Bring this client up to date with a kernel that outlived
+    // its previous windows, so it reattaches rather than launching anew
+    for broadcast in room.reattach_broadcasts().await {
+        connection::send_typed_json_frame(writer, NotebookFrameType::Broadcast, &broadcast).await?;
+    }
+    if let Some(broadcast) = room.auto_launch_disabled_broadcast().await {
+        connection::send_typed_json_frame(writer, NotebookFrameType::Broadcast, &broadcast).await?;
+    }
+
     // Phase 2: Exchange messages until sync is complete, then watch for changes
     loop {
         tokio::select! {
@@ -872,6 +1306,9 @@ where
     let mut peer_state = sync::State::new();
     let mut changed_rx = room.changed_tx.subscribe();
     let mut kernel_broadcast_rx = room.kernel_broadcast_tx.subscribe();
+    let mut coalescer = BroadcastCoalescer::new();
+    // Set while this client is subscribed to the environment build log
+    let mut build_log_rx = None;
 
     // Phase 1: Initial sync — server sends first (typed frame)
     {
@@ -902,6 +1339,7 @@ where
 
     // Phase 2: Exchange messages until sync is complete, then watch for changes
     loop {
+        let flush_at = coalescer.flush_at();
         tokio::select! {
             // Incoming message from this client
             result = connection::recv_typed_frame(reader) => {
@@ -946,8 +1384,17 @@ where
                             NotebookFrameType::Request => {
                                 // Handle NotebookRequest
                                 let request: NotebookRequest = serde_json::from_slice(&frame.payload)?;
-                                let response =
-                                    handle_notebook_request(room, request, daemon.clone()).await;
+                                let response = match request {
+                                    NotebookRequest::StreamEnvBuildLog { subscribe } => {
+                                        let (response, rx) =
+                                            stream_env_build_log(room, subscribe);
+                                        build_log_rx = rx;
+                                        response
+                                    }
+                                    request => {
+                                        handle_notebook_request(room, request, daemon.clone()).await
+                                    }
+                                };
                                 connection::send_typed_json_frame(
                                     writer,
                                     NotebookFrameType::Response,
@@ -985,71 +1432,220 @@ where
                 }
             }
 
-            // Kernel broadcast event — forward to this client
-            Ok(broadcast) = kernel_broadcast_rx.recv() => {
-                connection::send_typed_json_frame(
-                    writer,
-                    NotebookFrameType::Broadcast,
-                    &broadcast,
-                )
-                .await?;
+            // Kernel broadcast event — forward to this client, holding back
+            // rapid updates that supersede each other
+            result = kernel_broadcast_rx.recv() => {
+                match result {
+                    Ok(broadcast) => {
+                        for ready in coalescer.push(broadcast, tokio::time::Instant::now()) {
+                            connection::send_typed_json_frame(
+                                writer,
+                                NotebookFrameType::Broadcast,
+                                &ready,
+                            )
+                            .await?;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!(
+                            "[notebook-sync] Peer fell behind, skipped {} kernel broadcasts",
+                            skipped
+                        );
+                    }
+                    // The room owns the sender, so it outlives this connection
+                    Err(broadcast::error::RecvError::Closed) => {}
+                }
+            }
+
+            // Environment build output — forward to this client if subscribed
+            result = recv_build_log_line(&mut build_log_rx) => {
+                match result {
+                    Ok(line) => {
+                        connection::send_typed_json_frame(
+                            writer,
+                            NotebookFrameType::Broadcast,
+                            &NotebookBroadcast::EnvBuildLog { line },
+                        )
+                        .await?;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!(
+                            "[notebook-sync] Peer fell behind, skipped {} build log lines",
+                            skipped
+                        );
+                    }
+                    Err(broadcast::error::RecvError::Closed) => build_log_rx = None,
+                }
+            }
+
+            // Coalescing window elapsed — forward the latest held updates
+            _ = tokio::time::sleep_until(flush_at.unwrap_or_else(tokio::time::Instant::now)),
+                if flush_at.is_some() =>
+            {
+                for ready in coalescer.flush() {
+                    connection::send_typed_json_frame(
+                        writer,
+                        NotebookFrameType::Broadcast,
+                        &ready,
+                    )
+                    .await?;
+                }
             }
         }
     }
 }
 
-/// Acquire a pooled environment from the appropriate pool based on env_source.
-/// Returns None and broadcasts error if pool is empty.
+/// Answer a `StreamEnvBuildLog` request, returning the response and, when
+/// subscribing, a receiver for the build log lines after the backlog in it.
+fn stream_env_build_log(
+    room: &NotebookRoom,
+    subscribe: bool,
+) -> (
+    NotebookResponse,
+    Option<broadcast::Receiver<crate::protocol::EnvBuildLogLine>>,
+) {
+    if subscribe {
+        let (lines, rx) = room.env_build_log.subscribe();
+        (NotebookResponse::EnvBuildLog { lines }, Some(rx))
+    } else {
+        (NotebookResponse::Ok {}, None)
+    }
+}
+
+/// Next line from an optional build log subscription; never resolves when
+/// there is none.
+async fn recv_build_log_line(
+    rx: &mut Option<broadcast::Receiver<crate::protocol::EnvBuildLogLine>>,
+) -> Result<crate::protocol::EnvBuildLogLine, broadcast::error::RecvError> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Acquire a pooled environment from the appropriate pool based on env_source,
+/// or the env pinned to `env_id`. If the pool is empty because neither uv nor
+/// conda can produce environments, falls back to the system Python by setting
+/// `env_source` to `system:python` (with no pooled env). Returns None and
+/// broadcasts an error if there is nothing to launch from.
 async fn acquire_pool_env_for_source(
-    env_source: &str,
+    env_source: &mut String,
+    env_id: Option<&str>,
     daemon: &std::sync::Arc<crate::daemon::Daemon>,
     room: &NotebookRoom,
 ) -> Option<Option<crate::PooledEnv>> {
     // Route to appropriate pool based on source prefix
-    if env_source.starts_with("conda:") {
-        match daemon.take_conda_env().await {
-            Some(env) => {
-                info!(
-                    "[notebook-sync] Acquired Conda env from pool: {:?}",
-                    env.python_path
-                );
-                Some(Some(env))
-            }
-            None => {
-                error!("[notebook-sync] Conda pool empty, cannot launch");
-                let _ = room
-                    .kernel_broadcast_tx
-                    .send(NotebookBroadcast::KernelStatus {
-                        status: "error: Conda pool empty".to_string(),
-                        cell_id: None,
-                    });
-                None // Signal caller to return early
-            }
-        }
+    let (env, pool_name) = if env_source.starts_with("conda:") {
+        (daemon.take_conda_env(env_id).await, "Conda")
     } else {
         // UV pool for uv:* sources and as default
-        match daemon.take_uv_env().await {
-            Some(env) => {
-                info!(
-                    "[notebook-sync] Acquired UV env from pool: {:?}",
-                    env.python_path
-                );
-                Some(Some(env))
-            }
-            None => {
-                error!("[notebook-sync] UV pool empty, cannot launch");
-                let _ = room
-                    .kernel_broadcast_tx
-                    .send(NotebookBroadcast::KernelStatus {
-                        status: "error: UV pool empty".to_string(),
-                        cell_id: None,
-                    });
-                None // Signal caller to return early
-            }
+        (daemon.take_uv_env(env_id).await, "UV")
+    };
+    if let Some(env) = env {
+        info!(
+            "[notebook-sync] Acquired {} env from pool: {:?}",
+            pool_name, env.python_path
+        );
+        return Some(Some(env));
+    }
+
+    use crate::system_python::{self, EmptyPoolFallback};
+    let fallback = system_python::empty_pool_fallback(
+        daemon.env_manager_available(crate::EnvType::Uv).await,
+        daemon.env_manager_available(crate::EnvType::Conda).await,
+        system_python::find_system_python().as_deref(),
+    )
+    .await;
+    let status = match fallback {
+        EmptyPoolFallback::SystemPython(python) => {
+            warn!(
+                "[notebook-sync] {} pool empty and no env manager available, \
+                 falling back to system Python at {:?}",
+                pool_name, python
+            );
+            *env_source = system_python::SYSTEM_PYTHON_SOURCE.to_string();
+            return Some(None);
         }
+        EmptyPoolFallback::Wait => format!("error: {} pool empty", pool_name),
+        ref other => other.guidance().unwrap_or_default(),
+    };
+    error!("[notebook-sync] {} pool empty, cannot launch", pool_name);
+    let _ = room
+        .kernel_broadcast_tx
+        .send(NotebookBroadcast::KernelStatus {
+            status,
+            cell_id: None,
+        });
+    None // Signal caller to return early
+}
+
+/// Build output manifest options from the user's synced settings and the
+/// room's trust status.
+async fn manifest_options_from_settings(
+    daemon: &crate::daemon::Daemon,
+    room: &NotebookRoom,
+) -> crate::output_store::ManifestOptions {
+    let settings = daemon.synced_settings().await;
+    let mut options = crate::output_store::ManifestOptions {
+        ansi_spans: settings.ansi_spans,
+        trusted: refresh_output_trust(room).await,
+        allow_html: settings.outputs.allow_html,
+        allow_javascript: settings.outputs.allow_javascript,
+        ..Default::default()
+    };
+    if !settings.outputs.mime_priority.is_empty() {
+        options.mime_priority = settings.outputs.mime_priority;
+    }
+    options
+}
+
+/// Whether kernel outputs of a notebook with this trust state render
+/// unsanitized.
+fn output_trust_for(trust_state: &TrustState) -> crate::output_store::OutputTrust {
+    crate::output_store::OutputTrust::new(crate::output_sanitize::kernel_output_trusted(
+        &trust_state.status,
+    ))
+}
+
+/// Re-read the room's trust from the file, since the user may have approved
+/// the notebook (or it may have been edited) since the room opened, and
+/// update the output trust its kernel's readers share.
+async fn refresh_output_trust(room: &NotebookRoom) -> crate::output_store::OutputTrust {
+    let mut trust_state = room.trust_state.write().await;
+    if room.notebook_path.exists() {
+        let refreshed = verify_trust_from_file(&room.notebook_path);
+        trust_state.status = refreshed.status;
+        trust_state.info = refreshed.info;
+    }
+    room.output_trust
+        .set(crate::output_sanitize::kernel_output_trusted(
+            &trust_state.status,
+        ));
+    room.output_trust.clone()
+}
+
+/// Build the kernel startup timeout and retry policy from the user's synced
+/// settings.
+async fn startup_policy_from_settings(
+    daemon: &crate::daemon::Daemon,
+) -> crate::kernel_manager::StartupPolicy {
+    let startup = daemon.synced_settings().await.kernel_startup;
+    crate::kernel_manager::StartupPolicy {
+        timeout: std::time::Duration::from_secs(startup.timeout_secs.into()),
+        retry: startup.retry,
     }
 }
 
+/// The room's execution log, if the `run_log` setting is on and the notebook
+/// has been saved to a file to log next to.
+async fn run_log_from_settings(
+    daemon: &crate::daemon::Daemon,
+    room: &NotebookRoom,
+) -> Option<crate::run_log::RunLog> {
+    (daemon.synced_settings().await.run_log && room.notebook_path.exists())
+        .then(|| crate::run_log::RunLog::for_notebook(&room.notebook_path))
+}
+
 /// Auto-launch kernel for a trusted notebook when first peer connects.
 /// This is similar to handle_notebook_request(LaunchKernel) but without a request/response.
 ///
@@ -1108,6 +1704,12 @@ async fn auto_launch_kernel(
         room.blob_store.clone(),
         room.comm_state.clone(),
     );
+    kernel.set_manifest_options(manifest_options_from_settings(&daemon, room).await);
+    kernel.set_startup_policy(startup_policy_from_settings(&daemon).await);
+    kernel.set_run_log(run_log_from_settings(&daemon, room).await);
+    kernel.set_install_suggestions(!daemon.synced_settings().await.hide_install_suggestions);
+    kernel.set_env_claims(daemon.env_claims().clone(), notebook_id);
+    kernel.set_kernel_pids(daemon.kernel_pids().clone(), notebook_id);
 
     // Detection priority:
     // 1. Notebook's kernelspec (for existing notebooks) - determines python vs deno
@@ -1122,6 +1724,10 @@ async fn auto_launch_kernel(
 
     // Step 2: Check inline deps (for environment source, and runt.deno override)
     let inline_source = metadata_snapshot.as_ref().and_then(check_inline_deps);
+    // A prewarmed env pinned to the notebook is reattached instead of taking one
+    let env_id = metadata_snapshot
+        .as_ref()
+        .and_then(|s| s.runt.env_id.as_deref());
 
     // Step 3: Check project files (for Python environment resolution)
     let project_source = notebook_path_opt
@@ -1145,7 +1751,7 @@ async fn auto_launch_kernel(
         }
         Some("python") => {
             // Notebook is a Python notebook - resolve environment
-            let env_source = if let Some(ref source) = inline_source {
+            let mut env_source = if let Some(ref source) = inline_source {
                 // Skip "deno" inline source for Python notebooks (kernelspec takes priority)
                 if source != "deno" {
                     info!(
@@ -1195,7 +1801,7 @@ async fn auto_launch_kernel(
                 );
                 None
             } else {
-                match acquire_pool_env_for_source(&env_source, &daemon, room).await {
+                match acquire_pool_env_for_source(&mut env_source, env_id, &daemon, room).await {
                     Some(env) => env,
                     None => return, // Error already broadcast
                 }
@@ -1214,7 +1820,7 @@ async fn auto_launch_kernel(
                 ("deno", "deno".to_string(), None)
             } else {
                 // Default to Python
-                let env_source = if let Some(ref source) = inline_source {
+                let mut env_source = if let Some(ref source) = inline_source {
                     info!(
                         "[notebook-sync] Auto-launch: found inline deps -> {}",
                         source
@@ -1249,7 +1855,8 @@ async fn auto_launch_kernel(
                     );
                     None
                 } else {
-                    match acquire_pool_env_for_source(&env_source, &daemon, room).await {
+                    match acquire_pool_env_for_source(&mut env_source, env_id, &daemon, room).await
+                    {
                         Some(env) => env,
                         None => return, // Error already broadcast
                     }
@@ -1258,27 +1865,21 @@ async fn auto_launch_kernel(
             }
         }
         Some(other) => {
-            // Unknown kernel type - default to Python
-            warn!(
-                "[notebook-sync] Unknown kernel type '{}', defaulting to Python",
+            // Custom kernelspec - launched as-is, no environment resolution
+            info!(
+                "[notebook-sync] Auto-launch: {} kernel (notebook kernelspec)",
                 other
             );
-            let prewarmed = match default_python_env {
-                crate::settings_doc::PythonEnvType::Conda => "conda:prewarmed",
-                _ => "uv:prewarmed",
-            };
-            let pooled_env = match acquire_pool_env_for_source(prewarmed, &daemon, room).await {
-                Some(env) => env,
-                None => return,
-            };
-            ("python", prewarmed.to_string(), pooled_env)
+            (other, "kernelspec".to_string(), None)
         }
     };
 
     // For inline deps, prepare a cached environment with rich progress
-    let progress_handler: std::sync::Arc<dyn kernel_env::ProgressHandler> = std::sync::Arc::new(
-        crate::inline_env::BroadcastProgressHandler::new(room.kernel_broadcast_tx.clone()),
-    );
+    let progress_handler: std::sync::Arc<dyn kernel_env::ProgressHandler> =
+        std::sync::Arc::new(crate::inline_env::BroadcastProgressHandler::new(
+            room.kernel_broadcast_tx.clone(),
+            room.env_build_log.clone(),
+        ));
 
     let (pooled_env, inline_deps) = if env_source == "uv:inline" {
         if let Some(deps) = metadata_snapshot.as_ref().and_then(get_inline_uv_deps) {
@@ -1286,7 +1887,19 @@ async fn auto_launch_kernel(
                 "[notebook-sync] Preparing cached UV env for inline deps: {:?}",
                 deps
             );
-            match crate::inline_env::prepare_uv_inline_env(&deps, progress_handler.clone()).await {
+            let index_url = daemon
+                .synced_settings()
+                .await
+                .uv
+                .index_url()
+                .map(str::to_string);
+            match crate::inline_env::prepare_uv_inline_env(
+                &deps,
+                index_url.as_deref(),
+                progress_handler.clone(),
+            )
+            .await
+            {
                 Ok(prepared) => {
                     info!(
                         "[notebook-sync] Using cached inline env at {:?}",
@@ -1315,17 +1928,28 @@ async fn auto_launch_kernel(
         }
     } else if env_source == "conda:inline" {
         if let Some(deps) = metadata_snapshot.as_ref().and_then(get_inline_conda_deps) {
+            let conda_defaults = daemon.synced_settings().await.conda;
             let channels = metadata_snapshot
                 .as_ref()
-                .map(get_inline_conda_channels)
-                .unwrap_or_else(|| vec!["conda-forge".to_string()]);
+                .map(|s| get_inline_conda_channels(s, &conda_defaults))
+                .unwrap_or_else(|| conda_defaults.default_channels());
+            let channel_priority = metadata_snapshot
+                .as_ref()
+                .map(|s| get_inline_conda_channel_priority(s, &conda_defaults))
+                .unwrap_or_default();
             info!(
-                "[notebook-sync] Preparing cached Conda env for inline deps: {:?} (channels: {:?})",
-                deps, channels
+                "[notebook-sync] Preparing cached Conda env for inline deps: {:?} (channels: {:?}, priority: {:?})",
+                deps, channels, channel_priority
             );
+            let pip_deps = metadata_snapshot
+                .as_ref()
+                .map(get_inline_conda_pip_deps)
+                .unwrap_or_default();
             match crate::inline_env::prepare_conda_inline_env(
                 &deps,
                 &channels,
+                channel_priority,
+                &pip_deps,
                 progress_handler.clone(),
             )
             .await
@@ -1368,11 +1992,12 @@ async fn auto_launch_kernel(
         &env_source,
         inline_deps.as_deref(),
         metadata_snapshot.as_ref(),
+        &daemon.synced_settings().await.conda,
         venv_path,
         python_path,
     );
 
-    match kernel
+    let result = kernel
         .launch(
             kernel_type,
             &env_source,
@@ -1380,16 +2005,18 @@ async fn auto_launch_kernel(
             pooled_env,
             launched_config,
         )
-        .await
-    {
+        .await;
+    *room.last_launch_spec.write().await = kernel.last_launch_spec().cloned();
+    match result {
         Ok(()) => {
             let kt = kernel.kernel_type().to_string();
             let es = kernel.env_source().to_string();
+            daemon.record_notebook_env_source(notebook_id, &es).await;
 
             // Take the command receiver and spawn a task to process execution events
             if let Some(mut cmd_rx) = kernel.take_command_rx() {
                 let room_kernel = room.kernel.clone();
-                tokio::spawn(async move {
+                crate::log_format::spawn_in_scope(async move {
                     use crate::kernel_manager::QueueCommand;
                     while let Some(cmd) = cmd_rx.recv().await {
                         match cmd {
@@ -1405,12 +2032,33 @@ async fn auto_launch_kernel(
                             QueueCommand::CellError { cell_id } => {
                                 warn!("[notebook-sync] Cell error (stop-on-error): {}", cell_id);
                             }
-                        }
-                    }
-                });
-            }
-
-            *kernel_guard = Some(kernel);
+                            QueueCommand::KernelDied {
+                                exit_code,
+                                stderr_tail,
+                            } => {
+                                let mut guard = room_kernel.lock().await;
+                                if let Some(ref mut k) = *guard {
+                                    let cancelled = k.handle_process_exit(exit_code, stderr_tail);
+                                    warn!(
+                                        "[notebook-sync] Kernel crashed, cancelled {} cells",
+                                        cancelled.len()
+                                    );
+                                }
+                            }
+                            QueueCommand::InterruptAndClear => {
+                                let mut guard = room_kernel.lock().await;
+                                if let Some(ref mut k) = *guard {
+                                    if let Err(e) = k.interrupt_and_clear().await {
+                                        warn!("[notebook-sync] interrupt_and_clear error: {}", e);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                });
+            }
+
+            *kernel_guard = Some(kernel);
 
             // Broadcast kernel status to all connected peers
             let _ = room
@@ -1438,13 +2086,142 @@ async fn auto_launch_kernel(
     }
 }
 
+/// Get the room's language server client if `lsp.enabled` is set.
+///
+/// The server is started in the background on first use; until it's ready
+/// (or if it fails to start) this returns `None` and callers fall back to
+/// kernel-only results. Deno kernels never use the Python language server.
+async fn lsp_client_for_room(
+    room: &NotebookRoom,
+    daemon: &crate::daemon::Daemon,
+) -> Option<Arc<LspClient>> {
+    if !daemon.synced_settings().await.lsp.enabled {
+        return None;
+    }
+    let is_deno = room
+        .kernel
+        .lock()
+        .await
+        .as_ref()
+        .is_some_and(|k| k.kernel_type() == "deno");
+    if is_deno {
+        return None;
+    }
+
+    let mut state = room.lsp.lock().await;
+    match &*state {
+        LspState::Ready(client) => Some(client.clone()),
+        LspState::Starting | LspState::Failed => None,
+        LspState::NotStarted => {
+            *state = LspState::Starting;
+            let root = room
+                .notebook_path
+                .parent()
+                .filter(|p| p.is_dir())
+                .map(Path::to_path_buf)
+                .or_else(dirs::home_dir)
+                .unwrap_or_else(std::env::temp_dir);
+            let lsp = room.lsp.clone();
+            crate::log_format::spawn_in_scope(async move {
+                let next = match LspClient::start(&root).await {
+                    Ok(client) => {
+                        info!("[notebook-sync] Language server ready for {:?}", root);
+                        LspState::Ready(Arc::new(client))
+                    }
+                    Err(e) => {
+                        warn!("[notebook-sync] Language server unavailable: {}", e);
+                        LspState::Failed
+                    }
+                };
+                *lsp.lock().await = next;
+            });
+            None
+        }
+    }
+}
+
+/// Clear the outputs of `cell_ids` and queue them in the given order, reading
+/// sources from the synced doc. Unknown ids and non-code cells are skipped
+/// with a warning and reported back, rather than failing the whole batch.
+async fn run_cells(room: &NotebookRoom, cell_ids: Vec<String>) -> NotebookResponse {
+    // Read sources and clear outputs in one doc write
+    let (cells, skipped, dropped, persist_bytes) = {
+        let mut doc = room.doc.write().await;
+        let mut cells = Vec::with_capacity(cell_ids.len());
+        let mut skipped = Vec::new();
+        for cell_id in cell_ids {
+            match doc.get_cell(&cell_id) {
+                Some(cell) if cell.cell_type == "code" => cells.push(cell_tags::queued_cell(&cell)),
+                Some(cell) => {
+                    warn!(
+                        "[notebook-sync] Not running {} cell {}",
+                        cell.cell_type, cell_id
+                    );
+                    skipped.push(cell_id);
+                }
+                None => {
+                    warn!("[notebook-sync] Not running unknown cell {}", cell_id);
+                    skipped.push(cell_id);
+                }
+            }
+        }
+        let mut dropped = Vec::new();
+        for cell in &cells {
+            if let Some(snapshot) = doc.get_cell(&cell.cell_id) {
+                dropped.extend(snapshot.outputs);
+            }
+            if let Err(e) = doc.clear_outputs(&cell.cell_id) {
+                return NotebookResponse::Error {
+                    error: format!("Failed to clear outputs: {}", e),
+                };
+            }
+            let _ = doc.set_execution_count(&cell.cell_id, "null");
+        }
+        let bytes = doc.save();
+        let _ = room.changed_tx.send(());
+        (cells, skipped, dropped, bytes)
+    };
+    persist_notebook_bytes(&persist_bytes, &room.persist_path);
+    crate::output_store::release_outputs(&dropped, &room.blob_store).await;
+
+    for cell in &cells {
+        let _ = room
+            .kernel_broadcast_tx
+            .send(NotebookBroadcast::OutputsCleared {
+                cell_id: cell.cell_id.clone(),
+            });
+    }
+
+    let mut kernel_guard = room.kernel.lock().await;
+    if let Some(ref mut kernel) = *kernel_guard {
+        for cell in &cells {
+            kernel.clear_outputs(&cell.cell_id).await;
+        }
+        match kernel.execute_batch(&cells).await {
+            Ok(sent) => NotebookResponse::CellBatchQueued {
+                cell_ids: sent.into_iter().map(|(cell_id, _)| cell_id).collect(),
+                skipped,
+            },
+            Err(e) => NotebookResponse::Error {
+                error: format!("Failed to queue cell batch: {}", e),
+            },
+        }
+    } else {
+        NotebookResponse::NoKernel {}
+    }
+}
+
 /// Handle a NotebookRequest and return a NotebookResponse.
 async fn handle_notebook_request(
     room: &NotebookRoom,
     request: NotebookRequest,
     daemon: std::sync::Arc<crate::daemon::Daemon>,
 ) -> NotebookResponse {
-    info!("[notebook-sync] Handling request: {:?}", request);
+    info!(
+        notebook_id:% = room.notebook_path.display();
+        "[notebook-sync] Handling request: {:?}",
+        request
+    );
 
     match request {
         NotebookRequest::LaunchKernel {
@@ -1477,6 +2254,19 @@ async fn handle_notebook_request(
                 room.blob_store.clone(),
                 room.comm_state.clone(),
             );
+            kernel.set_manifest_options(manifest_options_from_settings(&daemon, room).await);
+            kernel.set_startup_policy(startup_policy_from_settings(&daemon).await);
+            kernel.set_run_log(run_log_from_settings(&daemon, room).await);
+            kernel
+                .set_install_suggestions(!daemon.synced_settings().await.hide_install_suggestions);
+            kernel.set_env_claims(
+                daemon.env_claims().clone(),
+                &room.notebook_path.to_string_lossy(),
+            );
+            kernel.set_kernel_pids(
+                daemon.kernel_pids().clone(),
+                &room.notebook_path.to_string_lossy(),
+            );
             let notebook_path = notebook_path.map(std::path::PathBuf::from);
 
             // Resolve metadata snapshot from Automerge doc (preferred) or disk
@@ -1499,49 +2289,62 @@ async fn handle_notebook_request(
                 resolved_kernel_type, kernel_type
             );
 
+            // Kernelspec kernels launch as their kernel.json describes; they
+            // never use uv/conda environments
+            let is_kernelspec = !matches!(resolved_kernel_type.as_str(), "python" | "deno");
+
             // Auto-detect environment if env_source is "auto" or empty
-            let resolved_env_source =
-                if env_source == "auto" || env_source.is_empty() || env_source == "prewarmed" {
-                    // Priority 1: Check inline deps in notebook metadata
-                    if let Some(inline_source) =
-                        metadata_snapshot.as_ref().and_then(check_inline_deps)
-                    {
-                        info!(
-                            "[notebook-sync] Found inline deps in notebook metadata -> {}",
-                            inline_source
-                        );
+            let resolved_env_source = if is_kernelspec {
+                "kernelspec".to_string()
+            } else if env_source == "auto" || env_source.is_empty() || env_source == "prewarmed" {
+                // Priority 1: Check inline deps in notebook metadata
+                if let Some(inline_source) = metadata_snapshot.as_ref().and_then(check_inline_deps)
+                {
+                    info!(
+                        "[notebook-sync] Found inline deps in notebook metadata -> {}",
                         inline_source
-                    }
-                    // Priority 2: Detect project files near notebook path
-                    else if let Some(detected) = notebook_path
-                        .as_ref()
-                        .and_then(|path| crate::project_file::detect_project_file(path))
-                    {
-                        info!(
-                            "[notebook-sync] Auto-detected project file: {:?} -> {}",
-                            detected.path,
-                            detected.to_env_source()
-                        );
-                        detected.to_env_source().to_string()
-                    }
-                    // Priority 3: Fall back to prewarmed
-                    else {
-                        info!("[notebook-sync] No project file detected, using prewarmed");
-                        "uv:prewarmed".to_string()
-                    }
-                } else {
-                    // Use explicit env_source (e.g., "uv:inline", "conda:inline")
-                    env_source.clone()
-                };
+                    );
+                    inline_source
+                }
+                // Priority 2: Detect project files near notebook path
+                else if let Some(detected) = notebook_path
+                    .as_ref()
+                    .and_then(|path| crate::project_file::detect_project_file(path))
+                {
+                    info!(
+                        "[notebook-sync] Auto-detected project file: {:?} -> {}",
+                        detected.path,
+                        detected.to_env_source()
+                    );
+                    detected.to_env_source().to_string()
+                }
+                // Priority 3: Fall back to prewarmed
+                else {
+                    info!("[notebook-sync] No project file detected, using prewarmed");
+                    "uv:prewarmed".to_string()
+                }
+            } else {
+                // Use explicit env_source (e.g., "uv:inline", "conda:inline")
+                env_source.clone()
+            };
 
-            // Deno kernels don't need pooled environments
+            // Deno and kernelspec kernels don't need pooled environments
+            let env_id = metadata_snapshot
+                .as_ref()
+                .and_then(|s| s.runt.env_id.as_deref());
             let pooled_env = if resolved_kernel_type == "deno" {
                 info!("[notebook-sync] LaunchKernel: Deno kernel (no pooled env)");
                 None
+            } else if is_kernelspec {
+                info!(
+                    "[notebook-sync] LaunchKernel: {} kernelspec (no pooled env)",
+                    resolved_kernel_type
+                );
+                None
             } else {
                 // Python kernels require pooled environment
                 match resolved_env_source.as_str() {
-                    "uv:prewarmed" => match daemon.take_uv_env().await {
+                    "uv:prewarmed" => match daemon.take_uv_env(env_id).await {
                         Some(env) => {
                             info!(
                                 "[notebook-sync] LaunchKernel: acquired UV env from pool: {:?}",
@@ -1555,7 +2358,7 @@ async fn handle_notebook_request(
                             };
                         }
                     },
-                    "conda:prewarmed" => match daemon.take_conda_env().await {
+                    "conda:prewarmed" => match daemon.take_conda_env(env_id).await {
                         Some(env) => {
                             info!(
                                 "[notebook-sync] LaunchKernel: acquired Conda env from pool: {:?}",
@@ -1569,8 +2372,9 @@ async fn handle_notebook_request(
                             };
                         }
                     },
-                    "uv:pyproject" | "uv:inline" | "conda:inline" => {
-                        // These sources prepare their own environments, no pooled env needed
+                    "uv:pyproject" | "uv:inline" | "conda:inline" | "system:python" => {
+                        // These sources prepare their own environments (or, for the
+                        // system Python, have none), no pooled env needed
                         info!(
                             "[notebook-sync] LaunchKernel: {} prepares its own env, no pool env",
                             resolved_env_source
@@ -1580,7 +2384,7 @@ async fn handle_notebook_request(
                     other => {
                         // For remaining conda sources, route to conda pool
                         if other.starts_with("conda:") {
-                            match daemon.take_conda_env().await {
+                            match daemon.take_conda_env(env_id).await {
                                 Some(env) => Some(env),
                                 None => {
                                     return NotebookResponse::Error {
@@ -1590,7 +2394,7 @@ async fn handle_notebook_request(
                             }
                         } else {
                             // Prewarmed UV
-                            match daemon.take_uv_env().await {
+                            match daemon.take_uv_env(env_id).await {
                                 Some(env) => Some(env),
                                 None => {
                                     return NotebookResponse::Error {
@@ -1607,6 +2411,7 @@ async fn handle_notebook_request(
             let launch_progress_handler: std::sync::Arc<dyn kernel_env::ProgressHandler> =
                 std::sync::Arc::new(crate::inline_env::BroadcastProgressHandler::new(
                     room.kernel_broadcast_tx.clone(),
+                    room.env_build_log.clone(),
                 ));
 
             let (pooled_env, inline_deps) = if resolved_env_source == "uv:inline" {
@@ -1615,8 +2420,15 @@ async fn handle_notebook_request(
                         "[notebook-sync] LaunchKernel: Preparing cached UV env for inline deps: {:?}",
                         deps
                     );
+                    let index_url = daemon
+                        .synced_settings()
+                        .await
+                        .uv
+                        .index_url()
+                        .map(str::to_string);
                     match crate::inline_env::prepare_uv_inline_env(
                         &deps,
+                        index_url.as_deref(),
                         launch_progress_handler.clone(),
                     )
                     .await
@@ -1644,17 +2456,28 @@ async fn handle_notebook_request(
                 }
             } else if resolved_env_source == "conda:inline" {
                 if let Some(deps) = metadata_snapshot.as_ref().and_then(get_inline_conda_deps) {
+                    let conda_defaults = daemon.synced_settings().await.conda;
                     let channels = metadata_snapshot
                         .as_ref()
-                        .map(get_inline_conda_channels)
-                        .unwrap_or_else(|| vec!["conda-forge".to_string()]);
+                        .map(|s| get_inline_conda_channels(s, &conda_defaults))
+                        .unwrap_or_else(|| conda_defaults.default_channels());
+                    let channel_priority = metadata_snapshot
+                        .as_ref()
+                        .map(|s| get_inline_conda_channel_priority(s, &conda_defaults))
+                        .unwrap_or_default();
                     info!(
-                        "[notebook-sync] LaunchKernel: Preparing cached Conda env for inline deps: {:?} (channels: {:?})",
-                        deps, channels
+                        "[notebook-sync] LaunchKernel: Preparing cached Conda env for inline deps: {:?} (channels: {:?}, priority: {:?})",
+                        deps, channels, channel_priority
                     );
+                    let pip_deps = metadata_snapshot
+                        .as_ref()
+                        .map(get_inline_conda_pip_deps)
+                        .unwrap_or_default();
                     match crate::inline_env::prepare_conda_inline_env(
                         &deps,
                         &channels,
+                        channel_priority,
+                        &pip_deps,
                         launch_progress_handler.clone(),
                     )
                     .await
@@ -1692,11 +2515,12 @@ async fn handle_notebook_request(
                 &resolved_env_source,
                 inline_deps.as_deref(),
                 metadata_snapshot.as_ref(),
+                &daemon.synced_settings().await.conda,
                 venv_path,
                 python_path,
             );
 
-            match kernel
+            let result = kernel
                 .launch(
                     &resolved_kernel_type,
                     &resolved_env_source,
@@ -1704,16 +2528,20 @@ async fn handle_notebook_request(
                     pooled_env,
                     launched_config.clone(),
                 )
-                .await
-            {
+                .await;
+            *room.last_launch_spec.write().await = kernel.last_launch_spec().cloned();
+            match result {
                 Ok(()) => {
                     let kt = kernel.kernel_type().to_string();
                     let es = kernel.env_source().to_string();
+                    daemon
+                        .record_notebook_env_source(&room.notebook_path.to_string_lossy(), &es)
+                        .await;
 
                     // Take the command receiver and spawn a task to process execution events
                     if let Some(mut cmd_rx) = kernel.take_command_rx() {
                         let room_kernel = room.kernel.clone();
-                        tokio::spawn(async move {
+                        crate::log_format::spawn_in_scope(async move {
                             use crate::kernel_manager::QueueCommand;
                             while let Some(cmd) = cmd_rx.recv().await {
                                 match cmd {
@@ -1740,7 +2568,7 @@ async fn handle_notebook_request(
                                         // Clear the queue to stop execution on error
                                         let mut guard = room_kernel.lock().await;
                                         if let Some(ref mut k) = *guard {
-                                            let cleared = k.clear_queue();
+                                            let cleared = k.handle_cell_error(&cell_id);
                                             if !cleared.is_empty() {
                                                 info!(
                                                     "[notebook-sync] Cleared {} queued cells due to error",
@@ -1749,6 +2577,31 @@ async fn handle_notebook_request(
                                             }
                                         }
                                     }
+                                    QueueCommand::KernelDied {
+                                        exit_code,
+                                        stderr_tail,
+                                    } => {
+                                        let mut guard = room_kernel.lock().await;
+                                        if let Some(ref mut k) = *guard {
+                                            let cancelled =
+                                                k.handle_process_exit(exit_code, stderr_tail);
+                                            warn!(
+                                                "[notebook-sync] Kernel crashed, cancelled {} cells",
+                                                cancelled.len()
+                                            );
+                                        }
+                                    }
+                                    QueueCommand::InterruptAndClear => {
+                                        let mut guard = room_kernel.lock().await;
+                                        if let Some(ref mut k) = *guard {
+                                            if let Err(e) = k.interrupt_and_clear().await {
+                                                warn!(
+                                                    "[notebook-sync] interrupt_and_clear error: {}",
+                                                    e
+                                                );
+                                            }
+                                        }
+                                    }
                                 }
                             }
                             info!(
@@ -1786,6 +2639,8 @@ async fn handle_notebook_request(
         }
 
         NotebookRequest::ExecuteCell { cell_id } => {
+            refresh_output_trust(room).await;
+
             // Read cell source FIRST (before kernel lock) to avoid holding
             // kernel mutex while waiting on doc lock
             let (source, cell_type) = {
@@ -1824,10 +2679,16 @@ async fn handle_notebook_request(
             }
         }
 
+        NotebookRequest::QueueCellBatch { cell_ids } => run_cells(room, cell_ids).await,
+
         NotebookRequest::ClearOutputs { cell_id } => {
             // 1. Mutate the Automerge document to remove outputs
-            let persist_bytes = {
+            let (dropped, persist_bytes) = {
                 let mut doc = room.doc.write().await;
+                let dropped = doc
+                    .get_cell(&cell_id)
+                    .map(|cell| cell.outputs)
+                    .unwrap_or_default();
                 if let Err(e) = doc.clear_outputs(&cell_id) {
                     return NotebookResponse::Error {
                         error: format!("Failed to clear outputs: {}", e),
@@ -1838,11 +2699,12 @@ async fn handle_notebook_request(
                 let bytes = doc.save();
                 // Notify other peers of doc change
                 let _ = room.changed_tx.send(());
-                bytes
+                (dropped, bytes)
             };
 
-            // 2. Persist outside the write lock
+            // 2. Persist outside the write lock, then free the outputs' blobs
             persist_notebook_bytes(&persist_bytes, &room.persist_path);
+            crate::output_store::release_outputs(&dropped, &room.blob_store).await;
 
             // 3. Broadcast for cross-window UI sync (fast path)
             let _ = room
@@ -1860,6 +2722,25 @@ async fn handle_notebook_request(
             NotebookResponse::OutputsCleared { cell_id }
         }
 
+        NotebookRequest::ClearAllOutputs {} => match clear_all_outputs(room).await {
+            Ok((cell_ids, blobs_reclaimed)) => NotebookResponse::AllOutputsCleared {
+                cell_ids,
+                blobs_reclaimed,
+            },
+            Err(error) => NotebookResponse::Error { error },
+        },
+
+        NotebookRequest::CopyOutputs {
+            from_cell_id,
+            to_cell_id,
+        } => match copy_outputs(room, &from_cell_id, &to_cell_id).await {
+            Ok(count) => NotebookResponse::OutputsCopied {
+                cell_id: to_cell_id,
+                count,
+            },
+            Err(error) => NotebookResponse::Error { error },
+        },
+
         NotebookRequest::InterruptExecution {} => {
             let mut kernel_guard = room.kernel.lock().await;
             if let Some(ref mut kernel) = *kernel_guard {
@@ -1874,6 +2755,31 @@ async fn handle_notebook_request(
             }
         }
 
+        NotebookRequest::CancelRunAll {} => {
+            // Through the queue, so it's ordered with the kernel's own
+            // ExecutionDone/CellError commands
+            let sender = room
+                .kernel
+                .lock()
+                .await
+                .as_ref()
+                .and_then(|kernel| kernel.command_sender());
+            match sender {
+                Some(sender) => {
+                    match sender
+                        .send(crate::kernel_manager::QueueCommand::InterruptAndClear)
+                        .await
+                    {
+                        Ok(()) => NotebookResponse::InterruptSent {},
+                        Err(_) => NotebookResponse::Error {
+                            error: "Kernel queue is no longer running".to_string(),
+                        },
+                    }
+                }
+                None => NotebookResponse::NoKernel {},
+            }
+        }
+
         NotebookRequest::ShutdownKernel {} => {
             let mut kernel_guard = room.kernel.lock().await;
             if let Some(ref mut kernel) = *kernel_guard {
@@ -1899,7 +2805,7 @@ async fn handle_notebook_request(
                 if kernel.is_running() {
                     NotebookResponse::KernelInfo {
                         kernel_type: Some(kernel.kernel_type().to_string()),
-                        env_source: Some(kernel.env_source().to_string()),
+                        env_source: kernel.running_env_source().map(String::from),
                         status: kernel.status().to_string(),
                     }
                 } else {
@@ -1918,6 +2824,8 @@ async fn handle_notebook_request(
             }
         }
 
+        NotebookRequest::Ping {} => NotebookResponse::Pong {},
+
         NotebookRequest::GetQueueState {} => {
             let kernel_guard = room.kernel.lock().await;
             if let Some(ref kernel) = *kernel_guard {
@@ -1934,26 +2842,22 @@ async fn handle_notebook_request(
         }
 
         NotebookRequest::RunAllCells {} => {
+            refresh_output_trust(room).await;
             let mut kernel_guard = room.kernel.lock().await;
             if let Some(ref mut kernel) = *kernel_guard {
-                // Read all cells from the synced Automerge document
-                let doc = room.doc.read().await;
-                let cells = doc.get_cells();
-
-                // Queue all code cells in document order
-                let mut count = 0;
-                for cell in cells {
-                    if cell.cell_type == "code" {
-                        if let Err(e) = kernel
-                            .queue_cell(cell.id.clone(), cell.source.clone())
-                            .await
-                        {
-                            return NotebookResponse::Error {
-                                error: format!("Failed to queue cell {}: {}", cell.id, e),
-                            };
-                        }
-                        count += 1;
-                    }
+                // Read the code cells to run from the synced Automerge
+                // document, honoring skip-execution and raises-exception tags
+                let cells = {
+                    let doc = room.doc.read().await;
+                    cell_tags::run_all_cells(&doc.get_cells())
+                };
+                let count = cells.len();
+
+                // Pipeline all code cells in document order
+                if let Err(e) = kernel.run_all(&cells).await {
+                    return NotebookResponse::Error {
+                        error: format!("Failed to queue cells: {}", e),
+                    };
                 }
 
                 NotebookResponse::AllCellsQueued { count }
@@ -1991,16 +2895,80 @@ async fn handle_notebook_request(
         }
 
         NotebookRequest::Complete { code, cursor_pos } => {
-            let mut kernel_guard = room.kernel.lock().await;
-            if let Some(ref mut kernel) = *kernel_guard {
-                match kernel.complete(code, cursor_pos).await {
-                    Ok((items, cursor_start, cursor_end)) => NotebookResponse::CompletionResult {
+            let lsp = lsp_client_for_room(room, &daemon).await;
+
+            // Query kernel and language server concurrently
+            let kernel_future = async {
+                let mut kernel_guard = room.kernel.lock().await;
+                match kernel_guard.as_mut() {
+                    Some(kernel) => Some(kernel.complete(code.clone(), cursor_pos).await),
+                    None => None,
+                }
+            };
+            let lsp_future = async {
+                let client = lsp.as_ref()?;
+                match client.complete(&code, cursor_pos).await {
+                    Ok(items) => Some(items),
+                    Err(e) => {
+                        debug!("[notebook-sync] LSP completion unavailable: {}", e);
+                        None
+                    }
+                }
+            };
+            let (kernel_result, lsp_items) = tokio::join!(kernel_future, lsp_future);
+
+            match (kernel_result, lsp_items) {
+                (Some(Ok((items, cursor_start, cursor_end))), lsp_items) => {
+                    NotebookResponse::CompletionResult {
+                        items: lsp_proxy::merge_completions(items, lsp_items.unwrap_or_default()),
+                        cursor_start,
+                        cursor_end,
+                    }
+                }
+                (_, Some(items)) => {
+                    let (cursor_start, cursor_end) = lsp_proxy::identifier_range(&code, cursor_pos);
+                    NotebookResponse::CompletionResult {
                         items,
                         cursor_start,
                         cursor_end,
+                    }
+                }
+                (Some(Err(e)), None) => NotebookResponse::Error {
+                    error: format!("Failed to get completions: {}", e),
+                },
+                (None, None) => NotebookResponse::NoKernel {},
+            }
+        }
+
+        NotebookRequest::Inspect {
+            code,
+            cursor_pos,
+            detail_level,
+        } => {
+            if let Some(client) = lsp_client_for_room(room, &daemon).await {
+                match client.hover(&code, cursor_pos).await {
+                    Ok(Some(text)) => {
+                        return NotebookResponse::InspectResult {
+                            found: true,
+                            text: Some(text),
+                            source: Some("lsp".to_string()),
+                        };
+                    }
+                    Ok(None) => {}
+                    Err(e) => debug!("[notebook-sync] LSP hover unavailable: {}", e),
+                }
+            }
+
+            let mut kernel_guard = room.kernel.lock().await;
+            if let Some(ref mut kernel) = *kernel_guard {
+                match kernel.inspect(code, cursor_pos, detail_level).await {
+                    Ok((found, text)) => NotebookResponse::InspectResult {
+                        found,
+                        text,
+                        source: Some("kernel".to_string()),
                     },
                     Err(e) => NotebookResponse::Error {
-                        error: format!("Failed to get completions: {}", e),
+                        error: format!("Failed to inspect: {}", e),
                     },
                 }
             } else {
@@ -2019,205 +2987,714 @@ async fn handle_notebook_request(
         }
 
         NotebookRequest::SyncEnvironment {} => handle_sync_environment(room).await,
-    }
-}
-
-/// Handle sync environment request - hot-install new packages without kernel restart.
-///
-/// Only supported for UV inline dependencies when there are only additions (no removals).
-/// Conda and other env types fall back to restart.
-async fn handle_sync_environment(room: &NotebookRoom) -> NotebookResponse {
-    use crate::inline_env::UvEnvironment;
-
-    // Get kernel info, venv_path, python_path, and launch_id while holding lock, then release
-    let (launched, venv_path, python_path, launch_id) = {
-        let kernel_guard = room.kernel.lock().await;
-
-        // Check if kernel is running
-        let kernel = match kernel_guard.as_ref() {
-            Some(k) if k.is_running() => k,
-            Some(_) | None => {
-                return NotebookResponse::SyncEnvironmentFailed {
-                    error: "No kernel running".to_string(),
-                    needs_restart: false,
-                };
-            }
-        };
-
-        let launched = kernel.launched_config().clone();
 
-        // Only UV inline deps support hot-sync
-        if launched.uv_deps.is_none() {
-            return NotebookResponse::SyncEnvironmentFailed {
-                error: "Hot-sync only supported for UV inline dependencies".to_string(),
-                needs_restart: true,
-            };
+        NotebookRequest::RepairEnvironment {} => {
+            let settings = daemon.synced_settings().await;
+            handle_repair_environment(room, &settings.uv, &settings.conda).await
         }
 
-        let venv_path = match &launched.venv_path {
-            Some(path) => path.clone(),
-            None => {
-                return NotebookResponse::SyncEnvironmentFailed {
-                    error: "No venv path stored - restart required".to_string(),
-                    needs_restart: true,
-                };
-            }
-        };
+        NotebookRequest::PinEnvironment {} => handle_pin_environment(room, &daemon).await,
 
-        let python_path = match &launched.python_path {
-            Some(path) => path.clone(),
-            None => {
-                return NotebookResponse::SyncEnvironmentFailed {
-                    error: "No python path stored - restart required".to_string(),
-                    needs_restart: true,
-                };
-            }
-        };
+        NotebookRequest::UnpinEnvironment {} => handle_unpin_environment(room, &daemon).await,
 
-        // Capture launch_id to detect if kernel is swapped during async install
-        let launch_id = launched.launch_id.clone();
+        NotebookRequest::GetDependencyTree {} => handle_get_dependency_tree(room).await,
 
-        (launched, venv_path, python_path, launch_id)
-    };
+        NotebookRequest::GetResolvedCells {} => NotebookResponse::ResolvedCells {
+            cells: resolved_cells(room).await,
+        },
 
-    // Get current metadata from the document
-    let current_metadata = match resolve_metadata_snapshot(room, Some(&room.notebook_path)).await {
-        Some(m) => m,
-        None => {
-            return NotebookResponse::SyncEnvironmentFailed {
-                error: "Could not read notebook metadata".to_string(),
-                needs_restart: false,
-            };
-        }
-    };
+        NotebookRequest::ListEnvironments {} => NotebookResponse::Environments {
+            options: room_env_options(room, &daemon, saved_notebook_path(room, None)).await,
+        },
 
-    // Compute diff
-    let diff = compute_env_sync_diff(&launched, &current_metadata);
+        NotebookRequest::ListNotebooks {} => NotebookResponse::Notebooks {
+            notebooks: daemon.registered_notebooks().await,
+        },
 
-    match diff {
-        None => {
-            // Already in sync
-            NotebookResponse::SyncEnvironmentComplete {
-                synced_packages: vec![],
+        NotebookRequest::SearchOutputs { query } => {
+            // Release the doc before reading manifests from the blob store
+            let outputs = room.doc.read().await.get_all_outputs();
+            NotebookResponse::OutputMatches {
+                matches: crate::output_search::search_outputs(&outputs, &room.blob_store, &query)
+                    .await,
             }
         }
-        Some(d) => {
-            // Check if there are removals - require restart for those
-            if !d.removed.is_empty() {
-                return NotebookResponse::SyncEnvironmentFailed {
-                    error: format!(
-                        "Cannot remove packages from running env: {:?}. Restart required.",
-                        d.removed
-                    ),
-                    needs_restart: true,
-                };
-            }
 
-            // Check for channel changes (conda only, but check anyway)
-            if d.channels_changed {
-                return NotebookResponse::SyncEnvironmentFailed {
-                    error: "Channel changes require restart".to_string(),
-                    needs_restart: true,
+        NotebookRequest::GetCellOutputs { cell_id } => {
+            let Some(cell) = room.doc.read().await.get_cell(&cell_id) else {
+                return NotebookResponse::Error {
+                    error: format!("Cell not found in document: {}", cell_id),
                 };
+            };
+            let blob_base_url = daemon.blob_base_url().await;
+            NotebookResponse::CellOutputs {
+                outputs: crate::output_store::cell_output_manifests(
+                    &cell.outputs,
+                    &room.blob_store,
+                    blob_base_url.as_deref(),
+                )
+                .await,
+                cell_id,
             }
+        }
 
-            // Nothing to add?
-            if d.added.is_empty() {
-                return NotebookResponse::SyncEnvironmentComplete {
-                    synced_packages: vec![],
-                };
-            }
+        NotebookRequest::GetCellTimings {} => {
+            let timings = room
+                .doc
+                .read()
+                .await
+                .get_execution_timings()
+                .into_iter()
+                .map(|(cell_id, timing)| CellTiming { cell_id, timing })
+                .collect();
+            NotebookResponse::CellTimings { timings }
+        }
 
-            // Send started notification
-            let packages_to_install = d.added.clone();
-            let _ = room
-                .kernel_broadcast_tx
-                .send(NotebookBroadcast::EnvProgress {
-                    env_type: "uv".to_string(),
-                    phase: kernel_env::progress::EnvProgressPhase::InstallingPackages {
-                        packages: packages_to_install.clone(),
-                    },
-                });
+        NotebookRequest::GetRunLog { limit } => NotebookResponse::RunLog {
+            entries: crate::run_log::read_recent(&room.notebook_path, limit),
+        },
 
-            // Build UvEnvironment and call sync_dependencies
-            let env = UvEnvironment {
-                venv_path: venv_path.clone(),
-                python_path: python_path.clone(),
-            };
+        NotebookRequest::GetLaunchSpec {} => NotebookResponse::LaunchSpec {
+            spec: room.last_launch_spec.read().await.clone(),
+        },
 
-            info!(
-                "[notebook-sync] Hot-syncing {} packages to {:?}",
-                packages_to_install.len(),
-                venv_path
-            );
+        NotebookRequest::GetConnectionFile {} => NotebookResponse::ConnectionFile {
+            path: room
+                .kernel
+                .lock()
+                .await
+                .as_ref()
+                .and_then(|kernel| kernel.connection_file())
+                .map(Path::to_path_buf),
+        },
 
-            match kernel_env::uv::sync_dependencies(&env, &packages_to_install).await {
-                Ok(()) => {
+        // Connections intercept this to track their subscription (see
+        // run_sync_loop_v2); answered here for callers without one.
+        NotebookRequest::StreamEnvBuildLog { subscribe } => stream_env_build_log(room, subscribe).0,
+
+        NotebookRequest::SnapshotKernelState {} => {
+            let path = kernel_snapshot::snapshot_path(&room.persist_path);
+            // Only sending the code needs the kernel; the run can take up to
+            // 25s, so the lock is released before waiting on it
+            let started = match room.kernel.lock().await.as_mut() {
+                Some(kernel) => kernel.snapshot_state(&path).await,
+                None => return NotebookResponse::NoKernel {},
+            };
+            let result = match started {
+                Ok(run) => run.finish().await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(report) => {
                     info!(
-                        "[notebook-sync] Hot-sync complete: {:?}",
-                        packages_to_install
+                        "[notebook-sync] Snapshotted kernel state: {} saved, {} dropped",
+                        report.names.len(),
+                        report.dropped.len()
                     );
+                    NotebookResponse::KernelStateSnapshot {
+                        saved: report.names,
+                        dropped: report.dropped,
+                    }
+                }
+                Err(e) => NotebookResponse::Error {
+                    error: format!("Failed to snapshot kernel state: {}", e),
+                },
+            }
+        }
 
-                    // Verify kernel wasn't swapped during async install (race protection)
-                    // Update the kernel's launched config so future sync checks are accurate
-                    let mut kernel_guard = room.kernel.lock().await;
-                    if let Some(ref mut kernel) = *kernel_guard {
-                        // Check launch_id still matches - if kernel was restarted, skip update
-                        let current_launch_id = kernel.launched_config().launch_id.clone();
-                        if current_launch_id != launch_id {
-                            warn!(
-                                "[notebook-sync] Kernel was swapped during hot-sync, skipping update"
-                            );
-                            // Still report success - packages were installed to the old env
-                            // User will see sync banner again for the new kernel
-                        } else if let Some(ref current_uv) = current_metadata.runt.uv {
-                            kernel.update_launched_uv_deps(current_uv.dependencies.clone());
-                        }
+        NotebookRequest::RestoreKernelState {} => {
+            let path = kernel_snapshot::snapshot_path(&room.persist_path);
+            // As with snapshots, don't hold the kernel while the run finishes
+            let started = match room.kernel.lock().await.as_mut() {
+                Some(kernel) => kernel.restore_state(&path).await,
+                None => return NotebookResponse::NoKernel {},
+            };
+            let result = match started {
+                Ok(run) => run.finish().await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(report) => {
+                    info!(
+                        "[notebook-sync] Restored kernel state: {} restored, {} dropped",
+                        report.names.len(),
+                        report.dropped.len()
+                    );
+                    NotebookResponse::KernelStateRestored {
+                        restored: report.names,
+                        dropped: report.dropped,
                     }
+                }
+                Err(e) => NotebookResponse::Error {
+                    error: format!("Failed to restore kernel state: {}", e),
+                },
+            }
+        }
 
-                    // Broadcast ready
-                    let _ = room
-                        .kernel_broadcast_tx
-                        .send(NotebookBroadcast::EnvProgress {
-                            env_type: "uv".to_string(),
-                            phase: kernel_env::progress::EnvProgressPhase::Ready {
-                                env_path: venv_path.to_string_lossy().to_string(),
-                                python_path: env.python_path.to_string_lossy().to_string(),
-                            },
-                        });
+        NotebookRequest::Debug { request } => {
+            // Only sending needs the kernel; the reply can take up to 10s,
+            // so the lock is released before waiting on it
+            let sent = match room.kernel.lock().await.as_mut() {
+                Some(kernel) => match request {
+                    DebugCommand::Initialize {} => kernel.debug_initialize().await,
+                    DebugCommand::SetBreakpoints { path, lines } => {
+                        kernel.debug_set_breakpoints(&path, &lines).await
+                    }
+                    DebugCommand::Continue { thread_id } => kernel.debug_continue(thread_id).await,
+                    DebugCommand::StackTrace { thread_id } => {
+                        kernel.debug_stack_trace(thread_id).await
+                    }
+                },
+                None => return NotebookResponse::NoKernel {},
+            };
+            let result = match sent {
+                Ok(exchange) => exchange.response().await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(body) => NotebookResponse::DebugResult { body },
+                Err(e) => NotebookResponse::Error {
+                    error: format!("Debug request failed: {}", e),
+                },
+            }
+        }
 
-                    // Broadcast that we're back in sync
-                    let _ = room
-                        .kernel_broadcast_tx
-                        .send(NotebookBroadcast::EnvSyncState {
-                            in_sync: true,
-                            diff: None,
-                        });
+        NotebookRequest::SwitchEnvironment {
+            option_id,
+            notebook_path,
+        } => {
+            let notebook_path = saved_notebook_path(room, notebook_path);
+            let Some(option) = room_env_options(room, &daemon, notebook_path.clone())
+                .await
+                .into_iter()
+                .find(|o| o.id == option_id)
+            else {
+                return NotebookResponse::Error {
+                    error: format!("Unknown environment option: {}", option_id),
+                };
+            };
 
-                    NotebookResponse::SyncEnvironmentComplete {
-                        synced_packages: packages_to_install,
-                    }
+            // Tear down the current kernel so LaunchKernel starts a fresh one
+            if let Some(mut kernel) = room.kernel.lock().await.take() {
+                if let Err(e) = kernel.shutdown().await {
+                    warn!(
+                        "[notebook-sync] Failed to shut down kernel before switching env: {}",
+                        e
+                    );
                 }
-                Err(e) => {
-                    error!("[notebook-sync] Hot-sync failed: {}", e);
+            }
+            room.comm_state.clear().await;
 
-                    // Broadcast error
-                    let _ = room
-                        .kernel_broadcast_tx
-                        .send(NotebookBroadcast::EnvProgress {
-                            env_type: "uv".to_string(),
-                            phase: kernel_env::progress::EnvProgressPhase::Error {
-                                message: e.to_string(),
-                            },
-                        });
+            info!(
+                "[notebook-sync] Switching environment to {}",
+                option.env_source
+            );
+            Box::pin(handle_notebook_request(
+                room,
+                NotebookRequest::LaunchKernel {
+                    kernel_type: "auto".to_string(),
+                    env_source: option.env_source,
+                    notebook_path: notebook_path.map(|p| p.to_string_lossy().into_owned()),
+                },
+                daemon,
+            ))
+            .await
+        }
+    }
+}
+
+/// Rebuild the room's inline-deps environment if it is broken.
+///
+/// Only inline environments are repairable: they are the ones whose
+/// dependency set is recorded in the notebook metadata.
+async fn handle_repair_environment(
+    room: &NotebookRoom,
+    uv_defaults: &UvDefaults,
+    conda_defaults: &CondaDefaults,
+) -> NotebookResponse {
+    if let Some(ref kernel) = *room.kernel.lock().await {
+        if kernel.is_running() {
+            return NotebookResponse::Error {
+                error: "Shut down the kernel before repairing its environment".to_string(),
+            };
+        }
+    }
+
+    let notebook_path = room
+        .notebook_path
+        .exists()
+        .then(|| room.notebook_path.clone());
+    let snapshot = resolve_metadata_snapshot(room, notebook_path.as_deref()).await;
+
+    let handler: std::sync::Arc<dyn kernel_env::ProgressHandler> =
+        std::sync::Arc::new(crate::inline_env::BroadcastProgressHandler::new(
+            room.kernel_broadcast_tx.clone(),
+            room.env_build_log.clone(),
+        ));
+    let (env_source, result) = if let Some(deps) = snapshot.as_ref().and_then(get_inline_uv_deps) {
+        (
+            "uv:inline",
+            crate::inline_env::repair_uv_inline_env(&deps, uv_defaults.index_url(), handler).await,
+        )
+    } else if let Some(deps) = snapshot.as_ref().and_then(get_inline_conda_deps) {
+        let channels = snapshot
+            .as_ref()
+            .map(|s| get_inline_conda_channels(s, conda_defaults))
+            .unwrap_or_default();
+        let channel_priority = snapshot
+            .as_ref()
+            .map(|s| get_inline_conda_channel_priority(s, conda_defaults))
+            .unwrap_or_default();
+        let pip_deps = snapshot
+            .as_ref()
+            .map(get_inline_conda_pip_deps)
+            .unwrap_or_default();
+        (
+            "conda:inline",
+            crate::inline_env::repair_conda_inline_env(
+                &deps,
+                &channels,
+                channel_priority,
+                &pip_deps,
+                handler,
+            )
+            .await,
+        )
+    } else {
+        return NotebookResponse::Error {
+            error: "Notebook has no inline dependencies to rebuild an environment from".to_string(),
+        };
+    };
+
+    match result {
+        Ok(rebuilt) => {
+            info!(
+                "[notebook-sync] Repaired {} environment (rebuilt={})",
+                env_source, rebuilt
+            );
+            NotebookResponse::EnvironmentRepaired {
+                env_source: env_source.to_string(),
+                rebuilt,
+            }
+        }
+        Err(e) => NotebookResponse::Error {
+            error: format!("Failed to repair {} environment: {}", env_source, e),
+        },
+    }
+}
+
+/// Pin the running kernel's prewarmed env to the notebook's env_id.
+async fn handle_pin_environment(
+    room: &NotebookRoom,
+    daemon: &crate::daemon::Daemon,
+) -> NotebookResponse {
+    let (env_id, venv_path, python_path) = {
+        let guard = room.kernel.lock().await;
+        let Some(kernel) = guard.as_ref().filter(|k| k.is_running()) else {
+            return NotebookResponse::Error {
+                error: "Start the kernel before pinning its environment".to_string(),
+            };
+        };
+        let config = kernel.launched_config();
+        let Some(env_id) = config.env_id.clone() else {
+            return NotebookResponse::Error {
+                error: "Notebook has no env_id to pin an environment to".to_string(),
+            };
+        };
+        match (config.venv_path.clone(), config.python_path.clone()) {
+            (Some(venv_path), Some(python_path)) => (env_id, venv_path, python_path),
+            _ => {
+                return NotebookResponse::Error {
+                    error: format!(
+                        "The {} kernel has no environment to pin",
+                        kernel.env_source()
+                    ),
+                };
+            }
+        }
+    };
+
+    let notebook_id = room.notebook_path.to_string_lossy();
+    match daemon
+        .pin_env(&env_id, &notebook_id, &venv_path, &python_path)
+        .await
+    {
+        Ok(env) => NotebookResponse::EnvironmentPinned {
+            env_id,
+            env_path: env.venv_path,
+        },
+        Err(error) => NotebookResponse::Error { error },
+    }
+}
+
+/// Release the env pinned to the notebook's env_id.
+async fn handle_unpin_environment(
+    room: &NotebookRoom,
+    daemon: &crate::daemon::Daemon,
+) -> NotebookResponse {
+    let notebook_path = room
+        .notebook_path
+        .exists()
+        .then(|| room.notebook_path.clone());
+    let env_id = resolve_metadata_snapshot(room, notebook_path.as_deref())
+        .await
+        .and_then(|s| s.runt.env_id);
+    let Some(env_id) = env_id else {
+        return NotebookResponse::Error {
+            error: "Notebook has no env_id".to_string(),
+        };
+    };
+    match daemon.unpin_env(&env_id).await {
+        Some(pin) => NotebookResponse::EnvironmentUnpinned {
+            env_id,
+            env_path: pin.env.venv_path,
+        },
+        None => NotebookResponse::Error {
+            error: "No environment is pinned to this notebook".to_string(),
+        },
+    }
+}
+
+/// Build the dependency tree of the running kernel's environment.
+async fn handle_get_dependency_tree(room: &NotebookRoom) -> NotebookResponse {
+    let (env_source, launched) = {
+        let guard = room.kernel.lock().await;
+        let Some(kernel) = guard.as_ref().filter(|k| k.is_running()) else {
+            return NotebookResponse::Error {
+                error: "Start the kernel to see its dependencies".to_string(),
+            };
+        };
+        (
+            kernel.env_source().to_string(),
+            kernel.launched_config().clone(),
+        )
+    };
+    let (Some(venv_path), Some(python_path)) = (launched.venv_path, launched.python_path) else {
+        return NotebookResponse::Error {
+            error: format!("The {} kernel has no environment to inspect", env_source),
+        };
+    };
+
+    let result = if env_source.starts_with("conda:") {
+        let env = kernel_env::CondaEnvironment {
+            env_path: venv_path,
+            python_path,
+        };
+        let declared = launched.conda_deps.unwrap_or_default();
+        tokio::task::spawn_blocking(move || kernel_env::conda::dependency_tree(&env, &declared))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|tree| tree)
+            .map(|roots| ("conda", roots))
+    } else {
+        let env = kernel_env::UvEnvironment {
+            venv_path,
+            python_path,
+        };
+        let declared = launched.uv_deps.unwrap_or_default();
+        kernel_env::uv::dependency_tree(&env, &declared)
+            .await
+            .map(|roots| ("uv", roots))
+    };
+
+    match result {
+        Ok((env_type, roots)) => NotebookResponse::DependencyTree {
+            env_type: env_type.to_string(),
+            roots,
+        },
+        Err(e) => NotebookResponse::Error {
+            error: format!("Failed to read dependencies: {}", e),
+        },
+    }
+}
+
+/// List the environments this room's kernel could be launched with.
+/// The notebook's file on disk: the client-reported `requested` path if it
+/// exists, otherwise the room's own path if that is a real file.
+///
+/// A room opened for an untitled notebook is keyed by a UUID, so after the
+/// notebook is saved only the client knows where it lives.
+fn saved_notebook_path(room: &NotebookRoom, requested: Option<String>) -> Option<PathBuf> {
+    requested
+        .map(PathBuf::from)
+        .filter(|path| path.exists())
+        .or_else(|| {
+            room.notebook_path
+                .exists()
+                .then(|| room.notebook_path.clone())
+        })
+}
+
+async fn room_env_options(
+    room: &NotebookRoom,
+    daemon: &crate::daemon::Daemon,
+    notebook_path: Option<PathBuf>,
+) -> Vec<crate::protocol::EnvOption> {
+    let snapshot = resolve_metadata_snapshot(room, notebook_path.as_deref()).await;
+    let kernel_type = snapshot
+        .as_ref()
+        .and_then(detect_notebook_kernel_type)
+        .unwrap_or_else(|| "python".to_string());
+    let settings = daemon.synced_settings().await;
+    crate::env_options::list_env_options(
+        &kernel_type,
+        snapshot.as_ref(),
+        notebook_path.as_deref(),
+        &crate::inline_env::get_inline_cache_dir(),
+        &settings.uv,
+        &settings.conda,
+    )
+}
+
+/// Handle sync environment request - hot-install new packages and uninstall
+/// removed ones without a kernel restart.
+///
+/// Only supported for UV inline dependencies. Conda and other env types fall
+/// back to restart. Packages that fail don't stop the rest; the response
+/// reports each outcome, and the broadcast sync state still lists the failed
+/// ones as pending.
+async fn handle_sync_environment(room: &NotebookRoom) -> NotebookResponse {
+    use crate::inline_env::UvEnvironment;
+
+    // Get kernel info, venv_path, python_path, and launch_id while holding lock, then release
+    let (launched, venv_path, python_path, launch_id) = {
+        let kernel_guard = room.kernel.lock().await;
+
+        // Check if kernel is running
+        let kernel = match kernel_guard.as_ref() {
+            Some(k) if k.is_running() => k,
+            Some(_) | None => {
+                return NotebookResponse::SyncEnvironmentFailed {
+                    error: "No kernel running".to_string(),
+                    needs_restart: false,
+                };
+            }
+        };
+
+        let launched = kernel.launched_config().clone();
+
+        // Only UV inline deps support hot-sync
+        if launched.uv_deps.is_none() {
+            return NotebookResponse::SyncEnvironmentFailed {
+                error: "Hot-sync only supported for UV inline dependencies".to_string(),
+                needs_restart: true,
+            };
+        }
+
+        let venv_path = match &launched.venv_path {
+            Some(path) => path.clone(),
+            None => {
+                return NotebookResponse::SyncEnvironmentFailed {
+                    error: "No venv path stored - restart required".to_string(),
+                    needs_restart: true,
+                };
+            }
+        };
+
+        let python_path = match &launched.python_path {
+            Some(path) => path.clone(),
+            None => {
+                return NotebookResponse::SyncEnvironmentFailed {
+                    error: "No python path stored - restart required".to_string(),
+                    needs_restart: true,
+                };
+            }
+        };
+
+        // Capture launch_id to detect if kernel is swapped during async install
+        let launch_id = launched.launch_id.clone();
+
+        (launched, venv_path, python_path, launch_id)
+    };
+
+    // Get current metadata from the document
+    let current_metadata = match resolve_metadata_snapshot(room, Some(&room.notebook_path)).await {
+        Some(m) => m,
+        None => {
+            return NotebookResponse::SyncEnvironmentFailed {
+                error: "Could not read notebook metadata".to_string(),
+                needs_restart: false,
+            };
+        }
+    };
+
+    // Compute diff
+    let diff = compute_env_sync_diff(&launched, &current_metadata);
+
+    let Some(d) = diff else {
+        // Already in sync
+        return NotebookResponse::SyncEnvironmentComplete {
+            report: kernel_env::SyncReport::default(),
+        };
+    };
+
+    // Check for channel changes (conda only, but check anyway)
+    if d.channels_changed {
+        return NotebookResponse::SyncEnvironmentFailed {
+            error: "Channel changes require restart".to_string(),
+            needs_restart: true,
+        };
+    }
+
+    // Nothing to change?
+    if d.added.is_empty() && d.removed.is_empty() {
+        return NotebookResponse::SyncEnvironmentComplete {
+            report: kernel_env::SyncReport::default(),
+        };
+    }
+
+    let env = UvEnvironment {
+        venv_path: venv_path.clone(),
+        python_path: python_path.clone(),
+    };
+    let handler: std::sync::Arc<dyn kernel_env::ProgressHandler> =
+        std::sync::Arc::new(crate::inline_env::BroadcastProgressHandler::new(
+            room.kernel_broadcast_tx.clone(),
+            room.env_build_log.clone(),
+        ));
+
+    info!(
+        "[notebook-sync] Hot-syncing {:?} (+{} -{})",
+        venv_path,
+        d.added.len(),
+        d.removed.len()
+    );
+
+    let report = match kernel_env::uv::sync_dependencies(
+        &env,
+        &d.added,
+        &d.removed,
+        handler.clone(),
+    )
+    .await
+    {
+        Ok(report) => report,
+        Err(e) => {
+            error!("[notebook-sync] Hot-sync failed: {}", e);
+            handler.on_progress(
+                "uv",
+                kernel_env::progress::EnvProgressPhase::Error {
+                    message: e.to_string(),
+                },
+            );
+            return NotebookResponse::SyncEnvironmentFailed {
+                error: format!("Failed to install packages: {}", e),
+                needs_restart: true,
+            };
+        }
+    };
+
+    if report.all_failed() {
+        let reasons: Vec<String> = report
+            .failed
+            .iter()
+            .map(|(package, reason)| format!("{}: {}", package, reason))
+            .collect();
+        let message = reasons.join("\n");
+        error!("[notebook-sync] Hot-sync failed: {}", message);
+        handler.on_progress(
+            "uv",
+            kernel_env::progress::EnvProgressPhase::Error {
+                message: message.clone(),
+            },
+        );
+        return NotebookResponse::SyncEnvironmentFailed {
+            error: format!("Failed to install packages: {}", message),
+            needs_restart: true,
+        };
+    }
+
+    info!(
+        "[notebook-sync] Hot-sync complete: installed {:?}, removed {:?}, failed {:?}",
+        report.installed, report.removed, report.failed
+    );
+
+    // Record what the environment now holds, so failed packages still show
+    // as pending. Skipped if the kernel was swapped during the install; the
+    // packages went to the old env and the new kernel tracks its own.
+    {
+        let mut kernel_guard = room.kernel.lock().await;
+        if let Some(ref mut kernel) = *kernel_guard {
+            if kernel.launched_config().launch_id != launch_id {
+                warn!("[notebook-sync] Kernel was swapped during hot-sync, skipping update");
+            } else {
+                let mut deps = launched.uv_deps.clone().unwrap_or_default();
+                deps.retain(|dep| !report.removed.contains(dep));
+                deps.extend(report.installed.iter().cloned());
+                kernel.update_launched_uv_deps(deps);
+            }
+        }
+    }
+
+    handler.on_progress(
+        "uv",
+        kernel_env::progress::EnvProgressPhase::Ready {
+            env_path: venv_path.to_string_lossy().to_string(),
+            python_path: python_path.to_string_lossy().to_string(),
+        },
+    );
+    check_and_broadcast_sync_state(room).await;
+
+    NotebookResponse::SyncEnvironmentComplete { report }
+}
+
+/// Write execution timing into a cell's `metadata.execution`, using the
+/// timestamp keys JupyterLab records, plus `duration_ms`. Other keys already
+/// in `execution` are kept.
+fn merge_execution_timing(cell_meta: &mut serde_json::Value, timing: &CellExecutionTiming) {
+    let Some(meta) = cell_meta.as_object_mut() else {
+        return;
+    };
+    let execution = meta
+        .entry("execution")
+        .or_insert_with(|| serde_json::json!({}));
+    if !execution.is_object() {
+        *execution = serde_json::json!({});
+    }
+    execution["iopub.execute_input"] = timing.started_at.clone().into();
+    execution["iopub.status.idle"] = timing.completed_at.clone().into();
+    execution["duration_ms"] = timing.duration_ms.into();
+}
+
+/// Write a cell's output layout into its metadata: nbformat `collapsed` and
+/// `scrolled`, and per-output collapse under `runt.collapsed_outputs`. The
+/// doc is authoritative, so flags it doesn't have are removed.
+fn merge_output_layout(cell_meta: &mut serde_json::Value, cell: &CellSnapshot) {
+    let Some(meta) = cell_meta.as_object_mut() else {
+        return;
+    };
+    for (key, value) in [("collapsed", cell.collapsed), ("scrolled", cell.scrolled)] {
+        match value {
+            Some(value) => {
+                meta.insert(key.to_string(), value.into());
+            }
+            None => {
+                meta.remove(key);
+            }
+        }
+    }
 
-                    NotebookResponse::SyncEnvironmentFailed {
-                        error: format!("Failed to install packages: {}", e),
-                        needs_restart: true,
-                    }
-                }
+    let runt = meta.get_mut("runt").and_then(|r| r.as_object_mut());
+    match (runt, cell.collapsed_outputs.is_empty()) {
+        (Some(runt), true) => {
+            runt.remove("collapsed_outputs");
+            if runt.is_empty() {
+                meta.remove("runt");
             }
         }
+        (Some(runt), false) => {
+            runt.insert(
+                "collapsed_outputs".to_string(),
+                serde_json::json!(cell.collapsed_outputs),
+            );
+        }
+        (None, false) => {
+            meta.insert(
+                "runt".to_string(),
+                serde_json::json!({ "collapsed_outputs": cell.collapsed_outputs }),
+            );
+        }
+        (None, true) => {}
     }
 }
 
@@ -2227,6 +3704,7 @@ async fn handle_sync_environment(room: &NotebookRoom) -> NotebookResponse {
 /// 2. Read cells and metadata from the Automerge doc
 /// 3. Merge metadata: replace kernelspec, language_info, runt; preserve everything else
 /// 4. Reconstruct cells: source and outputs from Automerge, cell metadata from existing file
+///    (with execution timing and output layout from Automerge merged in)
 /// 5. Write the merged notebook to disk
 async fn save_notebook_to_disk(room: &NotebookRoom) -> Result<(), String> {
     let notebook_path = &room.notebook_path;
@@ -2287,10 +3765,14 @@ async fn save_notebook_to_disk(room: &NotebookRoom) -> Result<(), String> {
     // Reconstruct cells as JSON
     let mut nb_cells = Vec::new();
     for cell in &cells {
-        let cell_meta = existing_cell_metadata
+        let mut cell_meta = existing_cell_metadata
             .get(&cell.id)
             .cloned()
             .unwrap_or(serde_json::json!({}));
+        if let Some(ref timing) = cell.execution {
+            merge_execution_timing(&mut cell_meta, timing);
+        }
+        merge_output_layout(&mut cell_meta, cell);
 
         // Parse source into multiline array format (split_inclusive('\n'))
         let source_lines: Vec<String> = if cell.source.is_empty() {
@@ -2383,6 +3865,19 @@ async fn save_notebook_to_disk(room: &NotebookRoom) -> Result<(), String> {
     Ok(())
 }
 
+/// The room's cells with each output resolved to JSON-encoded nbformat.
+async fn resolved_cells(room: &NotebookRoom) -> Vec<CellSnapshot> {
+    let mut cells = room.doc.read().await.get_cells();
+    for cell in &mut cells {
+        for output in &mut cell.outputs {
+            *output = resolve_cell_output(output, &room.blob_store)
+                .await
+                .to_string();
+        }
+    }
+    cells
+}
+
 /// Resolve a single cell output — handles both manifest hashes and raw JSON.
 async fn resolve_cell_output(output_str: &str, blob_store: &BlobStore) -> serde_json::Value {
     // Check if it's a manifest hash (64-char hex string)
@@ -2438,104 +3933,843 @@ pub(crate) fn persist_notebook_bytes(data: &[u8], path: &Path) {
                 "[notebook-sync] Failed to create parent dir for {:?}: {}",
                 path, e
             );
-            return;
+            return;
+        }
+    }
+    if let Err(e) = std::fs::write(path, data) {
+        warn!("[notebook-sync] Failed to save notebook doc: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a test blob store in the given temp directory.
+    fn test_blob_store(tmp: &tempfile::TempDir) -> Arc<BlobStore> {
+        Arc::new(BlobStore::new(tmp.path().join("blobs")))
+    }
+
+    #[test]
+    fn test_room_load_or_create_new() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let blob_store = test_blob_store(&tmp);
+        let room = NotebookRoom::load_or_create("test-nb", tmp.path(), blob_store);
+
+        let doc = room.doc.try_read().unwrap();
+        assert_eq!(doc.notebook_id(), Some("test-nb".to_string()));
+        assert_eq!(doc.cell_count(), 0);
+        assert_eq!(room.active_peers.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_room_persists_and_reloads() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let blob_store = test_blob_store(&tmp);
+
+        // Create room and add a cell
+        {
+            let room = NotebookRoom::load_or_create("persist-test", tmp.path(), blob_store.clone());
+            let mut doc = room.doc.try_write().unwrap();
+            doc.add_cell(0, "c1", "code").unwrap();
+            doc.update_source("c1", "hello").unwrap();
+            let bytes = doc.save();
+            persist_notebook_bytes(&bytes, &room.persist_path);
+        }
+
+        // Load again — should have the cell
+        {
+            let room = NotebookRoom::load_or_create("persist-test", tmp.path(), blob_store);
+            let doc = room.doc.try_read().unwrap();
+            assert_eq!(doc.cell_count(), 1);
+            let cell = doc.get_cell("c1").unwrap();
+            assert_eq!(cell.source, "hello");
+        }
+    }
+
+    #[test]
+    fn test_get_or_create_room_reuses_existing() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let blob_store = test_blob_store(&tmp);
+        let mut rooms = HashMap::new();
+
+        let room1 = get_or_create_room(&mut rooms, "nb1", tmp.path(), blob_store.clone());
+        let room2 = get_or_create_room(&mut rooms, "nb1", tmp.path(), blob_store);
+
+        // Should be the same Arc (same room)
+        assert!(Arc::ptr_eq(&room1, &room2));
+    }
+
+    #[test]
+    fn test_get_or_create_room_different_notebooks() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let blob_store = test_blob_store(&tmp);
+        let mut rooms = HashMap::new();
+
+        let room1 = get_or_create_room(&mut rooms, "nb1", tmp.path(), blob_store.clone());
+        let room2 = get_or_create_room(&mut rooms, "nb2", tmp.path(), blob_store);
+
+        // Should be different rooms
+        assert!(!Arc::ptr_eq(&room1, &room2));
+        assert_eq!(rooms.len(), 2);
+    }
+
+    #[test]
+    fn test_room_peer_counting() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let blob_store = test_blob_store(&tmp);
+        let room = NotebookRoom::load_or_create("peer-test", tmp.path(), blob_store);
+
+        assert_eq!(room.active_peers.load(Ordering::Relaxed), 0);
+
+        room.active_peers.fetch_add(1, Ordering::Relaxed);
+        room.active_peers.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(room.active_peers.load(Ordering::Relaxed), 2);
+
+        room.active_peers.fetch_sub(1, Ordering::Relaxed);
+        assert_eq!(room.active_peers.load(Ordering::Relaxed), 1);
+
+        room.active_peers.fetch_sub(1, Ordering::Relaxed);
+        assert_eq!(room.active_peers.load(Ordering::Relaxed), 0);
+    }
+
+    /// Put a room with an attached (not launched) kernel into a rooms map.
+    async fn room_with_kernel(
+        tmp: &tempfile::TempDir,
+        notebook_id: &str,
+    ) -> (NotebookRooms, Arc<NotebookRoom>) {
+        let blob_store = test_blob_store(tmp);
+        let room = Arc::new(NotebookRoom::new_fresh(
+            notebook_id,
+            tmp.path(),
+            blob_store.clone(),
+        ));
+        let kernel = RoomKernel::new(
+            room.kernel_broadcast_tx.clone(),
+            room.doc.clone(),
+            room.persist_path.clone(),
+            room.changed_tx.clone(),
+            blob_store,
+            room.comm_state.clone(),
+        );
+        *room.kernel.lock().await = Some(kernel);
+        let rooms: NotebookRooms = Arc::new(Mutex::new(HashMap::new()));
+        rooms
+            .lock()
+            .await
+            .insert(notebook_id.to_string(), room.clone());
+        (rooms, room)
+    }
+
+    #[tokio::test]
+    async fn test_close_shuts_down_kernel_by_default() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (rooms, room) = room_with_kernel(&tmp, "close-default").await;
+
+        assert!(evict_idle_room(&rooms, &room, "close-default", false).await);
+        assert!(room.kernel.lock().await.is_none());
+        assert!(rooms.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_close_keeps_kernel_when_enabled() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (rooms, room) = room_with_kernel(&tmp, "close-keep").await;
+        let _kernel_shell = room
+            .kernel
+            .lock()
+            .await
+            .as_mut()
+            .unwrap()
+            .attach_test_shell("python", "uv:prewarmed")
+            .await;
+
+        assert!(!evict_idle_room(&rooms, &room, "close-keep", true).await);
+        assert!(room.kernel.lock().await.is_some());
+        assert!(rooms.lock().await.contains_key("close-keep"));
+    }
+
+    #[tokio::test]
+    async fn test_close_evicts_dead_kernel_even_when_keeping() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (rooms, room) = room_with_kernel(&tmp, "close-dead").await;
+        if let Some(kernel) = room.kernel.lock().await.as_mut() {
+            let _kernel_shell = kernel.attach_test_shell("python", "uv:prewarmed").await;
+            kernel.handle_process_exit(Some(1), String::new());
+        }
+
+        assert!(evict_idle_room(&rooms, &room, "close-dead", true).await);
+        assert!(rooms.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_all_kernels_stops_every_room() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (rooms, first) = room_with_kernel(&tmp, "first").await;
+        let (other_rooms, second) = room_with_kernel(&tmp, "second").await;
+        let idle = Arc::new(NotebookRoom::new_fresh(
+            "idle",
+            tmp.path(),
+            test_blob_store(&tmp),
+        ));
+        {
+            let mut guard = rooms.lock().await;
+            guard.extend(other_rooms.lock().await.drain());
+            guard.insert("idle".to_string(), idle);
+        }
+        let mut status_rx = first.kernel_broadcast_tx.subscribe();
+
+        assert_eq!(shutdown_all_kernels(&rooms).await, vec!["first", "second"]);
+        assert!(first.kernel.lock().await.is_none());
+        assert!(second.kernel.lock().await.is_none());
+        // Rooms stay open for their peers
+        assert_eq!(rooms.lock().await.len(), 3);
+        assert!(matches!(
+            status_rx.recv().await.unwrap(),
+            NotebookBroadcast::KernelStatus { status, .. } if status == "shutdown"
+        ));
+
+        assert!(shutdown_all_kernels(&rooms).await.is_empty());
+    }
+
+    /// Store `output` as a manifest, spilling content over 100 bytes to blobs.
+    async fn store_test_output(output: serde_json::Value, blob_store: &BlobStore) -> String {
+        let manifest = crate::output_store::create_manifest(&output, blob_store, 100)
+            .await
+            .unwrap();
+        crate::output_store::store_manifest(&manifest, blob_store)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_resolved_cells_expand_manifest_hashes() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let blob_store = test_blob_store(&tmp);
+        let room = NotebookRoom::new_fresh("resolve-me", tmp.path(), blob_store.clone());
+        let display = serde_json::json!({
+            "output_type": "display_data",
+            "data": {"text/plain": "x".repeat(200)},
+            "metadata": {},
+        });
+        let stream = serde_json::json!({
+            "output_type": "stream",
+            "name": "stdout",
+            "text": "raw\n",
+        });
+        let hash = store_test_output(display.clone(), &blob_store).await;
+        {
+            let mut doc = room.doc.write().await;
+            doc.add_cell(0, "c1", "code").unwrap();
+            doc.append_output("c1", &hash).unwrap();
+            doc.append_output("c1", &stream.to_string()).unwrap();
+        }
+
+        let cells = resolved_cells(&room).await;
+        let outputs: Vec<serde_json::Value> = cells[0]
+            .outputs
+            .iter()
+            .map(|output| serde_json::from_str(output).unwrap())
+            .collect();
+        assert_eq!(outputs[0]["output_type"], "display_data");
+        assert_eq!(
+            outputs[0]["data"]["text/plain"],
+            display["data"]["text/plain"]
+        );
+        assert_eq!(outputs[1], stream);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_empty_pools_fall_back_to_system_python() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        // test_daemon disables both pools
+        let daemon = test_daemon(&tmp);
+        let room = NotebookRoom::new_fresh("no-env-manager", tmp.path(), test_blob_store(&tmp));
+        let mut broadcasts = room.kernel_broadcast_tx.subscribe();
+
+        let fake = crate::system_python::FakeSystemPython::install("exit 0").await;
+        let mut env_source = "uv:prewarmed".to_string();
+        let env = acquire_pool_env_for_source(&mut env_source, None, &daemon, &room).await;
+        assert!(matches!(env, Some(None)));
+        assert_eq!(env_source, crate::system_python::SYSTEM_PYTHON_SOURCE);
+        drop(fake);
+
+        // An interpreter without ipykernel isn't launched; the user is told
+        // how to fix it
+        let fake = crate::system_python::FakeSystemPython::install("exit 1").await;
+        let mut env_source = "uv:prewarmed".to_string();
+        let env = acquire_pool_env_for_source(&mut env_source, None, &daemon, &room).await;
+        assert!(env.is_none());
+        assert_eq!(env_source, "uv:prewarmed");
+        let Ok(NotebookBroadcast::KernelStatus { status, .. }) = broadcasts.try_recv() else {
+            panic!("no kernel status broadcast");
+        };
+        assert!(status.contains("pip install ipykernel"), "{status}");
+        drop(fake);
+    }
+
+    fn test_daemon(tmp: &tempfile::TempDir) -> Arc<crate::daemon::Daemon> {
+        crate::daemon::Daemon::new(crate::daemon::DaemonConfig {
+            socket_path: tmp.path().join("runtimed.sock"),
+            cache_dir: tmp.path().join("envs"),
+            blob_store_dir: tmp.path().join("blobs"),
+            notebook_docs_dir: tmp.path().join("notebook-docs"),
+            notebook_registry_path: tmp.path().join("notebooks.json"),
+            kernel_pids_path: tmp.path().join("kernel-pids.json"),
+            env_pins_path: tmp.path().join("env-pins.json"),
+            uv_pool_size: 0,
+            conda_pool_size: 0,
+            max_age_secs: 3600,
+            lock_dir: Some(tmp.path().to_path_buf()),
+            blob_server: Default::default(),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pin_and_unpin_environment_requests() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let daemon = test_daemon(&tmp);
+        let (_rooms, room) = room_with_kernel(&tmp, "/work/pinned.ipynb").await;
+
+        // Nothing to pin until a kernel is running
+        let response =
+            handle_notebook_request(&room, NotebookRequest::PinEnvironment {}, daemon.clone())
+                .await;
+        assert!(matches!(response, NotebookResponse::Error { .. }));
+
+        let venv_path = tmp.path().join("envs").join("runtimed-uv-pinned");
+        let python_path = venv_path.join("bin").join("python");
+        std::fs::create_dir_all(python_path.parent().unwrap()).unwrap();
+        std::fs::write(&python_path, "").unwrap();
+        let _kernel_shell = {
+            let mut guard = room.kernel.lock().await;
+            let kernel = guard.as_mut().unwrap();
+            kernel.set_test_launched_config(crate::kernel_manager::LaunchedEnvConfig {
+                venv_path: Some(venv_path.clone()),
+                python_path: Some(python_path.clone()),
+                env_id: Some("env-1".to_string()),
+                ..Default::default()
+            });
+            kernel.attach_test_shell("python", "uv:prewarmed").await
+        };
+        room.doc
+            .write()
+            .await
+            .set_metadata(
+                NOTEBOOK_METADATA_KEY,
+                &serde_json::json!({
+                    "runt": { "schema_version": "1", "env_id": "env-1" }
+                })
+                .to_string(),
+            )
+            .unwrap();
+
+        let response =
+            handle_notebook_request(&room, NotebookRequest::PinEnvironment {}, daemon.clone())
+                .await;
+        assert!(
+            matches!(
+                &response,
+                NotebookResponse::EnvironmentPinned { env_id, env_path }
+                    if env_id == "env-1" && *env_path == venv_path
+            ),
+            "{response:?}"
+        );
+        // Later launches with the notebook's env_id reattach the env
+        let reattached = daemon.take_uv_env(Some("env-1")).await.unwrap();
+        assert_eq!(reattached.venv_path, venv_path);
+
+        let response =
+            handle_notebook_request(&room, NotebookRequest::UnpinEnvironment {}, daemon.clone())
+                .await;
+        assert!(
+            matches!(&response, NotebookResponse::EnvironmentUnpinned { env_id, .. } if env_id == "env-1"),
+            "{response:?}"
+        );
+        assert!(daemon.take_uv_env(Some("env-1")).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_switch_environment_finds_project_env_at_saved_path() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let daemon = test_daemon(&tmp);
+        // The room of a notebook that was untitled when it was opened
+        let room = NotebookRoom::new_fresh(
+            "1f0c7a52-8d3e-4b6a-9c41-2e5d7b9a0f13",
+            tmp.path(),
+            test_blob_store(&tmp),
+        );
+        let project = tmp.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            project.join("pyproject.toml"),
+            "[project]\nname = \"demo\"\n",
+        )
+        .unwrap();
+        let saved = project.join("analysis.ipynb");
+        std::fs::write(&saved, "{}").unwrap();
+
+        // Without the saved path the room has nothing to detect from
+        let response = handle_notebook_request(
+            &room,
+            NotebookRequest::SwitchEnvironment {
+                option_id: "uv:pyproject".to_string(),
+                notebook_path: None,
+            },
+            daemon.clone(),
+        )
+        .await;
+        assert!(
+            matches!(&response, NotebookResponse::Error { error } if error.contains("Unknown environment option")),
+            "{response:?}"
+        );
+
+        let notebook_path = saved_notebook_path(&room, Some(saved.to_string_lossy().into_owned()));
+        assert_eq!(notebook_path.as_deref(), Some(saved.as_path()));
+        let options = room_env_options(&room, &daemon, notebook_path).await;
+        assert!(
+            options.iter().any(|o| o.id == "uv:pyproject"),
+            "{options:?}"
+        );
+
+        // A path that isn't on disk falls back to the room's own
+        assert_eq!(
+            saved_notebook_path(&room, Some("/nonexistent/x.ipynb".to_string())),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clear_outputs_releases_their_blobs() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let daemon = test_daemon(&tmp);
+        let blob_store = test_blob_store(&tmp);
+        let room = NotebookRoom::new_fresh("release-me", tmp.path(), blob_store.clone());
+        let image = serde_json::json!({
+            "output_type": "display_data",
+            "data": {"image/png": "i".repeat(200)},
+            "metadata": {}
+        });
+        // The same output in two cells: one blob, two references
+        let first = store_test_output(image.clone(), &blob_store).await;
+        let second = store_test_output(image, &blob_store).await;
+        assert_eq!(first, second);
+        let refs = crate::output_store::output_blob_refs(&first, &blob_store).await;
+        assert_eq!(refs.len(), 2);
+        {
+            let mut doc = room.doc.write().await;
+            doc.add_cell(0, "c1", "code").unwrap();
+            doc.add_cell(1, "c2", "code").unwrap();
+            doc.append_output("c1", &first).unwrap();
+            doc.append_output("c2", &second).unwrap();
+        }
+
+        let clear = |cell_id: &str| NotebookRequest::ClearOutputs {
+            cell_id: cell_id.to_string(),
+        };
+        handle_notebook_request(&room, clear("c1"), daemon.clone()).await;
+        for hash in &refs {
+            let meta = blob_store.get_meta(hash).await.unwrap().unwrap();
+            assert_eq!(meta.ref_count, 1, "c2 still shows the output");
+        }
+
+        handle_notebook_request(&room, clear("c2"), daemon.clone()).await;
+        for hash in &refs {
+            assert!(!blob_store.exists(hash), "released blob should be freed");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_copied_outputs_survive_clearing_the_original() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let daemon = test_daemon(&tmp);
+        let blob_store = test_blob_store(&tmp);
+        let room = NotebookRoom::new_fresh("duplicate-me", tmp.path(), blob_store.clone());
+        let output = store_test_output(
+            serde_json::json!({
+                "output_type": "display_data",
+                "data": {"image/png": "i".repeat(200)},
+                "metadata": {}
+            }),
+            &blob_store,
+        )
+        .await;
+        {
+            let mut doc = room.doc.write().await;
+            doc.add_cell(0, "original", "code").unwrap();
+            doc.add_cell(1, "duplicate", "code").unwrap();
+            doc.append_output("original", &output).unwrap();
+            doc.set_execution_count("original", "7").unwrap();
+        }
+
+        let copy = NotebookRequest::CopyOutputs {
+            from_cell_id: "original".to_string(),
+            to_cell_id: "duplicate".to_string(),
+        };
+        let response = handle_notebook_request(&room, copy, daemon.clone()).await;
+        assert!(matches!(
+            response,
+            NotebookResponse::OutputsCopied { count: 1, .. }
+        ));
+
+        let clear = NotebookRequest::ClearOutputs {
+            cell_id: "original".to_string(),
+        };
+        handle_notebook_request(&room, clear, daemon.clone()).await;
+
+        let duplicate = room.doc.read().await.get_cell("duplicate").unwrap();
+        assert_eq!(duplicate.outputs, vec![output.clone()]);
+        assert_eq!(duplicate.execution_count, "7");
+        for hash in crate::output_store::output_blob_refs(&output, &blob_store).await {
+            assert!(
+                blob_store.exists(&hash),
+                "duplicate's blob {hash} was freed"
+            );
+        }
+        let manifests =
+            crate::output_store::cell_output_manifests(&duplicate.outputs, &blob_store, None).await;
+        assert!(!manifests[0].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_clear_all_outputs_resets_cells_and_reclaims_blobs() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let blob_store = test_blob_store(&tmp);
+        let room = Arc::new(NotebookRoom::new_fresh(
+            "clear-me",
+            tmp.path(),
+            blob_store.clone(),
+        ));
+        let other = Arc::new(NotebookRoom::new_fresh(
+            "other",
+            tmp.path(),
+            blob_store.clone(),
+        ));
+        let unique = store_test_output(
+            serde_json::json!({
+                "output_type": "display_data",
+                "data": {"text/plain": "u".repeat(200)},
+                "metadata": {}
+            }),
+            &blob_store,
+        )
+        .await;
+        let shared_output = serde_json::json!({
+            "output_type": "stream",
+            "name": "stdout",
+            "text": "s".repeat(200)
+        });
+        let shared = store_test_output(shared_output.clone(), &blob_store).await;
+        // The other notebook's kernel produced the same output and stored it
+        // again, taking its own refs
+        assert_eq!(store_test_output(shared_output, &blob_store).await, shared);
+        let unique_refs = crate::output_store::output_blob_refs(&unique, &blob_store).await;
+        let shared_refs = crate::output_store::output_blob_refs(&shared, &blob_store).await;
+        assert_eq!(unique_refs.len(), 2);
+        assert_eq!(shared_refs.len(), 2);
+
+        {
+            let mut doc = room.doc.write().await;
+            doc.add_cell(0, "c1", "code").unwrap();
+            doc.add_cell(1, "c2", "code").unwrap();
+            doc.add_cell(2, "md", "markdown").unwrap();
+            doc.append_output("c1", &unique).unwrap();
+            doc.append_output("c2", &shared).unwrap();
+            doc.append_output(
+                "c2",
+                r#"{"output_type":"stream","name":"stdout","text":"raw"}"#,
+            )
+            .unwrap();
+            doc.set_execution_count("c1", "1").unwrap();
+            doc.set_execution_count("c2", "2").unwrap();
+        }
+        {
+            let mut doc = other.doc.write().await;
+            doc.add_cell(0, "o1", "code").unwrap();
+            doc.append_output("o1", &shared).unwrap();
+        }
+        let mut broadcast_rx = room.kernel_broadcast_tx.subscribe();
+
+        let (cell_ids, reclaimed) = clear_all_outputs(&room).await.unwrap();
+        assert_eq!(cell_ids, vec!["c1", "c2"]);
+        assert_eq!(reclaimed, unique_refs.len());
+
+        for cell in room.doc.read().await.get_cells() {
+            assert!(cell.outputs.is_empty());
+            assert_eq!(cell.execution_count, "null");
+        }
+        for hash in &unique_refs {
+            assert!(
+                !blob_store.exists(hash),
+                "unreferenced blob should be freed"
+            );
+        }
+        for hash in &shared_refs {
+            assert!(
+                blob_store.exists(hash),
+                "blob used by another notebook should be kept"
+            );
         }
+        assert_eq!(
+            other.doc.read().await.get_cell("o1").unwrap().outputs,
+            vec![shared]
+        );
+        assert!(matches!(
+            broadcast_rx.recv().await.unwrap(),
+            NotebookBroadcast::OutputsCleared { cell_id } if cell_id == "c1"
+        ));
     }
-    if let Err(e) = std::fs::write(path, data) {
-        warn!("[notebook-sync] Failed to save notebook doc: {}", e);
+
+    /// Room whose doc has code cells c1..c3 (sources "1".."3") with an output
+    /// each, plus markdown cell m1, and an attached test shell.
+    async fn room_with_runnable_cells(
+        tmp: &tempfile::TempDir,
+    ) -> (Arc<NotebookRoom>, runtimelib::KernelShellConnection) {
+        let (_rooms, room) = room_with_kernel(tmp, "run-cells").await;
+        {
+            let mut doc = room.doc.write().await;
+            for (i, cell_id) in ["c1", "c2", "c3"].into_iter().enumerate() {
+                doc.add_cell(i, cell_id, "code").unwrap();
+                doc.update_source(cell_id, &(i + 1).to_string()).unwrap();
+                doc.append_output(cell_id, "old-output").unwrap();
+            }
+            doc.add_cell(3, "m1", "markdown").unwrap();
+        }
+        let kernel_shell = room
+            .kernel
+            .lock()
+            .await
+            .as_mut()
+            .unwrap()
+            .attach_test_shell("python", "uv:prewarmed")
+            .await;
+        (room, kernel_shell)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[tokio::test]
+    async fn test_run_cells_preserves_requested_order() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (room, mut kernel_shell) = room_with_runnable_cells(&tmp).await;
+        let mut broadcast_rx = room.kernel_broadcast_tx.subscribe();
 
-    /// Create a test blob store in the given temp directory.
-    fn test_blob_store(tmp: &tempfile::TempDir) -> Arc<BlobStore> {
-        Arc::new(BlobStore::new(tmp.path().join("blobs")))
+        let response = run_cells(&room, vec!["c3".into(), "c1".into()]).await;
+        let NotebookResponse::CellBatchQueued { cell_ids, skipped } = response else {
+            panic!("expected CellBatchQueued, got {:?}", response);
+        };
+        assert_eq!(cell_ids, vec!["c3", "c1"]);
+        assert!(skipped.is_empty());
+
+        for expected in ["3", "1"] {
+            let request = kernel_shell.read().await.unwrap();
+            let jupyter_protocol::JupyterMessageContent::ExecuteRequest(execute) = request.content
+            else {
+                panic!("expected execute_request, got {:?}", request.content);
+            };
+            assert_eq!(execute.code, expected);
+        }
+
+        // Only the requested cells are cleared
+        let doc = room.doc.read().await;
+        assert!(doc.get_cell("c1").unwrap().outputs.is_empty());
+        assert!(doc.get_cell("c3").unwrap().outputs.is_empty());
+        assert_eq!(doc.get_cell("c2").unwrap().outputs, vec!["old-output"]);
+        let mut cleared = vec![];
+        while let Ok(event) = broadcast_rx.try_recv() {
+            if let NotebookBroadcast::OutputsCleared { cell_id } = event {
+                cleared.push(cell_id);
+            }
+        }
+        assert_eq!(cleared, vec!["c3", "c1"]);
     }
 
-    #[test]
-    fn test_room_load_or_create_new() {
+    #[tokio::test]
+    async fn test_run_cells_skips_unknown_and_non_code_cells() {
         let tmp = tempfile::TempDir::new().unwrap();
-        let blob_store = test_blob_store(&tmp);
-        let room = NotebookRoom::load_or_create("test-nb", tmp.path(), blob_store);
+        let (room, _kernel_shell) = room_with_runnable_cells(&tmp).await;
 
-        let doc = room.doc.try_read().unwrap();
-        assert_eq!(doc.notebook_id(), Some("test-nb".to_string()));
-        assert_eq!(doc.cell_count(), 0);
-        assert_eq!(room.active_peers.load(Ordering::Relaxed), 0);
+        let response = run_cells(
+            &room,
+            vec!["missing".into(), "c2".into(), "m1".into(), "c1".into()],
+        )
+        .await;
+        let NotebookResponse::CellBatchQueued { cell_ids, skipped } = response else {
+            panic!("expected CellBatchQueued, got {:?}", response);
+        };
+        assert_eq!(cell_ids, vec!["c2", "c1"]);
+        assert_eq!(skipped, vec!["missing", "m1"]);
     }
 
-    #[test]
-    fn test_room_persists_and_reloads() {
+    #[tokio::test]
+    async fn test_run_cells_sends_raises_exception_without_stop_on_error() {
         let tmp = tempfile::TempDir::new().unwrap();
-        let blob_store = test_blob_store(&tmp);
+        let (room, mut kernel_shell) = room_with_runnable_cells(&tmp).await;
+        room.doc
+            .write()
+            .await
+            .set_cell_tags("c1", &["raises-exception".to_string()])
+            .unwrap();
 
-        // Create room and add a cell
-        {
-            let room = NotebookRoom::load_or_create("persist-test", tmp.path(), blob_store.clone());
-            let mut doc = room.doc.try_write().unwrap();
-            doc.add_cell(0, "c1", "code").unwrap();
-            doc.update_source("c1", "hello").unwrap();
-            let bytes = doc.save();
-            persist_notebook_bytes(&bytes, &room.persist_path);
-        }
+        run_cells(&room, vec!["c1".into(), "c2".into()]).await;
 
-        // Load again — should have the cell
-        {
-            let room = NotebookRoom::load_or_create("persist-test", tmp.path(), blob_store);
-            let doc = room.doc.try_read().unwrap();
-            assert_eq!(doc.cell_count(), 1);
-            let cell = doc.get_cell("c1").unwrap();
-            assert_eq!(cell.source, "hello");
+        let mut stop_on_error = vec![];
+        for _ in 0..2 {
+            let request = kernel_shell.read().await.unwrap();
+            let jupyter_protocol::JupyterMessageContent::ExecuteRequest(execute) = request.content
+            else {
+                panic!("expected execute_request, got {:?}", request.content);
+            };
+            stop_on_error.push(execute.stop_on_error);
         }
+        assert_eq!(stop_on_error, vec![false, true]);
+
+        // c1's error leaves c2 queued
+        let mut guard = room.kernel.lock().await;
+        let kernel = guard.as_mut().unwrap();
+        assert!(kernel.handle_cell_error("c1").is_empty());
+        assert_eq!(kernel.queued_cells(), vec!["c2"]);
     }
 
-    #[test]
-    fn test_get_or_create_room_reuses_existing() {
+    #[tokio::test]
+    async fn test_live_kernel_is_reattached_not_relaunched() {
         let tmp = tempfile::TempDir::new().unwrap();
-        let blob_store = test_blob_store(&tmp);
-        let mut rooms = HashMap::new();
+        let notebook_id = uuid::Uuid::new_v4().to_string();
+        let (_rooms, room) = room_with_kernel(&tmp, &notebook_id).await;
 
-        let room1 = get_or_create_room(&mut rooms, "nb1", tmp.path(), blob_store.clone());
-        let room2 = get_or_create_room(&mut rooms, "nb1", tmp.path(), blob_store);
+        // An attached-but-unlaunched kernel doesn't count as live
+        assert!(room.reattach_broadcasts().await.is_empty());
+        assert!(should_auto_launch(&room, &notebook_id).await.0);
 
-        // Should be the same Arc (same room)
-        assert!(Arc::ptr_eq(&room1, &room2));
+        let _kernel_shell = room
+            .kernel
+            .lock()
+            .await
+            .as_mut()
+            .unwrap()
+            .attach_test_shell("python", "uv:prewarmed")
+            .await;
+
+        assert!(!should_auto_launch(&room, &notebook_id).await.0);
+        let broadcasts = room.reattach_broadcasts().await;
+        assert!(broadcasts.iter().any(|b| matches!(
+            b,
+            NotebookBroadcast::KernelLifecycle { state, kernel_type, .. }
+                if state == "reattached" && kernel_type.as_deref() == Some("python")
+        )));
     }
 
-    #[test]
-    fn test_get_or_create_room_different_notebooks() {
+    #[tokio::test]
+    async fn test_auto_launch_opt_out_overrides_trust() {
         let tmp = tempfile::TempDir::new().unwrap();
-        let blob_store = test_blob_store(&tmp);
-        let mut rooms = HashMap::new();
+        let notebook_id = uuid::Uuid::new_v4().to_string();
+        let (_rooms, room) = room_with_kernel(&tmp, &notebook_id).await;
+        room.trust_state.write().await.status = runt_trust::TrustStatus::Trusted;
+        assert!(should_auto_launch(&room, &notebook_id).await.0);
+        assert!(room.auto_launch_disabled_broadcast().await.is_none());
+
+        room.doc
+            .write()
+            .await
+            .set_metadata(
+                NOTEBOOK_METADATA_KEY,
+                &serde_json::json!({
+                    "runt": { "schema_version": "1", "auto_launch": false }
+                })
+                .to_string(),
+            )
+            .unwrap();
 
-        let room1 = get_or_create_room(&mut rooms, "nb1", tmp.path(), blob_store.clone());
-        let room2 = get_or_create_room(&mut rooms, "nb2", tmp.path(), blob_store);
+        let (should_launch, status) = should_auto_launch(&room, &notebook_id).await;
+        assert!(!should_launch);
+        assert_eq!(status, runt_trust::TrustStatus::Trusted);
+        assert!(matches!(
+            room.auto_launch_disabled_broadcast().await,
+            Some(NotebookBroadcast::KernelLifecycle { state, reason, .. })
+                if state == "auto_launch_disabled"
+                    && reason.as_deref() == Some("auto-launch disabled")
+        ));
+    }
 
-        // Should be different rooms
-        assert!(!Arc::ptr_eq(&room1, &room2));
-        assert_eq!(rooms.len(), 2);
+    #[tokio::test]
+    async fn test_room_info_reports_queue_backlog() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (_rooms, room) = room_with_kernel(&tmp, "backlog").await;
+
+        let info = room.room_info("backlog").await;
+        assert!(!info.has_kernel);
+        assert_eq!(info.queue_depth, 0);
+        assert_eq!(info.executing_cell, None);
+
+        let mut guard = room.kernel.lock().await;
+        let kernel = guard.as_mut().unwrap();
+        let _kernel_shell = kernel.attach_test_shell("python", "uv:prewarmed").await;
+        for cell_id in ["a", "b", "c"] {
+            kernel
+                .queue_cell(cell_id.to_string(), "1".to_string())
+                .await
+                .unwrap();
+        }
+        drop(guard);
+
+        let info = room.room_info("backlog").await;
+        assert!(info.has_kernel);
+        assert_eq!(info.kernel_status.as_deref(), Some("busy"));
+        assert_eq!(info.executing_cell.as_deref(), Some("a"));
+        assert_eq!(info.queue_depth, 2);
     }
 
-    #[test]
-    fn test_room_peer_counting() {
+    #[tokio::test]
+    async fn test_repair_environment_preconditions() {
         let tmp = tempfile::TempDir::new().unwrap();
-        let blob_store = test_blob_store(&tmp);
-        let room = NotebookRoom::load_or_create("peer-test", tmp.path(), blob_store);
+        let (_rooms, room) = room_with_kernel(&tmp, "repair-env").await;
 
-        assert_eq!(room.active_peers.load(Ordering::Relaxed), 0);
+        // Nothing recorded to rebuild from
+        let response =
+            handle_repair_environment(&room, &UvDefaults::default(), &CondaDefaults::default())
+                .await;
+        assert!(
+            matches!(&response, NotebookResponse::Error { error } if error.contains("no inline dependencies")),
+            "{response:?}"
+        );
 
-        room.active_peers.fetch_add(1, Ordering::Relaxed);
-        room.active_peers.fetch_add(1, Ordering::Relaxed);
-        assert_eq!(room.active_peers.load(Ordering::Relaxed), 2);
+        // Never rebuild an env out from under a live kernel
+        let _kernel_shell = room
+            .kernel
+            .lock()
+            .await
+            .as_mut()
+            .unwrap()
+            .attach_test_shell("python", "uv:inline")
+            .await;
+        room.doc
+            .write()
+            .await
+            .set_metadata(
+                NOTEBOOK_METADATA_KEY,
+                &serde_json::json!({
+                    "runt": { "schema_version": "1", "uv": { "dependencies": ["pandas"] } }
+                })
+                .to_string(),
+            )
+            .unwrap();
+        let response =
+            handle_repair_environment(&room, &UvDefaults::default(), &CondaDefaults::default())
+                .await;
+        assert!(
+            matches!(&response, NotebookResponse::Error { error } if error.contains("Shut down the kernel")),
+            "{response:?}"
+        );
+    }
 
-        room.active_peers.fetch_sub(1, Ordering::Relaxed);
-        assert_eq!(room.active_peers.load(Ordering::Relaxed), 1);
+    #[tokio::test]
+    async fn test_close_skips_eviction_when_peer_reconnected() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (rooms, room) = room_with_kernel(&tmp, "close-peer").await;
+        room.active_peers.fetch_add(1, Ordering::Relaxed);
 
-        room.active_peers.fetch_sub(1, Ordering::Relaxed);
-        assert_eq!(room.active_peers.load(Ordering::Relaxed), 0);
+        assert!(!evict_idle_room(&rooms, &room, "close-peer", false).await);
+        assert!(room.kernel.lock().await.is_some());
     }
 
     #[test]
@@ -2597,6 +4831,7 @@ mod tests {
                 }),
                 conda: None,
                 deno: None,
+                auto_launch: None,
             },
         }
     }
@@ -2613,9 +4848,12 @@ mod tests {
                 conda: Some(crate::notebook_metadata::CondaInlineMetadata {
                     dependencies: deps,
                     channels: vec!["conda-forge".to_string()],
+                    channel_priority: None,
                     python: None,
+                    pip_dependencies: vec![],
                 }),
                 deno: None,
+                auto_launch: None,
             },
         }
     }
@@ -2631,6 +4869,7 @@ mod tests {
                 uv: None,
                 conda: None,
                 deno: None,
+                auto_launch: None,
             },
         }
     }
@@ -2650,6 +4889,120 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_inline_conda_channel_config() {
+        let mut snapshot = snapshot_with_conda(vec!["pandas".to_string()]);
+        let strict_defaults = CondaDefaults::default();
+        assert_eq!(
+            get_inline_conda_channel_priority(&snapshot, &strict_defaults),
+            kernel_env::ChannelPriority::Strict
+        );
+
+        // Settings supply channels and priority when the notebook has none
+        let settings = CondaDefaults {
+            channels: vec!["bioconda".to_string(), "conda-forge".to_string()],
+            strict_channel_priority: false,
+            ..CondaDefaults::default()
+        };
+        let conda = snapshot.runt.conda.as_mut().unwrap();
+        conda.channels.clear();
+        assert_eq!(
+            get_inline_conda_channels(&snapshot, &settings),
+            vec!["bioconda", "conda-forge"]
+        );
+        assert_eq!(
+            get_inline_conda_channel_priority(&snapshot, &settings),
+            kernel_env::ChannelPriority::Disabled
+        );
+
+        // The notebook's own priority wins
+        snapshot.runt.conda.as_mut().unwrap().channel_priority =
+            Some(kernel_env::ChannelPriority::Strict);
+        assert_eq!(
+            get_inline_conda_channel_priority(&snapshot, &settings),
+            kernel_env::ChannelPriority::Strict
+        );
+    }
+
+    #[test]
+    fn test_launched_config_records_prewarmed_env_paths() {
+        let launched = build_launched_config(
+            "python",
+            "uv:prewarmed",
+            None,
+            Some(&snapshot_empty()),
+            &CondaDefaults::default(),
+            Some(PathBuf::from("/cache/runtimed-uv-1")),
+            Some(PathBuf::from("/cache/runtimed-uv-1/bin/python")),
+        );
+        assert_eq!(
+            launched.venv_path,
+            Some(PathBuf::from("/cache/runtimed-uv-1"))
+        );
+        assert_eq!(
+            launched.python_path,
+            Some(PathBuf::from("/cache/runtimed-uv-1/bin/python"))
+        );
+        assert!(launched.uv_deps.is_none());
+    }
+
+    #[test]
+    fn test_env_sync_diff_channel_priority_change() {
+        let mut snapshot = snapshot_with_conda(vec!["pandas".to_string()]);
+        let launched = build_launched_config(
+            "python",
+            "conda:inline",
+            Some(&["pandas".to_string()]),
+            Some(&snapshot),
+            &CondaDefaults::default(),
+            None,
+            None,
+        );
+        assert_eq!(
+            launched.conda_channel_priority,
+            Some(kernel_env::ChannelPriority::Strict)
+        );
+        assert!(compute_env_sync_diff(&launched, &snapshot).is_none());
+
+        snapshot.runt.conda.as_mut().unwrap().channel_priority =
+            Some(kernel_env::ChannelPriority::Disabled);
+        let diff = compute_env_sync_diff(&launched, &snapshot).unwrap();
+        assert!(diff.channels_changed);
+    }
+
+    #[test]
+    fn test_conda_pip_deps_launch_and_drift() {
+        // PyPI-only packages on their own still need a conda env
+        let mut snapshot = snapshot_with_conda(vec![]);
+        assert_eq!(check_inline_deps(&snapshot), None);
+        snapshot.runt.conda.as_mut().unwrap().pip_dependencies = vec!["overturemaps".to_string()];
+        assert_eq!(
+            check_inline_deps(&snapshot),
+            Some("conda:inline".to_string())
+        );
+        assert_eq!(get_inline_conda_deps(&snapshot), Some(vec![]));
+
+        let launched = build_launched_config(
+            "python",
+            "conda:inline",
+            Some(&[]),
+            Some(&snapshot),
+            &CondaDefaults::default(),
+            None,
+            None,
+        );
+        assert_eq!(
+            launched.conda_pip_deps,
+            Some(vec!["overturemaps".to_string()])
+        );
+        assert!(compute_env_sync_diff(&launched, &snapshot).is_none());
+
+        snapshot.runt.conda.as_mut().unwrap().pip_dependencies = vec!["lonboard".to_string()];
+        let diff = compute_env_sync_diff(&launched, &snapshot).unwrap();
+        assert_eq!(diff.added, vec!["lonboard"]);
+        assert_eq!(diff.removed, vec!["overturemaps"]);
+    }
+
     #[test]
     fn test_check_inline_deps_empty() {
         let snapshot = snapshot_empty();
@@ -2679,9 +5032,12 @@ mod tests {
                 conda: Some(crate::notebook_metadata::CondaInlineMetadata {
                     dependencies: vec!["pandas".to_string()],
                     channels: vec!["conda-forge".to_string()],
+                    channel_priority: None,
                     python: None,
+                    pip_dependencies: vec![],
                 }),
                 deno: None,
+                auto_launch: None,
             },
         };
         assert_eq!(check_inline_deps(&snapshot), Some("uv:inline".to_string()));
@@ -2707,11 +5063,66 @@ mod tests {
                     config: None,
                     flexible_npm_imports: None,
                 }),
+                auto_launch: None,
             },
         };
         assert_eq!(check_inline_deps(&snapshot), Some("deno".to_string()));
     }
 
+    #[test]
+    fn test_detect_kernel_type_custom_kernelspec() {
+        let mut snapshot = snapshot_empty();
+        snapshot.kernelspec = Some(crate::notebook_metadata::KernelspecSnapshot {
+            name: "ir".to_string(),
+            display_name: "R".to_string(),
+            language: Some("R".to_string()),
+        });
+        assert_eq!(
+            detect_kernel_type_with(&snapshot, |name| name == "ir"),
+            Some("ir".to_string())
+        );
+
+        // Python and Deno kernelspecs still map to the built-in runtimes
+        snapshot.kernelspec = Some(crate::notebook_metadata::KernelspecSnapshot {
+            name: "conda-env-analysis-py".to_string(),
+            display_name: "Python [conda env:analysis]".to_string(),
+            language: Some("python".to_string()),
+        });
+        assert_eq!(
+            detect_kernel_type_with(&snapshot, |_| false),
+            Some("python".to_string())
+        );
+    }
+
+    #[test]
+    fn test_detect_kernel_type_missing_kernelspec_falls_back() {
+        let mut snapshot = snapshot_empty();
+        snapshot.kernelspec = Some(crate::notebook_metadata::KernelspecSnapshot {
+            name: "tslab".to_string(),
+            display_name: "TypeScript".to_string(),
+            language: None,
+        });
+        snapshot.language_info = Some(crate::notebook_metadata::LanguageInfoSnapshot {
+            name: "typescript".to_string(),
+            version: None,
+        });
+        assert_eq!(
+            detect_kernel_type_with(&snapshot, |_| false),
+            Some("deno".to_string())
+        );
+        assert_eq!(
+            detect_kernel_type_with(&snapshot, |name| name == "tslab"),
+            Some("tslab".to_string())
+        );
+
+        // Nothing else to go on: Python
+        snapshot.language_info = None;
+        assert_eq!(
+            detect_kernel_type_with(&snapshot, |_| false),
+            Some("python".to_string())
+        );
+    }
+
     // ── Integration tests for save_notebook_to_disk ────────────────────────
 
     /// Create a test room with a notebook_path pointing to a file in temp dir.
@@ -2742,12 +5153,17 @@ mod tests {
                     uv_dependencies: vec![],
                     conda_dependencies: vec![],
                     conda_channels: vec![],
+                    trusted_scope: None,
                 },
                 pending_launch: false,
             })),
+            output_trust: crate::output_store::OutputTrust::new(false),
             notebook_path: notebook_path.clone(),
             auto_launch_at: Arc::new(RwLock::new(None)),
             comm_state: Arc::new(crate::comm_state::CommState::new()),
+            lsp: Arc::new(Mutex::new(LspState::NotStarted)),
+            last_launch_spec: Arc::new(RwLock::new(None)),
+            env_build_log: Arc::default(),
         };
 
         (room, notebook_path)
@@ -2847,6 +5263,118 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_save_notebook_to_disk_writes_execution_timing() {
+        use std::io::Write;
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (room, notebook_path) = test_room_with_path(&tmp, "timing.ipynb");
+
+        {
+            let mut f = std::fs::File::create(&notebook_path).unwrap();
+            writeln!(
+                f,
+                r#"{{
+                    "nbformat": 4,
+                    "nbformat_minor": 5,
+                    "metadata": {{}},
+                    "cells": [{{
+                        "id": "cell1",
+                        "cell_type": "code",
+                        "source": [],
+                        "outputs": [],
+                        "execution_count": null,
+                        "metadata": {{"tags": ["slow"], "execution": {{"shell.execute_reply": "x"}}}}
+                    }}]
+                }}"#
+            )
+            .unwrap();
+        }
+
+        let timing = CellExecutionTiming {
+            started_at: "2026-01-01T12:00:00.000Z".to_string(),
+            completed_at: "2026-01-01T12:00:02.000Z".to_string(),
+            duration_ms: 2000,
+        };
+        {
+            let mut doc = room.doc.write().await;
+            doc.add_cell(0, "cell1", "code").unwrap();
+            doc.set_execution_timing("cell1", &timing).unwrap();
+        }
+
+        save_notebook_to_disk(&room).await.unwrap();
+
+        let content = std::fs::read_to_string(&notebook_path).unwrap();
+        let saved: serde_json::Value = serde_json::from_str(&content).unwrap();
+        let meta = &saved["cells"][0]["metadata"];
+        assert_eq!(meta["tags"], serde_json::json!(["slow"]));
+        assert_eq!(meta["execution"]["duration_ms"], 2000);
+        assert_eq!(
+            meta["execution"]["iopub.execute_input"],
+            "2026-01-01T12:00:00.000Z"
+        );
+        assert_eq!(
+            meta["execution"]["iopub.status.idle"],
+            "2026-01-01T12:00:02.000Z"
+        );
+        assert_eq!(meta["execution"]["shell.execute_reply"], "x");
+    }
+
+    #[tokio::test]
+    async fn test_save_notebook_to_disk_writes_output_layout() {
+        use std::io::Write;
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (room, notebook_path) = test_room_with_path(&tmp, "layout.ipynb");
+
+        {
+            let mut f = std::fs::File::create(&notebook_path).unwrap();
+            writeln!(
+                f,
+                r#"{{
+                    "nbformat": 4,
+                    "nbformat_minor": 5,
+                    "metadata": {{}},
+                    "cells": [{{
+                        "id": "cell1",
+                        "cell_type": "code",
+                        "source": [],
+                        "outputs": [],
+                        "execution_count": null,
+                        "metadata": {{"scrolled": true, "runt": {{"collapsed_outputs": [3]}}}}
+                    }}]
+                }}"#
+            )
+            .unwrap();
+        }
+
+        {
+            let mut doc = room.doc.write().await;
+            doc.add_cell(0, "cell1", "code").unwrap();
+            doc.set_cell_flag("cell1", "collapsed", Some(true)).unwrap();
+        }
+        save_notebook_to_disk(&room).await.unwrap();
+
+        let content = std::fs::read_to_string(&notebook_path).unwrap();
+        let saved: serde_json::Value = serde_json::from_str(&content).unwrap();
+        // Flags cleared in the doc are removed from the file
+        assert_eq!(
+            saved["cells"][0]["metadata"],
+            serde_json::json!({"collapsed": true})
+        );
+
+        room.doc
+            .write()
+            .await
+            .set_output_collapsed("cell1", 1, true)
+            .unwrap();
+        save_notebook_to_disk(&room).await.unwrap();
+        let content = std::fs::read_to_string(&notebook_path).unwrap();
+        let saved: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(
+            saved["cells"][0]["metadata"]["runt"]["collapsed_outputs"],
+            serde_json::json!([1])
+        );
+    }
+
     #[tokio::test]
     async fn test_save_notebook_to_disk_enforces_nbformat_minor_5() {
         use std::io::Write;
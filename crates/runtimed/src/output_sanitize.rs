@@ -0,0 +1,417 @@
+//! Sanitizing outputs of untrusted notebooks.
+//!
+//! Rich outputs render in the app's WebView, so HTML from a notebook the user
+//! hasn't trusted could run script with the renderer's privileges. When a
+//! room's notebook isn't trusted, the manifest builder runs each MIME bundle
+//! through [`sanitize_bundle_entry`], and the app does the same for outputs
+//! it loads from disk ([`sanitize_output`]): JavaScript outputs are dropped
+//! and HTML/SVG are cleaned against an allowlist of elements and attributes
+//! (no scripts, frames, forms, `<meta>`/`<base>`, event handlers or
+//! `javascript:` URLs). The `outputs.allow_html` and
+//! `outputs.allow_javascript` settings loosen or tighten this.
+
+use std::path::Path;
+use std::sync::LazyLock;
+
+use ammonia::Builder;
+use runt_trust::TrustStatus;
+use serde_json::Value;
+
+/// MIME types whose content is script to execute.
+const JAVASCRIPT_MIME_TYPES: &[&str] = &[
+    "application/javascript",
+    "application/x-javascript",
+    "text/javascript",
+];
+
+/// Attributes kept on any HTML element on top of ammonia's defaults, so
+/// tables and styled reprs keep their layout.
+const HTML_ATTRIBUTES: &[&str] = &["class", "style"];
+
+/// SVG elements kept in SVG outputs, enough for plots and diagrams.
+const SVG_TAGS: &[&str] = &[
+    "svg",
+    "g",
+    "defs",
+    "symbol",
+    "use",
+    "path",
+    "rect",
+    "circle",
+    "ellipse",
+    "line",
+    "polyline",
+    "polygon",
+    "text",
+    "tspan",
+    "title",
+    "desc",
+    "clipPath",
+    "mask",
+    "marker",
+    "pattern",
+    "linearGradient",
+    "radialGradient",
+    "stop",
+    "image",
+];
+
+/// Geometry and presentation attributes kept on SVG elements.
+const SVG_ATTRIBUTES: &[&str] = &[
+    "class",
+    "style",
+    "id",
+    "href",
+    "viewBox",
+    "preserveAspectRatio",
+    "version",
+    "width",
+    "height",
+    "x",
+    "y",
+    "x1",
+    "y1",
+    "x2",
+    "y2",
+    "cx",
+    "cy",
+    "r",
+    "rx",
+    "ry",
+    "dx",
+    "dy",
+    "d",
+    "points",
+    "transform",
+    "offset",
+    "opacity",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "stroke",
+    "stroke-width",
+    "stroke-opacity",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "clip-path",
+    "clip-rule",
+    "clipPathUnits",
+    "mask",
+    "marker-start",
+    "marker-mid",
+    "marker-end",
+    "markerWidth",
+    "markerHeight",
+    "markerUnits",
+    "refX",
+    "refY",
+    "orient",
+    "patternUnits",
+    "patternTransform",
+    "gradientUnits",
+    "gradientTransform",
+    "stop-color",
+    "stop-opacity",
+    "font-family",
+    "font-size",
+    "font-style",
+    "font-weight",
+    "text-anchor",
+    "dominant-baseline",
+    "alignment-baseline",
+];
+
+/// URL schemes allowed on top of ammonia's defaults; outputs commonly
+/// embed their images as `data:` URLs.
+const EXTRA_URL_SCHEMES: &[&str] = &["data"];
+
+static HTML_SANITIZER: LazyLock<Builder<'static>> = LazyLock::new(|| {
+    let mut builder = Builder::default();
+    builder
+        .add_generic_attributes(HTML_ATTRIBUTES)
+        .add_url_schemes(EXTRA_URL_SCHEMES);
+    builder
+});
+
+static SVG_SANITIZER: LazyLock<Builder<'static>> = LazyLock::new(|| {
+    let mut builder = Builder::empty();
+    builder
+        .add_tags(SVG_TAGS)
+        .add_generic_attributes(SVG_ATTRIBUTES)
+        .add_url_schemes(EXTRA_URL_SCHEMES)
+        .url_relative(ammonia::UrlRelative::PassThrough)
+        .link_rel(None);
+    builder
+});
+
+/// Clean HTML against an allowlist of elements and attributes. Scripts,
+/// frames, forms, `<meta>`, `<base>`, event handlers and `javascript:` URLs
+/// never survive.
+pub fn sanitize_html(html: &str) -> String {
+    HTML_SANITIZER.clean(html).to_string()
+}
+
+/// Clean an SVG document against an allowlist of drawing elements and
+/// attributes.
+pub fn sanitize_svg(svg: &str) -> String {
+    SVG_SANITIZER.clean(svg).to_string()
+}
+
+/// Whether output from a notebook's running kernel renders without
+/// sanitization.
+///
+/// A notebook without dependencies has nothing to sign, so the user has no
+/// way to trust it; its kernel only runs code the user chose to run, so its
+/// live output renders as is.
+pub fn kernel_output_trusted(status: &TrustStatus) -> bool {
+    matches!(status, TrustStatus::Trusted | TrustStatus::NoDependencies)
+}
+
+/// Whether a notebook's saved outputs render without sanitization.
+///
+/// Only a trusted signature counts. A notebook without dependencies has
+/// nothing to sign, which says nothing about the outputs saved in it, so
+/// they're treated as trusted only while it has never been saved or when it
+/// lives in a trusted directory.
+pub fn renders_trusted(status: &TrustStatus, notebook_path: Option<&Path>) -> bool {
+    match status {
+        TrustStatus::Trusted => true,
+        TrustStatus::NoDependencies => match notebook_path.filter(|path| path.exists()) {
+            None => true,
+            Some(path) => runt_trust::trust_scope_for(path).ok().flatten().is_some(),
+        },
+        TrustStatus::Untrusted | TrustStatus::SignatureInvalid => false,
+    }
+}
+
+/// The content to keep for one entry of an untrusted notebook's MIME
+/// bundle, or `None` to drop it.
+///
+/// Without `allow_javascript`, JavaScript outputs are dropped and HTML and
+/// SVG are sanitized. Without `allow_html`, HTML is dropped altogether so
+/// the frontend falls back to another representation.
+pub fn sanitize_bundle_entry(
+    mime_type: &str,
+    content: String,
+    allow_html: bool,
+    allow_javascript: bool,
+) -> Option<String> {
+    if allow_javascript && (allow_html || mime_type != "text/html") {
+        return Some(content);
+    }
+    match mime_type {
+        mime if JAVASCRIPT_MIME_TYPES.contains(&mime) => None,
+        "text/html" if !allow_html => None,
+        "text/html" => Some(sanitize_html(&content)),
+        "image/svg+xml" => Some(sanitize_svg(&content)),
+        _ => Some(content),
+    }
+}
+
+/// Sanitize the MIME bundle of an nbformat output in place, as
+/// [`sanitize_bundle_entry`] does for each entry. Streams and errors are
+/// left alone.
+pub fn sanitize_output(output: &mut Value, allow_html: bool, allow_javascript: bool) {
+    let Some(Value::Object(data)) = output.get_mut("data") else {
+        return;
+    };
+    let entries = std::mem::take(data);
+    for (mime_type, value) in entries {
+        let content = match &value {
+            Value::String(text) => text.clone(),
+            // Multiline strings are stored as arrays of lines
+            Value::Array(lines) if lines.iter().all(Value::is_string) => {
+                lines.iter().filter_map(Value::as_str).collect::<String>()
+            }
+            // JSON payloads (widgets, plotly, ...) aren't markup
+            _ => {
+                data.insert(mime_type, value);
+                continue;
+            }
+        };
+        if let Some(content) =
+            sanitize_bundle_entry(&mime_type, content, allow_html, allow_javascript)
+        {
+            data.insert(mime_type, Value::String(content));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_html() {
+        assert_eq!(
+            sanitize_html("<b>hi</b><script>alert(1)</script><SCRIPT src=x.js></SCRIPT>"),
+            "<b>hi</b>"
+        );
+        assert_eq!(
+            sanitize_html(r#"<img src="x.png" onerror="alert(1)"><p onclick=go()>p</p>"#),
+            r#"<img src="x.png"><p>p</p>"#
+        );
+        assert_eq!(
+            sanitize_html(r#"<a href=" javascript:alert(1)">link</a>"#),
+            r#"<a rel="noopener noreferrer">link</a>"#
+        );
+        assert_eq!(
+            sanitize_html(r#"<iframe src="https://example.com"></iframe><table></table>"#),
+            "<table></table>"
+        );
+        // Plain markup and its classes are kept
+        let table =
+            r#"<table class="dataframe"><tbody><tr><td>1</td><td>online</td></tr></tbody></table>"#;
+        assert_eq!(sanitize_html(table), table);
+    }
+
+    #[test]
+    fn test_sanitize_html_bypasses() {
+        for html in [
+            "<img/onerror=alert(1) src=x>",
+            "<scr<script></script>ipt>alert(1)</script>",
+            "<svg><script>alert(1)</script></svg>",
+            "<math><mi xlink:href=\"javascript:alert(1)\">x</mi></math>",
+        ] {
+            let clean = sanitize_html(html);
+            assert!(!clean.contains("onerror"), "{html} -> {clean}");
+            assert!(!clean.contains("<script"), "{html} -> {clean}");
+            assert!(!clean.contains("javascript:"), "{html} -> {clean}");
+        }
+        for html in [
+            r#"<meta http-equiv="refresh" content="0;url=https://evil.example">"#,
+            r#"<base href="https://evil.example/">"#,
+            r#"<form action="https://evil.example"><input name="q"></form>"#,
+        ] {
+            let clean = sanitize_html(html);
+            assert!(!clean.contains("<meta"), "{html} -> {clean}");
+            assert!(!clean.contains("<base"), "{html} -> {clean}");
+            assert!(!clean.contains("<form"), "{html} -> {clean}");
+            assert!(!clean.contains("<input"), "{html} -> {clean}");
+        }
+    }
+
+    #[test]
+    fn test_sanitize_svg_keeps_drawing() {
+        let svg = concat!(
+            r#"<?xml version="1.0" encoding="utf-8"?>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="100pt" height="50pt" viewBox="0 0 100 50">"#,
+            r#"<g id="figure_1" onload="alert(1)"><path d="M 0 0 L 100 50" style="fill: none; stroke: #1f77b4"/>"#,
+            r#"<script>alert(2)</script><text x="10" y="20">label</text></g></svg>"#,
+        );
+        let clean = sanitize_svg(svg);
+        assert!(clean.starts_with("<svg"), "{clean}");
+        assert!(clean.contains(r#"viewBox="0 0 100 50""#), "{clean}");
+        assert!(clean.contains(r#"<path d="M 0 0 L 100 50""#), "{clean}");
+        assert!(
+            clean.contains(r#"<text x="10" y="20">label</text>"#),
+            "{clean}"
+        );
+        assert!(!clean.contains("onload"), "{clean}");
+        assert!(!clean.contains("script"), "{clean}");
+    }
+
+    #[test]
+    fn test_sanitize_bundle_entry() {
+        let html = "<b>x</b><script>alert(1)</script>".to_string();
+
+        // Default settings: sanitized HTML, no JS
+        assert_eq!(
+            sanitize_bundle_entry("text/html", html.clone(), true, false).as_deref(),
+            Some("<b>x</b>")
+        );
+        assert_eq!(
+            sanitize_bundle_entry("application/javascript", "alert(1)".into(), true, false),
+            None
+        );
+        assert_eq!(
+            sanitize_bundle_entry("text/plain", "<script>".into(), true, false).as_deref(),
+            Some("<script>")
+        );
+
+        // HTML off
+        assert_eq!(
+            sanitize_bundle_entry("text/html", html.clone(), false, false),
+            None
+        );
+        assert_eq!(
+            sanitize_bundle_entry("text/html", html.clone(), false, true),
+            None
+        );
+
+        // Everything allowed
+        assert_eq!(
+            sanitize_bundle_entry("text/html", html.clone(), true, true),
+            Some(html)
+        );
+        assert_eq!(
+            sanitize_bundle_entry("application/javascript", "alert(1)".into(), true, true)
+                .as_deref(),
+            Some("alert(1)")
+        );
+    }
+
+    #[test]
+    fn test_sanitize_output() {
+        let mut output = serde_json::json!({
+            "output_type": "display_data",
+            "data": {
+                "text/html": ["<b>x</b>", "<script>alert(1)</script>"],
+                "application/javascript": "alert(2)",
+                "application/vnd.plotly.v1+json": {"data": []},
+                "text/plain": "x"
+            },
+            "metadata": {}
+        });
+        sanitize_output(&mut output, true, false);
+        assert_eq!(
+            output["data"],
+            serde_json::json!({
+                "text/html": "<b>x</b>",
+                "application/vnd.plotly.v1+json": {"data": []},
+                "text/plain": "x"
+            })
+        );
+
+        let mut stream = serde_json::json!({
+            "output_type": "stream", "name": "stdout", "text": "<script>"
+        });
+        let before = stream.clone();
+        sanitize_output(&mut stream, true, false);
+        assert_eq!(stream, before);
+    }
+
+    #[test]
+    fn test_renders_trusted() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let saved = tmp.path().join("downloaded.ipynb");
+        std::fs::write(&saved, "{}").unwrap();
+        let unsaved = tmp.path().join("Untitled.ipynb");
+
+        assert!(renders_trusted(&TrustStatus::Trusted, Some(&saved)));
+        assert!(!renders_trusted(&TrustStatus::Untrusted, None));
+        assert!(!renders_trusted(
+            &TrustStatus::SignatureInvalid,
+            Some(&saved)
+        ));
+        // No dependencies: trusted until it exists on disk
+        assert!(renders_trusted(&TrustStatus::NoDependencies, None));
+        assert!(renders_trusted(
+            &TrustStatus::NoDependencies,
+            Some(&unsaved)
+        ));
+        assert!(!renders_trusted(&TrustStatus::NoDependencies, Some(&saved)));
+    }
+
+    #[test]
+    fn test_kernel_output_trusted() {
+        assert!(kernel_output_trusted(&TrustStatus::Trusted));
+        // Saved or not, a plain notebook's own kernel output isn't sanitized
+        assert!(kernel_output_trusted(&TrustStatus::NoDependencies));
+        assert!(!kernel_output_trusted(&TrustStatus::Untrusted));
+        assert!(!kernel_output_trusted(&TrustStatus::SignatureInvalid));
+    }
+}
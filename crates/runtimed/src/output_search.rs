@@ -0,0 +1,296 @@
+//! Search a notebook's outputs for text.
+//!
+//! Matches stream text, error names/messages/tracebacks, and the `text/*`
+//! representations of display data and execute results. Outputs stored as
+//! manifests are searched through their manifest: only text fields are read,
+//! so images and other binary blobs are never fetched.
+
+use serde_json::Value;
+
+use crate::blob_store::BlobStore;
+use crate::output_store::{
+    is_manifest_hash, normalize_text, strip_ansi, ContentRef, OutputManifest,
+};
+use crate::protocol::OutputMatch;
+
+/// Longest snippet returned for a match, in characters.
+const SNIPPET_CHARS: usize = 120;
+
+/// Search outputs for `query` (case-insensitive).
+///
+/// `outputs` are `(cell_id, output_index, output)` triples as returned by
+/// `NotebookDoc::get_all_outputs`, where each output is a manifest hash or
+/// raw Jupyter output JSON. Returns at most one match per output, in
+/// notebook order.
+pub async fn search_outputs(
+    outputs: &[(String, usize, String)],
+    blob_store: &BlobStore,
+    query: &str,
+) -> Vec<OutputMatch> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return vec![];
+    }
+
+    let mut matches = Vec::new();
+    for (cell_id, output_index, output) in outputs {
+        let Some((output_type, text)) = output_text(output, blob_store).await else {
+            continue;
+        };
+        if let Some(snippet) = snippet(&strip_ansi(&text), &query) {
+            matches.push(OutputMatch {
+                cell_id: cell_id.clone(),
+                output_index: *output_index,
+                output_type,
+                snippet,
+            });
+        }
+    }
+    matches
+}
+
+/// The searchable text of an output along with its `output_type`.
+async fn output_text(output: &str, blob_store: &BlobStore) -> Option<(String, String)> {
+    if is_manifest_hash(output) {
+        let bytes = blob_store.get(output).await.ok()??;
+        let manifest: OutputManifest = serde_json::from_slice(&bytes).ok()?;
+        manifest_text(manifest, blob_store).await
+    } else {
+        raw_output_text(&serde_json::from_str(output).ok()?)
+    }
+}
+
+async fn manifest_text(
+    manifest: OutputManifest,
+    blob_store: &BlobStore,
+) -> Option<(String, String)> {
+    match manifest {
+        OutputManifest::Stream { text, .. } => {
+            Some(("stream".to_string(), text.resolve(blob_store).await.ok()?))
+        }
+        OutputManifest::Error {
+            ename,
+            evalue,
+            traceback,
+            ..
+        } => {
+            let traceback = traceback.resolve(blob_store).await.ok();
+            let traceback = traceback
+                .and_then(|json| serde_json::from_str::<Vec<String>>(&json).ok())
+                .unwrap_or_default();
+            Some(("error".to_string(), error_text(&ename, &evalue, &traceback)))
+        }
+        OutputManifest::DisplayData { data, .. } => Some((
+            "display_data".to_string(),
+            text_bundle(data.into_iter(), blob_store).await,
+        )),
+        OutputManifest::ExecuteResult { data, .. } => Some((
+            "execute_result".to_string(),
+            text_bundle(data.into_iter(), blob_store).await,
+        )),
+    }
+}
+
+/// Join the `text/*` entries of a manifest data bundle.
+async fn text_bundle(
+    data: impl Iterator<Item = (String, ContentRef)>,
+    blob_store: &BlobStore,
+) -> String {
+    let mut entries: Vec<(String, ContentRef)> =
+        data.filter(|(mime, _)| mime.starts_with("text/")).collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut texts = Vec::new();
+    for (_, content) in entries {
+        if let Ok(text) = content.resolve(blob_store).await {
+            texts.push(text);
+        }
+    }
+    texts.join("\n")
+}
+
+fn raw_output_text(output: &Value) -> Option<(String, String)> {
+    let output_type = output.get("output_type")?.as_str()?.to_string();
+    let text = match output_type.as_str() {
+        "stream" => normalize_text(output.get("text")?),
+        "error" => {
+            let field = |key: &str| output.get(key).and_then(|v| v.as_str()).unwrap_or("");
+            let traceback: Vec<String> = output
+                .get("traceback")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default();
+            error_text(field("ename"), field("evalue"), &traceback)
+        }
+        "display_data" | "execute_result" => {
+            let mut entries: Vec<(&String, &Value)> = output
+                .get("data")?
+                .as_object()?
+                .iter()
+                .filter(|(mime, _)| mime.starts_with("text/"))
+                .collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            entries
+                .into_iter()
+                .map(|(_, value)| normalize_text(value))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        _ => return None,
+    };
+    Some((output_type, text))
+}
+
+fn error_text(ename: &str, evalue: &str, traceback: &[String]) -> String {
+    let mut text = format!("{}: {}", ename, evalue);
+    for line in traceback {
+        text.push('\n');
+        text.push_str(line);
+    }
+    text
+}
+
+/// The first line containing `query` (already lowercased), trimmed to at most
+/// `SNIPPET_CHARS` around the match.
+fn snippet(text: &str, query: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let lower = line.to_lowercase();
+        let byte_idx = lower.find(query)?;
+        let match_start = lower[..byte_idx].chars().count();
+        Some(window(line.trim_end(), match_start, query.chars().count()))
+    })
+}
+
+fn window(line: &str, match_start: usize, match_len: usize) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.len() <= SNIPPET_CHARS {
+        return line.trim().to_string();
+    }
+    let context = SNIPPET_CHARS.saturating_sub(match_len) / 2;
+    let start = match_start
+        .saturating_sub(context)
+        .min(chars.len() - SNIPPET_CHARS);
+    let end = start + SNIPPET_CHARS;
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    snippet.extend(&chars[start..end]);
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output_store::{create_manifest, store_manifest, DEFAULT_INLINE_THRESHOLD};
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    async fn manifest_hash(output: Value, store: &BlobStore, threshold: usize) -> String {
+        let manifest = create_manifest(&output, store, threshold).await.unwrap();
+        store_manifest(&manifest, store).await.unwrap()
+    }
+
+    fn outputs(entries: &[(&str, &str)]) -> Vec<(String, usize, String)> {
+        entries
+            .iter()
+            .enumerate()
+            .map(|(i, (cell_id, output))| (cell_id.to_string(), i, output.to_string()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_matches_stream_and_error_outputs() {
+        let dir = TempDir::new().unwrap();
+        let store = BlobStore::new(dir.path().join("blobs"));
+
+        let stream = manifest_hash(
+            json!({"output_type": "stream", "name": "stdout", "text": ["loading data\n", "Connection refused by host\n"]}),
+            &store,
+            DEFAULT_INLINE_THRESHOLD,
+        )
+        .await;
+        let error = manifest_hash(
+            json!({
+                "output_type": "error",
+                "ename": "KeyError",
+                "evalue": "'user_id'",
+                "traceback": ["\u{1b}[0;31mKeyError\u{1b}[0m: 'user_id'"]
+            }),
+            &store,
+            DEFAULT_INLINE_THRESHOLD,
+        )
+        .await;
+        let unrelated = r#"{"output_type":"stream","name":"stdout","text":"all good"}"#;
+        let outputs = outputs(&[("a", &stream), ("b", &error), ("c", unrelated)]);
+
+        let matches = search_outputs(&outputs, &store, "connection REFUSED").await;
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].cell_id, "a");
+        assert_eq!(matches[0].output_type, "stream");
+        assert_eq!(matches[0].snippet, "Connection refused by host");
+
+        let matches = search_outputs(&outputs, &store, "user_id").await;
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].cell_id, "b");
+        assert_eq!(matches[0].output_type, "error");
+        assert_eq!(matches[0].snippet, "KeyError: 'user_id'");
+
+        assert!(search_outputs(&outputs, &store, "   ").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_matches_raw_outputs_and_blobbed_text() {
+        let dir = TempDir::new().unwrap();
+        let store = BlobStore::new(dir.path().join("blobs"));
+
+        // A tiny threshold pushes the text into its own blob
+        let result = manifest_hash(
+            json!({
+                "output_type": "execute_result",
+                "data": {"text/plain": "DataFrame with 42 rows", "image/png": "iVBORw0KGgo="},
+                "metadata": {},
+                "execution_count": 3
+            }),
+            &store,
+            4,
+        )
+        .await;
+        let raw_error = json!({
+            "output_type": "error",
+            "ename": "ZeroDivisionError",
+            "evalue": "division by zero",
+            "traceback": []
+        })
+        .to_string();
+        let outputs = outputs(&[("a", &result), ("b", &raw_error)]);
+
+        let matches = search_outputs(&outputs, &store, "42 rows").await;
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].output_type, "execute_result");
+
+        // Binary representations are not searched
+        assert!(search_outputs(&outputs, &store, "iVBOR").await.is_empty());
+
+        let matches = search_outputs(&outputs, &store, "division").await;
+        assert_eq!(matches[0].cell_id, "b");
+        assert_eq!(matches[0].snippet, "ZeroDivisionError: division by zero");
+    }
+
+    #[test]
+    fn test_snippet_is_trimmed_around_match() {
+        let line = format!("{}needle{}", "a".repeat(200), "b".repeat(200));
+        let snippet = snippet(&line, "needle").unwrap();
+        assert!(snippet.contains("needle"));
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert_eq!(snippet.chars().count(), SNIPPET_CHARS + 2);
+
+        assert_eq!(
+            super::snippet("  needle  \nother", "needle").as_deref(),
+            Some("needle")
+        );
+    }
+}
@@ -6,7 +6,11 @@
 use serde::{Deserialize, Serialize};
 
 use crate::comm_state::CommSnapshot;
+use crate::env_cache::{EnvCacheCleared, EnvCacheInfo};
 use crate::kernel_manager::LaunchedEnvConfig;
+use crate::kernel_pids::TrackedKernel;
+use crate::kernel_snapshot::DroppedVariable;
+use crate::notebook_doc::CellExecutionTiming;
 use crate::{EnvType, PoolError, PoolStats, PooledEnv};
 
 /// Requests that clients can send to the daemon.
@@ -40,6 +44,30 @@ pub enum Request {
 
     /// List all active notebook rooms.
     ListRooms,
+
+    /// List every notebook in the registry, open or not, most recent first.
+    ListNotebooks,
+
+    /// Shut down the kernel of every notebook room. Rooms stay open.
+    ShutdownAllKernels,
+
+    /// List cached environments with their disk usage.
+    GetEnvCacheInfo,
+
+    /// Delete the named cached environments, skipping any a running kernel
+    /// uses.
+    ClearEnvCache { env_ids: Vec<String> },
+
+    /// Kill kernel processes a daemon spawned that no open room owns, e.g.
+    /// ones left running by a daemon that crashed.
+    ReapKernels,
+
+    /// Export the synced settings as JSON, without machine-specific paths.
+    ExportSettings,
+
+    /// Apply settings JSON from `ExportSettings`, merged over the current
+    /// settings unless `replace` is set.
+    ImportSettings { json: String, replace: bool },
 }
 
 /// Responses from the daemon to clients.
@@ -84,6 +112,29 @@ pub enum Response {
 
     /// List of active notebook rooms.
     RoomsList { rooms: Vec<RoomInfo> },
+
+    /// Notebooks from the registry, most recently opened first.
+    NotebooksList {
+        notebooks: Vec<crate::notebook_registry::NotebookRecord>,
+    },
+
+    /// Kernels stopped by `ShutdownAllKernels`, by notebook ID.
+    KernelsShutDown { notebook_ids: Vec<String> },
+
+    /// Cached environments and their disk usage.
+    EnvCacheInfo { info: EnvCacheInfo },
+
+    /// Result of `ClearEnvCache`.
+    EnvCacheCleared { cleared: EnvCacheCleared },
+
+    /// Orphaned kernels killed by `ReapKernels`.
+    KernelsReaped { reaped: Vec<TrackedKernel> },
+
+    /// Settings JSON from `ExportSettings`.
+    SettingsExported { json: String },
+
+    /// Result of `ImportSettings`: whether any setting changed.
+    SettingsImported { changed: bool },
 }
 
 /// Kernel info for a notebook room.
@@ -109,6 +160,15 @@ pub struct RoomInfo {
     /// Kernel status if running (e.g., "idle", "busy", "starting")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub kernel_status: Option<String>,
+    /// Cell currently executing, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executing_cell: Option<String>,
+    /// Number of cells waiting behind the executing one
+    #[serde(default)]
+    pub queue_depth: usize,
+    /// When the notebook was last opened, from the notebook registry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_opened: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 /// Blob channel request.
@@ -168,12 +228,36 @@ pub enum NotebookRequest {
     /// This is the preferred method - ensures execution matches synced document state.
     ExecuteCell { cell_id: String },
 
+    /// Execute several cells as one batch, in the given order, reading sources
+    /// from the synced doc. Outputs are cleared and execute requests pipelined
+    /// in a single pass. Unknown ids and non-code cells are skipped.
+    QueueCellBatch { cell_ids: Vec<String> },
+
     /// Clear outputs for a cell (before re-execution).
     ClearOutputs { cell_id: String },
 
+    /// Clear outputs and execution counts of every code cell, and release
+    /// the blobs their outputs referenced.
+    /// Returns AllOutputsCleared.
+    ClearAllOutputs {},
+
+    /// Copy a cell's outputs and execution count onto another cell (e.g. a
+    /// duplicate), taking blob references for the copies.
+    /// Returns OutputsCopied.
+    CopyOutputs {
+        from_cell_id: String,
+        to_cell_id: String,
+    },
+
     /// Interrupt the currently executing cell.
     InterruptExecution {},
 
+    /// Interrupt the executing cell and drop every queued cell, cancelling
+    /// a run-all. Handled in order with the kernel's queue updates; a
+    /// `RunAllCancelled` broadcast reports how many cells were skipped.
+    /// Returns InterruptSent.
+    CancelRunAll {},
+
     /// Shutdown the kernel for this room.
     ShutdownKernel {},
 
@@ -185,6 +269,8 @@ pub enum NotebookRequest {
 
     /// Run all code cells from the synced document.
     /// Daemon reads cell sources from the Automerge doc and queues them.
+    /// Cells tagged `skip-execution` are left out; an error in a cell tagged
+    /// `raises-exception` doesn't stop the rest.
     RunAllCells {},
 
     /// Send a comm message to the kernel (widget interactions).
@@ -215,6 +301,19 @@ pub enum NotebookRequest {
         cursor_pos: usize,
     },
 
+    /// Request documentation for the symbol at the cursor.
+    /// Answered by the language server when enabled, otherwise by the kernel.
+    /// Returns InspectResult.
+    Inspect {
+        /// The code containing the symbol
+        code: String,
+        /// Cursor position in the code
+        cursor_pos: usize,
+        /// 0 for `x?`, 1 for `x??` (kernel only)
+        #[serde(default)]
+        detail_level: usize,
+    },
+
     /// Save the notebook to disk.
     /// The daemon reads cells and metadata from the Automerge doc, merges
     /// with any existing .ipynb on disk (to preserve unknown metadata keys),
@@ -227,6 +326,96 @@ pub enum NotebookRequest {
     /// Sync environment with current metadata (hot-install new packages).
     /// Only supported for UV inline deps. Falls back to restart for removals/conda.
     SyncEnvironment {},
+
+    /// List the environments the kernel could be launched with.
+    /// Returns Environments.
+    ListEnvironments {},
+
+    /// Restart the kernel against one of the options from ListEnvironments.
+    /// Returns the same responses as LaunchKernel.
+    SwitchEnvironment {
+        /// `EnvOption::id` of the chosen option
+        option_id: String,
+        /// Where the notebook is saved now, if the room was opened under
+        /// another ID (e.g. an untitled notebook that was just saved)
+        #[serde(default)]
+        notebook_path: Option<String>,
+    },
+
+    /// Rebuild the notebook's cached inline environment if it is broken
+    /// (missing interpreter or ipykernel fails to import).
+    /// The kernel must not be running.
+    RepairEnvironment {},
+
+    /// Pin the running kernel's prewarmed environment to the notebook's
+    /// env_id, so later launches reattach it and it is never recycled.
+    /// Returns EnvironmentPinned.
+    PinEnvironment {},
+
+    /// Release the environment pinned to the notebook's env_id.
+    /// Returns EnvironmentUnpinned.
+    UnpinEnvironment {},
+
+    /// Get the dependency tree of the running kernel's environment, with the
+    /// notebook's declared dependencies marked direct.
+    /// Returns DependencyTree.
+    GetDependencyTree {},
+
+    /// Get the notebook's cells with every output as nbformat JSON, output
+    /// manifests resolved from the blob store.
+    /// Returns ResolvedCells.
+    GetResolvedCells {},
+
+    /// List notebooks the daemon has opened, across restarts.
+    /// Returns Notebooks.
+    ListNotebooks {},
+
+    /// Search cell outputs for text (case-insensitive).
+    /// Returns OutputMatches.
+    SearchOutputs { query: String },
+
+    /// Get a cell's outputs as manifests in one round trip.
+    /// Returns CellOutputs.
+    GetCellOutputs { cell_id: String },
+
+    /// Get the timing of each cell's most recent execution.
+    /// Returns CellTimings.
+    GetCellTimings {},
+
+    /// Get the last `limit` entries of the notebook's execution log (see
+    /// `run_log`). Returns RunLog.
+    GetRunLog { limit: usize },
+
+    /// Get the command line the room's kernel was last spawned with, for
+    /// debugging a failed launch. Returns LaunchSpec.
+    GetLaunchSpec {},
+
+    /// Get the running kernel's connection file, for attaching the sidecar
+    /// viewer. Returns ConnectionFile.
+    GetConnectionFile {},
+
+    /// Start or stop streaming this room's environment build output (uv's
+    /// stdout and stderr) to this connection as `EnvBuildLog` broadcasts.
+    /// Subscribing returns EnvBuildLog with the lines the in-progress (or
+    /// last) build has written so far; unsubscribing returns Ok.
+    StreamEnvBuildLog { subscribe: bool },
+
+    /// Save the Python kernel's user namespace with dill, for restoring into
+    /// the next kernel. Returns KernelStateSnapshot.
+    SnapshotKernelState {},
+
+    /// Load the last SnapshotKernelState into the running kernel.
+    /// Returns KernelStateRestored.
+    RestoreKernelState {},
+
+    /// Send a Debug Adapter Protocol request to the kernel's debugger on the
+    /// control channel. The kernel must advertise `debugger: true`.
+    /// Returns DebugResult.
+    Debug { request: DebugCommand },
+
+    /// Keepalive check that the daemon is still answering on this connection.
+    /// Returns Pong.
+    Ping {},
 }
 
 /// Responses from daemon to notebook app.
@@ -252,9 +441,27 @@ pub enum NotebookResponse {
     /// Cell queued for execution.
     CellQueued { cell_id: String },
 
+    /// Batch of cells queued for execution (in execution order).
+    CellBatchQueued {
+        cell_ids: Vec<String>,
+        /// Requested ids that were unknown or not code cells
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        skipped: Vec<String>,
+    },
+
     /// Outputs cleared.
     OutputsCleared { cell_id: String },
 
+    /// Outputs of every code cell cleared.
+    AllOutputsCleared {
+        cell_ids: Vec<String>,
+        /// Number of blobs deleted from the blob store
+        blobs_reclaimed: usize,
+    },
+
+    /// Outputs copied onto `cell_id`.
+    OutputsCopied { cell_id: String, count: usize },
+
     /// Interrupt sent to kernel.
     InterruptSent {},
 
@@ -301,16 +508,103 @@ pub enum NotebookResponse {
         cursor_end: usize,
     },
 
+    /// Symbol inspection result.
+    InspectResult {
+        found: bool,
+        /// Plain-text documentation, if found
+        #[serde(skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+        /// Source: "kernel" or "lsp"
+        #[serde(skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
+    },
+
+    /// Available environments, in auto-detection priority order.
+    Environments { options: Vec<EnvOption> },
+
+    /// Notebooks from the daemon's registry, most recently opened first.
+    Notebooks {
+        notebooks: Vec<crate::notebook_registry::NotebookRecord>,
+    },
+
+    /// Outputs matching a SearchOutputs query, in notebook order.
+    OutputMatches { matches: Vec<OutputMatch> },
+
+    /// A cell's outputs in document order (see `output_store::cell_output_manifests`).
+    CellOutputs {
+        cell_id: String,
+        outputs: Vec<serde_json::Value>,
+    },
+
+    /// Execution timings of cells that have run, in notebook order.
+    CellTimings { timings: Vec<CellTiming> },
+
+    /// Execution log entries, oldest first.
+    RunLog {
+        entries: Vec<crate::run_log::RunLogEntry>,
+    },
+
+    /// Most recent kernel spawn in this room, if any.
+    LaunchSpec {
+        spec: Option<crate::kernel_manager::LaunchSpec>,
+    },
+
+    /// Connection file of the room's running kernel, if one is running.
+    ConnectionFile { path: Option<std::path::PathBuf> },
+
+    /// Output of the room's current or most recent environment build, oldest
+    /// first. Later lines follow as `EnvBuildLog` broadcasts.
+    EnvBuildLog { lines: Vec<EnvBuildLogLine> },
+
+    /// Environment checked, and rebuilt if it was broken.
+    EnvironmentRepaired {
+        /// Inline env source that was checked ("uv:inline" or "conda:inline")
+        env_source: String,
+        /// Whether the environment was broken and had to be rebuilt
+        rebuilt: bool,
+    },
+
+    /// The kernel's prewarmed environment is pinned to the notebook.
+    EnvironmentPinned {
+        /// The notebook's env_id the environment is pinned to
+        env_id: String,
+        /// Directory of the pinned environment
+        env_path: std::path::PathBuf,
+    },
+
+    /// The environment pinned to the notebook was released.
+    EnvironmentUnpinned {
+        env_id: String,
+        /// Directory of the formerly pinned environment
+        env_path: std::path::PathBuf,
+    },
+
+    /// The running kernel's environment as a dependency tree.
+    DependencyTree {
+        /// "uv" or "conda"
+        env_type: String,
+        /// Packages nothing else in the environment depends on
+        roots: Vec<kernel_env::DepNode>,
+    },
+
+    /// The notebook's cells, each output a JSON-encoded nbformat output
+    /// rather than a manifest hash.
+    ResolvedCells {
+        cells: Vec<crate::notebook_doc::CellSnapshot>,
+    },
+
     /// Environment sync started (installing new packages).
     SyncEnvironmentStarted {
         /// Packages being installed
         packages: Vec<String>,
     },
 
-    /// Environment sync completed successfully.
+    /// Environment sync finished. Some packages may have failed; see
+    /// `failed` in the report.
     SyncEnvironmentComplete {
-        /// Packages that were installed
-        synced_packages: Vec<String>,
+        /// Packages installed, removed, and failed
+        #[serde(flatten)]
+        report: kernel_env::SyncReport,
     },
 
     /// Environment sync failed (fall back to restart).
@@ -320,6 +614,128 @@ pub enum NotebookResponse {
         /// Whether the user should restart instead
         needs_restart: bool,
     },
+
+    /// Kernel user namespace saved.
+    KernelStateSnapshot {
+        /// Variables saved
+        saved: Vec<String>,
+        /// Variables that couldn't be pickled
+        dropped: Vec<DroppedVariable>,
+    },
+
+    /// Kernel user namespace restored from the last snapshot.
+    KernelStateRestored {
+        /// Variables loaded into the kernel
+        restored: Vec<String>,
+        /// Variables that couldn't be unpickled
+        dropped: Vec<DroppedVariable>,
+    },
+
+    /// Body of the debugger's response to a Debug request.
+    DebugResult { body: serde_json::Value },
+
+    /// Reply to Ping.
+    Pong {},
+}
+
+/// A Debug Adapter Protocol request for the kernel's debugger.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum DebugCommand {
+    /// Start a debug session.
+    Initialize {},
+    /// Replace the breakpoints in the source file at `path` (1-based lines).
+    SetBreakpoints { path: String, lines: Vec<u32> },
+    /// Resume a thread stopped at a breakpoint.
+    Continue { thread_id: i64 },
+    /// Get the call stack of a stopped thread.
+    StackTrace { thread_id: i64 },
+}
+
+/// An environment a notebook's kernel can be launched with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvOption {
+    /// Stable identifier, passed back to SwitchEnvironment
+    pub id: String,
+    /// Human-readable label (e.g. "pyproject.toml (../pyproject.toml)")
+    pub label: String,
+    /// env_source the kernel is launched with (e.g. "uv:inline")
+    pub env_source: String,
+    /// Rough estimate of how long the kernel takes to start
+    pub startup_cost: StartupCost,
+    /// Whether `env_source: "auto"` would pick this option
+    pub is_default: bool,
+}
+
+/// Estimated kernel startup cost for an [`EnvOption`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupCost {
+    /// Taken from the prewarmed pool
+    Instant,
+    /// Environment exists on disk or is resolved quickly by the project tool
+    Fast,
+    /// Packages must be solved and installed first
+    Slow,
+}
+
+/// A cell output whose text matched a `SearchOutputs` query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputMatch {
+    pub cell_id: String,
+    /// Index of the output within the cell
+    pub output_index: usize,
+    /// Jupyter output type (e.g. "stream", "error")
+    pub output_type: String,
+    /// The matching line, shortened around the match
+    pub snippet: String,
+}
+
+/// A line written by an environment build subprocess, for `StreamEnvBuildLog`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvBuildLogLine {
+    /// Increases by one per line across the room's builds
+    pub seq: u64,
+    /// "uv" or "conda"
+    pub env_type: String,
+    pub stream: kernel_env::LogStream,
+    pub text: String,
+}
+
+/// A frontend action a kernel asked for in an `execute_reply` payload.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum KernelPayload {
+    /// Text for a pager (IPython `?`, `??`, `%pinfo`) as a MIME bundle,
+    /// starting at line `start`. The daemon also appends it to the cell as a
+    /// display_data output.
+    Page {
+        data: serde_json::Value,
+        #[serde(default)]
+        start: usize,
+    },
+    /// Source for the next cell (`%load`, `%recall`). The daemon inserts a
+    /// code cell with `text` after the executed cell, or with `replace`,
+    /// replaces the executed cell's source.
+    SetNextInput {
+        text: String,
+        #[serde(default)]
+        replace: bool,
+    },
+    /// The kernel asked to exit (`exit()`, `quit()`). With `keepkernel` the
+    /// kernel should be left running.
+    AskExit {
+        #[serde(default)]
+        keepkernel: bool,
+    },
+}
+
+/// Timing of a cell's most recent execution, for `GetCellTimings`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellTiming {
+    pub cell_id: String,
+    #[serde(flatten)]
+    pub timing: CellExecutionTiming,
 }
 
 /// A single entry from kernel input history.
@@ -374,6 +790,11 @@ pub enum NotebookBroadcast {
         cell_id: String,
         output_type: String, // "stream", "display_data", "execute_result", "error"
         output_json: String, // Serialized Jupyter output content
+        /// Position of the output within the cell, set when this broadcast
+        /// replaces the output there (stream upserts). A later broadcast for
+        /// the same index supersedes an earlier one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output_index: Option<usize>,
     },
 
     /// Display output updated in place (update_display_data).
@@ -392,12 +813,86 @@ pub enum NotebookBroadcast {
         queued: Vec<String>,
     },
 
+    /// A run-all batch advanced: `completed` of its `total` cells have
+    /// finished and `current_cell` is executing. Sent when the batch is
+    /// queued and after each of its cells.
+    RunAllProgress {
+        completed: usize,
+        total: usize,
+        current_cell: Option<String>,
+    },
+
+    /// A run was cancelled: the executing cell was interrupted and the
+    /// `skipped` cells queued behind it were dropped.
+    RunAllCancelled { skipped: usize },
+
     /// Kernel error (failed to launch, crashed, etc.)
     KernelError { error: String },
 
+    /// Kernel lifecycle transition outside the normal request flow.
+    ///
+    /// - `launched`: a kernel finished starting and answered `kernel_info`.
+    ///   Carries the kernel type (`python`, `deno`, or a kernelspec name) and
+    ///   env source (`kernelspec` for kernels launched from a kernelspec).
+    /// - `unresponsive`: the kernel process is alive but didn't answer a
+    ///   `kernel_info_request` before its first cell. Queued cells are
+    ///   cancelled with `reason`; the frontend can offer a restart.
+    /// - `crashed`: the kernel process exited without being shut down. Cells
+    ///   that were executing or queued are cancelled with `reason`; the
+    ///   frontend can offer a restart.
+    /// - `reattached`: sent only to a newly connected peer when the room
+    ///   already has a live kernel, instead of launching a new one.
+    /// - `auto_launch_disabled`: sent to a newly connected peer when no
+    ///   kernel is running because the notebook sets `runt.auto_launch: false`.
+    /// - `error`: a launch failed. `reason` says why; if the kernel process
+    ///   started but never answered (timed out or exited during startup),
+    ///   `stderr_tail` carries its last stderr lines and `exit_code` is set
+    ///   when it exited.
+    KernelLifecycle {
+        state: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        kernel_type: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        env_source: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stderr_tail: Option<String>,
+        #[serde(default)]
+        cancelled_cells: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+
     /// Outputs cleared for a cell.
     OutputsCleared { cell_id: String },
 
+    /// A cell failed to import a module that isn't installed. `package` and
+    /// `conda_package` are the PyPI and conda-forge names of the package
+    /// likely providing `module`; the frontend can offer to add the one for
+    /// the notebook's environment to its dependencies and sync it.
+    SuggestInstall {
+        cell_id: String,
+        module: String,
+        package: String,
+        conda_package: String,
+    },
+
+    /// A cell's `execute_reply` carried a payload for the frontend.
+    KernelPayload {
+        cell_id: String,
+        payload: KernelPayload,
+    },
+
+    /// Another open notebook's kernel was launched with the same `env_id`,
+    /// so their dependency syncs would fight over one environment. Sent to
+    /// every room involved; the frontend offers to regenerate the env_id.
+    EnvIdCollision {
+        env_id: String,
+        /// Notebook IDs of the other rooms sharing `env_id`
+        other_notebooks: Vec<String>,
+    },
+
     /// Comm message from kernel (ipywidgets protocol).
     /// Broadcast to all connected peers so all windows can display widgets.
     Comm {
@@ -427,6 +922,14 @@ pub enum NotebookBroadcast {
         phase: kernel_env::EnvProgressPhase,
     },
 
+    /// A line of environment build output. Only sent to the connections that
+    /// subscribed with `StreamEnvBuildLog`, not through the room's broadcast
+    /// channel.
+    EnvBuildLog {
+        #[serde(flatten)]
+        line: EnvBuildLogLine,
+    },
+
     /// Environment sync state changed.
     ///
     /// Broadcast when notebook metadata changes and differs from the
@@ -566,6 +1069,45 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_shutdown_all_kernels_roundtrip() {
+        assert!(matches!(
+            roundtrip_request(&Request::ShutdownAllKernels),
+            Request::ShutdownAllKernels
+        ));
+        let resp = Response::KernelsShutDown {
+            notebook_ids: vec!["/work/a.ipynb".to_string()],
+        };
+        match roundtrip_response(&resp) {
+            Response::KernelsShutDown { notebook_ids } => {
+                assert_eq!(notebook_ids, vec!["/work/a.ipynb"]);
+            }
+            _ => panic!("unexpected response type"),
+        }
+    }
+
+    #[test]
+    fn test_clear_env_cache_roundtrip() {
+        match roundtrip_request(&Request::ClearEnvCache {
+            env_ids: vec!["0123456789abcdef".to_string()],
+        }) {
+            Request::ClearEnvCache { env_ids } => assert_eq!(env_ids, vec!["0123456789abcdef"]),
+            _ => panic!("unexpected request type"),
+        }
+        let cleared = EnvCacheCleared {
+            removed: vec!["0123456789abcdef".to_string()],
+            in_use: vec!["runtimed-uv-abc".to_string()],
+            pinned: vec!["runtimed-conda-def".to_string()],
+            freed_bytes: 4096,
+        };
+        match roundtrip_response(&Response::EnvCacheCleared {
+            cleared: cleared.clone(),
+        }) {
+            Response::EnvCacheCleared { cleared: back } => assert_eq!(back, cleared),
+            _ => panic!("unexpected response type"),
+        }
+    }
+
     #[test]
     fn test_response_env() {
         let env = PooledEnv {
@@ -605,6 +1147,8 @@ mod tests {
             uv_warming: 1,
             conda_available: 2,
             conda_warming: 0,
+            uv_pinned: 1,
+            conda_pinned: 0,
             uv_error: None,
             conda_error: None,
         };
@@ -617,6 +1161,7 @@ mod tests {
                 assert_eq!(s.uv_warming, 1);
                 assert_eq!(s.conda_available, 2);
                 assert_eq!(s.conda_warming, 0);
+                assert_eq!(s.uv_pinned, 1);
             }
             _ => panic!("unexpected response type"),
         }
@@ -729,6 +1274,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_notebook_request_queue_cell_batch() {
+        let req = NotebookRequest::QueueCellBatch {
+            cell_ids: vec!["a".into(), "b".into()],
+        };
+        let json = serde_json::to_string(&req).unwrap();
+        assert!(json.contains("queue_cell_batch"));
+
+        let parsed: NotebookRequest = serde_json::from_str(&json).unwrap();
+        match parsed {
+            NotebookRequest::QueueCellBatch { cell_ids } => {
+                assert_eq!(cell_ids, vec!["a", "b"]);
+            }
+            _ => panic!("unexpected request type"),
+        }
+    }
+
     #[test]
     fn test_notebook_request_execute_cell() {
         let req = NotebookRequest::ExecuteCell {
@@ -765,12 +1327,36 @@ mod tests {
         assert!(matches!(parsed, NotebookResponse::KernelLaunched { .. }));
     }
 
+    #[test]
+    fn test_notebook_response_environments() {
+        let resp = NotebookResponse::Environments {
+            options: vec![EnvOption {
+                id: "uv:inline".into(),
+                label: "Inline uv dependencies (2)".into(),
+                env_source: "uv:inline".into(),
+                startup_cost: StartupCost::Slow,
+                is_default: true,
+            }],
+        };
+        let json = serde_json::to_string(&resp).unwrap();
+        assert!(json.contains(r#""result":"environments""#));
+        assert!(json.contains(r#""startup_cost":"slow""#));
+
+        let parsed: NotebookResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            serde_json::to_string(&parsed).unwrap(),
+            json,
+            "environments response should roundtrip"
+        );
+    }
+
     #[test]
     fn test_notebook_broadcast_output() {
         let broadcast = NotebookBroadcast::Output {
             cell_id: "cell-1".into(),
             output_type: "stream".into(),
             output_json: r#"{"name":"stdout","text":"hello\n"}"#.into(),
+            output_index: None,
         };
         let json = serde_json::to_string(&broadcast).unwrap();
         assert!(json.contains("output"));
@@ -801,6 +1387,23 @@ mod tests {
         assert!(json.contains("busy"));
     }
 
+    #[test]
+    fn test_notebook_broadcast_kernel_crashed() {
+        let broadcast = NotebookBroadcast::KernelLifecycle {
+            state: "crashed".into(),
+            kernel_type: Some("python".into()),
+            env_source: None,
+            exit_code: Some(139),
+            stderr_tail: None,
+            cancelled_cells: vec!["cell-1".into()],
+            reason: Some("kernel died".into()),
+        };
+        let json = serde_json::to_string(&broadcast).unwrap();
+        assert!(json.contains("\"event\":\"kernel_lifecycle\""));
+        assert!(json.contains("\"exit_code\":139"));
+        assert!(!json.contains("stderr_tail"));
+    }
+
     #[test]
     fn test_notebook_broadcast_comm_sync() {
         let comm = CommSnapshot {
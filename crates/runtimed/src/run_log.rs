@@ -0,0 +1,225 @@
+//! Per-notebook execution log, for auditing what ran and how it went.
+//!
+//! When the `run_log` setting is on, every finished cell execution is
+//! appended as one JSON line to `<notebook>.ipynb.runlog.jsonl` next to the
+//! notebook. Entries record the cell, a hash of the code that ran (so later
+//! edits are visible), when it started, how long it took and whether it
+//! raised.
+//!
+//! The file is capped at [`MAX_RUN_LOG_BYTES`]: an append that pushes it over
+//! rewrites it keeping the newest entries that fit in half the cap.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Size at which the log is trimmed.
+pub const MAX_RUN_LOG_BYTES: u64 = 1024 * 1024;
+
+/// How an execution ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Ok,
+    Error,
+}
+
+/// One finished execution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunLogEntry {
+    pub cell_id: String,
+    /// [`source_hash`] of the code the kernel ran.
+    pub source_hash: String,
+    /// RFC 3339 timestamp of the kernel's `execute_input`.
+    pub started_at: String,
+    pub duration_ms: u64,
+    pub status: RunStatus,
+    /// `ename: evalue` of the error, for failed executions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Short hash identifying a cell's source (first 16 hex digits of SHA-256).
+pub fn source_hash(code: &str) -> String {
+    hex::encode(Sha256::digest(code.as_bytes()))[..16].to_string()
+}
+
+/// Where the log for the notebook at `notebook_path` lives.
+pub fn run_log_path(notebook_path: &Path) -> PathBuf {
+    let mut name = notebook_path.file_name().unwrap_or_default().to_owned();
+    name.push(".runlog.jsonl");
+    notebook_path.with_file_name(name)
+}
+
+/// Append-only writer for one notebook's log.
+#[derive(Debug, Clone)]
+pub struct RunLog {
+    path: PathBuf,
+    max_bytes: u64,
+}
+
+impl RunLog {
+    /// Log for the notebook at `notebook_path`.
+    pub fn for_notebook(notebook_path: &Path) -> Self {
+        Self {
+            path: run_log_path(notebook_path),
+            max_bytes: MAX_RUN_LOG_BYTES,
+        }
+    }
+
+    /// Trim the log at `max_bytes` instead of [`MAX_RUN_LOG_BYTES`].
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `entry`, trimming the file if it has grown past the cap.
+    /// Failures are logged rather than returned; execution doesn't depend
+    /// on the log.
+    pub fn append(&self, entry: &RunLogEntry) {
+        if let Err(e) = self.try_append(entry) {
+            warn!(
+                "[run-log] Failed to append to {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+
+    fn try_append(&self, entry: &RunLogEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        let len = file.metadata()?.len();
+        drop(file);
+
+        if len > self.max_bytes {
+            self.trim()?;
+        }
+        Ok(())
+    }
+
+    /// Rewrite the log keeping the newest lines that fit in half the cap.
+    fn trim(&self) -> std::io::Result<()> {
+        let content = std::fs::read_to_string(&self.path)?;
+        let budget = (self.max_bytes / 2) as usize;
+        let mut kept = 0;
+        let mut start = content.len();
+        for line in content.lines().rev() {
+            let size = line.len() + 1;
+            if kept + size > budget {
+                break;
+            }
+            kept += size;
+            start -= size;
+        }
+        let tmp = self.path.with_extension("jsonl.tmp");
+        std::fs::write(&tmp, &content[start..])?;
+        std::fs::rename(&tmp, &self.path)
+    }
+}
+
+/// The last `limit` entries of the log for the notebook at `notebook_path`,
+/// oldest first. A missing log reads as empty; unparseable lines are skipped.
+pub fn read_recent(notebook_path: &Path, limit: usize) -> Vec<RunLogEntry> {
+    let Ok(content) = std::fs::read_to_string(run_log_path(notebook_path)) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<RunLogEntry> = content
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .take(limit)
+        .collect();
+    entries.reverse();
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(cell_id: &str, status: RunStatus) -> RunLogEntry {
+        RunLogEntry {
+            cell_id: cell_id.to_string(),
+            source_hash: source_hash(cell_id),
+            started_at: "2026-01-01T00:00:00.000Z".to_string(),
+            duration_ms: 5,
+            status,
+            error: (status == RunStatus::Error).then(|| "ValueError: bad".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_run_log_path_sits_next_to_notebook() {
+        assert_eq!(
+            run_log_path(Path::new("/work/analysis.ipynb")),
+            PathBuf::from("/work/analysis.ipynb.runlog.jsonl")
+        );
+    }
+
+    #[test]
+    fn test_source_hash_tracks_code() {
+        assert_eq!(source_hash("x = 1"), source_hash("x = 1"));
+        assert_ne!(source_hash("x = 1"), source_hash("x = 2"));
+        assert_eq!(source_hash("x = 1").len(), 16);
+    }
+
+    #[test]
+    fn test_read_recent_returns_newest_in_order() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let notebook = tmp.path().join("nb.ipynb");
+        assert!(read_recent(&notebook, 10).is_empty());
+
+        let log = RunLog::for_notebook(&notebook);
+        for cell in ["a", "b", "c"] {
+            log.append(&entry(cell, RunStatus::Ok));
+        }
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(log.path())
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+
+        let cells = |entries: Vec<RunLogEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.cell_id).collect()
+        };
+        assert_eq!(cells(read_recent(&notebook, 10)), vec!["a", "b", "c"]);
+        assert_eq!(cells(read_recent(&notebook, 2)), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_append_trims_oldest_entries_past_cap() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let notebook = tmp.path().join("nb.ipynb");
+        let line_len = serde_json::to_string(&entry("cell-00", RunStatus::Ok))
+            .unwrap()
+            .len() as u64
+            + 1;
+        let log = RunLog::for_notebook(&notebook).with_max_bytes(line_len * 10);
+
+        for i in 0..25 {
+            log.append(&entry(&format!("cell-{i:02}"), RunStatus::Ok));
+            assert!(std::fs::metadata(log.path()).unwrap().len() <= line_len * 10);
+        }
+
+        let entries = read_recent(&notebook, 100);
+        assert!(entries.len() >= 5 && entries.len() <= 10);
+        assert_eq!(entries.last().unwrap().cell_id, "cell-24");
+        // What survives is a contiguous run of the newest entries
+        let first: usize = entries[0].cell_id[5..].parse().unwrap();
+        assert_eq!(first + entries.len(), 25);
+    }
+}
@@ -14,17 +14,47 @@
 //!   default_python_env: "uv"
 //!   uv/                           ← nested Map
 //!     default_packages: List[…]   ← List of Str
+//!     default_index_url: ""       ← Str, empty uses PyPI
 //!   conda/                        ← nested Map
 //!     default_packages: List[…]   ← List of Str
+//!     channels: List[…]           ← List of Str, highest priority first
+//!     strict_channel_priority: true ← Boolean
+//!   ansi_spans: false             ← Boolean
+//!   keep_kernel_on_close: false   ← Boolean
+//!   keep_kernel_on_exit: false    ← Boolean
+//!   run_log: false                ← Boolean
+//!   block_invalid_save: false     ← Boolean
+//!   hide_install_suggestions: false ← Boolean
+//!   lsp/                          ← nested Map
+//!     enabled: false              ← Boolean
+//!   kernel_startup/               ← nested Map
+//!     timeout_secs: 60            ← Uint
+//!     retry: true                 ← Boolean
+//!   tools/                        ← nested Map
+//!     uv_path: ""                 ← Str, empty to search
+//!     ruff_path: ""               ← Str, empty to search
+//!     deno_path: ""               ← Str, empty to search
+//!     prefer_system: true         ← Boolean
+//!   keybindings/                  ← nested Map of menu id → accelerator
+//!     save: "CmdOrCtrl+Shift+S"   ← Str
+//!   outputs/                      ← nested Map
+//!     mime_priority: List[…]      ← List of Str
+//!     allow_html: true            ← Boolean
+//!     allow_javascript: false     ← Boolean
+//!     sidecar_mime: List[…]       ← List of Str
+//!     large_output_bytes: 1048576 ← Uint
+//!   new_notebook_template/        ← nested Map of runtime → cells
+//!     python: "[{…}]"             ← Str, JSON-encoded list of TemplateCell
 //! ```
 
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use automerge::sync;
 use automerge::sync::SyncDoc;
 use automerge::transaction::Transactable;
 use automerge::{AutoCommit, AutomergeError, ObjId, ObjType, ReadDoc};
-use log::info;
+use log::{info, warn};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
@@ -120,20 +150,239 @@ impl std::str::FromStr for PythonEnvType {
     }
 }
 
-/// Default packages for uv environments.
+/// Default packages and package index for uv environments.
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema, TS)]
 #[ts(export)]
 pub struct UvDefaults {
     pub default_packages: Vec<String>,
+    /// Package index to install from instead of PyPI; empty uses PyPI
+    #[serde(default)]
+    pub default_index_url: String,
 }
 
-/// Default packages for conda environments.
-#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema, TS)]
+impl UvDefaults {
+    /// The configured package index, if one is set.
+    pub fn index_url(&self) -> Option<&str> {
+        Some(self.default_index_url.trim()).filter(|url| !url.is_empty())
+    }
+}
+
+/// Default packages and channels for conda environments.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema, TS)]
 #[ts(export)]
 pub struct CondaDefaults {
     pub default_packages: Vec<String>,
+    /// Channels for notebooks that don't list their own, highest priority
+    /// first; empty uses conda-forge
+    #[serde(default)]
+    pub channels: Vec<String>,
+    /// Take each package only from the first channel that has it
+    #[serde(default = "default_true")]
+    pub strict_channel_priority: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for CondaDefaults {
+    fn default() -> Self {
+        Self {
+            default_packages: Vec::new(),
+            channels: Vec::new(),
+            strict_channel_priority: true,
+        }
+    }
+}
+
+impl CondaDefaults {
+    /// Channels for a notebook that doesn't list its own: the configured
+    /// ones, else conda-forge.
+    pub fn default_channels(&self) -> Vec<String> {
+        if self.channels.is_empty() {
+            vec!["conda-forge".to_string()]
+        } else {
+            self.channels.clone()
+        }
+    }
+
+    /// The solver channel priority these settings ask for.
+    pub fn channel_priority(&self) -> kernel_env::ChannelPriority {
+        if self.strict_channel_priority {
+            kernel_env::ChannelPriority::Strict
+        } else {
+            kernel_env::ChannelPriority::Disabled
+        }
+    }
+}
+
+/// Language server settings for static completions and hover.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema, TS)]
+#[ts(export)]
+pub struct LspSettings {
+    /// Merge completions from a Python language server with kernel completions
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// Kernel startup settings.
+///
+/// `timeout_secs` is clamped to `MIN_TIMEOUT_SECS..=MAX_TIMEOUT_SECS` when read.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema, TS)]
+#[ts(export)]
+pub struct KernelStartupSettings {
+    /// Seconds to wait for a launched kernel to answer before giving up
+    #[serde(default = "default_startup_timeout_secs")]
+    pub timeout_secs: u32,
+    /// Launch once more if the first attempt times out or the kernel exits
+    /// during startup
+    #[serde(default = "default_true")]
+    pub retry: bool,
+}
+
+fn default_startup_timeout_secs() -> u32 {
+    60
+}
+
+impl KernelStartupSettings {
+    /// Shortest startup timeout honored; a kernel needs a moment to bind.
+    pub const MIN_TIMEOUT_SECS: u32 = 5;
+    /// Longest startup timeout honored. Clients size their launch request
+    /// timeouts from this.
+    pub const MAX_TIMEOUT_SECS: u32 = 300;
+
+    /// A stored `timeout_secs`, clamped to the honored range.
+    pub fn clamp_timeout_secs(secs: u64) -> u32 {
+        secs.clamp(
+            u64::from(Self::MIN_TIMEOUT_SECS),
+            u64::from(Self::MAX_TIMEOUT_SECS),
+        ) as u32
+    }
+}
+
+impl Default for KernelStartupSettings {
+    fn default() -> Self {
+        Self {
+            timeout_secs: default_startup_timeout_secs(),
+            retry: true,
+        }
+    }
+}
+
+/// Where the `uv`, `ruff` and `deno` binaries come from.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema, TS)]
+#[ts(export)]
+pub struct ToolSettings {
+    /// Path to a uv binary to use; empty to search PATH or bootstrap
+    #[serde(default)]
+    pub uv_path: String,
+    /// Path to a ruff binary to use; empty to search PATH or bootstrap
+    #[serde(default)]
+    pub ruff_path: String,
+    /// Path to a deno binary to use; empty to search PATH or bootstrap
+    #[serde(default)]
+    pub deno_path: String,
+    /// Use binaries found on PATH before bootstrapping from conda-forge
+    #[serde(default = "default_true")]
+    pub prefer_system: bool,
+}
+
+impl Default for ToolSettings {
+    fn default() -> Self {
+        Self {
+            uv_path: String::new(),
+            ruff_path: String::new(),
+            deno_path: String::new(),
+            prefer_system: true,
+        }
+    }
 }
 
+impl ToolSettings {
+    /// The preferences `kernel_launch::tools` resolves binaries with.
+    pub fn preferences(&self) -> kernel_launch::tools::ToolPreferences {
+        let path = |path: &str| {
+            let path = path.trim();
+            (!path.is_empty()).then(|| std::path::PathBuf::from(path))
+        };
+        kernel_launch::tools::ToolPreferences {
+            uv_path: path(&self.uv_path),
+            ruff_path: path(&self.ruff_path),
+            deno_path: path(&self.deno_path),
+            prefer_system: self.prefer_system,
+        }
+    }
+}
+
+/// Output rendering settings.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema, TS)]
+#[ts(export)]
+pub struct OutputSettings {
+    /// MIME types in order of preference when choosing an output's primary
+    /// representation (a trailing `*` matches by prefix); empty uses the
+    /// built-in order
+    #[serde(default)]
+    pub mime_priority: Vec<String>,
+    /// Render HTML outputs of untrusted notebooks, with scripts stripped
+    /// unless `allow_javascript` is also set. Trusted notebooks always
+    /// render HTML.
+    #[serde(default = "default_true")]
+    pub allow_html: bool,
+    /// Keep scripts and JavaScript outputs of untrusted notebooks. Trusted
+    /// notebooks always run them.
+    #[serde(default)]
+    pub allow_javascript: bool,
+    /// MIME types (a trailing `*` matches by prefix) whose outputs open in
+    /// the sidecar viewer, leaving a placeholder in the notebook
+    #[serde(default)]
+    pub sidecar_mime: Vec<String>,
+    /// Outputs at least this many bytes are held back behind a placeholder
+    /// and rendered in chunks when shown; 0 renders everything immediately
+    #[serde(default = "default_large_output_bytes")]
+    pub large_output_bytes: u32,
+}
+
+fn default_large_output_bytes() -> u32 {
+    1024 * 1024
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        Self {
+            mime_priority: Vec::new(),
+            allow_html: true,
+            allow_javascript: false,
+            sidecar_mime: Vec::new(),
+            large_output_bytes: default_large_output_bytes(),
+        }
+    }
+}
+
+/// The type of a cell in a new-notebook template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export)]
+pub enum TemplateCellType {
+    Code,
+    Markdown,
+    Raw,
+}
+
+/// A starting cell in a new-notebook template.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema, TS)]
+#[ts(export)]
+pub struct TemplateCell {
+    pub cell_type: TemplateCellType,
+    #[serde(default)]
+    pub source: String,
+}
+
+/// Dotted keys of the explicit tool binary paths.
+const TOOL_PATH_KEYS: [&str; 3] = ["tools.uv_path", "tools.ruff_path", "tools.deno_path"];
+
+/// Settings key (and nested map) holding new-notebook templates.
+const TEMPLATE_MAP_KEY: &str = "new_notebook_template";
+
 /// Snapshot of all synced settings.
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema, TS)]
 #[ts(export)]
@@ -157,6 +406,60 @@ pub struct SyncedSettings {
     /// Conda environment defaults
     #[serde(default)]
     pub conda: CondaDefaults,
+
+    /// Parse ANSI escape codes in stream and error outputs into styled span
+    /// runs stored alongside the raw text in output manifests
+    #[serde(default)]
+    pub ansi_spans: bool,
+
+    /// Keep the daemon kernel running after the last window on a notebook
+    /// closes, so reopening the notebook picks it back up
+    #[serde(default)]
+    pub keep_kernel_on_close: bool,
+
+    /// When `keep_kernel_on_close` is on, also keep kernels running when the
+    /// app quits (otherwise quitting shuts them down)
+    #[serde(default)]
+    pub keep_kernel_on_exit: bool,
+
+    /// Append each finished cell execution to a `.ipynb.runlog.jsonl` file
+    /// next to the notebook
+    #[serde(default)]
+    pub run_log: bool,
+
+    /// Refuse to save a notebook that fails nbformat validation
+    #[serde(default)]
+    pub block_invalid_save: bool,
+
+    /// Don't offer to install the package behind a failed import
+    #[serde(default)]
+    pub hide_install_suggestions: bool,
+
+    /// Language server proxy settings
+    #[serde(default)]
+    pub lsp: LspSettings,
+
+    /// Kernel startup timeout and retry
+    #[serde(default)]
+    pub kernel_startup: KernelStartupSettings,
+
+    /// Where uv, ruff and deno come from
+    #[serde(default)]
+    pub tools: ToolSettings,
+
+    /// Menu accelerator overrides keyed by menu item id
+    /// (e.g. `"save": "CmdOrCtrl+Shift+S"`); an empty string removes the shortcut
+    #[serde(default)]
+    pub keybindings: BTreeMap<String, String>,
+
+    /// Output rendering settings
+    #[serde(default)]
+    pub outputs: OutputSettings,
+
+    /// Starting cells for new notebooks keyed by runtime (e.g. `"python"`,
+    /// `"deno"`); a runtime without a template starts with one empty code cell
+    #[serde(default)]
+    pub new_notebook_template: BTreeMap<String, Vec<TemplateCell>>,
 }
 
 /// Generate a JSON Schema string for the settings file.
@@ -205,13 +508,90 @@ impl SettingsDoc {
         // Nested uv map with empty package list
         if let Ok(uv_id) = doc.put_object(automerge::ROOT, "uv", ObjType::Map) {
             let _ = doc.put_object(&uv_id, "default_packages", ObjType::List);
+            let _ = doc.put(&uv_id, "default_index_url", "");
         }
 
-        // Nested conda map with empty package list
+        // Nested conda map with empty package and channel lists
         if let Ok(conda_id) = doc.put_object(automerge::ROOT, "conda", ObjType::Map) {
             let _ = doc.put_object(&conda_id, "default_packages", ObjType::List);
+            let _ = doc.put_object(&conda_id, "channels", ObjType::List);
+            let _ = doc.put(
+                &conda_id,
+                "strict_channel_priority",
+                defaults.conda.strict_channel_priority,
+            );
+        }
+
+        let _ = doc.put(automerge::ROOT, "ansi_spans", defaults.ansi_spans);
+        let _ = doc.put(
+            automerge::ROOT,
+            "keep_kernel_on_close",
+            defaults.keep_kernel_on_close,
+        );
+        let _ = doc.put(
+            automerge::ROOT,
+            "keep_kernel_on_exit",
+            defaults.keep_kernel_on_exit,
+        );
+        let _ = doc.put(automerge::ROOT, "run_log", defaults.run_log);
+        let _ = doc.put(
+            automerge::ROOT,
+            "block_invalid_save",
+            defaults.block_invalid_save,
+        );
+        let _ = doc.put(
+            automerge::ROOT,
+            "hide_install_suggestions",
+            defaults.hide_install_suggestions,
+        );
+
+        // Nested lsp map
+        if let Ok(lsp_id) = doc.put_object(automerge::ROOT, "lsp", ObjType::Map) {
+            let _ = doc.put(&lsp_id, "enabled", defaults.lsp.enabled);
+        }
+
+        // Nested kernel startup map
+        if let Ok(startup_id) = doc.put_object(automerge::ROOT, "kernel_startup", ObjType::Map) {
+            let _ = doc.put(
+                &startup_id,
+                "timeout_secs",
+                u64::from(defaults.kernel_startup.timeout_secs),
+            );
+            let _ = doc.put(&startup_id, "retry", defaults.kernel_startup.retry);
         }
 
+        // Nested tools map: no explicit paths, PATH before bootstrapping
+        if let Ok(tools_id) = doc.put_object(automerge::ROOT, "tools", ObjType::Map) {
+            for key in ["uv_path", "ruff_path", "deno_path"] {
+                let _ = doc.put(&tools_id, key, "");
+            }
+            let _ = doc.put(&tools_id, "prefer_system", defaults.tools.prefer_system);
+        }
+
+        // Nested keybindings map, empty until the user overrides something
+        let _ = doc.put_object(automerge::ROOT, "keybindings", ObjType::Map);
+
+        // Nested outputs map with an empty priority list (built-in order)
+        // and nothing routed to the sidecar
+        if let Ok(outputs_id) = doc.put_object(automerge::ROOT, "outputs", ObjType::Map) {
+            let _ = doc.put_object(&outputs_id, "mime_priority", ObjType::List);
+            let _ = doc.put(&outputs_id, "allow_html", defaults.outputs.allow_html);
+            let _ = doc.put(
+                &outputs_id,
+                "allow_javascript",
+                defaults.outputs.allow_javascript,
+            );
+            let _ = doc.put_object(&outputs_id, "sidecar_mime", ObjType::List);
+            let _ = doc.put(
+                &outputs_id,
+                "large_output_bytes",
+                u64::from(defaults.outputs.large_output_bytes),
+            );
+        }
+
+        // Nested template map, empty until the user configures one
+        let _ = doc.put_object(automerge::ROOT, "new_notebook_template", ObjType::Map);
+
         Self { doc }
     }
 
@@ -282,6 +662,84 @@ impl SettingsDoc {
         if let Some(env) = json.get("default_python_env").and_then(|v| v.as_str()) {
             settings.put("default_python_env", env);
         }
+        for key in [
+            "ansi_spans",
+            "keep_kernel_on_close",
+            "keep_kernel_on_exit",
+            "run_log",
+            "block_invalid_save",
+            "hide_install_suggestions",
+        ] {
+            if let Some(value) = json.get(key).and_then(|v| v.as_bool()) {
+                settings.put_bool(key, value);
+            }
+        }
+        if let Some(enabled) = json.pointer("/lsp/enabled").and_then(|v| v.as_bool()) {
+            settings.put_bool("lsp.enabled", enabled);
+        }
+        if let Some(retry) = json
+            .pointer("/kernel_startup/retry")
+            .and_then(|v| v.as_bool())
+        {
+            settings.put_bool("kernel_startup.retry", retry);
+        }
+        if let Some(timeout) = json
+            .pointer("/kernel_startup/timeout_secs")
+            .and_then(|v| v.as_u64())
+        {
+            settings.put_u64("kernel_startup.timeout_secs", timeout);
+        }
+        for key in TOOL_PATH_KEYS {
+            let pointer = format!("/{}", key.replace('.', "/"));
+            if let Some(path) = json.pointer(&pointer).and_then(|v| v.as_str()) {
+                settings.put(key, path);
+            }
+        }
+        if let Some(prefer) = json
+            .pointer("/tools/prefer_system")
+            .and_then(|v| v.as_bool())
+        {
+            settings.put_bool("tools.prefer_system", prefer);
+        }
+        if let Some(strict) = json
+            .pointer("/conda/strict_channel_priority")
+            .and_then(|v| v.as_bool())
+        {
+            settings.put_bool("conda.strict_channel_priority", strict);
+        }
+        if let Some(channels) = json.pointer("/conda/channels") {
+            settings.put_list("conda.channels", &string_list_from_json(channels));
+        }
+        if let Some(url) = json
+            .pointer("/uv/default_index_url")
+            .and_then(|v| v.as_str())
+        {
+            settings.put("uv.default_index_url", url);
+        }
+        if let Some(keybindings) = json.get("keybindings") {
+            settings.put_string_map("keybindings", &string_map_from_json(keybindings));
+        }
+        if let Some(priority) = json.pointer("/outputs/mime_priority") {
+            settings.put_list("outputs.mime_priority", &string_list_from_json(priority));
+        }
+        if let Some(sidecar_mime) = json.pointer("/outputs/sidecar_mime") {
+            settings.put_list("outputs.sidecar_mime", &string_list_from_json(sidecar_mime));
+        }
+        if let Some(bytes) = json
+            .pointer("/outputs/large_output_bytes")
+            .and_then(|v| v.as_u64())
+        {
+            settings.put_u64("outputs.large_output_bytes", bytes);
+        }
+        for key in ["outputs.allow_html", "outputs.allow_javascript"] {
+            let pointer = format!("/{}", key.replace('.', "/"));
+            if let Some(value) = json.pointer(&pointer).and_then(|v| v.as_bool()) {
+                settings.put_bool(key, value);
+            }
+        }
+        if let Some(templates) = json.get("new_notebook_template") {
+            settings.put_templates(&templates_from_json(templates));
+        }
 
         let uv_packages = Self::extract_packages_from_json(json, "uv");
         if !uv_packages.is_empty() {
@@ -394,30 +852,46 @@ impl SettingsDoc {
         }
     }
 
-    /// Get a boolean setting value from the root.
+    /// Get a boolean setting value, supporting dotted paths for nested maps.
     pub fn get_bool(&self, key: &str) -> Option<bool> {
-        self.doc
-            .get(automerge::ROOT, key)
-            .ok()
-            .flatten()
-            .and_then(|(value, _)| match value {
-                automerge::Value::Scalar(s) => match s.as_ref() {
-                    automerge::ScalarValue::Boolean(b) => Some(*b),
-                    // Also support string "true"/"false" for migration
-                    automerge::ScalarValue::Str(s) => match s.as_str() {
-                        "true" => Some(true),
-                        "false" => Some(false),
-                        _ => None,
-                    },
-                    _ => None,
-                },
-                _ => None,
-            })
+        if let Some((map_key, sub_key)) = key.split_once('.') {
+            let map_id = self.get_map_id(map_key)?;
+            read_scalar_bool(&self.doc, map_id, sub_key)
+        } else {
+            read_scalar_bool(&self.doc, automerge::ROOT, key)
+        }
     }
 
-    /// Set a boolean setting value at the root.
+    /// Set a boolean setting value, supporting dotted paths for nested maps.
     pub fn put_bool(&mut self, key: &str, value: bool) {
-        let _ = self.doc.put(automerge::ROOT, key, value);
+        if let Some((map_key, sub_key)) = key.split_once('.') {
+            let map_id = self.ensure_map(map_key);
+            let _ = self.doc.put(&map_id, sub_key, value);
+        } else {
+            let _ = self.doc.put(automerge::ROOT, key, value);
+        }
+    }
+
+    /// Get an unsigned integer setting value, supporting dotted paths for
+    /// nested maps.
+    pub fn get_u64(&self, key: &str) -> Option<u64> {
+        if let Some((map_key, sub_key)) = key.split_once('.') {
+            let map_id = self.get_map_id(map_key)?;
+            read_scalar_u64(&self.doc, map_id, sub_key)
+        } else {
+            read_scalar_u64(&self.doc, automerge::ROOT, key)
+        }
+    }
+
+    /// Set an unsigned integer setting value, supporting dotted paths for
+    /// nested maps.
+    pub fn put_u64(&mut self, key: &str, value: u64) {
+        if let Some((map_key, sub_key)) = key.split_once('.') {
+            let map_id = self.ensure_map(map_key);
+            let _ = self.doc.put(&map_id, sub_key, value);
+        } else {
+            let _ = self.doc.put(automerge::ROOT, key, value);
+        }
     }
 
     /// Set a scalar setting value, supporting dotted paths for nested maps.
@@ -485,19 +959,69 @@ impl SettingsDoc {
         }
     }
 
+    // ── Map accessors ────────────────────────────────────────────────
+
+    /// Read a nested map of string values at ROOT (e.g. `"keybindings"`).
+    pub fn get_string_map(&self, map_key: &str) -> BTreeMap<String, String> {
+        read_string_map(&self.doc, map_key)
+    }
+
+    /// Replace the contents of a nested map of string values at ROOT.
+    ///
+    /// Only keys that were removed or changed are touched, so concurrent
+    /// edits to other entries merge cleanly.
+    pub fn put_string_map(&mut self, map_key: &str, values: &BTreeMap<String, String>) {
+        let current = self.get_string_map(map_key);
+        let map_id = self.ensure_map(map_key);
+        for key in current.keys().filter(|k| !values.contains_key(*k)) {
+            let _ = self.doc.delete(&map_id, key.as_str());
+        }
+        for (key, value) in values {
+            if current.get(key) != Some(value) {
+                let _ = self.doc.put(&map_id, key.as_str(), value.as_str());
+            }
+        }
+    }
+
+    /// Read the new-notebook templates, keyed by runtime.
+    pub fn get_templates(&self) -> BTreeMap<String, Vec<TemplateCell>> {
+        read_templates(&self.doc)
+    }
+
+    /// Replace the new-notebook templates. Each runtime's cells are stored
+    /// as one JSON string, so editing one runtime's template leaves the
+    /// others untouched.
+    pub fn put_templates(&mut self, templates: &BTreeMap<String, Vec<TemplateCell>>) {
+        let encoded = templates
+            .iter()
+            .filter_map(|(runtime, cells)| {
+                Some((runtime.clone(), serde_json::to_string(cells).ok()?))
+            })
+            .collect();
+        self.put_string_map(TEMPLATE_MAP_KEY, &encoded);
+    }
+
     ///// Set a value from a `serde_json::Value` — dispatches to `put` for strings,
-    /// `put_list` for arrays, or `put_bool` for booleans. Used by Tauri commands.
+    /// `put_list` for arrays, `put_bool` for booleans, `put_u64` for
+    /// non-negative integers, or `put_string_map` for objects (`put_templates`
+    /// for `new_notebook_template`). Used by Tauri commands.
     pub fn put_value(&mut self, key: &str, value: &serde_json::Value) {
+        if key == TEMPLATE_MAP_KEY {
+            self.put_templates(&templates_from_json(value));
+            return;
+        }
         match value {
             serde_json::Value::String(s) => self.put(key, s),
-            serde_json::Value::Array(arr) => {
-                let items: Vec<String> = arr
-                    .iter()
-                    .filter_map(|v| v.as_str().map(String::from))
-                    .collect();
-                self.put_list(key, &items);
-            }
+            serde_json::Value::Array(_) => self.put_list(key, &string_list_from_json(value)),
             serde_json::Value::Bool(b) => self.put_bool(key, *b),
+            serde_json::Value::Number(n) => {
+                if let Some(n) = n.as_u64() {
+                    self.put_u64(key, n);
+                }
+            }
+            serde_json::Value::Object(_) => {
+                self.put_string_map(key, &string_map_from_json(value));
+            }
             _ => {}
         }
     }
@@ -574,10 +1098,65 @@ impl SettingsDoc {
                 .unwrap_or_default(),
             uv: UvDefaults {
                 default_packages: uv_packages,
+                default_index_url: self.get("uv.default_index_url").unwrap_or_default(),
             },
             conda: CondaDefaults {
                 default_packages: conda_packages,
+                channels: self.get_list("conda.channels"),
+                strict_channel_priority: self
+                    .get_bool("conda.strict_channel_priority")
+                    .unwrap_or(defaults.conda.strict_channel_priority),
+            },
+            ansi_spans: self.get_bool("ansi_spans").unwrap_or(defaults.ansi_spans),
+            keep_kernel_on_close: self
+                .get_bool("keep_kernel_on_close")
+                .unwrap_or(defaults.keep_kernel_on_close),
+            keep_kernel_on_exit: self
+                .get_bool("keep_kernel_on_exit")
+                .unwrap_or(defaults.keep_kernel_on_exit),
+            run_log: self.get_bool("run_log").unwrap_or(defaults.run_log),
+            block_invalid_save: self
+                .get_bool("block_invalid_save")
+                .unwrap_or(defaults.block_invalid_save),
+            hide_install_suggestions: self
+                .get_bool("hide_install_suggestions")
+                .unwrap_or(defaults.hide_install_suggestions),
+            lsp: LspSettings {
+                enabled: self.get_bool("lsp.enabled").unwrap_or(defaults.lsp.enabled),
+            },
+            kernel_startup: KernelStartupSettings {
+                timeout_secs: self
+                    .get_u64("kernel_startup.timeout_secs")
+                    .map(KernelStartupSettings::clamp_timeout_secs)
+                    .unwrap_or(defaults.kernel_startup.timeout_secs),
+                retry: self
+                    .get_bool("kernel_startup.retry")
+                    .unwrap_or(defaults.kernel_startup.retry),
+            },
+            tools: ToolSettings {
+                uv_path: self.get("tools.uv_path").unwrap_or_default(),
+                ruff_path: self.get("tools.ruff_path").unwrap_or_default(),
+                deno_path: self.get("tools.deno_path").unwrap_or_default(),
+                prefer_system: self
+                    .get_bool("tools.prefer_system")
+                    .unwrap_or(defaults.tools.prefer_system),
+            },
+            keybindings: self.get_string_map("keybindings"),
+            outputs: OutputSettings {
+                mime_priority: self.get_list("outputs.mime_priority"),
+                allow_html: self
+                    .get_bool("outputs.allow_html")
+                    .unwrap_or(defaults.outputs.allow_html),
+                allow_javascript: self
+                    .get_bool("outputs.allow_javascript")
+                    .unwrap_or(defaults.outputs.allow_javascript),
+                sidecar_mime: self.get_list("outputs.sidecar_mime"),
+                large_output_bytes: self
+                    .get_u64("outputs.large_output_bytes")
+                    .and_then(|n| u32::try_from(n).ok())
+                    .unwrap_or(defaults.outputs.large_output_bytes),
             },
+            new_notebook_template: self.get_templates(),
         }
     }
 
@@ -617,6 +1196,119 @@ impl SettingsDoc {
             }
         }
 
+        // Boolean fields (dotted keys address nested maps)
+        for key in &[
+            "ansi_spans",
+            "keep_kernel_on_close",
+            "keep_kernel_on_exit",
+            "run_log",
+            "block_invalid_save",
+            "hide_install_suggestions",
+            "lsp.enabled",
+            "conda.strict_channel_priority",
+            "kernel_startup.retry",
+            "tools.prefer_system",
+            "outputs.allow_html",
+            "outputs.allow_javascript",
+        ] {
+            let pointer = format!("/{}", key.replace('.', "/"));
+            if let Some(value) = json.pointer(&pointer).and_then(|v| v.as_bool()) {
+                if self.get_bool(key) != Some(value) {
+                    info!("[settings] apply_json_changes: {key} changed -> {value}");
+                    self.put_bool(key, value);
+                    changed = true;
+                }
+            }
+        }
+
+        // Kernel startup timeout
+        if let Some(timeout) = json
+            .pointer("/kernel_startup/timeout_secs")
+            .and_then(|v| v.as_u64())
+        {
+            if self.get_u64("kernel_startup.timeout_secs") != Some(timeout) {
+                info!("[settings] apply_json_changes: kernel_startup.timeout_secs changed -> {timeout}");
+                self.put_u64("kernel_startup.timeout_secs", timeout);
+                changed = true;
+            }
+        }
+
+        // Explicit tool paths and the uv package index
+        for key in TOOL_PATH_KEYS.iter().chain(&["uv.default_index_url"]) {
+            let pointer = format!("/{}", key.replace('.', "/"));
+            if let Some(path) = json.pointer(&pointer).and_then(|v| v.as_str()) {
+                if self.get(key).as_deref() != Some(path) {
+                    info!("[settings] apply_json_changes: {key} changed -> {path:?}");
+                    self.put(key, path);
+                    changed = true;
+                }
+            }
+        }
+
+        // Keybindings
+        if let Some(keybindings) = json.get("keybindings") {
+            let keybindings = string_map_from_json(keybindings);
+            if self.get_string_map("keybindings") != keybindings {
+                info!("[settings] apply_json_changes: keybindings changed");
+                self.put_string_map("keybindings", &keybindings);
+                changed = true;
+            }
+        }
+
+        // Output MIME priority
+        if let Some(priority) = json.pointer("/outputs/mime_priority") {
+            let priority = string_list_from_json(priority);
+            if self.get_list("outputs.mime_priority") != priority {
+                info!("[settings] apply_json_changes: outputs.mime_priority changed");
+                self.put_list("outputs.mime_priority", &priority);
+                changed = true;
+            }
+        }
+
+        // MIME types routed to the sidecar viewer
+        if let Some(sidecar_mime) = json.pointer("/outputs/sidecar_mime") {
+            let sidecar_mime = string_list_from_json(sidecar_mime);
+            if self.get_list("outputs.sidecar_mime") != sidecar_mime {
+                info!("[settings] apply_json_changes: outputs.sidecar_mime changed");
+                self.put_list("outputs.sidecar_mime", &sidecar_mime);
+                changed = true;
+            }
+        }
+
+        // Size above which outputs are deferred
+        if let Some(bytes) = json
+            .pointer("/outputs/large_output_bytes")
+            .and_then(|v| v.as_u64())
+        {
+            if self.get_u64("outputs.large_output_bytes") != Some(bytes) {
+                info!(
+                    "[settings] apply_json_changes: outputs.large_output_bytes changed -> {bytes}"
+                );
+                self.put_u64("outputs.large_output_bytes", bytes);
+                changed = true;
+            }
+        }
+
+        // Conda channels (ordered)
+        if let Some(channels) = json.pointer("/conda/channels") {
+            let channels = string_list_from_json(channels);
+            if self.get_list("conda.channels") != channels {
+                info!("[settings] apply_json_changes: conda.channels changed");
+                self.put_list("conda.channels", &channels);
+                changed = true;
+            }
+        }
+
+        // New-notebook templates
+        if let Some(templates) = json.get("new_notebook_template") {
+            let templates = templates_from_json(templates);
+            if self.get_templates() != templates {
+                info!("[settings] apply_json_changes: new_notebook_template changed");
+                self.put_templates(&templates);
+                changed = true;
+            }
+        }
+
         // UV packages
         if json.get("uv").is_some() {
             let uv_packages = Self::extract_packages_from_json(json, "uv");
@@ -637,6 +1329,73 @@ impl SettingsDoc {
 
         changed
     }
+
+    /// Serialize every setting for moving to another machine, leaving out
+    /// the explicit tool paths since those only make sense on this one.
+    pub fn export_json(&self) -> String {
+        let mut json = serde_json::to_value(self.get_all()).unwrap_or_default();
+        strip_tool_paths(&mut json);
+        serde_json::to_string_pretty(&json).unwrap_or_default()
+    }
+
+    /// Apply settings exported by [`export_json`](Self::export_json).
+    ///
+    /// By default the imported keys are merged over the current settings and
+    /// anything the import doesn't mention is kept; with `replace`, settings
+    /// missing from the import go back to their defaults. Either way this
+    /// machine's tool paths are kept. Returns whether anything changed, or an
+    /// error (leaving the settings untouched) if the import isn't valid
+    /// settings JSON.
+    pub fn import_json(&mut self, json: &str, replace: bool) -> Result<bool, String> {
+        let imported: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| format!("Invalid JSON: {e}"))?;
+        if !imported.is_object() {
+            return Err("Settings must be a JSON object".to_string());
+        }
+
+        let merged = if replace {
+            imported
+        } else {
+            let mut current = serde_json::to_value(self.get_all()).unwrap_or_default();
+            merge_json(&mut current, imported);
+            current
+        };
+        let settings: SyncedSettings =
+            serde_json::from_value(merged).map_err(|e| format!("Invalid settings: {e}"))?;
+
+        let mut json = serde_json::to_value(settings).unwrap_or_default();
+        strip_tool_paths(&mut json);
+        Ok(self.apply_json_changes(&json))
+    }
+}
+
+/// Remove the explicit tool paths from settings JSON.
+fn strip_tool_paths(json: &mut serde_json::Value) {
+    if let Some(tools) = json.get_mut("tools").and_then(|v| v.as_object_mut()) {
+        for key in TOOL_PATH_KEYS {
+            if let Some(name) = key.strip_prefix("tools.") {
+                tools.remove(name);
+            }
+        }
+    }
+}
+
+/// Recursively merge `overlay` into `base`: objects merge key by key, any
+/// other value in `overlay` replaces the one in `base`.
+fn merge_json(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
 }
 
 impl Default for SettingsDoc {
@@ -661,6 +1420,166 @@ fn read_scalar_str<O: AsRef<ObjId>>(doc: &AutoCommit, obj: O, key: &str) -> Opti
         })
 }
 
+/// Read a boolean value from any Automerge object.
+///
+/// Also accepts the strings `"true"`/`"false"` for values written before the
+/// field was stored as a native boolean.
+pub fn read_scalar_bool<O: AsRef<ObjId>>(doc: &AutoCommit, obj: O, key: &str) -> Option<bool> {
+    doc.get(obj, key)
+        .ok()
+        .flatten()
+        .and_then(|(value, _)| match value {
+            automerge::Value::Scalar(s) => match s.as_ref() {
+                automerge::ScalarValue::Boolean(b) => Some(*b),
+                automerge::ScalarValue::Str(s) => match s.as_str() {
+                    "true" => Some(true),
+                    "false" => Some(false),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+}
+
+/// Read an unsigned integer value from any Automerge object.
+pub fn read_scalar_u64<O: AsRef<ObjId>>(doc: &AutoCommit, obj: O, key: &str) -> Option<u64> {
+    doc.get(obj, key)
+        .ok()
+        .flatten()
+        .and_then(|(value, _)| match value {
+            automerge::Value::Scalar(s) => match s.as_ref() {
+                automerge::ScalarValue::Uint(n) => Some(*n),
+                automerge::ScalarValue::Int(n) => u64::try_from(*n).ok(),
+                _ => None,
+            },
+            _ => None,
+        })
+}
+
+/// Read an unsigned integer from a nested Automerge map within a raw
+/// `AutoCommit`.
+///
+/// Used by `sync_client::get_all_from_doc` which operates on bare docs.
+pub fn read_nested_u64(doc: &AutoCommit, map_key: &str, sub_key: &str) -> Option<u64> {
+    match doc.get(automerge::ROOT, map_key).ok().flatten() {
+        Some((automerge::Value::Object(ObjType::Map), id)) => read_scalar_u64(doc, id, sub_key),
+        _ => None,
+    }
+}
+
+/// Read a string from a nested Automerge map within a raw `AutoCommit`.
+///
+/// Used by `sync_client::get_all_from_doc` which operates on bare docs.
+pub fn read_nested_str(doc: &AutoCommit, map_key: &str, sub_key: &str) -> Option<String> {
+    match doc.get(automerge::ROOT, map_key).ok().flatten() {
+        Some((automerge::Value::Object(ObjType::Map), id)) => read_scalar_str(doc, id, sub_key),
+        _ => None,
+    }
+}
+
+/// Read a boolean from a nested Automerge map within a raw `AutoCommit`.
+///
+/// Used by `sync_client::get_all_from_doc` which operates on bare docs.
+pub fn read_nested_bool(doc: &AutoCommit, map_key: &str, sub_key: &str) -> Option<bool> {
+    match doc.get(automerge::ROOT, map_key).ok().flatten() {
+        Some((automerge::Value::Object(ObjType::Map), id)) => read_scalar_bool(doc, id, sub_key),
+        _ => None,
+    }
+}
+
+/// Read a nested map of string values at ROOT within a raw `AutoCommit`.
+///
+/// Non-string entries are skipped. Used by `sync_client::get_all_from_doc`.
+pub fn read_string_map(doc: &AutoCommit, map_key: &str) -> BTreeMap<String, String> {
+    let map_id = match doc.get(automerge::ROOT, map_key).ok().flatten() {
+        Some((automerge::Value::Object(ObjType::Map), id)) => id,
+        _ => return BTreeMap::new(),
+    };
+    doc.keys(&map_id)
+        .filter_map(|key| {
+            let value = read_scalar_str(doc, &map_id, &key)?;
+            Some((key, value))
+        })
+        .collect()
+}
+
+/// Read the new-notebook templates within a raw `AutoCommit`, skipping any
+/// runtime whose cells don't parse. Used by `sync_client::get_all_from_doc`.
+pub fn read_templates(doc: &AutoCommit) -> BTreeMap<String, Vec<TemplateCell>> {
+    read_string_map(doc, TEMPLATE_MAP_KEY)
+        .into_iter()
+        .filter_map(|(runtime, json)| {
+            let cells = serde_json::from_str(&json).unwrap_or_default();
+            Some((runtime.clone(), valid_template(&runtime, &cells)?))
+        })
+        .collect()
+}
+
+/// Parse one runtime's template cells. The error names the first cell that
+/// isn't valid, such as one with an unknown `cell_type`.
+pub fn parse_template_cells(
+    runtime: &str,
+    cells: &serde_json::Value,
+) -> Result<Vec<TemplateCell>, String> {
+    let cells = cells
+        .as_array()
+        .ok_or_else(|| format!("Template for {} is not a list of cells", runtime))?;
+    cells
+        .iter()
+        .enumerate()
+        .map(|(i, cell)| {
+            serde_json::from_value(cell.clone())
+                .map_err(|e| format!("Template cell {} for {}: {}", i + 1, runtime, e))
+        })
+        .collect()
+}
+
+/// Parse a runtime's template, logging and skipping it if it's invalid.
+fn valid_template(runtime: &str, cells: &serde_json::Value) -> Option<Vec<TemplateCell>> {
+    parse_template_cells(runtime, cells)
+        .map_err(|e| warn!("[settings] Ignoring new-notebook template: {}", e))
+        .ok()
+}
+
+/// Parse a JSON object of runtime → cell list, skipping invalid templates.
+fn templates_from_json(value: &serde_json::Value) -> BTreeMap<String, Vec<TemplateCell>> {
+    value
+        .as_object()
+        .map(|obj| {
+            obj.iter()
+                .filter_map(|(runtime, cells)| {
+                    Some((runtime.clone(), valid_template(runtime, cells)?))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Collect the string-valued entries of a JSON object.
+fn string_map_from_json(value: &serde_json::Value) -> BTreeMap<String, String> {
+    value
+        .as_object()
+        .map(|obj| {
+            obj.iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Collect the string items of a JSON array, skipping anything else.
+fn string_list_from_json(value: &serde_json::Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 /// Split a comma-separated string into a list of trimmed, non-empty strings.
 pub fn split_comma_list(s: &str) -> Vec<String> {
     s.split(',')
@@ -712,6 +1631,12 @@ mod tests {
         assert_eq!(settings.default_python_env, PythonEnvType::Uv);
         assert!(settings.uv.default_packages.is_empty());
         assert!(settings.conda.default_packages.is_empty());
+        assert!(!settings.ansi_spans);
+        assert!(!settings.keep_kernel_on_close);
+        assert!(!settings.keep_kernel_on_exit);
+        assert!(!settings.run_log);
+        assert!(!settings.block_invalid_save);
+        assert!(!settings.hide_install_suggestions);
     }
 
     #[test]
@@ -923,6 +1848,79 @@ mod tests {
         assert_eq!(doc.get("theme"), Some("dark".to_string()));
     }
 
+    #[test]
+    fn test_export_import_round_trip() {
+        let mut source = SettingsDoc::new();
+        source.put("theme", "dark");
+        source.put_list("uv.default_packages", &["numpy".to_string()]);
+        source.put_bool("run_log", true);
+        source.put("tools.uv_path", "/opt/uv/bin/uv");
+        let exported = source.export_json();
+
+        let parsed: serde_json::Value = serde_json::from_str(&exported).unwrap();
+        assert!(parsed["tools"].get("uv_path").is_none());
+        assert_eq!(parsed["tools"]["prefer_system"], true);
+
+        let mut target = SettingsDoc::new();
+        target.put("tools.uv_path", "/usr/local/bin/uv");
+        assert!(target.import_json(&exported, false).unwrap());
+
+        let expected = SyncedSettings {
+            tools: ToolSettings {
+                uv_path: "/usr/local/bin/uv".to_string(),
+                ..source.get_all().tools
+            },
+            ..source.get_all()
+        };
+        assert_eq!(target.get_all(), expected);
+        // Importing the same settings again changes nothing
+        assert!(!target.import_json(&exported, false).unwrap());
+    }
+
+    #[test]
+    fn test_import_merges_unless_replacing() {
+        let import = r#"{ "theme": "light", "uv": { "default_index_url": "https://pypi.example.com/simple" } }"#;
+
+        let mut merged = SettingsDoc::new();
+        merged.put_bool("run_log", true);
+        merged.put_list("uv.default_packages", &["numpy".to_string()]);
+        merged.import_json(import, false).unwrap();
+        let settings = merged.get_all();
+        assert_eq!(settings.theme, ThemeMode::Light);
+        assert_eq!(
+            settings.uv.default_index_url,
+            "https://pypi.example.com/simple"
+        );
+        assert!(settings.run_log);
+        assert_eq!(settings.uv.default_packages, vec!["numpy".to_string()]);
+
+        // Replacing needs a complete section, as in an export
+        let import = r#"{ "theme": "light", "uv": { "default_packages": [] } }"#;
+        let mut replaced = SettingsDoc::new();
+        replaced.put_bool("run_log", true);
+        replaced.put_list("uv.default_packages", &["numpy".to_string()]);
+        replaced.put("tools.ruff_path", "/opt/ruff");
+        replaced.import_json(import, true).unwrap();
+        let settings = replaced.get_all();
+        assert_eq!(settings.theme, ThemeMode::Light);
+        assert!(!settings.run_log);
+        assert!(settings.uv.default_packages.is_empty());
+        assert_eq!(settings.tools.ruff_path, "/opt/ruff");
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_settings() {
+        let mut doc = SettingsDoc::new();
+        doc.put("theme", "dark");
+        assert!(doc.import_json("not json", false).is_err());
+        assert!(doc.import_json("[]", false).is_err());
+        assert!(doc.import_json(r#"{ "theme": "purple" }"#, false).is_err());
+        assert!(doc
+            .import_json(r#"{ "theme": "light", "run_log": "yes" }"#, true)
+            .is_err());
+        assert_eq!(doc.get("theme"), Some("dark".to_string()));
+    }
+
     #[test]
     fn test_generate_settings_schema() {
         let schema = generate_settings_schema().unwrap();
@@ -1095,6 +2093,53 @@ mod tests {
         assert_eq!(doc.get("theme"), Some("dark".to_string())); // preserved
     }
 
+    #[test]
+    fn test_apply_json_changes_bool_field() {
+        let mut doc = SettingsDoc::new();
+        let json = serde_json::json!({ "ansi_spans": true });
+        assert!(doc.apply_json_changes(&json));
+        assert!(doc.get_all().ansi_spans);
+        assert!(!doc.apply_json_changes(&json));
+    }
+
+    #[test]
+    fn test_nested_bool_setting() {
+        let mut doc = SettingsDoc::new();
+        assert!(!doc.get_all().lsp.enabled);
+        doc.put_value("lsp.enabled", &serde_json::json!(true));
+        assert_eq!(doc.get_bool("lsp.enabled"), Some(true));
+        assert!(doc.get_all().lsp.enabled);
+
+        let json = serde_json::json!({ "lsp": { "enabled": false } });
+        assert!(doc.apply_json_changes(&json));
+        assert!(!doc.get_all().lsp.enabled);
+    }
+
+    #[test]
+    fn test_tool_settings() {
+        let mut doc = SettingsDoc::new();
+        let tools = doc.get_all().tools;
+        assert_eq!(tools, ToolSettings::default());
+        assert_eq!(
+            tools.preferences(),
+            kernel_launch::tools::ToolPreferences::default()
+        );
+
+        let json = serde_json::json!({
+            "tools": { "uv_path": "/opt/uv/bin/uv", "prefer_system": false }
+        });
+        assert!(doc.apply_json_changes(&json));
+        assert!(!doc.apply_json_changes(&json));
+
+        let preferences = doc.get_all().tools.preferences();
+        assert_eq!(
+            preferences.uv_path,
+            Some(std::path::PathBuf::from("/opt/uv/bin/uv"))
+        );
+        assert_eq!(preferences.ruff_path, None);
+        assert!(!preferences.prefer_system);
+    }
+
     #[test]
     fn test_apply_json_changes_nested_packages() {
         let mut doc = SettingsDoc::new();
@@ -1109,6 +2154,117 @@ mod tests {
         assert_eq!(doc.get_list("conda.default_packages"), vec!["scipy"]);
     }
 
+    #[test]
+    fn test_kernel_startup_settings() {
+        let mut doc = SettingsDoc::new();
+        assert_eq!(
+            doc.get_all().kernel_startup,
+            KernelStartupSettings::default()
+        );
+        assert_eq!(doc.get_u64("kernel_startup.timeout_secs"), Some(60));
+
+        let json = serde_json::json!({
+            "kernel_startup": { "timeout_secs": 180, "retry": false },
+        });
+        assert!(doc.apply_json_changes(&json));
+        let startup = doc.get_all().kernel_startup;
+        assert_eq!(startup.timeout_secs, 180);
+        assert!(!startup.retry);
+        assert!(!doc.apply_json_changes(&json));
+
+        // Tauri commands write numbers through put_value
+        doc.put_value("kernel_startup.timeout_secs", &serde_json::json!(30));
+        assert_eq!(doc.get_all().kernel_startup.timeout_secs, 30);
+        // Negative and fractional values are ignored
+        doc.put_value("kernel_startup.timeout_secs", &serde_json::json!(-5));
+        doc.put_value("kernel_startup.timeout_secs", &serde_json::json!(2.5));
+        assert_eq!(doc.get_all().kernel_startup.timeout_secs, 30);
+
+        // Out-of-range values are clamped when read
+        doc.put_value("kernel_startup.timeout_secs", &serde_json::json!(0));
+        assert_eq!(
+            doc.get_all().kernel_startup.timeout_secs,
+            KernelStartupSettings::MIN_TIMEOUT_SECS
+        );
+        doc.put_value(
+            "kernel_startup.timeout_secs",
+            &serde_json::json!(u64::from(u32::MAX) + 1),
+        );
+        assert_eq!(
+            doc.get_all().kernel_startup.timeout_secs,
+            KernelStartupSettings::MAX_TIMEOUT_SECS
+        );
+    }
+
+    #[test]
+    fn test_conda_channel_settings() {
+        let mut doc = SettingsDoc::new();
+        let conda = doc.get_all().conda;
+        assert!(conda.channels.is_empty());
+        assert!(conda.strict_channel_priority);
+        assert_eq!(
+            conda.channel_priority(),
+            kernel_env::ChannelPriority::Strict
+        );
+
+        let json = serde_json::json!({
+            "conda": {
+                "default_packages": [],
+                "channels": ["bioconda", "conda-forge"],
+                "strict_channel_priority": false,
+            },
+        });
+        assert!(doc.apply_json_changes(&json));
+        let conda = doc.get_all().conda;
+        // Order is priority, so it's kept as written
+        assert_eq!(conda.channels, vec!["bioconda", "conda-forge"]);
+        assert_eq!(
+            conda.channel_priority(),
+            kernel_env::ChannelPriority::Disabled
+        );
+        assert!(!doc.apply_json_changes(&json));
+
+        // Settings files written before these keys existed keep strict priority
+        let old: SyncedSettings =
+            serde_json::from_value(serde_json::json!({ "conda": { "default_packages": [] } }))
+                .unwrap();
+        assert!(old.conda.strict_channel_priority);
+    }
+
+    #[test]
+    fn test_default_env_sources() {
+        let mut doc = SettingsDoc::new();
+        let settings = doc.get_all();
+        assert_eq!(settings.uv.index_url(), None);
+        assert_eq!(settings.conda.default_channels(), vec!["conda-forge"]);
+
+        let json = serde_json::json!({
+            "uv": {
+                "default_packages": [],
+                "default_index_url": "https://pypi.example.com/simple",
+            },
+            "conda": { "default_packages": [], "channels": ["bioconda"] },
+        });
+        assert!(doc.apply_json_changes(&json));
+        let settings = doc.get_all();
+        assert_eq!(
+            settings.uv.index_url(),
+            Some("https://pypi.example.com/simple")
+        );
+        assert_eq!(settings.conda.default_channels(), vec!["bioconda"]);
+        assert!(!doc.apply_json_changes(&json));
+
+        // Blank means PyPI again
+        doc.put("uv.default_index_url", "  ");
+        assert_eq!(doc.get_all().uv.index_url(), None);
+
+        let imported = SettingsDoc::from_json(&json);
+        assert_eq!(
+            imported.get_all().uv.default_index_url,
+            "https://pypi.example.com/simple"
+        );
+    }
+
     #[test]
     fn test_apply_json_changes_packages_no_change() {
         let mut doc = SettingsDoc::new();
@@ -1124,4 +2280,179 @@ mod tests {
         let changed = doc.apply_json_changes(&json);
         assert!(!changed);
     }
+
+    #[test]
+    fn test_keybindings_map_round_trip() {
+        let mut doc = SettingsDoc::new();
+        assert!(doc.get_all().keybindings.is_empty());
+
+        doc.put_value(
+            "keybindings",
+            &serde_json::json!({ "save": "CmdOrCtrl+Shift+S", "open": "" }),
+        );
+        let keybindings = doc.get_all().keybindings;
+        assert_eq!(keybindings.len(), 2);
+        assert_eq!(keybindings["save"], "CmdOrCtrl+Shift+S");
+        assert_eq!(keybindings["open"], "");
+
+        // Replacing the map drops entries that are no longer present
+        let changed = doc.apply_json_changes(&serde_json::json!({
+            "keybindings": { "save": "CmdOrCtrl+Alt+S" },
+        }));
+        assert!(changed);
+        let keybindings = doc.get_all().keybindings;
+        assert_eq!(keybindings.len(), 1);
+        assert_eq!(keybindings["save"], "CmdOrCtrl+Alt+S");
+
+        let changed = doc.apply_json_changes(&serde_json::json!({
+            "keybindings": { "save": "CmdOrCtrl+Alt+S" },
+        }));
+        assert!(!changed);
+    }
+
+    #[test]
+    fn test_keybindings_from_json() {
+        let json = serde_json::json!({ "keybindings": { "zoom_in": "CmdOrCtrl+Shift+=" } });
+        let doc = SettingsDoc::from_json(&json);
+        assert_eq!(doc.get_all().keybindings["zoom_in"], "CmdOrCtrl+Shift+=");
+    }
+
+    #[test]
+    fn test_output_mime_priority() {
+        let mut doc = SettingsDoc::new();
+        assert!(doc.get_all().outputs.mime_priority.is_empty());
+
+        let changed = doc.apply_json_changes(&serde_json::json!({
+            "outputs": { "mime_priority": ["application/vnd.*", "text/html"] },
+        }));
+        assert!(changed);
+        assert_eq!(
+            doc.get_all().outputs.mime_priority,
+            vec!["application/vnd.*", "text/html"]
+        );
+        assert!(!doc.apply_json_changes(&serde_json::json!({
+            "outputs": { "mime_priority": ["application/vnd.*", "text/html"] },
+        })));
+
+        let json = serde_json::json!({ "outputs": { "mime_priority": ["text/plain"] } });
+        let doc = SettingsDoc::from_json(&json);
+        assert_eq!(doc.get_all().outputs.mime_priority, vec!["text/plain"]);
+    }
+
+    #[test]
+    fn test_output_sidecar_mime() {
+        let mut doc = SettingsDoc::new();
+        assert!(doc.get_all().outputs.sidecar_mime.is_empty());
+
+        assert!(doc.apply_json_changes(&serde_json::json!({
+            "outputs": { "sidecar_mime": ["application/vnd.plotly.v1+json"] },
+        })));
+        assert_eq!(
+            doc.get_all().outputs.sidecar_mime,
+            vec!["application/vnd.plotly.v1+json"]
+        );
+        // Other output settings are untouched
+        assert!(doc.get_all().outputs.mime_priority.is_empty());
+
+        let json = serde_json::json!({ "outputs": { "sidecar_mime": ["application/vnd.vega*"] } });
+        let doc = SettingsDoc::from_json(&json);
+        assert_eq!(
+            doc.get_all().outputs.sidecar_mime,
+            vec!["application/vnd.vega*"]
+        );
+    }
+
+    #[test]
+    fn test_output_large_output_bytes() {
+        let mut doc = SettingsDoc::new();
+        assert_eq!(doc.get_all().outputs.large_output_bytes, 1024 * 1024);
+
+        assert!(doc.apply_json_changes(&serde_json::json!({
+            "outputs": { "large_output_bytes": 0 },
+        })));
+        assert_eq!(doc.get_all().outputs.large_output_bytes, 0);
+        assert!(!doc.apply_json_changes(&serde_json::json!({
+            "outputs": { "large_output_bytes": 0 },
+        })));
+
+        let json = serde_json::json!({ "outputs": { "large_output_bytes": 65536 } });
+        let doc = SettingsDoc::from_json(&json);
+        assert_eq!(doc.get_all().outputs.large_output_bytes, 65536);
+    }
+
+    #[test]
+    fn test_output_html_and_javascript_gates() {
+        let mut doc = SettingsDoc::new();
+        let outputs = doc.get_all().outputs;
+        assert!(outputs.allow_html);
+        assert!(!outputs.allow_javascript);
+
+        assert!(doc.apply_json_changes(&serde_json::json!({
+            "outputs": { "allow_html": false, "allow_javascript": true },
+        })));
+        let outputs = doc.get_all().outputs;
+        assert!(!outputs.allow_html);
+        assert!(outputs.allow_javascript);
+
+        let json = serde_json::json!({ "outputs": { "allow_html": false } });
+        let doc = SettingsDoc::from_json(&json);
+        assert!(!doc.get_all().outputs.allow_html);
+        assert!(!doc.get_all().outputs.allow_javascript);
+    }
+
+    #[test]
+    fn test_new_notebook_template() {
+        let mut doc = SettingsDoc::new();
+        assert!(doc.get_all().new_notebook_template.is_empty());
+
+        doc.put_value(
+            "new_notebook_template",
+            &serde_json::json!({
+                "python": [
+                    { "cell_type": "markdown", "source": "# Analysis" },
+                    { "cell_type": "code", "source": "import pandas as pd" },
+                ],
+                "deno": "not a list",
+                "r": [
+                    { "cell_type": "code", "source": "library(dplyr)" },
+                    { "cell_type": "sql", "source": "select 1" },
+                ],
+            }),
+        );
+        let templates = doc.get_all().new_notebook_template;
+        assert_eq!(templates.len(), 1);
+        assert_eq!(
+            templates["python"],
+            vec![
+                TemplateCell {
+                    cell_type: TemplateCellType::Markdown,
+                    source: "# Analysis".into(),
+                },
+                TemplateCell {
+                    cell_type: TemplateCellType::Code,
+                    source: "import pandas as pd".into(),
+                },
+            ]
+        );
+
+        let err = parse_template_cells(
+            "r",
+            &serde_json::json!([{ "cell_type": "code" }, { "cell_type": "sql" }]),
+        )
+        .unwrap_err();
+        assert!(err.starts_with("Template cell 2 for r:"), "{err}");
+        assert!(err.contains("sql"), "{err}");
+
+        let json = serde_json::json!({
+            "new_notebook_template": { "deno": [{ "cell_type": "code" }] },
+        });
+        assert!(doc.apply_json_changes(&json));
+        assert!(!doc.apply_json_changes(&json));
+        let templates = doc.get_all().new_notebook_template;
+        assert!(!templates.contains_key("python"));
+        assert_eq!(templates["deno"][0].source, "");
+
+        let doc = SettingsDoc::from_json(&json);
+        assert_eq!(doc.get_all().new_notebook_template.len(), 1);
+    }
 }
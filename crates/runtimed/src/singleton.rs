@@ -3,6 +3,7 @@
 //! Ensures only one daemon instance runs per user using file-based locking.
 
 use std::fs::{File, OpenOptions};
+use std::io::Write;
 use std::path::PathBuf;
 
 use chrono::{DateTime, Utc};
@@ -23,6 +24,9 @@ pub struct DaemonInfo {
     /// HTTP port for the blob server (if running).
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub blob_port: Option<u16>,
+    /// Bearer token the blob server requires (if configured).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob_token: Option<String>,
     /// Path to the git worktree (dev mode only).
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub worktree_path: Option<String>,
@@ -46,7 +50,7 @@ impl DaemonLock {
     ///
     /// If `custom_lock_dir` is provided, uses that directory for lock files
     /// instead of the default. This is primarily for testing.
-    pub fn try_acquire(custom_lock_dir: Option<&PathBuf>) -> Result<Self, DaemonInfo> {
+    pub fn try_acquire(custom_lock_dir: Option<&PathBuf>) -> Result<Self, Box<DaemonInfo>> {
         let (lock_path, info_path) = if let Some(dir) = custom_lock_dir {
             (dir.join("daemon.lock"), dir.join("daemon.json"))
         } else {
@@ -70,18 +74,19 @@ impl DaemonLock {
                 warn!("[singleton] Failed to open lock file: {}", e);
                 // Try to read existing daemon info
                 if let Some(info) = read_daemon_info(&info_path) {
-                    return Err(info);
+                    return Err(Box::new(info));
                 }
                 // No info available, create a placeholder
-                return Err(DaemonInfo {
+                return Err(Box::new(DaemonInfo {
                     endpoint: "unknown".to_string(),
                     pid: 0,
                     version: "unknown".to_string(),
                     started_at: Utc::now(),
                     blob_port: None,
+                    blob_token: None,
                     worktree_path: None,
                     workspace_description: None,
-                });
+                }));
             }
         };
 
@@ -95,17 +100,18 @@ impl DaemonLock {
                 // Another process holds the lock
                 info!("[singleton] Another daemon is already running");
                 if let Some(info) = read_daemon_info(&info_path) {
-                    return Err(info);
+                    return Err(Box::new(info));
                 }
-                return Err(DaemonInfo {
+                return Err(Box::new(DaemonInfo {
                     endpoint: "unknown".to_string(),
                     pid: 0,
                     version: "unknown".to_string(),
                     started_at: Utc::now(),
                     blob_port: None,
+                    blob_token: None,
                     worktree_path: None,
                     workspace_description: None,
-                });
+                }));
             }
         }
 
@@ -133,17 +139,18 @@ impl DaemonLock {
             if result == 0 {
                 info!("[singleton] Another daemon is already running");
                 if let Some(info) = read_daemon_info(&info_path) {
-                    return Err(info);
+                    return Err(Box::new(info));
                 }
-                return Err(DaemonInfo {
+                return Err(Box::new(DaemonInfo {
                     endpoint: "unknown".to_string(),
                     pid: 0,
                     version: "unknown".to_string(),
                     started_at: Utc::now(),
                     blob_port: None,
+                    blob_token: None,
                     worktree_path: None,
                     workspace_description: None,
-                });
+                }));
             }
         }
 
@@ -157,7 +164,15 @@ impl DaemonLock {
     }
 
     /// Write daemon info after successful startup.
-    pub fn write_info(&self, endpoint: &str, blob_port: Option<u16>) -> std::io::Result<()> {
+    ///
+    /// The file is readable by the owner only, since it can hold the blob
+    /// token.
+    pub fn write_info(
+        &self,
+        endpoint: &str,
+        blob_port: Option<u16>,
+        blob_token: Option<&str>,
+    ) -> std::io::Result<()> {
         // Populate worktree info when in dev mode
         let (worktree_path, workspace_description) = if crate::is_dev_mode() {
             (
@@ -174,13 +189,25 @@ impl DaemonLock {
             version: format!("{}+{}", env!("CARGO_PKG_VERSION"), env!("GIT_COMMIT")),
             started_at: Utc::now(),
             blob_port,
+            blob_token: blob_token.map(String::from),
             worktree_path,
             workspace_description,
         };
 
         let json = serde_json::to_string_pretty(&info).map_err(std::io::Error::other)?;
 
-        std::fs::write(&self.info_path, json)?;
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&self.info_path)?;
+        // The mode only applies to a new file; tighten one left by an older daemon
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        }
+        file.write_all(json.as_bytes())?;
         info!("[singleton] Wrote daemon info to {:?}", self.info_path);
 
         Ok(())
@@ -237,4 +264,25 @@ mod tests {
         assert!(lock_path.to_string_lossy().contains("daemon.lock"));
         assert!(info_path.to_string_lossy().contains("daemon.json"));
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_info_file_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path().to_path_buf();
+        let info_path = dir.join("daemon.json");
+        std::fs::write(&info_path, "{}").unwrap();
+        std::fs::set_permissions(&info_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let lock = DaemonLock::try_acquire(Some(&dir)).unwrap();
+        lock.write_info("ipc:///tmp/runtimed.sock", Some(8765), Some("secret"))
+            .unwrap();
+
+        let mode = std::fs::metadata(&info_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let info = read_daemon_info(&info_path).unwrap();
+        assert_eq!(info.blob_token.as_deref(), Some("secret"));
+    }
 }
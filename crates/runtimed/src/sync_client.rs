@@ -15,7 +15,9 @@ use tokio::io::{AsyncRead, AsyncWrite};
 
 use crate::connection::{self, Handshake};
 use crate::settings_doc::{
-    read_nested_list, split_comma_list, CondaDefaults, SyncedSettings, ThemeMode, UvDefaults,
+    read_nested_bool, read_nested_list, read_nested_str, read_nested_u64, read_scalar_bool,
+    read_string_map, read_templates, split_comma_list, CondaDefaults, KernelStartupSettings,
+    LspSettings, OutputSettings, SyncedSettings, ThemeMode, ToolSettings, UvDefaults,
 };
 
 /// Error type for sync client operations.
@@ -375,10 +377,59 @@ fn get_all_from_doc(doc: &AutoCommit) -> SyncedSettings {
             .unwrap_or_default(),
         uv: UvDefaults {
             default_packages: uv_packages,
+            default_index_url: read_nested_str(doc, "uv", "default_index_url").unwrap_or_default(),
         },
         conda: CondaDefaults {
             default_packages: conda_packages,
+            channels: read_nested_list(doc, "conda", "channels"),
+            strict_channel_priority: read_nested_bool(doc, "conda", "strict_channel_priority")
+                .unwrap_or(defaults.conda.strict_channel_priority),
         },
+        ansi_spans: read_scalar_bool(doc, automerge::ROOT, "ansi_spans")
+            .unwrap_or(defaults.ansi_spans),
+        keep_kernel_on_close: read_scalar_bool(doc, automerge::ROOT, "keep_kernel_on_close")
+            .unwrap_or(defaults.keep_kernel_on_close),
+        keep_kernel_on_exit: read_scalar_bool(doc, automerge::ROOT, "keep_kernel_on_exit")
+            .unwrap_or(defaults.keep_kernel_on_exit),
+        run_log: read_scalar_bool(doc, automerge::ROOT, "run_log").unwrap_or(defaults.run_log),
+        block_invalid_save: read_scalar_bool(doc, automerge::ROOT, "block_invalid_save")
+            .unwrap_or(defaults.block_invalid_save),
+        hide_install_suggestions: read_scalar_bool(
+            doc,
+            automerge::ROOT,
+            "hide_install_suggestions",
+        )
+        .unwrap_or(defaults.hide_install_suggestions),
+        lsp: LspSettings {
+            enabled: read_nested_bool(doc, "lsp", "enabled").unwrap_or(defaults.lsp.enabled),
+        },
+        kernel_startup: KernelStartupSettings {
+            timeout_secs: read_nested_u64(doc, "kernel_startup", "timeout_secs")
+                .map(KernelStartupSettings::clamp_timeout_secs)
+                .unwrap_or(defaults.kernel_startup.timeout_secs),
+            retry: read_nested_bool(doc, "kernel_startup", "retry")
+                .unwrap_or(defaults.kernel_startup.retry),
+        },
+        tools: ToolSettings {
+            uv_path: read_nested_str(doc, "tools", "uv_path").unwrap_or_default(),
+            ruff_path: read_nested_str(doc, "tools", "ruff_path").unwrap_or_default(),
+            deno_path: read_nested_str(doc, "tools", "deno_path").unwrap_or_default(),
+            prefer_system: read_nested_bool(doc, "tools", "prefer_system")
+                .unwrap_or(defaults.tools.prefer_system),
+        },
+        keybindings: read_string_map(doc, "keybindings"),
+        outputs: OutputSettings {
+            mime_priority: read_nested_list(doc, "outputs", "mime_priority"),
+            allow_html: read_nested_bool(doc, "outputs", "allow_html")
+                .unwrap_or(defaults.outputs.allow_html),
+            allow_javascript: read_nested_bool(doc, "outputs", "allow_javascript")
+                .unwrap_or(defaults.outputs.allow_javascript),
+            sidecar_mime: read_nested_list(doc, "outputs", "sidecar_mime"),
+            large_output_bytes: read_nested_u64(doc, "outputs", "large_output_bytes")
+                .and_then(|n| u32::try_from(n).ok())
+                .unwrap_or(defaults.outputs.large_output_bytes),
+        },
+        new_notebook_template: read_templates(doc),
     }
 }
 
@@ -98,7 +98,7 @@ where
 }
 
 /// Persist the settings document to disk (both Automerge binary and JSON mirror).
-fn persist_settings(doc: &mut SettingsDoc) {
+pub(crate) fn persist_settings(doc: &mut SettingsDoc) {
     let automerge_path = crate::default_settings_doc_path();
     let json_path = crate::settings_json_path();
 
@@ -0,0 +1,221 @@
+//! Last-resort Python kernels from the system interpreter.
+//!
+//! Prewarmed kernels come from the uv and conda pools. When neither env
+//! manager can produce environments (uv isn't installed, conda keeps
+//! failing, or both pools are disabled), auto-launch falls back to running
+//! ipykernel from the `python3` on PATH, as env source `system:python`.
+//! There is no environment to manage, so the notebook's dependencies are
+//! not installed.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+/// Env source of kernels launched from the system Python.
+pub const SYSTEM_PYTHON_SOURCE: &str = "system:python";
+
+#[cfg(windows)]
+const CANDIDATES: &[&str] = &["python3.exe", "python.exe"];
+// A bare `python` is Python 2 on some systems
+#[cfg(not(windows))]
+const CANDIDATES: &[&str] = &["python3"];
+
+/// The first Python 3 interpreter on PATH.
+pub fn find_system_python() -> Option<PathBuf> {
+    find_python_in(&std::env::var_os("PATH")?)
+}
+
+fn find_python_in(path: &OsStr) -> Option<PathBuf> {
+    CANDIDATES.iter().find_map(|name| {
+        std::env::split_paths(path)
+            .map(|dir| dir.join(name))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+/// Whether `python` can import ipykernel, which the kernel launches with.
+pub async fn has_ipykernel(python: &Path) -> bool {
+    tokio::process::Command::new(python)
+        .args(["-c", "import ipykernel"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .is_ok_and(|status| status.success())
+}
+
+/// What to do when a prewarmed launch finds its pool empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmptyPoolFallback {
+    /// An env manager is working; its pool will refill, so report the empty
+    /// pool as before.
+    Wait,
+    /// No env manager is available; launch from this interpreter.
+    SystemPython(PathBuf),
+    /// No env manager is available and there is no Python on PATH either.
+    NoPython,
+    /// No env manager is available and the Python on PATH can't import
+    /// ipykernel.
+    NoIpykernel(PathBuf),
+}
+
+impl EmptyPoolFallback {
+    /// Kernel status telling the user why nothing could launch, or `None`
+    /// for a fallback that launches or waits.
+    pub fn guidance(&self) -> Option<String> {
+        match self {
+            Self::Wait | Self::SystemPython(_) => None,
+            Self::NoPython => Some(
+                "error: Neither uv nor conda is available and no python3 was found on PATH"
+                    .to_string(),
+            ),
+            Self::NoIpykernel(python) => Some(format!(
+                "error: Neither uv nor conda is available and {} can't import ipykernel; \
+                 install it with `{} -m pip install ipykernel`",
+                python.display(),
+                python.display()
+            )),
+        }
+    }
+}
+
+/// Choose the fallback for an empty pool, given whether the uv and conda
+/// pools can produce environments and the system Python, if any. The
+/// system Python is only used if it can import ipykernel.
+pub async fn empty_pool_fallback(
+    uv_available: bool,
+    conda_available: bool,
+    system_python: Option<&Path>,
+) -> EmptyPoolFallback {
+    if uv_available || conda_available {
+        return EmptyPoolFallback::Wait;
+    }
+    match system_python {
+        Some(python) if has_ipykernel(python).await => {
+            EmptyPoolFallback::SystemPython(python.to_path_buf())
+        }
+        Some(python) => EmptyPoolFallback::NoIpykernel(python.to_path_buf()),
+        None => EmptyPoolFallback::NoPython,
+    }
+}
+
+/// A fake `python3` first on PATH, for tests of the system Python fallback.
+#[cfg(all(test, unix))]
+pub(crate) struct FakeSystemPython {
+    _dir: tempfile::TempDir,
+    previous: Option<std::ffi::OsString>,
+    _turn: tokio::sync::MutexGuard<'static, ()>,
+}
+
+#[cfg(all(test, unix))]
+impl FakeSystemPython {
+    /// Install a `python3` that runs `script` (a shell script body) and
+    /// put it first on PATH until dropped. Tests take turns with PATH.
+    pub async fn install(script: &str) -> Self {
+        use std::os::unix::fs::PermissionsExt;
+
+        static PATH_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+        let turn = PATH_LOCK.lock().await;
+        let dir = tempfile::TempDir::new().unwrap();
+        let python = dir.path().join("python3");
+        std::fs::write(&python, format!("#!/bin/sh\n{script}\n")).unwrap();
+        std::fs::set_permissions(&python, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let previous = std::env::var_os("PATH");
+        let mut paths = vec![dir.path().to_path_buf()];
+        paths.extend(std::env::split_paths(
+            previous.as_deref().unwrap_or_default(),
+        ));
+        std::env::set_var("PATH", std::env::join_paths(paths).unwrap());
+        Self {
+            _dir: dir,
+            previous,
+            _turn: turn,
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+impl Drop for FakeSystemPython {
+    fn drop(&mut self) {
+        match &self.previous {
+            Some(path) => std::env::set_var("PATH", path),
+            None => std::env::remove_var("PATH"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A stand-in interpreter whose every run exits with `code`.
+    #[cfg(unix)]
+    fn fake_python(dir: &TempDir, code: i32) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let python = dir.path().join("python3");
+        std::fs::write(&python, format!("#!/bin/sh\nexit {code}\n")).unwrap();
+        std::fs::set_permissions(&python, std::fs::Permissions::from_mode(0o755)).unwrap();
+        python
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_falls_back_to_system_python_without_env_managers() {
+        let dir = TempDir::new().unwrap();
+        let python = fake_python(&dir, 0);
+        let fallback = empty_pool_fallback(false, false, Some(&python)).await;
+        assert_eq!(fallback, EmptyPoolFallback::SystemPython(python));
+        assert_eq!(fallback.guidance(), None);
+
+        let fallback = empty_pool_fallback(false, false, None).await;
+        assert_eq!(fallback, EmptyPoolFallback::NoPython);
+        assert!(fallback.guidance().unwrap().contains("no python3"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_system_python_without_ipykernel_is_not_used() {
+        let dir = TempDir::new().unwrap();
+        let python = fake_python(&dir, 1);
+        let fallback = empty_pool_fallback(false, false, Some(&python)).await;
+        assert_eq!(fallback, EmptyPoolFallback::NoIpykernel(python.clone()));
+        let guidance = fallback.guidance().unwrap();
+        assert!(
+            guidance.contains(&format!("{} -m pip install ipykernel", python.display())),
+            "{guidance}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_an_available_env_manager() {
+        let python = Some(Path::new("/usr/bin/python3"));
+        assert_eq!(
+            empty_pool_fallback(true, false, python).await,
+            EmptyPoolFallback::Wait
+        );
+        assert_eq!(
+            empty_pool_fallback(false, true, python).await,
+            EmptyPoolFallback::Wait
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_python_skips_bare_python() {
+        let first = TempDir::new().unwrap();
+        let second = TempDir::new().unwrap();
+        std::fs::write(first.path().join("python"), "").unwrap();
+        let python3 = second.path().join("python3");
+        std::fs::write(&python3, "").unwrap();
+
+        let path = std::env::join_paths([first.path(), second.path()]).unwrap();
+        assert_eq!(find_python_in(&path), Some(python3));
+
+        let path = std::env::join_paths([first.path()]).unwrap();
+        assert_eq!(find_python_in(&path), None);
+    }
+}
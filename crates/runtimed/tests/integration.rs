@@ -18,10 +18,14 @@ fn test_config(temp_dir: &TempDir) -> DaemonConfig {
         cache_dir: temp_dir.path().join("envs"),
         blob_store_dir: temp_dir.path().join("blobs"),
         notebook_docs_dir: temp_dir.path().join("notebook-docs"),
+        notebook_registry_path: temp_dir.path().join("notebooks.json"),
+        kernel_pids_path: temp_dir.path().join("kernel-pids.json"),
+        env_pins_path: temp_dir.path().join("env-pins.json"),
         uv_pool_size: 0, // Don't create real envs in tests
         conda_pool_size: 0,
         max_age_secs: 3600,
         lock_dir: Some(temp_dir.path().to_path_buf()),
+        blob_server: Default::default(),
     }
 }
 
@@ -136,10 +140,14 @@ async fn test_singleton_prevents_second_daemon() {
         cache_dir: temp_dir.path().join("envs"),
         blob_store_dir: temp_dir.path().join("blobs"),
         notebook_docs_dir: temp_dir.path().join("notebook-docs"),
+        notebook_registry_path: temp_dir.path().join("notebooks.json"),
+        kernel_pids_path: temp_dir.path().join("kernel-pids.json"),
+        env_pins_path: temp_dir.path().join("env-pins.json"),
         uv_pool_size: 0,
         conda_pool_size: 0,
         max_age_secs: 3600,
         lock_dir: Some(temp_dir.path().to_path_buf()),
+        blob_server: Default::default(),
     };
 
     let result = Daemon::new(config2);
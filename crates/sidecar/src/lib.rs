@@ -24,7 +24,7 @@ use jupyter_protocol::{
     media::MediaType, ConnectionInfo, ExecuteRequest, ExpressionResult, JupyterMessage,
     JupyterMessageContent, KernelInfoRequest,
 };
-use tauri_jupyter::WebViewJupyterMessage;
+use tauri_jupyter::{BufferLimits, WebViewJupyterMessage};
 
 use std::path::PathBuf;
 use tao::{
@@ -198,7 +198,7 @@ async fn run(
         .with_devtools(true)
         .with_asynchronous_custom_protocol("sidecar".into(), move |_webview_id, req, responder| {
             if let (&Method::POST, "/message") = (req.method(), req.uri().path()) {
-                match serde_json::from_slice::<WryJupyterMessage>(req.body()) {
+                match WryJupyterMessage::from_slice(req.body(), BufferLimits::DEFAULT) {
                     Ok(wry_message) => {
                         let message: JupyterMessage = wry_message.into();
 
@@ -1,8 +1,12 @@
 //! Base64 serialization/deserialization utilities for Jupyter message buffers.
 
+use std::cell::Cell;
+use std::fmt;
+
 use base64::prelude::*;
 use bytes::Bytes;
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{self, DeserializeSeed, SeqAccess, Visitor};
+use serde::{Deserializer, Serialize, Serializer};
 
 /// Serialize a slice of Bytes as base64-encoded strings.
 ///
@@ -17,28 +21,184 @@ where
         .serialize(serializer)
 }
 
+/// Caps on the buffers one incoming message may carry.
+///
+/// Checked against the base64 strings before any of them is decoded, so a
+/// message declaring thousands of buffers or a huge blob is rejected without
+/// allocating for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferLimits {
+    /// Most buffers a message may carry
+    pub max_buffers: usize,
+    /// Most bytes a message's buffers may hold together, once decoded
+    pub max_total_bytes: usize,
+}
+
+impl BufferLimits {
+    /// Well above what widgets send (a few large arrays or images), but
+    /// finite.
+    pub const DEFAULT: Self = Self {
+        max_buffers: 1024,
+        max_total_bytes: 256 * 1024 * 1024,
+    };
+}
+
+impl Default for BufferLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl BufferLimits {
+    /// Check buffers of the given decoded sizes against the limits, failing
+    /// at the first one that goes over them.
+    ///
+    /// For messages whose buffers arrive already parsed (such as JSON byte
+    /// arrays), before any of them is copied.
+    pub fn check_sizes(
+        &self,
+        sizes: impl IntoIterator<Item = usize>,
+    ) -> Result<(), BufferLimitExceeded> {
+        let mut budget = BufferBudget::new(*self);
+        sizes.into_iter().try_for_each(|size| budget.admit(size))
+    }
+}
+
+/// Which of the [`BufferLimits`] a message broke.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum BufferLimitExceeded {
+    #[error("message carries more than {limit} buffers")]
+    TooManyBuffers { limit: usize },
+
+    #[error("message buffers hold more than {limit} bytes")]
+    TooManyBytes { limit: usize },
+}
+
+/// Running count of the buffers admitted so far.
+struct BufferBudget {
+    limits: BufferLimits,
+    buffers: usize,
+    bytes: usize,
+}
+
+impl BufferBudget {
+    fn new(limits: BufferLimits) -> Self {
+        Self {
+            limits,
+            buffers: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Fail early when a sequence says up front it's over the count.
+    fn check_len_hint(&self, len: Option<usize>) -> Result<(), BufferLimitExceeded> {
+        match len {
+            Some(len) if len > self.limits.max_buffers => Err(self.too_many_buffers()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Admit one more buffer of `decoded_len` bytes.
+    fn admit(&mut self, decoded_len: usize) -> Result<(), BufferLimitExceeded> {
+        if self.buffers >= self.limits.max_buffers {
+            return Err(self.too_many_buffers());
+        }
+        let bytes = self.bytes.saturating_add(decoded_len);
+        if bytes > self.limits.max_total_bytes {
+            return Err(BufferLimitExceeded::TooManyBytes {
+                limit: self.limits.max_total_bytes,
+            });
+        }
+        self.buffers += 1;
+        self.bytes = bytes;
+        Ok(())
+    }
+
+    fn too_many_buffers(&self) -> BufferLimitExceeded {
+        BufferLimitExceeded::TooManyBuffers {
+            limit: self.limits.max_buffers,
+        }
+    }
+}
+
+/// Bytes a base64 string decodes to, without decoding it.
+fn decoded_len(encoded: &str) -> usize {
+    let padding = encoded.bytes().rev().take_while(|&b| b == b'=').count();
+    (encoded.len() / 4 * 3 + encoded.len() % 4 * 3 / 4).saturating_sub(padding)
+}
+
 /// Deserialize base64-encoded buffer strings into Bytes.
 ///
 /// Handles both `null` and missing `buffers` field gracefully,
-/// returning an empty Vec in those cases.
+/// returning an empty Vec in those cases. Enforces
+/// [`BufferLimits::DEFAULT`], failing before decoding the buffer that would
+/// go over them. [`RawJupyterMessage::from_slice`](crate::RawJupyterMessage::from_slice)
+/// takes other limits.
 ///
 /// Used with `#[serde(default, deserialize_with = "deserialize_buffers")]`
 pub fn deserialize_buffers<'de, D>(deserializer: D) -> Result<Vec<Bytes>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let encoded: Option<Vec<String>> = Option::deserialize(deserializer)?;
-    match encoded {
-        Some(vec) => vec
-            .iter()
-            .map(|s| {
-                BASE64_STANDARD
-                    .decode(s)
-                    .map(Bytes::from)
-                    .map_err(serde::de::Error::custom)
-            })
-            .collect(),
-        None => Ok(Vec::new()),
+    DecodeBuffers {
+        limits: BufferLimits::DEFAULT,
+        exceeded: &Cell::new(None),
+    }
+    .deserialize(deserializer)
+}
+
+/// Decodes a `buffers` list within `limits`, recording in `exceeded` the
+/// limit that stopped it so callers can tell it apart from malformed input.
+pub(crate) struct DecodeBuffers<'a> {
+    pub limits: BufferLimits,
+    pub exceeded: &'a Cell<Option<BufferLimitExceeded>>,
+}
+
+impl<'de> DeserializeSeed<'de> for DecodeBuffers<'_> {
+    type Value = Vec<Bytes>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Vec<Bytes>, D::Error> {
+        deserializer.deserialize_option(self)
+    }
+}
+
+impl<'de> Visitor<'de> for DecodeBuffers<'_> {
+    type Value = Vec<Bytes>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of base64-encoded buffers")
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Vec::new())
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Vec::new())
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let fail = |exceeded: BufferLimitExceeded| {
+            let error = de::Error::custom(&exceeded);
+            self.exceeded.set(Some(exceeded));
+            error
+        };
+        let mut budget = BufferBudget::new(self.limits);
+        budget.check_len_hint(seq.size_hint()).map_err(fail)?;
+
+        let mut buffers = Vec::new();
+        while let Some(encoded) = seq.next_element::<String>()? {
+            budget.admit(decoded_len(&encoded)).map_err(fail)?;
+            let bytes = BASE64_STANDARD
+                .decode(&encoded)
+                .map_err(de::Error::custom)?;
+            buffers.push(Bytes::from(bytes));
+        }
+        Ok(buffers)
     }
 }
 
@@ -89,4 +249,94 @@ mod tests {
         let test: TestStruct = serde_json::from_str(json).unwrap();
         assert!(test.buffers.is_empty());
     }
+
+    /// Decode a JSON `buffers` list within `limits`, reporting which limit
+    /// stopped it, if any.
+    fn decode(json: &str, limits: BufferLimits) -> Result<usize, Option<BufferLimitExceeded>> {
+        let exceeded = Cell::new(None);
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        DecodeBuffers {
+            limits,
+            exceeded: &exceeded,
+        }
+        .deserialize(&mut deserializer)
+        .map(|buffers| buffers.len())
+        .map_err(|_| exceeded.take())
+    }
+
+    #[test]
+    fn test_decoded_len_matches_decoding() {
+        for data in ["", "h", "hi", "hey", "hello", "hello world"] {
+            let padded = BASE64_STANDARD.encode(data);
+            let unpadded = BASE64_STANDARD_NO_PAD.encode(data);
+            assert_eq!(decoded_len(&padded), data.len(), "{padded}");
+            assert_eq!(decoded_len(&unpadded), data.len(), "{unpadded}");
+        }
+    }
+
+    #[test]
+    fn test_too_many_buffers_rejected_before_decoding() {
+        let limits = BufferLimits {
+            max_buffers: 2,
+            ..BufferLimits::DEFAULT
+        };
+        // The third entry isn't valid base64; hitting the limit first shows
+        // it was never decoded
+        assert_eq!(
+            decode(r#"["aGk=", "aGk=", "not base64!"]"#, limits),
+            Err(Some(BufferLimitExceeded::TooManyBuffers { limit: 2 }))
+        );
+        assert_eq!(decode(r#"["aGk=", "aGk="]"#, limits), Ok(2));
+    }
+
+    #[test]
+    fn test_oversized_buffers_rejected_before_decoding() {
+        let limits = BufferLimits {
+            max_total_bytes: 1024,
+            ..BufferLimits::DEFAULT
+        };
+        let blob = "@".repeat(4096);
+        assert_eq!(
+            decode(&format!(r#"["aGk=", "{blob}"]"#), limits),
+            Err(Some(BufferLimitExceeded::TooManyBytes { limit: 1024 }))
+        );
+
+        // Exactly at the limit is fine
+        let exact = BASE64_STANDARD.encode([0u8; 1024]);
+        assert_eq!(decode(&format!(r#"["{exact}"]"#), limits), Ok(1));
+    }
+
+    #[test]
+    fn test_check_sizes_counts_buffers_and_bytes() {
+        let limits = BufferLimits {
+            max_buffers: 2,
+            max_total_bytes: 10,
+        };
+        assert_eq!(limits.check_sizes([4, 6]), Ok(()));
+        assert_eq!(
+            limits.check_sizes([4, 7]),
+            Err(BufferLimitExceeded::TooManyBytes { limit: 10 })
+        );
+        assert_eq!(
+            limits.check_sizes([1, 1, 1]),
+            Err(BufferLimitExceeded::TooManyBuffers { limit: 2 })
+        );
+    }
+
+    #[test]
+    fn test_deserialize_buffers_enforces_limits() {
+        let encoded = vec!["aGk="; BufferLimits::DEFAULT.max_buffers + 1];
+        let json = serde_json::json!({ "buffers": encoded }).to_string();
+        let err = serde_json::from_str::<TestStruct>(&json)
+            .err()
+            .expect("over the buffer count");
+        assert!(err.to_string().contains("more than 1024 buffers"), "{err}");
+    }
+
+    #[test]
+    fn test_malformed_buffers_are_not_limit_errors() {
+        let limits = BufferLimits::DEFAULT;
+        assert_eq!(decode("[1, 2]", limits), Err(None));
+        assert_eq!(decode(r#"["not base64!"]"#, limits), Err(None));
+    }
 }
@@ -0,0 +1,225 @@
+//! Builders for comm messages sent from a frontend to a kernel.
+//!
+//! Widgets talk to the kernel over comms: `comm_open` creates one,
+//! `comm_msg` carries state updates and custom messages, and `comm_close`
+//! tears it down. These helpers build well-formed messages for Rust-side
+//! tooling and tests, which would otherwise hand-assemble the JSON envelope
+//! the frontend sends.
+
+use bytes::Bytes;
+use jupyter_protocol::{
+    Channel, CommClose, CommId, CommMsg, CommOpen, JupyterMessage, JupyterMessageContent,
+};
+use serde_json::{Map, Value};
+
+use crate::message::WebViewJupyterMessage;
+
+/// Target name of the ipywidgets comm channel.
+pub const WIDGET_TARGET_NAME: &str = "jupyter.widget";
+
+/// Builds comm messages that all belong to one client session.
+#[derive(Debug, Clone)]
+pub struct CommMessageBuilder {
+    session: String,
+}
+
+impl CommMessageBuilder {
+    /// Build messages for the client session `session`.
+    pub fn new(session: impl Into<String>) -> Self {
+        Self {
+            session: session.into(),
+        }
+    }
+
+    /// The session every built message's header carries.
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    /// Open a comm with `target_name`, sending `data` as its initial payload.
+    pub fn open(
+        &self,
+        comm_id: &str,
+        target_name: &str,
+        data: Map<String, Value>,
+        buffers: Vec<Bytes>,
+    ) -> WebViewJupyterMessage {
+        self.build(
+            CommOpen {
+                comm_id: CommId(comm_id.to_string()),
+                target_name: target_name.to_string(),
+                data,
+                target_module: None,
+            },
+            buffers,
+        )
+    }
+
+    /// Send `data` on an open comm.
+    pub fn msg(
+        &self,
+        comm_id: &str,
+        data: Map<String, Value>,
+        buffers: Vec<Bytes>,
+    ) -> WebViewJupyterMessage {
+        self.build(
+            CommMsg {
+                comm_id: CommId(comm_id.to_string()),
+                data,
+            },
+            buffers,
+        )
+    }
+
+    /// Close a comm.
+    pub fn close(&self, comm_id: &str, data: Map<String, Value>) -> WebViewJupyterMessage {
+        self.build(
+            CommClose {
+                comm_id: CommId(comm_id.to_string()),
+                data,
+            },
+            Vec::new(),
+        )
+    }
+
+    /// Send a widget state update (`method: "update"`).
+    ///
+    /// Each binary value is given with its path into the state (e.g.
+    /// `["value"]` or `["data", 0]`); the paths become `buffer_paths` and
+    /// the bytes the message's buffers, in the same order, so the two always
+    /// line up. Binary values should be left out of `state`.
+    pub fn widget_update(
+        &self,
+        comm_id: &str,
+        state: Map<String, Value>,
+        binary: Vec<(Vec<Value>, Bytes)>,
+    ) -> WebViewJupyterMessage {
+        let (buffer_paths, buffers): (Vec<Value>, Vec<Bytes>) = binary
+            .into_iter()
+            .map(|(path, bytes)| (Value::Array(path), bytes))
+            .unzip();
+
+        let mut data = Map::new();
+        data.insert("method".to_string(), Value::from("update"));
+        data.insert("state".to_string(), Value::Object(state));
+        data.insert("buffer_paths".to_string(), Value::Array(buffer_paths));
+        self.msg(comm_id, data, buffers)
+    }
+
+    fn build(
+        &self,
+        content: impl Into<JupyterMessageContent>,
+        buffers: Vec<Bytes>,
+    ) -> WebViewJupyterMessage {
+        JupyterMessage::new(content, None)
+            .with_session(&self.session)
+            .with_buffers(buffers)
+            .with_channel(Channel::Shell)
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn object(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => panic!("not an object"),
+        }
+    }
+
+    /// Serialize as the frontend envelope and parse it back.
+    fn roundtrip(msg: &WebViewJupyterMessage) -> WebViewJupyterMessage {
+        let envelope = serde_json::to_value(msg).unwrap();
+        serde_json::from_value(envelope).unwrap()
+    }
+
+    #[test]
+    fn test_open_roundtrip() {
+        let builder = CommMessageBuilder::new("session-1");
+        let msg = builder.open(
+            "comm-1",
+            WIDGET_TARGET_NAME,
+            object(json!({"state": {"_model_name": "IntSliderModel", "value": 3}})),
+            Vec::new(),
+        );
+
+        assert_eq!(msg.header.msg_type, "comm_open");
+        assert_eq!(msg.header.session, "session-1");
+        assert!(msg.parent_header.is_none());
+
+        let parsed = roundtrip(&msg);
+        assert_eq!(parsed.header.msg_id, msg.header.msg_id);
+        match parsed.content {
+            JupyterMessageContent::CommOpen(open) => {
+                assert_eq!(open.comm_id, CommId("comm-1".to_string()));
+                assert_eq!(open.target_name, "jupyter.widget");
+                assert_eq!(open.data["state"]["value"], json!(3));
+            }
+            other => panic!("expected comm_open, got {:?}", other),
+        }
+        assert!(matches!(parsed.channel, Some(Channel::Shell)));
+    }
+
+    #[test]
+    fn test_msg_and_close_share_session() {
+        let builder = CommMessageBuilder::new("session-1");
+        let msg = builder.msg(
+            "comm-1",
+            object(json!({"method": "custom", "content": {"event": "click"}})),
+            Vec::new(),
+        );
+        let close = builder.close("comm-1", Map::new());
+
+        assert_eq!(msg.header.session, close.header.session);
+        assert_ne!(msg.header.msg_id, close.header.msg_id);
+
+        let jupyter_msg: JupyterMessage = roundtrip(&close).into();
+        assert_eq!(jupyter_msg.header.msg_type, "comm_close");
+        assert!(jupyter_msg.zmq_identities.is_empty());
+        match jupyter_msg.content {
+            JupyterMessageContent::CommClose(close) => {
+                assert_eq!(close.comm_id, CommId("comm-1".to_string()));
+            }
+            other => panic!("expected comm_close, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_widget_update_carries_buffers() {
+        let builder = CommMessageBuilder::new("session-1");
+        let msg = builder.widget_update(
+            "comm-1",
+            object(json!({"width": 2})),
+            vec![
+                (vec![json!("value")], Bytes::from_static(b"\x89PNG")),
+                (
+                    vec![json!("data"), json!(1)],
+                    Bytes::from_static(&[0, 1, 2]),
+                ),
+            ],
+        );
+
+        let envelope = serde_json::to_value(&msg).unwrap();
+        assert_eq!(envelope["buffers"], json!(["iVBORw==", "AAEC"]));
+
+        let parsed = roundtrip(&msg);
+        assert_eq!(parsed.buffers, msg.buffers);
+        assert_eq!(&parsed.buffers[0][..], b"\x89PNG");
+        assert_eq!(&parsed.buffers[1][..], &[0, 1, 2]);
+        match parsed.content {
+            JupyterMessageContent::CommMsg(comm_msg) => {
+                assert_eq!(comm_msg.data["method"], json!("update"));
+                assert_eq!(comm_msg.data["state"], json!({"width": 2}));
+                assert_eq!(
+                    comm_msg.data["buffer_paths"],
+                    json!([["value"], ["data", 1]])
+                );
+            }
+            other => panic!("expected comm_msg, got {:?}", other),
+        }
+    }
+}
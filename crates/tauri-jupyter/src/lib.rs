@@ -5,13 +5,17 @@
 //!
 //! # Features
 //!
-//! - Base64 encoding/decoding for binary buffers
+//! - Base64 encoding/decoding for binary buffers, with limits on how many
+//!   and how large
 //! - Intermediate deserialization struct for incoming messages
 //! - Serializable output struct for outgoing messages
 //! - Bidirectional conversion to/from `jupyter_protocol::JupyterMessage`
+//! - Builders for widget comm messages
 
 mod base64;
+mod comm;
 mod message;
 
-pub use base64::{deserialize_buffers, serialize_buffers};
+pub use base64::{deserialize_buffers, serialize_buffers, BufferLimitExceeded, BufferLimits};
+pub use comm::{CommMessageBuilder, WIDGET_TARGET_NAME};
 pub use message::{ConversionError, RawJupyterMessage, WebViewJupyterMessage};
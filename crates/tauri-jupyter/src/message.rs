@@ -1,11 +1,15 @@
 //! Core Jupyter message types for WebView communication.
 
+use std::cell::Cell;
+use std::fmt;
+
 use bytes::Bytes;
 use jupyter_protocol::{Channel, Header, JupyterMessage, JupyterMessageContent};
-use serde::{Deserialize, Serialize};
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 
-use crate::base64::{deserialize_buffers, serialize_buffers};
+use crate::base64::{serialize_buffers, BufferLimitExceeded, BufferLimits, DecodeBuffers};
 
 /// Error type for message conversion failures.
 #[derive(Debug, thiserror::Error)]
@@ -15,6 +19,9 @@ pub enum ConversionError {
 
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
+
+    #[error("Buffer limit exceeded: {0}")]
+    BufferLimitExceeded(#[from] BufferLimitExceeded),
 }
 
 /// Intermediate struct for deserializing incoming Jupyter messages from WebView.
@@ -23,29 +30,123 @@ pub enum ConversionError {
 /// - `content` is passed as raw JSON (parsed later based on `msg_type`)
 /// - `buffers` are base64-encoded strings
 /// - `parent_header` may be empty `{}`, `null`, or a valid header
-#[derive(Debug, Clone, Deserialize)]
+///
+/// Deserializing directly enforces [`BufferLimits::DEFAULT`]; use
+/// [`from_slice`](Self::from_slice) to choose the limits.
+#[derive(Debug, Clone)]
 pub struct RawJupyterMessage {
     pub header: Header,
-
-    #[serde(
-        default,
-        deserialize_with = "jupyter_protocol::deserialize_parent_header"
-    )]
     pub parent_header: Option<Header>,
-
-    #[serde(default)]
     pub metadata: Value,
-
     /// Raw JSON content, to be parsed based on header.msg_type
     pub content: Value,
-
-    #[serde(default, deserialize_with = "deserialize_buffers")]
     pub buffers: Vec<Bytes>,
-
-    #[serde(default)]
     pub channel: Option<Channel>,
 }
 
+impl RawJupyterMessage {
+    /// Parse a message from WebView JSON in one pass, checking each buffer
+    /// against `limits` before decoding it.
+    pub fn from_slice(json: &[u8], limits: BufferLimits) -> Result<Self, ConversionError> {
+        let exceeded = Cell::new(None);
+        let mut deserializer = serde_json::Deserializer::from_slice(json);
+        let parsed = RawMessageSeed {
+            limits,
+            exceeded: &exceeded,
+        }
+        .deserialize(&mut deserializer)
+        .and_then(|raw| deserializer.end().map(|()| raw));
+        match (parsed, exceeded.take()) {
+            (_, Some(exceeded)) => Err(exceeded.into()),
+            (parsed, None) => Ok(parsed?),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RawJupyterMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        RawMessageSeed {
+            limits: BufferLimits::DEFAULT,
+            exceeded: &Cell::new(None),
+        }
+        .deserialize(deserializer)
+    }
+}
+
+/// Parses a [`RawJupyterMessage`], decoding its buffers within `limits`.
+struct RawMessageSeed<'a> {
+    limits: BufferLimits,
+    exceeded: &'a Cell<Option<BufferLimitExceeded>>,
+}
+
+impl<'de> DeserializeSeed<'de> for RawMessageSeed<'_> {
+    type Value = RawJupyterMessage;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for RawMessageSeed<'_> {
+    type Value = RawJupyterMessage;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a Jupyter message object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut header = None;
+        let mut parent_header = None;
+        let mut metadata = None;
+        let mut content = None;
+        let mut buffers = None;
+        let mut channel = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "header" => header = Some(map.next_value()?),
+                "parent_header" => parent_header = Some(map.next_value::<ParentHeader>()?.0),
+                "metadata" => metadata = Some(map.next_value()?),
+                // Jupyter message content is always an object. Anything else
+                // would parse into an `UnknownMessage` that can't be
+                // serialized back (it flattens its content), so it's
+                // rejected here instead.
+                "content" => {
+                    content = Some(Value::Object(
+                        map.next_value::<serde_json::Map<String, Value>>()?,
+                    ))
+                }
+                "buffers" => {
+                    buffers = Some(map.next_value_seed(DecodeBuffers {
+                        limits: self.limits,
+                        exceeded: self.exceeded,
+                    })?)
+                }
+                "channel" => channel = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(RawJupyterMessage {
+            header: header.ok_or_else(|| de::Error::missing_field("header"))?,
+            parent_header: parent_header.flatten(),
+            metadata: metadata.unwrap_or_default(),
+            content: content.ok_or_else(|| de::Error::missing_field("content"))?,
+            buffers: buffers.unwrap_or_default(),
+            channel: channel.flatten(),
+        })
+    }
+}
+
+/// A `parent_header` that may be empty `{}`, `null`, or a valid header.
+struct ParentHeader(Option<Header>);
+
+impl<'de> Deserialize<'de> for ParentHeader {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        jupyter_protocol::deserialize_parent_header(deserializer).map(ParentHeader)
+    }
+}
+
 impl TryFrom<RawJupyterMessage> for JupyterMessage {
     type Error = ConversionError;
 
@@ -82,6 +183,24 @@ pub struct WebViewJupyterMessage {
     pub channel: Option<Channel>,
 }
 
+impl WebViewJupyterMessage {
+    /// Parse a message from WebView JSON in one pass, checking each buffer
+    /// against `limits` before decoding it.
+    pub fn from_slice(json: &[u8], limits: BufferLimits) -> Result<Self, ConversionError> {
+        let raw = RawJupyterMessage::from_slice(json, limits)?;
+        let content =
+            JupyterMessageContent::from_type_and_content(&raw.header.msg_type, raw.content)?;
+        Ok(WebViewJupyterMessage {
+            header: raw.header,
+            parent_header: raw.parent_header,
+            metadata: raw.metadata,
+            content,
+            buffers: raw.buffers,
+            channel: raw.channel,
+        })
+    }
+}
+
 impl From<JupyterMessage> for WebViewJupyterMessage {
     fn from(msg: JupyterMessage) -> Self {
         WebViewJupyterMessage {
@@ -240,4 +359,328 @@ mod tests {
         let wv_msg2 = WebViewJupyterMessage::from(jupyter_msg);
         assert_eq!(wv_msg2.header.msg_type, "kernel_info_request");
     }
+
+    fn comm_msg_with_buffers(buffers: &[&str]) -> String {
+        serde_json::json!({
+            "header": {
+                "date": "2025-05-14T14:32:23.490Z",
+                "msg_id": "test-id",
+                "msg_type": "comm_msg",
+                "session": "test-session",
+                "username": "",
+                "version": "5.2"
+            },
+            "parent_header": {},
+            "metadata": {},
+            "content": {"comm_id": "c", "data": {}},
+            "buffers": buffers,
+            "channel": "shell"
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_from_slice_rejects_too_many_buffers() {
+        let buffers = vec!["aGk="; BufferLimits::DEFAULT.max_buffers + 1];
+        let msg = comm_msg_with_buffers(&buffers);
+
+        let err =
+            WebViewJupyterMessage::from_slice(msg.as_bytes(), BufferLimits::DEFAULT).unwrap_err();
+        assert!(matches!(
+            err,
+            ConversionError::BufferLimitExceeded(BufferLimitExceeded::TooManyBuffers {
+                limit: 1024
+            })
+        ));
+
+        let raw = RawJupyterMessage::from_slice(msg.as_bytes(), BufferLimits::DEFAULT).unwrap_err();
+        assert!(matches!(raw, ConversionError::BufferLimitExceeded(_)));
+        assert!(serde_json::from_str::<RawJupyterMessage>(&msg).is_err());
+    }
+
+    #[test]
+    fn test_from_slice_uses_the_given_limits() {
+        let msg = comm_msg_with_buffers(&["aGk=", "aGk=", "aGk="]);
+        let tight = BufferLimits {
+            max_buffers: 2,
+            ..BufferLimits::DEFAULT
+        };
+        let err = RawJupyterMessage::from_slice(msg.as_bytes(), tight).unwrap_err();
+        assert!(matches!(
+            err,
+            ConversionError::BufferLimitExceeded(BufferLimitExceeded::TooManyBuffers { limit: 2 })
+        ));
+
+        let raw = RawJupyterMessage::from_slice(msg.as_bytes(), BufferLimits::DEFAULT).unwrap();
+        assert_eq!(raw.buffers.len(), 3);
+        assert_eq!(&raw.buffers[0][..], b"hi");
+    }
+
+    #[test]
+    fn test_from_slice_reports_malformed_json() {
+        let err =
+            RawJupyterMessage::from_slice(b"{\"header\": 1}", BufferLimits::DEFAULT).unwrap_err();
+        assert!(matches!(err, ConversionError::JsonError(_)));
+        let err = RawJupyterMessage::from_slice(b"{} trailing", BufferLimits::DEFAULT).unwrap_err();
+        assert!(matches!(err, ConversionError::JsonError(_)));
+    }
+
+    #[test]
+    fn test_non_object_content_rejected() {
+        let msg = r#"
+        {
+            "header": {
+                "date": "2025-05-14T14:32:23.490Z",
+                "msg_id": "test-id",
+                "msg_type": "x_custom",
+                "session": "test-session",
+                "username": "",
+                "version": "5.2"
+            },
+            "parent_header": null,
+            "metadata": {},
+            "content": "not an object",
+            "channel": "shell"
+        }
+        "#;
+
+        assert!(serde_json::from_str::<RawJupyterMessage>(msg).is_err());
+        assert!(serde_json::from_str::<WebViewJupyterMessage>(msg).is_err());
+    }
+
+    #[test]
+    fn test_equivalent_inputs_normalize_to_one_form() {
+        // `{}` and `null` parent headers, and absent, `null` and empty
+        // buffers, all mean the same message
+        let header = r#"{
+            "date": "2025-05-14T14:32:23.490Z",
+            "msg_id": "test-id",
+            "msg_type": "kernel_info_request",
+            "session": "test-session",
+            "username": "",
+            "version": "5.2"
+        }"#;
+        let variants = [
+            r#""parent_header": {}, "buffers": []"#,
+            r#""parent_header": null, "buffers": null"#,
+            r#""parent_header": null"#,
+        ];
+
+        let forms: Vec<Value> = variants
+            .iter()
+            .map(|fields| {
+                let msg = format!(
+                    r#"{{"header": {header}, {fields}, "metadata": {{}}, "content": {{}}, "channel": "shell"}}"#
+                );
+                let parsed: WebViewJupyterMessage = serde_json::from_str(&msg).unwrap();
+                let form = serde_json::to_value(&parsed).unwrap();
+
+                // The normalized form is a fixed point
+                let reparsed: WebViewJupyterMessage = serde_json::from_value(form.clone()).unwrap();
+                assert_eq!(serde_json::to_value(&reparsed).unwrap(), form);
+                form
+            })
+            .collect();
+
+        assert!(forms.windows(2).all(|pair| pair[0] == pair[1]));
+        assert_eq!(forms[0]["parent_header"], Value::Null);
+        assert_eq!(forms[0]["buffers"], serde_json::json!([]));
+    }
+
+    mod round_trip {
+        use super::*;
+        use jupyter_protocol::{
+            ClearOutput, CommClose, CommId, CommMsg, CommOpen, CompleteRequest, ErrorOutput,
+            ExecuteRequest, ExecuteResult, ExecutionCount, InputRequest, InterruptRequest,
+            KernelInfoRequest, MediaType, ShutdownRequest, Status, StreamContent, UnknownMessage,
+            UpdateDisplayData,
+        };
+        use proptest::prelude::*;
+
+        fn json_value() -> impl Strategy<Value = Value> {
+            let leaf = prop_oneof![
+                Just(Value::Null),
+                any::<bool>().prop_map(Value::from),
+                any::<i64>().prop_map(Value::from),
+                any::<f64>()
+                    .prop_filter("finite", |f| f.is_finite())
+                    .prop_map(Value::from),
+                ".*".prop_map(Value::from),
+            ];
+            leaf.prop_recursive(3, 16, 4, |inner| {
+                prop_oneof![
+                    prop::collection::vec(inner.clone(), 0..4).prop_map(Value::from),
+                    prop::collection::btree_map(".*", inner, 0..4)
+                        .prop_map(|map| Value::Object(map.into_iter().collect())),
+                ]
+            })
+        }
+
+        fn json_map() -> impl Strategy<Value = serde_json::Map<String, Value>> {
+            prop::collection::btree_map(".*", json_value(), 0..4)
+                .prop_map(|map| map.into_iter().collect())
+        }
+
+        fn header(msg_type: String) -> impl Strategy<Value = Header> {
+            (".*", ".*", ".*", 0i64..4_000_000_000, 0u32..1_000_000_000).prop_map(
+                move |(msg_id, username, session, secs, nanos)| Header {
+                    msg_id,
+                    username,
+                    session,
+                    date: chrono::DateTime::from_timestamp(secs, nanos).unwrap(),
+                    msg_type: msg_type.clone(),
+                    version: "5.3".to_string(),
+                },
+            )
+        }
+
+        fn content() -> impl Strategy<Value = JupyterMessageContent> {
+            prop_oneof![
+                (".*", any::<bool>()).prop_map(|(code, silent)| {
+                    let mut request = ExecuteRequest::new(code);
+                    request.silent = silent;
+                    request.into()
+                }),
+                ".*".prop_map(|text| StreamContent::stdout(&text).into()),
+                ".*".prop_map(|text| {
+                    JupyterMessageContent::DisplayData(vec![MediaType::Plain(text)].into())
+                }),
+                (any::<usize>(), ".*", json_map()).prop_map(|(count, html, metadata)| {
+                    let mut result = ExecuteResult::new(
+                        ExecutionCount::new(count),
+                        vec![MediaType::Html(html)].into(),
+                    );
+                    result.metadata = metadata;
+                    result.into()
+                }),
+                (".*", ".*").prop_map(|(text, display_id)| {
+                    UpdateDisplayData::new(vec![MediaType::Plain(text)].into(), &display_id).into()
+                }),
+                (".*", json_map()).prop_map(|(comm_id, data)| CommMsg {
+                    comm_id: CommId(comm_id),
+                    data,
+                }
+                .into()),
+                (".*", ".*", json_map(), prop::option::of(".*")).prop_map(
+                    |(comm_id, target_name, data, target_module)| CommOpen {
+                        comm_id: CommId(comm_id),
+                        target_name,
+                        data,
+                        target_module,
+                    }
+                    .into()
+                ),
+                (".*", ".*", prop::collection::vec(".*", 0..3)).prop_map(
+                    |(ename, evalue, traceback)| ErrorOutput {
+                        ename,
+                        evalue,
+                        traceback,
+                    }
+                    .into()
+                ),
+                (".*", json_map()).prop_map(|(comm_id, data)| CommClose {
+                    comm_id: CommId(comm_id),
+                    data,
+                }
+                .into()),
+                (".*", any::<usize>()).prop_map(|(code, cursor_pos)| CompleteRequest {
+                    code,
+                    cursor_pos
+                }
+                .into()),
+                (".*", any::<bool>()).prop_map(|(prompt, password)| InputRequest {
+                    prompt,
+                    password
+                }
+                .into()),
+                any::<bool>().prop_map(|restart| ShutdownRequest { restart }.into()),
+                Just(InterruptRequest {}.into()),
+                Just(Status::busy().into()),
+                Just(KernelInfoRequest {}.into()),
+                any::<bool>().prop_map(|wait| ClearOutput { wait }.into()),
+                ("x_[a-z]{1,8}", json_map()).prop_map(|(msg_type, content)| {
+                    JupyterMessageContent::UnknownMessage(UnknownMessage {
+                        msg_type,
+                        content: Value::Object(content),
+                    })
+                }),
+            ]
+        }
+
+        fn channel() -> impl Strategy<Value = Option<Channel>> {
+            prop::option::of(prop_oneof![
+                Just(Channel::Shell),
+                Just(Channel::Control),
+                Just(Channel::Stdin),
+                Just(Channel::IOPub),
+                Just(Channel::Heartbeat),
+            ])
+        }
+
+        fn message() -> impl Strategy<Value = JupyterMessage> {
+            content()
+                .prop_flat_map(|content| {
+                    let msg_type = content.message_type().to_string();
+                    (
+                        Just(content),
+                        header(msg_type.clone()),
+                        prop::option::of(header("execute_request".to_string())),
+                        json_value(),
+                        prop::collection::vec(prop::collection::vec(any::<u8>(), 0..64), 0..4),
+                        channel(),
+                    )
+                })
+                .prop_map(
+                    |(content, header, parent_header, metadata, buffers, channel)| JupyterMessage {
+                        zmq_identities: Vec::new(),
+                        header,
+                        parent_header,
+                        metadata,
+                        content,
+                        buffers: buffers.into_iter().map(Bytes::from).collect(),
+                        channel,
+                    },
+                )
+        }
+
+        /// Every field of a message, each in a comparable form of its own.
+        /// The WebView form isn't used, so a field it drops can't vanish
+        /// from both sides of a comparison.
+        #[derive(Debug, PartialEq)]
+        struct Snapshot {
+            zmq_identities: Vec<Bytes>,
+            header: Value,
+            parent_header: Value,
+            metadata: Value,
+            msg_type: String,
+            content: Value,
+            buffers: Vec<Bytes>,
+            channel: Value,
+        }
+
+        fn snapshot(message: &JupyterMessage) -> Snapshot {
+            Snapshot {
+                zmq_identities: message.zmq_identities.clone(),
+                header: serde_json::to_value(&message.header).unwrap(),
+                parent_header: serde_json::to_value(&message.parent_header).unwrap(),
+                metadata: message.metadata.clone(),
+                msg_type: message.content.message_type().to_string(),
+                content: serde_json::to_value(&message.content).unwrap(),
+                buffers: message.buffers.clone(),
+                channel: serde_json::to_value(&message.channel).unwrap(),
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn test_round_trip_through_webview_json(message in message()) {
+                let json = serde_json::to_vec(&WebViewJupyterMessage::from(message.clone())).unwrap();
+                let back: JupyterMessage = WebViewJupyterMessage::from_slice(&json, BufferLimits::DEFAULT).unwrap().into();
+                prop_assert_eq!(snapshot(&back), snapshot(&message));
+
+                let raw: JupyterMessage = RawJupyterMessage::from_slice(&json, BufferLimits::DEFAULT).unwrap().try_into().unwrap();
+                prop_assert_eq!(snapshot(&raw), snapshot(&message));
+            }
+        }
+    }
 }
@@ -384,3 +384,50 @@ export function isManagedEnv(path) {
     isUvManagedEnv(path) || isCondaManagedEnv(path) || isSystemPythonEnv(path)
   );
 }
+
+/**
+ * URL on the WebDriver server the session is connected to.
+ */
+function webdriverUrl(path) {
+  const { protocol = "http", hostname, port } = browser.options;
+  return `${protocol}://${hostname}:${port}${path}`;
+}
+
+/**
+ * Execute a cell by ID and wait for the kernel to finish it.
+ * Uses the built-in WebDriver server's runt extension command, so it only
+ * works in native mode. Returns `{ cellId, executionCount, outputs }`,
+ * where outputs are the cell's output manifests.
+ */
+export async function executeCellAndWait(cellId, timeout = 60000) {
+  const response = await fetch(
+    webdriverUrl(
+      `/session/${browser.sessionId}/runt/cell/${encodeURIComponent(cellId)}/execute`,
+    ),
+    {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({ timeout }),
+    },
+  );
+  const body = await response.json();
+  if (!response.ok) {
+    const reason = body.value?.message ?? response.status;
+    throw new Error(`executeCellAndWait(${cellId}) failed: ${reason}`);
+  }
+  return body.value;
+}
+
+/**
+ * Whether the session is served by the app's built-in WebDriver server
+ * (native mode) rather than tauri-driver, which lacks runt extensions.
+ */
+export async function hasRuntWebDriver() {
+  try {
+    const response = await fetch(webdriverUrl("/status"));
+    const body = await response.json();
+    return body.value?.message === "runt webdriver server";
+  } catch {
+    return false;
+  }
+}
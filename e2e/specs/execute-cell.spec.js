@@ -0,0 +1,48 @@
+/**
+ * E2E Test: executeCellAndWait
+ *
+ * Self-test for the WebDriver server's runt extension command that runs a
+ * cell and resolves once the kernel is idle, returning its outputs.
+ * Skipped under tauri-driver (Docker mode), which doesn't serve it.
+ */
+
+import { browser } from "@wdio/globals";
+import {
+  executeCellAndWait,
+  hasRuntWebDriver,
+  setupCodeCell,
+  typeSlowly,
+  waitForKernelReady,
+} from "../helpers.js";
+
+describe("executeCellAndWait", () => {
+  before(async function () {
+    if (!(await hasRuntWebDriver())) {
+      this.skip();
+    }
+    await waitForKernelReady(90000);
+  });
+
+  it("should run a cell and return its outputs", async () => {
+    const cell = await setupCodeCell();
+    await typeSlowly("print('ran via webdriver')\n6 * 7");
+    // Let the edit sync to the daemon before it reads the source
+    await browser.pause(500);
+
+    const cellId = await cell.getAttribute("data-cell-id");
+    const result = await executeCellAndWait(cellId, 30000);
+
+    expect(result.cellId).toBe(cellId);
+    expect(typeof result.executionCount).toBe("number");
+    const types = result.outputs.map((output) => output.output_type);
+    expect(types).toEqual(["stream", "execute_result"]);
+    expect(result.outputs[0].text.inline).toContain("ran via webdriver");
+    expect(result.outputs[1].data["text/plain"].inline).toBe("42");
+  });
+
+  it("should fail for a cell that doesn't exist", async () => {
+    await expect(executeCellAndWait("no-such-cell", 5000)).rejects.toThrow(
+      "no cell with id no-such-cell",
+    );
+  });
+});
@@ -1,6 +1,15 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
 /**
- * Default packages for conda environments.
+ * Default packages and channels for conda environments.
  */
-export type CondaDefaults = { default_packages: Array<string>, };
+export type CondaDefaults = { default_packages: Array<string>, 
+/**
+ * Channels for notebooks that don't list their own, highest priority
+ * first; empty uses conda-forge
+ */
+channels: Array<string>, 
+/**
+ * Take each package only from the first channel that has it
+ */
+strict_channel_priority: boolean, };
@@ -0,0 +1,17 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Kernel startup settings.
+ *
+ * `timeout_secs` is clamped to `MIN_TIMEOUT_SECS..=MAX_TIMEOUT_SECS` when read.
+ */
+export type KernelStartupSettings = { 
+/**
+ * Seconds to wait for a launched kernel to answer before giving up
+ */
+timeout_secs: number, 
+/**
+ * Launch once more if the first attempt times out or the kernel exits
+ * during startup
+ */
+retry: boolean, };
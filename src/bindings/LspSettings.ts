@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Language server settings for static completions and hover.
+ */
+export type LspSettings = { 
+/**
+ * Merge completions from a Python language server with kernel completions
+ */
+enabled: boolean, };
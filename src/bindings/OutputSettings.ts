@@ -0,0 +1,33 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Output rendering settings.
+ */
+export type OutputSettings = { 
+/**
+ * MIME types in order of preference when choosing an output's primary
+ * representation (a trailing `*` matches by prefix); empty uses the
+ * built-in order
+ */
+mime_priority: Array<string>, 
+/**
+ * Render HTML outputs of untrusted notebooks, with scripts stripped
+ * unless `allow_javascript` is also set. Trusted notebooks always
+ * render HTML.
+ */
+allow_html: boolean, 
+/**
+ * Keep scripts and JavaScript outputs of untrusted notebooks. Trusted
+ * notebooks always run them.
+ */
+allow_javascript: boolean, 
+/**
+ * MIME types (a trailing `*` matches by prefix) whose outputs open in
+ * the sidecar viewer, leaving a placeholder in the notebook
+ */
+sidecar_mime: Array<string>, 
+/**
+ * Outputs at least this many bytes are held back behind a placeholder
+ * and rendered in chunks when shown; 0 renders everything immediately
+ */
+large_output_bytes: number, };
@@ -1,8 +1,13 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { CondaDefaults } from "./CondaDefaults";
+import type { KernelStartupSettings } from "./KernelStartupSettings";
+import type { LspSettings } from "./LspSettings";
+import type { OutputSettings } from "./OutputSettings";
 import type { PythonEnvType } from "./PythonEnvType";
 import type { Runtime } from "./Runtime";
+import type { TemplateCell } from "./TemplateCell";
 import type { ThemeMode } from "./ThemeMode";
+import type { ToolSettings } from "./ToolSettings";
 import type { UvDefaults } from "./UvDefaults";
 
 /**
@@ -28,4 +33,58 @@ uv: UvDefaults,
 /**
  * Conda environment defaults
  */
-conda: CondaDefaults, };
+conda: CondaDefaults, 
+/**
+ * Parse ANSI escape codes in stream and error outputs into styled span
+ * runs stored alongside the raw text in output manifests
+ */
+ansi_spans: boolean, 
+/**
+ * Keep the daemon kernel running after the last window on a notebook
+ * closes, so reopening the notebook picks it back up
+ */
+keep_kernel_on_close: boolean, 
+/**
+ * When `keep_kernel_on_close` is on, also keep kernels running when the
+ * app quits (otherwise quitting shuts them down)
+ */
+keep_kernel_on_exit: boolean, 
+/**
+ * Append each finished cell execution to a `.ipynb.runlog.jsonl` file
+ * next to the notebook
+ */
+run_log: boolean, 
+/**
+ * Refuse to save a notebook that fails nbformat validation
+ */
+block_invalid_save: boolean, 
+/**
+ * Don't offer to install the package behind a failed import
+ */
+hide_install_suggestions: boolean, 
+/**
+ * Language server proxy settings
+ */
+lsp: LspSettings, 
+/**
+ * Kernel startup timeout and retry
+ */
+kernel_startup: KernelStartupSettings, 
+/**
+ * Where uv, ruff and deno come from
+ */
+tools: ToolSettings, 
+/**
+ * Menu accelerator overrides keyed by menu item id
+ * (e.g. `"save": "CmdOrCtrl+Shift+S"`); an empty string removes the shortcut
+ */
+keybindings: { [key in string]: string }, 
+/**
+ * Output rendering settings
+ */
+outputs: OutputSettings, 
+/**
+ * Starting cells for new notebooks keyed by runtime (e.g. `"python"`,
+ * `"deno"`); a runtime without a template starts with one empty code cell
+ */
+new_notebook_template: { [key in string]: Array<TemplateCell> }, };
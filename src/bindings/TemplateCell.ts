@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TemplateCellType } from "./TemplateCellType";
+
+/**
+ * A starting cell in a new-notebook template.
+ */
+export type TemplateCell = { cell_type: TemplateCellType, source: string, };
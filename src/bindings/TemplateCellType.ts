@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The type of a cell in a new-notebook template.
+ */
+export type TemplateCellType = "code" | "markdown" | "raw";
@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Where the `uv`, `ruff` and `deno` binaries come from.
+ */
+export type ToolSettings = { 
+/**
+ * Path to a uv binary to use; empty to search PATH or bootstrap
+ */
+uv_path: string, 
+/**
+ * Path to a ruff binary to use; empty to search PATH or bootstrap
+ */
+ruff_path: string, 
+/**
+ * Path to a deno binary to use; empty to search PATH or bootstrap
+ */
+deno_path: string, 
+/**
+ * Use binaries found on PATH before bootstrapping from conda-forge
+ */
+prefer_system: boolean, };
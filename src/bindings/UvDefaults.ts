@@ -1,6 +1,10 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
 /**
- * Default packages for uv environments.
+ * Default packages and package index for uv environments.
  */
-export type UvDefaults = { default_packages: Array<string>, };
+export type UvDefaults = { default_packages: Array<string>, 
+/**
+ * Package index to install from instead of PyPI; empty uses PyPI
+ */
+default_index_url: string, };
@@ -1,8 +1,14 @@
 // Generated by ts-rs from Rust struct definitions. Re-run `cargo test` to regenerate.
 
 export type { CondaDefaults } from "./CondaDefaults";
+export type { KernelStartupSettings } from "./KernelStartupSettings";
+export type { LspSettings } from "./LspSettings";
+export type { OutputSettings } from "./OutputSettings";
 export type { PythonEnvType } from "./PythonEnvType";
 export type { Runtime } from "./Runtime";
 export type { SyncedSettings } from "./SyncedSettings";
+export type { TemplateCell } from "./TemplateCell";
+export type { TemplateCellType } from "./TemplateCellType";
 export type { ThemeMode } from "./ThemeMode";
+export type { ToolSettings } from "./ToolSettings";
 export type { UvDefaults } from "./UvDefaults";
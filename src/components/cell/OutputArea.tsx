@@ -1,9 +1,10 @@
-import { ChevronDown, ChevronRight } from "lucide-react";
+import { ChevronDown, ChevronRight, ExternalLink, Eye } from "lucide-react";
 import {
   type ReactNode,
   useCallback,
   useEffect,
   useId,
+  useMemo,
   useRef,
   useState,
 } from "react";
@@ -17,10 +18,16 @@ import {
   AnsiErrorOutput,
   AnsiStreamOutput,
 } from "@/components/outputs/ansi-output";
+import {
+  formatSize,
+  isLargeOutput,
+  outputSize,
+} from "@/components/outputs/large-output";
 import {
   DEFAULT_PRIORITY,
   MediaRouter,
 } from "@/components/outputs/media-router";
+import { routesToSidecar } from "@/components/outputs/sidecar-routing";
 import { useWidgetStore } from "@/components/widgets/widget-store-context";
 import { isDarkMode as detectDarkMode } from "@/lib/dark-mode";
 import { ErrorBoundary } from "@/lib/error-boundary";
@@ -108,6 +115,31 @@ interface OutputAreaProps {
    * @deprecated Use the comm bridge instead for full widget support
    */
   onWidgetUpdate?: (commId: string, state: Record<string, unknown>) => void;
+  /**
+   * MIME types (a trailing `*` matches by prefix) whose outputs belong in
+   * the sidecar viewer. They are replaced by a placeholder linking to it.
+   * Only pass it when the outputs were produced while a sidecar was
+   * attached; the sidecar never sees outputs from before it opened.
+   * Has no effect without `onOpenSidecar`.
+   */
+  sidecarMime?: readonly string[];
+  /** Open the sidecar viewer, from the placeholder link. */
+  onOpenSidecar?: () => void;
+  /**
+   * Indices into `outputs` of individually collapsed outputs. They are left
+   * out and listed with a button to expand them.
+   */
+  collapsedOutputs?: readonly number[];
+  /**
+   * Collapse or expand a single output, by its index in `outputs`.
+   */
+  onToggleOutputCollapse?: (index: number) => void;
+  /**
+   * Outputs at least this many bytes are held back behind a placeholder
+   * until asked for, so a huge repr doesn't stall the page. 0 or unset
+   * renders everything.
+   */
+  largeOutputBytes?: number;
 }
 
 /**
@@ -269,7 +301,7 @@ function renderOutput(
  * ```
  */
 export function OutputArea({
-  outputs,
+  outputs: allOutputs,
   collapsed = false,
   onToggleCollapse,
   maxHeight,
@@ -280,6 +312,11 @@ export function OutputArea({
   preloadIframe = false,
   onLinkClick,
   onWidgetUpdate,
+  sidecarMime,
+  onOpenSidecar,
+  collapsedOutputs,
+  onToggleOutputCollapse,
+  largeOutputBytes = 0,
 }: OutputAreaProps) {
   const id = useId();
   const frameRef = useRef<IsolatedFrameHandle>(null);
@@ -305,6 +342,72 @@ export function OutputArea({
   // Get widget store context (may be null if not in provider)
   const widgetContext = useWidgetStore();
 
+  // Hold back outputs routed to the sidecar unless asked to show them here
+  const [showSidecarInline, setShowSidecarInline] = useState(false);
+  const sidecarCount = useMemo(
+    () =>
+      onOpenSidecar && sidecarMime?.length
+        ? allOutputs.filter((output) => routesToSidecar(output, sidecarMime))
+            .length
+        : 0,
+    [allOutputs, sidecarMime, onOpenSidecar],
+  );
+  const hasSidecarOutputs = sidecarCount > 0;
+  const hideSidecarOutputs = hasSidecarOutputs && !showSidecarInline;
+
+  // Hold back large outputs until the user asks to render them
+  const [renderedLarge, setRenderedLarge] = useState<readonly number[]>([]);
+  useEffect(() => {
+    if (allOutputs.length === 0) {
+      setRenderedLarge([]);
+    }
+  }, [allOutputs.length]);
+  const deferredOutputs = useMemo(
+    () =>
+      allOutputs
+        .map((output, index) => ({ output, index }))
+        .filter(
+          ({ output, index }) =>
+            !renderedLarge.includes(index) &&
+            !collapsedOutputs?.includes(index) &&
+            isLargeOutput(output, largeOutputBytes),
+        )
+        .map(({ output, index }) => ({ index, size: outputSize(output) })),
+    [allOutputs, collapsedOutputs, largeOutputBytes, renderedLarge],
+  );
+  // Outputs rendered here, with their indices in `allOutputs` so per-output
+  // collapse addresses the same output in every window
+  const shownOutputs = useMemo(
+    () =>
+      allOutputs
+        .map((output, index) => ({ output, index }))
+        .filter(
+          ({ output, index }) =>
+            !collapsedOutputs?.includes(index) &&
+            !deferredOutputs.some((deferred) => deferred.index === index) &&
+            !(
+              hideSidecarOutputs &&
+              sidecarMime &&
+              routesToSidecar(output, sidecarMime)
+            ),
+        ),
+    [
+      allOutputs,
+      collapsedOutputs,
+      deferredOutputs,
+      hideSidecarOutputs,
+      sidecarMime,
+    ],
+  );
+  const outputs = useMemo(
+    () => shownOutputs.map(({ output }) => output),
+    [shownOutputs],
+  );
+  const collapsedIndices = useMemo(
+    () => collapsedOutputs?.filter((index) => index < allOutputs.length) ?? [],
+    [collapsedOutputs, allOutputs.length],
+  );
+
   // Determine if we should use isolation (when we have outputs)
   const shouldIsolate =
     outputs.length > 0 &&
@@ -320,7 +423,7 @@ export function OutputArea({
   const shouldUseBridge = shouldIsolate && hasWidgets && widgetContext !== null;
 
   const hasCollapseControl = onToggleCollapse !== undefined;
-  const outputCount = outputs.length;
+  const outputCount = allOutputs.length;
 
   // Handle messages from iframe, routing widget messages to comm bridge
   const handleIframeMessage = useCallback(
@@ -420,12 +523,12 @@ export function OutputArea({
   }, [handleFrameReady]);
 
   // Empty state: render nothing (unless preloading iframe)
-  if (outputs.length === 0 && !showPreloadedIframe) {
+  if (allOutputs.length === 0 && !showPreloadedIframe) {
     return null;
   }
 
   // Hide the entire output area when only preloading (no visible outputs)
-  const isPreloadOnly = showPreloadedIframe && outputs.length === 0;
+  const isPreloadOnly = showPreloadedIframe && allOutputs.length === 0;
 
   return (
     <div
@@ -482,12 +585,23 @@ export function OutputArea({
 
           {/* In-DOM outputs (when not using isolation) */}
           {!shouldIsolate &&
-            outputs.map((output, index) => (
+            shownOutputs.map(({ output, index }) => (
               <div
                 key={`output-${index}`}
                 data-slot="output-item"
                 data-output-index={index}
+                className={cn(onToggleOutputCollapse && "group relative")}
               >
+                {onToggleOutputCollapse && (
+                  <button
+                    type="button"
+                    onClick={() => onToggleOutputCollapse(index)}
+                    className="absolute right-1 top-1 z-10 hidden text-muted-foreground hover:text-foreground group-hover:inline-flex"
+                    aria-label="Collapse output"
+                  >
+                    <ChevronDown className="h-3 w-3" />
+                  </button>
+                )}
                 <ErrorBoundary
                   resetKeys={[JSON.stringify(output)]}
                   fallback={(error, reset) => (
@@ -509,6 +623,77 @@ export function OutputArea({
                 </ErrorBoundary>
               </div>
             ))}
+
+          {/* Individually collapsed outputs */}
+          {collapsedIndices.length > 0 && (
+            <div
+              data-slot="collapsed-outputs"
+              className="flex flex-wrap items-center gap-2 px-2 py-1 text-xs text-muted-foreground"
+            >
+              {collapsedIndices.map((index) => (
+                <button
+                  key={index}
+                  type="button"
+                  onClick={() => onToggleOutputCollapse?.(index)}
+                  className="inline-flex items-center gap-1 hover:text-foreground transition-colors"
+                  aria-expanded={false}
+                >
+                  <ChevronRight className="h-3 w-3" />
+                  Output {index + 1} collapsed
+                </button>
+              ))}
+            </div>
+          )}
+
+          {/* Large outputs held back until asked for */}
+          {deferredOutputs.length > 0 && (
+            <div
+              data-slot="large-outputs"
+              className="flex flex-wrap items-center gap-2 px-2 py-1 text-xs text-muted-foreground"
+            >
+              {deferredOutputs.map(({ index, size }) => (
+                <button
+                  key={index}
+                  type="button"
+                  onClick={() =>
+                    setRenderedLarge((rendered) => [...rendered, index])
+                  }
+                  className="inline-flex items-center gap-1 hover:text-foreground transition-colors"
+                >
+                  <Eye className="h-3 w-3" />
+                  Render large output {index + 1} ({formatSize(size)})
+                </button>
+              ))}
+            </div>
+          )}
+
+          {/* Placeholder for outputs shown in the sidecar viewer */}
+          {hasSidecarOutputs && (
+            <div
+              data-slot="sidecar-placeholder"
+              className="flex items-center gap-2 px-2 py-1 text-xs text-muted-foreground"
+            >
+              <span>
+                {sidecarCount} output{sidecarCount > 1 ? "s" : ""}{" "}
+                {hideSidecarOutputs ? "in" : "also in"} the sidecar viewer
+              </span>
+              <button
+                type="button"
+                onClick={() => onOpenSidecar?.()}
+                className="inline-flex items-center gap-1 hover:text-foreground transition-colors"
+              >
+                <ExternalLink className="h-3 w-3" />
+                Open sidecar
+              </button>
+              <button
+                type="button"
+                onClick={() => setShowSidecarInline((shown) => !shown)}
+                className="hover:text-foreground transition-colors"
+              >
+                {hideSidecarOutputs ? "Show here" : "Hide here"}
+              </button>
+            </div>
+          )}
         </div>
       )}
     </div>
@@ -0,0 +1,131 @@
+/**
+ * Tests for large-output.ts - which outputs the `outputs.large_output_bytes`
+ * setting holds back, and chunked appending of huge HTML.
+ */
+
+import { describe, expect, it } from "vitest";
+import type { JupyterOutput } from "@/components/cell/OutputArea";
+import {
+  appendInChunks,
+  formatSize,
+  isLargeOutput,
+  outputSize,
+} from "../large-output";
+
+function tableHtml(rows: number): string {
+  const body = Array.from(
+    { length: rows },
+    (_, i) => `<tr><td>${i}</td></tr>`,
+  ).join("");
+  return `<table><tbody>${body}</tbody></table><p id="after">end</p>`;
+}
+
+function fragmentOf(html: string): DocumentFragment {
+  return document.createRange().createContextualFragment(html);
+}
+
+describe("isLargeOutput", () => {
+  it("measures every representation, whatever its MIME type", () => {
+    const output: JupyterOutput = {
+      output_type: "display_data",
+      data: {
+        "text/plain": "Custom()",
+        "application/x-custom-repr": "x".repeat(2000),
+        "application/vnd.custom+json": { rows: [1, 2, 3] },
+      },
+    };
+    expect(outputSize(output)).toBe(
+      8 + 2000 + JSON.stringify({ rows: [1, 2, 3] }).length,
+    );
+    expect(isLargeOutput(output, 2000)).toBe(true);
+    expect(isLargeOutput(output, 4000)).toBe(false);
+  });
+
+  it("measures streams and tracebacks", () => {
+    const stream: JupyterOutput = {
+      output_type: "stream",
+      name: "stdout",
+      text: ["a".repeat(600), "b".repeat(600)],
+    };
+    const error: JupyterOutput = {
+      output_type: "error",
+      ename: "ValueError",
+      evalue: "bad",
+      traceback: ["c".repeat(100)],
+    };
+    expect(isLargeOutput(stream, 1000)).toBe(true);
+    expect(isLargeOutput(error, 1000)).toBe(false);
+  });
+
+  it("never defers with a threshold of 0", () => {
+    const output: JupyterOutput = {
+      output_type: "display_data",
+      data: { "text/html": tableHtml(1000) },
+    };
+    expect(isLargeOutput(output, 0)).toBe(false);
+  });
+});
+
+describe("formatSize", () => {
+  it("picks a readable unit", () => {
+    expect(formatSize(512)).toBe("512 B");
+    expect(formatSize(2048)).toBe("2.0 KB");
+    expect(formatSize(3.5 * 1024 * 1024)).toBe("3.5 MB");
+  });
+});
+
+describe("appendInChunks", () => {
+  it("renders a huge table over several frames, in order", () => {
+    const target = document.createElement("div");
+    const frames: Array<() => void> = [];
+
+    appendInChunks(target, fragmentOf(tableHtml(2000)), 500, (step) =>
+      frames.push(step),
+    );
+
+    // Only the first chunk is in the document before the next frame
+    const rows = () => target.querySelectorAll("tr").length;
+    expect(rows()).toBeGreaterThan(0);
+    expect(rows()).toBeLessThan(2000);
+    expect(target.querySelector("#after")).toBeNull();
+
+    let count = 0;
+    while (frames.length > 0) {
+      frames.shift()?.();
+      count++;
+    }
+    expect(count).toBeGreaterThanOrEqual(3);
+    expect(rows()).toBe(2000);
+    expect(target.innerHTML).toBe(tableHtml(2000));
+  });
+
+  it("appends small content in one go", () => {
+    const target = document.createElement("div");
+    const frames: Array<() => void> = [];
+
+    appendInChunks(target, fragmentOf(tableHtml(10)), 500, (step) =>
+      frames.push(step),
+    );
+
+    expect(frames).toHaveLength(0);
+    expect(target.innerHTML).toBe(tableHtml(10));
+  });
+
+  it("stops when cancelled", () => {
+    const target = document.createElement("div");
+    const frames: Array<() => void> = [];
+
+    const cancel = appendInChunks(
+      target,
+      fragmentOf(tableHtml(2000)),
+      500,
+      (step) => frames.push(step),
+    );
+    const before = target.querySelectorAll("tr").length;
+    cancel();
+    while (frames.length > 0) {
+      frames.shift()?.();
+    }
+    expect(target.querySelectorAll("tr").length).toBe(before);
+  });
+});
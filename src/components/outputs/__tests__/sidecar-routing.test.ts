@@ -0,0 +1,90 @@
+/**
+ * Tests for sidecar-routing.ts - which outputs the `outputs.sidecar_mime`
+ * setting sends to the sidecar viewer.
+ */
+
+import { describe, expect, it } from "vitest";
+import type { JupyterOutput } from "@/components/cell/OutputArea";
+import {
+  mimeMatches,
+  routesToSidecar,
+  sidecarCellsAfterRun,
+} from "../sidecar-routing";
+
+const plotly: JupyterOutput = {
+  output_type: "display_data",
+  data: {
+    "application/vnd.plotly.v1+json": { data: [] },
+    "text/html": "<div>plot</div>",
+    "text/plain": "Figure()",
+  },
+};
+
+describe("mimeMatches", () => {
+  it("matches exact MIME types", () => {
+    expect(mimeMatches("text/html", "text/html")).toBe(true);
+    expect(mimeMatches("text/html", "text/plain")).toBe(false);
+  });
+
+  it("matches by prefix with a trailing *", () => {
+    expect(
+      mimeMatches("application/vnd.vegalite.v5+json", "application/vnd.vega*"),
+    ).toBe(true);
+    expect(mimeMatches("application/json", "application/vnd.*")).toBe(false);
+  });
+});
+
+describe("routesToSidecar", () => {
+  it("routes outputs with a listed MIME type to the sidecar", () => {
+    expect(routesToSidecar(plotly, ["application/vnd.plotly.v1+json"])).toBe(
+      true,
+    );
+    expect(routesToSidecar(plotly, ["application/vnd.*"])).toBe(true);
+    expect(
+      routesToSidecar(
+        { ...plotly, output_type: "execute_result", execution_count: 1 },
+        ["application/vnd.plotly.v1+json"],
+      ),
+    ).toBe(true);
+  });
+
+  it("keeps other outputs inline", () => {
+    expect(routesToSidecar(plotly, [])).toBe(false);
+    expect(routesToSidecar(plotly, ["image/png"])).toBe(false);
+    expect(
+      routesToSidecar(
+        { output_type: "display_data", data: { "image/png": null } },
+        ["image/png"],
+      ),
+    ).toBe(false);
+  });
+
+  it("never routes streams or errors", () => {
+    expect(
+      routesToSidecar(
+        { output_type: "stream", name: "stdout", text: "hi" },
+        ["*"],
+      ),
+    ).toBe(false);
+    expect(
+      routesToSidecar(
+        { output_type: "error", ename: "E", evalue: "", traceback: [] },
+        ["*"],
+      ),
+    ).toBe(false);
+  });
+});
+
+describe("sidecarCellsAfterRun", () => {
+  it("routes cells run while a sidecar is attached", () => {
+    const cells = sidecarCellsAfterRun(new Set(["a"]), ["b", "c"], true);
+    expect([...cells].sort()).toEqual(["a", "b", "c"]);
+  });
+
+  it("renders cells inline again when run without a sidecar", () => {
+    const before = new Set(["a", "b"]);
+    const cells = sidecarCellsAfterRun(before, ["b"], false);
+    expect([...cells]).toEqual(["a"]);
+    expect([...before].sort()).toEqual(["a", "b"]);
+  });
+});
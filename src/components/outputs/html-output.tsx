@@ -1,5 +1,6 @@
 import { useEffect, useRef } from "react";
 import { cn } from "@/lib/utils";
+import { appendInChunks, CHUNKED_HTML_LENGTH } from "./large-output";
 
 interface HtmlOutputProps {
   /**
@@ -55,6 +56,11 @@ export function HtmlOutput({ content, className = "" }: HtmlOutputProps) {
     const range = document.createRange();
     const fragment = range.createContextualFragment(content);
     ref.current.innerHTML = "";
+    // Huge HTML (e.g. a giant DataFrame repr) is laid out a chunk at a
+    // time so the frame stays responsive
+    if (content.length >= CHUNKED_HTML_LENGTH) {
+      return appendInChunks(ref.current, fragment);
+    }
     ref.current.appendChild(fragment);
   }, [content]);
 
@@ -0,0 +1,132 @@
+import type { JupyterOutput } from "@/components/cell/OutputArea";
+
+/**
+ * Default for the `outputs.large_output_bytes` setting, used where synced
+ * settings aren't available (the sidecar viewer).
+ */
+export const DEFAULT_LARGE_OUTPUT_BYTES = 1024 * 1024;
+
+/** HTML at least this long is appended in chunks rather than all at once. */
+export const CHUNKED_HTML_LENGTH = 256 * 1024;
+
+/** Nodes appended per animation frame by `appendInChunks`. */
+export const NODES_PER_CHUNK = 500;
+
+function valueSize(value: unknown): number {
+  if (value == null) return 0;
+  if (typeof value === "string") return value.length;
+  try {
+    return JSON.stringify(value)?.length ?? 0;
+  } catch {
+    return 0;
+  }
+}
+
+/**
+ * Approximate size of an output's payload: every representation of a
+ * display or execute result (whatever its MIME type), a stream's text, or
+ * an error's traceback. Measured in UTF-16 code units, which is close
+ * enough to bytes to compare against the threshold.
+ */
+export function outputSize(output: JupyterOutput): number {
+  switch (output.output_type) {
+    case "execute_result":
+    case "display_data":
+      return Object.values(output.data).reduce<number>(
+        (total, value) => total + valueSize(value),
+        0,
+      );
+    case "stream":
+      return Array.isArray(output.text)
+        ? output.text.reduce((total, line) => total + line.length, 0)
+        : output.text.length;
+    case "error":
+      return output.traceback.reduce((total, line) => total + line.length, 0);
+  }
+}
+
+/**
+ * Whether an output is large enough to hold back behind a placeholder.
+ * A threshold of 0 never defers.
+ */
+export function isLargeOutput(
+  output: JupyterOutput,
+  threshold: number,
+): boolean {
+  return threshold > 0 && outputSize(output) >= threshold;
+}
+
+/** Human-readable size, e.g. "2.4 MB". */
+export function formatSize(size: number): string {
+  if (size < 1024) return `${size} B`;
+  if (size < 1024 * 1024) return `${(size / 1024).toFixed(1)} KB`;
+  return `${(size / (1024 * 1024)).toFixed(1)} MB`;
+}
+
+function hasManyDescendants(node: Node, limit: number): boolean {
+  return (
+    node.nodeType === Node.ELEMENT_NODE &&
+    (node as Element).getElementsByTagName("*").length > limit
+  );
+}
+
+interface PendingNodes {
+  parent: Node;
+  nodes: Node[];
+  next: number;
+}
+
+/**
+ * Move the contents of `fragment` into `target` a chunk of nodes per
+ * animation frame, so a huge document (a giant DataFrame table) doesn't
+ * block the page while it's laid out.
+ *
+ * Elements with more than `nodesPerChunk` descendants (a `<table>` with
+ * many rows) are appended empty and filled over later frames. Nodes land in
+ * document order, so scripts still run after the markup before them.
+ * The first chunk is appended synchronously. Returns a function that
+ * stops appending.
+ */
+export function appendInChunks(
+  target: Node,
+  fragment: DocumentFragment,
+  nodesPerChunk = NODES_PER_CHUNK,
+  schedule: (step: () => void) => void = (step) => {
+    requestAnimationFrame(step);
+  },
+): () => void {
+  const stack: PendingNodes[] = [
+    { parent: target, nodes: Array.from(fragment.childNodes), next: 0 },
+  ];
+  let cancelled = false;
+
+  const step = () => {
+    if (cancelled) return;
+    let budget = nodesPerChunk;
+    while (budget > 0 && stack.length > 0) {
+      const pending = stack[stack.length - 1];
+      if (pending.next >= pending.nodes.length) {
+        stack.pop();
+        continue;
+      }
+      const node = pending.nodes[pending.next++];
+      if (hasManyDescendants(node, nodesPerChunk)) {
+        const children = Array.from(node.childNodes);
+        for (const child of children) {
+          node.removeChild(child);
+        }
+        stack.push({ parent: node, nodes: children, next: 0 });
+      }
+      pending.parent.appendChild(node);
+      budget--;
+    }
+    if (stack.length > 0) {
+      schedule(step);
+    }
+  };
+
+  step();
+  return () => {
+    cancelled = true;
+  };
+}
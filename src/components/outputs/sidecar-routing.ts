@@ -0,0 +1,55 @@
+import type { JupyterOutput } from "@/components/cell/OutputArea";
+
+/**
+ * Check whether a MIME type matches a pattern from the
+ * `outputs.sidecar_mime` setting. A trailing `*` matches by prefix.
+ */
+export function mimeMatches(mimeType: string, pattern: string): boolean {
+  if (pattern.endsWith("*")) {
+    return mimeType.startsWith(pattern.slice(0, -1));
+  }
+  return mimeType === pattern;
+}
+
+/**
+ * Whether an output should open in the sidecar viewer instead of rendering
+ * inline: a display or execute result with any representation matching
+ * `sidecarMime`. Streams and errors always render inline.
+ */
+export function routesToSidecar(
+  output: JupyterOutput,
+  sidecarMime: readonly string[],
+): boolean {
+  if (
+    output.output_type !== "execute_result" &&
+    output.output_type !== "display_data"
+  ) {
+    return false;
+  }
+  return Object.keys(output.data).some(
+    (mimeType) =>
+      output.data[mimeType] != null &&
+      sidecarMime.some((pattern) => mimeMatches(mimeType, pattern)),
+  );
+}
+
+/**
+ * The cells whose routed outputs go to the sidecar once `cellIds` run. A
+ * sidecar only sees outputs produced after it opened, so the cells are added
+ * when one is attached for the run and dropped (rendering inline) otherwise.
+ */
+export function sidecarCellsAfterRun(
+  cells: ReadonlySet<string>,
+  cellIds: readonly string[],
+  attached: boolean,
+): ReadonlySet<string> {
+  const next = new Set(cells);
+  for (const cellId of cellIds) {
+    if (attached) {
+      next.add(cellId);
+    } else {
+      next.delete(cellId);
+    }
+  }
+  return next;
+}
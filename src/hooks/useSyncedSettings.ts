@@ -9,6 +9,7 @@ import type {
   SyncedSettings,
   ThemeMode,
 } from "@/bindings";
+import { DEFAULT_LARGE_OUTPUT_BYTES } from "@/components/outputs/large-output";
 
 // Re-export generated types so consumers can import from this module.
 export type { ThemeMode, Runtime, PythonEnvType };
@@ -102,6 +103,10 @@ export function useSyncedSettings() {
   const [defaultCondaPackages, setDefaultCondaPackagesState] = useState<
     string[]
   >([]);
+  const [sidecarMime, setSidecarMime] = useState<string[]>([]);
+  const [largeOutputBytes, setLargeOutputBytes] = useState(
+    DEFAULT_LARGE_OUTPUT_BYTES,
+  );
 
   // Load initial settings from daemon
   useEffect(() => {
@@ -123,6 +128,12 @@ export function useSyncedSettings() {
         if (Array.isArray(settings.conda?.default_packages)) {
           setDefaultCondaPackagesState(settings.conda.default_packages);
         }
+        if (Array.isArray(settings.outputs?.sidecar_mime)) {
+          setSidecarMime(settings.outputs.sidecar_mime);
+        }
+        if (typeof settings.outputs?.large_output_bytes === "number") {
+          setLargeOutputBytes(settings.outputs.large_output_bytes);
+        }
       })
       .catch(() => {
         // Daemon unavailable — defaults are fine
@@ -153,6 +164,12 @@ export function useSyncedSettings() {
       if (Array.isArray(event.payload.conda?.default_packages)) {
         setDefaultCondaPackagesState(event.payload.conda.default_packages);
       }
+      if (Array.isArray(event.payload.outputs?.sidecar_mime)) {
+        setSidecarMime(event.payload.outputs.sidecar_mime);
+      }
+      if (typeof event.payload.outputs?.large_output_bytes === "number") {
+        setLargeOutputBytes(event.payload.outputs.large_output_bytes);
+      }
     });
     return () => {
       unlisten.then((u) => u());
@@ -210,6 +227,8 @@ export function useSyncedSettings() {
     setDefaultUvPackages,
     defaultCondaPackages,
     setDefaultCondaPackages,
+    sidecarMime,
+    largeOutputBytes,
   };
 }
 